@@ -2,7 +2,149 @@ extern crate proc_macro;
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, DeriveInput, Lit, Meta, MetaNameValue, NestedMeta};
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Fields, GenericArgument, Lit, Meta,
+    MetaNameValue, NestedMeta, PathArguments, Type,
+};
+
+const FIELD_USAGE: &str = "[#baris] on a field accepts system, read_only, skip_serializing, \
+rename = \"Name\", and relationship = \"Name\"";
+
+/// The options given by `#[baris(...)]` on a field.
+#[derive(Default)]
+struct FieldOptions {
+    /// Marked `system` or `read_only`: deserialized, but never serialized.
+    read_only: bool,
+    skip_serializing: bool,
+    rename: Option<String>,
+    relationship: Option<String>,
+}
+
+fn get_field_options(attrs: &[Attribute]) -> FieldOptions {
+    let mut options = FieldOptions::default();
+
+    for attr in attrs {
+        if attr.path.is_ident("baris") {
+            match attr.parse_meta().expect(FIELD_USAGE) {
+                Meta::List(list) => {
+                    for nested in list.nested {
+                        match nested {
+                            NestedMeta::Meta(Meta::Path(path))
+                                if path.is_ident("system") || path.is_ident("read_only") =>
+                            {
+                                options.read_only = true
+                            }
+                            NestedMeta::Meta(Meta::Path(path))
+                                if path.is_ident("skip_serializing") =>
+                            {
+                                options.skip_serializing = true
+                            }
+                            NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                                path,
+                                lit: Lit::Str(name),
+                                eq_token: _,
+                            })) if path.is_ident("rename") => options.rename = Some(name.value()),
+                            NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                                path,
+                                lit: Lit::Str(name),
+                                eq_token: _,
+                            })) if path.is_ident("relationship") => {
+                                options.relationship = Some(name.value())
+                            }
+                            _ => panic!("{}", FIELD_USAGE),
+                        }
+                    }
+                }
+                _ => panic!("{}", FIELD_USAGE),
+            }
+        }
+    }
+
+    options
+}
+
+/// The value of a `#[serde(name = "...")]` attribute, if any.
+fn get_serde_name_value(attrs: &[Attribute], name: &str) -> Option<String> {
+    for attr in attrs {
+        if attr.path.is_ident("serde") {
+            if let Ok(Meta::List(list)) = attr.parse_meta() {
+                for nested in list.nested {
+                    if let NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                        path,
+                        lit: Lit::Str(value),
+                        eq_token: _,
+                    })) = nested
+                    {
+                        if path.is_ident(name) {
+                            return Some(value.value());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Apply a `#[serde(rename_all)]` rule to a snake-case field identifier.
+fn apply_rename_rule(rule: &str, ident: &str) -> String {
+    let pascal_case = || {
+        ident
+            .split('_')
+            .map(|part| {
+                let mut chars = part.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+                    None => String::new(),
+                }
+            })
+            .collect::<String>()
+    };
+
+    match rule {
+        "PascalCase" => pascal_case(),
+        "camelCase" => {
+            let pascal = pascal_case();
+            let mut chars = pascal.chars();
+            match chars.next() {
+                Some(first) => first.to_lowercase().collect::<String>() + chars.as_str(),
+                None => String::new(),
+            }
+        }
+        "lowercase" => ident.replace('_', ""),
+        "UPPERCASE" => ident.replace('_', "").to_uppercase(),
+        "SCREAMING_SNAKE_CASE" => ident.to_uppercase(),
+        "kebab-case" => ident.replace('_', "-"),
+        _ => ident.to_string(),
+    }
+}
+
+/// The serialized name of a field: its `#[serde(rename)]`, if any, or its
+/// identifier under the struct's `#[serde(rename_all)]` rule.
+fn get_serialized_name(attrs: &[Attribute], ident: String, rename_all: Option<&str>) -> String {
+    get_serde_name_value(attrs, "rename").unwrap_or_else(|| match rename_all {
+        Some(rule) => apply_rename_rule(rule, &ident),
+        None => ident,
+    })
+}
+
+/// The `T` of an `Option<T>` field, or the field's own type.
+fn get_option_inner(ty: &Type) -> &Type {
+    if let Type::Path(path) = ty {
+        if let Some(segment) = path.path.segments.last() {
+            if segment.ident == "Option" {
+                if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                    if let Some(GenericArgument::Type(inner)) = args.args.first() {
+                        return inner;
+                    }
+                }
+            }
+        }
+    }
+
+    ty
+}
 
 #[proc_macro_derive(SObjectRepresentation, attributes(baris))]
 pub fn sobject_representation_derive(input: TokenStream) -> TokenStream {
@@ -12,6 +154,50 @@ pub fn sobject_representation_derive(input: TokenStream) -> TokenStream {
 
     const USAGE: &str = "[#baris] requires an API name argument: api_name(\"Name\")";
 
+    // Collect the options of fields marked #[baris(...)].
+    let rename_all = get_serde_name_value(&ast.attrs, "rename_all");
+    let mut system_fields: Vec<String> = Vec::new();
+    let mut skipped_fields: Vec<String> = Vec::new();
+    let mut renamed_fields: Vec<String> = Vec::new();
+    let mut api_names: Vec<String> = Vec::new();
+    let mut query_fields: Vec<String> = Vec::new();
+    let mut relationship_names: Vec<String> = Vec::new();
+    let mut relationship_types: Vec<&Type> = Vec::new();
+    if let Data::Struct(data) = &ast.data {
+        if let Fields::Named(fields) = &data.fields {
+            for field in &fields.named {
+                let options = get_field_options(&field.attrs);
+                let serialized_name = get_serialized_name(
+                    &field.attrs,
+                    field.ident.as_ref().unwrap().to_string(),
+                    rename_all.as_deref(),
+                );
+
+                if options.skip_serializing {
+                    skipped_fields.push(serialized_name.clone());
+                } else if let Some(relationship) = &options.relationship {
+                    relationship_names.push(relationship.clone());
+                    relationship_types.push(get_option_inner(&field.ty));
+                } else {
+                    query_fields.push(
+                        options
+                            .rename
+                            .clone()
+                            .unwrap_or_else(|| serialized_name.clone()),
+                    );
+                }
+                // Parent records are read through queries, not written.
+                if options.read_only || options.relationship.is_some() {
+                    system_fields.push(serialized_name.clone());
+                }
+                if let Some(api_name) = options.relationship.or(options.rename) {
+                    renamed_fields.push(serialized_name);
+                    api_names.push(api_name);
+                }
+            }
+        }
+    }
+
     // Were we given an api_name attribute?
     for attr in ast.attrs {
         if attr.path.is_ident("baris") {
@@ -67,7 +253,111 @@ pub fn sobject_representation_derive(input: TokenStream) -> TokenStream {
             }
         }
 
+        impl baris::data::traits::SObjectBase for #ident {
+            fn get_system_fields() -> &'static [&'static str] {
+                &[#(#system_fields),*]
+            }
+
+            fn get_skipped_fields() -> &'static [&'static str] {
+                &[#(#skipped_fields),*]
+            }
+
+            fn get_field_renames() -> &'static [(&'static str, &'static str)] {
+                &[#((#renamed_fields, #api_names)),*]
+            }
+        }
+
+        impl #ident {
+            /// The API names of the fields of this struct, other than
+            /// relationship and `skip_serializing` fields.
+            pub const FIELDS: &'static [&'static str] = &[#(#query_fields),*];
+
+            /// A SOQL select list of `FIELDS`, followed by the fields of the
+            /// parent records held in relationship fields, such as `Account.Name`.
+            pub fn soql_field_list() -> String {
+                let mut fields: Vec<String> = Self::FIELDS.iter().map(|f| f.to_string()).collect();
+                #(
+                    fields.extend(
+                        <#relationship_types>::soql_field_list()
+                            .split(", ")
+                            .map(|f| format!("{}.{}", #relationship_names, f)),
+                    );
+                )*
+                fields.join(", ")
+            }
+        }
+    };
+    gen.into()
+}
+
+#[proc_macro_derive(PolymorphicSObject)]
+pub fn polymorphic_sobject_derive(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    let ident = ast.ident;
+
+    const USAGE: &str =
+        "PolymorphicSObject requires an enum whose variants each hold one typed sObject";
+
+    let mut variants = Vec::new();
+    let mut types = Vec::new();
+    match &ast.data {
+        Data::Enum(data) => {
+            for variant in &data.variants {
+                match &variant.fields {
+                    Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                        variants.push(&variant.ident);
+                        types.push(&fields.unnamed.first().unwrap().ty);
+                    }
+                    _ => panic!("{}", USAGE),
+                }
+            }
+        }
+        _ => panic!("{}", USAGE),
+    }
+
+    let gen = quote! {
+        impl baris::data::traits::PolymorphicSObject for #ident {
+            fn variant_from_value(
+                sobject_type: &str,
+                value: &serde_json::Value,
+            ) -> Option<anyhow::Result<Self>> {
+                #(
+                    if sobject_type.eq_ignore_ascii_case(
+                        <#types as baris::data::traits::SingleTypedSObject>::get_type_api_name(),
+                    ) {
+                        return Some(
+                            baris::data::traits::deserialize_sobject::<#types>(value)
+                                .map(#ident::#variants),
+                        );
+                    }
+                )*
+
+                None
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for #ident {
+            fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                baris::data::traits::deserialize_polymorphic(deserializer)
+            }
+        }
+
         impl baris::data::traits::SObjectBase for #ident {}
+
+        impl baris::data::traits::TypedSObject for #ident {
+            fn get_api_name(&self) -> &str {
+                match self {
+                    #(
+                        #ident::#variants(_) => {
+                            <#types as baris::data::traits::SingleTypedSObject>::get_type_api_name()
+                        }
+                    )*
+                }
+            }
+        }
     };
     gen.into()
 }
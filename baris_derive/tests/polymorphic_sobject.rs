@@ -0,0 +1,97 @@
+use anyhow::Result;
+use baris::data::traits::PolymorphicSObject;
+use baris::prelude::*;
+use baris_derive::{PolymorphicSObject, SObjectRepresentation};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SObjectRepresentation)]
+#[baris(api_name = "Account")]
+#[serde(rename_all = "PascalCase")]
+struct Account {
+    id: Option<SalesforceId>,
+    name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SObjectRepresentation)]
+#[baris(api_name = "Contact")]
+#[serde(rename_all = "PascalCase")]
+struct Contact {
+    id: Option<SalesforceId>,
+    last_name: Option<String>,
+    #[baris(rename = "Custom_Field__c")]
+    custom_field: Option<String>,
+}
+
+#[derive(Debug, PartialEq, PolymorphicSObject)]
+enum Party {
+    Account(Account),
+    Contact(Contact),
+}
+
+#[derive(Debug, Deserialize)]
+struct Task {
+    #[serde(rename = "Who")]
+    who: Option<Party>,
+}
+
+#[test]
+fn test_variant_dispatch() -> Result<()> {
+    let task: Task = serde_json::from_value(json!({
+        "Who": {
+            "attributes": {"type": "Account"},
+            "Id": "001000000000001AAA",
+            "Name": "Acme"
+        }
+    }))?;
+    let who = task.who.unwrap();
+
+    assert_eq!(
+        who,
+        Party::Account(Account {
+            id: Some(SalesforceId::new("001000000000001AAA")?),
+            name: Some("Acme".to_owned()),
+        })
+    );
+    assert_eq!(who.get_api_name(), "Account");
+
+    // Each variant's field renames are applied.
+    let who = Party::from_typed_value(&json!({
+        "attributes": {"type": "Contact"},
+        "LastName": "Smith",
+        "Custom_Field__c": "Value"
+    }))?;
+
+    assert_eq!(
+        who,
+        Party::Contact(Contact {
+            id: None,
+            last_name: Some("Smith".to_owned()),
+            custom_field: Some("Value".to_owned()),
+        })
+    );
+    assert_eq!(who.get_api_name(), "Contact");
+
+    let task: Task = serde_json::from_value(json!({"Who": null}))?;
+    assert_eq!(task.who, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_unknown_type() {
+    assert!(Party::variant_from_value("Lead", &json!({})).is_none());
+
+    let error = Party::from_typed_value(&json!({
+        "attributes": {"type": "Lead"},
+        "LastName": "Smith"
+    }))
+    .unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<SalesforceError>(),
+        Some(SalesforceError::SchemaError(_))
+    ));
+
+    let error = serde_json::from_value::<Task>(json!({"Who": {"LastName": "Smith"}})).unwrap_err();
+    assert!(error.to_string().contains("attributes.type"));
+}
@@ -0,0 +1,103 @@
+use anyhow::Result;
+use baris::data::traits::deserialize_sobject;
+use baris::prelude::*;
+use baris_derive::SObjectRepresentation;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SObjectRepresentation)]
+#[baris(api_name = "Account")]
+#[serde(rename_all = "PascalCase")]
+struct Account {
+    id: Option<SalesforceId>,
+    name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SObjectRepresentation)]
+#[baris(api_name = "Contact")]
+#[serde(rename_all = "PascalCase")]
+struct Contact {
+    id: Option<SalesforceId>,
+    last_name: String,
+    #[baris(rename = "Custom_Field__c")]
+    custom_field: Option<String>,
+    #[baris(read_only)]
+    created_date: Option<DateTime>,
+    #[baris(skip_serializing)]
+    local_state: Option<String>,
+    #[baris(relationship = "Account")]
+    account: Option<Account>,
+}
+
+fn get_contact() -> Result<Contact> {
+    Ok(Contact {
+        id: Some(SalesforceId::new("003000000000001AAA")?),
+        last_name: "Smith".to_owned(),
+        custom_field: Some("Value".to_owned()),
+        created_date: Some("2021-11-15T00:00:00.000+0000".parse()?),
+        local_state: Some("Local".to_owned()),
+        account: Some(Account {
+            id: None,
+            name: Some("Acme".to_owned()),
+        }),
+    })
+}
+
+#[test]
+fn test_field_attributes_follow_rename_all() {
+    assert_eq!(Contact::get_system_fields(), &["CreatedDate", "Account"]);
+    assert_eq!(Contact::get_skipped_fields(), &["LocalState"]);
+    assert_eq!(
+        Contact::get_field_renames(),
+        &[("CustomField", "Custom_Field__c"), ("Account", "Account")]
+    );
+}
+
+#[test]
+fn test_to_value() -> Result<()> {
+    assert_eq!(
+        get_contact()?.to_value_with_options(true, false)?,
+        json!({
+            "attributes": {"type": "Contact"},
+            "LastName": "Smith",
+            "Custom_Field__c": "Value"
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_deserialize_sobject() -> Result<()> {
+    let contact: Contact = deserialize_sobject(&json!({
+        "attributes": {"type": "Contact"},
+        "Id": "003000000000001AAA",
+        "LastName": "Smith",
+        "Custom_Field__c": "Value",
+        "CreatedDate": "2021-11-15T00:00:00.000+0000",
+        "LocalState": "Ignored",
+        "Account": {"attributes": {"type": "Account"}, "Name": "Acme"}
+    }))?;
+
+    assert_eq!(
+        contact,
+        Contact {
+            local_state: None,
+            ..get_contact()?
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_field_list() {
+    assert_eq!(
+        Contact::FIELDS,
+        &["Id", "LastName", "Custom_Field__c", "CreatedDate"]
+    );
+    assert_eq!(
+        Contact::soql_field_list(),
+        "Id, LastName, Custom_Field__c, CreatedDate, Account.Id, Account.Name"
+    );
+}
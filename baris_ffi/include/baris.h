@@ -0,0 +1,72 @@
+/* C bindings for Baris. See baris_ffi/src/lib.rs for details. */
+
+#ifndef BARIS_H
+#define BARIS_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define BARIS_OK 0
+#define BARIS_ERR_INVALID_ARGUMENT 1
+#define BARIS_ERR_IO 2
+#define BARIS_ERR_SALESFORCE 3
+#define BARIS_ERR_JOB_FAILED 4
+#define BARIS_ERR_PANIC 5
+#define BARIS_ERR_TIMEOUT 6
+
+#define BARIS_OP_INSERT 0
+#define BARIS_OP_UPDATE 1
+#define BARIS_OP_UPSERT 2
+#define BARIS_OP_DELETE 3
+#define BARIS_OP_HARD_DELETE 4
+
+typedef struct BarisConnection BarisConnection;
+
+typedef void (*BarisProgressCallback)(uint64_t records, void *user_data);
+
+const char *baris_last_error(void);
+
+int baris_connection_new_access_token(const char *access_token,
+                                      const char *instance_url,
+                                      const char *api_version,
+                                      BarisConnection **out);
+
+int baris_connection_new_username_password(const char *username,
+                                           const char *password,
+                                           const char *security_token,
+                                           const char *consumer_key,
+                                           const char *client_secret,
+                                           const char *login_url,
+                                           const char *api_version,
+                                           BarisConnection **out);
+
+void baris_connection_free(BarisConnection *conn);
+
+int baris_connection_set_job_timeout(const BarisConnection *conn, uint64_t timeout_secs);
+
+int baris_query_to_csv(const BarisConnection *conn,
+                       const char *sobject,
+                       const char *query,
+                       bool all,
+                       const char *path,
+                       BarisProgressCallback progress,
+                       void *user_data);
+
+int baris_bulk_load_csv(const BarisConnection *conn,
+                        int operation,
+                        const char *sobject,
+                        const char *external_id,
+                        const char *path,
+                        BarisProgressCallback progress,
+                        void *user_data,
+                        uint64_t *failed_records);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* BARIS_H */
@@ -0,0 +1,517 @@
+//! C bindings for Baris, for embedding in hosts such as C# or Java ETL tools.
+//!
+//! Every function that can fail returns a `BARIS_*` status code. After a
+//! failure, `baris_last_error()` describes the error on the calling thread.
+//! Panics are caught at the boundary and reported as `BARIS_ERR_PANIC`,
+//! as unwinding into the host is undefined behavior.
+//! Strings passed in must be NUL-terminated UTF-8. Calls block the calling
+//! thread on a shared Tokio runtime. See `include/baris.h`.
+
+use std::cell::RefCell;
+use std::ffi::{c_void, CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::Result;
+use baris::api::{polling::PollingOptions, Connection};
+use baris::auth::{AccessTokenAuth, ConnectedApp, UsernamePasswordAuth};
+use baris::bulk::v2::{
+    BulkApiDmlOperation, BulkCsvFormat, BulkDmlJob, BulkJobStatus, BulkQueryJob, SObjectCsvEncoder,
+};
+use baris::data::SObject;
+use baris::errors::SalesforceError;
+use baris::soql::{self, Expression, SelectItem};
+use reqwest::Url;
+use tokio::io::AsyncWriteExt;
+use tokio::runtime::Runtime;
+use tokio_stream::StreamExt;
+use tokio_util::io::ReaderStream;
+
+pub const BARIS_OK: c_int = 0;
+pub const BARIS_ERR_INVALID_ARGUMENT: c_int = 1;
+pub const BARIS_ERR_IO: c_int = 2;
+pub const BARIS_ERR_SALESFORCE: c_int = 3;
+pub const BARIS_ERR_JOB_FAILED: c_int = 4;
+pub const BARIS_ERR_PANIC: c_int = 5;
+pub const BARIS_ERR_TIMEOUT: c_int = 6;
+
+pub const BARIS_OP_INSERT: c_int = 0;
+pub const BARIS_OP_UPDATE: c_int = 1;
+pub const BARIS_OP_UPSERT: c_int = 2;
+pub const BARIS_OP_DELETE: c_int = 3;
+pub const BARIS_OP_HARD_DELETE: c_int = 4;
+
+/// Receives the number of records processed so far, and the caller's `user_data`.
+pub type BarisProgressCallback = Option<extern "C" fn(records: u64, user_data: *mut c_void)>;
+
+/// How often, in records, `baris_query_to_csv()` reports progress.
+const PROGRESS_INTERVAL: u64 = 1000;
+
+/// How often Bulk jobs are polled, and how long they are awaited unless
+/// changed with `baris_connection_set_job_timeout()`.
+const JOB_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const DEFAULT_JOB_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// An opaque handle to a Connection.
+pub struct BarisConnection {
+    conn: Connection,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn get_runtime() -> std::result::Result<&'static Runtime, c_int> {
+    static RUNTIME: OnceLock<std::result::Result<Runtime, String>> = OnceLock::new();
+
+    RUNTIME
+        .get_or_init(|| Runtime::new().map_err(|e| e.to_string()))
+        .as_ref()
+        .map_err(|e| {
+            fail(
+                BARIS_ERR_IO,
+                format!("Unable to start the Tokio runtime: {}", e),
+            )
+        })
+}
+
+fn set_last_error(message: String) {
+    LAST_ERROR.with(|e| {
+        *e.borrow_mut() = CString::new(message.replace('\0', "")).ok();
+    });
+}
+
+fn fail(code: c_int, message: String) -> c_int {
+    set_last_error(message);
+    code
+}
+
+fn get_error_code(err: &anyhow::Error) -> c_int {
+    if err.is::<std::io::Error>() || err.is::<csv::Error>() {
+        BARIS_ERR_IO
+    } else if let Some(SalesforceError::Timeout(_)) = err.downcast_ref::<SalesforceError>() {
+        BARIS_ERR_TIMEOUT
+    } else {
+        BARIS_ERR_SALESFORCE
+    }
+}
+
+/// Read a required string argument.
+unsafe fn get_str<'a>(value: *const c_char, name: &str) -> std::result::Result<&'a str, c_int> {
+    get_optional_str(value, name)?.ok_or_else(|| {
+        fail(
+            BARIS_ERR_INVALID_ARGUMENT,
+            format!("Argument {} must not be null", name),
+        )
+    })
+}
+
+/// Read a nullable string argument.
+unsafe fn get_optional_str<'a>(
+    value: *const c_char,
+    name: &str,
+) -> std::result::Result<Option<&'a str>, c_int> {
+    if value.is_null() {
+        return Ok(None);
+    }
+
+    CStr::from_ptr(value).to_str().map(Some).map_err(|_| {
+        fail(
+            BARIS_ERR_INVALID_ARGUMENT,
+            format!("Argument {} is not valid UTF-8", name),
+        )
+    })
+}
+
+fn get_url(value: &str) -> std::result::Result<Url, c_int> {
+    Url::parse(value).map_err(|e| fail(BARIS_ERR_INVALID_ARGUMENT, e.to_string()))
+}
+
+fn get_operation(operation: c_int) -> std::result::Result<BulkApiDmlOperation, c_int> {
+    match operation {
+        BARIS_OP_INSERT => Ok(BulkApiDmlOperation::Insert),
+        BARIS_OP_UPDATE => Ok(BulkApiDmlOperation::Update),
+        BARIS_OP_UPSERT => Ok(BulkApiDmlOperation::Upsert),
+        BARIS_OP_DELETE => Ok(BulkApiDmlOperation::Delete),
+        BARIS_OP_HARD_DELETE => Ok(BulkApiDmlOperation::HardDelete),
+        _ => Err(fail(
+            BARIS_ERR_INVALID_ARGUMENT,
+            format!("Unknown operation {}", operation),
+        )),
+    }
+}
+
+fn report(progress: BarisProgressCallback, records: u64, user_data: *mut c_void) {
+    if let Some(progress) = progress {
+        progress(records, user_data);
+    }
+}
+
+fn new_connection(
+    conn: Result<Connection>,
+    out: *mut *mut BarisConnection,
+) -> std::result::Result<(), c_int> {
+    let conn = conn.map_err(|e| fail(BARIS_ERR_SALESFORCE, format!("{:#}", e)))?;
+    conn.set_polling_options(
+        PollingOptions::new(JOB_POLL_INTERVAL).with_timeout(Some(DEFAULT_JOB_TIMEOUT)),
+    );
+
+    unsafe {
+        *out = Box::into_raw(Box::new(BarisConnection { conn }));
+    }
+    Ok(())
+}
+
+fn get_panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    match payload
+        .downcast_ref::<&str>()
+        .map(|m| m.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+    {
+        Some(message) => format!("Baris panicked: {}", message),
+        None => "Baris panicked".to_owned(),
+    }
+}
+
+/// Run the body of an entry point, converting its result, or a panic,
+/// into a status code.
+fn guard(body: impl FnOnce() -> std::result::Result<(), c_int>) -> c_int {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => BARIS_OK,
+        Ok(Err(code)) => code,
+        Err(payload) => fail(BARIS_ERR_PANIC, get_panic_message(payload)),
+    }
+}
+
+/// The message for the last error on this thread, or null. The string is
+/// valid until the next Baris call on this thread.
+#[no_mangle]
+pub extern "C" fn baris_last_error() -> *const c_char {
+    panic::catch_unwind(|| {
+        LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+    })
+    .unwrap_or(ptr::null())
+}
+
+/// Create a Connection from an existing access token. `api_version` is,
+/// for example, `v52.0`. On success, `*out` must be freed with
+/// `baris_connection_free()`.
+///
+/// # Safety
+///
+/// String arguments must be valid NUL-terminated strings, and `out` must be
+/// a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn baris_connection_new_access_token(
+    access_token: *const c_char,
+    instance_url: *const c_char,
+    api_version: *const c_char,
+    out: *mut *mut BarisConnection,
+) -> c_int {
+    guard(|| {
+        if out.is_null() {
+            return Err(fail(BARIS_ERR_INVALID_ARGUMENT, "out is null".to_owned()));
+        }
+
+        let auth = AccessTokenAuth::new(
+            get_str(access_token, "access_token")?.to_owned(),
+            get_url(get_str(instance_url, "instance_url")?)?,
+        );
+
+        new_connection(
+            Connection::new(Box::new(auth), get_str(api_version, "api_version")?),
+            out,
+        )
+    })
+}
+
+/// Create a Connection with the OAuth username-password flow.
+/// `security_token` may be null.
+///
+/// # Safety
+///
+/// String arguments must be valid NUL-terminated strings or, where noted,
+/// null, and `out` must be a valid pointer.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn baris_connection_new_username_password(
+    username: *const c_char,
+    password: *const c_char,
+    security_token: *const c_char,
+    consumer_key: *const c_char,
+    client_secret: *const c_char,
+    login_url: *const c_char,
+    api_version: *const c_char,
+    out: *mut *mut BarisConnection,
+) -> c_int {
+    guard(|| {
+        if out.is_null() {
+            return Err(fail(BARIS_ERR_INVALID_ARGUMENT, "out is null".to_owned()));
+        }
+
+        let auth = UsernamePasswordAuth::new(
+            get_str(username, "username")?.to_owned(),
+            get_str(password, "password")?.to_owned(),
+            get_optional_str(security_token, "security_token")?.map(|t| t.to_owned()),
+            ConnectedApp::new(
+                get_str(consumer_key, "consumer_key")?.to_owned(),
+                get_str(client_secret, "client_secret")?.to_owned(),
+                None,
+            ),
+            get_url(get_str(login_url, "login_url")?)?,
+        );
+
+        new_connection(
+            Connection::new(Box::new(auth), get_str(api_version, "api_version")?),
+            out,
+        )
+    })
+}
+
+/// Free a Connection. Null is ignored.
+///
+/// # Safety
+///
+/// `conn` must be null or a Connection not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn baris_connection_free(conn: *mut BarisConnection) {
+    if !conn.is_null() {
+        // A panic while dropping leaks the Connection rather than unwinding into the host.
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(conn))));
+    }
+}
+
+/// Set how long `baris_query_to_csv()` and `baris_bulk_load_csv()` wait for
+/// a Bulk job to complete before failing with `BARIS_ERR_TIMEOUT`. The job
+/// itself continues. Zero waits indefinitely. The default is one hour.
+///
+/// # Safety
+///
+/// `conn` must be a live Connection.
+#[no_mangle]
+pub unsafe extern "C" fn baris_connection_set_job_timeout(
+    conn: *const BarisConnection,
+    timeout_secs: u64,
+) -> c_int {
+    guard(|| {
+        let conn = conn
+            .as_ref()
+            .ok_or_else(|| fail(BARIS_ERR_INVALID_ARGUMENT, "conn is null".to_owned()))?;
+        let timeout = match timeout_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+
+        conn.conn
+            .set_polling_options(conn.conn.get_polling_options().with_timeout(timeout));
+        Ok(())
+    })
+}
+
+/// The fields selected by `query`, if it selects only fields of the queried
+/// sObject, such as `SELECT Id, Name FROM Account`.
+fn get_selected_fields(query: &str) -> Option<Vec<String>> {
+    soql::parse(query)
+        .ok()?
+        .select
+        .iter()
+        .map(|item| match item {
+            SelectItem::Expression {
+                expression: Expression::Field(field),
+                alias: None,
+            } => match field.get_path() {
+                [name] => Some(name.clone()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+async fn query_to_csv(
+    conn: &Connection,
+    sobject: &str,
+    query: &str,
+    all: bool,
+    path: &str,
+    progress: BarisProgressCallback,
+    user_data: *mut c_void,
+) -> Result<()> {
+    let sobject_type = conn.get_type(sobject).await?;
+    let job = BulkQueryJob::create(conn, query, all)
+        .await?
+        .complete(conn)
+        .await?;
+
+    if job.get_state() != BulkJobStatus::JobComplete {
+        return Err(SalesforceError::GeneralError(format!(
+            "Bulk query job {} ended in state {:?}",
+            job.get_id(),
+            job.get_state()
+        ))
+        .into());
+    }
+
+    let mut results = job.get_results_stream::<SObject>(conn, &sobject_type).await;
+    let mut encoder = match get_selected_fields(query) {
+        Some(fields) => {
+            let fields: Vec<&str> = fields.iter().map(|f| f.as_str()).collect();
+            SObjectCsvEncoder::new(BulkCsvFormat::default()).with_columns(&fields)
+        }
+        None => SObjectCsvEncoder::new(BulkCsvFormat::default()),
+    };
+    let mut file = tokio::fs::File::create(path).await?;
+    let mut count: u64 = 0;
+
+    while let Some(record) = results.next().await {
+        file.write_all(&encoder.encode(&record?).await?).await?;
+
+        count += 1;
+        if count.is_multiple_of(PROGRESS_INTERVAL) {
+            report(progress, count, user_data);
+        }
+    }
+
+    file.write_all(&encoder.finish().await?).await?;
+    file.flush().await?;
+    report(progress, count, user_data);
+
+    Ok(())
+}
+
+/// Run `query` against `sobject` as a Bulk API 2.0 query job, and write the
+/// results to a CSV file at `path`. If the query selects only fields of
+/// `sobject`, the columns are those fields, in order, and a header row is
+/// written even if no records match. Otherwise, the columns are the fields
+/// of the first record, sorted by name.
+/// `progress` may be null; otherwise it is called periodically with the
+/// number of records written.
+///
+/// # Safety
+///
+/// `conn` must be a live Connection, and string arguments must be valid
+/// NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn baris_query_to_csv(
+    conn: *const BarisConnection,
+    sobject: *const c_char,
+    query: *const c_char,
+    all: bool,
+    path: *const c_char,
+    progress: BarisProgressCallback,
+    user_data: *mut c_void,
+) -> c_int {
+    guard(|| {
+        let conn = conn
+            .as_ref()
+            .ok_or_else(|| fail(BARIS_ERR_INVALID_ARGUMENT, "conn is null".to_owned()))?;
+        let sobject = get_str(sobject, "sobject")?;
+        let query = get_str(query, "query")?;
+        let path = get_str(path, "path")?;
+
+        get_runtime()?
+            .block_on(query_to_csv(
+                &conn.conn, sobject, query, all, path, progress, user_data,
+            ))
+            .map_err(|e| fail(get_error_code(&e), format!("{:#}", e)))
+    })
+}
+
+async fn bulk_load_csv(
+    conn: &Connection,
+    operation: BulkApiDmlOperation,
+    sobject: &str,
+    external_id: Option<&str>,
+    path: &str,
+    progress: BarisProgressCallback,
+    user_data: *mut c_void,
+) -> Result<BulkDmlJob> {
+    let file = tokio::fs::File::open(path).await?;
+    let job = BulkDmlJob::create_with_options(
+        conn,
+        operation,
+        sobject.to_owned(),
+        external_id.map(|e| e.to_owned()),
+        None,
+    )
+    .await?;
+
+    job.ingest_csv(
+        conn,
+        ReaderStream::new(file).map(|chunk| chunk.map_err(|e| e.into())),
+    )
+    .await?;
+    job.close(conn).await?;
+
+    job.complete_with_progress(conn, |status| {
+        report(progress, status.records_processed, user_data)
+    })
+    .await
+}
+
+/// Load a CSV file, with a header row, at `path` into `sobject` with a Bulk
+/// API 2.0 job, using one of the `BARIS_OP_*` operations. `external_id` is
+/// required for upserts and otherwise may be null. `progress` may be null;
+/// otherwise it is called with the number of records processed each time
+/// the job is polled. If `failed_records` is not null, it receives the
+/// number of records that failed.
+///
+/// Returns `BARIS_ERR_JOB_FAILED` if the job fails or is aborted, and
+/// `BARIS_ERR_TIMEOUT` if it does not complete within the Connection's job
+/// timeout.
+///
+/// # Safety
+///
+/// `conn` must be a live Connection, string arguments must be valid
+/// NUL-terminated strings or, where noted, null, and `failed_records` must
+/// be null or a valid pointer.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn baris_bulk_load_csv(
+    conn: *const BarisConnection,
+    operation: c_int,
+    sobject: *const c_char,
+    external_id: *const c_char,
+    path: *const c_char,
+    progress: BarisProgressCallback,
+    user_data: *mut c_void,
+    failed_records: *mut u64,
+) -> c_int {
+    guard(|| {
+        let conn = conn
+            .as_ref()
+            .ok_or_else(|| fail(BARIS_ERR_INVALID_ARGUMENT, "conn is null".to_owned()))?;
+        let operation = get_operation(operation)?;
+        let sobject = get_str(sobject, "sobject")?;
+        let external_id = get_optional_str(external_id, "external_id")?;
+        let path = get_str(path, "path")?;
+
+        let job = get_runtime()?
+            .block_on(bulk_load_csv(
+                &conn.conn,
+                operation,
+                sobject,
+                external_id,
+                path,
+                progress,
+                user_data,
+            ))
+            .map_err(|e| fail(get_error_code(&e), format!("{:#}", e)))?;
+
+        if !failed_records.is_null() {
+            *failed_records = job.number_records_failed.unwrap_or_default();
+        }
+
+        if job.state != BulkJobStatus::JobComplete {
+            return Err(fail(
+                BARIS_ERR_JOB_FAILED,
+                format!("Bulk job {} ended in state {:?}", job.id, job.state),
+            ));
+        }
+
+        Ok(())
+    })
+}
@@ -0,0 +1,466 @@
+//! Python bindings for Baris.
+//!
+//! Records cross the boundary as Python dictionaries, converted through
+//! JSON. Each call blocks on a shared Tokio runtime with the GIL released,
+//! so Python threads can run Baris operations concurrently.
+
+use std::pin::Pin;
+use std::sync::OnceLock;
+
+use anyhow::Result;
+use futures::Stream;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyList;
+use reqwest::Url;
+use serde_json::Value;
+use tokio::runtime::Runtime;
+use tokio_stream::StreamExt;
+
+use baris::api;
+use baris::auth::{AccessTokenAuth, ConnectedApp, UsernamePasswordAuth};
+use baris::bulk::v2::{BulkApiDmlOperation, BulkDmlJob, BulkJobStatus, BulkQueryJob};
+use baris::data::traits::{SObjectDeserialization, SObjectSerialization};
+use baris::data::{SObject, SObjectType};
+use baris::errors::SalesforceError;
+use baris::rest::collections::{
+    SObjectCollectionCreateRequest, SObjectCollectionDeleteRequest, SObjectCollectionUpdateRequest,
+    SObjectCollectionUpsertRequest, MAX_COLLECTION_RECORDS,
+};
+use baris::rest::query::QueryRequest;
+use baris::rest::DmlResult;
+
+const DEFAULT_API_VERSION: &str = "v52.0";
+
+type RecordStream = Pin<Box<dyn Stream<Item = Result<SObject>> + Send>>;
+
+fn get_runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+    RUNTIME.get_or_init(|| Runtime::new().expect("Unable to start the Tokio runtime"))
+}
+
+fn to_py_err(err: anyhow::Error) -> PyErr {
+    PyRuntimeError::new_err(format!("{:#}", err))
+}
+
+/// Run `future` to completion on the shared runtime without holding the GIL.
+fn block_on<F, T>(py: Python<'_>, future: F) -> PyResult<T>
+where
+    F: std::future::Future<Output = Result<T>> + Send,
+    T: Send,
+{
+    py.allow_threads(|| get_runtime().block_on(future))
+        .map_err(to_py_err)
+}
+
+fn to_python(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    Ok(py
+        .import_bound("json")?
+        .call_method1("loads", (value.to_string(),))?
+        .unbind())
+}
+
+fn from_python(py: Python<'_>, object: &Bound<'_, PyAny>) -> PyResult<Value> {
+    let json: String = py
+        .import_bound("json")?
+        .call_method1("dumps", (object,))?
+        .extract()?;
+
+    serde_json::from_str(&json).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+fn to_sobjects(
+    py: Python<'_>,
+    sobject_type: &SObjectType,
+    records: &Bound<'_, PyList>,
+) -> PyResult<Vec<SObject>> {
+    records
+        .iter()
+        .map(|r| {
+            SObject::from_value(&from_python(py, &r)?, sobject_type)
+                .map_err(|e| PyValueError::new_err(e.to_string()))
+        })
+        .collect()
+}
+
+fn results_to_python(py: Python<'_>, results: Vec<DmlResult>) -> PyResult<PyObject> {
+    to_python(
+        py,
+        &serde_json::to_value(results).map_err(|e| PyRuntimeError::new_err(e.to_string()))?,
+    )
+}
+
+/// An iterator over query results, retrieving further pages as needed.
+#[pyclass]
+struct RecordIterator {
+    stream: Option<RecordStream>,
+}
+
+#[pymethods]
+impl RecordIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        let stream = match &mut self.stream {
+            Some(stream) => stream,
+            None => return Ok(None),
+        };
+
+        match block_on(py, async { Ok(stream.next().await) })? {
+            Some(Ok(record)) => Ok(Some(to_python(py, &record.to_value().map_err(to_py_err)?)?)),
+            Some(Err(e)) => {
+                self.stream = None;
+                Err(to_py_err(e))
+            }
+            None => {
+                self.stream = None;
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[pyclass]
+struct Connection {
+    conn: api::Connection,
+}
+
+impl Connection {
+    async fn collection_dml(
+        conn: &api::Connection,
+        operation: &str,
+        records: Vec<SObject>,
+        external_id: Option<String>,
+        all_or_none: bool,
+    ) -> Result<Vec<DmlResult>> {
+        let mut results = Vec::with_capacity(records.len());
+
+        for chunk in records.chunks(MAX_COLLECTION_RECORDS) {
+            results.extend(match (operation, &external_id) {
+                ("create", _) => {
+                    conn.execute(&SObjectCollectionCreateRequest::new(chunk, all_or_none)?)
+                        .await?
+                }
+                ("update", _) => {
+                    conn.execute(&SObjectCollectionUpdateRequest::new(chunk, all_or_none)?)
+                        .await?
+                }
+                ("upsert", Some(external_id)) => {
+                    conn.execute(&SObjectCollectionUpsertRequest::new(
+                        chunk,
+                        external_id,
+                        all_or_none,
+                    )?)
+                    .await?
+                }
+                ("delete", _) => {
+                    conn.execute(&SObjectCollectionDeleteRequest::new(chunk, all_or_none)?)
+                        .await?
+                }
+                ("upsert", None) => {
+                    return Err(SalesforceError::GeneralError(
+                        "An upsert requires an external Id field".to_owned(),
+                    )
+                    .into())
+                }
+                (operation, _) => {
+                    return Err(SalesforceError::GeneralError(format!(
+                        "Unknown DML operation {}",
+                        operation
+                    ))
+                    .into())
+                }
+            });
+        }
+
+        Ok(results)
+    }
+
+    fn dml(
+        &self,
+        py: Python<'_>,
+        operation: &str,
+        sobject: &str,
+        records: &Bound<'_, PyList>,
+        external_id: Option<String>,
+        all_or_none: bool,
+    ) -> PyResult<PyObject> {
+        let conn = self.conn.clone();
+        let sobject_type = block_on(py, conn.get_type(sobject))?;
+        let records = to_sobjects(py, &sobject_type, records)?;
+
+        let results = block_on(
+            py,
+            Connection::collection_dml(&conn, operation, records, external_id, all_or_none),
+        )?;
+
+        results_to_python(py, results)
+    }
+
+    fn bulk_dml(
+        &self,
+        py: Python<'_>,
+        operation: BulkApiDmlOperation,
+        sobject: &str,
+        records: &Bound<'_, PyList>,
+        external_id: Option<String>,
+    ) -> PyResult<PyObject> {
+        let conn = self.conn.clone();
+        let sobject_type = block_on(py, conn.get_type(sobject))?;
+        let records = to_sobjects(py, &sobject_type, records)?;
+        let sobject = sobject_type.get_api_name().to_owned();
+
+        let job = block_on(py, async move {
+            let job = BulkDmlJob::create_with_options(&conn, operation, sobject, external_id, None)
+                .await?;
+            job.ingest_sobjects(&conn, tokio_stream::iter(records))
+                .await?;
+            job.close(&conn).await?.complete(&conn).await
+        })?;
+
+        to_python(
+            py,
+            &serde_json::to_value(job).map_err(|e| PyRuntimeError::new_err(e.to_string()))?,
+        )
+    }
+}
+
+// The wrappers that pyo3 0.22 generates for these methods convert each
+// `PyErr` into itself, which clippy reports at their return types. An
+// `allow` on the impl or its methods does not reach those wrappers.
+#[allow(clippy::useless_conversion)]
+mod connection_methods {
+    use super::*;
+
+    #[pymethods]
+    impl Connection {
+        /// Connect with an existing access token, such as a session Id.
+        #[staticmethod]
+        #[pyo3(signature = (access_token, instance_url, api_version = DEFAULT_API_VERSION))]
+        fn with_access_token(
+            access_token: String,
+            instance_url: &str,
+            api_version: &str,
+        ) -> PyResult<Connection> {
+            let instance_url =
+                Url::parse(instance_url).map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+            Ok(Connection {
+                conn: api::Connection::new(
+                    Box::new(AccessTokenAuth::new(access_token, instance_url)),
+                    api_version,
+                )
+                .map_err(to_py_err)?,
+            })
+        }
+
+        /// Connect with the OAuth username-password flow.
+        #[staticmethod]
+        #[pyo3(signature = (
+            username,
+            password,
+            consumer_key,
+            client_secret,
+            security_token = None,
+            login_url = "https://login.salesforce.com",
+            api_version = DEFAULT_API_VERSION
+        ))]
+        #[allow(clippy::too_many_arguments)]
+        fn with_username_password(
+            username: String,
+            password: String,
+            consumer_key: String,
+            client_secret: String,
+            security_token: Option<String>,
+            login_url: &str,
+            api_version: &str,
+        ) -> PyResult<Connection> {
+            let login_url =
+                Url::parse(login_url).map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+            Ok(Connection {
+                conn: api::Connection::new(
+                    Box::new(UsernamePasswordAuth::new(
+                        username,
+                        password,
+                        security_token,
+                        ConnectedApp::new(consumer_key, client_secret, None),
+                        login_url,
+                    )),
+                    api_version,
+                )
+                .map_err(to_py_err)?,
+            })
+        }
+
+        /// Run a SOQL query against `sobject`, returning an iterator of records.
+        #[pyo3(signature = (sobject, query, all = false))]
+        fn query(
+            &self,
+            py: Python<'_>,
+            sobject: &str,
+            query: String,
+            all: bool,
+        ) -> PyResult<RecordIterator> {
+            let conn = self.conn.clone();
+            let stream = block_on(py, async move {
+                let sobject_type = conn.get_type(sobject).await?;
+                let stream: RecordStream = Box::pin(
+                    conn.execute(&QueryRequest::new(&query, all))
+                        .await?
+                        .to_result_stream(&conn, &sobject_type)?,
+                );
+                Ok(stream)
+            })?;
+
+            Ok(RecordIterator {
+                stream: Some(stream),
+            })
+        }
+
+        /// Run a SOQL query against `sobject` as a Bulk API 2.0 job, returning an
+        /// iterator of records once the job completes.
+        #[pyo3(signature = (sobject, query, all = false))]
+        fn bulk_query(
+            &self,
+            py: Python<'_>,
+            sobject: &str,
+            query: String,
+            all: bool,
+        ) -> PyResult<RecordIterator> {
+            let conn = self.conn.clone();
+            let stream = block_on(py, async move {
+                let sobject_type = conn.get_type(sobject).await?;
+                let job = BulkQueryJob::create(&conn, &query, all)
+                    .await?
+                    .complete(&conn)
+                    .await?;
+
+                if job.get_state() != BulkJobStatus::JobComplete {
+                    return Err(SalesforceError::GeneralError(format!(
+                        "Bulk query job {} ended in state {:?}",
+                        job.get_id(),
+                        job.get_state()
+                    ))
+                    .into());
+                }
+
+                let stream: RecordStream =
+                    Box::pin(job.get_results_stream(&conn, &sobject_type).await);
+                Ok(stream)
+            })?;
+
+            Ok(RecordIterator {
+                stream: Some(stream),
+            })
+        }
+
+        /// Insert records with the sObject Collections API, returning one result per record.
+        #[pyo3(signature = (sobject, records, all_or_none = false))]
+        fn create(
+            &self,
+            py: Python<'_>,
+            sobject: &str,
+            records: &Bound<'_, PyList>,
+            all_or_none: bool,
+        ) -> PyResult<PyObject> {
+            self.dml(py, "create", sobject, records, None, all_or_none)
+        }
+
+        #[pyo3(signature = (sobject, records, all_or_none = false))]
+        fn update(
+            &self,
+            py: Python<'_>,
+            sobject: &str,
+            records: &Bound<'_, PyList>,
+            all_or_none: bool,
+        ) -> PyResult<PyObject> {
+            self.dml(py, "update", sobject, records, None, all_or_none)
+        }
+
+        #[pyo3(signature = (sobject, records, external_id, all_or_none = false))]
+        fn upsert(
+            &self,
+            py: Python<'_>,
+            sobject: &str,
+            records: &Bound<'_, PyList>,
+            external_id: String,
+            all_or_none: bool,
+        ) -> PyResult<PyObject> {
+            self.dml(
+                py,
+                "upsert",
+                sobject,
+                records,
+                Some(external_id),
+                all_or_none,
+            )
+        }
+
+        #[pyo3(signature = (sobject, records, all_or_none = false))]
+        fn delete(
+            &self,
+            py: Python<'_>,
+            sobject: &str,
+            records: &Bound<'_, PyList>,
+            all_or_none: bool,
+        ) -> PyResult<PyObject> {
+            self.dml(py, "delete", sobject, records, None, all_or_none)
+        }
+
+        /// Insert records with a Bulk API 2.0 job, returning the completed job.
+        fn bulk_insert(
+            &self,
+            py: Python<'_>,
+            sobject: &str,
+            records: &Bound<'_, PyList>,
+        ) -> PyResult<PyObject> {
+            self.bulk_dml(py, BulkApiDmlOperation::Insert, sobject, records, None)
+        }
+
+        fn bulk_update(
+            &self,
+            py: Python<'_>,
+            sobject: &str,
+            records: &Bound<'_, PyList>,
+        ) -> PyResult<PyObject> {
+            self.bulk_dml(py, BulkApiDmlOperation::Update, sobject, records, None)
+        }
+
+        fn bulk_upsert(
+            &self,
+            py: Python<'_>,
+            sobject: &str,
+            records: &Bound<'_, PyList>,
+            external_id: String,
+        ) -> PyResult<PyObject> {
+            self.bulk_dml(
+                py,
+                BulkApiDmlOperation::Upsert,
+                sobject,
+                records,
+                Some(external_id),
+            )
+        }
+
+        fn bulk_delete(
+            &self,
+            py: Python<'_>,
+            sobject: &str,
+            records: &Bound<'_, PyList>,
+        ) -> PyResult<PyObject> {
+            self.bulk_dml(py, BulkApiDmlOperation::Delete, sobject, records, None)
+        }
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "baris")]
+fn baris_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Connection>()?;
+    m.add_class::<RecordIterator>()?;
+    Ok(())
+}
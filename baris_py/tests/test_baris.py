@@ -0,0 +1,292 @@
+"""Tests for the Python bindings, against a local stand-in for Salesforce.
+
+Build the extension into the current environment with `maturin develop`,
+then run `python -m unittest discover tests` (or pytest) from `baris_py`.
+"""
+
+import json
+import threading
+import unittest
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+from urllib.parse import parse_qs, urlparse
+
+import baris
+
+API = "/services/data/v52.0"
+ACCOUNT_IDS = ["001000000000001AAA", "001000000000002AAA", "001000000000003AAA"]
+
+
+def field_describe(name, soap_type, field_type):
+    return {
+        "aggregatable": True,
+        "aiPredictionField": False,
+        "autoNumber": False,
+        "byteLength": 0,
+        "calculated": False,
+        "calculatedFormula": None,
+        "cascadeDelete": False,
+        "caseSensitive": False,
+        "compoundFieldName": None,
+        "controllerName": None,
+        "createable": True,
+        "custom": False,
+        "defaultValue": None,
+        "defaultValueFormula": None,
+        "defaultedOnCreate": False,
+        "dependentPicklist": False,
+        "deprecatedAndHidden": False,
+        "digits": 0,
+        "displayLocationInDecimal": False,
+        "encrypted": False,
+        "externalId": False,
+        "filterable": True,
+        "formulaTreatNullNumberAsZero": False,
+        "groupable": True,
+        "highScaleNumber": False,
+        "htmlFormatted": False,
+        "idLookup": False,
+        "inlineHelpText": None,
+        "label": name,
+        "length": 255,
+        "name": name,
+        "nameField": False,
+        "namePointing": False,
+        "nillable": True,
+        "permissionable": True,
+        "picklistValues": [],
+        "polymorphicForeignKey": False,
+        "precision": 0,
+        "queryByDistance": False,
+        "referenceTargetField": None,
+        "referenceTo": [],
+        "relationshipName": None,
+        "relationshipOrder": None,
+        "restrictedDelete": False,
+        "restrictedPicklist": False,
+        "scale": 0,
+        "searchPrefilterable": False,
+        "soapType": soap_type,
+        "sortable": True,
+        "type": field_type,
+        "unique": False,
+        "updateable": True,
+        "writeRequiresMasterRead": False,
+    }
+
+
+ACCOUNT_DESCRIBE = {
+    "activateable": False,
+    "childRelationships": [],
+    "compactLayoutable": True,
+    "createable": True,
+    "custom": False,
+    "customSetting": False,
+    "deepCloneable": False,
+    "deletable": True,
+    "feedEnabled": False,
+    "fields": [
+        field_describe("Id", "tns:ID", "id"),
+        field_describe("Name", "xsd:string", "string"),
+    ],
+    "hasSubtypes": False,
+    "isInterface": False,
+    "isSubtype": False,
+    "keyPrefix": "001",
+    "label": "Account",
+    "labelPlural": "Accounts",
+    "layoutable": True,
+    "listviewable": None,
+    "lookupLayoutable": None,
+    "mergeable": True,
+    "mruEnabled": True,
+    "name": "Account",
+    "namedLayoutInfos": [],
+    "networkScopeFieldName": None,
+    "queryable": True,
+    "recordTypeInfos": [],
+    "replicateable": True,
+    "retrieveable": True,
+    "searchLayoutable": True,
+    "searchable": True,
+    "supportedScopes": [],
+    "triggerable": True,
+    "undeletable": True,
+    "updateable": True,
+    "urls": {},
+}
+
+
+def account(index, name):
+    return {
+        "attributes": {"type": "Account"},
+        "Id": ACCOUNT_IDS[index],
+        "Name": name,
+    }
+
+
+class SalesforceHandler(BaseHTTPRequestHandler):
+    """Serves describes, two pages of query results, and sObject Collections
+    DML, rejecting records named "Bad". Each request is recorded on the server."""
+
+    def log_message(self, format, *args):
+        pass
+
+    def send_json(self, status, body):
+        content = json.dumps(body).encode("utf-8")
+        self.send_response(status)
+        self.send_header("Content-Type", "application/json")
+        self.send_header("Content-Length", str(len(content)))
+        self.end_headers()
+        self.wfile.write(content)
+
+    def record_request(self):
+        length = int(self.headers.get("Content-Length") or 0)
+        body = json.loads(self.rfile.read(length)) if length else None
+        url = urlparse(self.path)
+        self.server.requests.append((self.command, url.path, body))
+        return url, body
+
+    def do_GET(self):
+        url, _ = self.record_request()
+
+        if url.path == API + "/sobjects/Account/describe":
+            self.send_json(200, ACCOUNT_DESCRIBE)
+        elif url.path == API + "/query":
+            assert parse_qs(url.query)["q"] == ["SELECT Id, Name FROM Account"]
+            self.send_json(
+                200,
+                {
+                    "totalSize": 3,
+                    "done": False,
+                    "nextRecordsUrl": API + "/query/01g000000000001-2",
+                    "records": [account(0, "First"), account(1, "Second")],
+                },
+            )
+        elif url.path == API + "/query/01g000000000001-2":
+            self.send_json(
+                200,
+                {"totalSize": 3, "done": True, "records": [account(2, "Third")]},
+            )
+        else:
+            self.send_json(
+                404,
+                [{"errorCode": "NOT_FOUND", "message": "The requested resource does not exist"}],
+            )
+
+    def send_dml_results(self, records):
+        self.send_json(
+            200,
+            [
+                {"success": True, "id": ACCOUNT_IDS[index], "errors": []}
+                if record.get("name") != "Bad"
+                else {
+                    "success": False,
+                    "errors": [
+                        {
+                            "statusCode": "FIELD_CUSTOM_VALIDATION_EXCEPTION",
+                            "message": "Bad name",
+                            "fields": ["Name"],
+                        }
+                    ],
+                }
+                for index, record in enumerate(records)
+            ],
+        )
+
+    def do_POST(self):
+        _, body = self.record_request()
+        self.send_dml_results(body["records"])
+
+    def do_PATCH(self):
+        _, body = self.record_request()
+        self.send_dml_results(body["records"])
+
+    def do_DELETE(self):
+        url, _ = self.record_request()
+        ids = parse_qs(url.query)["ids"][0].split(",")
+        self.send_dml_results([{"id": id} for id in ids])
+
+
+class ConnectionTest(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.server = ThreadingHTTPServer(("127.0.0.1", 0), SalesforceHandler)
+        cls.server.requests = []
+        cls.thread = threading.Thread(target=cls.server.serve_forever, daemon=True)
+        cls.thread.start()
+
+    @classmethod
+    def tearDownClass(cls):
+        cls.server.shutdown()
+        cls.server.server_close()
+
+    def setUp(self):
+        self.server.requests.clear()
+        self.conn = baris.Connection.with_access_token(
+            "00D000000000001!token",
+            "http://127.0.0.1:{}".format(self.server.server_address[1]),
+        )
+
+    def get_requests(self, method):
+        return [r for r in self.server.requests if r[0] == method]
+
+    def test_query_iterates_all_pages(self):
+        records = list(self.conn.query("Account", "SELECT Id, Name FROM Account"))
+
+        # Field names are case-insensitive, and returned in lowercase.
+        self.assertEqual([r["name"] for r in records], ["First", "Second", "Third"])
+        self.assertEqual([r["id"] for r in records], ACCOUNT_IDS)
+        self.assertEqual(
+            [r[1] for r in self.get_requests("GET") if "/query" in r[1]],
+            [API + "/query", API + "/query/01g000000000001-2"],
+        )
+
+    def test_create_returns_one_result_per_record(self):
+        results = self.conn.create("Account", [{"Name": "Good"}, {"Name": "Bad"}])
+
+        self.assertEqual(len(results), 2)
+        self.assertTrue(results[0]["success"])
+        self.assertEqual(results[0]["id"], ACCOUNT_IDS[0])
+        self.assertFalse(results[1]["success"])
+        self.assertEqual(
+            results[1]["errors"][0]["statusCode"], "FIELD_CUSTOM_VALIDATION_EXCEPTION"
+        )
+
+        (_, path, body), = self.get_requests("POST")
+        self.assertEqual(path, API + "/composite/sobjects")
+        self.assertFalse(body["allOrNone"])
+        self.assertEqual(
+            [(r["attributes"]["type"], r["name"]) for r in body["records"]],
+            [("Account", "Good"), ("Account", "Bad")],
+        )
+
+    def test_update_and_delete(self):
+        results = self.conn.update(
+            "Account", [{"Id": ACCOUNT_IDS[0], "Name": "Renamed"}], all_or_none=True
+        )
+        self.assertTrue(results[0]["success"])
+        (_, _, body), = self.get_requests("PATCH")
+        self.assertTrue(body["allOrNone"])
+        self.assertEqual(body["records"][0]["id"], ACCOUNT_IDS[0])
+
+        results = self.conn.delete("Account", [{"Id": ACCOUNT_IDS[0]}, {"Id": ACCOUNT_IDS[1]}])
+        self.assertEqual(len(results), 2)
+        self.assertTrue(all(r["success"] for r in results))
+        (_, path, _), = self.get_requests("DELETE")
+        self.assertEqual(path, API + "/composite/sobjects")
+
+    def test_create_rejects_records_with_ids(self):
+        with self.assertRaises(RuntimeError):
+            self.conn.create("Account", [{"Id": ACCOUNT_IDS[0], "Name": "Existing"}])
+        self.assertEqual(self.get_requests("POST"), [])
+
+    def test_errors_are_raised(self):
+        with self.assertRaises(RuntimeError):
+            self.conn.query("Contact", "SELECT Id FROM Contact")
+
+        with self.assertRaises(ValueError):
+            baris.Connection.with_access_token("token", "not a url")
+
+
+if __name__ == "__main__":
+    unittest.main()
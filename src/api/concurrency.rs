@@ -0,0 +1,229 @@
+//! Adaptive concurrency for parallel DML and Bulk result retrieval.
+//!
+//! Rather than a fixed number of requests in flight, an `AdaptiveConcurrency`
+//! policy lets a `Connection` find the concurrency an org will bear. The limit
+//! grows by about one request per round of successful requests, and is cut
+//! multiplicatively when Salesforce reports lock contention or exhausted limits,
+//! or when the median latency climbs well above the best median observed.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::Notify;
+
+use super::retry::is_transient_error;
+use crate::rest::DmlError;
+
+/// Controls how a `Connection` adjusts the number of sObject Collections
+/// requests in flight when DML is performed without a fixed `parallel` count,
+/// and the number of Bulk API query result ranges retrieved at once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptiveConcurrency {
+    min: usize,
+    max: usize,
+    initial: usize,
+    window: usize,
+    latency_tolerance: f64,
+    backoff: f64,
+}
+
+impl AdaptiveConcurrency {
+    /// A policy that keeps between `min` and `max` requests in flight,
+    /// starting from `min`.
+    pub fn new(min: usize, max: usize) -> AdaptiveConcurrency {
+        let min = min.max(1);
+        let max = max.max(min);
+
+        AdaptiveConcurrency {
+            min,
+            max,
+            initial: min,
+            window: 20,
+            latency_tolerance: 2.0,
+            backoff: 0.5,
+        }
+    }
+
+    #[must_use]
+    pub fn with_initial(mut self, initial: usize) -> Self {
+        self.initial = initial.clamp(self.min, self.max);
+        self
+    }
+
+    /// Judge latency by the median of the last `window` requests.
+    #[must_use]
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = window.max(1);
+        self
+    }
+
+    /// Back off once the median latency exceeds the best median observed
+    /// by a factor of `latency_tolerance`.
+    #[must_use]
+    pub fn with_latency_tolerance(mut self, latency_tolerance: f64) -> Self {
+        self.latency_tolerance = latency_tolerance.max(1.0);
+        self
+    }
+
+    /// Multiply the limit by `backoff`, between 0 and 1, when backing off.
+    #[must_use]
+    pub fn with_backoff(mut self, backoff: f64) -> Self {
+        self.backoff = backoff.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn get_min(&self) -> usize {
+        self.min
+    }
+
+    pub fn get_max(&self) -> usize {
+        self.max
+    }
+}
+
+#[derive(Debug)]
+struct ControllerState {
+    limit: f64,
+    in_flight: usize,
+    latencies: VecDeque<Duration>,
+    best_median: Option<Duration>,
+}
+
+/// The shared state of an `AdaptiveConcurrency` policy: the current limit
+/// and the requests in flight under it.
+#[derive(Debug)]
+pub struct ConcurrencyController {
+    policy: AdaptiveConcurrency,
+    state: Mutex<ControllerState>,
+    released: Notify,
+}
+
+impl ConcurrencyController {
+    pub fn new(policy: AdaptiveConcurrency) -> ConcurrencyController {
+        ConcurrencyController {
+            state: Mutex::new(ControllerState {
+                limit: policy.initial as f64,
+                in_flight: 0,
+                latencies: VecDeque::with_capacity(policy.window),
+                best_median: None,
+            }),
+            policy,
+            released: Notify::new(),
+        }
+    }
+
+    pub fn get_policy(&self) -> &AdaptiveConcurrency {
+        &self.policy
+    }
+
+    /// The number of requests currently allowed in flight.
+    pub fn get_limit(&self) -> usize {
+        self.state.lock().unwrap().limit as usize
+    }
+
+    pub fn get_in_flight(&self) -> usize {
+        self.state.lock().unwrap().in_flight
+    }
+
+    /// Wait until another request may be sent. The request counts as in flight
+    /// until the returned permit is dropped.
+    pub async fn acquire(self: &Arc<Self>) -> ConcurrencyPermit {
+        loop {
+            let released = self.released.notified();
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.in_flight < state.limit as usize {
+                    state.in_flight += 1;
+                    return ConcurrencyPermit {
+                        controller: Arc::clone(self),
+                    };
+                }
+            }
+            released.await;
+        }
+    }
+
+    /// Record a completed request that took `latency`. If `throttled`, Salesforce
+    /// reported contention or exhausted limits, and the limit is cut.
+    pub fn record(&self, latency: Duration, throttled: bool) {
+        let policy = &self.policy;
+        let mut state = self.state.lock().unwrap();
+
+        if throttled {
+            state.limit = (state.limit * policy.backoff).max(policy.min as f64);
+            // Latencies observed at the old limit no longer apply.
+            state.latencies.clear();
+        } else {
+            if state.latencies.len() == policy.window {
+                state.latencies.pop_front();
+            }
+            state.latencies.push_back(latency);
+
+            let median = if state.latencies.len() == policy.window {
+                Some(get_median(&state.latencies))
+            } else {
+                None
+            };
+            let congested = match (median, state.best_median) {
+                (Some(median), Some(best)) => {
+                    median.as_secs_f64() > best.as_secs_f64() * policy.latency_tolerance
+                }
+                _ => false,
+            };
+
+            if let Some(median) = median {
+                state.best_median = Some(state.best_median.map_or(median, |b| b.min(median)));
+            }
+
+            if congested {
+                state.limit = (state.limit * policy.backoff).max(policy.min as f64);
+                state.latencies.clear();
+            } else {
+                state.limit = (state.limit + 1.0 / state.limit).min(policy.max as f64);
+            }
+        }
+
+        drop(state);
+        self.released.notify_waiters();
+    }
+
+    fn release(&self) {
+        self.state.lock().unwrap().in_flight -= 1;
+        self.released.notify_waiters();
+    }
+}
+
+/// A request in flight under a `ConcurrencyController`.
+#[derive(Debug)]
+pub struct ConcurrencyPermit {
+    controller: Arc<ConcurrencyController>,
+}
+
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        self.controller.release();
+    }
+}
+
+fn get_median(latencies: &VecDeque<Duration>) -> Duration {
+    let mut sorted: Vec<Duration> = latencies.iter().copied().collect();
+    sorted.sort();
+
+    sorted[sorted.len() / 2]
+}
+
+/// Whether `error`, for a request or a single record, shows that Salesforce is
+/// shedding load, such that fewer requests should be in flight.
+pub(crate) fn is_throttling_error(error: &anyhow::Error) -> bool {
+    if let Some(error) = error.downcast_ref::<DmlError>() {
+        return error.get_error_code().is_some_and(|code| {
+            matches!(
+                code.as_str(),
+                "UNABLE_TO_LOCK_ROW" | "REQUEST_LIMIT_EXCEEDED" | "SERVER_UNAVAILABLE"
+            )
+        });
+    }
+
+    is_transient_error(error)
+}
@@ -0,0 +1,288 @@
+//! Caching of sObject describes.
+//!
+//! A `Connection` describes each sObject type once and, by default, keeps the
+//! result for its lifetime. A `DescribeCachePolicy` can instead expire describes
+//! after a time-to-live, after which they are revalidated with a conditional
+//! request, and can persist them to disk so that later processes against the
+//! same org start with a warm cache.
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use log::warn;
+use serde_derive::{Deserialize, Serialize};
+use tokio::fs;
+
+use super::Connection;
+use crate::{
+    data::{DateTime, SObjectType},
+    rest::describe::{SObjectDescribe, SObjectDescribeIfModifiedRequest, SObjectDescribeRequest},
+};
+
+/// Controls how long a `Connection` keeps sObject describes, and where,
+/// if anywhere, it persists them.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DescribeCachePolicy {
+    ttl: Option<Duration>,
+    directory: Option<PathBuf>,
+}
+
+impl DescribeCachePolicy {
+    /// A policy that keeps describes in memory indefinitely.
+    pub fn new() -> DescribeCachePolicy {
+        DescribeCachePolicy::default()
+    }
+
+    /// Revalidate describes older than `ttl`. Salesforce answers the
+    /// revalidation with an empty response if the describe has not changed.
+    #[must_use]
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Persist describes as JSON files beneath `directory`, organized by
+    /// instance and API version.
+    #[must_use]
+    pub fn with_directory(mut self, directory: impl AsRef<Path>) -> Self {
+        self.directory = Some(directory.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn get_ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    pub fn get_directory(&self) -> Option<&Path> {
+        self.directory.as_deref()
+    }
+
+    fn is_fresh(&self, entry: &CachedType) -> bool {
+        match self.ttl {
+            Some(ttl) => chrono::Utc::now()
+                .signed_duration_since(*entry.fetched)
+                .to_std()
+                .map(|age| age < ttl)
+                .unwrap_or(true), // Fetched in the future, per our clock.
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct CachedType {
+    pub(crate) sobject_type: SObjectType,
+    pub(crate) fetched: DateTime,
+}
+
+impl CachedType {
+    pub(crate) fn new(sobject_type: SObjectType) -> CachedType {
+        CachedType {
+            sobject_type,
+            fetched: DateTime::now(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PersistedDescribeRef<'a> {
+    fetched: &'a DateTime,
+    describe: &'a SObjectDescribe,
+}
+
+#[derive(Deserialize)]
+struct PersistedDescribe {
+    fetched: DateTime,
+    describe: SObjectDescribe,
+}
+
+impl Connection {
+    pub fn set_describe_cache_policy(&self, policy: DescribeCachePolicy) {
+        *self.describe_cache_policy.write().unwrap() = policy;
+    }
+
+    pub fn get_describe_cache_policy(&self) -> DescribeCachePolicy {
+        self.describe_cache_policy.read().unwrap().clone()
+    }
+
+    /// Discard the cached describe for `type_name`, in memory and on disk,
+    /// so that the next `get_type()` describes it afresh.
+    pub async fn invalidate_type(&self, type_name: &str) -> Result<()> {
+        let key = self.resolve_type_name(type_name).to_lowercase();
+
+        self.sobject_types.write().await.remove(&key);
+        if let Some(path) = self.get_cache_path(&key).await? {
+            match fs::remove_file(path).await {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Describe `type_name` afresh, replacing any cached describe.
+    pub async fn refresh_type(&self, type_name: &str) -> Result<SObjectType> {
+        self.invalidate_type(type_name).await?;
+        self.get_type(type_name).await
+    }
+
+    /// Get a cached type from memory or disk, describing it if it is absent,
+    /// or revalidating it if it has expired.
+    pub(crate) async fn get_cached_type(&self, type_name: &str) -> Result<SObjectType> {
+        let key = type_name.to_lowercase();
+        let policy = self.get_describe_cache_policy();
+
+        if let Some(entry) = self.get_fresh_type(&key, &policy).await {
+            return Ok(entry);
+        }
+
+        // Only one task describes a given type at a time. The others wait
+        // for it, then find its describe in the cache.
+        let flight = Arc::clone(
+            self.describe_flights
+                .lock()
+                .unwrap()
+                .entry(key.clone())
+                .or_default(),
+        );
+        let _guard = flight.lock().await;
+
+        if let Some(entry) = self.get_fresh_type(&key, &policy).await {
+            return Ok(entry);
+        }
+
+        let cached = match self.sobject_types.read().await.get(&key).cloned() {
+            Some(entry) => Some(entry),
+            None => self.load_cached_type(&key).await?,
+        };
+        let entry = match cached {
+            Some(entry) if policy.is_fresh(&entry) => entry,
+            cached => {
+                let entry = self.describe_type(type_name, cached).await?;
+                if let Err(e) = self.save_cached_type(&key, &entry).await {
+                    warn!("Unable to persist describe for {}: {}", type_name, e);
+                }
+                entry
+            }
+        };
+
+        let sobject_type = entry.sobject_type.clone();
+        self.sobject_types.write().await.insert(key.clone(), entry);
+        self.describe_flights.lock().unwrap().remove(&key);
+
+        Ok(sobject_type)
+    }
+
+    async fn get_fresh_type(&self, key: &str, policy: &DescribeCachePolicy) -> Option<SObjectType> {
+        self.sobject_types
+            .read()
+            .await
+            .get(key)
+            .filter(|entry| policy.is_fresh(entry))
+            .map(|entry| entry.sobject_type.clone())
+    }
+
+    async fn describe_type(
+        &self,
+        type_name: &str,
+        cached: Option<CachedType>,
+    ) -> Result<CachedType> {
+        match cached {
+            Some(cached) => match self
+                .execute(&SObjectDescribeIfModifiedRequest::new(
+                    type_name,
+                    cached.fetched.clone(),
+                ))
+                .await?
+            {
+                Some(describe) => Ok(CachedType::new(SObjectType::new(
+                    describe.name.clone(),
+                    describe,
+                ))),
+                // 304 Not Modified: the cached describe remains current.
+                None => Ok(CachedType::new(cached.sobject_type)),
+            },
+            None => {
+                let describe = self
+                    .execute(&SObjectDescribeRequest::new(type_name))
+                    .await?;
+                Ok(CachedType::new(SObjectType::new(
+                    describe.name.clone(),
+                    describe,
+                )))
+            }
+        }
+    }
+
+    async fn get_cache_path(&self, key: &str) -> Result<Option<PathBuf>> {
+        let policy = self.get_describe_cache_policy();
+        let directory = match policy.get_directory() {
+            Some(directory) => directory,
+            None => return Ok(None),
+        };
+        // Describes differ between orgs, so key them by instance.
+        let instance_url = self.get_instance_url().await?;
+        let host = instance_url.host_str().unwrap_or("default");
+
+        Ok(Some(
+            directory
+                .join(host)
+                .join(&self.api_version)
+                .join(format!("{}.json", key)),
+        ))
+    }
+
+    async fn load_cached_type(&self, key: &str) -> Result<Option<CachedType>> {
+        let path = match self.get_cache_path(key).await? {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let content = match fs::read(&path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        // An unreadable cache file is treated as a cache miss.
+        match serde_json::from_slice::<PersistedDescribe>(&content) {
+            Ok(persisted) => Ok(Some(CachedType {
+                sobject_type: SObjectType::new(persisted.describe.name.clone(), persisted.describe),
+                fetched: persisted.fetched,
+            })),
+            Err(e) => {
+                warn!("Ignoring unreadable describe cache {:?}: {}", path, e);
+                Ok(None)
+            }
+        }
+    }
+
+    async fn save_cached_type(&self, key: &str, entry: &CachedType) -> Result<()> {
+        let path = match self.get_cache_path(key).await? {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        // Write to a sibling file and rename, so that a concurrent reader
+        // never sees a partial describe.
+        let temp_path = path.with_extension("tmp");
+        fs::write(
+            &temp_path,
+            serde_json::to_vec(&PersistedDescribeRef {
+                fetched: &entry.fetched,
+                describe: entry.sobject_type.get_describe(),
+            })?,
+        )
+        .await?;
+        fs::rename(&temp_path, &path).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use reqwest::Method;
+use serde_json::Value;
+
+use super::{Connection, SalesforceRequest};
+
+/// An object-safe view of a request, for callers that must store or pass
+/// requests as trait objects, such as scripting layers. Every
+/// `SalesforceRequest` is an `ErasedRequest`; executing it through
+/// `Connection::execute_erased()` returns the raw JSON response rather than
+/// the request's typed result.
+///
+/// Erased requests cannot synthesize dry-run results, so mutating erased
+/// requests fail in dry-run mode. This trait shares method names with
+/// `SalesforceRequest`, so import it only where requests are erased.
+pub trait ErasedRequest: Send + Sync {
+    fn get_body(&self) -> Option<Value>;
+    fn get_url(&self) -> String;
+    fn get_method(&self) -> Method;
+    fn get_query_parameters(&self) -> Option<Value>;
+    fn get_headers(&self) -> Option<HashMap<String, String>>;
+    fn is_mutating(&self) -> bool;
+}
+
+impl<T> ErasedRequest for T
+where
+    T: SalesforceRequest + Send + Sync,
+{
+    fn get_body(&self) -> Option<Value> {
+        SalesforceRequest::get_body(self)
+    }
+
+    fn get_url(&self) -> String {
+        SalesforceRequest::get_url(self)
+    }
+
+    fn get_method(&self) -> Method {
+        SalesforceRequest::get_method(self)
+    }
+
+    fn get_query_parameters(&self) -> Option<Value> {
+        SalesforceRequest::get_query_parameters(self)
+    }
+
+    fn get_headers(&self) -> Option<HashMap<String, String>> {
+        SalesforceRequest::get_headers(self)
+    }
+
+    fn is_mutating(&self) -> bool {
+        SalesforceRequest::is_mutating(self)
+    }
+}
+
+/// A request assembled at runtime from its parts, relative to the
+/// Connection's base URL, such as `sobjects/Account/describe`.
+pub struct JsonRequest {
+    method: Method,
+    url: String,
+    body: Option<Value>,
+    query_parameters: Option<Value>,
+    headers: Option<HashMap<String, String>>,
+}
+
+impl JsonRequest {
+    pub fn new(method: Method, url: &str) -> JsonRequest {
+        JsonRequest {
+            method,
+            url: url.to_owned(),
+            body: None,
+            query_parameters: None,
+            headers: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_body(mut self, body: Value) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    #[must_use]
+    pub fn with_query_parameters(mut self, query_parameters: Value) -> Self {
+        self.query_parameters = Some(query_parameters);
+        self
+    }
+
+    #[must_use]
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers
+            .get_or_insert_with(HashMap::new)
+            .insert(name.to_owned(), value.to_owned());
+        self
+    }
+}
+
+impl SalesforceRequest for JsonRequest {
+    type ReturnValue = Value;
+
+    fn get_body(&self) -> Option<Value> {
+        self.body.clone()
+    }
+
+    fn get_url(&self) -> String {
+        self.url.clone()
+    }
+
+    fn get_method(&self) -> Method {
+        self.method.clone()
+    }
+
+    fn get_query_parameters(&self) -> Option<Value> {
+        self.query_parameters.clone()
+    }
+
+    fn get_headers(&self) -> Option<HashMap<String, String>> {
+        self.headers.clone()
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        Ok(body.cloned().unwrap_or(Value::Null))
+    }
+}
+
+// Executes an erased request through the typed machinery.
+struct ErasedAdapter<'a>(&'a dyn ErasedRequest);
+
+impl SalesforceRequest for ErasedAdapter<'_> {
+    type ReturnValue = Value;
+
+    fn get_body(&self) -> Option<Value> {
+        self.0.get_body()
+    }
+
+    fn get_url(&self) -> String {
+        self.0.get_url()
+    }
+
+    fn get_method(&self) -> Method {
+        self.0.get_method()
+    }
+
+    fn get_query_parameters(&self) -> Option<Value> {
+        self.0.get_query_parameters()
+    }
+
+    fn get_headers(&self) -> Option<HashMap<String, String>> {
+        self.0.get_headers()
+    }
+
+    fn is_mutating(&self) -> bool {
+        self.0.is_mutating()
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        Ok(body.cloned().unwrap_or(Value::Null))
+    }
+}
+
+impl Connection {
+    /// Execute a request held as a trait object, returning its JSON response
+    /// body, or `Value::Null` if the response has no body.
+    pub async fn execute_erased(&self, request: &dyn ErasedRequest) -> Result<Value> {
+        self.execute(&ErasedAdapter(request)).await
+    }
+}
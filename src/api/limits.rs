@@ -0,0 +1,214 @@
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::Result;
+use log::info;
+use reqwest::header::HeaderMap;
+use reqwest::Response;
+use tokio::time::{sleep, Instant};
+
+use crate::errors::SalesforceError;
+use crate::rest::org::LimitsRequest;
+
+use super::{Connection, SalesforceRequest};
+
+pub(crate) const LIMIT_INFO_HEADER: &str = "Sforce-Limit-Info";
+
+/// The default age after which a usage report that exceeds a throttle's
+/// headroom is refreshed from the `limits` resource.
+pub const DEFAULT_USAGE_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The org's API usage in the last 24 hours, as most recently reported by
+/// Salesforce in the `Sforce-Limit-Info` header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ApiUsage {
+    used: u64,
+    limit: u64,
+}
+
+impl ApiUsage {
+    pub fn new(used: u64, limit: u64) -> ApiUsage {
+        ApiUsage { used, limit }
+    }
+
+    /// Parse a `Sforce-Limit-Info` value, such as `api-usage=18/5000`.
+    pub fn parse(value: &str) -> Option<ApiUsage> {
+        let usage = value
+            .split(';')
+            .find_map(|part| part.trim().strip_prefix("api-usage="))?;
+        let (used, limit) = usage.split_once('/')?;
+
+        Some(ApiUsage::new(
+            used.trim().parse().ok()?,
+            limit.trim().parse().ok()?,
+        ))
+    }
+
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Option<ApiUsage> {
+        ApiUsage::parse(headers.get(LIMIT_INFO_HEADER)?.to_str().ok()?)
+    }
+
+    pub fn get_used(&self) -> u64 {
+        self.used
+    }
+
+    pub fn get_limit(&self) -> u64 {
+        self.limit
+    }
+
+    pub fn get_remaining(&self) -> u64 {
+        self.limit.saturating_sub(self.used)
+    }
+}
+
+impl fmt::Display for ApiUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{} API requests", self.used, self.limit)
+    }
+}
+
+/// Slows or stops a Connection's requests once the org's remaining API
+/// requests fall to `headroom`, so that other integrations are not starved.
+///
+/// By default, requests fail with `SalesforceError::ApiUsageExceeded`.
+/// Use `with_delay()` to wait, checking again after each delay, until usage
+/// falls below the headroom instead.
+///
+/// A throttled Connection receives no responses to report its usage, so
+/// once the last report is older than the refresh interval, usage is read
+/// again from the `limits` resource before a request is held back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiThrottle {
+    headroom: u64,
+    delay: Option<Duration>,
+    refresh_interval: Duration,
+}
+
+impl ApiThrottle {
+    pub fn new(headroom: u64) -> ApiThrottle {
+        ApiThrottle {
+            headroom,
+            delay: None,
+            refresh_interval: DEFAULT_USAGE_REFRESH_INTERVAL,
+        }
+    }
+
+    #[must_use]
+    pub fn with_refresh_interval(mut self, refresh_interval: Duration) -> Self {
+        self.refresh_interval = refresh_interval;
+        self
+    }
+
+    pub fn get_refresh_interval(&self) -> Duration {
+        self.refresh_interval
+    }
+
+    #[must_use]
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    pub fn get_headroom(&self) -> u64 {
+        self.headroom
+    }
+
+    pub fn get_delay(&self) -> Option<Duration> {
+        self.delay
+    }
+
+    fn is_exceeded(&self, usage: &ApiUsage) -> bool {
+        usage.get_remaining() <= self.headroom
+    }
+}
+
+impl Connection {
+    /// The org's API usage as of the most recent response, if Salesforce has reported it.
+    pub fn get_api_usage(&self) -> Option<ApiUsage> {
+        self.api_usage.read().unwrap().map(|(usage, _)| usage)
+    }
+
+    /// Set the throttle applied to this Connection's requests based on API usage.
+    /// If `None`, requests are sent regardless of usage.
+    pub fn set_api_throttle(&self, api_throttle: Option<ApiThrottle>) {
+        *self.api_throttle.write().unwrap() = api_throttle;
+    }
+
+    pub fn get_api_throttle(&self) -> Option<ApiThrottle> {
+        self.api_throttle.read().unwrap().clone()
+    }
+
+    pub(crate) fn record_api_usage(&self, headers: &HeaderMap) {
+        if let Some(usage) = ApiUsage::from_headers(headers) {
+            self.set_api_usage(usage);
+        }
+    }
+
+    fn set_api_usage(&self, usage: ApiUsage) {
+        *self.api_usage.write().unwrap() = Some((usage, Instant::now()));
+    }
+
+    /// Hold back a request while the org's API usage is within the throttle's headroom.
+    pub(crate) async fn throttle(&self) -> Result<()> {
+        let throttle = match self.get_api_throttle() {
+            Some(throttle) => throttle,
+            None => return Ok(()),
+        };
+
+        loop {
+            let usage = match self.get_current_api_usage(&throttle).await {
+                Some(usage) if throttle.is_exceeded(&usage) => usage,
+                _ => return Ok(()),
+            };
+
+            match throttle.get_delay() {
+                Some(delay) => {
+                    info!("API usage is at {}: waiting {:?}", usage, delay);
+                    sleep(delay).await;
+                }
+                None => return Err(SalesforceError::ApiUsageExceeded(usage).into()),
+            }
+        }
+    }
+
+    // The last usage report, refreshed if it exceeds the headroom and is stale.
+    async fn get_current_api_usage(&self, throttle: &ApiThrottle) -> Option<ApiUsage> {
+        let (usage, updated) = (*self.api_usage.read().unwrap())?;
+        if !throttle.is_exceeded(&usage) || updated.elapsed() < throttle.get_refresh_interval() {
+            return Some(usage);
+        }
+
+        match self.refresh_api_usage().await {
+            Ok(usage) => Some(usage),
+            Err(e) => {
+                // Keep the stale report, but wait a full interval before trying again.
+                info!("Unable to refresh API usage: {}", e);
+                self.set_api_usage(usage);
+                Some(usage)
+            }
+        }
+    }
+
+    /// Read the org's API usage from the `limits` resource. The request
+    /// is sent directly, bypassing the throttle and retries.
+    async fn refresh_api_usage(&self) -> Result<ApiUsage> {
+        let request = LimitsRequest::new();
+        let response = Response::from(
+            self.get_transport()
+                .send(self.build_request(&request).await?)
+                .await?,
+        )
+        .error_for_status()?;
+        let limits = request.get_result(self, Some(&response.json().await?))?;
+
+        let usage = limits
+            .get("DailyApiRequests")
+            .map(|limit| ApiUsage::new(limit.get_used().max(0) as u64, limit.max.max(0) as u64))
+            .ok_or_else(|| {
+                SalesforceError::GeneralError("No DailyApiRequests limit was returned".to_owned())
+            })?;
+        self.set_api_usage(usage);
+
+        Ok(usage)
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::info;
+
+/// A snapshot of the throughput of a result stream or Bulk API upload.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StreamMetrics {
+    pub records: u64,
+    /// Pages of results retrieved, or Bulk API uploads made.
+    pub pages: u64,
+    /// Bytes of Bulk API result and upload data. Pages of JSON results
+    /// are decoded as they are read, and are not counted.
+    pub bytes: u64,
+    pub elapsed: Duration,
+    /// The average time taken to retrieve a page or make an upload.
+    pub average_page_latency: Option<Duration>,
+}
+
+impl fmt::Display for StreamMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} records in {} pages ({} bytes) after {:.1?}",
+            self.records, self.pages, self.bytes, self.elapsed
+        )?;
+        if let Some(latency) = self.average_page_latency {
+            write!(f, ", averaging {:.1?} per page", latency)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct RecorderState {
+    started: Instant,
+    records: u64,
+    pages: u64,
+    bytes: u64,
+    timed_pages: u32,
+    total_latency: Duration,
+    log: Option<(String, Duration)>,
+    last_logged: Instant,
+}
+
+/// Collects `StreamMetrics` as a stream is consumed or an upload proceeds.
+/// Clones share the same counts, so one clone can be read while another records.
+#[derive(Clone)]
+pub struct MetricsRecorder(Arc<Mutex<RecorderState>>);
+
+impl Default for MetricsRecorder {
+    fn default() -> Self {
+        MetricsRecorder::new()
+    }
+}
+
+impl MetricsRecorder {
+    pub fn new() -> MetricsRecorder {
+        let now = Instant::now();
+
+        MetricsRecorder(Arc::new(Mutex::new(RecorderState {
+            started: now,
+            records: 0,
+            pages: 0,
+            bytes: 0,
+            timed_pages: 0,
+            total_latency: Duration::ZERO,
+            log: None,
+            last_logged: now,
+        })))
+    }
+
+    /// Log the metrics, prefixed with `label`, at most once per `interval`
+    /// as pages are recorded.
+    #[must_use]
+    pub fn with_log(self, label: &str, interval: Duration) -> Self {
+        self.0.lock().unwrap().log = Some((label.to_owned(), interval));
+        self
+    }
+
+    pub fn get_metrics(&self) -> StreamMetrics {
+        let state = self.0.lock().unwrap();
+
+        StreamMetrics {
+            records: state.records,
+            pages: state.pages,
+            bytes: state.bytes,
+            elapsed: state.started.elapsed(),
+            average_page_latency: (state.timed_pages > 0)
+                .then(|| state.total_latency / state.timed_pages),
+        }
+    }
+
+    pub(crate) fn record_records(&self, records: u64) {
+        self.0.lock().unwrap().records += records;
+    }
+
+    /// Record a page of `bytes`, taking `latency` to retrieve or upload if known.
+    pub(crate) fn record_page(&self, bytes: u64, latency: Option<Duration>) {
+        let mut state = self.0.lock().unwrap();
+        state.pages += 1;
+        state.bytes += bytes;
+        if let Some(latency) = latency {
+            state.timed_pages += 1;
+            state.total_latency += latency;
+        }
+
+        let due = match &state.log {
+            Some((_, interval)) => state.last_logged.elapsed() >= *interval,
+            None => false,
+        };
+        if due {
+            state.last_logged = Instant::now();
+            let label = state
+                .log
+                .as_ref()
+                .map(|(l, _)| l.clone())
+                .unwrap_or_default();
+            drop(state);
+            info!("{}: {}", label, self.get_metrics());
+        }
+    }
+}
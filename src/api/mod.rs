@@ -4,20 +4,45 @@ extern crate serde_derive;
 extern crate serde_json;
 
 use std::collections::HashMap;
+use std::future::Future;
 use std::ops::Deref;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use super::data::SObjectType;
+use super::data::{SObjectType, SalesforceId};
 use super::errors::SalesforceError;
 
-use crate::auth::Authentication;
-use crate::rest::describe::{SObjectDescribe, SObjectDescribeRequest};
+use crate::api::concurrency::{AdaptiveConcurrency, ConcurrencyController};
+use crate::api::describe_cache::{CachedType, DescribeCachePolicy};
+use crate::api::limits::{ApiThrottle, ApiUsage};
+use crate::api::polling::PollingOptions;
+use crate::api::retry::RetryPolicy;
+use crate::api::transport::{HttpTransport, ReqwestTransport, TransportBody, TransportRequest};
+use crate::auth::{Authentication, TokenInfo};
+use crate::rest::rows::coalesce::RetrieveCoalescer;
+use crate::rest::ApiError;
+use crate::users::UserCache;
 
 use anyhow::{Error, Result};
 use async_trait::async_trait;
-use reqwest::{header, Body, Client, Method, RequestBuilder, Response, StatusCode, Url};
+use futures::{stream, Stream, StreamExt};
+use log::info;
+use reqwest::{header, Client, Method, Response, StatusCode, Url};
 use serde_json::Value;
+use tokio::spawn;
 use tokio::sync::{Mutex, RwLock};
+use tokio::time::sleep;
+
+pub mod concurrency;
+pub mod describe_cache;
+pub mod erased;
+pub mod limits;
+pub mod metrics;
+pub mod polling;
+pub mod retry;
+pub mod transport;
 
 #[cfg(test)]
 mod test;
@@ -29,6 +54,13 @@ pub trait SalesforceRequest {
         None
     }
 
+    /// The body sent when this request is executed on `conn`. Requests that
+    /// carry other requests, such as Composite requests, apply the Connection's
+    /// defaults to them here.
+    fn get_body_for_connection(&self, _conn: &Connection) -> Option<Value> {
+        self.get_body()
+    }
+
     fn get_url(&self) -> String;
     fn get_method(&self) -> Method;
 
@@ -37,13 +69,42 @@ pub trait SalesforceRequest {
     }
 
     fn get_result(&self, conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue>;
+
+    /// Whether this request may change data in the org.
+    /// Mutating requests are not sent while the Connection is in dry-run mode.
+    fn is_mutating(&self) -> bool {
+        self.get_method() != Method::GET
+    }
+
+    /// The synthetic result returned in place of executing this request
+    /// while the Connection is in dry-run mode.
+    fn get_dry_run_result(&self, _conn: &Connection) -> Result<Self::ReturnValue> {
+        Err(SalesforceError::DryRunNotSupported.into())
+    }
+
+    fn get_headers(&self) -> Option<HashMap<String, String>> {
+        None
+    }
+
+    /// Whether a response with the unsuccessful `status` carries a body for
+    /// `get_result()` to interpret, rather than errors to be returned as-is.
+    fn accepts_error_status(&self, _status: StatusCode) -> bool {
+        false
+    }
+
+    /// The size in bytes of this request's serialized body.
+    fn estimate_payload_size(&self) -> usize {
+        self.get_body()
+            .map(|body| body.to_string().len())
+            .unwrap_or(0)
+    }
 }
 
 #[async_trait]
-pub(crate) trait SalesforceRawRequest {
+pub trait SalesforceRawRequest {
     type ReturnValue;
 
-    fn get_body(&self) -> Option<Body> {
+    fn get_body(&self) -> Option<TransportBody> {
         None
     }
     fn get_mime_type(&self) -> String {
@@ -57,17 +118,152 @@ pub(crate) trait SalesforceRawRequest {
         None
     }
 
+    fn get_headers(&self) -> Option<HashMap<String, String>> {
+        None
+    }
+
     async fn get_result(&self, conn: &Connection, response: Response) -> Result<Self::ReturnValue>;
+
+    fn is_mutating(&self) -> bool {
+        self.get_method() != Method::GET
+    }
+
+    fn get_dry_run_result(&self, _conn: &Connection) -> Result<Self::ReturnValue> {
+        Err(SalesforceError::DryRunNotSupported.into())
+    }
+
+    /// Whether the request may be sent more than once, as when it is retried
+    /// per the Connection's `RetryPolicy` or after the access token is refreshed.
+    /// Requests whose bodies are streamed can be sent only once.
+    fn is_replayable(&self) -> bool {
+        true
+    }
 }
 
 pub trait CompositeFriendlyRequest: SalesforceRequest {}
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// All requests are sent to Salesforce.
+    Live,
+    /// Requests that change data are logged and skipped, and return synthetic results.
+    /// Read requests are sent to Salesforce as usual.
+    DryRun,
+}
+
+pub(crate) const AUTO_ASSIGN_HEADER: &str = "Sforce-Auto-Assign";
+
+/// Controls which assignment rule runs when Cases and Leads are created or updated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AssignmentRule {
+    /// Run the org's active assignment rule.
+    ActiveDefault,
+    /// Run a specific assignment rule.
+    Specific(SalesforceId),
+    /// Do not run assignment rules.
+    Disabled,
+}
+
+impl AssignmentRule {
+    pub(crate) fn get_header_value(&self) -> String {
+        match self {
+            AssignmentRule::ActiveDefault => "TRUE".to_owned(),
+            AssignmentRule::Specific(id) => id.to_string(),
+            AssignmentRule::Disabled => "FALSE".to_owned(),
+        }
+    }
+
+    pub(crate) fn get_header(&self) -> HashMap<String, String> {
+        let mut headers = HashMap::new();
+
+        headers.insert(AUTO_ASSIGN_HEADER.to_owned(), self.get_header_value());
+
+        headers
+    }
+
+    /// The Bulk API 2.0 accepts only a specific assignment rule Id.
+    #[cfg(feature = "bulk")]
+    pub(crate) fn get_bulk_assignment_rule_id(&self) -> Result<Option<SalesforceId>> {
+        match self {
+            AssignmentRule::ActiveDefault => Err(SalesforceError::GeneralError(
+                "The Bulk API requires a specific assignment rule Id".to_owned(),
+            )
+            .into()),
+            AssignmentRule::Specific(id) => Ok(Some(*id)),
+            AssignmentRule::Disabled => Ok(None),
+        }
+    }
+}
+
 pub struct ConnectionBody {
     pub(crate) api_version: String,
-    sobject_types: RwLock<HashMap<String, SObjectType>>,
+    pub(crate) sobject_types: RwLock<HashMap<String, CachedType>>,
+    describe_flights: std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>>,
+    describe_cache_policy: std::sync::RwLock<DescribeCachePolicy>,
+    type_aliases: std::sync::RwLock<HashMap<String, String>>,
     auth: RwLock<Box<dyn Authentication>>,
     auth_refresh: Mutex<()>,
     auth_global_lock: Mutex<()>,
+    mode: std::sync::RwLock<Mode>,
+    assignment_rule: std::sync::RwLock<Option<AssignmentRule>>,
+    dry_run_ids: AtomicUsize,
+    max_retry_wait: std::sync::RwLock<Duration>,
+    retry_policy: std::sync::RwLock<Option<RetryPolicy>>,
+    polling_options: std::sync::RwLock<PollingOptions>,
+    concurrency_controller: std::sync::RwLock<Option<Arc<ConcurrencyController>>>,
+    transport: std::sync::RwLock<Arc<dyn HttpTransport>>,
+    api_usage: std::sync::RwLock<Option<(ApiUsage, tokio::time::Instant)>>,
+    api_throttle: std::sync::RwLock<Option<ApiThrottle>>,
+    pub(crate) retrieve_coalescer: std::sync::RwLock<Option<Arc<RetrieveCoalescer>>>,
+    pub(crate) user_cache: RwLock<UserCache>,
+}
+
+/// The default upper bound on the total time a request waits out `Retry-After`.
+pub const DEFAULT_MAX_RETRY_WAIT: Duration = Duration::from_secs(60);
+
+/// Parse a `Retry-After` header value, given either in seconds or as an HTTP date.
+pub(crate) fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        Some(Duration::from_secs(seconds))
+    } else {
+        let date = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+        Some(
+            (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+                .to_std()
+                .unwrap_or_default(),
+        )
+    }
+}
+
+/// Whether `error` is a 4xx response from Salesforce.
+pub(crate) fn is_client_error(error: &Error) -> bool {
+    matches!(
+        error.downcast_ref::<SalesforceError>(),
+        Some(SalesforceError::ApiErrors(_))
+    ) || error
+        .downcast_ref::<reqwest::Error>()
+        .and_then(|e| e.status())
+        .is_some_and(|s| s.is_client_error())
+}
+
+/// Return an error if `response` is unsuccessful. The errors Salesforce
+/// returns in the body of a 4xx response become `SalesforceError::ApiErrors`;
+/// otherwise, the error is the `reqwest::Error` for the response's status.
+pub(crate) async fn error_for_status(response: Response) -> Result<Response> {
+    let error = match response.error_for_status_ref() {
+        Ok(_) => return Ok(response),
+        Err(error) => error,
+    };
+
+    if response.status().is_client_error() {
+        let body = response.bytes().await?;
+        let errors = serde_json::from_slice::<Vec<ApiError>>(&body).unwrap_or_default();
+        if !errors.is_empty() {
+            return Err(SalesforceError::ApiErrors(errors).into());
+        }
+    }
+
+    Err(error.into())
 }
 
 pub struct Connection(Arc<ConnectionBody>);
@@ -91,12 +287,115 @@ impl Connection {
         Ok(Connection(Arc::new(ConnectionBody {
             api_version: api_version.to_string(),
             sobject_types: RwLock::new(HashMap::new()),
+            describe_flights: std::sync::Mutex::new(HashMap::new()),
+            describe_cache_policy: std::sync::RwLock::new(DescribeCachePolicy::new()),
+            type_aliases: std::sync::RwLock::new(HashMap::new()),
             auth: RwLock::new(auth),
             auth_refresh: Mutex::new(()),
             auth_global_lock: Mutex::new(()),
+            mode: std::sync::RwLock::new(Mode::Live),
+            assignment_rule: std::sync::RwLock::new(None),
+            dry_run_ids: AtomicUsize::new(0),
+            max_retry_wait: std::sync::RwLock::new(DEFAULT_MAX_RETRY_WAIT),
+            retry_policy: std::sync::RwLock::new(None),
+            polling_options: std::sync::RwLock::new(PollingOptions::default()),
+            concurrency_controller: std::sync::RwLock::new(None),
+            transport: std::sync::RwLock::new(Arc::new(ReqwestTransport::new())),
+            api_usage: std::sync::RwLock::new(None),
+            api_throttle: std::sync::RwLock::new(None),
+            retrieve_coalescer: std::sync::RwLock::new(None),
+            user_cache: RwLock::new(UserCache::default()),
         })))
     }
 
+    pub fn set_mode(&self, mode: Mode) {
+        *self.mode.write().unwrap() = mode;
+    }
+
+    pub fn get_mode(&self) -> Mode {
+        *self.mode.read().unwrap()
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.get_mode() == Mode::DryRun
+    }
+
+    /// Set the assignment rule applied to DML performed through this Connection,
+    /// unless a request specifies its own. If `None`, Salesforce's default behavior applies.
+    /// Bulk API jobs cannot run `AssignmentRule::ActiveDefault`, and creating a
+    /// Bulk job under that default fails unless the job names its own rule.
+    pub fn set_assignment_rule(&self, assignment_rule: Option<AssignmentRule>) {
+        *self.assignment_rule.write().unwrap() = assignment_rule;
+    }
+
+    pub fn get_assignment_rule(&self) -> Option<AssignmentRule> {
+        *self.assignment_rule.read().unwrap()
+    }
+
+    /// Set the longest total time a request will wait, honoring `Retry-After`,
+    /// while Salesforce returns 503 Service Unavailable. Beyond this bound, the
+    /// request fails with `SalesforceError::ServiceUnavailable`.
+    pub fn set_max_retry_wait(&self, max_retry_wait: Duration) {
+        *self.max_retry_wait.write().unwrap() = max_retry_wait;
+    }
+
+    pub fn get_max_retry_wait(&self) -> Duration {
+        *self.max_retry_wait.read().unwrap()
+    }
+
+    /// Set the policy for retrying requests that fail transiently. If `None`,
+    /// only 503 responses that carry `Retry-After` are retried.
+    pub fn set_retry_policy(&self, retry_policy: Option<RetryPolicy>) {
+        *self.retry_policy.write().unwrap() = retry_policy;
+    }
+
+    pub fn get_retry_policy(&self) -> Option<RetryPolicy> {
+        self.retry_policy.read().unwrap().clone()
+    }
+
+    /// Set how Bulk API jobs are polled while waiting for them to complete.
+    pub fn set_polling_options(&self, polling_options: PollingOptions) {
+        *self.polling_options.write().unwrap() = polling_options;
+    }
+
+    pub fn get_polling_options(&self) -> PollingOptions {
+        self.polling_options.read().unwrap().clone()
+    }
+
+    /// Adjust the number of requests in flight for DML performed through
+    /// `SObjectStream` without a fixed `parallel` count, per `policy`.
+    /// If `None`, such DML sends one request at a time.
+    pub fn set_adaptive_concurrency(&self, policy: Option<AdaptiveConcurrency>) {
+        *self.concurrency_controller.write().unwrap() =
+            policy.map(|p| Arc::new(ConcurrencyController::new(p)));
+    }
+
+    /// The controller shared by DML performed through this Connection
+    /// under its adaptive concurrency policy, if any.
+    pub fn get_concurrency_controller(&self) -> Option<Arc<ConcurrencyController>> {
+        self.concurrency_controller.read().unwrap().clone()
+    }
+
+    /// Set the transport through which this Connection sends its requests.
+    /// The default is a `ReqwestTransport`.
+    pub fn set_transport(&self, transport: Arc<dyn HttpTransport>) {
+        *self.transport.write().unwrap() = transport;
+    }
+
+    pub fn get_transport(&self) -> Arc<dyn HttpTransport> {
+        self.transport.read().unwrap().clone()
+    }
+
+    /// Generate a placeholder Id for a record or job that was not actually
+    /// created because this Connection is in dry-run mode.
+    /// Placeholder Ids use the key prefix `000`, which no sObject uses.
+    pub(crate) fn get_dry_run_id(&self) -> SalesforceId {
+        let count = self.dry_run_ids.fetch_add(1, Ordering::Relaxed);
+
+        // Cannot fail; the Id is always 15 alphanumeric characters.
+        SalesforceId::new(&format!("000{:012}", count)).unwrap()
+    }
+
     pub async fn get_instance_url(&self) -> Result<Url> {
         if self.get_current_access_token().await.is_none() {
             // We haven't done an initial token refresh yet, so we may not have
@@ -172,29 +471,68 @@ impl Connection {
         Ok(())
     }
 
+    /// The identity and scopes of the current access token, if known.
+    /// Applications can use the granted scopes to enable features.
+    pub async fn get_token_info(&self) -> Option<TokenInfo> {
+        self.auth.read().await.get_token_info().cloned()
+    }
+
+    /// Revoke the Connection's tokens, logging out. Later requests fail
+    /// unless the authentication can log in again without a refresh token.
+    pub async fn revoke(&self) -> Result<()> {
+        self.auth.write().await.revoke().await
+    }
+
+    /// Register `alias` as another name for the sObject `type_name`, such as
+    /// a short name for a namespaced object. Aliases are case-insensitive.
+    pub fn register_alias(&self, alias: &str, type_name: &str) {
+        self.type_aliases
+            .write()
+            .unwrap()
+            .insert(alias.to_lowercase(), type_name.to_owned());
+    }
+
+    /// Resolve any alias for `type_name`.
+    pub fn resolve_type_name(&self, type_name: &str) -> String {
+        self.type_aliases
+            .read()
+            .unwrap()
+            .get(&type_name.to_lowercase())
+            .cloned()
+            .unwrap_or_else(|| type_name.to_owned())
+    }
+
+    /// Get the describe for the sObject `type_name`, which may be an alias.
+    /// Types are cached case-insensitively, according to the Connection's
+    /// `DescribeCachePolicy`, and take their API name from the describe.
     pub async fn get_type(&self, type_name: &str) -> Result<SObjectType> {
-        let mut sobject_types = self.sobject_types.write().await;
-
-        if !sobject_types.contains_key(type_name) {
-            // Pull the Describe information for this sObject
-            let describe: SObjectDescribe = self
-                .execute(&SObjectDescribeRequest::new(type_name))
-                .await?;
-            sobject_types.insert(
-                type_name.to_string(),
-                SObjectType::new(type_name.to_string(), describe),
-            );
-        }
-        let sobject_types = sobject_types.downgrade();
+        self.get_cached_type(&self.resolve_type_name(type_name))
+            .await
+    }
 
-        match sobject_types.get(type_name) {
-            Some(rc) => Ok(rc.clone()), // TODO: Is this correct?
-            None => Err(Error::new(SalesforceError::GeneralError(
-                "sObject Type not found".to_string(),
-            ))),
+    /// Get the describe for `type_name`, able to decode records related to it
+    /// of the types `related`, such as those named in a `TYPEOF` expression.
+    /// Queries retrieve the types of related records themselves; this is for
+    /// decoding records from other sources with `SObject::from_value()`.
+    pub async fn get_type_with_related(
+        &self,
+        type_name: &str,
+        related: &[&str],
+    ) -> Result<SObjectType> {
+        let mut related_types = Vec::with_capacity(related.len());
+        for name in related {
+            related_types.push(self.get_type(name).await?);
         }
+
+        Ok(self
+            .get_type(type_name)
+            .await?
+            .with_related_types(&related_types))
     }
 
+    /// Get a `reqwest::Client` that carries this Connection's access token.
+    /// Requests sent through it bypass the Connection's transport, retries,
+    /// and token refresh.
     pub async fn get_client(&self) -> Result<Client> {
         // TODO: it is more efficient to cache the client for connection pooling.
         let mut headers = header::HeaderMap::new();
@@ -207,70 +545,229 @@ impl Connection {
         Ok(Client::builder().default_headers(headers).build()?)
     }
 
-    async fn build_request<K>(&self, request: &K) -> Result<RequestBuilder>
+    /// Start a request to `url` that carries this Connection's access token.
+    pub(crate) async fn new_request(
+        &self,
+        method: Method,
+        url: &Url,
+    ) -> Result<http::request::Builder> {
+        let access_token = self.get_access_token().await?;
+        let mut builder = http::Request::builder()
+            .method(method)
+            .uri(url.as_str())
+            .header(header::AUTHORIZATION, format!("Bearer {}", access_token));
+
+        // The Bulk API 1.0 takes the access token in its own session header.
+        if url.path().starts_with("/services/async/") {
+            builder = builder.header("X-SFDC-Session", access_token);
+        }
+
+        Ok(builder)
+    }
+
+    /// Send `request` once through this Connection's transport, without
+    /// refreshing the access token or retrying, and record the API usage
+    /// Salesforce reports.
+    pub(crate) async fn send_request(&self, request: TransportRequest) -> Result<Response> {
+        self.throttle().await?;
+
+        let response = Response::from(self.get_transport().send(request).await?);
+        self.record_api_usage(response.headers());
+
+        Ok(response)
+    }
+
+    async fn get_request_url(&self, path: &str, query: Option<Value>) -> Result<Url> {
+        let mut url = self.get_base_url().await?.join(path)?;
+
+        if let Some(params) = query {
+            let params = serde_urlencoded::to_string(&params)?;
+            let query = match url.query() {
+                Some(existing) if !existing.is_empty() => format!("{}&{}", existing, params),
+                _ => params,
+            };
+            url.set_query(Some(&query));
+        }
+
+        Ok(url)
+    }
+
+    async fn build_request<K>(&self, request: &K) -> Result<TransportRequest>
     where
         K: SalesforceRequest,
     {
-        let url = self.get_base_url().await?.join(&request.get_url())?;
+        let method = request.get_method();
+        let url = self
+            .get_request_url(&request.get_url(), request.get_query_parameters())
+            .await?;
+        let mut builder = self.new_request(method.clone(), &url).await?;
+
+        let mut headers = request.get_headers().unwrap_or_default();
+
+        if request.is_mutating() {
+            if let Some(assignment_rule) = self.get_assignment_rule() {
+                headers
+                    .entry(AUTO_ASSIGN_HEADER.to_owned())
+                    .or_insert_with(|| assignment_rule.get_header_value());
+            }
+        }
 
-        let mut builder = self.get_client().await?.request(request.get_method(), url);
+        for (name, value) in headers {
+            builder = builder.header(name, value);
+        }
 
-        let method = request.get_method();
+        let mut body = TransportBody::Empty;
 
         if method == Method::POST || method == Method::PUT || method == Method::PATCH {
-            if let Some(body) = request.get_body() {
-                builder = builder.json(&body);
+            if let Some(json) = request.get_body_for_connection(self) {
+                if !builder
+                    .headers_ref()
+                    .is_some_and(|h| h.contains_key(header::CONTENT_TYPE))
+                {
+                    builder = builder.header(header::CONTENT_TYPE, "application/json");
+                }
+                body = serde_json::to_vec(&json)?.into();
             }
         }
 
-        if let Some(params) = request.get_query_parameters() {
-            builder = builder.query(&params);
-        }
-
-        Ok(builder)
+        Ok(builder.body(body)?)
     }
 
     // The following violates DRY but is challenging to express due to the two-trait structure.
     // TODO: figure out how to do a blanket impl of SalesforceRawRequest for SalesforceRequest
     // without impacting the external-facing API.
 
-    async fn build_raw_request<K>(&self, request: &K) -> Result<RequestBuilder>
+    async fn build_raw_request<K>(&self, request: &K) -> Result<TransportRequest>
     where
         K: SalesforceRawRequest,
     {
-        let url = self.get_base_url().await?.join(&request.get_url())?;
+        let method = request.get_method();
+        let url = self
+            .get_request_url(&request.get_url(), request.get_query_parameters())
+            .await?;
+        let mut builder = self
+            .new_request(method.clone(), &url)
+            .await?
+            .header(header::CONTENT_TYPE, request.get_mime_type());
 
-        let mut builder = self.get_client().await?.request(request.get_method(), url);
+        for (name, value) in request.get_headers().unwrap_or_default() {
+            builder = builder.header(name, value);
+        }
 
-        let method = request.get_method();
+        let mut body = TransportBody::Empty;
 
         if method == Method::POST || method == Method::PUT || method == Method::PATCH {
-            if let Some(body) = request.get_body() {
-                builder = builder.body(body);
+            if let Some(request_body) = request.get_body() {
+                body = request_body;
             }
         }
 
-        builder = builder.header(reqwest::header::CONTENT_TYPE, request.get_mime_type());
+        Ok(builder.body(body)?)
+    }
 
-        if let Some(params) = request.get_query_parameters() {
-            builder = builder.query(&params);
-        }
+    // Requests that are not `replayable` are built and sent once,
+    // without retries or a token refresh.
+    async fn send<F, Fut>(&self, build: F, mutating: bool, replayable: bool) -> Result<Response>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<TransportRequest>>,
+    {
+        let retry_policy = if replayable {
+            self.get_retry_policy()
+        } else {
+            None
+        };
+        let mut attempt = 1;
+        let mut refreshed = false;
+        let mut waited = Duration::ZERO;
+
+        loop {
+            let result = match self.send_request(build().await?).await {
+                Ok(result) => result,
+                Err(e) => match &retry_policy {
+                    Some(policy)
+                        if attempt < policy.get_max_attempts()
+                            && policy.should_retry_error(&e, mutating) =>
+                    {
+                        let backoff = policy.get_backoff(attempt);
+                        info!("Request failed: retrying after {:?}: {}", backoff, e);
+                        sleep(backoff).await;
+                        attempt += 1;
+                        continue;
+                    }
+                    _ => return Err(e),
+                },
+            };
+            let status = result.status();
+
+            // If the token is expired, refresh it and try again.
+            if status == StatusCode::UNAUTHORIZED && !refreshed && replayable {
+                self.refresh_access_token().await?;
+                refreshed = true;
+                continue;
+            }
 
-        Ok(builder)
+            let retry_after = result
+                .headers()
+                .get(header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(parse_retry_after);
+            let delay = match &retry_policy {
+                Some(policy) if policy.should_retry_status(status, mutating) => {
+                    if attempt < policy.get_max_attempts() {
+                        attempt += 1;
+                        Some(retry_after.unwrap_or_else(|| policy.get_backoff(attempt - 1)))
+                    } else {
+                        None
+                    }
+                }
+                // If the org is in maintenance, wait as long as Salesforce requests,
+                // even for a request that the retry policy would not retry.
+                _ if status == StatusCode::SERVICE_UNAVAILABLE && replayable => retry_after,
+                _ => return Ok(result),
+            };
+
+            // Waits requested by Salesforce are bounded by our configured maximum.
+            match delay {
+                Some(delay)
+                    if retry_after.is_none() || waited + delay <= self.get_max_retry_wait() =>
+                {
+                    info!("Received {}: retrying after {:?}", status, delay);
+                    sleep(delay).await;
+                    if retry_after.is_some() {
+                        waited += delay;
+                    }
+                }
+                _ if status == StatusCode::SERVICE_UNAVAILABLE => {
+                    return Err(SalesforceError::ServiceUnavailable { retry_after }.into())
+                }
+                _ => return Ok(result),
+            }
+        }
     }
 
-    pub(crate) async fn execute_raw_request<K, T>(&self, request: &K) -> Result<T>
+    pub async fn execute_raw_request<K, T>(&self, request: &K) -> Result<T>
     where
         K: SalesforceRawRequest<ReturnValue = T>,
     {
-        let mut result = self.build_raw_request(request).await?.send().await?;
-
-        // If the token is expired, refresh it and try again.
-        if result.status().as_u16() == 401 {
-            self.refresh_access_token().await?;
-            result = self.build_raw_request(request).await?.send().await?
+        if request.is_mutating() && self.is_dry_run() {
+            info!(
+                "Dry run: skipping {} {}",
+                request.get_method(),
+                request.get_url()
+            );
+            return request.get_dry_run_result(self);
         }
-        result = result.error_for_status()?;
+
+        let result = error_for_status(
+            self.send(
+                || self.build_raw_request(request),
+                request.is_mutating(),
+                request.is_replayable(),
+            )
+            .await?,
+        )
+        .await?;
 
         request.get_result(self, result).await
     }
@@ -279,21 +776,55 @@ impl Connection {
     where
         K: SalesforceRequest<ReturnValue = T>,
     {
-        let mut result = self.build_request(request).await?.send().await?;
-
-        // If the token is expired, refresh it and try again.
-        if result.status().as_u16() == 401 {
-            self.refresh_access_token().await?;
-            result = self.build_request(request).await?.send().await?
+        if request.is_mutating() && self.is_dry_run() {
+            info!(
+                "Dry run: skipping {} {}",
+                request.get_method(),
+                request.get_url()
+            );
+            return request.get_dry_run_result(self);
         }
 
-        // TODO: we don't consume any error details returned in the case of a 400.
-        result = result.error_for_status()?;
+        let result = self
+            .send(|| self.build_request(request), request.is_mutating(), true)
+            .await?;
+        let result = if request.accepts_error_status(result.status()) {
+            result
+        } else {
+            error_for_status(result).await?
+        };
 
-        if result.status() == StatusCode::NO_CONTENT {
+        if result.status() == StatusCode::NO_CONTENT || result.status() == StatusCode::NOT_MODIFIED
+        {
             Ok(request.get_result(self, None)?)
         } else {
             Ok(request.get_result(self, Some(&result.json().await?))?)
         }
     }
+
+    /// Execute each of `requests`, with at most `concurrency` in flight at once.
+    /// Results are yielded in the order of `requests`.
+    pub fn execute_all<I, K, T>(
+        &self,
+        requests: I,
+        concurrency: usize,
+    ) -> Pin<Box<dyn Stream<Item = Result<T>> + Send>>
+    where
+        I: IntoIterator<Item = K>,
+        I::IntoIter: Send + 'static,
+        K: SalesforceRequest<ReturnValue = T> + Send + Sync + 'static,
+        T: Send + 'static,
+    {
+        let conn = self.clone();
+
+        Box::pin(
+            stream::iter(requests)
+                .map(move |request| {
+                    let conn = conn.clone();
+                    spawn(async move { conn.execute(&request).await })
+                })
+                .buffered(concurrency.max(1))
+                .map(|result| result?),
+        )
+    }
 }
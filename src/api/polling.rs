@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+#[cfg(feature = "bulk")]
+use anyhow::Result;
+#[cfg(feature = "bulk")]
+use tokio::time::Instant;
+
+#[cfg(feature = "bulk")]
+use crate::errors::SalesforceError;
+
+/// The default interval between polls of a long-running job.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How often to check the status of a long-running job, such as a Bulk API
+/// job, and how long to wait for it before giving up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PollingOptions {
+    interval: Duration,
+    backoff: f64,
+    max_interval: Duration,
+    timeout: Option<Duration>,
+}
+
+impl Default for PollingOptions {
+    fn default() -> Self {
+        PollingOptions::new(DEFAULT_POLL_INTERVAL)
+    }
+}
+
+impl PollingOptions {
+    /// Poll every `interval`, with no backoff and no timeout.
+    pub fn new(interval: Duration) -> PollingOptions {
+        PollingOptions {
+            interval,
+            backoff: 1.0,
+            max_interval: interval,
+            timeout: None,
+        }
+    }
+
+    /// Multiply the interval by `backoff` after each poll, up to `max_interval`.
+    /// Factors below 1.0 are treated as 1.0.
+    #[must_use]
+    pub fn with_backoff(mut self, backoff: f64, max_interval: Duration) -> Self {
+        self.backoff = backoff.max(1.0);
+        self.max_interval = max_interval.max(self.interval);
+        self
+    }
+
+    /// Stop waiting with `SalesforceError::Timeout` once `timeout` has elapsed.
+    /// The job itself is not stopped.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn get_interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn get_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// The wait after poll number `poll`, counting from 1.
+    #[cfg(feature = "bulk")]
+    pub(crate) fn get_delay(&self, poll: u32) -> Duration {
+        let exponent = poll.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let seconds = self.interval.as_secs_f64() * self.backoff.powi(exponent);
+
+        if seconds < self.max_interval.as_secs_f64() {
+            Duration::from_secs_f64(seconds)
+        } else {
+            self.max_interval
+        }
+    }
+}
+
+/// Tracks the polls of one job against its `PollingOptions`.
+#[cfg(feature = "bulk")]
+pub(crate) struct Poller {
+    options: PollingOptions,
+    deadline: Option<Instant>,
+    polls: u32,
+}
+
+#[cfg(feature = "bulk")]
+impl Poller {
+    pub(crate) fn new(options: PollingOptions) -> Poller {
+        Poller {
+            deadline: options.get_timeout().map(|t| Instant::now() + t),
+            options,
+            polls: 0,
+        }
+    }
+
+    /// How long to wait before the next poll, or `SalesforceError::Timeout`
+    /// if the timeout has elapsed.
+    pub(crate) fn next_delay(&mut self) -> Result<Duration> {
+        self.polls += 1;
+        let delay = self.options.get_delay(self.polls);
+
+        match self.deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    Err(
+                        SalesforceError::Timeout(self.options.get_timeout().unwrap_or_default())
+                            .into(),
+                    )
+                } else {
+                    Ok(delay.min(remaining))
+                }
+            }
+            None => Ok(delay),
+        }
+    }
+}
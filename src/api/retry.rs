@@ -0,0 +1,212 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+
+use crate::errors::SalesforceError;
+
+/// Controls how a `Connection` retries requests that fail transiently: on
+/// 429 Too Many Requests, 5xx responses, and dropped connections.
+///
+/// Retries wait with exponential backoff, or as long as Salesforce asks
+/// with `Retry-After`, up to the Connection's maximum retry wait.
+///
+/// By default, requests that change data are retried only when Salesforce
+/// cannot have processed them: on 429 responses and failures to connect.
+/// A 5xx response or dropped connection may follow a change that was
+/// applied, so retrying it could apply the change twice.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+    retry_mutating: bool,
+}
+
+impl RetryPolicy {
+    /// A policy that makes up to `max_attempts` attempts per request,
+    /// including the first.
+    pub fn new(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            jitter: true,
+            retry_mutating: false,
+        }
+    }
+
+    /// Wait `initial_backoff` before the first retry, doubling the wait for
+    /// each further retry.
+    #[must_use]
+    pub fn with_initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    #[must_use]
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Randomize each backoff between half and all of its length, so that
+    /// clients that fail together do not retry together.
+    #[must_use]
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Retry requests that change data on any transient failure.
+    #[must_use]
+    pub fn with_retry_mutating(mut self, retry_mutating: bool) -> Self {
+        self.retry_mutating = retry_mutating;
+        self
+    }
+
+    pub fn get_max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// The backoff before retry number `retry`, counting from 1.
+    pub(crate) fn get_backoff(&self, retry: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_backoff);
+
+        if self.jitter {
+            let fraction = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+            backoff.mul_f64(0.5 + fraction / 2.0)
+        } else {
+            backoff
+        }
+    }
+
+    pub(crate) fn should_retry_status(&self, status: StatusCode, mutating: bool) -> bool {
+        status == StatusCode::TOO_MANY_REQUESTS
+            || (status.is_server_error() && (!mutating || self.retry_mutating))
+    }
+
+    pub(crate) fn should_retry_error(&self, error: &anyhow::Error, mutating: bool) -> bool {
+        if is_connect_error(error) {
+            return true;
+        }
+        if mutating && !self.retry_mutating {
+            return false;
+        }
+
+        is_timeout(error) || is_connection_dropped(error)
+    }
+}
+
+/// Whether `error` reports that a query or Bulk result locator has expired
+/// or is otherwise no longer valid. Retrying such a request cannot succeed.
+pub(crate) fn is_locator_expired(error: &anyhow::Error) -> bool {
+    match error.downcast_ref::<SalesforceError>() {
+        Some(SalesforceError::LocatorExpired { .. }) => true,
+        Some(SalesforceError::ApiErrors(errors)) => errors.iter().any(|e| {
+            e.get_error_code().is_some_and(|code| {
+                matches!(code.as_str(), "INVALID_QUERY_LOCATOR" | "INVALID_LOCATOR")
+            })
+        }),
+        _ => false,
+    }
+}
+
+/// Whether `error` reflects lost connectivity, an unavailable org, or exhausted
+/// limits, rather than a problem with the request itself.
+pub(crate) fn is_transient_error(error: &anyhow::Error) -> bool {
+    if is_connect_error(error) || is_timeout(error) || is_connection_dropped(error) {
+        return true;
+    }
+    if let Some(status) = error
+        .downcast_ref::<reqwest::Error>()
+        .and_then(|e| e.status())
+    {
+        return status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+    }
+
+    match error.downcast_ref::<SalesforceError>() {
+        Some(SalesforceError::ServiceUnavailable { .. })
+        | Some(SalesforceError::ApiUsageExceeded(_)) => true,
+        Some(SalesforceError::ApiErrors(errors)) => errors.iter().any(|e| {
+            e.get_error_code().is_some_and(|code| {
+                matches!(
+                    code.as_str(),
+                    "REQUEST_LIMIT_EXCEEDED" | "SERVER_UNAVAILABLE" | "UNABLE_TO_LOCK_ROW"
+                )
+            })
+        }),
+        _ => false,
+    }
+}
+
+/// Whether `error` shows that a request was never processed by Salesforce:
+/// it could not connect, or the request was refused for exhausted limits.
+/// Other transient failures, such as a server error or a dropped connection,
+/// leave open whether the request took effect.
+#[cfg(feature = "outbox")]
+pub(crate) fn is_unsent_error(error: &anyhow::Error) -> bool {
+    if is_connect_error(error) {
+        return true;
+    }
+    if let Some(status) = error
+        .downcast_ref::<reqwest::Error>()
+        .and_then(|e| e.status())
+    {
+        return status == StatusCode::TOO_MANY_REQUESTS;
+    }
+
+    match error.downcast_ref::<SalesforceError>() {
+        Some(SalesforceError::ApiUsageExceeded(_)) => true,
+        Some(SalesforceError::ApiErrors(errors)) => errors.iter().any(|e| {
+            e.get_error_code()
+                .is_some_and(|code| code == "REQUEST_LIMIT_EXCEEDED")
+        }),
+        _ => false,
+    }
+}
+
+fn get_io_error_kind(error: &anyhow::Error) -> Option<ErrorKind> {
+    error
+        .chain()
+        .find_map(|e| e.downcast_ref::<std::io::Error>())
+        .map(|e| e.kind())
+}
+
+fn is_connect_error(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_connect())
+        || get_io_error_kind(error) == Some(ErrorKind::ConnectionRefused)
+}
+
+fn is_timeout(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_timeout())
+        || get_io_error_kind(error) == Some(ErrorKind::TimedOut)
+}
+
+fn is_connection_dropped(error: &anyhow::Error) -> bool {
+    if let Some(kind) = get_io_error_kind(error) {
+        return matches!(
+            kind,
+            ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::BrokenPipe
+                | ErrorKind::UnexpectedEof
+        );
+    }
+
+    // hyper reports a connection closed before a response as its own error.
+    error.chain().any(|e| {
+        e.to_string()
+            .contains("connection closed before message completed")
+    })
+}
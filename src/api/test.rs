@@ -1 +1,717 @@
+use anyhow::Result;
 
+use std::collections::VecDeque;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::StreamExt;
+
+use super::{
+    is_client_error, parse_retry_after, AssignmentRule, Mode, SalesforceRequest,
+    AUTO_ASSIGN_HEADER, DEFAULT_MAX_RETRY_WAIT,
+};
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use super::concurrency::{AdaptiveConcurrency, ConcurrencyController};
+use super::describe_cache::{CachedType, DescribeCachePolicy};
+use super::erased::JsonRequest;
+use super::limits::{ApiThrottle, ApiUsage};
+#[cfg(feature = "bulk")]
+use super::polling::PollingOptions;
+use super::retry::RetryPolicy;
+use super::transport::TransportResponse;
+use crate::errors::SalesforceError;
+use crate::prelude::*;
+use crate::rest::query::QueryRequest;
+use crate::test_integration_base::{
+    get_offline_connection, get_test_field_describe, get_test_sobject_describe,
+    get_test_sobject_type, json_response, serve_responses, serve_responses_with_headers,
+    serve_routes, Account, RecordedRequest, RouteTransport,
+};
+
+#[tokio::test]
+async fn test_dry_run_skips_mutating_requests() -> Result<()> {
+    let conn = get_offline_connection()?;
+    conn.set_mode(Mode::DryRun);
+
+    let mut account = Account {
+        id: None,
+        name: "Dry Run".to_owned(),
+    };
+
+    account.create(&conn).await?;
+    assert!(account.id.is_some());
+
+    account.update(&conn).await?;
+    account.delete(&conn).await?;
+    assert!(account.id.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dry_run_sends_reads() -> Result<()> {
+    let conn = get_offline_connection()?;
+    conn.set_mode(Mode::DryRun);
+
+    assert!(conn
+        .execute(&QueryRequest::new("SELECT Id FROM Account", false))
+        .await
+        .is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_live_mode_sends_mutating_requests() -> Result<()> {
+    let conn = get_offline_connection()?;
+
+    assert_eq!(conn.get_mode(), Mode::Live);
+
+    let mut account = Account {
+        id: None,
+        name: "Live".to_owned(),
+    };
+
+    assert!(account.create(&conn).await.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_assignment_rule_values() -> Result<()> {
+    let id = SalesforceId::new("01Q36000000RXX5")?;
+
+    assert_eq!(AssignmentRule::ActiveDefault.get_header_value(), "TRUE");
+    assert_eq!(AssignmentRule::Disabled.get_header_value(), "FALSE");
+    assert_eq!(
+        AssignmentRule::Specific(id).get_header_value(),
+        "01Q36000000RXX5EAO"
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "bulk")]
+#[test]
+fn test_bulk_assignment_rule_ids() -> Result<()> {
+    let id = SalesforceId::new("01Q36000000RXX5")?;
+
+    assert!(AssignmentRule::ActiveDefault
+        .get_bulk_assignment_rule_id()
+        .is_err());
+    assert_eq!(
+        AssignmentRule::Disabled.get_bulk_assignment_rule_id()?,
+        None
+    );
+    assert_eq!(
+        AssignmentRule::Specific(id).get_bulk_assignment_rule_id()?,
+        Some(id)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_assignment_rule_request_headers() -> Result<()> {
+    let conn = get_offline_connection()?;
+    let account = Account {
+        id: None,
+        name: "Assignment".to_owned(),
+    };
+
+    assert!(account.create_request()?.get_headers().is_none());
+    assert_eq!(
+        account
+            .create_request()?
+            .with_assignment_rule(AssignmentRule::Disabled)
+            .get_headers()
+            .unwrap()[AUTO_ASSIGN_HEADER],
+        "FALSE"
+    );
+
+    assert_eq!(conn.get_assignment_rule(), None);
+    conn.set_assignment_rule(Some(AssignmentRule::ActiveDefault));
+    assert_eq!(
+        conn.get_assignment_rule(),
+        Some(AssignmentRule::ActiveDefault)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_retry_after() {
+    assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+    assert_eq!(
+        parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+        Some(Duration::ZERO)
+    );
+    assert_eq!(parse_retry_after("soon"), None);
+}
+
+#[test]
+fn test_max_retry_wait() -> Result<()> {
+    let conn = get_offline_connection()?;
+
+    assert_eq!(conn.get_max_retry_wait(), DEFAULT_MAX_RETRY_WAIT);
+    conn.set_max_retry_wait(Duration::from_secs(5));
+    assert_eq!(conn.get_max_retry_wait(), Duration::from_secs(5));
+
+    Ok(())
+}
+
+fn is_service_unavailable(error: &anyhow::Error, expected: Option<Duration>) -> bool {
+    matches!(
+        error.downcast_ref::<SalesforceError>(),
+        Some(SalesforceError::ServiceUnavailable { retry_after }) if *retry_after == expected
+    )
+}
+
+#[tokio::test]
+async fn test_retry_after() -> Result<()> {
+    let request = JsonRequest::new(Method::GET, "sobjects/");
+
+    // Without a retry policy, the request is sent again after the requested wait.
+    let (conn, count) = serve_responses_with_headers(vec![
+        ("503 Service Unavailable", vec![("Retry-After", "1")], ""),
+        ("200 OK", vec![], "{}"),
+    ])
+    .await?;
+    let start = std::time::Instant::now();
+    conn.execute(&request).await?;
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+
+    // Waits beyond the maximum are not taken.
+    let (conn, count) = serve_responses_with_headers(vec![
+        ("503 Service Unavailable", vec![("Retry-After", "1")], ""),
+        ("503 Service Unavailable", vec![("Retry-After", "1")], ""),
+        ("200 OK", vec![], "{}"),
+    ])
+    .await?;
+    conn.set_max_retry_wait(Duration::from_secs(1));
+    let error = conn.execute(&request).await.unwrap_err();
+    assert!(is_service_unavailable(&error, Some(Duration::from_secs(1))));
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+
+    // Nor is a 503 that does not say when to retry.
+    let (conn, count) = serve_responses(vec![("503 Service Unavailable", "")]).await?;
+    let error = conn.execute(&request).await.unwrap_err();
+    assert!(is_service_unavailable(&error, None));
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    // A retry policy bounds the number of attempts.
+    let (conn, count) = serve_responses_with_headers(vec![
+        ("503 Service Unavailable", vec![("Retry-After", "0")], ""),
+        ("503 Service Unavailable", vec![("Retry-After", "0")], ""),
+        ("200 OK", vec![], "{}"),
+    ])
+    .await?;
+    conn.set_retry_policy(Some(RetryPolicy::new(2)));
+    let error = conn.execute(&request).await.unwrap_err();
+    assert!(is_service_unavailable(&error, Some(Duration::ZERO)));
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+
+    // A mutating request that the policy declines to retry still waits out maintenance.
+    let (conn, count) = serve_responses_with_headers(vec![
+        ("503 Service Unavailable", vec![("Retry-After", "1")], ""),
+        ("200 OK", vec![], "{}"),
+    ])
+    .await?;
+    conn.set_retry_policy(Some(RetryPolicy::new(3)));
+    let start = std::time::Instant::now();
+    conn.execute(&JsonRequest::new(Method::POST, "sobjects/Account").with_body(json!({})))
+        .await?;
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+
+    // And reports it, rather than returning the raw response, if it cannot.
+    let (conn, count) = serve_responses(vec![("503 Service Unavailable", "")]).await?;
+    conn.set_retry_policy(Some(RetryPolicy::new(3)));
+    let error = conn
+        .execute(&JsonRequest::new(Method::POST, "sobjects/Account").with_body(json!({})))
+        .await
+        .unwrap_err();
+    assert!(is_service_unavailable(&error, None));
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_type_case_insensitive_and_aliases() -> Result<()> {
+    let conn = get_offline_connection()?;
+    let account_type = get_test_sobject_type("ns__Account__c", vec![], vec![])?;
+
+    // Seed the cache, since the offline Connection cannot describe.
+    conn.sobject_types.write().await.insert(
+        "ns__account__c".to_owned(),
+        CachedType::new(account_type.clone()),
+    );
+
+    assert_eq!(conn.get_type("NS__ACCOUNT__C").await?, account_type);
+    assert!(conn.get_type("Acc").await.is_err());
+
+    conn.register_alias("Acc", "ns__Account__c");
+    assert_eq!(conn.resolve_type_name("acc"), "ns__Account__c");
+    assert_eq!(conn.resolve_type_name("Contact"), "Contact");
+    assert_eq!(conn.get_type("ACC").await?.get_api_name(), "ns__Account__c");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_erased_requests() -> Result<()> {
+    use super::erased::ErasedRequest;
+
+    let conn = get_offline_connection()?;
+    conn.set_mode(Mode::DryRun);
+
+    let requests: Vec<Box<dyn ErasedRequest>> = vec![
+        Box::new(QueryRequest::new("SELECT Id FROM Account", false)),
+        Box::new(
+            JsonRequest::new(Method::PATCH, "sobjects/Account/001000000000000AAA")
+                .with_body(json!({"Name": "Test"}))
+                .with_header("Sforce-Auto-Assign", "FALSE"),
+        ),
+    ];
+
+    assert_eq!(requests[0].get_url(), "query");
+    assert_eq!(
+        requests[0].get_query_parameters().unwrap()["q"],
+        "SELECT Id FROM Account"
+    );
+    assert!(!requests[0].is_mutating());
+    assert_eq!(requests[1].get_body().unwrap()["Name"], "Test");
+    assert_eq!(
+        requests[1].get_headers().unwrap()["Sforce-Auto-Assign"],
+        "FALSE"
+    );
+    assert!(requests[1].is_mutating());
+
+    // Erased requests cannot synthesize dry-run results.
+    assert!(conn.execute_erased(requests[1].as_ref()).await.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_retry_policy_backoff() {
+    let policy = RetryPolicy::new(5)
+        .with_initial_backoff(Duration::from_secs(1))
+        .with_max_backoff(Duration::from_secs(5))
+        .with_jitter(false);
+
+    assert_eq!(policy.get_backoff(1), Duration::from_secs(1));
+    assert_eq!(policy.get_backoff(2), Duration::from_secs(2));
+    assert_eq!(policy.get_backoff(3), Duration::from_secs(4));
+    assert_eq!(policy.get_backoff(4), Duration::from_secs(5));
+    assert_eq!(policy.get_backoff(100), Duration::from_secs(5));
+
+    let policy = policy.with_jitter(true);
+    for _ in 0..20 {
+        let backoff = policy.get_backoff(2);
+        assert!(backoff >= Duration::from_secs(1) && backoff <= Duration::from_secs(2));
+    }
+
+    assert_eq!(RetryPolicy::new(0).get_max_attempts(), 1);
+}
+
+#[test]
+#[cfg(feature = "bulk")]
+fn test_polling_options_backoff() {
+    let options = PollingOptions::new(Duration::from_secs(2));
+    assert_eq!(options.get_delay(1), Duration::from_secs(2));
+    assert_eq!(options.get_delay(10), Duration::from_secs(2));
+
+    let options = options.with_backoff(1.5, Duration::from_secs(4));
+    assert_eq!(options.get_delay(1), Duration::from_secs(2));
+    assert_eq!(options.get_delay(2), Duration::from_secs(3));
+    assert_eq!(options.get_delay(3), Duration::from_secs(4));
+    assert_eq!(options.get_delay(u32::MAX), Duration::from_secs(4));
+    assert_eq!(PollingOptions::default().get_timeout(), None);
+}
+
+#[test]
+fn test_retry_policy_statuses() {
+    let policy = RetryPolicy::new(3);
+
+    assert!(policy.should_retry_status(StatusCode::TOO_MANY_REQUESTS, true));
+    assert!(policy.should_retry_status(StatusCode::BAD_GATEWAY, false));
+    assert!(!policy.should_retry_status(StatusCode::BAD_GATEWAY, true));
+    assert!(!policy.should_retry_status(StatusCode::NOT_FOUND, false));
+    assert!(policy
+        .with_retry_mutating(true)
+        .should_retry_status(StatusCode::INTERNAL_SERVER_ERROR, true));
+}
+
+#[tokio::test]
+async fn test_retry_policy_retries_requests() -> Result<()> {
+    let (conn, count) = serve_responses(vec![
+        ("500 Internal Server Error", "{}"),
+        ("429 Too Many Requests", "{}"),
+        ("200 OK", "{}"),
+    ])
+    .await?;
+    conn.set_retry_policy(Some(
+        RetryPolicy::new(3).with_initial_backoff(Duration::from_millis(1)),
+    ));
+
+    conn.execute(&JsonRequest::new(Method::GET, "limits"))
+        .await?;
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+
+    // Attempts are bounded, and mutating requests are not retried on 5xx.
+    let (conn, count) = serve_responses(vec![
+        ("502 Bad Gateway", "{}"),
+        ("502 Bad Gateway", "{}"),
+        ("200 OK", "{}"),
+    ])
+    .await?;
+    conn.set_retry_policy(Some(
+        RetryPolicy::new(2).with_initial_backoff(Duration::from_millis(1)),
+    ));
+
+    assert!(conn
+        .execute(&JsonRequest::new(Method::GET, "limits"))
+        .await
+        .is_err());
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+
+    let (conn, count) = serve_responses(vec![("502 Bad Gateway", "{}"), ("200 OK", "{}")]).await?;
+    conn.set_retry_policy(Some(
+        RetryPolicy::new(2).with_initial_backoff(Duration::from_millis(1)),
+    ));
+
+    assert!(conn
+        .execute(&JsonRequest::new(Method::POST, "sobjects/Account"))
+        .await
+        .is_err());
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_custom_transport() -> Result<()> {
+    let (conn, transport) =
+        serve_routes(RouteTransport::new().with_any_route("", |_| json_response(200, "{}")))?;
+
+    conn.execute(
+        &JsonRequest::new(Method::POST, "sobjects/Account/")
+            .with_body(json!({"Name": "Test"}))
+            .with_query_parameters(json!({"q": "a b"})),
+    )
+    .await?;
+
+    let request = &transport.get_requests()[0];
+    assert_eq!(request.method, Method::POST);
+    assert_eq!(
+        request.url.as_str(),
+        "http://127.0.0.1:9/services/data/v52.0/sobjects/Account/?q=a+b"
+    );
+    assert_eq!(request.get_header("Authorization"), Some("Bearer token"));
+    assert_eq!(request.text(), r#"{"Name":"Test"}"#);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_api_errors() -> Result<()> {
+    let (conn, _) = serve_responses(vec![
+        (
+            "400 Bad Request",
+            r#"[{"message": "duplicate value found", "errorCode": "DUPLICATE_VALUE", "fields": []}]"#,
+        ),
+        ("404 Not Found", "Not Found"),
+    ])
+    .await?;
+    let request = JsonRequest::new(Method::GET, "sobjects/Account/");
+
+    let error = conn.execute(&request).await.unwrap_err();
+    assert!(is_client_error(&error));
+    match error.downcast_ref::<SalesforceError>() {
+        Some(SalesforceError::ApiErrors(errors)) => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(
+                errors[0].get_error_code().map(|c| c.as_str()),
+                Some("DUPLICATE_VALUE")
+            );
+            assert_eq!(errors[0].message, "duplicate value found");
+        }
+        _ => panic!("Expected API errors, got {}", error),
+    }
+
+    // Bodies that are not Salesforce errors leave the HTTP error in place.
+    let error = conn.execute(&request).await.unwrap_err();
+    assert!(is_client_error(&error));
+    assert_eq!(
+        error
+            .downcast_ref::<reqwest::Error>()
+            .and_then(|e| e.status()),
+        Some(StatusCode::NOT_FOUND)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_api_usage() {
+    let usage = ApiUsage::parse("api-usage=18/5000").unwrap();
+    assert_eq!(usage.get_used(), 18);
+    assert_eq!(usage.get_limit(), 5000);
+    assert_eq!(usage.get_remaining(), 4982);
+
+    assert_eq!(
+        ApiUsage::parse("per-app-api-usage=2/250(appName=Test); api-usage=25/5000"),
+        Some(ApiUsage::new(25, 5000))
+    );
+    assert_eq!(ApiUsage::parse("api-usage=lots"), None);
+}
+
+// Reports `usage` in Sforce-Limit-Info, and the next of `remaining`, or the
+// last if only one is left, as the DailyApiRequests limit.
+fn get_limit_info_transport(usage: &'static str, remaining: Vec<i64>) -> RouteTransport {
+    let with_usage = move |response: Result<TransportResponse>| {
+        let mut response = response?;
+        response
+            .headers_mut()
+            .insert("Sforce-Limit-Info", http::HeaderValue::from_static(usage));
+        Ok(response)
+    };
+    let remaining = Mutex::new(VecDeque::from(remaining));
+
+    RouteTransport::new()
+        .with_route(Method::GET, "/limits", move |_| {
+            let mut remaining = remaining.lock().unwrap();
+            let next = if remaining.len() > 1 {
+                remaining.pop_front().unwrap()
+            } else {
+                remaining[0]
+            };
+            with_usage(json_response(
+                200,
+                &json!({"DailyApiRequests": {"Max": 5000, "Remaining": next}}),
+            ))
+        })
+        .with_any_route("", move |_| with_usage(json_response(200, "{}")))
+}
+
+#[tokio::test]
+async fn test_api_usage_throttle() -> Result<()> {
+    let (conn, _) = serve_routes(get_limit_info_transport("api-usage=4990/5000", vec![10]))?;
+    let request = JsonRequest::new(Method::GET, "sobjects/");
+    conn.set_api_throttle(Some(ApiThrottle::new(100)));
+
+    assert_eq!(conn.get_api_usage(), None);
+    conn.execute(&request).await?;
+    assert_eq!(conn.get_api_usage(), Some(ApiUsage::new(4990, 5000)));
+
+    let error = conn.execute(&request).await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<SalesforceError>(),
+        Some(SalesforceError::ApiUsageExceeded(_))
+    ));
+
+    conn.set_api_throttle(Some(ApiThrottle::new(5)));
+    conn.execute(&request).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_api_usage_throttle_recovers() -> Result<()> {
+    // Once the usage report is stale, it is refreshed before a request is rejected.
+    let (conn, transport) =
+        serve_routes(get_limit_info_transport("api-usage=4990/5000", vec![4000]))?;
+    let request = JsonRequest::new(Method::GET, "sobjects/");
+    conn.execute(&request).await?;
+    conn.set_api_throttle(Some(
+        ApiThrottle::new(100).with_refresh_interval(Duration::ZERO),
+    ));
+
+    conn.execute(&request).await?;
+    assert_eq!(transport.count_requests("/limits"), 1);
+
+    // Waiting requests are sent only once usage falls below the headroom.
+    let (conn, transport) = serve_routes(get_limit_info_transport(
+        "api-usage=4990/5000",
+        vec![10, 50, 4000],
+    ))?;
+    conn.execute(&request).await?;
+    conn.set_api_throttle(Some(
+        ApiThrottle::new(100)
+            .with_delay(Duration::from_millis(1))
+            .with_refresh_interval(Duration::ZERO),
+    ));
+
+    conn.execute(&request).await?;
+    assert_eq!(transport.count_requests("/limits"), 3);
+
+    Ok(())
+}
+
+// Answers every request with its own path.
+fn echo_path(request: &RecordedRequest) -> Result<TransportResponse> {
+    json_response(200, &json!({ "path": request.path() }))
+}
+
+#[tokio::test]
+async fn test_execute_all() -> Result<()> {
+    // Finish later requests first.
+    let (conn, _) = serve_routes(
+        RouteTransport::new()
+            .with_any_route("/0", echo_path)
+            .with_delay(Duration::from_millis(20))
+            .with_any_route("", echo_path),
+    )?;
+
+    let results: Vec<Value> = conn
+        .execute_all(
+            (0..5).map(|i| JsonRequest::new(Method::GET, &format!("limits/{}", i))),
+            3,
+        )
+        .collect::<Vec<Result<Value>>>()
+        .await
+        .into_iter()
+        .collect::<Result<_>>()?;
+
+    assert_eq!(
+        results,
+        (0..5)
+            .map(|i| json!({ "path": format!("/services/data/v52.0/limits/{}", i) }))
+            .collect::<Vec<Value>>()
+    );
+
+    Ok(())
+}
+
+fn get_account_describe() -> Result<String> {
+    Ok(get_test_sobject_describe(
+        "Account",
+        vec![get_test_field_describe("Name", "xsd:string", "string")],
+        vec![],
+        vec![],
+    )?
+    .to_string())
+}
+
+#[tokio::test]
+async fn test_describe_cache_ttl() -> Result<()> {
+    let describe = get_account_describe()?;
+    let (conn, count) = serve_responses(vec![
+        ("200 OK", describe.as_str()),
+        ("304 Not Modified", ""),
+        ("200 OK", describe.as_str()),
+    ])
+    .await?;
+
+    // Concurrent requests for the same type share one describe.
+    let (first, second) = tokio::join!(conn.get_type("Account"), conn.get_type("account"));
+    assert_eq!(first?, second?);
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    // By default, describes are cached indefinitely.
+    conn.get_type("Account").await?;
+    conn.get_type("account").await?;
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    // An expired describe is revalidated, and kept if unchanged.
+    conn.set_describe_cache_policy(DescribeCachePolicy::new().with_ttl(Duration::ZERO));
+    assert_eq!(conn.get_type("Account").await?.get_api_name(), "Account");
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+
+    // Refreshing describes unconditionally.
+    conn.set_describe_cache_policy(DescribeCachePolicy::new());
+    conn.refresh_type("Account").await?;
+    conn.get_type("Account").await?;
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_describe_cache_directory() -> Result<()> {
+    let directory =
+        std::env::temp_dir().join(format!("baris-describe-cache-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    let policy = DescribeCachePolicy::new().with_directory(&directory);
+
+    let (conn, count) = serve_responses(vec![("200 OK", get_account_describe()?)]).await?;
+    conn.set_describe_cache_policy(policy.clone());
+    conn.get_type("Account").await?;
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    // A new Connection to the same instance starts from the persisted describe.
+    let (conn, count) = serve_responses(Vec::<(_, &str)>::new()).await?;
+    conn.set_describe_cache_policy(policy.clone());
+    let account_type = conn.get_type("Account").await?;
+    assert_eq!(count.load(Ordering::SeqCst), 0);
+    assert!(account_type.get_describe().get_field("Name").is_some());
+
+    conn.invalidate_type("Account").await?;
+    assert!(
+        std::fs::read_dir(directory.join("127.0.0.1").join("v52.0"))?
+            .next()
+            .is_none()
+    );
+
+    std::fs::remove_dir_all(&directory)?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_adaptive_concurrency_limits() -> Result<()> {
+    let controller = Arc::new(ConcurrencyController::new(
+        AdaptiveConcurrency::new(1, 4).with_window(3),
+    ));
+    let fast = Duration::from_millis(100);
+
+    // Additive increase: about one more request per round of successes.
+    assert_eq!(1, controller.get_limit());
+    controller.record(fast, false);
+    assert_eq!(2, controller.get_limit());
+    controller.record(fast, false);
+    controller.record(fast, false);
+    assert_eq!(2, controller.get_limit());
+    controller.record(fast, false);
+    assert_eq!(3, controller.get_limit());
+
+    // Multiplicative decrease on contention, but never below the minimum.
+    controller.record(fast, true);
+    assert_eq!(1, controller.get_limit());
+    controller.record(fast, true);
+    assert_eq!(1, controller.get_limit());
+
+    // Never above the maximum.
+    for _ in 0..20 {
+        controller.record(fast, false);
+    }
+    assert_eq!(4, controller.get_limit());
+
+    // Back off once the median latency is well above the best observed.
+    let slow = Duration::from_millis(500);
+    controller.record(slow, false);
+    assert_eq!(4, controller.get_limit());
+    controller.record(slow, false);
+    assert_eq!(2, controller.get_limit());
+
+    // Requests beyond the limit wait for a permit to be released.
+    let first = controller.acquire().await;
+    let _second = controller.acquire().await;
+    assert_eq!(2, controller.get_in_flight());
+    assert!(
+        tokio::time::timeout(Duration::from_millis(50), controller.acquire())
+            .await
+            .is_err()
+    );
+    drop(first);
+    let _third = tokio::time::timeout(Duration::from_millis(50), controller.acquire()).await?;
+    assert_eq!(2, controller.get_in_flight());
+
+    Ok(())
+}
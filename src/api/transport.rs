@@ -0,0 +1,140 @@
+use std::pin::Pin;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, StreamExt, TryStreamExt};
+use reqwest::Client;
+
+pub type BodyStream = Pin<Box<dyn Stream<Item = Result<Bytes>> + Send + Sync>>;
+
+/// The body of a request sent, or response received, through an `HttpTransport`.
+pub enum TransportBody {
+    Empty,
+    Bytes(Bytes),
+    Stream(BodyStream),
+}
+
+pub type TransportRequest = http::Request<TransportBody>;
+pub type TransportResponse = http::Response<TransportBody>;
+
+impl TransportBody {
+    pub fn wrap_stream<S>(stream: S) -> TransportBody
+    where
+        S: Stream<Item = Result<Bytes>> + Send + Sync + 'static,
+    {
+        TransportBody::Stream(Box::pin(stream))
+    }
+
+    /// The whole body, if it is not streamed.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            TransportBody::Empty => Some(&[]),
+            TransportBody::Bytes(bytes) => Some(bytes),
+            TransportBody::Stream(_) => None,
+        }
+    }
+
+    /// Read the whole body into memory.
+    pub async fn into_bytes(self) -> Result<Bytes> {
+        match self {
+            TransportBody::Empty => Ok(Bytes::new()),
+            TransportBody::Bytes(bytes) => Ok(bytes),
+            TransportBody::Stream(stream) => Ok(stream
+                .try_fold(BytesMut::new(), |mut buf, chunk| async move {
+                    buf.extend_from_slice(&chunk);
+                    Ok(buf)
+                })
+                .await?
+                .freeze()),
+        }
+    }
+
+    pub fn into_stream(self) -> BodyStream {
+        match self {
+            TransportBody::Empty => Box::pin(futures::stream::empty()),
+            TransportBody::Bytes(bytes) => Box::pin(futures::stream::once(async { Ok(bytes) })),
+            TransportBody::Stream(stream) => stream,
+        }
+    }
+}
+
+impl From<Bytes> for TransportBody {
+    fn from(bytes: Bytes) -> Self {
+        TransportBody::Bytes(bytes)
+    }
+}
+
+impl From<Vec<u8>> for TransportBody {
+    fn from(bytes: Vec<u8>) -> Self {
+        TransportBody::Bytes(bytes.into())
+    }
+}
+
+impl From<String> for TransportBody {
+    fn from(string: String) -> Self {
+        TransportBody::Bytes(string.into())
+    }
+}
+
+impl From<TransportBody> for reqwest::Body {
+    fn from(body: TransportBody) -> Self {
+        match body {
+            TransportBody::Empty => reqwest::Body::from(Bytes::new()),
+            TransportBody::Bytes(bytes) => reqwest::Body::from(bytes),
+            TransportBody::Stream(stream) => reqwest::Body::wrap_stream(stream),
+        }
+    }
+}
+
+/// Sends HTTP requests on behalf of a `Connection`.
+///
+/// Implement this trait to send requests through a client other than the
+/// default `ReqwestTransport`, such as one with a custom connection pool,
+/// proxy, or instrumentation. Requests arrive fully formed, including the
+/// `Authorization` header; the transport only needs to send them.
+/// Errors are retried by the Connection's `RetryPolicy` when they contain a
+/// `reqwest::Error` or `std::io::Error` that indicates a transient failure.
+#[async_trait]
+pub trait HttpTransport: Send + Sync {
+    async fn send(&self, request: TransportRequest) -> Result<TransportResponse>;
+}
+
+/// The default `HttpTransport`, which sends requests through a `reqwest::Client`.
+#[derive(Clone, Default)]
+pub struct ReqwestTransport {
+    client: Client,
+}
+
+impl ReqwestTransport {
+    pub fn new() -> ReqwestTransport {
+        ReqwestTransport::default()
+    }
+
+    /// Send requests through `client`, such as one configured with timeouts
+    /// or a proxy.
+    pub fn with_client(client: Client) -> ReqwestTransport {
+        ReqwestTransport { client }
+    }
+}
+
+#[async_trait]
+impl HttpTransport for ReqwestTransport {
+    async fn send(&self, request: TransportRequest) -> Result<TransportResponse> {
+        let response = self
+            .client
+            .execute(reqwest::Request::try_from(request)?)
+            .await?;
+
+        let mut builder = http::Response::builder()
+            .status(response.status())
+            .version(response.version());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = response.headers().clone();
+        }
+
+        Ok(builder.body(TransportBody::wrap_stream(
+            response.bytes_stream().map(|chunk| Ok(chunk?)),
+        ))?)
+    }
+}
@@ -0,0 +1,182 @@
+//! The OAuth 2.0 device authorization grant, for headless environments.
+//!
+//! A server or container cannot receive a browser redirect. Instead, it shows
+//! the user a code to enter at a verification URL on any other device, and polls
+//! Salesforce until the user approves access.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode, Url};
+use serde_derive::Deserialize;
+use tokio::time::sleep;
+
+use super::{
+    parse_instance_url, Authentication, ConnectedApp, RefreshTokenAuth, TokenInfo, TokenResponse,
+};
+use crate::errors::SalesforceError;
+
+/// The device code and user instructions issued at the start of the flow.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceAuthorization {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    /// The minimum number of seconds between polls.
+    pub interval: u64,
+}
+
+#[derive(Deserialize)]
+struct OAuthError {
+    error: String,
+    error_description: Option<String>,
+}
+
+/// Authentication through the OAuth 2.0 device flow.
+///
+/// `start()` issues a device code. Show the user `get_user_code()` and
+/// `get_verification_uri()`; the first token refresh then polls until they
+/// approve access, after which the refresh token is used as usual.
+/// The Connected App must have the device flow enabled.
+#[derive(Clone)]
+pub struct DeviceFlowAuth {
+    app: ConnectedApp,
+    login_url: Url,
+    authorization: DeviceAuthorization,
+    auth: Option<RefreshTokenAuth>,
+}
+
+impl DeviceFlowAuth {
+    /// Request a device code from `login_url`, such as `https://login.salesforce.com`.
+    pub async fn start(app: &ConnectedApp, login_url: &Url) -> Result<DeviceFlowAuth> {
+        let authorization: DeviceAuthorization = Client::builder()
+            .build()?
+            .post(login_url.join("services/oauth2/token")?)
+            .form(&[
+                ("response_type", "device_code"),
+                ("client_id", app.consumer_key.as_str()),
+                ("scope", "api refresh_token"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(DeviceFlowAuth {
+            app: app.clone(),
+            login_url: login_url.clone(),
+            authorization,
+            auth: None,
+        })
+    }
+
+    pub fn get_authorization(&self) -> &DeviceAuthorization {
+        &self.authorization
+    }
+
+    /// The code the user must enter at the verification URL.
+    pub fn get_user_code(&self) -> &str {
+        &self.authorization.user_code
+    }
+
+    pub fn get_verification_uri(&self) -> &str {
+        &self.authorization.verification_uri
+    }
+
+    /// The refresh token, once the user has approved access.
+    pub fn get_refresh_token(&self) -> Option<&String> {
+        self.auth.as_ref().map(|a| a.get_refresh_token())
+    }
+
+    /// Poll until the user approves access, denies it, or the device code expires.
+    pub async fn wait_for_authorization(&mut self) -> Result<()> {
+        let client = Client::builder().build()?;
+        let url = self.login_url.join("services/oauth2/token")?;
+        let mut interval = Duration::from_secs(self.authorization.interval);
+
+        loop {
+            sleep(interval).await;
+
+            let response = client
+                .post(url.clone())
+                .form(&[
+                    ("grant_type", "device"),
+                    ("client_id", self.app.consumer_key.as_str()),
+                    ("code", self.authorization.device_code.as_str()),
+                ])
+                .send()
+                .await?;
+
+            if response.status() == StatusCode::BAD_REQUEST {
+                match response.json::<OAuthError>().await? {
+                    OAuthError { error, .. } if error == "authorization_pending" => continue,
+                    OAuthError { error, .. } if error == "slow_down" => {
+                        interval += Duration::from_secs(5);
+                        continue;
+                    }
+                    OAuthError {
+                        error,
+                        error_description,
+                    } => {
+                        return Err(SalesforceError::GeneralError(format!(
+                            "Device authorization failed: {} {}",
+                            error,
+                            error_description.unwrap_or_default()
+                        ))
+                        .into())
+                    }
+                }
+            }
+
+            let result: TokenResponse = response.error_for_status()?.json().await?;
+            let token_info = TokenInfo::from_response(&result);
+            let refresh_token = result.refresh_token.ok_or(SalesforceError::CannotRefresh)?;
+
+            self.auth = Some(RefreshTokenAuth {
+                refresh_token,
+                instance_url: parse_instance_url(&result.instance_url, &self.login_url)?,
+                token_info: Some(token_info),
+                access_token: Some(result.access_token),
+                app: self.app.clone(),
+                login_url: None,
+            });
+
+            return Ok(());
+        }
+    }
+}
+
+#[async_trait]
+impl Authentication for DeviceFlowAuth {
+    async fn refresh_access_token(&mut self) -> Result<()> {
+        match &mut self.auth {
+            Some(auth) => auth.refresh_access_token().await,
+            None => self.wait_for_authorization().await,
+        }
+    }
+
+    async fn get_instance_url(&self) -> Result<&Url> {
+        match &self.auth {
+            Some(auth) => auth.get_instance_url().await,
+            // We may not yet be authenticated.
+            None => Err(SalesforceError::NotAuthenticated.into()),
+        }
+    }
+
+    fn get_access_token(&self) -> Option<&String> {
+        self.auth.as_ref().and_then(|a| a.get_access_token())
+    }
+
+    fn get_token_info(&self) -> Option<&TokenInfo> {
+        self.auth.as_ref().and_then(|a| a.get_token_info())
+    }
+
+    async fn revoke(&mut self) -> Result<()> {
+        match &mut self.auth {
+            Some(auth) => auth.revoke().await,
+            None => Err(SalesforceError::NotAuthenticated.into()),
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//! Interactive authorization of a new org for desktop and command-line tools.
+//!
+//! `interactive_login()` runs the OAuth 2.0 web server flow end to end: it listens
+//! on the Connected App's `localhost` redirect URL, opens the user's browser to the
+//! authorization page, and exchanges the returned code for a `Connection`.
+//!
+//! `WebFlowAuth` runs the authorization code flow with PKCE, for tools that
+//! cannot keep a client secret, and produces a `RefreshTokenAuth`.
+
+use std::process::Command;
+
+use anyhow::Result;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use reqwest::{Client, Url};
+use ring::digest::{digest, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+use super::{
+    parse_instance_url, ConnectedApp, RefreshTokenAuth, TokenInfo, TokenResponse, WebServerAuth,
+};
+use crate::{api::Connection, errors::SalesforceError};
+
+const SUCCESS_BODY: &str =
+    "<html><body>Authorization complete. You may close this window.</body></html>";
+const FAILURE_BODY: &str =
+    "<html><body>Authorization failed. Return to the application for details.</body></html>";
+const NOT_FOUND_BODY: &str = "<html><body>Not found.</body></html>";
+
+/// Authorize a new org in the user's browser and return a `Connection` to it.
+///
+/// The Connected App's redirect URL must be an `http://localhost` URL with an
+/// explicit port, such as `http://localhost:1717/OauthRedirect`.
+pub async fn interactive_login(
+    app: &ConnectedApp,
+    login_url: &Url,
+    api_version: &str,
+) -> Result<Connection> {
+    let redirect_url = get_redirect_url(app)?;
+    let port = get_listener_port(redirect_url)?;
+    let state = get_random_token()?;
+
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    open_browser(&WebServerAuth::get_authorize_url(app, login_url, &state)?)?;
+    let code = receive_redirect(&listener, redirect_url.path(), &state).await?;
+    let auth = WebServerAuth::from_code(app, login_url, &code).await?;
+
+    Connection::new(Box::new(auth), api_version)
+}
+
+/// The OAuth 2.0 authorization code flow with PKCE (Proof Key for Code Exchange).
+///
+/// PKCE protects the authorization code without a client secret, so this flow
+/// suits command-line and desktop tools whose Connected App secret cannot be kept
+/// private. Either call `authorize()` to run the flow end to end through the
+/// Connected App's `localhost` redirect URL, or send the user to
+/// `get_authorize_url()` and pass the code delivered to your own callback to
+/// `exchange_code()`.
+pub struct WebFlowAuth {
+    app: ConnectedApp,
+    login_url: Url,
+    code_verifier: String,
+    state: String,
+}
+
+impl WebFlowAuth {
+    pub fn new(app: &ConnectedApp, login_url: &Url) -> Result<WebFlowAuth> {
+        get_redirect_url(app)?;
+
+        Ok(WebFlowAuth {
+            app: app.clone(),
+            login_url: login_url.clone(),
+            code_verifier: get_random_token()?,
+            state: get_random_token()?,
+        })
+    }
+
+    /// The value of `state` that the redirect must return.
+    pub fn get_state(&self) -> &str {
+        &self.state
+    }
+
+    fn get_code_challenge(&self) -> String {
+        URL_SAFE_NO_PAD.encode(digest(&SHA256, self.code_verifier.as_bytes()))
+    }
+
+    /// The URL to which the user should be sent to authorize the Connected App.
+    pub fn get_authorize_url(&self) -> Result<Url> {
+        let mut url = WebServerAuth::get_authorize_url(&self.app, &self.login_url, &self.state)?;
+        url.query_pairs_mut()
+            .append_pair("code_challenge", &self.get_code_challenge())
+            .append_pair("code_challenge_method", "S256");
+
+        Ok(url)
+    }
+
+    /// Exchange an authorization code delivered to the redirect URL for tokens.
+    /// The Connected App's scopes must include `refresh_token`.
+    pub async fn exchange_code(&self, code: &str) -> Result<RefreshTokenAuth> {
+        let redirect_url = get_redirect_url(&self.app)?;
+        let mut form = vec![
+            ("client_id", self.app.consumer_key.as_str()),
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_url.as_str()),
+            ("code_verifier", self.code_verifier.as_str()),
+        ];
+        if !self.app.client_secret.is_empty() {
+            form.push(("client_secret", self.app.client_secret.as_str()));
+        }
+
+        let result: TokenResponse = Client::builder()
+            .build()?
+            .post(self.login_url.join("services/oauth2/token")?)
+            .form(&form)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let token_info = TokenInfo::from_response(&result);
+        let refresh_token = result.refresh_token.ok_or_else(|| {
+            SalesforceError::GeneralError(
+                "No refresh token was granted. Add the refresh_token scope to the Connected App."
+                    .to_owned(),
+            )
+        })?;
+
+        Ok(RefreshTokenAuth {
+            refresh_token,
+            instance_url: parse_instance_url(&result.instance_url, &self.login_url)?,
+            token_info: Some(token_info),
+            access_token: Some(result.access_token),
+            app: self.app.clone(),
+            login_url: None,
+        })
+    }
+
+    /// Open the user's browser to authorize the Connected App, receive the code
+    /// on its `localhost` redirect URL, and exchange it for tokens.
+    pub async fn authorize(&self) -> Result<RefreshTokenAuth> {
+        let redirect_url = get_redirect_url(&self.app)?;
+        let port = get_listener_port(redirect_url)?;
+
+        let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+        open_browser(&self.get_authorize_url()?)?;
+        let code = receive_redirect(&listener, redirect_url.path(), &self.state).await?;
+
+        self.exchange_code(&code).await
+    }
+}
+
+fn get_redirect_url(app: &ConnectedApp) -> Result<&Url> {
+    app.redirect_url.as_ref().ok_or_else(|| {
+        SalesforceError::GeneralError(
+            "Interactive login requires a Connected App redirect URL".to_owned(),
+        )
+        .into()
+    })
+}
+
+/// Accept connections on `listener` until the browser requests `redirect_path`,
+/// and return the authorization code from that request. Other requests, such as
+/// for `/favicon.ico`, are answered with 404. Connections are read concurrently,
+/// so that one the browser opens speculatively and leaves idle blocks no other.
+pub(super) async fn receive_redirect(
+    listener: &TcpListener,
+    redirect_path: &str,
+    state: &str,
+) -> Result<String> {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    let mut request_line = String::new();
+                    if stream.read_line(&mut request_line).await.is_ok() {
+                        let _ = sender.send((stream.into_inner(), request_line));
+                    }
+                });
+            }
+            received = receiver.recv() => {
+                // The channel stays open, as this function holds a sender.
+                let (mut stream, request_line) = match received {
+                    Some(received) => received,
+                    None => continue,
+                };
+                if get_request_path(&request_line) != Some(redirect_path) {
+                    respond(&mut stream, "404 Not Found", NOT_FOUND_BODY).await;
+                    continue;
+                }
+
+                let result = parse_redirect_request(&request_line, state);
+                match result {
+                    Ok(_) => respond(&mut stream, "200 OK", SUCCESS_BODY).await,
+                    Err(_) => respond(&mut stream, "400 Bad Request", FAILURE_BODY).await,
+                }
+
+                return result;
+            }
+        }
+    }
+}
+
+/// The path of an HTTP request line, without its query.
+fn get_request_path(request_line: &str) -> Option<&str> {
+    request_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|target| target.split('?').next())
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) {
+    // The browser may already have closed the connection.
+    let _ = stream
+        .write_all(
+            format!(
+                "HTTP/1.1 {}\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .as_bytes(),
+        )
+        .await;
+}
+
+fn get_listener_port(redirect_url: &Url) -> Result<u16> {
+    match (
+        redirect_url.scheme(),
+        redirect_url.host_str(),
+        redirect_url.port(),
+    ) {
+        ("http", Some("localhost" | "127.0.0.1"), Some(port)) => Ok(port),
+        _ => Err(SalesforceError::GeneralError(format!(
+            "Redirect URL {} must be an http://localhost URL with a port",
+            redirect_url
+        ))
+        .into()),
+    }
+}
+
+/// 32 random bytes from the system CSPRNG, base64url-encoded, for use as
+/// a PKCE verifier or an unguessable `state`.
+pub(super) fn get_random_token() -> Result<String> {
+    let mut token = [0u8; 32];
+    SystemRandom::new().fill(&mut token).map_err(|_| {
+        SalesforceError::GeneralError("Unable to generate a random token".to_owned())
+    })?;
+
+    Ok(URL_SAFE_NO_PAD.encode(token))
+}
+
+/// Extract the authorization code from the first line of the redirect's HTTP request.
+pub(super) fn parse_redirect_request(request_line: &str, state: &str) -> Result<String> {
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .ok_or_else(|| SalesforceError::GeneralError("Malformed redirect request".to_owned()))?;
+    let url = Url::parse("http://localhost")?.join(path)?;
+
+    let get_param = |name: &str| {
+        url.query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    };
+
+    if let Some(error) = get_param("error") {
+        return Err(SalesforceError::GeneralError(format!(
+            "Authorization failed: {} {}",
+            error,
+            get_param("error_description").unwrap_or_default()
+        ))
+        .into());
+    }
+
+    if get_param("state").as_deref() != Some(state) {
+        return Err(
+            SalesforceError::GeneralError("Authorization state did not match".to_owned()).into(),
+        );
+    }
+
+    get_param("code").ok_or_else(|| {
+        SalesforceError::GeneralError("No authorization code returned".to_owned()).into()
+    })
+}
+
+fn open_browser(url: &Url) -> Result<()> {
+    let mut command = if cfg!(target_os = "windows") {
+        // `cmd /C start` would split the URL at each `&`.
+        let mut command = Command::new("rundll32");
+        command.arg("url.dll,FileProtocolHandler");
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+
+    command.arg(url.as_str()).spawn()?;
+
+    Ok(())
+}
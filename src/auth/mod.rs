@@ -1,10 +1,20 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use anyhow::Result;
 use async_trait::async_trait;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
 use reqwest::{Client, Url};
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
+use crate::data::SalesforceId;
 use crate::errors::SalesforceError;
 
+pub mod device;
+pub mod interactive;
+
+pub use device::DeviceFlowAuth;
+pub use interactive::WebFlowAuth;
+
 #[cfg(test)]
 mod test;
 
@@ -13,6 +23,34 @@ pub trait Authentication: Send + Sync {
     async fn refresh_access_token(&mut self) -> Result<()>;
     async fn get_instance_url(&self) -> Result<&Url>;
     fn get_access_token(&self) -> Option<&String>;
+
+    /// The identity and scopes of the current access token, if known.
+    fn get_token_info(&self) -> Option<&TokenInfo> {
+        None
+    }
+
+    /// Revoke this authentication's tokens, ending the session. Revoking a
+    /// refresh token also revokes the access tokens issued from it.
+    async fn revoke(&mut self) -> Result<()> {
+        let access_token = self
+            .get_access_token()
+            .cloned()
+            .ok_or(SalesforceError::NotAuthenticated)?;
+
+        revoke_token(self.get_instance_url().await?, &access_token).await
+    }
+}
+
+async fn revoke_token(url: &Url, token: &str) -> Result<()> {
+    Client::builder()
+        .build()?
+        .post(url.join("services/oauth2/revoke")?)
+        .form(&[("token", token)])
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
 }
 
 #[derive(Debug, Clone)]
@@ -36,15 +74,162 @@ impl ConnectedApp {
     }
 }
 
+/// The host against which a user authenticates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoginHost {
+    /// `https://login.salesforce.com`
+    Production,
+    /// `https://test.salesforce.com`
+    Sandbox,
+    /// An org's My Domain host, such as `example.my.salesforce.com` or,
+    /// in Government Cloud, `example.my.salesforce.mil`.
+    MyDomain(String),
+    /// Any other login URL, such as an Experience Cloud site.
+    Custom(Url),
+}
+
+/// Domains under which Salesforce serves instances, including Government Cloud.
+const SALESFORCE_DOMAINS: [&str; 7] = [
+    "salesforce.com",
+    "force.com",
+    "cloudforce.com",
+    "database.com",
+    "salesforce-setup.com",
+    "salesforce.mil",
+    "crmforce.mil",
+];
+
+impl LoginHost {
+    pub fn get_url(&self) -> Result<Url> {
+        let url = match self {
+            LoginHost::Production => Url::parse("https://login.salesforce.com")?,
+            LoginHost::Sandbox => Url::parse("https://test.salesforce.com")?,
+            LoginHost::MyDomain(host) => {
+                let url = Url::parse(&format!("https://{}", host))?;
+                if url.path() != "/" || !is_salesforce_host(&url) {
+                    return Err(SalesforceError::GeneralError(format!(
+                        "{} is not a Salesforce My Domain host",
+                        host
+                    ))
+                    .into());
+                }
+                url
+            }
+            LoginHost::Custom(url) => url.clone(),
+        };
+
+        check_secure(&url)?;
+        Ok(url)
+    }
+}
+
+fn is_salesforce_host(url: &Url) -> bool {
+    url.host_str().is_some_and(|host| {
+        SALESFORCE_DOMAINS
+            .iter()
+            .any(|d| host == *d || host.ends_with(&format!(".{}", d)))
+    })
+}
+
+fn check_secure(url: &Url) -> Result<()> {
+    // Plain HTTP is accepted only for local test servers.
+    let local = matches!(url.host_str(), Some("localhost") | Some("127.0.0.1"));
+    if url.scheme() != "https" && !local {
+        return Err(SalesforceError::GeneralError(format!("{} does not use HTTPS", url)).into());
+    }
+
+    Ok(())
+}
+
+/// Parse the instance URL from a token response, and reject it unless it is a
+/// secure URL on a Salesforce domain or on the host that issued the token.
+pub(crate) fn parse_instance_url(instance_url: &str, login_url: &Url) -> Result<Url> {
+    let url = Url::parse(instance_url)?;
+    check_secure(&url)?;
+
+    if !is_salesforce_host(&url) && url.host_str() != login_url.host_str() {
+        return Err(SalesforceError::GeneralError(format!(
+            "The token response returned an untrusted instance URL: {}",
+            url
+        ))
+        .into());
+    }
+
+    Ok(url)
+}
+
+/// The identity and granted scopes returned with an access token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenInfo {
+    identity_url: String,
+    scopes: Vec<String>,
+}
+
+impl TokenInfo {
+    fn from_response(response: &TokenResponse) -> TokenInfo {
+        TokenInfo {
+            identity_url: response.id.clone(),
+            scopes: response
+                .scope
+                .as_deref()
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(|s| s.to_owned())
+                .collect(),
+        }
+    }
+
+    /// The Identity URL, `https://login.salesforce.com/id/<org Id>/<user Id>`.
+    pub fn get_identity_url(&self) -> &str {
+        &self.identity_url
+    }
+
+    pub fn get_granted_scopes(&self) -> &[String] {
+        &self.scopes
+    }
+
+    /// Whether `scope` was granted. The `full` scope grants all others
+    /// except `refresh_token`.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes
+            .iter()
+            .any(|s| s == scope || (s == "full" && scope != "refresh_token"))
+    }
+
+    pub fn get_org_id(&self) -> Option<SalesforceId> {
+        self.get_identity_segment(2)
+    }
+
+    pub fn get_user_id(&self) -> Option<SalesforceId> {
+        self.get_identity_segment(1)
+    }
+
+    // The Identity URL ends with the org Id, then the user Id.
+    fn get_identity_segment(&self, from_end: usize) -> Option<SalesforceId> {
+        let segments: Vec<&str> = self
+            .identity_url
+            .trim_end_matches('/')
+            .rsplit('/')
+            .collect();
+
+        if segments.get(2) != Some(&"id") {
+            return None;
+        }
+        SalesforceId::new(segments.get(from_end - 1)?).ok()
+    }
+}
+
 #[derive(Deserialize)]
 struct TokenResponse {
     id: String,
-    issued_at: String,
+    issued_at: Option<String>,
     instance_url: String,
-    signature: String,
+    // Not returned by the JWT bearer flow.
+    signature: Option<String>,
     access_token: String,
     token_type: String,
     scope: Option<String>,
+    refresh_token: Option<String>,
 }
 
 #[derive(Clone)]
@@ -52,7 +237,51 @@ pub struct RefreshTokenAuth {
     refresh_token: String,
     instance_url: Url,
     access_token: Option<String>,
+    token_info: Option<TokenInfo>,
     app: ConnectedApp,
+    login_url: Option<Url>,
+}
+
+impl RefreshTokenAuth {
+    pub fn new(refresh_token: String, instance_url: Url, app: ConnectedApp) -> RefreshTokenAuth {
+        RefreshTokenAuth {
+            refresh_token,
+            instance_url,
+            access_token: None,
+            token_info: None,
+            app,
+            login_url: None,
+        }
+    }
+
+    /// Refresh tokens against `login_host` rather than the instance URL.
+    pub fn with_login_host(mut self, login_host: &LoginHost) -> Result<Self> {
+        self.login_url = Some(login_host.get_url()?);
+        Ok(self)
+    }
+
+    /// The refresh token, which may be stored to reconnect to the org later.
+    pub fn get_refresh_token(&self) -> &String {
+        &self.refresh_token
+    }
+
+    fn get_refresh_form(&self) -> Vec<(&str, &str)> {
+        let mut form = vec![
+            ("client_id", self.app.consumer_key.as_str()),
+            ("grant_type", "refresh_token"),
+            ("refresh_token", self.refresh_token.as_str()),
+        ];
+        // Connected Apps used with PKCE need not require the secret.
+        if !self.app.client_secret.is_empty() {
+            form.push(("client_secret", self.app.client_secret.as_str()));
+        }
+
+        form
+    }
+
+    fn get_login_url(&self) -> &Url {
+        self.login_url.as_ref().unwrap_or(&self.instance_url)
+    }
 }
 
 #[async_trait]
@@ -60,25 +289,22 @@ impl Authentication for RefreshTokenAuth {
     async fn refresh_access_token(&mut self) -> Result<()> {
         self.access_token = None;
 
-        let url = format!("{}/services/oauth2/token", self.instance_url);
+        let login_url = self.get_login_url().clone();
+        let url = login_url.join("services/oauth2/token")?;
 
         let result: TokenResponse = Client::builder()
             .build()?
             .post(url)
-            .form(&[
-                ("client_id", &self.app.consumer_key),
-                ("client_secret", &self.app.client_secret),
-                ("grant_type", &"refresh_token".to_string()),
-                ("refresh_token", &self.refresh_token),
-            ])
+            .form(&self.get_refresh_form())
             .send()
             .await?
             .error_for_status()? // TODO: handle differently, parse error body
             .json()
             .await?;
 
+        self.instance_url = parse_instance_url(&result.instance_url, &login_url)?;
+        self.token_info = Some(TokenInfo::from_response(&result));
         self.access_token = Some(result.access_token);
-        self.instance_url = Url::parse(&result.instance_url)?;
 
         Ok(())
     }
@@ -90,29 +316,303 @@ impl Authentication for RefreshTokenAuth {
     fn get_access_token(&self) -> Option<&String> {
         self.access_token.as_ref()
     }
+
+    fn get_token_info(&self) -> Option<&TokenInfo> {
+        self.token_info.as_ref()
+    }
+
+    async fn revoke(&mut self) -> Result<()> {
+        revoke_token(self.get_login_url(), &self.refresh_token).await?;
+        self.access_token = None;
+        self.token_info = None;
+
+        Ok(())
+    }
+}
+
+/// Authentication obtained through the OAuth 2.0 web server (authorization code) flow.
+#[derive(Clone)]
+pub struct WebServerAuth {
+    access_token: Option<String>,
+    token_info: Option<TokenInfo>,
+    refresh_token: Option<String>,
+    instance_url: Url,
+    app: ConnectedApp,
+}
+
+impl WebServerAuth {
+    /// The URL to which the user should be sent to authorize `app`.
+    /// `state` is returned unchanged to the redirect URL.
+    pub fn get_authorize_url(app: &ConnectedApp, login_url: &Url, state: &str) -> Result<Url> {
+        let redirect_url = app.redirect_url.as_ref().ok_or_else(|| {
+            SalesforceError::GeneralError(
+                "The web server flow requires a Connected App redirect URL".to_owned(),
+            )
+        })?;
+
+        let mut url = login_url.join("services/oauth2/authorize")?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &app.consumer_key)
+            .append_pair("redirect_uri", redirect_url.as_str())
+            .append_pair("state", state);
+
+        Ok(url)
+    }
+
+    /// Exchange an authorization code delivered to the redirect URL for tokens.
+    pub async fn from_code(
+        app: &ConnectedApp,
+        login_url: &Url,
+        code: &str,
+    ) -> Result<WebServerAuth> {
+        let redirect_url = app.redirect_url.as_ref().ok_or_else(|| {
+            SalesforceError::GeneralError(
+                "The web server flow requires a Connected App redirect URL".to_owned(),
+            )
+        })?;
+
+        let result: TokenResponse = Client::builder()
+            .build()?
+            .post(login_url.join("services/oauth2/token")?)
+            .form(&[
+                ("client_id", app.consumer_key.as_str()),
+                ("client_secret", app.client_secret.as_str()),
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", redirect_url.as_str()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(WebServerAuth {
+            instance_url: parse_instance_url(&result.instance_url, login_url)?,
+            token_info: Some(TokenInfo::from_response(&result)),
+            access_token: Some(result.access_token),
+            refresh_token: result.refresh_token,
+            app: app.clone(),
+        })
+    }
+
+    /// The refresh token, if the Connected App's scopes granted one.
+    pub fn get_refresh_token(&self) -> Option<&String> {
+        self.refresh_token.as_ref()
+    }
+}
+
+#[async_trait]
+impl Authentication for WebServerAuth {
+    async fn refresh_access_token(&mut self) -> Result<()> {
+        let refresh_token = self
+            .refresh_token
+            .clone()
+            .ok_or(SalesforceError::CannotRefresh)?;
+
+        let mut auth =
+            RefreshTokenAuth::new(refresh_token, self.instance_url.clone(), self.app.clone());
+        auth.refresh_access_token().await?;
+
+        self.access_token = auth.access_token;
+        self.token_info = auth.token_info;
+        self.instance_url = auth.instance_url;
+
+        Ok(())
+    }
+
+    async fn get_instance_url(&self) -> Result<&Url> {
+        Ok(&self.instance_url)
+    }
+
+    fn get_access_token(&self) -> Option<&String> {
+        self.access_token.as_ref()
+    }
+
+    fn get_token_info(&self) -> Option<&TokenInfo> {
+        self.token_info.as_ref()
+    }
+
+    async fn revoke(&mut self) -> Result<()> {
+        let token = self
+            .refresh_token
+            .as_ref()
+            .or(self.access_token.as_ref())
+            .ok_or(SalesforceError::NotAuthenticated)?;
+        revoke_token(&self.instance_url, token).await?;
+        self.access_token = None;
+        self.token_info = None;
+        self.refresh_token = None;
+
+        Ok(())
+    }
+}
+
+const JWT_BEARER_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
+/// How long a JWT bearer assertion is valid. Salesforce accepts at most three minutes.
+const JWT_LIFETIME: Duration = Duration::from_secs(180);
+
+#[derive(Serialize)]
+struct JwtClaims<'a> {
+    iss: &'a str,
+    sub: &'a str,
+    aud: &'a str,
+    exp: u64,
 }
 
+/// The default audience of JWT bearer assertions sent to `login_url`.
+/// Salesforce accepts only its login hosts, and Experience Cloud site URLs,
+/// as audiences, so a My Domain host maps to the login host for its kind of org.
+fn get_jwt_audience(login_url: &Url) -> Result<Url> {
+    if !is_salesforce_host(login_url) {
+        return Ok(login_url.clone());
+    }
+
+    // Sandbox My Domains are `example--sandbox.my.salesforce.com`
+    // or, with enhanced domains, `example--sandbox.sandbox.my.salesforce.com`.
+    let sandbox = login_url.host_str().is_some_and(|host| {
+        host == "test.salesforce.com" || host.contains("--") || host.contains(".sandbox.")
+    });
+
+    if sandbox {
+        LoginHost::Sandbox.get_url()
+    } else {
+        LoginHost::Production.get_url()
+    }
+}
+
+/// Authentication through the OAuth 2.0 JWT bearer flow, for server-to-server
+/// integrations that cannot prompt a user.
+///
+/// The Connected App must have the certificate matching the private key uploaded,
+/// and the user must be pre-authorized for the app.
 #[derive(Clone)]
 pub struct JwtAuth {
     access_token: Option<String>,
+    token_info: Option<TokenInfo>,
     instance_url: Url,
     app: ConnectedApp,
-    cert: String,
+    username: String,
+    login_url: Url,
+    audience: Url,
+    private_key: EncodingKey,
+}
+
+impl JwtAuth {
+    /// `private_key` is a PEM-encoded RSA private key. `login_url` is
+    /// `https://login.salesforce.com`, `https://test.salesforce.com`,
+    /// a My Domain URL, or an Experience Cloud site URL. The assertion's
+    /// audience is `https://test.salesforce.com` for a sandbox,
+    /// `https://login.salesforce.com` for any other Salesforce host, and
+    /// otherwise `login_url` itself.
+    pub fn new(
+        username: String,
+        private_key: &[u8],
+        app: ConnectedApp,
+        login_url: Url,
+    ) -> Result<JwtAuth> {
+        Ok(JwtAuth {
+            access_token: None,
+            token_info: None,
+            instance_url: login_url.clone(),
+            app,
+            username,
+            audience: get_jwt_audience(&login_url)?,
+            login_url,
+            private_key: EncodingKey::from_rsa_pem(private_key)?,
+        })
+    }
+
+    /// Request tokens from `login_host`. The assertion's audience is reset
+    /// to the default for that host, as for `new()`.
+    pub fn with_login_host(mut self, login_host: &LoginHost) -> Result<Self> {
+        self.login_url = login_host.get_url()?;
+        self.instance_url = self.login_url.clone();
+        self.audience = get_jwt_audience(&self.login_url)?;
+        Ok(self)
+    }
+
+    /// Set the assertion's audience, such as an Experience Cloud site URL
+    /// when tokens are requested from another host.
+    #[must_use]
+    pub fn with_audience(mut self, audience: Url) -> Self {
+        self.audience = audience;
+        self
+    }
+
+    pub fn get_audience(&self) -> &Url {
+        &self.audience
+    }
+
+    /// Build the signed (RS256) assertion exchanged for an access token.
+    pub(crate) fn get_assertion(&self) -> Result<String> {
+        let exp = (SystemTime::now().duration_since(UNIX_EPOCH)? + JWT_LIFETIME).as_secs();
+
+        Ok(encode(
+            &Header::new(Algorithm::RS256),
+            &JwtClaims {
+                iss: &self.app.consumer_key,
+                sub: &self.username,
+                aud: self.audience.as_str().trim_end_matches('/'),
+                exp,
+            },
+            &self.private_key,
+        )?)
+    }
 }
 
 #[async_trait]
 impl Authentication for JwtAuth {
     async fn refresh_access_token(&mut self) -> Result<()> {
-        todo!();
+        self.access_token = None;
+
+        let result: TokenResponse = Client::builder()
+            .build()?
+            .post(self.login_url.join("services/oauth2/token")?)
+            .form(&[
+                ("grant_type", JWT_BEARER_GRANT_TYPE),
+                ("assertion", &self.get_assertion()?),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        self.instance_url = parse_instance_url(&result.instance_url, &self.login_url)?;
+        self.token_info = Some(TokenInfo::from_response(&result));
+        self.access_token = Some(result.access_token);
+
+        Ok(())
     }
 
     async fn get_instance_url(&self) -> Result<&Url> {
+        // We may not yet be authenticated.
+        if self.access_token.is_none() {
+            return Err(SalesforceError::NotAuthenticated.into());
+        }
+
         Ok(&self.instance_url)
     }
 
     fn get_access_token(&self) -> Option<&String> {
         self.access_token.as_ref()
     }
+
+    fn get_token_info(&self) -> Option<&TokenInfo> {
+        self.token_info.as_ref()
+    }
+
+    async fn revoke(&mut self) -> Result<()> {
+        let access_token = self
+            .access_token
+            .take()
+            .ok_or(SalesforceError::NotAuthenticated)?;
+        self.token_info = None;
+
+        revoke_token(&self.instance_url, &access_token).await
+    }
 }
 
 #[derive(Clone)]
@@ -122,6 +622,7 @@ pub struct UsernamePasswordAuth {
     security_token: Option<String>,
     app: ConnectedApp,
     access_token: Option<String>,
+    token_info: Option<TokenInfo>,
     instance_url: Url,
 }
 
@@ -140,8 +641,15 @@ impl UsernamePasswordAuth {
             app,
             instance_url,
             access_token: None,
+            token_info: None,
         }
     }
+
+    /// Log in through `login_host` rather than `instance_url`.
+    pub fn with_login_host(mut self, login_host: &LoginHost) -> Result<Self> {
+        self.instance_url = login_host.get_url()?;
+        Ok(self)
+    }
 }
 
 #[async_trait]
@@ -159,7 +667,7 @@ impl Authentication for UsernamePasswordAuth {
 
         let result: TokenResponse = Client::builder()
             .build()?
-            .post(url)
+            .post(url.clone())
             .form(&[
                 // TODO: make this into a struct
                 ("client_id", &self.app.consumer_key),
@@ -174,8 +682,9 @@ impl Authentication for UsernamePasswordAuth {
             .json()
             .await?; // TODO: is there a 200-with-error-body case?
 
+        self.instance_url = parse_instance_url(&result.instance_url, &url)?;
+        self.token_info = Some(TokenInfo::from_response(&result));
         self.access_token = Some(result.access_token);
-        self.instance_url = Url::parse(&result.instance_url)?;
 
         Ok(())
     }
@@ -192,6 +701,20 @@ impl Authentication for UsernamePasswordAuth {
     fn get_access_token(&self) -> Option<&String> {
         self.access_token.as_ref()
     }
+
+    fn get_token_info(&self) -> Option<&TokenInfo> {
+        self.token_info.as_ref()
+    }
+
+    async fn revoke(&mut self) -> Result<()> {
+        let access_token = self
+            .access_token
+            .take()
+            .ok_or(SalesforceError::NotAuthenticated)?;
+        self.token_info = None;
+
+        revoke_token(&self.instance_url, &access_token).await
+    }
 }
 
 #[derive(Clone)]
@@ -0,0 +1,246 @@
+//! Extracts of large objects split into several Bulk API query jobs.
+//!
+//! A single Bulk API query job is bounded in how long it may run and how much
+//! data it may return. A `SlicedExport` divides an extract into slices of
+//! `CreatedDate`, bisecting the range until each slice holds no more than a
+//! set number of records, then runs a job per slice and merges their results.
+//!
+//! Slices are at least one second wide, so records created in the same second
+//! are always in the same slice, however many there are.
+
+use std::pin::Pin;
+
+use anyhow::Result;
+use async_stream::try_stream;
+use chrono::{TimeZone, Utc};
+use futures::{Stream, StreamExt};
+use tokio::spawn;
+
+use crate::{
+    api::Connection,
+    bulk::v2::{BulkJobStatus, BulkQueryJob},
+    data::{DateTime, SObjectDeserialization, SObjectType},
+    errors::SalesforceError,
+    rest::query::{query_all_values, QueryCountRequest, QueryRequest},
+};
+
+const DEFAULT_MAX_SLICE_RECORDS: usize = 1_000_000;
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// A range of `CreatedDate`, from `start` inclusive to `end` exclusive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeSlice {
+    pub start: DateTime,
+    pub end: DateTime,
+    /// The number of records in the slice when it was planned.
+    pub records: usize,
+}
+
+pub struct SlicedExport {
+    sobject: String,
+    fields: Vec<String>,
+    filter: Option<String>,
+    all: bool,
+    max_slice_records: usize,
+    concurrency: usize,
+}
+
+/// Format `value` as a SOQL date/time literal, which may not have fractional seconds.
+fn to_soql_datetime(value: &DateTime) -> String {
+    value.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/// `value` plus `seconds`, discarding any fractional second.
+fn add_seconds(value: &DateTime, seconds: i64) -> Result<DateTime> {
+    Ok(Utc
+        .timestamp_opt(value.timestamp() + seconds, 0)
+        .single()
+        .ok_or(SalesforceError::DateTimeError)?
+        .into())
+}
+
+impl SlicedExport {
+    pub fn new(sobject: &str, fields: &[&str]) -> SlicedExport {
+        SlicedExport {
+            sobject: sobject.to_owned(),
+            fields: fields.iter().map(|f| f.to_string()).collect(),
+            filter: None,
+            all: false,
+            max_slice_records: DEFAULT_MAX_SLICE_RECORDS,
+            concurrency: DEFAULT_CONCURRENCY,
+        }
+    }
+
+    /// Restrict the export with a SOQL `WHERE` clause, without the `WHERE` keyword.
+    #[must_use]
+    pub fn with_filter(mut self, filter: &str) -> SlicedExport {
+        self.filter = Some(filter.to_owned());
+        self
+    }
+
+    /// Include deleted and archived records.
+    #[must_use]
+    pub fn with_query_all(mut self, all: bool) -> SlicedExport {
+        self.all = all;
+        self
+    }
+
+    /// Split slices holding more than `max_slice_records` records. Defaults to 1,000,000.
+    #[must_use]
+    pub fn with_max_slice_records(mut self, max_slice_records: usize) -> SlicedExport {
+        self.max_slice_records = max_slice_records.max(1);
+        self
+    }
+
+    /// Run up to `concurrency` query jobs at once. Defaults to 4.
+    #[must_use]
+    pub fn with_concurrency(mut self, concurrency: usize) -> SlicedExport {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    fn get_where_clause(&self, slice: Option<(&DateTime, &DateTime)>) -> String {
+        let mut clauses = Vec::new();
+
+        if let Some(filter) = &self.filter {
+            clauses.push(format!("({})", filter));
+        }
+        if let Some((start, end)) = slice {
+            clauses.push(format!(
+                "CreatedDate >= {} AND CreatedDate < {}",
+                to_soql_datetime(start),
+                to_soql_datetime(end)
+            ));
+        }
+
+        if clauses.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", clauses.join(" AND "))
+        }
+    }
+
+    pub fn get_slice_query(&self, slice: &TimeSlice) -> String {
+        format!(
+            "SELECT {} FROM {}{}",
+            self.fields.join(", "),
+            self.sobject,
+            self.get_where_clause(Some((&slice.start, &slice.end)))
+        )
+    }
+
+    async fn get_created_date(&self, conn: &Connection, order: &str) -> Result<Option<DateTime>> {
+        let query = format!(
+            "SELECT CreatedDate FROM {}{} ORDER BY CreatedDate {} LIMIT 1",
+            self.sobject,
+            self.get_where_clause(None),
+            order
+        );
+        let records = query_all_values(conn, &QueryRequest::new(&query, self.all)).await?;
+
+        records
+            .first()
+            .and_then(|r| r["CreatedDate"].as_str())
+            .map(|d| d.parse())
+            .transpose()
+    }
+
+    async fn count(&self, conn: &Connection, start: &DateTime, end: &DateTime) -> Result<usize> {
+        let query = format!(
+            "SELECT COUNT() FROM {}{}",
+            self.sobject,
+            self.get_where_clause(Some((start, end)))
+        );
+
+        conn.execute(&QueryCountRequest::new(&query, self.all))
+            .await
+    }
+
+    /// Divide the records to export into slices, in order of `CreatedDate`,
+    /// by bisecting the range of `CreatedDate` and counting the records in
+    /// each half. Ranges without records are omitted.
+    pub async fn plan(&self, conn: &Connection) -> Result<Vec<TimeSlice>> {
+        let (first, last) = match (
+            self.get_created_date(conn, "ASC").await?,
+            self.get_created_date(conn, "DESC").await?,
+        ) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Ok(Vec::new()),
+        };
+
+        let mut pending = vec![(add_seconds(&first, 0)?, add_seconds(&last, 1)?)];
+        let mut slices = Vec::new();
+
+        while let Some((start, end)) = pending.pop() {
+            let records = self.count(conn, &start, &end).await?;
+            let width = end.timestamp() - start.timestamp();
+
+            if records > self.max_slice_records && width > 1 {
+                let middle = add_seconds(&start, width / 2)?;
+                // Earlier halves are counted, and so planned, first.
+                pending.push((middle.clone(), end));
+                pending.push((start, middle));
+            } else if records > 0 {
+                slices.push(TimeSlice {
+                    start,
+                    end,
+                    records,
+                });
+            }
+        }
+
+        Ok(slices)
+    }
+
+    /// Run a query job for each of `slices`, with up to the configured
+    /// concurrency at once, and stream their records. Records from different
+    /// slices are yielded in the order their jobs complete.
+    pub fn stream<T>(
+        &self,
+        conn: &Connection,
+        sobject_type: &SObjectType,
+        slices: Vec<TimeSlice>,
+    ) -> Pin<Box<dyn Stream<Item = Result<T>> + Send>>
+    where
+        T: SObjectDeserialization + Unpin + Send + Sync + 'static,
+    {
+        let conn = conn.clone();
+        let sobject_type = sobject_type.clone();
+        let all = self.all;
+        let queries: Vec<String> = slices.iter().map(|s| self.get_slice_query(s)).collect();
+        let concurrency = self.concurrency;
+
+        Box::pin(try_stream! {
+            // Jobs are spawned so that they continue to run while earlier
+            // slices' results are being consumed.
+            let mut jobs = futures::stream::iter(queries)
+                .map(|query| {
+                    let conn = conn.clone();
+                    spawn(async move {
+                        BulkQueryJob::create(&conn, &query, all)
+                            .await?
+                            .complete(&conn)
+                            .await
+                    })
+                })
+                .buffer_unordered(concurrency);
+
+            while let Some(job) = jobs.next().await {
+                let job = job??;
+                if job.get_state() != BulkJobStatus::JobComplete {
+                    Err(SalesforceError::GeneralError(format!(
+                        "Export query job {} finished in state {:?}",
+                        job.get_id(),
+                        job.get_state()
+                    )))?;
+                }
+
+                let mut records = job.get_results_stream::<T>(&conn, &sobject_type).await;
+                while let Some(record) = records.next().await {
+                    let record = record?;
+                    yield record;
+                }
+            }
+        })
+    }
+}
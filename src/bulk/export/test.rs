@@ -0,0 +1,139 @@
+use anyhow::Result;
+
+use super::KeysetExport;
+#[cfg(feature = "unstable")]
+use super::{SlicedExport, TimeSlice};
+#[cfg(feature = "unstable")]
+use crate::test_integration_base::serve_responses;
+use crate::{
+    prelude::*,
+    test_integration_base::{get_test_connection, Account},
+};
+
+#[test]
+fn test_keyset_export_query() -> Result<()> {
+    let export = KeysetExport::new("Account", &["Name"]).with_batch_size(100);
+
+    assert_eq!(
+        export.get_batch_query(),
+        "SELECT Id, Name FROM Account ORDER BY Id ASC LIMIT 100"
+    );
+
+    let last_id = SalesforceId::new("0013600000AAAAA")?;
+    let export = export
+        .with_filter("Name = 'a' OR Name = 'b'")
+        .resume_after(last_id);
+
+    assert_eq!(
+        export.get_batch_query(),
+        format!(
+            "SELECT Id, Name FROM Account WHERE (Name = 'a' OR Name = 'b') AND Id > '{}' ORDER BY Id ASC LIMIT 100",
+            last_id
+        )
+    );
+    assert_eq!(export.get_last_id(), Some(last_id));
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_keyset_export() -> Result<()> {
+    let conn = get_test_connection()?;
+    let account_type = conn.get_type("Account").await?;
+
+    let mut accounts: Vec<Account> = (0..3)
+        .map(|i| Account {
+            id: None,
+            name: format!("Keyset Export {}", i),
+        })
+        .collect();
+    accounts.create(conn.clone(), true).await?;
+
+    let mut export = KeysetExport::new("Account", &["Id", "Name"])
+        .with_filter("Name LIKE 'Keyset Export %'")
+        .with_batch_size(2);
+    let mut exported: Vec<Account> = Vec::new();
+
+    while let Some(mut batch) = export.next_batch(&conn, &account_type).await? {
+        exported.append(&mut batch);
+    }
+
+    assert_eq!(exported.len(), 3);
+    assert!(export.is_done());
+
+    accounts.delete(&conn, true).await?;
+
+    Ok(())
+}
+
+#[cfg(feature = "unstable")]
+#[test]
+fn test_sliced_export_query() -> Result<()> {
+    let export = SlicedExport::new("Account", &["Id", "Name"]).with_filter("Name != null");
+    let slice = TimeSlice {
+        start: DateTime::new(2020, 1, 1, 0, 0, 0, 0)?,
+        end: DateTime::new(2020, 2, 1, 12, 30, 15, 0)?,
+        records: 10,
+    };
+
+    assert_eq!(
+        export.get_slice_query(&slice),
+        "SELECT Id, Name FROM Account WHERE (Name != null) AND CreatedDate >= 2020-01-01T00:00:00Z AND CreatedDate < 2020-02-01T12:30:15Z"
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "unstable")]
+#[tokio::test]
+async fn test_sliced_export_plan() -> Result<()> {
+    let (conn, _) = serve_responses(vec![
+        (
+            "200 OK",
+            r#"{"totalSize": 1, "done": true, "records": [{"attributes": {"type": "Account"}, "CreatedDate": "2020-01-01T00:00:00.000+0000"}]}"#,
+        ),
+        (
+            "200 OK",
+            r#"{"totalSize": 1, "done": true, "records": [{"attributes": {"type": "Account"}, "CreatedDate": "2020-01-01T00:00:10.000+0000"}]}"#,
+        ),
+        // Counts of [0, 11), [0, 5), [5, 11), [5, 8), [8, 11), [8, 9), [9, 11)
+        ("200 OK", r#"{"totalSize": 300, "done": true, "records": []}"#),
+        ("200 OK", r#"{"totalSize": 80, "done": true, "records": []}"#),
+        ("200 OK", r#"{"totalSize": 220, "done": true, "records": []}"#),
+        ("200 OK", r#"{"totalSize": 100, "done": true, "records": []}"#),
+        ("200 OK", r#"{"totalSize": 120, "done": true, "records": []}"#),
+        ("200 OK", r#"{"totalSize": 60, "done": true, "records": []}"#),
+        ("200 OK", r#"{"totalSize": 0, "done": true, "records": []}"#),
+    ])
+    .await?;
+
+    let slices = SlicedExport::new("Account", &["Id"])
+        .with_max_slice_records(100)
+        .plan(&conn)
+        .await?;
+
+    let at = |s| DateTime::new(2020, 1, 1, 0, 0, s, 0);
+    assert_eq!(
+        slices,
+        vec![
+            TimeSlice {
+                start: at(0)?,
+                end: at(5)?,
+                records: 80
+            },
+            TimeSlice {
+                start: at(5)?,
+                end: at(8)?,
+                records: 100
+            },
+            TimeSlice {
+                start: at(8)?,
+                end: at(9)?,
+                records: 60
+            },
+        ]
+    );
+
+    Ok(())
+}
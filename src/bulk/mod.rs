@@ -1 +1,4 @@
+pub mod export;
+pub mod registry;
+pub mod v1;
 pub mod v2;
@@ -0,0 +1,229 @@
+//! Durable bookkeeping for Bulk API jobs.
+//!
+//! Long-running pipelines need to find the jobs they created after a restart.
+//! A `JobRegistry` records the Id, kind, parameters, and last known state of each
+//! job, and can resume or abort those jobs against a `Connection`.
+//! `JsonFileJobRegistry` persists the registry to a local JSON file; other storage
+//! backends can be supplied by implementing the trait.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    api::Connection,
+    bulk::v2::{BulkApiDmlOperation, BulkDmlJob, BulkJobStatus, BulkQueryJob, BulkQueryOperation},
+    data::SalesforceId,
+    errors::SalesforceError,
+};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum JobKind {
+    Query(BulkQueryOperation),
+    Ingest(BulkApiDmlOperation),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobRecord {
+    pub id: SalesforceId,
+    pub kind: JobKind,
+    pub object: String,
+    pub parameters: HashMap<String, String>,
+    pub state: BulkJobStatus,
+}
+
+impl JobRecord {
+    pub fn new(id: SalesforceId, kind: JobKind, object: String, state: BulkJobStatus) -> JobRecord {
+        JobRecord {
+            id,
+            kind,
+            object,
+            parameters: HashMap::new(),
+            state,
+        }
+    }
+
+    #[must_use]
+    pub fn with_parameter(mut self, key: &str, value: &str) -> JobRecord {
+        self.parameters.insert(key.to_owned(), value.to_owned());
+        self
+    }
+
+    pub fn from_query_job(job: &BulkQueryJob) -> JobRecord {
+        JobRecord::new(
+            job.get_id(),
+            JobKind::Query(job.get_operation()),
+            job.get_object().to_owned(),
+            job.get_state(),
+        )
+    }
+
+    pub fn from_dml_job(job: &BulkDmlJob) -> JobRecord {
+        let record = JobRecord::new(
+            job.id,
+            JobKind::Ingest(job.operation),
+            job.object.clone(),
+            job.state,
+        );
+
+        if let Some(external_id) = &job.external_id_field_name {
+            record.with_parameter("externalIdFieldName", external_id)
+        } else {
+            record
+        }
+    }
+}
+
+pub enum ResumedJob {
+    Query(BulkQueryJob),
+    Ingest(BulkDmlJob),
+}
+
+impl ResumedJob {
+    pub fn get_state(&self) -> BulkJobStatus {
+        match self {
+            ResumedJob::Query(job) => job.get_state(),
+            ResumedJob::Ingest(job) => job.state,
+        }
+    }
+}
+
+/// Storage for `JobRecord`s. Implementors provide persistence; the async methods
+/// that talk to Salesforce are provided on top of that storage.
+#[async_trait]
+pub trait JobRegistry: Send + Sync {
+    /// Add a record to the registry, replacing any record with the same Id.
+    fn register(&mut self, record: JobRecord) -> Result<()>;
+    fn get(&self, id: SalesforceId) -> Result<Option<JobRecord>>;
+    fn list(&self) -> Result<Vec<JobRecord>>;
+    fn set_state(&mut self, id: SalesforceId, state: BulkJobStatus) -> Result<()>;
+    fn remove(&mut self, id: SalesforceId) -> Result<Option<JobRecord>>;
+
+    /// Retrieve the current status of a registered job from Salesforce
+    /// and record its state.
+    async fn resume(&mut self, conn: &Connection, id: SalesforceId) -> Result<ResumedJob> {
+        let record = self.get(id)?.ok_or_else(|| unregistered_job(id))?;
+
+        let job = match record.kind {
+            JobKind::Query(_) => ResumedJob::Query(BulkQueryJob::get(conn, id).await?),
+            JobKind::Ingest(_) => ResumedJob::Ingest(BulkDmlJob::get(conn, id).await?),
+        };
+
+        self.set_state(id, job.get_state())?;
+
+        Ok(job)
+    }
+
+    async fn abort(&mut self, conn: &Connection, id: SalesforceId) -> Result<()> {
+        let record = self.get(id)?.ok_or_else(|| unregistered_job(id))?;
+
+        let state = match record.kind {
+            JobKind::Query(_) => BulkQueryJob::get(conn, id)
+                .await?
+                .abort(conn)
+                .await?
+                .get_state(),
+            JobKind::Ingest(_) => BulkDmlJob::get(conn, id).await?.abort(conn).await?.state,
+        };
+
+        self.set_state(id, state)
+    }
+
+    /// Update the recorded state of every job that has not yet reached
+    /// a completed state.
+    async fn refresh(&mut self, conn: &Connection) -> Result<()> {
+        for record in self.list()? {
+            if !record.state.is_completed_state() {
+                self.resume(conn, record.id).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn unregistered_job(id: SalesforceId) -> anyhow::Error {
+    SalesforceError::GeneralError(format!("Job {} is not registered", id)).into()
+}
+
+/// A `JobRegistry` persisted to a JSON file. Every mutation is written through
+/// to disk immediately.
+pub struct JsonFileJobRegistry {
+    path: PathBuf,
+    jobs: Vec<JobRecord>,
+}
+
+impl JsonFileJobRegistry {
+    /// Open the registry at `path`, creating an empty registry if the file
+    /// does not yet exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<JsonFileJobRegistry> {
+        let path = path.as_ref().to_path_buf();
+        let jobs = if path.exists() {
+            serde_json::from_slice(&fs::read(&path)?)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(JsonFileJobRegistry { path, jobs })
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    fn save(&self) -> Result<()> {
+        // Write to a sibling file and rename, so that a crash mid-write
+        // cannot leave a truncated registry behind.
+        let temp_path = self.path.with_extension("tmp");
+
+        fs::write(&temp_path, serde_json::to_vec_pretty(&self.jobs)?)?;
+        fs::rename(&temp_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+impl JobRegistry for JsonFileJobRegistry {
+    fn register(&mut self, record: JobRecord) -> Result<()> {
+        self.jobs.retain(|j| j.id != record.id);
+        self.jobs.push(record);
+        self.save()
+    }
+
+    fn get(&self, id: SalesforceId) -> Result<Option<JobRecord>> {
+        Ok(self.jobs.iter().find(|j| j.id == id).cloned())
+    }
+
+    fn list(&self) -> Result<Vec<JobRecord>> {
+        Ok(self.jobs.clone())
+    }
+
+    fn set_state(&mut self, id: SalesforceId, state: BulkJobStatus) -> Result<()> {
+        self.jobs
+            .iter_mut()
+            .find(|j| j.id == id)
+            .ok_or_else(|| unregistered_job(id))?
+            .state = state;
+        self.save()
+    }
+
+    fn remove(&mut self, id: SalesforceId) -> Result<Option<JobRecord>> {
+        let position = self.jobs.iter().position(|j| j.id == id);
+
+        if let Some(position) = position {
+            let record = self.jobs.remove(position);
+            self.save()?;
+            Ok(Some(record))
+        } else {
+            Ok(None)
+        }
+    }
+}
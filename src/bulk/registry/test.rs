@@ -0,0 +1,80 @@
+use std::{env, fs, path::PathBuf};
+
+use anyhow::Result;
+
+use super::{JobKind, JobRecord, JobRegistry, JsonFileJobRegistry};
+use crate::bulk::v2::{BulkApiDmlOperation, BulkJobStatus, BulkQueryOperation};
+use crate::data::SalesforceId;
+
+fn get_registry_path(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("baris-{}-{}.json", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    path
+}
+
+#[test]
+fn test_json_registry_round_trip() -> Result<()> {
+    let path = get_registry_path("registry-round-trip");
+    let query_id = SalesforceId::new("7503600000AAAAA")?;
+    let ingest_id = SalesforceId::new("7503600000BBBBB")?;
+
+    let mut registry = JsonFileJobRegistry::open(&path)?;
+    registry.register(JobRecord::new(
+        query_id,
+        JobKind::Query(BulkQueryOperation::Query),
+        "Account".to_owned(),
+        BulkJobStatus::InProgress,
+    ))?;
+    registry.register(
+        JobRecord::new(
+            ingest_id,
+            JobKind::Ingest(BulkApiDmlOperation::Upsert),
+            "Contact".to_owned(),
+            BulkJobStatus::Open,
+        )
+        .with_parameter("externalIdFieldName", "Ext__c"),
+    )?;
+    registry.set_state(query_id, BulkJobStatus::JobComplete)?;
+
+    let registry = JsonFileJobRegistry::open(&path)?;
+    assert_eq!(registry.list()?.len(), 2);
+    assert_eq!(
+        registry.get(query_id)?.unwrap().state,
+        BulkJobStatus::JobComplete
+    );
+    assert_eq!(
+        registry.get(ingest_id)?.unwrap().parameters["externalIdFieldName"],
+        "Ext__c"
+    );
+
+    fs::remove_file(&path)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_json_registry_replace_and_remove() -> Result<()> {
+    let path = get_registry_path("registry-remove");
+    let id = SalesforceId::new("7503600000CCCCC")?;
+    let record = JobRecord::new(
+        id,
+        JobKind::Ingest(BulkApiDmlOperation::Insert),
+        "Account".to_owned(),
+        BulkJobStatus::Open,
+    );
+
+    let mut registry = JsonFileJobRegistry::open(&path)?;
+    registry.register(record.clone())?;
+    registry.register(record.clone())?;
+    assert_eq!(registry.list()?.len(), 1);
+
+    assert_eq!(registry.remove(id)?, Some(record));
+    assert_eq!(registry.remove(id)?, None);
+    assert!(JsonFileJobRegistry::open(&path)?.list()?.is_empty());
+    assert!(registry.set_state(id, BulkJobStatus::Aborted).is_err());
+
+    fs::remove_file(&path)?;
+
+    Ok(())
+}
@@ -0,0 +1,588 @@
+//! The Bulk API 1.0, for queries of very large tables with PK chunking.
+//!
+//! With PK chunking enabled, Salesforce splits a query job into one batch per
+//! range of record Ids, which lets extractions of hundreds of millions of rows
+//! proceed where a single query would time out. Jobs are created with the JSON
+//! content type, and the results of all of a job's batches are read as a single
+//! `ResultStream`.
+
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::{Method, Response};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::task::{spawn, JoinHandle};
+use tokio::time::sleep;
+
+use crate::api::transport::TransportBody;
+use crate::{
+    api::polling::Poller,
+    api::{Connection, SalesforceRawRequest, SalesforceRequest},
+    bulk::v2::BulkQueryOperation,
+    data::traits::SObjectDeserialization,
+    data::DateTime,
+    data::SObjectType,
+    data::SalesforceId,
+    errors::SalesforceError,
+    streams::{ResultStream, ResultStreamManager, ResultStreamState},
+};
+
+#[cfg(test)]
+mod test;
+
+/// The header that enables PK chunking on a Bulk API 1.0 query job.
+pub const PK_CHUNKING_HEADER: &str = "Sforce-Enable-PKChunking";
+/// The largest chunk size Salesforce accepts.
+pub const MAX_PK_CHUNK_SIZE: usize = 250_000;
+
+/// The path of the Bulk API 1.0 resource `path`. Unlike the REST API, the Bulk
+/// API 1.0 omits the `v` from the API version.
+fn get_async_url(api_version: &str, path: &str) -> String {
+    format!(
+        "/services/async/{}/{}",
+        api_version.trim_start_matches('v'),
+        path
+    )
+}
+
+/// Options for PK chunking. Without a chunk size, Salesforce uses 100,000
+/// records per chunk.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PkChunking {
+    chunk_size: Option<usize>,
+    parent: Option<String>,
+    start_row: Option<SalesforceId>,
+}
+
+impl PkChunking {
+    pub fn new() -> PkChunking {
+        PkChunking::default()
+    }
+
+    /// Split the query into chunks of `chunk_size` records, up to `MAX_PK_CHUNK_SIZE`.
+    #[must_use]
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = Some(chunk_size.clamp(1, MAX_PK_CHUNK_SIZE));
+        self
+    }
+
+    /// Chunk on the Ids of the parent sObject `parent`, as is required to
+    /// query sharing tables such as `AccountShare`.
+    #[must_use]
+    pub fn with_parent(mut self, parent: &str) -> Self {
+        self.parent = Some(parent.to_owned());
+        self
+    }
+
+    /// Start the first chunk at `start_row`, such as to resume an extraction.
+    #[must_use]
+    pub fn with_start_row(mut self, start_row: SalesforceId) -> Self {
+        self.start_row = Some(start_row);
+        self
+    }
+
+    pub fn get_chunk_size(&self) -> Option<usize> {
+        self.chunk_size
+    }
+
+    pub fn get_header_value(&self) -> String {
+        let mut options = Vec::new();
+
+        if let Some(chunk_size) = self.chunk_size {
+            options.push(format!("chunkSize={}", chunk_size));
+        }
+        if let Some(parent) = &self.parent {
+            options.push(format!("parent={}", parent));
+        }
+        if let Some(start_row) = &self.start_row {
+            options.push(format!("startRow={}", start_row));
+        }
+
+        if options.is_empty() {
+            "true".to_owned()
+        } else {
+            options.join("; ")
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub enum BulkV1JobState {
+    Open,
+    Closed,
+    Aborted,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub enum BulkBatchState {
+    Queued,
+    InProgress,
+    Completed,
+    Failed,
+    /// The batch will not be processed. Salesforce places the batch holding
+    /// the original query in this state once PK chunking has split it.
+    NotProcessed,
+}
+
+impl BulkBatchState {
+    pub fn is_completed_state(&self) -> bool {
+        !matches!(self, Self::Queued | Self::InProgress)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkV1QueryJob {
+    pub id: SalesforceId,
+    pub operation: BulkQueryOperation,
+    pub object: String,
+    pub created_by_id: SalesforceId,
+    pub created_date: DateTime,
+    pub state: BulkV1JobState,
+    #[serde(default)]
+    pub number_batches_total: usize,
+    #[serde(default)]
+    pub number_batches_completed: usize,
+    #[serde(default)]
+    pub number_batches_failed: usize,
+    #[serde(default)]
+    pub number_records_processed: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkBatch {
+    pub id: SalesforceId,
+    pub job_id: SalesforceId,
+    pub state: BulkBatchState,
+    pub state_message: Option<String>,
+    #[serde(default)]
+    pub number_records_processed: usize,
+}
+
+struct BulkV1JobCreateRequest {
+    api_version: String,
+    operation: BulkQueryOperation,
+    object: String,
+    pk_chunking: Option<PkChunking>,
+}
+
+impl SalesforceRequest for BulkV1JobCreateRequest {
+    type ReturnValue = BulkV1QueryJob;
+
+    fn get_url(&self) -> String {
+        get_async_url(&self.api_version, "job")
+    }
+
+    fn get_method(&self) -> Method {
+        Method::POST
+    }
+
+    fn get_body(&self) -> Option<Value> {
+        Some(json!({
+            "operation": self.operation,
+            "object": self.object,
+            "contentType": "JSON",
+        }))
+    }
+
+    fn get_headers(&self) -> Option<HashMap<String, String>> {
+        self.pk_chunking.as_ref().map(|p| {
+            let mut headers = HashMap::new();
+            headers.insert(PK_CHUNKING_HEADER.to_owned(), p.get_header_value());
+            headers
+        })
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(serde_json::from_value::<Self::ReturnValue>(body.clone())?)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+
+    fn is_mutating(&self) -> bool {
+        // Creating a query job does not change data.
+        false
+    }
+}
+
+struct BulkV1JobStatusRequest {
+    api_version: String,
+    id: SalesforceId,
+    // The state to set, if any, rather than just retrieving the job.
+    state: Option<BulkV1JobState>,
+}
+
+impl SalesforceRequest for BulkV1JobStatusRequest {
+    type ReturnValue = BulkV1QueryJob;
+
+    fn get_url(&self) -> String {
+        get_async_url(&self.api_version, &format!("job/{}", self.id))
+    }
+
+    fn get_method(&self) -> Method {
+        match self.state {
+            Some(_) => Method::POST,
+            None => Method::GET,
+        }
+    }
+
+    fn get_body(&self) -> Option<Value> {
+        self.state.map(|state| json!({ "state": state }))
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(serde_json::from_value::<Self::ReturnValue>(body.clone())?)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+
+    fn is_mutating(&self) -> bool {
+        // Closing or aborting a query job does not change data.
+        false
+    }
+}
+
+struct BulkV1QueryBatchCreateRequest {
+    api_version: String,
+    job_id: SalesforceId,
+    query: String,
+}
+
+#[async_trait]
+impl SalesforceRawRequest for BulkV1QueryBatchCreateRequest {
+    type ReturnValue = BulkBatch;
+
+    fn get_body(&self) -> Option<TransportBody> {
+        Some(self.query.clone().into())
+    }
+
+    fn get_mime_type(&self) -> String {
+        "application/json".to_owned()
+    }
+
+    fn get_url(&self) -> String {
+        get_async_url(&self.api_version, &format!("job/{}/batch", self.job_id))
+    }
+
+    fn get_method(&self) -> Method {
+        Method::POST
+    }
+
+    async fn get_result(
+        &self,
+        _conn: &Connection,
+        response: Response,
+    ) -> Result<Self::ReturnValue> {
+        Ok(response.json().await?)
+    }
+
+    fn is_mutating(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BulkBatchList {
+    batch_info: Vec<BulkBatch>,
+}
+
+struct BulkV1BatchListRequest {
+    api_version: String,
+    job_id: SalesforceId,
+}
+
+impl SalesforceRequest for BulkV1BatchListRequest {
+    type ReturnValue = Vec<BulkBatch>;
+
+    fn get_url(&self) -> String {
+        get_async_url(&self.api_version, &format!("job/{}/batch", self.job_id))
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(serde_json::from_value::<BulkBatchList>(body.clone())?.batch_info)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+}
+
+struct BulkV1BatchResultListRequest {
+    api_version: String,
+    job_id: SalesforceId,
+    batch_id: SalesforceId,
+}
+
+impl SalesforceRequest for BulkV1BatchResultListRequest {
+    type ReturnValue = Vec<String>;
+
+    fn get_url(&self) -> String {
+        get_async_url(
+            &self.api_version,
+            &format!("job/{}/batch/{}/result", self.job_id, self.batch_id),
+        )
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(serde_json::from_value::<Self::ReturnValue>(body.clone())?)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+}
+
+struct BulkV1BatchResultRequest {
+    api_version: String,
+    job_id: SalesforceId,
+    batch_id: SalesforceId,
+    result_id: String,
+}
+
+impl SalesforceRequest for BulkV1BatchResultRequest {
+    type ReturnValue = Vec<Value>;
+
+    fn get_url(&self) -> String {
+        get_async_url(
+            &self.api_version,
+            &format!(
+                "job/{}/batch/{}/result/{}",
+                self.job_id, self.batch_id, self.result_id
+            ),
+        )
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(serde_json::from_value::<Self::ReturnValue>(body.clone())?)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+}
+
+impl BulkV1QueryJob {
+    /// Create a query job on `object` and add `query` to it as a batch. With
+    /// `pk_chunking`, Salesforce splits the query into batches by Id range.
+    pub async fn create(
+        conn: &Connection,
+        object: &str,
+        query: &str,
+        query_all: bool,
+        pk_chunking: Option<PkChunking>,
+    ) -> Result<Self> {
+        let job = conn
+            .execute(&BulkV1JobCreateRequest {
+                api_version: conn.api_version.clone(),
+                operation: if query_all {
+                    BulkQueryOperation::QueryAll
+                } else {
+                    BulkQueryOperation::Query
+                },
+                object: object.to_owned(),
+                pk_chunking,
+            })
+            .await?;
+
+        conn.execute_raw_request(&BulkV1QueryBatchCreateRequest {
+            api_version: conn.api_version.clone(),
+            job_id: job.id,
+            query: query.to_owned(),
+        })
+        .await?;
+
+        Ok(job)
+    }
+
+    pub async fn check_status(&self, conn: &Connection) -> Result<BulkV1QueryJob> {
+        conn.execute(&BulkV1JobStatusRequest {
+            api_version: conn.api_version.clone(),
+            id: self.id,
+            state: None,
+        })
+        .await
+    }
+
+    pub async fn close(&self, conn: &Connection) -> Result<BulkV1QueryJob> {
+        self.set_state(conn, BulkV1JobState::Closed).await
+    }
+
+    pub async fn abort(&self, conn: &Connection) -> Result<BulkV1QueryJob> {
+        self.set_state(conn, BulkV1JobState::Aborted).await
+    }
+
+    async fn set_state(&self, conn: &Connection, state: BulkV1JobState) -> Result<BulkV1QueryJob> {
+        conn.execute(&BulkV1JobStatusRequest {
+            api_version: conn.api_version.clone(),
+            id: self.id,
+            state: Some(state),
+        })
+        .await
+    }
+
+    /// The job's batches, including those Salesforce created by PK chunking.
+    pub async fn get_batches(&self, conn: &Connection) -> Result<Vec<BulkBatch>> {
+        conn.execute(&BulkV1BatchListRequest {
+            api_version: conn.api_version.clone(),
+            job_id: self.id,
+        })
+        .await
+    }
+
+    /// Wait until every batch has finished, then close the job. Returns an
+    /// error if any batch failed. Batches are polled per the Connection's
+    /// `PollingOptions`; if their timeout elapses first, `SalesforceError::Timeout`
+    /// is returned and the job is left open.
+    pub async fn complete(self, conn: &Connection) -> Result<BulkV1QueryJob> {
+        let mut poller = Poller::new(conn.get_polling_options());
+
+        loop {
+            let batches = self.get_batches(conn).await?;
+
+            if let Some(failed) = batches.iter().find(|b| b.state == BulkBatchState::Failed) {
+                return Err(SalesforceError::GeneralError(format!(
+                    "Batch {} failed: {}",
+                    failed.id,
+                    failed.state_message.clone().unwrap_or_default()
+                ))
+                .into());
+            }
+            if !batches.is_empty() && batches.iter().all(|b| b.state.is_completed_state()) {
+                return self.close(conn).await;
+            }
+
+            sleep(poller.next_delay()?).await;
+        }
+    }
+
+    /// Stream the results of all of the job's completed batches, in order.
+    pub fn get_results_stream<T>(
+        &self,
+        conn: &Connection,
+        sobject_type: &SObjectType,
+    ) -> ResultStream<T>
+    where
+        T: SObjectDeserialization + Unpin + Send + Sync + 'static,
+    {
+        ResultStream::new(
+            None,
+            Box::new(BulkV1ResultsManager {
+                job_id: self.id,
+                conn: conn.clone(),
+                sobject_type: sobject_type.clone(),
+                phantom: PhantomData,
+            }),
+        )
+    }
+}
+
+struct BulkV1ResultsManager<T: SObjectDeserialization> {
+    job_id: SalesforceId,
+    conn: Connection,
+    sobject_type: SObjectType,
+    phantom: PhantomData<T>,
+}
+
+impl<T> BulkV1ResultsManager<T>
+where
+    T: SObjectDeserialization,
+{
+    /// List the result sets of the job's completed batches, as a locator.
+    async fn get_result_locator(conn: &Connection, job_id: SalesforceId) -> Result<String> {
+        let mut results = Vec::new();
+
+        for batch in conn
+            .execute(&BulkV1BatchListRequest {
+                api_version: conn.api_version.clone(),
+                job_id,
+            })
+            .await?
+            .into_iter()
+            .filter(|b| b.state == BulkBatchState::Completed)
+        {
+            for result_id in conn
+                .execute(&BulkV1BatchResultListRequest {
+                    api_version: conn.api_version.clone(),
+                    job_id,
+                    batch_id: batch.id,
+                })
+                .await?
+            {
+                results.push(format!("{}/{}", batch.id, result_id));
+            }
+        }
+
+        Ok(results.join(","))
+    }
+}
+
+impl<T> ResultStreamManager for BulkV1ResultsManager<T>
+where
+    T: SObjectDeserialization,
+{
+    type Output = T;
+
+    fn get_next_future(
+        &mut self,
+        state: Option<ResultStreamState<T>>,
+    ) -> JoinHandle<Result<ResultStreamState<T>>> {
+        let conn = self.conn.clone();
+        let job_id = self.job_id;
+        let sobject_type = self.sobject_type.clone();
+        // The locator lists the result sets yet to be retrieved, as
+        // comma-separated `batchId/resultId` pairs.
+        let locator = state.and_then(|s| s.locator);
+
+        spawn(async move {
+            let locator = match locator {
+                Some(locator) => locator,
+                None => Self::get_result_locator(&conn, job_id).await?,
+            };
+            let (current, remaining) = match locator.split_once(',') {
+                Some((current, remaining)) => (current, Some(remaining.to_owned())),
+                None => (locator.as_str(), None),
+            };
+
+            let buffer = match current.split_once('/') {
+                Some((batch_id, result_id)) => conn
+                    .execute(&BulkV1BatchResultRequest {
+                        api_version: conn.api_version.clone(),
+                        job_id,
+                        batch_id: SalesforceId::new(batch_id)?,
+                        result_id: result_id.to_owned(),
+                    })
+                    .await?
+                    .iter()
+                    .map(|r| T::from_value(r, &sobject_type))
+                    .collect::<Result<VecDeque<T>>>()?,
+                // The job has no results.
+                None => VecDeque::new(),
+            };
+
+            let done = remaining.is_none();
+            Ok(ResultStreamState::new(buffer, remaining, None, done))
+        })
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{
+    bulk::v1::{BulkBatchState, BulkV1QueryJob, PkChunking, PK_CHUNKING_HEADER},
+    prelude::*,
+    test_integration_base::{
+        get_test_field_describe, get_test_sobject_type, serve_routes, RouteTransport,
+    },
+};
+use anyhow::Result;
+use reqwest::Method;
+use serde_json::json;
+use tokio_stream::StreamExt;
+
+#[test]
+fn test_pk_chunking_header_value() -> Result<()> {
+    assert_eq!(PkChunking::new().get_header_value(), "true");
+    assert_eq!(
+        PkChunking::new()
+            .with_chunk_size(1_000_000)
+            .with_parent("Account")
+            .get_header_value(),
+        "chunkSize=250000; parent=Account"
+    );
+
+    Ok(())
+}
+
+fn get_batch_json(id: &str, state: &str) -> serde_json::Value {
+    json!({"id": id, "jobId": "750000000000001AAA", "state": state, "numberRecordsProcessed": 1})
+}
+
+/// Serves a PK-chunked query job whose original batch was split into two.
+fn get_chunked_job_transport() -> RouteTransport {
+    let job = "/job/750000000000001AAA/batch";
+    RouteTransport::new()
+        .with_json(
+            Method::POST,
+            "/job",
+            200,
+            json!({
+                "id": "750000000000001AAA", "operation": "query", "object": "Account",
+                "createdById": "005000000000000AAA", "createdDate": "2022-01-01T00:00:00.000+0000",
+                "state": "Open"
+            }),
+        )
+        .with_json(
+            Method::POST,
+            job,
+            200,
+            get_batch_json("751000000000001AAA", "Queued"),
+        )
+        .with_json(
+            Method::GET,
+            job,
+            200,
+            json!({"batchInfo": [
+                get_batch_json("751000000000001AAA", "NotProcessed"),
+                get_batch_json("751000000000002AAA", "Completed"),
+                get_batch_json("751000000000003AAA", "Completed")
+            ]}),
+        )
+        .with_json(
+            Method::GET,
+            "/751000000000002AAA/result",
+            200,
+            json!(["752000000000002"]),
+        )
+        .with_json(
+            Method::GET,
+            "/751000000000003AAA/result",
+            200,
+            json!(["752000000000003"]),
+        )
+        .with_json(
+            Method::GET,
+            "/result/752000000000002",
+            200,
+            json!([{"attributes": {"type": "Account"}, "Name": "One"}]),
+        )
+        .with_json(
+            Method::GET,
+            "/result/752000000000003",
+            200,
+            json!([{"attributes": {"type": "Account"}, "Name": "Two"}]),
+        )
+}
+
+#[tokio::test]
+async fn test_bulk_v1_pk_chunked_query() -> Result<()> {
+    let (conn, transport) = serve_routes(get_chunked_job_transport())?;
+    let sobject_type = get_test_sobject_type(
+        "Account",
+        vec![get_test_field_describe("Name", "xsd:string", "string")],
+        vec![],
+    )?;
+
+    let job = BulkV1QueryJob::create(
+        &conn,
+        "Account",
+        "SELECT Name FROM Account",
+        false,
+        Some(PkChunking::new().with_chunk_size(100_000)),
+    )
+    .await?;
+    let batches = job.get_batches(&conn).await?;
+    assert_eq!(batches[0].state, BulkBatchState::NotProcessed);
+
+    let names: Vec<String> = job
+        .get_results_stream::<SObject>(&conn, &sobject_type)
+        .map(|r| r.map(|s| s.get("Name").unwrap().as_string()))
+        .collect::<Result<Vec<String>>>()
+        .await?;
+    assert_eq!(names, vec!["One", "Two"]);
+
+    let requests = transport.get_requests();
+    assert_eq!(requests[0].method, Method::POST);
+    assert_eq!(requests[0].path(), "/services/async/52.0/job");
+    assert_eq!(
+        requests[0].get_header(PK_CHUNKING_HEADER),
+        Some("chunkSize=100000")
+    );
+    assert!(requests
+        .iter()
+        .all(|r| r.get_header("X-SFDC-Session").is_some()));
+    assert_eq!(requests.len(), 8);
+
+    Ok(())
+}
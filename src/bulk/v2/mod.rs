@@ -1,28 +1,42 @@
+use async_stream::try_stream;
 use async_trait::async_trait;
-use bytes::{BufMut, Bytes, BytesMut};
+use bytes::Bytes;
+use futures::future::Either;
 use futures::Stream;
-use reqwest::{Body, Method, Response};
+use reqwest::{Method, Response};
 use serde::Serialize;
-use serde_derive::{Deserialize, Serialize};
+use serde_derive::Deserialize;
+use std::collections::HashMap;
+use std::future::Future;
 use std::marker::PhantomData;
 use std::pin::Pin;
-use std::sync::RwLock;
-use std::{collections::HashMap, time::Duration};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use std::time::Instant;
 use tokio_stream::StreamExt;
 
 use anyhow::Result;
-use csv_async::AsyncDeserializer;
+use csv_async::{AsyncReaderBuilder, AsyncSerializer, AsyncWriterBuilder, ByteRecord, Terminator};
+use log::warn;
 use serde_json::{json, Map, Value};
 use std::collections::VecDeque;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::task::{spawn, JoinHandle};
 use tokio::time::sleep;
-use tokio_util::io::StreamReader;
+use tokio_util::io::{ReaderStream, StreamReader};
+use tokio_util::sync::CancellationToken;
 
+use crate::api::transport::TransportBody;
 use crate::{
+    api::concurrency::is_throttling_error,
+    api::metrics::MetricsRecorder,
+    api::polling::{Poller, PollingOptions},
     api::Connection,
-    api::{SalesforceRawRequest, SalesforceRequest},
+    api::{is_client_error, AssignmentRule, SalesforceRawRequest, SalesforceRequest},
     data::traits::{SObjectDeserialization, SObjectSerialization},
     data::DateTime,
+    data::FieldValue,
+    data::SObject,
     data::SObjectType,
     data::SalesforceId,
     errors::SalesforceError,
@@ -35,9 +49,7 @@ pub mod traits;
 #[cfg(test)]
 mod test;
 
-const POLL_INTERVAL: u64 = 10;
-
-#[derive(Serialize, Deserialize, PartialEq)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
 pub enum BulkJobStatus {
     Open,
     UploadComplete,
@@ -53,20 +65,80 @@ impl BulkJobStatus {
     }
 }
 
-#[derive(Serialize, Deserialize, PartialEq)]
+/// The progress of a Bulk API job, reported each time its status is polled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JobProgress {
+    pub id: SalesforceId,
+    pub state: BulkJobStatus,
+    pub records_processed: u64,
+    /// The number of records that failed. Always zero for query jobs.
+    pub records_failed: u64,
+}
+
+/// Poll a job with `check_status`, per `options`, until it reaches a completed
+/// state, reporting each status to `progress`. If `cancel` is cancelled first,
+/// `abort` the job and return `SalesforceError::Cancelled`.
+async fn poll_until_complete<J, S, SF, A, AF>(
+    check_status: S,
+    abort: A,
+    get_progress: impl Fn(&J) -> JobProgress,
+    progress: impl Fn(JobProgress),
+    cancel: &CancellationToken,
+    options: PollingOptions,
+) -> Result<J>
+where
+    S: Fn() -> SF,
+    SF: Future<Output = Result<J>>,
+    A: FnOnce() -> AF,
+    AF: Future<Output = Result<J>>,
+{
+    let mut poller = Poller::new(options);
+
+    loop {
+        if cancel.is_cancelled() {
+            abort().await?;
+            return Err(SalesforceError::Cancelled.into());
+        }
+
+        let status = check_status().await?;
+        let current = get_progress(&status);
+        progress(current);
+
+        if current.state.is_completed_state() {
+            return Ok(status);
+        }
+
+        let delay = poller.next_delay()?;
+        tokio::select! {
+            _ = sleep(delay) => {}
+            _ = cancel.cancelled() => {}
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
 #[serde(rename_all = "camelCase")]
 pub enum BulkQueryOperation {
     Query,
     QueryAll,
 }
 
-#[derive(Serialize, Deserialize, PartialEq)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
 pub enum BulkApiLineEnding {
     LF,
     CRLF,
 }
 
-#[derive(Serialize, Deserialize, PartialEq)]
+impl BulkApiLineEnding {
+    fn get_terminator(&self) -> Terminator {
+        match self {
+            BulkApiLineEnding::LF => Terminator::Any(b'\n'),
+            BulkApiLineEnding::CRLF => Terminator::CRLF,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum BulkApiColumnDelimiter {
     Backquote,
@@ -77,18 +149,219 @@ pub enum BulkApiColumnDelimiter {
     Tab,
 }
 
-#[derive(Serialize, Deserialize, PartialEq)]
+impl BulkApiColumnDelimiter {
+    pub fn get_delimiter(&self) -> u8 {
+        match self {
+            BulkApiColumnDelimiter::Backquote => b'`',
+            BulkApiColumnDelimiter::Caret => b'^',
+            BulkApiColumnDelimiter::Comma => b',',
+            BulkApiColumnDelimiter::Pipe => b'|',
+            BulkApiColumnDelimiter::Semicolon => b';',
+            BulkApiColumnDelimiter::Tab => b'\t',
+        }
+    }
+}
+
+/// The value with which the Bulk API sets a field to null. An empty
+/// value leaves the field unchanged.
+pub const BULK_NULL_VALUE: &str = "#N/A";
+
+/// The delimiter and line ending of a job's CSV data. Salesforce uses a
+/// job's format both for the data uploaded to it and for the results it
+/// returns. The default is comma-delimited with LF line endings.
+///
+/// If `nulls_as_na` is set, null values are written as `#N/A`, so that
+/// they clear fields rather than leave them unchanged.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct BulkCsvFormat {
+    pub column_delimiter: BulkApiColumnDelimiter,
+    pub line_ending: BulkApiLineEnding,
+    pub nulls_as_na: bool,
+}
+
+impl Default for BulkCsvFormat {
+    fn default() -> Self {
+        BulkCsvFormat {
+            column_delimiter: BulkApiColumnDelimiter::Comma,
+            line_ending: BulkApiLineEnding::LF,
+            nulls_as_na: false,
+        }
+    }
+}
+
+impl BulkCsvFormat {
+    pub fn new(
+        column_delimiter: BulkApiColumnDelimiter,
+        line_ending: BulkApiLineEnding,
+    ) -> BulkCsvFormat {
+        BulkCsvFormat {
+            column_delimiter,
+            line_ending,
+            nulls_as_na: false,
+        }
+    }
+
+    #[must_use]
+    pub fn with_nulls_as_na(mut self, nulls_as_na: bool) -> Self {
+        self.nulls_as_na = nulls_as_na;
+        self
+    }
+
+    fn from_options(
+        column_delimiter: Option<BulkApiColumnDelimiter>,
+        line_ending: Option<BulkApiLineEnding>,
+    ) -> BulkCsvFormat {
+        let default = BulkCsvFormat::default();
+
+        BulkCsvFormat {
+            column_delimiter: column_delimiter.unwrap_or(default.column_delimiter),
+            line_ending: line_ending.unwrap_or(default.line_ending),
+            nulls_as_na: default.nulls_as_na,
+        }
+    }
+
+    pub(crate) fn get_writer_builder(&self, has_headers: bool) -> AsyncWriterBuilder {
+        let mut builder = AsyncWriterBuilder::new();
+        builder
+            .delimiter(self.column_delimiter.get_delimiter())
+            .terminator(self.line_ending.get_terminator())
+            .has_headers(has_headers);
+        builder
+    }
+
+    // Readers accept either line ending.
+    pub(crate) fn get_reader_builder(&self) -> AsyncReaderBuilder {
+        let mut builder = AsyncReaderBuilder::new();
+        builder.delimiter(self.column_delimiter.get_delimiter());
+        builder
+    }
+}
+
+/// Options for creating a `BulkDmlJob`: the format of its CSV data, the
+/// external Id field for upserts, and the assignment rule to run.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct BulkJobOptions {
+    csv_format: BulkCsvFormat,
+    external_id_field_name: Option<String>,
+    assignment_rule: Option<AssignmentRule>,
+}
+
+impl BulkJobOptions {
+    pub fn new() -> BulkJobOptions {
+        BulkJobOptions::default()
+    }
+
+    #[must_use]
+    pub fn with_csv_format(mut self, csv_format: BulkCsvFormat) -> Self {
+        self.csv_format = csv_format;
+        self
+    }
+
+    #[must_use]
+    pub fn with_column_delimiter(mut self, column_delimiter: BulkApiColumnDelimiter) -> Self {
+        self.csv_format.column_delimiter = column_delimiter;
+        self
+    }
+
+    #[must_use]
+    pub fn with_line_ending(mut self, line_ending: BulkApiLineEnding) -> Self {
+        self.csv_format.line_ending = line_ending;
+        self
+    }
+
+    /// Write null values as `#N/A`, so that they clear fields.
+    #[must_use]
+    pub fn with_nulls_as_na(mut self, nulls_as_na: bool) -> Self {
+        self.csv_format.nulls_as_na = nulls_as_na;
+        self
+    }
+
+    #[must_use]
+    pub fn with_external_id_field_name(mut self, external_id_field_name: &str) -> Self {
+        self.external_id_field_name = Some(external_id_field_name.to_owned());
+        self
+    }
+
+    /// Run `assignment_rule`, which must be `AssignmentRule::Specific` or
+    /// `AssignmentRule::Disabled`. If unset, the Connection's default assignment
+    /// rule is used.
+    #[must_use]
+    pub fn with_assignment_rule(mut self, assignment_rule: AssignmentRule) -> Self {
+        self.assignment_rule = Some(assignment_rule);
+        self
+    }
+
+    pub fn get_csv_format(&self) -> BulkCsvFormat {
+        self.csv_format
+    }
+
+    pub fn get_external_id_field_name(&self) -> Option<&str> {
+        self.external_id_field_name.as_deref()
+    }
+
+    pub fn get_assignment_rule(&self) -> Option<AssignmentRule> {
+        self.assignment_rule
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
 pub enum BulkApiConcurrencyMode {
     // This type uses uppercase, so no serde-renaming required.
     Parallel,
 }
 
-#[derive(Serialize, Deserialize, PartialEq)]
+/// The format of Bulk API job data. CSV is the default and the only format
+/// Salesforce currently accepts for Bulk API 2.0; query jobs requesting another
+/// format fall back to CSV if Salesforce rejects it.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
 pub enum BulkApiContentType {
     CSV,
+    JSON,
+}
+
+impl BulkApiContentType {
+    pub fn get_mime_type(&self) -> &'static str {
+        match self {
+            BulkApiContentType::CSV => "text/csv",
+            BulkApiContentType::JSON => "application/json",
+        }
+    }
+
+    /// Decode a page of query results in this format. CSV results are
+    /// read in `csv_format`.
+    pub(crate) async fn decode_records<T>(
+        &self,
+        content: &[u8],
+        sobject_type: &SObjectType,
+        csv_format: BulkCsvFormat,
+    ) -> Result<VecDeque<T>>
+    where
+        T: SObjectDeserialization,
+    {
+        match self {
+            BulkApiContentType::CSV => {
+                let mut deserializer = csv_format.get_reader_builder().create_deserializer(content);
+                let mut rows = deserializer.deserialize::<HashMap<String, String>>();
+                let mut records = VecDeque::new();
+
+                while let Some(row) = rows.next().await {
+                    records.push_back(T::from_value(
+                        &value_from_csv(&row?, sobject_type)?,
+                        sobject_type,
+                    )?);
+                }
+
+                Ok(records)
+            }
+            BulkApiContentType::JSON => serde_json::from_slice::<Vec<Value>>(content)?
+                .iter()
+                .map(|r| T::from_value(r, sobject_type))
+                .collect(),
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct BulkQueryJob {
     id: SalesforceId,
@@ -103,12 +376,16 @@ pub struct BulkQueryJob {
     api_version: f32,
     line_ending: BulkApiLineEnding,
     column_delimiter: BulkApiColumnDelimiter,
+    // Returned only when retrieving the job's status.
+    number_records_processed: Option<u64>,
 }
 
 const RESULTS_CHUNK_SIZE: usize = 2000;
 
 struct BulkQueryLocatorManager<T: SObjectDeserialization> {
     job_id: SalesforceId,
+    content_type: BulkApiContentType,
+    csv_format: BulkCsvFormat,
     conn: Connection,
     sobject_type: SObjectType,
     phantom: PhantomData<T>,
@@ -127,6 +404,8 @@ where
         let conn = self.conn.clone();
         let sobject_type = self.sobject_type.clone();
         let job_id = self.job_id;
+        let content_type = self.content_type;
+        let csv_format = self.csv_format;
         let mut locator = None;
 
         if let Some(state) = state {
@@ -144,12 +423,9 @@ where
                 ))
                 .await?;
 
-            // Ingest the CSV records
-            // TODO: respect this job's settings for delimiter.
-            let buffer = csv::Reader::from_reader(&*result.content)
-                .into_deserialize::<HashMap<String, String>>()
-                .map(|r| T::from_value(&value_from_csv(&r?, &sobject_type)?, &sobject_type))
-                .collect::<Result<VecDeque<T>>>()?;
+            let buffer = content_type
+                .decode_records(&result.content, &sobject_type, csv_format)
+                .await?;
 
             let done = result.locator.is_none();
             Ok(ResultStreamState {
@@ -157,19 +433,34 @@ where
                 locator: result.locator,
                 total_size: None, // TODO
                 done,
+                bytes: result.content.len(),
             })
         })
     }
 }
 
 #[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
 struct BulkQueryJobCreateRequest {
     operation: BulkQueryOperation,
     query: String,
+    content_type: BulkApiContentType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column_delimiter: Option<BulkApiColumnDelimiter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line_ending: Option<BulkApiLineEnding>,
 }
 
 impl BulkQueryJobCreateRequest {
     pub fn new(query: String, query_all: bool) -> Self {
+        Self::new_with_content_type(query, query_all, BulkApiContentType::CSV)
+    }
+
+    pub fn new_with_content_type(
+        query: String,
+        query_all: bool,
+        content_type: BulkApiContentType,
+    ) -> Self {
         Self {
             query,
             operation: if query_all {
@@ -177,8 +468,18 @@ impl BulkQueryJobCreateRequest {
             } else {
                 BulkQueryOperation::Query
             },
+            content_type,
+            column_delimiter: None,
+            line_ending: None,
         }
     }
+
+    #[must_use]
+    pub fn with_csv_format(mut self, csv_format: BulkCsvFormat) -> Self {
+        self.column_delimiter = Some(csv_format.column_delimiter);
+        self.line_ending = Some(csv_format.line_ending);
+        self
+    }
 }
 
 impl SalesforceRequest for BulkQueryJobCreateRequest {
@@ -207,6 +508,11 @@ impl SalesforceRequest for BulkQueryJobCreateRequest {
             Err(SalesforceError::ResponseBodyExpected.into())
         }
     }
+
+    fn is_mutating(&self) -> bool {
+        // Creating a query job does not change data.
+        false
+    }
 }
 
 #[derive(Serialize)]
@@ -244,11 +550,204 @@ impl SalesforceRequest for BulkQueryJobStatusRequest {
     }
 }
 
+struct BulkQueryJobAbortRequest {
+    id: SalesforceId,
+}
+
+impl BulkQueryJobAbortRequest {
+    pub fn new(id: SalesforceId) -> Self {
+        Self { id }
+    }
+}
+
+impl SalesforceRequest for BulkQueryJobAbortRequest {
+    type ReturnValue = BulkQueryJob;
+
+    fn get_url(&self) -> String {
+        format!("jobs/query/{}", self.id)
+    }
+
+    fn get_method(&self) -> Method {
+        Method::PATCH
+    }
+
+    fn get_body(&self) -> Option<Value> {
+        Some(json!({"state": BulkJobStatus::Aborted}))
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(serde_json::from_value::<Self::ReturnValue>(body.clone())?)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+
+    fn is_mutating(&self) -> bool {
+        // Aborting a query job does not change data.
+        false
+    }
+}
+
+struct BulkQueryJobDeleteRequest {
+    id: SalesforceId,
+}
+
+impl BulkQueryJobDeleteRequest {
+    pub fn new(id: SalesforceId) -> Self {
+        Self { id }
+    }
+}
+
+impl SalesforceRequest for BulkQueryJobDeleteRequest {
+    type ReturnValue = ();
+
+    fn get_url(&self) -> String {
+        format!("jobs/query/{}", self.id)
+    }
+
+    fn get_method(&self) -> Method {
+        Method::DELETE
+    }
+
+    fn get_result(&self, _conn: &Connection, _body: Option<&Value>) -> Result<Self::ReturnValue> {
+        // HTTP errors handled by the Connection; no body.
+        Ok(())
+    }
+
+    fn is_mutating(&self) -> bool {
+        // Deleting a query job removes only its results, not data.
+        false
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkQueryJobListResponse {
+    pub done: bool,
+    pub records: Vec<BulkQueryJob>,
+    pub next_records_url: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkQueryJobListRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_pk_chunking_enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    job_type: Option<BulkApiJobType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query_locator: Option<String>,
+}
+
+impl BulkQueryJobListRequest {
+    pub fn new(
+        is_pk_chunking_enabled: Option<bool>,
+        job_type: Option<BulkApiJobType>,
+        query_locator: Option<String>,
+    ) -> Self {
+        Self {
+            is_pk_chunking_enabled,
+            job_type,
+            query_locator,
+        }
+    }
+}
+
+impl SalesforceRequest for BulkQueryJobListRequest {
+    type ReturnValue = BulkQueryJobListResponse;
+
+    fn get_url(&self) -> String {
+        "jobs/query".to_string()
+    }
+
+    fn get_query_parameters(&self) -> Option<Value> {
+        serde_json::to_value(self).ok()
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(serde_json::from_value::<Self::ReturnValue>(body.clone())?)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+}
+
 struct BulkQueryJobResultsResponse {
     locator: Option<String>,
     content: Bytes,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct BulkQueryResultPage {
+    result_link: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct BulkQueryJobResultPagesResponse {
+    result_pages: Vec<BulkQueryResultPage>,
+    next_records_url: Option<String>,
+}
+
+/// Lists links to ranges of a completed query job's results, each of which
+/// can be retrieved independently. Requires API version 63.0 or later.
+struct BulkQueryJobResultPagesRequest {
+    url: String,
+}
+
+impl SalesforceRequest for BulkQueryJobResultPagesRequest {
+    type ReturnValue = BulkQueryJobResultPagesResponse;
+
+    fn get_url(&self) -> String {
+        self.url.clone()
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(serde_json::from_value::<Self::ReturnValue>(body.clone())?)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+}
+
+/// Retrieves one range of results from a link returned by `BulkQueryJobResultPagesRequest`.
+struct BulkQueryResultPageRequest {
+    result_link: String,
+}
+
+#[async_trait]
+impl SalesforceRawRequest for BulkQueryResultPageRequest {
+    type ReturnValue = Bytes;
+
+    fn get_url(&self) -> String {
+        self.result_link.clone()
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn get_result(
+        &self,
+        _conn: &Connection,
+        response: Response,
+    ) -> Result<Self::ReturnValue> {
+        Ok(response.bytes().await?)
+    }
+}
+
 struct BulkQueryJobResultsRequest {
     id: SalesforceId,
     locator: Option<String>,
@@ -325,31 +824,206 @@ impl BulkQueryJob {
             .await?)
     }
 
-    pub async fn abort(&self, _conn: &Connection) -> Result<()> {
-        todo!();
-    }
-
-    // TODO: should this take `&mut self` and replace self, returning Result<()>?
-    pub async fn check_status(&self, conn: &Connection) -> Result<BulkQueryJob> {
-        Ok(conn
-            .execute(&BulkQueryJobStatusRequest::new(self.id))
-            .await?)
-    }
-
-    pub async fn complete(self, conn: &Connection) -> Result<BulkQueryJob> {
-        loop {
-            let status: BulkQueryJob = self.check_status(conn).await?;
+    /// Create a job whose results are returned as `content_type`. If Salesforce
+    /// rejects that format, the job is created with CSV instead; check
+    /// `get_content_type()` for the format in use. Results streams always decode
+    /// the job's actual format.
+    pub async fn create_with_content_type(
+        conn: &Connection,
+        query: &str,
+        query_all: bool,
+        content_type: BulkApiContentType,
+    ) -> Result<Self> {
+        let result = conn
+            .execute(&BulkQueryJobCreateRequest::new_with_content_type(
+                query.to_owned(),
+                query_all,
+                content_type,
+            ))
+            .await;
 
-            if status.state.is_completed_state() {
-                return Ok(status);
+        match result {
+            Err(e) if content_type != BulkApiContentType::CSV && is_client_error(&e) => {
+                BulkQueryJob::create(conn, query, query_all).await
             }
-
-            sleep(Duration::from_secs(POLL_INTERVAL)).await;
+            result => result,
         }
     }
 
-    pub async fn get_results_stream<T>(
-        &self,
+    /// Create a job whose CSV results are delimited as `csv_format` specifies.
+    pub async fn create_with_csv_format(
+        conn: &Connection,
+        query: &str,
+        query_all: bool,
+        csv_format: BulkCsvFormat,
+    ) -> Result<Self> {
+        conn.execute(
+            &BulkQueryJobCreateRequest::new(query.to_owned(), query_all)
+                .with_csv_format(csv_format),
+        )
+        .await
+    }
+
+    pub async fn get(conn: &Connection, id: SalesforceId) -> Result<Self> {
+        conn.execute(&BulkQueryJobStatusRequest::new(id)).await
+    }
+
+    pub fn get_id(&self) -> SalesforceId {
+        self.id
+    }
+
+    pub fn get_object(&self) -> &str {
+        &self.object
+    }
+
+    pub fn get_operation(&self) -> BulkQueryOperation {
+        self.operation
+    }
+
+    pub fn get_content_type(&self) -> BulkApiContentType {
+        self.content_type
+    }
+
+    pub fn get_state(&self) -> BulkJobStatus {
+        self.state
+    }
+
+    pub fn get_csv_format(&self) -> BulkCsvFormat {
+        BulkCsvFormat::new(self.column_delimiter, self.line_ending)
+    }
+
+    /// List the org's query jobs, including those created by other clients.
+    /// Pass the `next_records_url` of a response as `query_locator` to
+    /// retrieve the next page.
+    pub async fn list(
+        conn: &Connection,
+        is_pk_chunking_enabled: Option<bool>,
+        job_type: Option<BulkApiJobType>,
+        query_locator: Option<String>,
+    ) -> Result<BulkQueryJobListResponse> {
+        conn.execute(&BulkQueryJobListRequest::new(
+            is_pk_chunking_enabled,
+            job_type,
+            query_locator,
+        ))
+        .await
+    }
+
+    pub async fn abort(&self, conn: &Connection) -> Result<BulkQueryJob> {
+        conn.execute(&BulkQueryJobAbortRequest::new(self.id)).await
+    }
+
+    /// Delete the job and its results. The job must be complete, aborted, or failed.
+    pub async fn delete(&self, conn: &Connection) -> Result<()> {
+        conn.execute(&BulkQueryJobDeleteRequest::new(self.id)).await
+    }
+
+    /// Stream the job's CSV results without decoding them: the bytes of each
+    /// results page in turn, with the header row of every page after the
+    /// first removed. Suitable for writing an export directly to a file.
+    /// Fails if the job's results are not CSV.
+    pub fn stream_raw_csv(
+        &self,
+        conn: &Connection,
+    ) -> Pin<Box<dyn Stream<Item = Result<Bytes>> + Send>> {
+        let conn = conn.clone();
+        let id = self.id;
+        let content_type = self.content_type;
+
+        Box::pin(try_stream! {
+            if content_type != BulkApiContentType::CSV {
+                Err(SalesforceError::GeneralError(format!(
+                    "Job {} returns {:?} results, not CSV",
+                    id, content_type
+                )))?;
+            }
+
+            let mut locator = None;
+            let mut first = true;
+
+            loop {
+                let page = conn
+                    .execute_raw_request(&BulkQueryJobResultsRequest::new(
+                        id,
+                        locator.take(),
+                        RESULTS_CHUNK_SIZE,
+                    ))
+                    .await?;
+
+                let content = if first {
+                    page.content
+                } else {
+                    strip_header_row(page.content)
+                };
+                first = false;
+                if !content.is_empty() {
+                    yield content;
+                }
+
+                match page.locator {
+                    Some(next) => locator = Some(next),
+                    None => break,
+                }
+            }
+        })
+    }
+
+    // TODO: should this take `&mut self` and replace self, returning Result<()>?
+    pub async fn check_status(&self, conn: &Connection) -> Result<BulkQueryJob> {
+        Ok(conn
+            .execute(&BulkQueryJobStatusRequest::new(self.id))
+            .await?)
+    }
+
+    pub async fn complete(self, conn: &Connection) -> Result<BulkQueryJob> {
+        self.complete_with_progress(conn, |_| {}).await
+    }
+
+    /// Wait for the job to complete, passing its progress to `progress`
+    /// each time its status is polled.
+    pub async fn complete_with_progress(
+        self,
+        conn: &Connection,
+        progress: impl Fn(JobProgress),
+    ) -> Result<BulkQueryJob> {
+        self.complete_with_cancellation(conn, progress, &CancellationToken::new())
+            .await
+    }
+
+    /// Wait for the job to complete, as `complete_with_progress()`. If `cancel`
+    /// is cancelled first, the job is aborted and `SalesforceError::Cancelled`
+    /// is returned.
+    ///
+    /// The job is polled per the Connection's `PollingOptions`. If their timeout
+    /// elapses first, `SalesforceError::Timeout` is returned, and the job continues.
+    pub async fn complete_with_cancellation(
+        self,
+        conn: &Connection,
+        progress: impl Fn(JobProgress),
+        cancel: &CancellationToken,
+    ) -> Result<BulkQueryJob> {
+        poll_until_complete(
+            || self.check_status(conn),
+            || self.abort(conn),
+            BulkQueryJob::get_progress,
+            progress,
+            cancel,
+            conn.get_polling_options(),
+        )
+        .await
+    }
+
+    pub fn get_progress(&self) -> JobProgress {
+        JobProgress {
+            id: self.id,
+            state: self.state,
+            records_processed: self.number_records_processed.unwrap_or(0),
+            records_failed: 0,
+        }
+    }
+
+    pub async fn get_results_stream<T>(
+        &self,
         conn: &Connection,
         sobject_type: &SObjectType,
     ) -> ResultStream<T>
@@ -360,12 +1034,114 @@ impl BulkQueryJob {
             None,
             Box::new(BulkQueryLocatorManager {
                 job_id: self.id,
+                content_type: self.content_type,
+                csv_format: self.get_csv_format(),
+                sobject_type: sobject_type.clone(),
+                conn: conn.clone(),
+                phantom: PhantomData,
+            }),
+        )
+    }
+
+    /// Resume retrieving results at `locator`, such as the locator carried by
+    /// a `SalesforceError::ResultPageError`.
+    pub fn resume_results_stream<T>(
+        &self,
+        conn: &Connection,
+        sobject_type: &SObjectType,
+        locator: String,
+    ) -> ResultStream<T>
+    where
+        T: SObjectDeserialization + Unpin + Send + Sync + 'static,
+    {
+        ResultStream::new(
+            Some(ResultStreamState::new(
+                VecDeque::new(),
+                Some(locator),
+                None,
+                false,
+            )),
+            Box::new(BulkQueryLocatorManager {
+                job_id: self.id,
+                content_type: self.content_type,
+                csv_format: self.get_csv_format(),
                 sobject_type: sobject_type.clone(),
                 conn: conn.clone(),
                 phantom: PhantomData,
             }),
         )
     }
+
+    /// Stream the results of a completed job, retrieving up to `workers` ranges
+    /// of results at once. Records are yielded as their ranges arrive, so their
+    /// order is not guaranteed. Requires API version 63.0 or later.
+    ///
+    /// If the Connection has adaptive concurrency, it also admits each range,
+    /// so fewer than `workers` may be retrieved at once.
+    pub fn get_results_stream_parallel<T>(
+        &self,
+        conn: &Connection,
+        sobject_type: &SObjectType,
+        workers: usize,
+    ) -> Pin<Box<dyn Stream<Item = Result<T>> + Send>>
+    where
+        T: SObjectDeserialization + Unpin + Send + Sync + 'static,
+    {
+        let conn = conn.clone();
+        let sobject_type = sobject_type.clone();
+        let content_type = self.content_type;
+        let csv_format = self.get_csv_format();
+        let controller = conn.get_concurrency_controller();
+        let mut url = Some(format!("jobs/query/{}/resultPages", self.id));
+
+        Box::pin(try_stream! {
+            let mut links = Vec::new();
+            while let Some(current) = url {
+                let pages = conn
+                    .execute(&BulkQueryJobResultPagesRequest { url: current })
+                    .await?;
+                links.extend(pages.result_pages.into_iter().map(|p| p.result_link));
+                url = pages.next_records_url;
+            }
+
+            let mut ranges = futures::StreamExt::buffer_unordered(
+                tokio_stream::iter(links).map(|result_link| {
+                    let conn = conn.clone();
+                    let sobject_type = sobject_type.clone();
+                    let controller = controller.clone();
+
+                    async move {
+                        let permit = match &controller {
+                            Some(controller) => Some(controller.acquire().await),
+                            None => None,
+                        };
+                        let start = Instant::now();
+                        let content = conn
+                            .execute_raw_request(&BulkQueryResultPageRequest { result_link })
+                            .await;
+                        if let Some(controller) = &controller {
+                            controller.record(
+                                start.elapsed(),
+                                content.as_ref().err().is_some_and(is_throttling_error),
+                            );
+                        }
+                        drop(permit);
+
+                        content_type
+                            .decode_records::<T>(&content?, &sobject_type, csv_format)
+                            .await
+                    }
+                }),
+                workers.max(1),
+            );
+
+            while let Some(records) = ranges.next().await {
+                for record in records? {
+                    yield record;
+                }
+            }
+        })
+    }
 }
 
 // Bulk API DML support
@@ -400,7 +1176,8 @@ impl SalesforceRequest for BulkDmlJobStatusRequest {
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound(serialize = "", deserialize = ""))]
 pub struct BulkDmlResult<T>
 where
     T: SObjectDeserialization,
@@ -411,6 +1188,7 @@ where
     pub id: SalesforceId,
     #[serde(flatten)]
     data: Value,
+    #[serde(skip)]
     phantom: PhantomData<T>,
 }
 
@@ -428,9 +1206,23 @@ where
     T: SObjectDeserialization,
 {
     id: SalesforceId,
+    csv_format: BulkCsvFormat,
     phantom: PhantomData<T>,
 }
 
+impl<T> BulkDmlJobSuccessfulRecordsRequest<T>
+where
+    T: SObjectDeserialization,
+{
+    pub fn new(id: SalesforceId, csv_format: BulkCsvFormat) -> Self {
+        Self {
+            id,
+            csv_format,
+            phantom: PhantomData,
+        }
+    }
+}
+
 #[async_trait]
 impl<T> SalesforceRawRequest for BulkDmlJobSuccessfulRecordsRequest<T>
 where
@@ -446,27 +1238,210 @@ where
         Method::GET
     }
 
-    // TODO: delimiter settings?
     async fn get_result(
         &self,
         _conn: &Connection,
         response: Response,
     ) -> Result<Self::ReturnValue> {
         Ok(Box::pin(
-            AsyncDeserializer::from_reader(StreamReader::new(
+            self.csv_format
+                .get_reader_builder()
+                .create_deserializer(StreamReader::new(response.bytes_stream().map(|b| {
+                    b.map_err(|e| tokio::io::Error::new(tokio::io::ErrorKind::Other, e))
+                })))
+                .into_deserialize::<BulkDmlResult<T>>()
+                .map(|r| r.map_err(|e| e.into())),
+        ))
+    }
+}
+
+type CsvRowStream = Pin<Box<dyn Stream<Item = Result<HashMap<String, String>>>>>;
+
+/// Read each row of a CSV response body as it arrives.
+fn read_csv_response_rows(response: Response, csv_format: BulkCsvFormat) -> CsvRowStream {
+    Box::pin(
+        csv_format
+            .get_reader_builder()
+            .create_deserializer(StreamReader::new(
                 response
                     .bytes_stream()
-                    .map(|b| b.map_err(|e| tokio::io::Error::new(tokio::io::ErrorKind::Other, e))),
+                    .map(|b| b.map_err(tokio::io::Error::other)),
             ))
-            .into_deserialize::<BulkDmlResult<T>>()
+            .into_deserialize::<HashMap<String, String>>()
             .map(|r| r.map_err(|e| e.into())),
+    )
+}
+
+/// A record that a Bulk API job failed to process, with its original data.
+#[derive(Debug, Clone)]
+pub struct BulkDmlFailedResult<T>
+where
+    T: SObjectDeserialization,
+{
+    /// The record's Id, if it had one, such as in an update.
+    pub id: Option<SalesforceId>,
+    /// The error, such as `REQUIRED_FIELD_MISSING:Required fields are missing: [Name]:Name --`.
+    pub error: String,
+    row: HashMap<String, String>,
+    phantom: PhantomData<T>,
+}
+
+impl<T> BulkDmlFailedResult<T>
+where
+    T: SObjectDeserialization,
+{
+    fn from_row(mut row: HashMap<String, String>) -> Result<Self> {
+        let id = match row.remove("sf__Id") {
+            Some(id) if !id.is_empty() => Some(SalesforceId::try_from(id)?),
+            _ => None,
+        };
+        let error = row.remove("sf__Error").ok_or_else(|| {
+            SalesforceError::GeneralError("Failed result has no sf__Error column".to_owned())
+        })?;
+
+        Ok(BulkDmlFailedResult {
+            id,
+            error,
+            row,
+            phantom: PhantomData,
+        })
+    }
+
+    pub fn get_sobject(&self, sobject_type: &SObjectType) -> Result<T> {
+        T::from_value(&value_from_csv(&self.row, sobject_type)?, sobject_type)
+    }
+
+    /// The status code that begins the error, such as `REQUIRED_FIELD_MISSING`.
+    pub fn get_error_code(&self) -> &str {
+        self.error.split(':').next().unwrap_or_default()
+    }
+}
+
+/// A record that a Bulk API job did not process, because the job was
+/// aborted or failed before reaching it.
+#[derive(Debug, Clone)]
+pub struct BulkDmlUnprocessedRecord<T>
+where
+    T: SObjectDeserialization,
+{
+    row: HashMap<String, String>,
+    phantom: PhantomData<T>,
+}
+
+impl<T> BulkDmlUnprocessedRecord<T>
+where
+    T: SObjectDeserialization,
+{
+    pub fn get_sobject(&self, sobject_type: &SObjectType) -> Result<T> {
+        T::from_value(&value_from_csv(&self.row, sobject_type)?, sobject_type)
+    }
+}
+
+pub type BulkDmlFailedResultStream<T> = Pin<Box<dyn Stream<Item = Result<BulkDmlFailedResult<T>>>>>;
+pub type BulkDmlUnprocessedRecordStream<T> =
+    Pin<Box<dyn Stream<Item = Result<BulkDmlUnprocessedRecord<T>>>>>;
+
+pub struct BulkDmlJobFailedRecordsRequest<T>
+where
+    T: SObjectDeserialization,
+{
+    id: SalesforceId,
+    csv_format: BulkCsvFormat,
+    phantom: PhantomData<T>,
+}
+
+impl<T> BulkDmlJobFailedRecordsRequest<T>
+where
+    T: SObjectDeserialization,
+{
+    pub fn new(id: SalesforceId, csv_format: BulkCsvFormat) -> Self {
+        Self {
+            id,
+            csv_format,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T> SalesforceRawRequest for BulkDmlJobFailedRecordsRequest<T>
+where
+    T: SObjectDeserialization,
+{
+    type ReturnValue = BulkDmlFailedResultStream<T>;
+
+    fn get_url(&self) -> String {
+        format!("jobs/ingest/{}/failedResults", self.id)
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn get_result(
+        &self,
+        _conn: &Connection,
+        response: Response,
+    ) -> Result<Self::ReturnValue> {
+        Ok(Box::pin(
+            read_csv_response_rows(response, self.csv_format)
+                .map(|row| BulkDmlFailedResult::from_row(row?)),
         ))
     }
 }
 
-// TODO
-pub struct BulkDmlJobFailedRecordsRequest {}
-pub struct BulkDmlJobUnprocessedRecordsRequest {}
+pub struct BulkDmlJobUnprocessedRecordsRequest<T>
+where
+    T: SObjectDeserialization,
+{
+    id: SalesforceId,
+    csv_format: BulkCsvFormat,
+    phantom: PhantomData<T>,
+}
+
+impl<T> BulkDmlJobUnprocessedRecordsRequest<T>
+where
+    T: SObjectDeserialization,
+{
+    pub fn new(id: SalesforceId, csv_format: BulkCsvFormat) -> Self {
+        Self {
+            id,
+            csv_format,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T> SalesforceRawRequest for BulkDmlJobUnprocessedRecordsRequest<T>
+where
+    T: SObjectDeserialization,
+{
+    type ReturnValue = BulkDmlUnprocessedRecordStream<T>;
+
+    fn get_url(&self) -> String {
+        format!("jobs/ingest/{}/unprocessedrecords", self.id)
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn get_result(
+        &self,
+        _conn: &Connection,
+        response: Response,
+    ) -> Result<Self::ReturnValue> {
+        Ok(Box::pin(
+            read_csv_response_rows(response, self.csv_format).map(|row| {
+                Ok(BulkDmlUnprocessedRecord {
+                    row: row?,
+                    phantom: PhantomData,
+                })
+            }),
+        ))
+    }
+}
 
 pub struct BulkDmlJobSetStatusRequest {
     id: SalesforceId,
@@ -528,10 +1503,14 @@ impl SalesforceRequest for BulkDmlJobDeleteRequest {
         // HTTP errors handled by the Connection; no body.
         Ok(())
     }
+
+    fn get_dry_run_result(&self, _conn: &Connection) -> Result<Self::ReturnValue> {
+        Ok(())
+    }
 }
 
 // TODO: implement query stream interface.
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct BulkDmlJobListResponse {
     pub done: bool,
@@ -585,7 +1564,7 @@ impl SalesforceRequest for BulkDmlJobListRequest {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
 #[serde(rename_all = "camelCase")]
 pub enum BulkApiDmlOperation {
     Insert,
@@ -595,7 +1574,7 @@ pub enum BulkApiDmlOperation {
     Upsert,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
 pub enum BulkApiJobType {
     // serde rename is not required; this are the actual API values
     BigObjectIngest,
@@ -603,7 +1582,89 @@ pub enum BulkApiJobType {
     V2Ingest,
 }
 
-#[derive(Deserialize)]
+/// Salesforce accepts uploads of up to 150 MB once base64-encoded, and
+/// recommends that the data itself not exceed 100 MB.
+pub const MAX_INGEST_UPLOAD_SIZE: usize = 100 * 1024 * 1024;
+
+/// Limits on the data uploaded to each job by `BulkDmlJob::ingest_split()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BulkIngestLimits {
+    max_bytes: usize,
+    max_records: Option<usize>,
+}
+
+impl Default for BulkIngestLimits {
+    fn default() -> Self {
+        BulkIngestLimits {
+            max_bytes: MAX_INGEST_UPLOAD_SIZE,
+            max_records: None,
+        }
+    }
+}
+
+impl BulkIngestLimits {
+    pub fn new() -> BulkIngestLimits {
+        BulkIngestLimits::default()
+    }
+
+    /// Upload at most `max_bytes` of CSV, including the header row, per job.
+    #[must_use]
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Upload at most `max_records` records per job, such as to keep jobs
+    /// within the org's processing limits.
+    #[must_use]
+    pub fn with_max_records(mut self, max_records: usize) -> Self {
+        self.max_records = Some(max_records.max(1));
+        self
+    }
+
+    pub fn get_max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    pub fn get_max_records(&self) -> Option<usize> {
+        self.max_records
+    }
+}
+
+/// A job to which `BulkDmlJob::ingest_split()` uploaded records.
+#[derive(Debug, Clone)]
+pub struct BulkIngestJobSummary {
+    pub job: BulkDmlJob,
+    pub record_count: usize,
+    pub byte_count: usize,
+}
+
+/// The jobs to which `BulkDmlJob::ingest_split()` uploaded records.
+#[derive(Debug, Clone, Default)]
+pub struct BulkIngestSummary {
+    pub jobs: Vec<BulkIngestJobSummary>,
+}
+
+impl BulkIngestSummary {
+    pub fn get_job_ids(&self) -> Vec<SalesforceId> {
+        self.jobs.iter().map(|j| j.job.id).collect()
+    }
+
+    pub fn get_record_count(&self) -> usize {
+        self.jobs.iter().map(|j| j.record_count).sum()
+    }
+}
+
+/// A chunk of records uploaded by `BulkDmlJob::ingest_checkpointed()`
+/// whose job has been closed and queued for processing.
+#[derive(Debug, Clone)]
+pub struct BulkIngestCheckpoint {
+    pub chunk_index: usize,
+    pub record_count: usize,
+    pub job: BulkDmlJob,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct BulkDmlJob {
     pub id: SalesforceId,
@@ -629,6 +1690,10 @@ pub struct BulkDmlJob {
     pub number_records_processed: Option<u64>,
     pub retries: Option<u32>,
     pub total_processing_time: Option<u64>,
+    /// Whether records ingested into this job write nulls as `#N/A`.
+    /// This is a client-side setting, which Salesforce does not report.
+    #[serde(skip)]
+    pub nulls_as_na: bool,
 }
 
 impl BulkDmlJob {
@@ -647,38 +1712,457 @@ impl BulkDmlJob {
             .await?)
     }
 
+    pub async fn get(conn: &Connection, id: SalesforceId) -> Result<BulkDmlJob> {
+        conn.execute(&BulkDmlJobStatusRequest::new(id)).await
+    }
+
     pub async fn create(
         conn: &Connection,
         operation: BulkApiDmlOperation,
         object: String,
     ) -> Result<BulkDmlJob> {
-        Ok(conn
-            .execute(&BulkDmlJobCreateRequest::new(operation, object))
-            .await?)
+        BulkDmlJob::create_with_options(conn, operation, object, None, None).await
     }
 
-    pub async fn ingest<T>(
-        &self,
+    pub fn get_csv_format(&self) -> BulkCsvFormat {
+        BulkCsvFormat::from_options(self.column_delimiter, self.line_ending)
+            .with_nulls_as_na(self.nulls_as_na)
+    }
+
+    /// Create a job guarded by a `BulkJobGuard`, which aborts the job
+    /// if it is dropped before the job is closed.
+    pub async fn create_guarded(
         conn: &Connection,
-        records: impl Stream<Item = T> + 'static + Send + Sync,
-    ) -> Result<()>
-    where
+        operation: BulkApiDmlOperation,
+        object: String,
+    ) -> Result<BulkJobGuard> {
+        Ok(BulkDmlJob::create(conn, operation, object)
+            .await?
+            .guard(conn))
+    }
+
+    /// Guard this job with a `BulkJobGuard`.
+    pub fn guard(self, conn: &Connection) -> BulkJobGuard {
+        BulkJobGuard::new(conn, self)
+    }
+
+    /// Create a job. If `assignment_rule` is `None`, the Connection's
+    /// default assignment rule is used.
+    pub async fn create_with_options(
+        conn: &Connection,
+        operation: BulkApiDmlOperation,
+        object: String,
+        external_id_field_name: Option<String>,
+        assignment_rule: Option<AssignmentRule>,
+    ) -> Result<BulkDmlJob> {
+        BulkDmlJob::create_with_csv_format(
+            conn,
+            operation,
+            object,
+            external_id_field_name,
+            assignment_rule,
+            BulkCsvFormat::default(),
+        )
+        .await
+    }
+
+    /// Create a job whose data is delimited as `csv_format` specifies.
+    /// Records ingested into the job are serialized in this format.
+    pub async fn create_with_csv_format(
+        conn: &Connection,
+        operation: BulkApiDmlOperation,
+        object: String,
+        external_id_field_name: Option<String>,
+        assignment_rule: Option<AssignmentRule>,
+        csv_format: BulkCsvFormat,
+    ) -> Result<BulkDmlJob> {
+        let mut options = BulkJobOptions::new().with_csv_format(csv_format);
+        options.external_id_field_name = external_id_field_name;
+        options.assignment_rule = assignment_rule;
+
+        BulkDmlJob::create_with_job_options(conn, operation, object, &options).await
+    }
+
+    /// Create a job with `options`. Records ingested into the job are
+    /// serialized in its CSV format. A Connection default of
+    /// `AssignmentRule::ActiveDefault` cannot be expressed in the Bulk API,
+    /// so a job created under it must name its own assignment rule.
+    pub async fn create_with_job_options(
+        conn: &Connection,
+        operation: BulkApiDmlOperation,
+        object: String,
+        options: &BulkJobOptions,
+    ) -> Result<BulkDmlJob> {
+        let mut options = options.clone();
+        if options.assignment_rule.is_none() {
+            options.assignment_rule = conn.get_assignment_rule();
+
+            if options.assignment_rule == Some(AssignmentRule::ActiveDefault) {
+                return Err(SalesforceError::GeneralError(
+                    "The Connection's default assignment rule cannot be applied to a Bulk API \
+                    job; set a specific rule, or AssignmentRule::Disabled, in its BulkJobOptions"
+                        .to_owned(),
+                )
+                .into());
+            }
+        }
+
+        let mut job = conn
+            .execute(&BulkDmlJobCreateRequest::new_with_job_options(
+                operation, object, &options,
+            )?)
+            .await?;
+        job.nulls_as_na = options.csv_format.nulls_as_na;
+
+        Ok(job)
+    }
+
+    pub async fn ingest<T>(
+        &self,
+        conn: &Connection,
+        records: impl Stream<Item = T> + 'static + Send + Sync,
+    ) -> Result<()>
+    where
         T: SObjectSerialization + Serialize,
     {
         Ok(conn
-            .execute_raw_request(&BulkDmlJobIngestRequest::new(self.id, records))
+            .execute_raw_request(&BulkDmlJobIngestRequest::new_with_csv_format(
+                self.id,
+                records,
+                self.get_csv_format(),
+            ))
             .await?)
     }
 
-    pub async fn complete(&self, conn: &Connection) -> Result<Self> {
-        loop {
-            let status = self.check_status(conn).await?;
+    /// Upload dynamic sObjects. External Id references are written as
+    /// `Relationship.Field` columns. The columns are those of the first record,
+    /// and a later record with other fields fails the upload.
+    pub async fn ingest_sobjects(
+        &self,
+        conn: &Connection,
+        records: impl Stream<Item = SObject> + 'static + Send + Sync,
+    ) -> Result<()> {
+        conn.execute_raw_request(&BulkDmlJobIngestRequest::new_sobjects_with_csv_format(
+            self.id,
+            records,
+            self.get_csv_format(),
+        ))
+        .await
+    }
+
+    /// Upload dynamic sObjects under the fields `columns`, in order.
+    /// A record with a field outside them fails the upload.
+    pub async fn ingest_sobjects_with_columns(
+        &self,
+        conn: &Connection,
+        records: impl Stream<Item = SObject> + 'static + Send + Sync,
+        columns: &[&str],
+    ) -> Result<()> {
+        conn.execute_raw_request(&BulkDmlJobIngestRequest::new_sobjects_with_encoder(
+            self.id,
+            records,
+            SObjectCsvEncoder::new(self.get_csv_format()).with_columns(columns),
+        ))
+        .await
+    }
+
+    /// Upload CSV data, with a header row, without parsing it.
+    /// The data must be in this job's CSV format.
+    pub async fn ingest_csv(
+        &self,
+        conn: &Connection,
+        body: impl Stream<Item = Result<Bytes>> + 'static + Send + Sync,
+    ) -> Result<()> {
+        conn.execute_raw_request(&BulkDmlJobIngestRequest::new_csv(self.id, body))
+            .await
+    }
+
+    /// Upload CSV data, with a header row, from any async reader, such as
+    /// a file or a decompressor. The data is streamed to Salesforce as it is read,
+    /// and must be in this job's CSV format.
+    pub async fn ingest_reader(
+        &self,
+        conn: &Connection,
+        reader: impl AsyncRead + 'static + Send + Sync,
+    ) -> Result<()> {
+        conn.execute_raw_request(&BulkDmlJobIngestRequest::new_reader(self.id, reader))
+            .await
+    }
+
+    /// Upload `records` in chunks of at most `chunk_size`, awaiting
+    /// `on_accepted` after each chunk's job has been closed and queued by
+    /// Salesforce. Consumers can commit source offsets from the callback;
+    /// an error from the callback stops ingestion.
+    ///
+    /// Bulk API 2.0 accepts one upload per job, so the first chunk is
+    /// uploaded to this job and each further chunk to a new job created
+    /// with the same options. All jobs used are returned.
+    pub async fn ingest_checkpointed<T, F, Fut>(
+        &self,
+        conn: &Connection,
+        records: impl Stream<Item = T> + Unpin,
+        chunk_size: usize,
+        mut on_accepted: F,
+    ) -> Result<Vec<BulkDmlJob>>
+    where
+        T: SObjectSerialization + Serialize + Send + Sync + 'static,
+        F: FnMut(BulkIngestCheckpoint) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        if chunk_size == 0 {
+            return Err(SalesforceError::GeneralError(
+                "Chunk size must be greater than zero".to_string(),
+            )
+            .into());
+        }
+
+        let mut chunks = futures::StreamExt::chunks(records, chunk_size);
+        let mut jobs = Vec::new();
+
+        while let Some(chunk) = chunks.next().await {
+            let job = if jobs.is_empty() {
+                self.clone()
+            } else {
+                BulkDmlJob::create_with_csv_format(
+                    conn,
+                    self.operation,
+                    self.object.clone(),
+                    self.external_id_field_name.clone(),
+                    self.assignment_rule_id.map(AssignmentRule::Specific),
+                    self.get_csv_format(),
+                )
+                .await?
+            };
+            let record_count = chunk.len();
+
+            job.ingest(conn, tokio_stream::iter(chunk)).await?;
+            let job = job.close(conn).await?;
+
+            on_accepted(BulkIngestCheckpoint {
+                chunk_index: jobs.len(),
+                record_count,
+                job: job.clone(),
+            })
+            .await?;
+            jobs.push(job);
+        }
+
+        Ok(jobs)
+    }
 
-            if status.state.is_completed_state() {
-                return Ok(status);
+    /// Upload `records`, splitting them across jobs so that no upload exceeds
+    /// `limits`. The first upload goes to this job and each further upload to
+    /// a new job created with the same options. Each job is closed once its
+    /// data is uploaded.
+    ///
+    /// Each upload is assembled in memory before it is sent, so up to
+    /// `limits.get_max_bytes()` of CSV is held at once.
+    pub async fn ingest_split<T>(
+        &self,
+        conn: &Connection,
+        records: impl Stream<Item = T> + Unpin,
+        limits: &BulkIngestLimits,
+    ) -> Result<BulkIngestSummary>
+    where
+        T: SObjectSerialization + Serialize + Send + Sync + 'static,
+    {
+        self.ingest_split_with_metrics(conn, records, limits, &MetricsRecorder::new())
+            .await
+    }
+
+    /// Upload `records` as `ingest_split()`, recording each upload in `metrics`,
+    /// which may be read from another task while the upload proceeds.
+    ///
+    /// If an upload fails, `SalesforceError::IngestSplitError` holds the jobs
+    /// already uploaded and closed, which Salesforce will process. A job
+    /// created for the failed upload is aborted.
+    pub async fn ingest_split_with_metrics<T>(
+        &self,
+        conn: &Connection,
+        records: impl Stream<Item = T> + Unpin,
+        limits: &BulkIngestLimits,
+        metrics: &MetricsRecorder,
+    ) -> Result<BulkIngestSummary>
+    where
+        T: SObjectSerialization + Serialize + Send + Sync + 'static,
+    {
+        let mut summary = BulkIngestSummary::default();
+
+        match self
+            .ingest_split_into(conn, records, limits, metrics, &mut summary)
+            .await
+        {
+            Ok(()) => Ok(summary),
+            Err(error) => Err(SalesforceError::IngestSplitError { summary, error }.into()),
+        }
+    }
+
+    async fn ingest_split_into<T>(
+        &self,
+        conn: &Connection,
+        mut records: impl Stream<Item = T> + Unpin,
+        limits: &BulkIngestLimits,
+        metrics: &MetricsRecorder,
+        summary: &mut BulkIngestSummary,
+    ) -> Result<()>
+    where
+        T: SObjectSerialization + Serialize + Send + Sync + 'static,
+    {
+        let mut encoder = CsvRowEncoder::new(self.get_csv_format());
+        let mut rows: Vec<Bytes> = Vec::new();
+        let mut size = 0;
+
+        while let Some(record) = records.next().await {
+            let row = encoder.encode(record).await?;
+            let header_size = encoder.get_header().map_or(0, |h| h.len());
+
+            if header_size + row.len() > limits.max_bytes {
+                return Err(SalesforceError::GeneralError(format!(
+                    "A single record of {} bytes exceeds the upload limit of {} bytes",
+                    row.len(),
+                    limits.max_bytes
+                ))
+                .into());
+            }
+
+            let full = header_size + size + row.len() > limits.max_bytes
+                || limits.max_records.is_some_and(|max| rows.len() >= max);
+            if full {
+                let upload = std::mem::take(&mut rows);
+                let job = self
+                    .ingest_split_upload(conn, encoder.get_header(), upload, summary, metrics)
+                    .await?;
+                summary.jobs.push(job);
+                size = 0;
             }
 
-            sleep(Duration::from_secs(POLL_INTERVAL)).await;
+            size += row.len();
+            rows.push(row);
+        }
+
+        if !rows.is_empty() {
+            let job = self
+                .ingest_split_upload(conn, encoder.get_header(), rows, summary, metrics)
+                .await?;
+            summary.jobs.push(job);
+        }
+
+        Ok(())
+    }
+
+    async fn ingest_split_upload(
+        &self,
+        conn: &Connection,
+        header: Option<Bytes>,
+        rows: Vec<Bytes>,
+        summary: &BulkIngestSummary,
+        metrics: &MetricsRecorder,
+    ) -> Result<BulkIngestJobSummary> {
+        let header = header.unwrap_or_default();
+        let job = if summary.jobs.is_empty() {
+            self.clone()
+        } else {
+            BulkDmlJob::create_with_csv_format(
+                conn,
+                self.operation,
+                self.object.clone(),
+                self.external_id_field_name.clone(),
+                self.assignment_rule_id.map(AssignmentRule::Specific),
+                self.get_csv_format(),
+            )
+            .await?
+        };
+        let record_count = rows.len();
+        let byte_count = header.len() + rows.iter().map(|r| r.len()).sum::<usize>();
+        let started = Instant::now();
+
+        let uploaded = job
+            .ingest_csv(
+                conn,
+                tokio_stream::iter(std::iter::once(header).chain(rows).map(Ok)),
+            )
+            .await;
+        let closed = match uploaded {
+            Ok(()) => job.close(conn).await,
+            Err(e) => Err(e),
+        };
+        let job = match closed {
+            Ok(closed) => closed,
+            Err(e) => {
+                // The caller never learns of a job created here, so it is not left open.
+                if !summary.jobs.is_empty() {
+                    if let Err(abort_error) = job.abort(conn).await {
+                        warn!("Unable to abort Bulk job {}: {}", job.id, abort_error);
+                    }
+                }
+                return Err(e);
+            }
+        };
+
+        metrics.record_records(record_count as u64);
+        metrics.record_page(byte_count as u64, Some(started.elapsed()));
+
+        Ok(BulkIngestJobSummary {
+            job,
+            record_count,
+            byte_count,
+        })
+    }
+
+    pub async fn complete(&self, conn: &Connection) -> Result<Self> {
+        self.complete_with_progress(conn, |_| {}).await
+    }
+
+    /// Wait for the job to complete, passing its progress to `progress`
+    /// each time its status is polled.
+    pub async fn complete_with_progress(
+        &self,
+        conn: &Connection,
+        progress: impl Fn(JobProgress),
+    ) -> Result<Self> {
+        self.complete_with_cancellation(conn, progress, &CancellationToken::new())
+            .await
+    }
+
+    /// Wait for the job to complete, as `complete_with_progress()`. If `cancel`
+    /// is cancelled first, the job is aborted and `SalesforceError::Cancelled`
+    /// is returned. Records processed before the job was aborted remain processed.
+    ///
+    /// The job is polled per the Connection's `PollingOptions`. If their timeout
+    /// elapses first, `SalesforceError::Timeout` is returned, and the job continues.
+    pub async fn complete_with_cancellation(
+        &self,
+        conn: &Connection,
+        progress: impl Fn(JobProgress),
+        cancel: &CancellationToken,
+    ) -> Result<Self> {
+        if conn.is_dry_run() {
+            // Jobs are never started in dry-run mode, so there is nothing to poll.
+            let job = BulkDmlJob {
+                state: BulkJobStatus::JobComplete,
+                ..self.clone()
+            };
+            progress(job.get_progress());
+            return Ok(job);
+        }
+
+        poll_until_complete(
+            || self.check_status(conn),
+            || self.abort(conn),
+            BulkDmlJob::get_progress,
+            progress,
+            cancel,
+            conn.get_polling_options(),
+        )
+        .await
+    }
+
+    pub fn get_progress(&self) -> JobProgress {
+        JobProgress {
+            id: self.id,
+            state: self.state,
+            records_processed: self.number_records_processed.unwrap_or(0),
+            records_failed: self.number_records_failed.unwrap_or(0),
         }
     }
 
@@ -687,26 +2171,155 @@ impl BulkDmlJob {
     }
 
     pub async fn abort(&self, conn: &Connection) -> Result<Self> {
-        Ok(conn
-            .execute(&BulkDmlJobSetStatusRequest::new(
-                self.id,
-                BulkJobStatus::Aborted,
-            ))
-            .await?)
+        self.set_status(conn, BulkJobStatus::Aborted).await
     }
 
     pub async fn close(&self, conn: &Connection) -> Result<Self> {
-        Ok(conn
-            .execute(&BulkDmlJobSetStatusRequest::new(
-                self.id,
-                BulkJobStatus::UploadComplete,
-            ))
-            .await?)
+        self.set_status(conn, BulkJobStatus::UploadComplete).await
+    }
+
+    async fn set_status(&self, conn: &Connection, status: BulkJobStatus) -> Result<Self> {
+        if conn.is_dry_run() {
+            return Ok(BulkDmlJob {
+                state: status,
+                ..self.clone()
+            });
+        }
+
+        conn.execute(&BulkDmlJobSetStatusRequest::new(self.id, status))
+            .await
     }
 
     pub async fn delete(&self, conn: &Connection) -> Result<()> {
         Ok(conn.execute(&BulkDmlJobDeleteRequest::new(self.id)).await?)
     }
+
+    /// Get the records this job processed successfully, with their Ids.
+    pub async fn get_successful_results<T>(
+        &self,
+        conn: &Connection,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<BulkDmlResult<T>>>>>>
+    where
+        T: SObjectDeserialization,
+    {
+        conn.execute_raw_request(&BulkDmlJobSuccessfulRecordsRequest::new(
+            self.id,
+            self.get_csv_format(),
+        ))
+        .await
+    }
+
+    /// Get the records this job failed to process, with their errors,
+    /// so that they can be corrected and retried.
+    pub async fn get_failed_records<T>(
+        &self,
+        conn: &Connection,
+    ) -> Result<BulkDmlFailedResultStream<T>>
+    where
+        T: SObjectDeserialization,
+    {
+        conn.execute_raw_request(&BulkDmlJobFailedRecordsRequest::new(
+            self.id,
+            self.get_csv_format(),
+        ))
+        .await
+    }
+
+    /// Get the records this job did not process, because it was aborted or failed.
+    pub async fn get_unprocessed_records<T>(
+        &self,
+        conn: &Connection,
+    ) -> Result<BulkDmlUnprocessedRecordStream<T>>
+    where
+        T: SObjectDeserialization,
+    {
+        conn.execute_raw_request(&BulkDmlJobUnprocessedRecordsRequest::new(
+            self.id,
+            self.get_csv_format(),
+        ))
+        .await
+    }
+}
+
+/// Guards an open `BulkDmlJob`: if the guard is dropped before the job is
+/// closed or released, for example because a pipeline failed between creating
+/// the job and uploading its data, the job is aborted in the background so that
+/// it is not left open in the org.
+///
+/// The guard dereferences to its job, so records can be ingested through it.
+pub struct BulkJobGuard {
+    conn: Connection,
+    job: Option<BulkDmlJob>,
+}
+
+impl BulkJobGuard {
+    pub fn new(conn: &Connection, job: BulkDmlJob) -> BulkJobGuard {
+        BulkJobGuard {
+            conn: conn.clone(),
+            job: Some(job),
+        }
+    }
+
+    /// Close the job, queuing it for processing, and disarm the guard.
+    /// If closing fails, the guard remains armed.
+    pub async fn close(mut self) -> Result<BulkDmlJob> {
+        let job = self.as_ref().close(&self.conn).await?;
+        self.job = None;
+
+        Ok(job)
+    }
+
+    /// Abort the job now, rather than when the guard is dropped.
+    pub async fn abort(mut self) -> Result<BulkDmlJob> {
+        let job = self.as_ref().abort(&self.conn).await?;
+        self.job = None;
+
+        Ok(job)
+    }
+
+    /// Disarm the guard, leaving the job in its current state.
+    pub fn release(mut self) -> BulkDmlJob {
+        self.job.take().unwrap()
+    }
+}
+
+impl AsRef<BulkDmlJob> for BulkJobGuard {
+    fn as_ref(&self) -> &BulkDmlJob {
+        // The job is only taken when the guard is consumed.
+        self.job.as_ref().unwrap()
+    }
+}
+
+impl std::ops::Deref for BulkJobGuard {
+    type Target = BulkDmlJob;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_ref()
+    }
+}
+
+impl Drop for BulkJobGuard {
+    fn drop(&mut self) {
+        let job = match self.job.take() {
+            Some(job) if job.state == BulkJobStatus::Open && !self.conn.is_dry_run() => job,
+            _ => return,
+        };
+
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                let conn = self.conn.clone();
+                handle.spawn(async move {
+                    if let Err(e) = job.abort(&conn).await {
+                        warn!("Unable to abort Bulk API job {}: {}", job.id, e);
+                    }
+                });
+            }
+            Err(_) => warn!(
+                "Bulk API job {} was left open: no runtime is available to abort it",
+                job.id
+            ),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -740,9 +2353,37 @@ impl BulkDmlJobCreateRequest {
             assignment_rule_id,
             content_type: BulkApiContentType::CSV,
             line_ending: BulkApiLineEnding::LF,
-            column_delimiter: BulkApiColumnDelimiter::Comma, // TODO: allow configuration of these two parameters
+            column_delimiter: BulkApiColumnDelimiter::Comma,
         }
     }
+
+    /// A request for a job with `options`. Unlike `BulkDmlJob::create_with_job_options()`,
+    /// this does not apply the Connection's default assignment rule.
+    pub fn new_with_job_options(
+        operation: BulkApiDmlOperation,
+        object: String,
+        options: &BulkJobOptions,
+    ) -> Result<Self> {
+        let assignment_rule_id = match options.assignment_rule {
+            Some(assignment_rule) => assignment_rule.get_bulk_assignment_rule_id()?,
+            None => None,
+        };
+
+        Ok(Self::new_with_options(
+            operation,
+            object,
+            options.external_id_field_name.clone(),
+            assignment_rule_id,
+        )
+        .with_csv_format(options.csv_format))
+    }
+
+    #[must_use]
+    pub fn with_csv_format(mut self, csv_format: BulkCsvFormat) -> Self {
+        self.column_delimiter = csv_format.column_delimiter;
+        self.line_ending = csv_format.line_ending;
+        self
+    }
 }
 
 impl SalesforceRequest for BulkDmlJobCreateRequest {
@@ -767,30 +2408,381 @@ impl SalesforceRequest for BulkDmlJobCreateRequest {
             Err(SalesforceError::ResponseBodyExpected.into())
         }
     }
+
+    fn get_dry_run_result(&self, conn: &Connection) -> Result<Self::ReturnValue> {
+        Ok(BulkDmlJob {
+            id: conn.get_dry_run_id(),
+            assignment_rule_id: self.assignment_rule_id,
+            column_delimiter: Some(self.column_delimiter),
+            content_type: self.content_type,
+            external_id_field_name: self.external_id_field_name.clone(),
+            line_ending: Some(self.line_ending),
+            object: self.object.clone(),
+            operation: self.operation,
+            api_version: 0.0,
+            concurrency_mode: BulkApiConcurrencyMode::Parallel,
+            content_url: None,
+            created_by_id: conn.get_dry_run_id(),
+            created_date: DateTime::now(),
+            job_type: Some(BulkApiJobType::V2Ingest),
+            state: BulkJobStatus::Open,
+            system_modstamp: DateTime::now(),
+            apex_processing_time: None,
+            api_active_processing_time: None,
+            number_records_failed: None,
+            number_records_processed: None,
+            retries: None,
+            total_processing_time: None,
+            nulls_as_na: false,
+        })
+    }
+}
+
+/// Remove the first line of a page of CSV results. Bulk API header rows
+/// hold field names, which contain no quotes or line breaks.
+fn strip_header_row(content: Bytes) -> Bytes {
+    match content.iter().position(|b| *b == b'\n') {
+        Some(end) => content.slice(end + 1..),
+        None => Bytes::new(),
+    }
+}
+
+/// A byte buffer shared between a CSV serializer and its owner, so that rows
+/// can be taken as they are written without recreating the serializer.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn take(&self) -> Bytes {
+        Bytes::from(std::mem::take(&mut *self.0.lock().unwrap()))
+    }
+}
+
+impl AsyncWrite for SharedBuffer {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Serialize records one CSV row at a time with a single serializer. The
+/// header row is written with the first record and kept apart from it.
+struct CsvRowEncoder {
+    serializer: AsyncSerializer<SharedBuffer>,
+    buffer: SharedBuffer,
+    csv_format: BulkCsvFormat,
+    header: Option<Bytes>,
+}
+
+impl CsvRowEncoder {
+    fn new(csv_format: BulkCsvFormat) -> CsvRowEncoder {
+        let buffer = SharedBuffer::default();
+
+        CsvRowEncoder {
+            serializer: csv_format
+                .get_writer_builder(true)
+                .create_serializer(buffer.clone()),
+            buffer,
+            csv_format,
+            header: None,
+        }
+    }
+
+    fn get_header(&self) -> Option<Bytes> {
+        self.header.clone()
+    }
+
+    async fn encode<T: Serialize>(&mut self, record: T) -> Result<Bytes> {
+        self.serializer.serialize(record).await?;
+        self.serializer.flush().await?;
+
+        let mut row = self.buffer.take();
+        if self.header.is_none() {
+            let content = row;
+            row = strip_header_row(content.clone());
+            self.header = Some(content.slice(..content.len() - row.len()));
+        }
+
+        if self.csv_format.nulls_as_na {
+            Ok(Bytes::from(
+                empty_fields_to_na(&row, self.csv_format, false).await?,
+            ))
+        } else {
+            Ok(row)
+        }
+    }
 }
 
-// NTH: parameterize how many records it consumes at a time. One at a time is probably not efficient.
-// TODO: figure out how to set "#N/A" for nulls, and make it configurable.
+/// The number of records serialized together by one encoder task.
+const ENCODE_CHUNK_SIZE: usize = 1000;
+/// The maximum number of chunks being encoded at once.
+const MAX_ENCODE_TASKS: usize = 4;
 
 type BytesStream = Pin<Box<dyn Stream<Item = Result<Bytes>> + Send + Sync>>;
-pub fn new_bytes_stream<T>(source: Pin<Box<dyn Stream<Item = T> + Send + Sync>>) -> BytesStream
+
+/// Serialize records as CSV. Chunks of records are encoded concurrently on
+/// the runtime's worker threads, with at most `MAX_ENCODE_TASKS` chunks, and
+/// no more than one per available core, in flight. Each chunk is yielded, in
+/// its original order, as soon as it and the chunks before it are encoded.
+pub fn new_bytes_stream<T>(
+    source: Pin<Box<dyn Stream<Item = T> + Send + Sync>>,
+    csv_format: BulkCsvFormat,
+) -> BytesStream
 where
-    T: SObjectSerialization + Serialize,
+    T: SObjectSerialization + Serialize + 'static,
 {
-    use futures::StreamExt; // TODO: this is not an appealing solution.
-    Box::pin(tokio_stream::StreamExt::map(
-        source.enumerate(),
-        |(i, s)| {
-            let buf = BytesMut::new();
-            let mut writer = csv::WriterBuilder::new()
-                .has_headers(i == 0)
-                .from_writer(buf.writer());
-            writer.serialize(s).unwrap(); // TODO: can panic
-            writer.flush().unwrap(); // TODO
-            let bytes = writer.into_inner()?.into_inner().freeze();
-            Ok(bytes)
-        },
-    ))
+    let max_pending = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(MAX_ENCODE_TASKS);
+    let mut chunks = futures::StreamExt::chunks(source, ENCODE_CHUNK_SIZE);
+
+    Box::pin(try_stream! {
+        let mut pending: VecDeque<JoinHandle<Result<Bytes>>> = VecDeque::new();
+        let mut has_headers = true;
+        let mut source_done = false;
+
+        loop {
+            let accepting = !source_done && pending.len() < max_pending;
+
+            // `Left` for the next chunk of records, `Right` for the encoding
+            // of the oldest chunk in flight. Finished chunks take precedence.
+            let next = match pending.front_mut() {
+                Some(handle) if accepting => tokio::select! {
+                    biased;
+                    bytes = handle => Either::Right(bytes),
+                    chunk = chunks.next() => Either::Left(chunk),
+                },
+                Some(handle) => Either::Right(handle.await),
+                None if accepting => Either::Left(chunks.next().await),
+                None => break,
+            };
+
+            match next {
+                Either::Left(Some(chunk)) => {
+                    pending.push_back(spawn(encode_chunk(chunk, csv_format, has_headers)));
+                    has_headers = false;
+                }
+                Either::Left(None) => source_done = true,
+                Either::Right(bytes) => {
+                    pending.pop_front();
+                    let bytes = bytes??;
+                    yield bytes;
+                }
+            }
+        }
+    })
+}
+
+async fn encode_chunk<T>(
+    records: Vec<T>,
+    csv_format: BulkCsvFormat,
+    has_headers: bool,
+) -> Result<Bytes>
+where
+    T: Serialize,
+{
+    let mut serializer = csv_format
+        .get_writer_builder(has_headers)
+        .create_serializer(Vec::new());
+
+    for record in records {
+        serializer.serialize(record).await?;
+    }
+
+    let bytes = serializer.into_inner().await.map_err(|e| e.into_error())?;
+    if csv_format.nulls_as_na {
+        Ok(Bytes::from(
+            empty_fields_to_na(&bytes, csv_format, has_headers).await?,
+        ))
+    } else {
+        Ok(Bytes::from(bytes))
+    }
+}
+
+/// Rewrite the empty fields of serialized records as `#N/A`. Salesforce does
+/// not distinguish empty text from null, so every empty field is a null.
+async fn empty_fields_to_na(
+    content: &[u8],
+    csv_format: BulkCsvFormat,
+    has_headers: bool,
+) -> Result<Vec<u8>> {
+    let mut reader = csv_format
+        .get_reader_builder()
+        .has_headers(false)
+        .create_reader(content);
+    let mut writer = csv_format
+        .get_writer_builder(false)
+        .create_writer(Vec::new());
+    let mut records = reader.byte_records();
+    let mut is_header = has_headers;
+
+    while let Some(record) = records.next().await {
+        let record = record?;
+
+        if is_header {
+            writer.write_byte_record(&record).await?;
+            is_header = false;
+        } else {
+            let mut row = ByteRecord::new();
+            for field in record.iter() {
+                if field.is_empty() {
+                    row.push_field(BULK_NULL_VALUE.as_bytes());
+                } else {
+                    row.push_field(field);
+                }
+            }
+            writer.write_byte_record(&row).await?;
+        }
+    }
+
+    Ok(writer.into_inner().await?)
+}
+
+/// Serialize dynamic sObjects as CSV. The columns are taken from the first record.
+/// A later record with a field outside them ends the stream with an error.
+pub fn new_sobject_bytes_stream(
+    source: Pin<Box<dyn Stream<Item = SObject> + Send + Sync>>,
+    csv_format: BulkCsvFormat,
+) -> BytesStream {
+    new_sobject_bytes_stream_with_encoder(source, SObjectCsvEncoder::new(csv_format))
+}
+
+/// Serialize dynamic sObjects as CSV with `encoder`, such as one with explicit columns.
+pub fn new_sobject_bytes_stream_with_encoder(
+    mut source: Pin<Box<dyn Stream<Item = SObject> + Send + Sync>>,
+    mut encoder: SObjectCsvEncoder,
+) -> BytesStream {
+    Box::pin(try_stream! {
+        while let Some(sobject) = source.next().await {
+            let bytes = encoder.encode(&sobject).await?;
+            yield bytes;
+        }
+
+        let header = encoder.finish().await?;
+        if !header.is_empty() {
+            yield header;
+        }
+    })
+}
+
+/// Encodes dynamic sObjects as CSV rows under one set of columns.
+///
+/// The columns are given with `with_columns()`, or else taken from the fields
+/// of the first record. Fields absent from a record are written as empty
+/// values, and a record with a field outside the columns is an error rather
+/// than being truncated.
+pub struct SObjectCsvEncoder {
+    columns: Option<Vec<String>>,
+    csv_format: BulkCsvFormat,
+    has_header: bool,
+}
+
+impl SObjectCsvEncoder {
+    pub fn new(csv_format: BulkCsvFormat) -> SObjectCsvEncoder {
+        SObjectCsvEncoder {
+            columns: None,
+            csv_format,
+            has_header: false,
+        }
+    }
+
+    /// Write exactly the fields `columns`, in order, matched ignoring case.
+    #[must_use]
+    pub fn with_columns(mut self, columns: &[&str]) -> Self {
+        self.columns = Some(columns.iter().map(|c| c.to_string()).collect());
+        self
+    }
+
+    pub fn get_columns(&self) -> Option<&[String]> {
+        self.columns.as_deref()
+    }
+
+    /// Render one CSV row, preceded by the header row if it has not yet been written.
+    pub async fn encode(&mut self, sobject: &SObject) -> Result<Bytes> {
+        let columns = self.columns.get_or_insert_with(|| {
+            let mut keys: Vec<String> = sobject.fields.keys().cloned().collect();
+            keys.sort();
+            keys
+        });
+
+        if let Some(key) = sobject
+            .fields
+            .keys()
+            .find(|k| !columns.iter().any(|c| c.eq_ignore_ascii_case(k)))
+        {
+            return Err(SalesforceError::SchemaError(format!(
+                "The field {} is not among the CSV columns {}",
+                key,
+                columns.join(", ")
+            ))
+            .into());
+        }
+
+        let mut writer = self
+            .csv_format
+            .get_writer_builder(false)
+            .create_writer(Vec::new());
+
+        if !self.has_header {
+            // External Id references are written under `Relationship.Field`.
+            let header: Vec<String> = columns
+                .iter()
+                .map(|c| match sobject.fields.get(&c.to_lowercase()) {
+                    Some(value) => value.get_csv_column(c),
+                    None => c.clone(),
+                })
+                .collect();
+            writer.write_record(&header).await?;
+            self.has_header = true;
+        }
+
+        // Related records are not flattened into columns; they are an error.
+        let row = columns
+            .iter()
+            .map(|c| match sobject.fields.get(&c.to_lowercase()) {
+                Some(FieldValue::Null) if self.csv_format.nulls_as_na => {
+                    Ok(BULK_NULL_VALUE.to_owned())
+                }
+                Some(v) => v.try_as_string(),
+                None => Ok(String::new()),
+            })
+            .collect::<Result<Vec<String>>>()?;
+        writer.write_record(&row).await?;
+
+        Ok(Bytes::from(writer.into_inner().await?))
+    }
+
+    /// The header row, if no record has been encoded and the columns were
+    /// given, so that a set of no records is still a valid CSV file.
+    /// Otherwise, nothing.
+    pub async fn finish(&mut self) -> Result<Bytes> {
+        match &self.columns {
+            Some(columns) if !self.has_header => {
+                let mut writer = self
+                    .csv_format
+                    .get_writer_builder(false)
+                    .create_writer(Vec::new());
+                writer.write_record(columns).await?;
+                self.has_header = true;
+
+                Ok(Bytes::from(writer.into_inner().await?))
+            }
+            _ => Ok(Bytes::new()),
+        }
+    }
 }
 
 pub struct BulkDmlJobIngestRequest {
@@ -801,13 +2793,79 @@ pub struct BulkDmlJobIngestRequest {
 impl BulkDmlJobIngestRequest {
     pub fn new<T>(id: SalesforceId, records: impl Stream<Item = T> + 'static + Send + Sync) -> Self
     where
-        T: SObjectSerialization + Serialize, // FIXME This bound is undesirable but satisfies `csv`
+        T: SObjectSerialization + Serialize + 'static, // FIXME This bound is undesirable but satisfies `csv`
+    {
+        Self::new_with_csv_format(id, records, BulkCsvFormat::default())
+    }
+
+    pub fn new_with_csv_format<T>(
+        id: SalesforceId,
+        records: impl Stream<Item = T> + 'static + Send + Sync,
+        csv_format: BulkCsvFormat,
+    ) -> Self
+    where
+        T: SObjectSerialization + Serialize + 'static,
     {
         Self {
             id,
-            body: RwLock::new(Some(new_bytes_stream(Box::pin(records)))),
+            body: RwLock::new(Some(new_bytes_stream(Box::pin(records), csv_format))),
+        }
+    }
+
+    pub fn new_sobjects(
+        id: SalesforceId,
+        records: impl Stream<Item = SObject> + 'static + Send + Sync,
+    ) -> Self {
+        Self::new_sobjects_with_csv_format(id, records, BulkCsvFormat::default())
+    }
+
+    pub fn new_sobjects_with_csv_format(
+        id: SalesforceId,
+        records: impl Stream<Item = SObject> + 'static + Send + Sync,
+        csv_format: BulkCsvFormat,
+    ) -> Self {
+        Self {
+            id,
+            body: RwLock::new(Some(new_sobject_bytes_stream(
+                Box::pin(records),
+                csv_format,
+            ))),
         }
     }
+
+    pub fn new_sobjects_with_encoder(
+        id: SalesforceId,
+        records: impl Stream<Item = SObject> + 'static + Send + Sync,
+        encoder: SObjectCsvEncoder,
+    ) -> Self {
+        Self {
+            id,
+            body: RwLock::new(Some(new_sobject_bytes_stream_with_encoder(
+                Box::pin(records),
+                encoder,
+            ))),
+        }
+    }
+
+    /// Upload CSV data as is, such as the contents of a file. The data must
+    /// include a header row.
+    pub fn new_csv(
+        id: SalesforceId,
+        body: impl Stream<Item = Result<Bytes>> + 'static + Send + Sync,
+    ) -> Self {
+        Self {
+            id,
+            body: RwLock::new(Some(Box::pin(body))),
+        }
+    }
+
+    /// Upload CSV data read from `reader`, with a chunked request body.
+    pub fn new_reader(id: SalesforceId, reader: impl AsyncRead + 'static + Send + Sync) -> Self {
+        Self::new_csv(
+            id,
+            ReaderStream::new(reader).map(|chunk| chunk.map_err(anyhow::Error::from)),
+        )
+    }
 }
 
 #[async_trait]
@@ -822,18 +2880,22 @@ impl SalesforceRawRequest for BulkDmlJobIngestRequest {
         format!("jobs/ingest/{}/batches", self.id)
     }
 
-    fn get_body(&self) -> Option<Body> {
-        // This is not a good implementation. Panics are possible
-        // and this results in only one possible call to get_body().
-        // TODO: should get_body() consume self?
-        Some(Body::wrap_stream(
-            self.body.write().unwrap().take().unwrap(),
-        ))
+    fn get_body(&self) -> Option<TransportBody> {
+        // The body is streamed, and so can be taken only once.
+        self.body
+            .write()
+            .unwrap()
+            .take()
+            .map(TransportBody::wrap_stream)
+    }
+
+    fn is_replayable(&self) -> bool {
+        false
     }
 
     fn get_mime_type(&self) -> String {
         // NOTE: The Bulk API 2.0 will throw a 500 if this MIME type is not set.
-        "text/csv".to_owned()
+        BulkApiContentType::CSV.get_mime_type().to_owned()
     }
 
     async fn get_result(
@@ -844,4 +2906,8 @@ impl SalesforceRawRequest for BulkDmlJobIngestRequest {
         // HTTP errors are handled by the Connection.
         Ok(())
     }
+
+    fn get_dry_run_result(&self, _conn: &Connection) -> Result<Self::ReturnValue> {
+        Ok(())
+    }
 }
@@ -1,9 +1,30 @@
 use crate::{
+    api::transport::TransportResponse,
+    api::{
+        concurrency::AdaptiveConcurrency, metrics::MetricsRecorder, polling::PollingOptions,
+        retry::RetryPolicy, AssignmentRule, Mode, SalesforceRequest,
+    },
+    bulk::v2::{
+        BulkApiColumnDelimiter, BulkApiContentType, BulkApiDmlOperation, BulkApiLineEnding,
+        BulkCsvFormat, BulkDmlJob, BulkDmlJobCreateRequest, BulkIngestLimits, BulkJobGuard,
+        BulkJobOptions, BulkJobStatus, BulkQueryJob, BulkQueryJobCreateRequest, SObjectCsvEncoder,
+    },
     prelude::*,
-    test_integration_base::{get_test_connection, Account},
+    test_integration_base::{
+        get_offline_connection, get_test_connection, get_test_field_describe,
+        get_test_sobject_type, json_response, raw_response, serve_responses, serve_routes, Account,
+        RecordedRequest, RouteTransport,
+    },
 };
 use anyhow::Result;
+use bytes::{Bytes, BytesMut};
+use reqwest::Method;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
 use tokio_stream::StreamExt;
+use tokio_util::sync::CancellationToken;
 
 #[tokio::test]
 #[ignore]
@@ -95,3 +116,1022 @@ async fn test_bulk_query_to_update() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+#[ignore]
+async fn test_bulk_ingest_checkpointed() -> Result<()> {
+    let conn = get_test_connection().expect("No connection present");
+
+    let accounts: Vec<Account> = (0..5)
+        .map(|i| Account {
+            id: None,
+            name: format!("Checkpoint Test {}", i),
+        })
+        .collect();
+
+    let job = BulkDmlJob::create(&conn, BulkApiDmlOperation::Insert, "Account".to_owned()).await?;
+    let mut checkpoints = Vec::new();
+    let jobs = job
+        .ingest_checkpointed(&conn, tokio_stream::iter(accounts), 2, |checkpoint| {
+            checkpoints.push((checkpoint.chunk_index, checkpoint.record_count));
+            async { Ok(()) }
+        })
+        .await?;
+
+    assert_eq!(jobs.len(), 3);
+    assert_eq!(jobs[0].id, job.id);
+    assert_eq!(checkpoints, vec![(0, 2), (1, 2), (2, 1)]);
+
+    for job in jobs {
+        let job = job.complete(&conn).await?;
+        assert_eq!(job.number_records_failed, Some(0));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_bulk_ingest_csv() -> Result<()> {
+    let conn = get_test_connection().expect("No connection present");
+
+    let job = BulkDmlJob::create(&conn, BulkApiDmlOperation::Insert, "Account".to_owned()).await?;
+    job.ingest_csv(
+        &conn,
+        tokio_stream::iter(vec![
+            Ok(Bytes::from("Name\n")),
+            Ok(Bytes::from("CSV Test 1\nCSV Test 2\n")),
+        ]),
+    )
+    .await?;
+
+    let job = job.close(&conn).await?.complete(&conn).await?;
+    assert_eq!(job.number_records_processed, Some(2));
+    assert_eq!(job.number_records_failed, Some(0));
+
+    Ok(())
+}
+
+#[test]
+fn test_bulk_dml_job_round_trip() -> Result<()> {
+    let value = serde_json::json!({
+        "id": "7503600000AAAAAAAA",
+        "assignmentRuleId": null,
+        "columnDelimiter": "COMMA",
+        "contentType": "CSV",
+        "externalIdFieldName": null,
+        "lineEnding": "LF",
+        "object": "Account",
+        "operation": "insert",
+        "apiVersion": 52.0,
+        "concurrencyMode": "Parallel",
+        "contentUrl": "services/data/v52.0/jobs/ingest/7503600000AAAAAAAA/batches",
+        "createdById": "00536000000AAAAAAA",
+        "createdDate": "2021-11-19T01:51:47.000+0000",
+        "jobType": "V2Ingest",
+        "state": "JobComplete",
+        "systemModstamp": "2021-11-19T01:52:47.000+0000",
+        "apexProcessingTime": 0,
+        "apiActiveProcessingTime": 10,
+        "numberRecordsFailed": 1,
+        "numberRecordsProcessed": 2,
+        "retries": 0,
+        "totalProcessingTime": 20
+    });
+
+    let job: super::BulkDmlJob = serde_json::from_value(value.clone())?;
+    let serialized = serde_json::to_value(&job)?;
+    let job: super::BulkDmlJob = serde_json::from_value(serialized)?;
+
+    assert_eq!(job.state, super::BulkJobStatus::JobComplete);
+    assert_eq!(job.number_records_failed, Some(1));
+    assert_eq!(
+        serde_json::to_value(&job)?["createdDate"],
+        value["createdDate"]
+    );
+
+    Ok(())
+}
+
+fn get_job_json(state: &str) -> String {
+    serde_json::json!({
+        "id": "7503600000AAAAAAAA",
+        "assignmentRuleId": null,
+        "columnDelimiter": "COMMA",
+        "contentType": "CSV",
+        "externalIdFieldName": null,
+        "lineEnding": "LF",
+        "object": "Account",
+        "operation": "insert",
+        "apiVersion": 52.0,
+        "concurrencyMode": "Parallel",
+        "contentUrl": "services/data/v52.0/jobs/ingest/7503600000AAAAAAAA/batches",
+        "createdById": "00536000000AAAAAAA",
+        "createdDate": "2021-11-19T01:51:47.000+0000",
+        "jobType": "V2Ingest",
+        "state": state,
+        "systemModstamp": "2021-11-19T01:52:47.000+0000"
+    })
+    .to_string()
+}
+
+/// Wait until the server has received `expected` requests, failing after a bound.
+async fn wait_for_requests(count: &AtomicUsize, expected: usize) -> Result<()> {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while count.load(Ordering::SeqCst) < expected {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bulk_job_guard() -> Result<()> {
+    let (conn, count) = serve_responses(vec![
+        ("200 OK", get_job_json("Open")),
+        ("200 OK", get_job_json("UploadComplete")),
+        ("200 OK", get_job_json("Open")),
+        ("200 OK", get_job_json("Aborted")),
+    ])
+    .await?;
+
+    // A closed guard does nothing further.
+    let guard =
+        BulkDmlJob::create_guarded(&conn, BulkApiDmlOperation::Insert, "Account".to_owned())
+            .await?;
+    let job = guard.close().await?;
+    assert_eq!(job.state, BulkJobStatus::UploadComplete);
+
+    // A released guard leaves its job open.
+    let guard: BulkJobGuard =
+        serde_json::from_str::<BulkDmlJob>(&get_job_json("Open"))?.guard(&conn);
+    assert_eq!(guard.release().state, BulkJobStatus::Open);
+
+    // A dropped guard aborts its open job. Had either guard above sent an
+    // abort, it would have taken a response meant for these requests.
+    let guard =
+        BulkDmlJob::create_guarded(&conn, BulkApiDmlOperation::Insert, "Account".to_owned())
+            .await?;
+    assert_eq!(guard.state, BulkJobStatus::Open);
+
+    drop(guard);
+    wait_for_requests(&count, 4).await?;
+    assert_eq!(count.load(Ordering::SeqCst), 4);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sobject_csv_external_id_reference() -> Result<()> {
+    let contact_type = get_test_sobject_type("Contact", vec![], vec![])?;
+    let first = SObject::new(&contact_type)
+        .with_str("LastName", "One")
+        .with_external_id_reference("Account", "MyExtId__c", "abc");
+    let second = SObject::new(&contact_type).with_str("LastName", "Two, Jr.");
+
+    let mut encoder = SObjectCsvEncoder::new(BulkCsvFormat::default());
+    assert_eq!(
+        &encoder.encode(&first).await?[..],
+        b"Account.MyExtId__c,lastname\nabc,One\n"
+    );
+    assert_eq!(&encoder.encode(&second).await?[..], b",\"Two, Jr.\"\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sobject_csv_columns() -> Result<()> {
+    let contact_type = get_test_sobject_type("Contact", vec![], vec![])?;
+    let first = SObject::new(&contact_type).with_str("LastName", "One");
+    let second = SObject::new(&contact_type)
+        .with_str("LastName", "Two")
+        .with_str("Title", "CEO");
+
+    // Fields first seen after the header is written are an error, not dropped.
+    let mut encoder = SObjectCsvEncoder::new(BulkCsvFormat::default());
+    encoder.encode(&first).await?;
+    assert!(encoder.encode(&second).await.is_err());
+
+    let mut content = Vec::new();
+    let mut stream = super::new_sobject_bytes_stream(
+        Box::pin(tokio_stream::iter(vec![first.clone(), second.clone()])),
+        BulkCsvFormat::default(),
+    );
+    while let Some(chunk) = stream.next().await {
+        match chunk {
+            Ok(chunk) => content.extend_from_slice(&chunk),
+            Err(_) => break,
+        }
+    }
+    assert_eq!(&content[..], b"lastname\nOne\n");
+
+    // Explicit columns admit fields absent from the first record.
+    let mut encoder =
+        SObjectCsvEncoder::new(BulkCsvFormat::default()).with_columns(&["LastName", "Title"]);
+    assert_eq!(
+        &encoder.encode(&first).await?[..],
+        b"LastName,Title\nOne,\n"
+    );
+    assert_eq!(&encoder.encode(&second).await?[..], b"Two,CEO\n");
+    assert!(encoder.finish().await?.is_empty());
+
+    // With no records, the header is still written.
+    let mut encoder =
+        SObjectCsvEncoder::new(BulkCsvFormat::default()).with_columns(&["LastName", "Title"]);
+    assert_eq!(&encoder.finish().await?[..], b"LastName,Title\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sobject_csv_related_records() -> Result<()> {
+    let account_type = get_test_sobject_type("Account", vec![], vec![])?;
+    let contact_type = get_test_sobject_type("Contact", vec![], vec![])?;
+    let contact = SObject::new(&contact_type).with_str("LastName", "Test");
+    let account = SObject::new(&account_type).with_str("Name", "Test");
+
+    // As from `SELECT Name, (SELECT LastName FROM Contacts) FROM Account`
+    let with_children = account
+        .clone()
+        .with_child_records("Contacts", vec![contact.clone()]);
+    let mut encoder = SObjectCsvEncoder::new(BulkCsvFormat::default());
+    let err = encoder.encode(&with_children).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<SalesforceError>(),
+        Some(SalesforceError::SchemaError(_))
+    ));
+
+    // As from `SELECT Account.Name FROM Contact`
+    let with_parent = contact.with_relationship("Account", account);
+    let mut encoder = SObjectCsvEncoder::new(BulkCsvFormat::default());
+    let err = encoder.encode(&with_parent).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<SalesforceError>(),
+        Some(SalesforceError::SchemaError(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bulk_content_type_decoding() -> Result<()> {
+    let sobject_type = get_test_sobject_type(
+        "Account",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            get_test_field_describe("Name", "xsd:string", "string"),
+        ],
+        vec![],
+    )?;
+
+    let records: VecDeque<SObject> = BulkApiContentType::CSV
+        .decode_records(
+            b"Id,Name\n001000000000000AAA,Test\n",
+            &sobject_type,
+            BulkCsvFormat::default(),
+        )
+        .await?;
+    assert_eq!(records.len(), 1);
+    assert_eq!(
+        records[0].get("Name"),
+        Some(&FieldValue::String("Test".to_owned()))
+    );
+
+    let records: VecDeque<SObject> = BulkApiContentType::JSON
+        .decode_records(
+            br#"[{"Id": "001000000000000AAA", "Name": "Test"}]"#,
+            &sobject_type,
+            BulkCsvFormat::default(),
+        )
+        .await?;
+    assert_eq!(records.len(), 1);
+    assert_eq!(
+        records[0].get("Name"),
+        Some(&FieldValue::String("Test".to_owned()))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_bulk_query_job_create_request_content_type() {
+    let request = BulkQueryJobCreateRequest::new_with_content_type(
+        "SELECT Id FROM Account".to_owned(),
+        false,
+        BulkApiContentType::JSON,
+    );
+    let body = request.get_body().unwrap();
+
+    assert_eq!(body["contentType"], "JSON");
+    assert_eq!(body["operation"], "query");
+    assert_eq!(BulkApiContentType::JSON.get_mime_type(), "application/json");
+
+    let body = BulkQueryJobCreateRequest::new("SELECT Id FROM Account".to_owned(), true)
+        .get_body()
+        .unwrap();
+    assert_eq!(body["contentType"], "CSV");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_bytes_stream_preserves_order() -> Result<()> {
+    let accounts: Vec<Account> = (0..2500)
+        .map(|i| Account {
+            id: None,
+            name: format!("Account {}", i),
+        })
+        .collect();
+
+    let mut content = BytesMut::new();
+    let mut stream = super::new_bytes_stream(
+        Box::pin(tokio_stream::iter(accounts)),
+        BulkCsvFormat::default(),
+    );
+    while let Some(chunk) = stream.next().await {
+        content.extend_from_slice(&chunk?);
+    }
+
+    let expected: String = std::iter::once("Id,Name\n".to_owned())
+        .chain((0..2500).map(|i| format!(",Account {}\n", i)))
+        .collect();
+    assert_eq!(std::str::from_utf8(&content)?, expected);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_bytes_stream_yields_before_source_ends() -> Result<()> {
+    // One full chunk of records, then a source that never ends.
+    let accounts = tokio_stream::iter((0..1000).map(|i| Account {
+        id: None,
+        name: format!("Account {}", i),
+    }))
+    .chain(futures::stream::pending());
+
+    let mut stream = super::new_bytes_stream(Box::pin(accounts), BulkCsvFormat::default());
+    let chunk = tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await?
+        .unwrap()?;
+
+    assert!(chunk.starts_with(b"Id,Name\n,Account 0\n"));
+    assert!(chunk.ends_with(b",Account 999\n"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bulk_csv_format_round_trip() -> Result<()> {
+    let sobject_type = get_test_sobject_type(
+        "Account",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            get_test_field_describe("Name", "xsd:string", "string"),
+        ],
+        vec![],
+    )?;
+    let csv_format = BulkCsvFormat::new(BulkApiColumnDelimiter::Semicolon, BulkApiLineEnding::CRLF);
+
+    let mut content = BytesMut::new();
+    let mut stream = super::new_bytes_stream(
+        Box::pin(tokio_stream::iter(vec![
+            Account {
+                id: None,
+                name: "One; Two".to_owned(),
+            },
+            Account {
+                id: None,
+                name: "Three".to_owned(),
+            },
+        ])),
+        csv_format,
+    );
+    while let Some(chunk) = stream.next().await {
+        content.extend_from_slice(&chunk?);
+    }
+    assert_eq!(&content[..], b"Id;Name\r\n;\"One; Two\"\r\n;Three\r\n");
+
+    let records: VecDeque<SObject> = BulkApiContentType::CSV
+        .decode_records(
+            b"Id;Name\r\n001000000000000AAA;\"One; Two\"\r\n001000000000001AAA;Three\r\n",
+            &sobject_type,
+            csv_format,
+        )
+        .await?;
+    assert_eq!(records.len(), 2);
+    assert_eq!(
+        records[0].get("Name"),
+        Some(&FieldValue::String("One; Two".to_owned()))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bulk_csv_nulls_as_na() -> Result<()> {
+    let csv_format = BulkCsvFormat::default().with_nulls_as_na(true);
+
+    let mut content = BytesMut::new();
+    let mut stream = super::new_bytes_stream(
+        Box::pin(tokio_stream::iter(vec![Account {
+            id: None,
+            name: "One".to_owned(),
+        }])),
+        csv_format,
+    );
+    while let Some(chunk) = stream.next().await {
+        content.extend_from_slice(&chunk?);
+    }
+    assert_eq!(&content[..], b"Id,Name\n#N/A,One\n");
+
+    // Explicit nulls clear fields; absent fields are left unchanged.
+    let contact_type = get_test_sobject_type("Contact", vec![], vec![])?;
+    let first = SObject::new(&contact_type)
+        .with_str("LastName", "One")
+        .with_null("Title");
+    let second = SObject::new(&contact_type).with_null("LastName");
+
+    let mut encoder = SObjectCsvEncoder::new(csv_format);
+    assert_eq!(
+        &encoder.encode(&first).await?[..],
+        b"lastname,title\nOne,#N/A\n"
+    );
+    assert_eq!(&encoder.encode(&second).await?[..], b"#N/A,\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bulk_job_options() -> Result<()> {
+    let rule_id: SalesforceId = "01Q000000000001".try_into()?;
+    let options = BulkJobOptions::new()
+        .with_column_delimiter(BulkApiColumnDelimiter::Pipe)
+        .with_line_ending(BulkApiLineEnding::CRLF)
+        .with_external_id_field_name("MyExtId__c")
+        .with_assignment_rule(AssignmentRule::Specific(rule_id))
+        .with_nulls_as_na(true);
+
+    let body = BulkDmlJobCreateRequest::new_with_job_options(
+        BulkApiDmlOperation::Upsert,
+        "Lead".to_owned(),
+        &options,
+    )?
+    .get_body()
+    .unwrap();
+    assert_eq!(body["columnDelimiter"], "PIPE");
+    assert_eq!(body["lineEnding"], "CRLF");
+    assert_eq!(body["externalIdFieldName"], "MyExtId__c");
+    assert_eq!(body["assignmentRuleId"], rule_id.to_string());
+
+    assert!(BulkDmlJobCreateRequest::new_with_job_options(
+        BulkApiDmlOperation::Insert,
+        "Lead".to_owned(),
+        &BulkJobOptions::new().with_assignment_rule(AssignmentRule::ActiveDefault),
+    )
+    .is_err());
+
+    let conn = get_offline_connection()?;
+    conn.set_mode(Mode::DryRun);
+    let job = BulkDmlJob::create_with_job_options(
+        &conn,
+        BulkApiDmlOperation::Upsert,
+        "Lead".to_owned(),
+        &options,
+    )
+    .await?;
+    assert_eq!(job.get_csv_format(), options.get_csv_format());
+    assert!(job.get_csv_format().nulls_as_na);
+
+    Ok(())
+}
+
+/// Runs each ingest job created to completion.
+fn get_job_lifecycle_transport() -> RouteTransport {
+    RouteTransport::new()
+        .with_route(Method::POST, "/jobs/ingest", |_| {
+            json_response(200, &get_job_json("Open"))
+        })
+        .with_route(Method::PUT, "/batches", |_| raw_response(201, ""))
+        .with_route(Method::PATCH, "", |_| {
+            json_response(200, &get_job_json("UploadComplete"))
+        })
+        .with_any_route("", |_| json_response(200, &get_job_json("JobComplete")))
+}
+
+#[tokio::test]
+async fn test_bulk_job_default_assignment_rule() -> Result<()> {
+    let (conn, transport) = serve_routes(get_job_lifecycle_transport())?;
+    let rule_id: SalesforceId = "01Q000000000001".try_into()?;
+    let other_rule_id: SalesforceId = "01Q000000000002".try_into()?;
+    let create = |options: BulkJobOptions| {
+        let conn = conn.clone();
+        async move {
+            BulkDmlJob::create_with_job_options(
+                &conn,
+                BulkApiDmlOperation::Insert,
+                "Lead".to_owned(),
+                &options,
+            )
+            .await
+        }
+    };
+    // The body of the last job created since the previous call.
+    let take_body = || {
+        transport
+            .get_requests()
+            .drain(..)
+            .filter(|r| r.method == Method::POST)
+            .last()
+            .map(|r| r.json().unwrap())
+    };
+    let get_rule = || take_body().unwrap()["assignmentRuleId"].clone();
+
+    // A specific default rule is used unless the job names its own.
+    conn.set_assignment_rule(Some(AssignmentRule::Specific(rule_id)));
+    create(BulkJobOptions::new()).await?;
+    assert_eq!(get_rule(), rule_id.to_string());
+    create(BulkJobOptions::new().with_assignment_rule(AssignmentRule::Specific(other_rule_id)))
+        .await?;
+    assert_eq!(get_rule(), other_rule_id.to_string());
+    create(BulkJobOptions::new().with_assignment_rule(AssignmentRule::Disabled)).await?;
+    assert_eq!(get_rule(), serde_json::Value::Null);
+
+    // The active default rule cannot be expressed in the Bulk API,
+    // so each job must name its own.
+    conn.set_assignment_rule(Some(AssignmentRule::ActiveDefault));
+    assert!(create(BulkJobOptions::new()).await.is_err());
+    assert!(take_body().is_none());
+    create(BulkJobOptions::new().with_assignment_rule(AssignmentRule::Disabled)).await?;
+    assert_eq!(get_rule(), serde_json::Value::Null);
+
+    // The Bulk DML traits take the same options.
+    let records = || {
+        tokio_stream::iter(vec![Account {
+            id: None,
+            name: "Lead".to_owned(),
+        }])
+    };
+    assert!(records()
+        .bulk_insert(&conn, "Lead".to_owned())
+        .await
+        .is_err());
+    let job = records()
+        .bulk_insert_with_options(
+            &conn,
+            "Lead".to_owned(),
+            &BulkJobOptions::new().with_assignment_rule(AssignmentRule::Specific(other_rule_id)),
+        )
+        .await?;
+    assert_eq!(job.state, BulkJobStatus::JobComplete);
+    assert_eq!(get_rule(), other_rule_id.to_string());
+    records()
+        .bulk_upsert_with_options(
+            &conn,
+            "Lead".to_owned(),
+            "Ext__c".to_owned(),
+            &BulkJobOptions::new().with_assignment_rule(AssignmentRule::Disabled),
+        )
+        .await?;
+    let body = take_body().unwrap();
+    assert_eq!(body["externalIdFieldName"], "Ext__c");
+    assert_eq!(body["assignmentRuleId"], serde_json::Value::Null);
+
+    Ok(())
+}
+
+// Serves two pages of bulk query results, linked by a locator.
+fn get_query_job_json(state: &str) -> String {
+    serde_json::json!({
+        "id": "750000000000000AAA",
+        "operation": "query",
+        "object": "Account",
+        "createdById": "005000000000000AAA",
+        "createdDate": "2022-01-01T00:00:00.000+0000",
+        "systemModstamp": "2022-01-01T00:00:00.000+0000",
+        "state": state,
+        "concurrencyMode": "Parallel",
+        "contentType": "CSV",
+        "apiVersion": 52.0,
+        "lineEnding": "LF",
+        "columnDelimiter": "COMMA"
+    })
+    .to_string()
+}
+
+fn get_result_page(request: &RecordedRequest) -> Result<TransportResponse> {
+    let (locator, content) = if request.get_query_parameter("locator").is_some() {
+        ("null", "Id,Name\n001000000000001AAA,Two\n")
+    } else {
+        ("MjAwMA", "Id,Name\n001000000000000AAA,\"One, Inc.\"\n")
+    };
+    let mut response = raw_response(200, content)?;
+    response
+        .headers_mut()
+        .insert("Sforce-Locator", http::HeaderValue::from_static(locator));
+
+    Ok(response)
+}
+
+#[tokio::test]
+async fn test_bulk_query_stream_raw_csv() -> Result<()> {
+    let (conn, _) =
+        serve_routes(RouteTransport::new().with_route(Method::GET, "/results", get_result_page))?;
+    let job: BulkQueryJob = serde_json::from_str(&get_query_job_json("JobComplete"))?;
+
+    let mut content = BytesMut::new();
+    let mut stream = job.stream_raw_csv(&conn);
+    while let Some(chunk) = stream.next().await {
+        content.extend_from_slice(&chunk?);
+    }
+
+    assert_eq!(
+        &content[..],
+        b"Id,Name\n001000000000000AAA,\"One, Inc.\"\n001000000000001AAA,Two\n"
+    );
+
+    Ok(())
+}
+
+// Serves the failed and unprocessed records of an ingest job.
+fn get_job_results_transport() -> RouteTransport {
+    RouteTransport::new()
+        .with_route(Method::GET, "/failedResults", |_| {
+            raw_response(
+                200,
+                "\"sf__Id\",\"sf__Error\",Name\n\
+                 \"\",\"REQUIRED_FIELD_MISSING:Required fields are missing: [Name]:Name --\",\"\"\n\
+                 \"001000000000001AAA\",\"UNABLE_TO_LOCK_ROW:unable to obtain exclusive access to this record:--\",\"Two\"\n",
+            )
+        })
+        .with_route(Method::GET, "/unprocessedrecords", |_| {
+            raw_response(200, "Name\nThree\n")
+        })
+}
+
+#[tokio::test]
+async fn test_bulk_failed_and_unprocessed_records() -> Result<()> {
+    let (conn, _) = serve_routes(get_job_results_transport())?;
+    let sobject_type = get_test_sobject_type(
+        "Account",
+        vec![get_test_field_describe("Name", "xsd:string", "string")],
+        vec![],
+    )?;
+    let job = serde_json::from_str::<BulkDmlJob>(&get_job_json("Failed"))?;
+
+    let failed = job
+        .get_failed_records::<SObject>(&conn)
+        .await?
+        .collect::<Result<Vec<_>>>()
+        .await?;
+    assert_eq!(failed.len(), 2);
+    assert_eq!(failed[0].id, None);
+    assert_eq!(failed[0].get_error_code(), "REQUIRED_FIELD_MISSING");
+    assert_eq!(failed[1].id, Some("001000000000001AAA".try_into()?));
+    assert_eq!(failed[1].get_error_code(), "UNABLE_TO_LOCK_ROW");
+    assert_eq!(
+        failed[1].get_sobject(&sobject_type)?.get("Name"),
+        Some(&FieldValue::String("Two".to_owned()))
+    );
+
+    let unprocessed = job
+        .get_unprocessed_records::<SObject>(&conn)
+        .await?
+        .collect::<Result<Vec<_>>>()
+        .await?;
+    assert_eq!(unprocessed.len(), 1);
+    assert_eq!(
+        unprocessed[0].get_sobject(&sobject_type)?.get("Name"),
+        Some(&FieldValue::String("Three".to_owned()))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bulk_ingest_split() -> Result<()> {
+    let conn = get_offline_connection()?;
+    conn.set_mode(Mode::DryRun);
+    let accounts = |n| {
+        tokio_stream::iter((0..n).map(|i| Account {
+            id: None,
+            name: format!("Account {}", i),
+        }))
+    };
+
+    // "Id,Name\n" is 8 bytes and ",Account N\n" 11, so three rows fit in 41 bytes.
+    let job = BulkDmlJob::create(&conn, BulkApiDmlOperation::Insert, "Account".to_owned()).await?;
+    let metrics = MetricsRecorder::new();
+    let summary = job
+        .ingest_split_with_metrics(
+            &conn,
+            accounts(7),
+            &BulkIngestLimits::new().with_max_bytes(41),
+            &metrics,
+        )
+        .await?;
+
+    assert_eq!(summary.jobs.len(), 3);
+    assert_eq!(summary.jobs[0].job.id, job.id);
+    assert_eq!(
+        summary
+            .jobs
+            .iter()
+            .map(|j| (j.record_count, j.byte_count))
+            .collect::<Vec<_>>(),
+        vec![(3, 41), (3, 41), (1, 19)]
+    );
+    assert_eq!(summary.get_record_count(), 7);
+    let recorded = metrics.get_metrics();
+    assert_eq!(
+        (recorded.records, recorded.pages, recorded.bytes),
+        (7, 3, 101)
+    );
+    assert!(summary
+        .jobs
+        .iter()
+        .all(|j| j.job.state == BulkJobStatus::UploadComplete));
+
+    let summary = job
+        .ingest_split(
+            &conn,
+            accounts(5),
+            &BulkIngestLimits::new().with_max_records(2),
+        )
+        .await?;
+    assert_eq!(
+        summary
+            .jobs
+            .iter()
+            .map(|j| j.record_count)
+            .collect::<Vec<_>>(),
+        vec![2, 2, 1]
+    );
+
+    assert!(job
+        .ingest_split(
+            &conn,
+            accounts(1),
+            &BulkIngestLimits::new().with_max_bytes(10),
+        )
+        .await
+        .is_err());
+
+    Ok(())
+}
+
+// Accepts the first upload and rejects the second, and moves each job
+// to the state it is set to.
+fn get_ingest_split_transport() -> RouteTransport {
+    let uploads = AtomicUsize::new(0);
+    RouteTransport::new()
+        .with_route(Method::PUT, "/batches", move |_| {
+            if uploads.fetch_add(1, Ordering::SeqCst) == 0 {
+                raw_response(201, "")
+            } else {
+                json_response(
+                    400,
+                    r#"[{"errorCode": "INVALIDJOB", "message": "Rejected"}]"#,
+                )
+            }
+        })
+        .with_route(Method::PATCH, "", |r| {
+            json_response(200, &get_job_json(r.json()?["state"].as_str().unwrap()))
+        })
+        .with_any_route("", |_| json_response(200, &get_job_json("Open")))
+}
+
+#[tokio::test]
+async fn test_bulk_ingest_split_partial_failure() -> Result<()> {
+    let (conn, transport) = serve_routes(get_ingest_split_transport())?;
+    let accounts = tokio_stream::iter((0..5).map(|i| Account {
+        id: None,
+        name: format!("Account {}", i),
+    }));
+
+    let job = BulkDmlJob::create(&conn, BulkApiDmlOperation::Insert, "Account".to_owned()).await?;
+    let error = job
+        .ingest_split(
+            &conn,
+            accounts,
+            &BulkIngestLimits::new().with_max_records(3),
+        )
+        .await
+        .unwrap_err();
+
+    // The first job was uploaded and closed, and is reported with the error.
+    match error.downcast_ref::<SalesforceError>() {
+        Some(SalesforceError::IngestSplitError { summary, .. }) => {
+            assert_eq!(summary.jobs.len(), 1);
+            assert_eq!(summary.get_record_count(), 3);
+            assert_eq!(summary.jobs[0].job.state, BulkJobStatus::UploadComplete);
+        }
+        _ => panic!("Expected an IngestSplitError, got {:?}", error),
+    }
+
+    // The second job was created for the failed upload, and aborted.
+    assert_eq!(
+        transport
+            .get_requests()
+            .iter()
+            .map(|r| (
+                r.method.clone(),
+                r.json()
+                    .ok()
+                    .and_then(|b| b["state"].as_str().map(|s| s.to_owned()))
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (Method::POST, None),
+            (Method::PUT, None),
+            (Method::PATCH, Some("UploadComplete".to_owned())),
+            (Method::POST, None),
+            (Method::PUT, None),
+            (Method::PATCH, Some("Aborted".to_owned())),
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bulk_query_job_abort_delete_list() -> Result<()> {
+    let list = format!(
+        r#"{{"done": true, "records": [{}], "nextRecordsUrl": null}}"#,
+        get_query_job_json("InProgress")
+    );
+    let (conn, count) = serve_responses(vec![
+        ("200 OK", list),
+        ("200 OK", get_query_job_json("Aborted")),
+        ("204 No Content", String::new()),
+    ])
+    .await?;
+
+    let jobs = BulkQueryJob::list(&conn, None, None, None).await?;
+    assert!(jobs.done);
+    assert_eq!(jobs.records.len(), 1);
+
+    let job = jobs.records[0].abort(&conn).await?;
+    assert_eq!(job.get_state(), BulkJobStatus::Aborted);
+    job.delete(&conn).await?;
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bulk_job_progress_and_cancellation() -> Result<()> {
+    let (conn, count) = serve_responses(vec![
+        ("200 OK", get_job_json("InProgress")),
+        ("200 OK", get_job_json("Aborted")),
+    ])
+    .await?;
+    let job = serde_json::from_str::<BulkDmlJob>(&get_job_json("UploadComplete"))?;
+    let cancel = CancellationToken::new();
+    let reports = Mutex::new(Vec::new());
+
+    // Cancel as soon as the first status is reported, rather than waiting to poll again.
+    let result = job
+        .complete_with_cancellation(
+            &conn,
+            |p| {
+                reports.lock().unwrap().push(p.state);
+                cancel.cancel();
+            },
+            &cancel,
+        )
+        .await;
+
+    assert!(matches!(
+        result.unwrap_err().downcast_ref::<SalesforceError>(),
+        Some(SalesforceError::Cancelled)
+    ));
+    assert_eq!(*reports.lock().unwrap(), vec![BulkJobStatus::InProgress]);
+    // One status poll, then the abort.
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bulk_job_polling_timeout() -> Result<()> {
+    let (conn, count) = serve_responses(vec![("200 OK", get_job_json("InProgress")); 10]).await?;
+    conn.set_polling_options(
+        PollingOptions::new(Duration::from_millis(20))
+            .with_timeout(Some(Duration::from_millis(50))),
+    );
+    let job = serde_json::from_str::<BulkDmlJob>(&get_job_json("UploadComplete"))?;
+
+    let result = job.complete(&conn).await;
+
+    assert!(matches!(
+        result.unwrap_err().downcast_ref::<SalesforceError>(),
+        Some(SalesforceError::Timeout(t)) if *t == Duration::from_millis(50)
+    ));
+    assert!(count.load(Ordering::SeqCst) < 10);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bulk_ingest_reader() -> Result<()> {
+    let (conn, transport) =
+        serve_routes(
+            RouteTransport::new().with_route(Method::PUT, "/batches", |_| raw_response(201, "")),
+        )?;
+    let job = serde_json::from_str::<BulkDmlJob>(&get_job_json("Open"))?;
+
+    job.ingest_reader(&conn, std::io::Cursor::new(b"Name\nOne\nTwo\n".to_vec()))
+        .await?;
+
+    let upload = &transport.get_requests()[0];
+    assert!(upload.streamed);
+    assert_eq!(upload.get_header("Content-Type"), Some("text/csv"));
+    assert_eq!(upload.body, Bytes::from("Name\nOne\nTwo\n"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bulk_ingest_is_not_retried() -> Result<()> {
+    // The upload body is streamed, so it cannot be sent again on a 429.
+    let (conn, count) =
+        serve_responses(vec![("429 Too Many Requests", "{}"), ("201 Created", "{}")]).await?;
+    conn.set_retry_policy(Some(
+        RetryPolicy::new(3).with_initial_backoff(Duration::from_millis(1)),
+    ));
+    let job = serde_json::from_str::<BulkDmlJob>(&get_job_json("Open"))?;
+
+    assert!(job
+        .ingest_reader(&conn, std::io::Cursor::new(b"Name\nOne\n".to_vec()))
+        .await
+        .is_err());
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
+
+/// Lists three ranges of results across two pages of links, and serves each range.
+fn get_parallel_results_transport() -> RouteTransport {
+    let job = "/services/data/v52.0/jobs/query/750000000000000AAA";
+    let link = move |locator: &str| serde_json::json!({"resultLink": format!("{}/results?locator={}", job, locator)});
+
+    RouteTransport::new()
+        .with_route(Method::GET, "/resultPages", move |r| {
+            json_response(
+                200,
+                &if r.get_query_parameter("page").is_none() {
+                    serde_json::json!({
+                        "resultPages": [link("MA"), link("MQ")],
+                        "nextRecordsUrl": format!("{}/resultPages?page=2", job),
+                        "done": false
+                    })
+                } else {
+                    serde_json::json!({"resultPages": [link("Mg")], "nextRecordsUrl": null, "done": true})
+                },
+            )
+        })
+        .with_route(Method::GET, "/750000000000000AAA/results", |r| {
+            let name = match r.get_query_parameter("locator").as_deref() {
+                Some("MA") => "One",
+                Some("MQ") => "Two",
+                Some("Mg") => "Three",
+                locator => panic!("Unexpected locator {:?}", locator),
+            };
+            raw_response(200, format!("Name\n{}\n", name))
+        })
+}
+
+#[tokio::test]
+async fn test_bulk_query_results_parallel() -> Result<()> {
+    let (conn, _) = serve_routes(get_parallel_results_transport())?;
+    let sobject_type = get_test_sobject_type(
+        "Account",
+        vec![get_test_field_describe("Name", "xsd:string", "string")],
+        vec![],
+    )?;
+    let job: BulkQueryJob = serde_json::from_str(&get_query_job_json("JobComplete"))?;
+
+    let mut names: Vec<String> = job
+        .get_results_stream_parallel::<SObject>(&conn, &sobject_type, 2)
+        .map(|r| r.map(|s| s.get("Name").unwrap().as_string()))
+        .collect::<Result<Vec<String>>>()
+        .await?;
+    names.sort();
+
+    assert_eq!(names, vec!["One", "Three", "Two"]);
+
+    // Under adaptive concurrency, each range is admitted and recorded.
+    conn.set_adaptive_concurrency(Some(AdaptiveConcurrency::new(1, 4)));
+    let count = job
+        .get_results_stream_parallel::<SObject>(&conn, &sobject_type, 2)
+        .collect::<Result<Vec<SObject>>>()
+        .await?
+        .len();
+    assert_eq!(count, 3);
+
+    let controller = conn.get_concurrency_controller().unwrap();
+    assert_eq!(controller.get_in_flight(), 0);
+    assert_eq!(controller.get_limit(), 2);
+
+    Ok(())
+}
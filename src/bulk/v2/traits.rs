@@ -8,7 +8,7 @@ use crate::data::traits::{
 };
 use crate::{api::Connection, data::SObjectType, streams::ResultStream};
 
-use super::{BulkApiDmlOperation, BulkDmlJob, BulkDmlJobCreateRequest, BulkQueryJob};
+use super::{BulkApiDmlOperation, BulkDmlJob, BulkJobOptions, BulkQueryJob};
 
 #[async_trait]
 pub trait BulkQueryable: DynamicallyTypedSObject + SObjectDeserialization + Unpin {
@@ -53,9 +53,36 @@ pub trait SingleTypeBulkQueryable: SingleTypedSObject + SObjectDeserialization +
 
 impl<T> SingleTypeBulkQueryable for T where T: SingleTypedSObject + SObjectDeserialization + Unpin {}
 
+/// Create a job with `options`, upload `records`, and wait for the job to complete.
+async fn run_dml_job<K, T>(
+    conn: &Connection,
+    operation: BulkApiDmlOperation,
+    object: String,
+    options: &BulkJobOptions,
+    records: K,
+) -> Result<BulkDmlJob>
+where
+    K: Stream<Item = T> + Send + Sync + 'static,
+    T: SObjectSerialization + Serialize,
+{
+    let job = BulkDmlJob::create_with_job_options(conn, operation, object, options).await?;
+    job.ingest(conn, records).await?;
+    job.close(conn).await?;
+
+    job.complete(conn).await
+}
+
 #[async_trait]
 pub trait BulkInsertable {
     async fn bulk_insert(self, conn: &Connection, object: String) -> Result<BulkDmlJob>;
+
+    /// Insert with `options`, such as to run a specific assignment rule.
+    async fn bulk_insert_with_options(
+        self,
+        conn: &Connection,
+        object: String,
+        options: &BulkJobOptions,
+    ) -> Result<BulkDmlJob>;
 }
 
 #[async_trait]
@@ -65,20 +92,29 @@ where
     T: SObjectSerialization + Unpin + Serialize, // FIXME: undesirable but supports CSV
 {
     async fn bulk_insert(self, conn: &Connection, object: String) -> Result<BulkDmlJob> {
-        let conn = conn.clone();
-        let job = BulkDmlJob::create(&conn, BulkApiDmlOperation::Insert, object).await?;
-        job.ingest(&conn, self).await?;
-        job.close(&conn).await?;
-
-        let job = job.complete(&conn).await?;
+        self.bulk_insert_with_options(conn, object, &BulkJobOptions::new())
+            .await
+    }
 
-        Ok(job)
+    async fn bulk_insert_with_options(
+        self,
+        conn: &Connection,
+        object: String,
+        options: &BulkJobOptions,
+    ) -> Result<BulkDmlJob> {
+        run_dml_job(conn, BulkApiDmlOperation::Insert, object, options, self).await
     }
 }
 
 #[async_trait]
 pub trait SingleTypeBulkInsertable {
     async fn bulk_insert_t(self, conn: &Connection) -> Result<BulkDmlJob>;
+
+    async fn bulk_insert_with_options_t(
+        self,
+        conn: &Connection,
+        options: &BulkJobOptions,
+    ) -> Result<BulkDmlJob>;
 }
 
 #[async_trait]
@@ -88,25 +124,37 @@ where
     T: SObjectSerialization + SingleTypedSObject + Unpin + Serialize,
 {
     async fn bulk_insert_t(self, conn: &Connection) -> Result<BulkDmlJob> {
-        let conn = conn.clone();
-        let job = BulkDmlJob::create(
-            &conn,
+        self.bulk_insert_with_options_t(conn, &BulkJobOptions::new())
+            .await
+    }
+
+    async fn bulk_insert_with_options_t(
+        self,
+        conn: &Connection,
+        options: &BulkJobOptions,
+    ) -> Result<BulkDmlJob> {
+        run_dml_job(
+            conn,
             BulkApiDmlOperation::Insert,
             T::get_type_api_name().to_owned(),
+            options,
+            self,
         )
-        .await?;
-        job.ingest(&conn, self).await?;
-        job.close(&conn).await?;
-
-        let job = job.complete(&conn).await?;
-
-        Ok(job)
+        .await
     }
 }
 
 #[async_trait]
 pub trait BulkUpdateable {
     async fn bulk_update(self, conn: &Connection, object: String) -> Result<BulkDmlJob>;
+
+    /// Update with `options`, such as to run a specific assignment rule.
+    async fn bulk_update_with_options(
+        self,
+        conn: &Connection,
+        object: String,
+        options: &BulkJobOptions,
+    ) -> Result<BulkDmlJob>;
 }
 
 #[async_trait]
@@ -116,20 +164,29 @@ where
     T: SObjectSerialization + Unpin + Serialize, // FIXME: undesirable but supports CSV
 {
     async fn bulk_update(self, conn: &Connection, object: String) -> Result<BulkDmlJob> {
-        let conn = conn.clone();
-        let job = BulkDmlJob::create(&conn, BulkApiDmlOperation::Update, object).await?;
-        job.ingest(&conn, self).await?;
-        job.close(&conn).await?;
-
-        let job = job.complete(&conn).await?;
+        self.bulk_update_with_options(conn, object, &BulkJobOptions::new())
+            .await
+    }
 
-        Ok(job)
+    async fn bulk_update_with_options(
+        self,
+        conn: &Connection,
+        object: String,
+        options: &BulkJobOptions,
+    ) -> Result<BulkDmlJob> {
+        run_dml_job(conn, BulkApiDmlOperation::Update, object, options, self).await
     }
 }
 
 #[async_trait]
 pub trait SingleTypeBulkUpdateable {
     async fn bulk_update_t(self, conn: &Connection) -> Result<BulkDmlJob>;
+
+    async fn bulk_update_with_options_t(
+        self,
+        conn: &Connection,
+        options: &BulkJobOptions,
+    ) -> Result<BulkDmlJob>;
 }
 
 #[async_trait]
@@ -139,19 +196,23 @@ where
     T: SObjectSerialization + SingleTypedSObject + Unpin + Serialize,
 {
     async fn bulk_update_t(self, conn: &Connection) -> Result<BulkDmlJob> {
-        let conn = conn.clone();
-        let job = BulkDmlJob::create(
-            &conn,
+        self.bulk_update_with_options_t(conn, &BulkJobOptions::new())
+            .await
+    }
+
+    async fn bulk_update_with_options_t(
+        self,
+        conn: &Connection,
+        options: &BulkJobOptions,
+    ) -> Result<BulkDmlJob> {
+        run_dml_job(
+            conn,
             BulkApiDmlOperation::Update,
             T::get_type_api_name().to_owned(),
+            options,
+            self,
         )
-        .await?;
-        job.ingest(&conn, self).await?;
-        job.close(&conn).await?;
-
-        let job = job.complete(&conn).await?;
-
-        Ok(job)
+        .await
     }
 }
 
@@ -237,6 +298,16 @@ pub trait BulkUpsertable {
         object: String,
         external_id: String,
     ) -> Result<BulkDmlJob>;
+
+    /// Upsert on `external_id` with `options`, such as to run a specific
+    /// assignment rule. The external Id field in `options` is ignored.
+    async fn bulk_upsert_with_options(
+        self,
+        conn: &Connection,
+        object: String,
+        external_id: String,
+        options: &BulkJobOptions,
+    ) -> Result<BulkDmlJob>;
 }
 
 #[async_trait]
@@ -251,27 +322,38 @@ where
         object: String,
         external_id: String,
     ) -> Result<BulkDmlJob> {
-        let conn = conn.clone();
-        let job = conn
-            .execute(&BulkDmlJobCreateRequest::new_with_options(
-                BulkApiDmlOperation::Upsert,
-                object,
-                Some(external_id),
-                None,
-            ))
-            .await?;
-        job.ingest(&conn, self).await?;
-        job.close(&conn).await?;
-
-        let job = job.complete(&conn).await?;
+        self.bulk_upsert_with_options(conn, object, external_id, &BulkJobOptions::new())
+            .await
+    }
 
-        Ok(job)
+    async fn bulk_upsert_with_options(
+        self,
+        conn: &Connection,
+        object: String,
+        external_id: String,
+        options: &BulkJobOptions,
+    ) -> Result<BulkDmlJob> {
+        run_dml_job(
+            conn,
+            BulkApiDmlOperation::Upsert,
+            object,
+            &options.clone().with_external_id_field_name(&external_id),
+            self,
+        )
+        .await
     }
 }
 
 #[async_trait]
 pub trait SingleTypeBulkUpsertable {
     async fn bulk_upsert_t(self, conn: &Connection, external_id: String) -> Result<BulkDmlJob>;
+
+    async fn bulk_upsert_with_options_t(
+        self,
+        conn: &Connection,
+        external_id: String,
+        options: &BulkJobOptions,
+    ) -> Result<BulkDmlJob>;
 }
 
 #[async_trait]
@@ -281,20 +363,23 @@ where
     T: SObjectSerialization + SingleTypedSObject + Unpin + Serialize,
 {
     async fn bulk_upsert_t(self, conn: &Connection, external_id: String) -> Result<BulkDmlJob> {
-        let conn = conn.clone();
-        let job = conn
-            .execute(&BulkDmlJobCreateRequest::new_with_options(
-                BulkApiDmlOperation::Upsert,
-                T::get_type_api_name().to_owned(),
-                Some(external_id),
-                None,
-            ))
-            .await?;
-        job.ingest(&conn, self).await?;
-        job.close(&conn).await?;
-
-        let job = job.complete(&conn).await?;
+        self.bulk_upsert_with_options_t(conn, external_id, &BulkJobOptions::new())
+            .await
+    }
 
-        Ok(job)
+    async fn bulk_upsert_with_options_t(
+        self,
+        conn: &Connection,
+        external_id: String,
+        options: &BulkJobOptions,
+    ) -> Result<BulkDmlJob> {
+        run_dml_job(
+            conn,
+            BulkApiDmlOperation::Upsert,
+            T::get_type_api_name().to_owned(),
+            &options.clone().with_external_id_field_name(&external_id),
+            self,
+        )
+        .await
     }
 }
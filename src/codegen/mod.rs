@@ -0,0 +1,199 @@
+//! Generation of typed sObject structs from org describes.
+//!
+//! Structs for wide objects are tedious to write by hand, and easily fall out
+//! of step with the org. `generate_structs()` renders a Rust module with one
+//! struct per sObject, each deriving `SObjectRepresentation` through
+//! `baris_derive`. Every field is an `Option`, so that queries may select any
+//! subset of fields, and fields that are neither createable nor updateable are
+//! marked `#[baris(system)]` so that they are never sent back to Salesforce.
+//!
+//! The output may be checked in, or written to `OUT_DIR` from a build script
+//! and brought in with `include!`. The crate that compiles it must depend on
+//! `anyhow`, `baris_derive`, and `serde_derive`.
+
+use std::fmt::Write;
+
+use anyhow::Result;
+
+use crate::{
+    api::Connection,
+    data::{SObjectType, SoapType},
+    rest::describe::FieldDescribe,
+};
+
+#[cfg(test)]
+mod test;
+
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+/// The Rust type of a field's values, or `None` for fields that cannot be
+/// represented in a struct, such as base64 fields, whose content is
+/// retrieved separately.
+fn get_rust_type(soap_type: &SoapType) -> Option<&'static str> {
+    match soap_type {
+        SoapType::Address => Some("Address"),
+        SoapType::Any => Some("serde_json::Value"),
+        SoapType::Blob => None,
+        SoapType::Boolean => Some("bool"),
+        SoapType::Date => Some("Date"),
+        SoapType::DateTime => Some("DateTime"),
+        SoapType::Double => Some("f64"),
+        SoapType::Id => Some("SalesforceId"),
+        SoapType::Integer => Some("i32"),
+        SoapType::Long => Some("i64"),
+        SoapType::Geolocation => Some("Geolocation"),
+        SoapType::String => Some("String"),
+        SoapType::Time => Some("Time"),
+    }
+}
+
+/// Convert an API name, such as `SLA_Expiration__c`, to a snake-case
+/// Rust identifier, such as `sla_expiration_c`.
+pub fn get_field_ident(api_name: &str) -> String {
+    let chars: Vec<char> = api_name.chars().collect();
+    let mut ident = String::with_capacity(api_name.len() + 4);
+
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_ascii_lowercase());
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next_is_lower)
+            {
+                ident.push('_');
+            }
+        }
+
+        if c.is_ascii_alphanumeric() {
+            ident.push(c.to_ascii_lowercase());
+        } else if !ident.ends_with('_') {
+            ident.push('_');
+        }
+    }
+
+    let ident = ident.trim_matches('_').to_string();
+    if RUST_KEYWORDS.contains(&ident.as_str()) {
+        format!("r#{}", ident)
+    } else {
+        ident
+    }
+}
+
+/// Convert an API name, such as `Invoice_Line__c`, to a Rust type name,
+/// such as `InvoiceLineC`.
+pub fn get_struct_ident(api_name: &str) -> String {
+    api_name
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn is_system_field(field: &FieldDescribe) -> bool {
+    !field.createable && !field.updateable
+}
+
+/// Render a struct for `sobject_type`, without the imports it requires.
+pub fn generate_struct(sobject_type: &SObjectType) -> String {
+    let describe = sobject_type.get_describe();
+    let mut output = String::new();
+
+    writeln!(
+        output,
+        "#[derive(Debug, Clone, Serialize, Deserialize, SObjectRepresentation)]"
+    )
+    .unwrap();
+    writeln!(
+        output,
+        "#[baris(api_name = \"{}\")]",
+        sobject_type.get_api_name()
+    )
+    .unwrap();
+    writeln!(
+        output,
+        "pub struct {} {{",
+        get_struct_ident(sobject_type.get_api_name())
+    )
+    .unwrap();
+    writeln!(
+        output,
+        "    #[serde(rename = \"Id\", skip_serializing_if = \"Option::is_none\")]"
+    )
+    .unwrap();
+    writeln!(output, "    pub id: Option<SalesforceId>,").unwrap();
+
+    for field in describe
+        .get_fields()
+        .iter()
+        .filter(|f| !f.name.eq_ignore_ascii_case("id"))
+    {
+        let rust_type = match get_rust_type(&field.soap_type) {
+            Some(rust_type) => rust_type,
+            None => continue,
+        };
+
+        writeln!(
+            output,
+            "    #[serde(rename = \"{}\", skip_serializing_if = \"Option::is_none\")]",
+            field.name
+        )
+        .unwrap();
+        if is_system_field(field) {
+            writeln!(output, "    #[baris(system)]").unwrap();
+        }
+        writeln!(
+            output,
+            "    pub {}: Option<{}>,",
+            get_field_ident(&field.name),
+            rust_type
+        )
+        .unwrap();
+    }
+
+    writeln!(output, "}}").unwrap();
+    output
+}
+
+/// Render a module containing a struct for each of `sobject_types`.
+pub fn generate_module(sobject_types: &[SObjectType]) -> String {
+    let mut output = String::from(
+        "// Generated by baris::codegen. Do not edit.\n\n\
+         use anyhow::Result;\n\
+         use baris::prelude::*;\n\
+         use baris_derive::SObjectRepresentation;\n\
+         use serde_derive::{Deserialize, Serialize};\n",
+    );
+
+    for sobject_type in sobject_types {
+        output.push('\n');
+        output.push_str(&generate_struct(sobject_type));
+    }
+
+    output
+}
+
+impl Connection {
+    /// Describe each of `sobjects` and render a module containing a struct for each.
+    pub async fn generate_structs(&self, sobjects: &[&str]) -> Result<String> {
+        let mut sobject_types = Vec::with_capacity(sobjects.len());
+
+        for sobject in sobjects {
+            sobject_types.push(self.get_type(sobject).await?);
+        }
+
+        Ok(generate_module(&sobject_types))
+    }
+}
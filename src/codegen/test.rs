@@ -0,0 +1,64 @@
+use anyhow::Result;
+
+use super::{generate_module, generate_struct, get_field_ident, get_struct_ident};
+use crate::test_integration_base::{get_test_field_describe, get_test_sobject_type};
+
+#[test]
+fn test_identifiers() {
+    assert_eq!(get_field_ident("Name"), "name");
+    assert_eq!(get_field_ident("AccountId"), "account_id");
+    assert_eq!(
+        get_field_ident("SLAExpirationDate__c"),
+        "sla_expiration_date_c"
+    );
+    assert_eq!(get_field_ident("Custom_Field__c"), "custom_field_c");
+    assert_eq!(get_field_ident("Address2__c"), "address2_c");
+    assert_eq!(get_field_ident("Type"), "r#type");
+    assert_eq!(get_struct_ident("Account"), "Account");
+    assert_eq!(get_struct_ident("Invoice_Line__c"), "InvoiceLineC");
+}
+
+#[test]
+fn test_generate_struct() -> Result<()> {
+    let mut created_date = get_test_field_describe("CreatedDate", "xsd:dateTime", "datetime");
+    created_date["createable"] = serde_json::json!(false);
+    created_date["updateable"] = serde_json::json!(false);
+    let sobject_type = get_test_sobject_type(
+        "Invoice__c",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            get_test_field_describe("Name", "xsd:string", "string"),
+            get_test_field_describe("Account__c", "tns:ID", "reference"),
+            get_test_field_describe("Amount__c", "xsd:double", "currency"),
+            get_test_field_describe("Due__c", "xsd:date", "date"),
+            get_test_field_describe("Scan__c", "xsd:base64Binary", "base64"),
+            created_date,
+        ],
+        vec![],
+    )?;
+
+    assert_eq!(
+        generate_struct(&sobject_type),
+        r#"#[derive(Debug, Clone, Serialize, Deserialize, SObjectRepresentation)]
+#[baris(api_name = "Invoice__c")]
+pub struct InvoiceC {
+    #[serde(rename = "Id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<SalesforceId>,
+    #[serde(rename = "Name", skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "Account__c", skip_serializing_if = "Option::is_none")]
+    pub account_c: Option<SalesforceId>,
+    #[serde(rename = "Amount__c", skip_serializing_if = "Option::is_none")]
+    pub amount_c: Option<f64>,
+    #[serde(rename = "Due__c", skip_serializing_if = "Option::is_none")]
+    pub due_c: Option<Date>,
+    #[serde(rename = "CreatedDate", skip_serializing_if = "Option::is_none")]
+    #[baris(system)]
+    pub created_date: Option<DateTime>,
+}
+"#
+    );
+    assert!(generate_module(&[sobject_type]).contains("use baris::prelude::*;\n"));
+
+    Ok(())
+}
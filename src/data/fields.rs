@@ -0,0 +1,143 @@
+use std::fmt;
+
+use anyhow::Result;
+
+use super::{sobjects::*, types::*};
+use crate::errors::SalesforceError;
+use crate::rest::describe::FieldDescribe;
+
+/// A field of an sObject type, resolved against its describe.
+///
+/// Handles are obtained from `SObjectType::field()`, which fails if the field
+/// does not exist, and are used for typed access to `SObject` fields
+/// and as field references in queries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldHandle {
+    sobject_type: SObjectType,
+    index: usize,
+}
+
+impl FieldHandle {
+    pub(crate) fn new(sobject_type: &SObjectType, index: usize) -> FieldHandle {
+        FieldHandle {
+            sobject_type: sobject_type.clone(),
+            index,
+        }
+    }
+
+    pub fn get_sobject_type(&self) -> &SObjectType {
+        &self.sobject_type
+    }
+
+    pub fn get_describe(&self) -> &FieldDescribe {
+        &self.sobject_type.get_describe().get_fields()[self.index]
+    }
+
+    /// The API name of the field, as given by the describe.
+    pub fn get_name(&self) -> &str {
+        &self.get_describe().name
+    }
+
+    /// Check that `value` may be stored in this field: that its type
+    /// matches the field's type, that it is not null if the field is required,
+    /// and that text fits within the field's length.
+    pub fn validate(&self, value: &FieldValue) -> Result<()> {
+        let describe = self.get_describe();
+
+        let compatible = match value {
+            FieldValue::Null => describe.nillable,
+            _ => is_compatible(value, describe),
+        };
+
+        if !compatible {
+            return Err(SalesforceError::SchemaError(format!(
+                "{:?} is not a valid value for {}.{}",
+                value, self.sobject_type, describe.name
+            ))
+            .into());
+        }
+
+        if let FieldValue::String(s) = value {
+            if describe.length > 0 && s.chars().count() > describe.length as usize {
+                return Err(SalesforceError::SchemaError(format!(
+                    "Value is longer than the {} characters allowed in {}.{}",
+                    describe.length, self.sobject_type, describe.name
+                ))
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a value of `value`'s type may be stored in the field `describe`.
+/// Null is compatible with every field; whether it is permitted depends on
+/// whether the field is nillable.
+pub(crate) fn is_compatible(value: &FieldValue, describe: &FieldDescribe) -> bool {
+    matches!(
+        (value, describe.soap_type),
+        (FieldValue::Null, _)
+            | (_, SoapType::Any)
+            | (FieldValue::Address(_), SoapType::Address)
+            | (
+                FieldValue::Integer(_),
+                SoapType::Integer | SoapType::Long | SoapType::Double
+            )
+            | (FieldValue::Double(_), SoapType::Double)
+            | (FieldValue::Boolean(_), SoapType::Boolean)
+            | (FieldValue::String(_), SoapType::String)
+            | (FieldValue::DateTime(_), SoapType::DateTime)
+            | (FieldValue::Time(_), SoapType::Time)
+            | (FieldValue::Date(_), SoapType::Date)
+            | (FieldValue::Blob(_), SoapType::Blob)
+            | (FieldValue::Geolocation(_), SoapType::Geolocation)
+            | (FieldValue::Id(_), SoapType::Id)
+            | (FieldValue::Relationship(_), SoapType::Id)
+            | (FieldValue::CompositeReference(_), SoapType::Id)
+            | (FieldValue::ExternalIdReference { .. }, SoapType::Id)
+    )
+}
+
+impl fmt::Display for FieldHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.get_name())
+    }
+}
+
+/// A Rust type that holds the value of a field, for use with
+/// `SObject::get_typed()` and `SObject::put_typed()`.
+pub trait FieldType: Sized {
+    fn from_field_value(value: &FieldValue) -> Option<Self>;
+    fn into_field_value(self) -> FieldValue;
+}
+
+macro_rules! impl_field_type {
+    ($t:ty, $variant:ident) => {
+        impl FieldType for $t {
+            fn from_field_value(value: &FieldValue) -> Option<Self> {
+                match value {
+                    FieldValue::$variant(v) => Some(v.clone()),
+                    // `anyType` values are converted on a best-effort basis.
+                    FieldValue::Any(v) => serde_json::from_value(v.clone()).ok(),
+                    _ => None,
+                }
+            }
+
+            fn into_field_value(self) -> FieldValue {
+                FieldValue::$variant(self)
+            }
+        }
+    };
+}
+
+impl_field_type!(Address, Address);
+impl_field_type!(i64, Integer);
+impl_field_type!(f64, Double);
+impl_field_type!(bool, Boolean);
+impl_field_type!(String, String);
+impl_field_type!(DateTime, DateTime);
+impl_field_type!(Time, Time);
+impl_field_type!(Date, Date);
+impl_field_type!(SalesforceId, Id);
+impl_field_type!(Geolocation, Geolocation);
@@ -1,9 +1,13 @@
+pub mod fields;
 pub mod sobjects;
 #[cfg(test)]
 mod test;
 pub mod traits;
 pub mod types;
+pub mod validation;
 
+pub use fields::*;
 pub use sobjects::*;
 pub use traits::*;
 pub use types::*;
+pub use validation::*;
@@ -3,11 +3,13 @@ use std::convert::TryInto;
 use std::fmt;
 use std::ops::Deref;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use anyhow::{Error, Result};
 use serde_json::{json, Value};
 
 use super::{
+    fields::{FieldHandle, FieldType},
     traits::{
         DynamicallyTypedSObject, SObjectBase, SObjectDeserialization, SObjectSerialization,
         SObjectWithId, TypedSObject,
@@ -15,12 +17,14 @@ use super::{
     types::*,
 };
 use crate::errors::SalesforceError;
-use crate::rest::describe::SObjectDescribe;
+use crate::rest::describe::{ChildRelationshipDescribe, SObjectDescribe};
 
 #[derive(Debug)]
 pub struct SObjectTypeBody {
     api_name: String,
-    describe: SObjectDescribe,
+    describe: Arc<SObjectDescribe>,
+    record_type_ids: HashMap<String, SalesforceId>,
+    related_types: HashMap<String, SObjectType>,
 }
 
 impl PartialEq for SObjectTypeBody {
@@ -48,7 +52,47 @@ impl Clone for SObjectType {
 
 impl SObjectType {
     pub fn new(api_name: String, describe: SObjectDescribe) -> SObjectType {
-        SObjectType(Arc::new(SObjectTypeBody { api_name, describe }))
+        // Developer names are case-insensitive.
+        let record_type_ids = describe
+            .record_type_infos
+            .iter()
+            .map(|r| (r.developer_name.to_lowercase(), r.record_type_id))
+            .collect();
+
+        SObjectType(Arc::new(SObjectTypeBody {
+            api_name,
+            describe: Arc::new(describe),
+            record_type_ids,
+            related_types: HashMap::new(),
+        }))
+    }
+
+    /// A copy of this type that can also decode related records of the types
+    /// `related`, such as the parents selected through a polymorphic
+    /// relationship with `TYPEOF`.
+    pub fn with_related_types(&self, related: &[SObjectType]) -> SObjectType {
+        let mut related_types = self.related_types.clone();
+        related_types.extend(
+            related
+                .iter()
+                .map(|t| (t.api_name.to_lowercase(), t.clone())),
+        );
+
+        SObjectType(Arc::new(SObjectTypeBody {
+            api_name: self.api_name.clone(),
+            describe: Arc::clone(&self.describe),
+            record_type_ids: self.record_type_ids.clone(),
+            related_types,
+        }))
+    }
+
+    /// Get a type added with `with_related_types()`, or this type itself.
+    pub fn get_related_type(&self, api_name: &str) -> Option<&SObjectType> {
+        if self.api_name.eq_ignore_ascii_case(api_name) {
+            Some(self)
+        } else {
+            self.related_types.get(&api_name.to_lowercase())
+        }
     }
 
     pub fn get_describe(&self) -> &SObjectDescribe {
@@ -58,6 +102,36 @@ impl SObjectType {
     pub fn get_api_name(&self) -> &str {
         &self.api_name
     }
+
+    /// Get the Id of the record type with the given developer name.
+    pub fn record_type_id(&self, developer_name: &str) -> Result<SalesforceId> {
+        self.record_type_ids
+            .get(&developer_name.to_lowercase())
+            .copied()
+            .ok_or_else(|| {
+                SalesforceError::SchemaError(format!(
+                    "Record type {} does not exist on {}",
+                    developer_name, self.api_name
+                ))
+                .into()
+            })
+    }
+
+    /// Get a handle to the field `api_name`, which is matched case-insensitively.
+    pub fn field(&self, api_name: &str) -> Result<FieldHandle> {
+        self.describe
+            .get_fields()
+            .iter()
+            .position(|f| f.name.eq_ignore_ascii_case(api_name))
+            .map(|index| FieldHandle::new(self, index))
+            .ok_or_else(|| {
+                SalesforceError::SchemaError(format!(
+                    "Field {} does not exist on {}",
+                    api_name, self.api_name
+                ))
+                .into()
+            })
+    }
 }
 
 impl fmt::Display for SObjectType {
@@ -67,10 +141,14 @@ impl fmt::Display for SObjectType {
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
 pub enum FieldValue {
     // TODO: JunctionIdList?
     Address(Address),
-    Integer(i64), // TODO: long/short?
+    /// The value of an `int` or `long` field, or of a `double` field whose
+    /// whole-number value is too large to hold exactly in an `f64`, such as
+    /// an 18-digit external Id.
+    Integer(i64),
     Double(f64),
     Boolean(bool),
     String(String),
@@ -79,10 +157,24 @@ pub enum FieldValue {
     Date(Date),
     Id(SalesforceId),
     Relationship(SObject),
+    /// The records of a child relationship selected by a subquery, such as
+    /// `(SELECT LastName FROM Contacts)`.
+    ChildRecords(Vec<SObject>),
     Blob(Blob),
     Geolocation(Geolocation),
     Null,
     CompositeReference(String),
+    /// A lookup set by the value of an external Id field on the parent,
+    /// such as `Account.MyExtId__c`, rather than by Salesforce Id.
+    ExternalIdReference {
+        relationship: String,
+        field: String,
+        value: String,
+    },
+    /// The value of an `anyType` field, such as `AccountHistory.OldValue`,
+    /// whose type varies from record to record. `as_typed()` makes a
+    /// best-effort conversion to a typed value.
+    Any(serde_json::Value),
 }
 
 impl FieldValue {
@@ -134,35 +226,115 @@ impl FieldValue {
         matches!(self, FieldValue::Relationship(_))
     }
 
+    pub fn is_child_records(&self) -> bool {
+        matches!(self, FieldValue::ChildRecords(_))
+    }
+
     pub fn is_composite_reference(&self) -> bool {
         matches!(self, FieldValue::CompositeReference(_))
     }
 
+    pub fn is_external_id_reference(&self) -> bool {
+        matches!(self, FieldValue::ExternalIdReference { .. })
+    }
+
+    pub fn is_any(&self) -> bool {
+        matches!(self, FieldValue::Any(_))
+    }
+
+    /// Convert an `anyType` value to the most specific typed value it
+    /// represents. Strings are tried, in order, as a Salesforce Id (any
+    /// 15 or 18 alphanumeric characters), a date/time, a date, and a time,
+    /// and otherwise remain strings.
+    /// Objects and arrays remain `Any`; values of other variants are unchanged.
+    pub fn as_typed(&self) -> FieldValue {
+        let value = match self {
+            FieldValue::Any(value) => value,
+            other => return other.clone(),
+        };
+
+        match value {
+            serde_json::Value::Null => FieldValue::Null,
+            serde_json::Value::Bool(b) => FieldValue::Boolean(*b),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(i) => FieldValue::Integer(i),
+                None => n
+                    .as_f64()
+                    .map(FieldValue::Double)
+                    .unwrap_or_else(|| self.clone()),
+            },
+            serde_json::Value::String(s) => {
+                // Non-ASCII text cannot be an Id, and may not be sliced as one.
+                let id = if s.is_ascii() {
+                    SalesforceId::try_from(s.as_str()).ok()
+                } else {
+                    None
+                };
+
+                if let Some(id) = id {
+                    FieldValue::Id(id)
+                } else if let Ok(dt) = s.parse::<DateTime>() {
+                    FieldValue::DateTime(dt)
+                } else if let Ok(d) = s.parse::<Date>() {
+                    FieldValue::Date(d)
+                } else if let Ok(t) = s.parse::<Time>() {
+                    FieldValue::Time(t)
+                } else {
+                    FieldValue::String(s.clone())
+                }
+            }
+            serde_json::Value::Array(_) | serde_json::Value::Object(_) => self.clone(),
+        }
+    }
+
     pub fn is_blob(&self) -> bool {
         matches!(self, FieldValue::Blob(_))
     }
 
     pub fn from_str(input: &str, field_type: &SoapType) -> Result<FieldValue> {
         match field_type {
-            SoapType::Integer => Ok(FieldValue::Integer(input.parse()?)),
-            SoapType::Double => Ok(FieldValue::Double(input.parse()?)),
+            SoapType::Integer | SoapType::Long => Ok(FieldValue::Integer(
+                input.parse().map_err(|_| integer_out_of_range(input))?,
+            )),
+            SoapType::Double => match input.parse::<i64>() {
+                Ok(i) if !is_exact_in_f64(i) => Ok(FieldValue::Integer(i)),
+                _ => Ok(FieldValue::Double(input.parse()?)),
+            },
             SoapType::Boolean => Ok(FieldValue::Boolean(input.parse()?)),
             SoapType::String => Ok(FieldValue::String(input.to_owned())),
             SoapType::DateTime => Ok(FieldValue::DateTime(input.parse()?)),
             SoapType::Time => Ok(FieldValue::Time(input.parse()?)),
             SoapType::Date => Ok(FieldValue::Date(input.parse()?)),
             SoapType::Id => Ok(FieldValue::Id(input.try_into()?)),
+            SoapType::Any => Ok(FieldValue::Any(serde_json::Value::String(input.to_owned()))),
             _ => panic!("Unsupported type"), // TODO
         }
     }
 }
 
+/// The largest magnitude below which every integer is exactly representable in an `f64`.
+const MAX_EXACT_F64_INTEGER: i64 = 1 << 53;
+
+fn is_exact_in_f64(i: i64) -> bool {
+    i.unsigned_abs() <= MAX_EXACT_F64_INTEGER as u64
+}
+
+fn integer_out_of_range(input: &str) -> anyhow::Error {
+    SalesforceError::GeneralError(format!(
+        "{} is not an integer within the range of a 64-bit signed integer",
+        input
+    ))
+    .into()
+}
+
+fn not_a_string(kind: &str) -> anyhow::Error {
+    SalesforceError::SchemaError(format!("{} cannot be rendered as strings", kind)).into()
+}
+
 impl From<&FieldValue> for serde_json::Value {
     fn from(f: &FieldValue) -> serde_json::Value {
         match f {
-            FieldValue::Integer(i) => {
-                serde_json::Value::Number(serde_json::Number::from_f64(*i as f64).unwrap())
-            }
+            FieldValue::Integer(i) => serde_json::Value::Number((*i).into()),
             FieldValue::Double(i) => {
                 serde_json::Value::Number(serde_json::Number::from_f64(*i).unwrap())
             }
@@ -174,14 +346,70 @@ impl From<&FieldValue> for serde_json::Value {
             FieldValue::Id(i) => serde_json::Value::String(i.to_string()),
             FieldValue::Null => serde_json::Value::Null,
             FieldValue::Address(address) => serde_json::to_value(address).unwrap(), // This should be infallible
-            FieldValue::Relationship(_) => todo!(),
+            FieldValue::Relationship(r) => record_to_value(r),
+            FieldValue::ChildRecords(records) => json!({
+                "totalSize": records.len(),
+                "done": true,
+                "records": records.iter().map(record_to_value).collect::<Vec<Value>>(),
+            }),
             FieldValue::Blob(_) => todo!(),
             FieldValue::Geolocation(g) => serde_json::to_value(g).unwrap(), // This should be infallible
             FieldValue::CompositeReference(s) => serde_json::Value::String(s.clone()),
+            FieldValue::ExternalIdReference { field, value, .. } => json!({ field: value }),
+            FieldValue::Any(v) => v.clone(),
         }
     }
 }
 
+// Records carry their type so that polymorphic relationships can be decoded again.
+fn record_to_value(record: &SObject) -> serde_json::Value {
+    record
+        .to_value_with_options(true, false)
+        .unwrap_or(serde_json::Value::Null) // Infallible without the Id
+}
+
+impl From<DateTime> for FieldValue {
+    fn from(value: DateTime) -> FieldValue {
+        FieldValue::DateTime(value)
+    }
+}
+
+impl From<Date> for FieldValue {
+    fn from(value: Date) -> FieldValue {
+        FieldValue::Date(value)
+    }
+}
+
+impl From<Time> for FieldValue {
+    fn from(value: Time) -> FieldValue {
+        FieldValue::Time(value)
+    }
+}
+
+impl From<chrono::DateTime<chrono::Utc>> for FieldValue {
+    fn from(value: chrono::DateTime<chrono::Utc>) -> FieldValue {
+        FieldValue::DateTime(value.into())
+    }
+}
+
+impl From<chrono::NaiveDate> for FieldValue {
+    fn from(value: chrono::NaiveDate) -> FieldValue {
+        FieldValue::Date(value.into())
+    }
+}
+
+impl From<chrono::NaiveTime> for FieldValue {
+    fn from(value: chrono::NaiveTime) -> FieldValue {
+        FieldValue::Time(value.into())
+    }
+}
+
+impl From<SystemTime> for FieldValue {
+    fn from(value: SystemTime) -> FieldValue {
+        FieldValue::DateTime(value.into())
+    }
+}
+
 impl From<&FieldValue> for String {
     fn from(f: &FieldValue) -> String {
         f.as_string()
@@ -195,8 +423,42 @@ impl From<FieldValue> for String {
 }
 
 impl FieldValue {
-    pub fn as_string(&self) -> String {
+    /// The name under which this value is serialized for the field `key`.
+    /// External Id references are keyed by their relationship name.
+    pub(crate) fn get_field_name<'a>(&'a self, key: &'a str) -> &'a str {
         match self {
+            FieldValue::ExternalIdReference { relationship, .. } => relationship,
+            _ => key,
+        }
+    }
+
+    /// The Bulk API CSV column under which this value is serialized for the field `key`.
+    #[cfg(feature = "bulk")]
+    pub(crate) fn get_csv_column(&self, key: &str) -> String {
+        match self {
+            FieldValue::ExternalIdReference {
+                relationship,
+                field,
+                ..
+            } => format!("{}.{}", relationship, field),
+            _ => key.to_owned(),
+        }
+    }
+
+    /// Render this value as a string.
+    ///
+    /// # Panics
+    ///
+    /// If the value is not a scalar, such as an address or related records.
+    /// Use `try_as_string()` for values that may come from a query.
+    pub fn as_string(&self) -> String {
+        self.try_as_string().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Render this value as a string, or a `SchemaError` if it is not a
+    /// scalar, such as an address or related records.
+    pub fn try_as_string(&self) -> Result<String> {
+        Ok(match self {
             FieldValue::Integer(i) => format!("{}", i),
             FieldValue::Double(i) => format!("{}", i),
             FieldValue::Boolean(i) => format!("{}", i),
@@ -206,27 +468,26 @@ impl FieldValue {
             FieldValue::Date(i) => i.to_string(),
             FieldValue::Id(i) => i.to_string(),
             FieldValue::Null => "".to_string(),
-            FieldValue::Address(_) => panic!("Address fields cannot be rendered as strings."),
-            FieldValue::Relationship(_) => todo!(),
-            FieldValue::Blob(_) => todo!(),
-            FieldValue::Geolocation(_) => {
-                panic!("Geolocation fields cannot be rendered as strings.")
-            }
             FieldValue::CompositeReference(i) => i.clone(),
-        }
+            FieldValue::ExternalIdReference { value, .. } => value.clone(),
+            FieldValue::Any(serde_json::Value::String(i)) => i.clone(),
+            FieldValue::Any(i) => i.to_string(),
+            FieldValue::Address(_) => return Err(not_a_string("Address fields")),
+            FieldValue::Relationship(_) => return Err(not_a_string("Parent relationships")),
+            FieldValue::ChildRecords(_) => return Err(not_a_string("Child relationships")),
+            FieldValue::Blob(_) => return Err(not_a_string("Blob fields")),
+            FieldValue::Geolocation(_) => return Err(not_a_string("Geolocation fields")),
+        })
     }
 
-    fn from_json(value: &serde_json::Value, soap_type: SoapType) -> Result<FieldValue> {
+    pub(crate) fn from_json(value: &serde_json::Value, soap_type: SoapType) -> Result<FieldValue> {
         if let serde_json::Value::Null = value {
             return Ok(FieldValue::Null);
         }
 
         match soap_type {
             // TODO: Make these not clone.
-            SoapType::Any => Err(SalesforceError::SchemaError(
-                "Unable to convert value from JSON".to_string(),
-            )
-            .into()),
+            SoapType::Any => Ok(FieldValue::Any(value.clone())),
             SoapType::Address => Ok(FieldValue::Address(serde_json::from_value::<Address>(
                 value.clone(),
             )?)),
@@ -245,12 +506,17 @@ impl FieldValue {
             SoapType::Time => Ok(FieldValue::Time(serde_json::from_value::<Time>(
                 value.clone(),
             )?)),
-            SoapType::Double => Ok(FieldValue::Double(serde_json::from_value::<f64>(
-                value.clone(),
-            )?)),
-            SoapType::Integer => Ok(FieldValue::Integer(serde_json::from_value::<i64>(
-                value.clone(),
-            )?)),
+            SoapType::Double => match value.as_i64() {
+                Some(i) if !is_exact_in_f64(i) => Ok(FieldValue::Integer(i)),
+                _ => Ok(FieldValue::Double(serde_json::from_value::<f64>(
+                    value.clone(),
+                )?)),
+            },
+            SoapType::Integer | SoapType::Long => Ok(FieldValue::Integer(
+                value
+                    .as_i64()
+                    .ok_or_else(|| integer_out_of_range(&value.to_string()))?,
+            )),
             SoapType::Id => Ok(FieldValue::Id(serde_json::from_value::<SalesforceId>(
                 value.clone(),
             )?)),
@@ -270,6 +536,17 @@ pub struct SObject {
     pub fields: HashMap<String, FieldValue>,
 }
 
+/// A field whose value differs between two records, as found by `SObject::diff()`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FieldChange {
+    /// The field's name, in lowercase.
+    pub field: String,
+    /// The field's value in the original record, or `None` if it was absent.
+    pub old_value: Option<FieldValue>,
+    /// The field's value in the changed record, or `None` if it is absent.
+    pub new_value: Option<FieldValue>,
+}
+
 impl SObjectWithId for SObject {
     fn get_id(&self) -> FieldValue {
         self.get("id").unwrap_or(&FieldValue::Null).clone()
@@ -299,7 +576,7 @@ impl SObjectSerialization for SObject {
         let mut map = serde_json::Map::new();
 
         for (k, v) in self.fields.iter() {
-            map.insert(k.to_string(), v.into());
+            map.insert(v.get_field_name(k).to_string(), v.into());
         }
 
         Ok(serde_json::Value::Object(map))
@@ -358,12 +635,19 @@ impl SObjectDeserialization for SObject {
             for k in content.keys() {
                 // Get the describe for this field.
                 if k != "attributes" {
-                    let describe = sobjecttype.get_describe().get_field(k).unwrap();
-
-                    ret.put(
-                        &k.to_lowercase(),
-                        FieldValue::from_json(value.get(k).unwrap(), describe.soap_type)?,
-                    );
+                    let field_value = match sobjecttype.get_describe().get_field(k) {
+                        Some(describe) => {
+                            FieldValue::from_json(value.get(k).unwrap(), describe.soap_type)?
+                        }
+                        None => match get_child_relationship(k, sobjecttype) {
+                            Some(child) => {
+                                child_records_from_json(child, value.get(k).unwrap(), sobjecttype)?
+                            }
+                            None => relationship_from_json(k, value.get(k).unwrap(), sobjecttype)?,
+                        },
+                    };
+
+                    ret.put(&k.to_lowercase(), field_value);
                 }
             }
             Ok(ret)
@@ -375,6 +659,109 @@ impl SObjectDeserialization for SObject {
     }
 }
 
+fn get_child_relationship<'a>(
+    relationship: &str,
+    sobjecttype: &'a SObjectType,
+) -> Option<&'a ChildRelationshipDescribe> {
+    sobjecttype
+        .get_describe()
+        .child_relationships
+        .iter()
+        .find(|r| r.relationship_name.eq_ignore_ascii_case(relationship))
+}
+
+// Decode the records selected by a subquery on the child relationship `child`,
+// which are nested as a query result. Only the first batch of a large subquery
+// result is included; the remainder must be retrieved from its `nextRecordsUrl`.
+fn child_records_from_json(
+    child: &ChildRelationshipDescribe,
+    value: &serde_json::Value,
+    sobjecttype: &SObjectType,
+) -> Result<FieldValue> {
+    // Subqueries that match no records are null.
+    if value.is_null() {
+        return Ok(FieldValue::Null);
+    }
+
+    let records = value
+        .get("records")
+        .and_then(|r| r.as_array())
+        .ok_or_else(|| {
+            SalesforceError::GeneralError(format!(
+                "Invalid query result for {}.{}",
+                sobjecttype, child.relationship_name
+            ))
+        })?;
+    let child_type = sobjecttype
+        .get_related_type(&child.child_sobject)
+        .ok_or_else(|| {
+            SalesforceError::SchemaError(format!(
+                "The sObject {} is not a related type of {}",
+                child.child_sobject, sobjecttype
+            ))
+        })?;
+
+    Ok(FieldValue::ChildRecords(
+        records
+            .iter()
+            .map(|r| SObject::from_value(r, child_type))
+            .collect::<Result<Vec<SObject>>>()?,
+    ))
+}
+
+// Decode a parent record selected through the relationship `relationship`.
+fn relationship_from_json(
+    relationship: &str,
+    value: &serde_json::Value,
+    sobjecttype: &SObjectType,
+) -> Result<FieldValue> {
+    let field = sobjecttype
+        .get_describe()
+        .get_fields()
+        .iter()
+        .find(|f| {
+            f.relationship_name
+                .as_ref()
+                .is_some_and(|r| r.eq_ignore_ascii_case(relationship))
+        })
+        .ok_or_else(|| {
+            SalesforceError::SchemaError(format!(
+                "No field or relationship {} on {}",
+                relationship, sobjecttype
+            ))
+        })?;
+
+    if value.is_null() {
+        return Ok(FieldValue::Null);
+    }
+
+    // The record's own type distinguishes the branches of a polymorphic relationship.
+    let type_name = value
+        .pointer("/attributes/type")
+        .and_then(|t| t.as_str())
+        .or(match field.reference_to.as_slice() {
+            [target] => Some(target.as_str()),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            SalesforceError::SchemaError(format!(
+                "Unable to determine the type of {}.{}",
+                sobjecttype, relationship
+            ))
+        })?;
+    let related_type = sobjecttype.get_related_type(type_name).ok_or_else(|| {
+        SalesforceError::SchemaError(format!(
+            "The sObject {} is not a related type of {}",
+            type_name, sobjecttype
+        ))
+    })?;
+
+    Ok(FieldValue::Relationship(SObject::from_value(
+        value,
+        related_type,
+    )?))
+}
+
 impl SObject {
     pub fn new(sobject_type: &SObjectType) -> SObject {
         SObject {
@@ -383,6 +770,51 @@ impl SObject {
         }
     }
 
+    /// Create a record with each createable field's default from the describe:
+    /// its static default value, or its default picklist value.
+    /// Defaults given by formulas, and fields Salesforce populates on create,
+    /// are left for Salesforce to apply.
+    pub fn new_with_defaults(sobject_type: &SObjectType) -> Result<SObject> {
+        let mut sobject = SObject::new(sobject_type);
+
+        for field in sobject_type.get_describe().get_fields() {
+            if !field.createable {
+                continue;
+            }
+
+            if let Some(default) = field.default_value.as_ref().filter(|v| !v.is_null()) {
+                sobject.put(
+                    &field.name,
+                    FieldValue::from_json(default, field.soap_type)?,
+                );
+            } else if let Some(picklist_value) = field
+                .picklist_values
+                .iter()
+                .find(|p| p.active && p.default_value)
+            {
+                sobject.put(
+                    &field.name,
+                    FieldValue::String(picklist_value.value.clone()),
+                );
+            }
+        }
+
+        Ok(sobject)
+    }
+
+    /// The names of required fields without a value: those that are createable,
+    /// not nillable, and not populated by Salesforce on create.
+    pub fn required_missing(&self) -> Vec<String> {
+        self.sobject_type
+            .get_describe()
+            .get_fields()
+            .iter()
+            .filter(|f| f.createable && !f.nillable && !f.defaulted_on_create)
+            .filter(|f| matches!(self.get(&f.name), None | Some(FieldValue::Null)))
+            .map(|f| f.name.clone())
+            .collect()
+    }
+
     #[must_use]
     pub fn with_address(mut self, key: &str, value: Address) -> SObject {
         self.put(key, FieldValue::Address(value));
@@ -437,6 +869,34 @@ impl SObject {
         self
     }
 
+    #[must_use]
+    pub fn with_chrono_datetime(
+        mut self,
+        key: &str,
+        value: chrono::DateTime<chrono::Utc>,
+    ) -> SObject {
+        self.put(key, value.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_chrono_time(mut self, key: &str, value: chrono::NaiveTime) -> SObject {
+        self.put(key, value.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_chrono_date(mut self, key: &str, value: chrono::NaiveDate) -> SObject {
+        self.put(key, value.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_system_time(mut self, key: &str, value: SystemTime) -> SObject {
+        self.put(key, value.into());
+        self
+    }
+
     #[must_use]
     pub fn with_reference(mut self, key: &str, value: SalesforceId) -> SObject {
         self.put(key, FieldValue::Id(value));
@@ -449,6 +909,12 @@ impl SObject {
         self
     }
 
+    #[must_use]
+    pub fn with_child_records(mut self, key: &str, value: Vec<SObject>) -> SObject {
+        self.put(key, FieldValue::ChildRecords(value));
+        self
+    }
+
     // TODO: Blob, Geolocation
 
     #[must_use]
@@ -457,6 +923,26 @@ impl SObject {
         self
     }
 
+    /// Set the lookup `relationship` (such as `Account`) to the parent record
+    /// whose external Id `field` has `value`.
+    #[must_use]
+    pub fn with_external_id_reference(
+        mut self,
+        relationship: &str,
+        field: &str,
+        value: &str,
+    ) -> SObject {
+        self.put(
+            relationship,
+            FieldValue::ExternalIdReference {
+                relationship: relationship.to_owned(),
+                field: field.to_owned(),
+                value: value.to_owned(),
+            },
+        );
+        self
+    }
+
     #[must_use]
     pub fn with_geolocation(mut self, key: &str, value: Geolocation) -> SObject {
         self.put(key, FieldValue::Geolocation(value));
@@ -469,6 +955,14 @@ impl SObject {
         self
     }
 
+    /// Whether this record is in the Recycle Bin, if `IsDeleted` was queried.
+    pub fn is_deleted(&self) -> Option<bool> {
+        match self.get("IsDeleted") {
+            Some(FieldValue::Boolean(b)) => Some(*b),
+            _ => None,
+        }
+    }
+
     pub fn get(&self, key: &str) -> Option<&FieldValue> {
         self.fields.get(&key.to_lowercase())
     }
@@ -476,4 +970,105 @@ impl SObject {
     pub fn put(&mut self, key: &str, val: FieldValue) {
         self.fields.insert(key.to_lowercase(), val);
     }
+
+    /// Get the value of `field` as `T`, or `None` if it is absent or null.
+    pub fn get_typed<T: FieldType>(&self, field: &FieldHandle) -> Result<Option<T>> {
+        self.check_field(field)?;
+
+        match self.get(field.get_name()) {
+            None | Some(FieldValue::Null) => Ok(None),
+            Some(value) => Ok(Some(T::from_field_value(value).ok_or_else(|| {
+                SalesforceError::SchemaError(format!(
+                    "{}.{} does not hold a {}",
+                    self.sobject_type,
+                    field,
+                    std::any::type_name::<T>()
+                ))
+            })?)),
+        }
+    }
+
+    /// Set `field` to `value`, after validating it against the field's describe.
+    pub fn put_typed<T: FieldType>(&mut self, field: &FieldHandle, value: T) -> Result<()> {
+        self.check_field(field)?;
+
+        let value = value.into_field_value();
+        field.validate(&value)?;
+        self.put(field.get_name(), value);
+
+        Ok(())
+    }
+
+    /// A copy of this record with only those of `fields` it has values for.
+    pub fn subset(&self, fields: &[&str]) -> SObject {
+        let mut subset = SObject::new(&self.sobject_type);
+
+        for field in fields {
+            if let Some(value) = self.get(field) {
+                subset.put(field, value.clone());
+            }
+        }
+
+        subset
+    }
+
+    /// Copy the fields of `other`, a record of the same sObject type, into this record.
+    /// Fields this record already has are replaced only if `overwrite` is set.
+    pub fn merge(&mut self, other: &SObject, overwrite: bool) -> Result<()> {
+        if !self
+            .get_api_name()
+            .eq_ignore_ascii_case(other.get_api_name())
+        {
+            return Err(SalesforceError::SchemaError(format!(
+                "Cannot merge a {} record into a {} record",
+                other.sobject_type, self.sobject_type
+            ))
+            .into());
+        }
+
+        for (key, value) in other.fields.iter() {
+            if overwrite || !self.fields.contains_key(key) {
+                self.fields.insert(key.clone(), value.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The fields whose values differ between this record and `other`,
+    /// ordered by field name. A field absent from one record differs from
+    /// the same field set to null in the other.
+    pub fn diff(&self, other: &SObject) -> Vec<FieldChange> {
+        let mut fields: Vec<&String> = self.fields.keys().chain(other.fields.keys()).collect();
+        fields.sort();
+        fields.dedup();
+
+        fields
+            .into_iter()
+            .filter_map(|field| {
+                let old_value = self.fields.get(field);
+                let new_value = other.fields.get(field);
+
+                (old_value != new_value).then(|| FieldChange {
+                    field: field.clone(),
+                    old_value: old_value.cloned(),
+                    new_value: new_value.cloned(),
+                })
+            })
+            .collect()
+    }
+
+    fn check_field(&self, field: &FieldHandle) -> Result<()> {
+        if field.get_sobject_type() != &self.sobject_type {
+            return Err(SalesforceError::SchemaError(format!(
+                "Field {}.{} does not belong to {}",
+                field.get_sobject_type(),
+                field,
+                self.sobject_type
+            ))
+            .into());
+        }
+
+        Ok(())
+    }
 }
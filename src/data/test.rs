@@ -2,7 +2,13 @@ use anyhow::Result;
 use bytes::{BufMut, BytesMut};
 use futures::StreamExt;
 
-use crate::{prelude::*, test_integration_base::get_test_connection};
+use crate::{
+    prelude::*,
+    test_integration_base::{
+        get_test_connection, get_test_field_describe, get_test_record_type_describe,
+        get_test_sobject_type, get_test_sobject_type_with_children, serve_responses, Account,
+    },
+};
 
 use super::*;
 
@@ -64,6 +70,44 @@ fn test_datetimes_serialize() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_datetimes_parse_offsets() -> Result<()> {
+    let expected = DateTime::new(2021, 3, 14, 10, 30, 0, 0)?;
+
+    // Pacific Daylight Time, the first day after the 2021 change.
+    for value in [
+        "2021-03-14T10:30:00.000+0000",
+        "2021-03-14T10:30:00Z",
+        "2021-03-14T10:30:00.000+00:00",
+        "2021-03-14T03:30:00.000-0700",
+        "2021-03-14T03:30:00-07:00",
+        "2021-03-14T15:00:00.000+0430",
+    ] {
+        assert_eq!(value.parse::<DateTime>()?, expected, "{}", value);
+    }
+
+    // Pacific Standard Time, the day before.
+    assert_eq!(
+        "2021-03-13T02:30:00.000-08:00".parse::<DateTime>()?,
+        DateTime::new(2021, 3, 13, 10, 30, 0, 0)?
+    );
+
+    assert!("2021-03-14T10:30:00".parse::<DateTime>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_datetimes_round_trip_offsets() -> Result<()> {
+    let parsed: DateTime = serde_json::from_str("\"2021-11-07T01:30:00.250-07:00\"")?;
+    let serialized = serde_json::to_string(&parsed)?;
+
+    assert_eq!(serialized, "\"2021-11-07T08:30:00.250+0000\"");
+    assert_eq!(serde_json::from_str::<DateTime>(&serialized)?, parsed);
+
+    Ok(())
+}
+
 #[test]
 fn test_dates_parse() -> Result<()> {
     assert_eq!("2021-11-15".parse::<Date>()?, Date::new(2021, 11, 15)?);
@@ -112,6 +156,216 @@ fn test_times_serialize() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_chrono_conversions() -> Result<()> {
+    let naive_date = chrono::NaiveDate::from_ymd_opt(2021, 11, 15).unwrap();
+    let naive_time = chrono::NaiveTime::from_hms_milli_opt(1, 2, 3, 4).unwrap();
+    let utc = *DateTime::new(2021, 11, 19, 1, 51, 47, 323)?;
+
+    assert_eq!(
+        FieldValue::from(naive_date),
+        FieldValue::Date(Date::new(2021, 11, 15)?)
+    );
+    assert_eq!(
+        FieldValue::from(naive_time),
+        FieldValue::Time(Time::new(1, 2, 3, 4)?)
+    );
+    assert_eq!(
+        FieldValue::from(utc),
+        FieldValue::DateTime(DateTime::new(2021, 11, 19, 1, 51, 47, 323)?)
+    );
+    assert_eq!(
+        FieldValue::from(std::time::SystemTime::from(utc)),
+        FieldValue::DateTime(DateTime::new(2021, 11, 19, 1, 51, 47, 323)?)
+    );
+
+    let sobject = SObject::new(&get_test_sobject_type("Account", vec![], vec![])?)
+        .with_chrono_date("CloseDate", naive_date)
+        .with_chrono_datetime("LastActivity__c", utc);
+
+    assert_eq!(
+        sobject.get("CloseDate"),
+        Some(&FieldValue::Date(Date::new(2021, 11, 15)?))
+    );
+    assert_eq!(
+        sobject.get("LastActivity__c").unwrap().as_string(),
+        "2021-11-19T01:51:47.323+0000"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_external_id_reference_serialization() -> Result<()> {
+    let sobject = SObject::new(&get_test_sobject_type("Contact", vec![], vec![])?)
+        .with_str("LastName", "Test")
+        .with_external_id_reference("Account", "MyExtId__c", "abc");
+
+    assert!(sobject.get("account").unwrap().is_external_id_reference());
+    assert_eq!(
+        sobject.to_value()?,
+        serde_json::json!({"lastname": "Test", "Account": {"MyExtId__c": "abc"}})
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_record_type_id() -> Result<()> {
+    let sobject_type = get_test_sobject_type(
+        "Account",
+        vec![],
+        vec![get_test_record_type_describe(
+            "Business_Account",
+            "012360000000001AAA",
+        )],
+    )?;
+
+    assert_eq!(
+        sobject_type.record_type_id("Business_Account")?,
+        SalesforceId::new("012360000000001AAA")?
+    );
+    assert_eq!(
+        sobject_type.record_type_id("business_account")?,
+        SalesforceId::new("012360000000001AAA")?
+    );
+    assert!(sobject_type.record_type_id("Person_Account").is_err());
+
+    Ok(())
+}
+
+#[derive(serde_derive::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct AuditedAccount {
+    name: String,
+    created_date: Option<DateTime>,
+    #[serde(rename = "LastModifiedById")]
+    modifier: Option<SalesforceId>,
+    // Only the exact serialized name of a system field is omitted.
+    #[serde(rename = "created_date")]
+    created_date_note: Option<String>,
+}
+
+impl SObjectBase for AuditedAccount {
+    fn get_system_fields() -> &'static [&'static str] {
+        &["CreatedDate", "LastModifiedById"]
+    }
+}
+
+impl SingleTypedSObject for AuditedAccount {
+    fn get_type_api_name() -> &'static str {
+        "Account"
+    }
+}
+
+impl SObjectWithId for AuditedAccount {
+    fn get_id(&self) -> FieldValue {
+        FieldValue::Null
+    }
+
+    fn set_id(&mut self, _id: FieldValue) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_system_fields_not_serialized() -> Result<()> {
+    let account = AuditedAccount {
+        name: "Test".to_owned(),
+        created_date: Some("2021-11-15T00:00:00.000+0000".parse()?),
+        modifier: Some(SalesforceId::new("00536000000AAAA")?),
+        created_date_note: Some("Note".to_owned()),
+    };
+
+    assert_eq!(
+        account.to_value_with_options(true, false)?,
+        serde_json::json!({
+            "attributes": {"type": "Account"},
+            "Name": "Test",
+            "created_date": "Note"
+        })
+    );
+
+    Ok(())
+}
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AnnotatedContact {
+    last_name: String,
+    custom_field: Option<String>,
+    local_state: Option<String>,
+    account: Option<serde_json::Value>,
+}
+
+impl SObjectBase for AnnotatedContact {
+    fn get_system_fields() -> &'static [&'static str] {
+        &["Account"]
+    }
+
+    fn get_skipped_fields() -> &'static [&'static str] {
+        &["LocalState"]
+    }
+
+    fn get_field_renames() -> &'static [(&'static str, &'static str)] {
+        &[("CustomField", "Custom_Field__c"), ("Account", "Account")]
+    }
+}
+
+impl SingleTypedSObject for AnnotatedContact {
+    fn get_type_api_name() -> &'static str {
+        "Contact"
+    }
+}
+
+impl SObjectWithId for AnnotatedContact {
+    fn get_id(&self) -> FieldValue {
+        FieldValue::Null
+    }
+
+    fn set_id(&mut self, _id: FieldValue) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_field_renames_and_skipped_fields() -> Result<()> {
+    let sobject_type = get_test_sobject_type(
+        "Contact",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            get_test_field_describe("LastName", "xsd:string", "string"),
+        ],
+        vec![],
+    )?;
+
+    let contact = AnnotatedContact::from_value(
+        &serde_json::json!({
+            "attributes": {"type": "Contact"},
+            "LastName": "Smith",
+            "custom_field__c": "Value",
+            "LocalState": "Ignored",
+            "Account": {"Name": "Acme"}
+        }),
+        &sobject_type,
+    )?;
+
+    assert_eq!(contact.custom_field.as_deref(), Some("Value"));
+    assert_eq!(contact.local_state, None);
+    assert_eq!(contact.account, Some(serde_json::json!({"Name": "Acme"})));
+
+    let contact = AnnotatedContact {
+        local_state: Some("Local".to_owned()),
+        ..contact
+    };
+    assert_eq!(
+        contact.to_value()?,
+        serde_json::json!({"LastName": "Smith", "Custom_Field__c": "Value"})
+    );
+
+    Ok(())
+}
+
 #[tokio::test]
 #[ignore]
 async fn test_blob_retrieve() -> Result<()> {
@@ -150,3 +404,704 @@ async fn test_blob_retrieve() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_blob_download() -> Result<()> {
+    let (conn, _) = serve_responses(vec![("200 OK", "Foo"), ("200 OK", "Foo")]).await?;
+    let blob: Blob = "/services/data/v52.0/sobjects/ContentVersion/068000000000001AAA/VersionData"
+        .to_owned()
+        .try_into()?;
+
+    assert_eq!(&b"Foo"[..], &blob.bytes(&conn).await?[..]);
+
+    let path = std::env::temp_dir().join(format!("baris-blob-{}.txt", std::process::id()));
+    assert_eq!(3, blob.download_to_file(&conn, &path).await?);
+    assert_eq!(b"Foo", &std::fs::read(&path)?[..]);
+    std::fs::remove_file(&path)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_new_with_defaults() -> Result<()> {
+    let mut name = get_test_field_describe("Name", "xsd:string", "string");
+    name["nillable"] = serde_json::json!(false);
+    let mut industry = get_test_field_describe("Industry", "xsd:string", "picklist");
+    industry["picklistValues"] = serde_json::json!([
+        {"active": true, "defaultValue": false, "label": "Retail", "validFor": null, "value": "Retail"},
+        {"active": true, "defaultValue": true, "label": "Tech", "validFor": null, "value": "Tech"}
+    ]);
+    let mut active = get_test_field_describe("Active__c", "xsd:boolean", "boolean");
+    active["defaultValue"] = serde_json::json!(true);
+    active["defaultedOnCreate"] = serde_json::json!(true);
+    active["nillable"] = serde_json::json!(false);
+    let mut owner = get_test_field_describe("OwnerId", "tns:ID", "reference");
+    owner["defaultedOnCreate"] = serde_json::json!(true);
+    owner["nillable"] = serde_json::json!(false);
+    let mut rating = get_test_field_describe("Rating__c", "xsd:double", "double");
+    rating["defaultValue"] = serde_json::json!(5.0);
+    rating["createable"] = serde_json::json!(false);
+
+    let sobject_type = get_test_sobject_type(
+        "Account",
+        vec![name, industry, active, owner, rating],
+        vec![],
+    )?;
+
+    let account = SObject::new_with_defaults(&sobject_type)?;
+    assert_eq!(
+        account.get("Industry"),
+        Some(&FieldValue::String("Tech".to_owned()))
+    );
+    assert_eq!(account.get("Active__c"), Some(&FieldValue::Boolean(true)));
+    assert_eq!(account.get("OwnerId"), None);
+    assert_eq!(account.get("Rating__c"), None);
+    assert_eq!(account.required_missing(), vec!["Name".to_owned()]);
+
+    let account = account.with_str("Name", "Test");
+    assert!(account.required_missing().is_empty());
+    assert_eq!(
+        account.with_null("Name").required_missing(),
+        vec!["Name".to_owned()]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_field_handles() -> Result<()> {
+    let mut name = get_test_field_describe("Name", "xsd:string", "string");
+    name["nillable"] = serde_json::json!(false);
+    name["length"] = serde_json::json!(10);
+    let sobject_type = get_test_sobject_type(
+        "Account",
+        vec![
+            name,
+            get_test_field_describe("NumberOfEmployees", "xsd:int", "int"),
+        ],
+        vec![],
+    )?;
+    let contact_type = get_test_sobject_type(
+        "Contact",
+        vec![get_test_field_describe("Name", "xsd:string", "string")],
+        vec![],
+    )?;
+
+    let name = sobject_type.field("name")?;
+    let employees = sobject_type.field("NumberOfEmployees")?;
+    assert_eq!(name.get_name(), "Name");
+    assert_eq!(name.to_string(), "Name");
+    assert!(sobject_type.field("Industry").is_err());
+
+    let mut account = SObject::new(&sobject_type);
+    assert_eq!(account.get_typed::<String>(&name)?, None);
+
+    account.put_typed(&name, "Test".to_owned())?;
+    account.put_typed(&employees, 10i64)?;
+    assert_eq!(account.get_typed::<String>(&name)?, Some("Test".to_owned()));
+    assert_eq!(account.get_typed::<i64>(&employees)?, Some(10));
+    assert!(account.get_typed::<bool>(&name).is_err());
+
+    assert!(account.put_typed(&employees, true).is_err());
+    assert!(account
+        .put_typed(&name, "Much too long for this field".to_owned())
+        .is_err());
+    assert!(name.validate(&FieldValue::Null).is_err());
+    assert!(employees.validate(&FieldValue::Null).is_ok());
+
+    let contact_name = contact_type.field("Name")?;
+    assert!(account.get_typed::<String>(&contact_name).is_err());
+
+    assert_eq!(
+        crate::soql::FieldPath::from(&name),
+        crate::soql::FieldPath(vec!["Name".to_owned()])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_polymorphic_relationships() -> Result<()> {
+    let mut what_id = get_test_field_describe("WhatId", "tns:ID", "reference");
+    what_id["referenceTo"] = serde_json::json!(["Account", "Opportunity"]);
+    what_id["relationshipName"] = serde_json::json!("What");
+    let task_type = get_test_sobject_type(
+        "Task",
+        vec![
+            get_test_field_describe("Subject", "xsd:string", "string"),
+            what_id,
+        ],
+        vec![],
+    )?;
+    let account_type = get_test_sobject_type(
+        "Account",
+        vec![get_test_field_describe("Name", "xsd:string", "string")],
+        vec![],
+    )?;
+    let opportunity_type = get_test_sobject_type(
+        "Opportunity",
+        vec![get_test_field_describe("Amount", "xsd:double", "currency")],
+        vec![],
+    )?;
+    let records = serde_json::json!([
+        {
+            "attributes": {"type": "Task"},
+            "Subject": "Call",
+            "What": {"attributes": {"type": "Account"}, "Name": "Acme"}
+        },
+        {
+            "attributes": {"type": "Task"},
+            "Subject": "Follow up",
+            "What": {"attributes": {"type": "Opportunity"}, "Amount": 100.0}
+        },
+        {"attributes": {"type": "Task"}, "Subject": "Plan", "What": null}
+    ]);
+
+    // Each related record is decoded with the describe for its own type.
+    assert!(SObject::from_value(&records[0], &task_type).is_err());
+    let task_type = task_type.with_related_types(&[account_type, opportunity_type]);
+    let tasks = records
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| SObject::from_value(r, &task_type))
+        .collect::<Result<Vec<SObject>>>()?;
+
+    match tasks[0].get("What") {
+        Some(FieldValue::Relationship(what)) => {
+            assert_eq!(what.get_api_name(), "Account");
+            assert_eq!(
+                what.get("Name"),
+                Some(&FieldValue::String("Acme".to_owned()))
+            );
+        }
+        _ => panic!("Expected a relationship"),
+    }
+    match tasks[1].get("What") {
+        Some(FieldValue::Relationship(what)) => {
+            assert_eq!(what.get_api_name(), "Opportunity");
+            assert_eq!(what.get("Amount"), Some(&FieldValue::Double(100.0)));
+        }
+        _ => panic!("Expected a relationship"),
+    }
+    assert_eq!(tasks[2].get("What"), Some(&FieldValue::Null));
+
+    #[derive(serde_derive::Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Task {
+        what: Option<PolymorphicRecord>,
+    }
+
+    let task: Task = serde_json::from_value(records[0].clone())?;
+    let what = task.what.unwrap();
+    assert_eq!(what.get_type(), "Account");
+    assert_eq!(what.downcast::<Account>()?.unwrap().name, "Acme");
+
+    let task: Task = serde_json::from_value(records[1].clone())?;
+    let what = task.what.unwrap();
+    assert!(what.downcast::<Account>()?.is_none());
+    assert_eq!(what.get("Amount"), Some(&serde_json::json!(100.0)));
+
+    Ok(())
+}
+
+enum Party {
+    Account(Account),
+    Contact(AnnotatedContact),
+}
+
+impl PolymorphicSObject for Party {
+    fn variant_from_value(sobject_type: &str, value: &serde_json::Value) -> Option<Result<Self>> {
+        if sobject_type.eq_ignore_ascii_case(Account::get_type_api_name()) {
+            Some(deserialize_sobject(value).map(Party::Account))
+        } else if sobject_type.eq_ignore_ascii_case(AnnotatedContact::get_type_api_name()) {
+            Some(deserialize_sobject(value).map(Party::Contact))
+        } else {
+            None
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Party {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserialize_polymorphic(deserializer)
+    }
+}
+
+#[test]
+fn test_polymorphic_sobject() -> Result<()> {
+    #[derive(serde_derive::Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Task {
+        who: Option<Party>,
+    }
+
+    let task: Task = serde_json::from_value(serde_json::json!({
+        "Who": {"attributes": {"type": "Account"}, "Id": null, "Name": "Acme"}
+    }))?;
+    match task.who {
+        Some(Party::Account(account)) => assert_eq!(account.name, "Acme"),
+        _ => panic!("Expected an Account"),
+    }
+
+    match Party::from_typed_value(&serde_json::json!({
+        "attributes": {"type": "contact"},
+        "LastName": "Smith",
+        "Custom_Field__c": "Value"
+    }))? {
+        Party::Contact(contact) => assert_eq!(contact.custom_field.as_deref(), Some("Value")),
+        _ => panic!("Expected a Contact"),
+    }
+
+    assert!(Party::from_typed_value(&serde_json::json!({"attributes": {"type": "Lead"}})).is_err());
+    assert!(Party::from_typed_value(&serde_json::json!({"Name": "Untyped"})).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_parent_and_child_relationships() -> Result<()> {
+    let mut account_id = get_test_field_describe("AccountId", "tns:ID", "reference");
+    account_id["referenceTo"] = serde_json::json!(["Account"]);
+    account_id["relationshipName"] = serde_json::json!("Account");
+    let contact_type = get_test_sobject_type(
+        "Contact",
+        vec![
+            get_test_field_describe("LastName", "xsd:string", "string"),
+            account_id,
+        ],
+        vec![],
+    )?;
+    let account_type = get_test_sobject_type_with_children(
+        "Account",
+        vec![get_test_field_describe("Name", "xsd:string", "string")],
+        vec![],
+        vec![serde_json::json!({
+            "cascadeDelete": true,
+            "childSObject": "Contact",
+            "deprecatedAndHidden": false,
+            "field": "AccountId",
+            "junctionIdListNames": [],
+            "junctionReferenceTo": [],
+            "relationshipName": "Contacts",
+            "restrictedDelete": false
+        })],
+    )?;
+    let unlinked_account_type = account_type.clone();
+    let contact_type = contact_type.with_related_types(std::slice::from_ref(&account_type));
+    let account_type = account_type.with_related_types(std::slice::from_ref(&contact_type));
+
+    // SELECT LastName, Account.Name FROM Contact
+    let contact = SObject::from_value(
+        &serde_json::json!({
+            "attributes": {"type": "Contact"},
+            "LastName": "Smith",
+            "Account": {"attributes": {"type": "Account"}, "Name": "Acme"}
+        }),
+        &contact_type,
+    )?;
+    match contact.get("Account") {
+        Some(FieldValue::Relationship(account)) => assert_eq!(
+            account.get("Name"),
+            Some(&FieldValue::String("Acme".to_owned()))
+        ),
+        _ => panic!("Expected a relationship"),
+    }
+
+    // SELECT Name, (SELECT LastName FROM Contacts) FROM Account
+    let value = serde_json::json!({
+        "attributes": {"type": "Account"},
+        "Name": "Acme",
+        "Contacts": {
+            "totalSize": 2,
+            "done": true,
+            "records": [
+                {"attributes": {"type": "Contact"}, "LastName": "Smith"},
+                {"attributes": {"type": "Contact"}, "LastName": "Jones"}
+            ]
+        }
+    });
+    let account = SObject::from_value(&value, &account_type)?;
+    match account.get("Contacts") {
+        Some(FieldValue::ChildRecords(contacts)) => {
+            assert_eq!(contacts.len(), 2);
+            assert_eq!(contacts[1].get_api_name(), "Contact");
+            assert_eq!(
+                contacts[1].get("LastName"),
+                Some(&FieldValue::String("Jones".to_owned()))
+            );
+        }
+        _ => panic!("Expected child records"),
+    }
+
+    // A subquery with no results is null.
+    let account = SObject::from_value(
+        &serde_json::json!({"attributes": {"type": "Account"}, "Contacts": null}),
+        &account_type,
+    )?;
+    assert_eq!(account.get("Contacts"), Some(&FieldValue::Null));
+
+    // The child type must be known to decode its records.
+    assert!(SObject::from_value(&value, &unlinked_account_type).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_any_type_history_values() -> Result<()> {
+    let history_type = get_test_sobject_type(
+        "AccountHistory",
+        vec![
+            get_test_field_describe("Field", "xsd:string", "picklist"),
+            get_test_field_describe("OldValue", "xsd:anyType", "anyType"),
+            get_test_field_describe("NewValue", "xsd:anyType", "anyType"),
+        ],
+        vec![],
+    )?;
+    let records = serde_json::json!([
+        {
+            "attributes": {"type": "AccountHistory"},
+            "Field": "NumberOfEmployees",
+            "OldValue": 10,
+            "NewValue": 12
+        },
+        {
+            "attributes": {"type": "AccountHistory"},
+            "Field": "Owner",
+            "OldValue": "005000000000001AAA",
+            "NewValue": "Ada Lovelace"
+        },
+        {
+            "attributes": {"type": "AccountHistory"},
+            "Field": "SLAExpirationDate__c",
+            "OldValue": null,
+            "NewValue": "2022-03-01"
+        }
+    ]);
+    let history = records
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| SObject::from_value(r, &history_type))
+        .collect::<Result<Vec<SObject>>>()?;
+
+    assert_eq!(
+        history[0].get("NewValue"),
+        Some(&FieldValue::Any(serde_json::json!(12)))
+    );
+    assert_eq!(
+        history[0].get("NewValue").unwrap().as_typed(),
+        FieldValue::Integer(12)
+    );
+    assert_eq!(
+        history[0].get_typed::<i64>(&history_type.field("OldValue")?)?,
+        Some(10)
+    );
+
+    assert_eq!(
+        history[1].get("OldValue").unwrap().as_typed(),
+        FieldValue::Id("005000000000001AAA".try_into()?)
+    );
+    assert_eq!(
+        history[1].get("NewValue").unwrap().as_typed(),
+        FieldValue::String("Ada Lovelace".to_owned())
+    );
+    assert_eq!(
+        history[1].get_typed::<String>(&history_type.field("NewValue")?)?,
+        Some("Ada Lovelace".to_owned())
+    );
+
+    assert_eq!(history[2].get("OldValue"), Some(&FieldValue::Null));
+    assert_eq!(
+        history[2].get("NewValue").unwrap().as_typed(),
+        FieldValue::Date(Date::new(2022, 3, 1)?)
+    );
+    assert_eq!(
+        history[2].get_typed::<Date>(&history_type.field("NewValue")?)?,
+        Some(Date::new(2022, 3, 1)?)
+    );
+    assert_eq!(
+        history[2].get("NewValue").unwrap().as_string(),
+        "2022-03-01"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_integer_json_precision() -> Result<()> {
+    let sobject_type = get_test_sobject_type(
+        "Account",
+        vec![
+            get_test_field_describe("NumberOfEmployees", "xsd:int", "int"),
+            get_test_field_describe("Serial__c", "xsd:long", "long"),
+            get_test_field_describe("LegacyNumber__c", "xsd:double", "double"),
+        ],
+        vec![],
+    )?;
+
+    // Eighteen digits are beyond the precision of an f64.
+    let account = SObject::from_value(
+        &serde_json::json!({
+            "attributes": {"type": "Account"},
+            "NumberOfEmployees": 12,
+            "Serial__c": 123456789012345678i64,
+            "LegacyNumber__c": 987654321098765432i64
+        }),
+        &sobject_type,
+    )?;
+    assert_eq!(
+        account.get("Serial__c"),
+        Some(&FieldValue::Integer(123456789012345678))
+    );
+    assert_eq!(
+        account.get("LegacyNumber__c"),
+        Some(&FieldValue::Integer(987654321098765432))
+    );
+
+    let value = account.to_value()?;
+    assert_eq!(value["serial__c"].to_string(), "123456789012345678");
+    assert_eq!(value["legacynumber__c"].to_string(), "987654321098765432");
+    assert_eq!(value["numberofemployees"].as_i64(), Some(12));
+
+    assert_eq!(
+        FieldValue::from_str("987654321098765432", &SoapType::Double)?,
+        FieldValue::Integer(987654321098765432)
+    );
+    assert_eq!(
+        FieldValue::from_str("12", &SoapType::Double)?,
+        FieldValue::Double(12.0)
+    );
+
+    let error = SObject::from_value(
+        &serde_json::json!({
+            "attributes": {"type": "Account"},
+            "Serial__c": 18446744073709551615u64
+        }),
+        &sobject_type,
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("64-bit signed integer"));
+    assert!(FieldValue::from_str("12.5", &SoapType::Integer).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_subset_merge_and_diff() -> Result<()> {
+    let account_type = get_test_sobject_type("Account", vec![], vec![])?;
+    let id = SalesforceId::new("001000000000001AAA")?;
+    let original = SObject::new(&account_type)
+        .with_reference("Id", id)
+        .with_str("Name", "Acme")
+        .with_str("Phone", "555-0100")
+        .with_null("Website");
+
+    let subset = original.subset(&["Id", "Name", "Industry"]);
+    assert_eq!(subset.fields.len(), 2);
+    assert_eq!(subset.get("id"), Some(&FieldValue::Id(id)));
+    assert_eq!(subset.get("Name"), original.get("Name"));
+
+    let mut changed = original.clone();
+    changed.merge(
+        &SObject::new(&account_type)
+            .with_str("Name", "Acme Corp")
+            .with_str("Industry", "Retail"),
+        false,
+    )?;
+    assert_eq!(changed.get("Name"), original.get("Name"));
+    assert_eq!(
+        changed.get("Industry"),
+        Some(&FieldValue::String("Retail".to_owned()))
+    );
+
+    changed.merge(
+        &SObject::new(&account_type)
+            .with_str("Name", "Acme Corp")
+            .with_str("Website", "example.com"),
+        true,
+    )?;
+    changed.fields.remove("phone");
+
+    assert_eq!(
+        original.diff(&changed),
+        vec![
+            FieldChange {
+                field: "industry".to_owned(),
+                old_value: None,
+                new_value: Some(FieldValue::String("Retail".to_owned())),
+            },
+            FieldChange {
+                field: "name".to_owned(),
+                old_value: Some(FieldValue::String("Acme".to_owned())),
+                new_value: Some(FieldValue::String("Acme Corp".to_owned())),
+            },
+            FieldChange {
+                field: "phone".to_owned(),
+                old_value: Some(FieldValue::String("555-0100".to_owned())),
+                new_value: None,
+            },
+            FieldChange {
+                field: "website".to_owned(),
+                old_value: Some(FieldValue::Null),
+                new_value: Some(FieldValue::String("example.com".to_owned())),
+            },
+        ]
+    );
+    assert!(original.diff(&original.clone()).is_empty());
+
+    let contact = SObject::new(&get_test_sobject_type("Contact", vec![], vec![])?);
+    assert!(changed.merge(&contact, true).is_err());
+
+    Ok(())
+}
+
+fn get_validation_test_type() -> Result<SObjectType> {
+    let mut name = get_test_field_describe("Name", "xsd:string", "string");
+    name["length"] = serde_json::json!(10);
+    name["nillable"] = serde_json::json!(false);
+    let mut created_date = get_test_field_describe("CreatedDate", "xsd:dateTime", "datetime");
+    created_date["createable"] = serde_json::json!(false);
+    created_date["updateable"] = serde_json::json!(false);
+    let mut external_id = get_test_field_describe("ExtId__c", "xsd:string", "string");
+    external_id["updateable"] = serde_json::json!(false);
+    let mut amount = get_test_field_describe("Amount__c", "xsd:double", "currency");
+    amount["precision"] = serde_json::json!(5);
+    amount["scale"] = serde_json::json!(2);
+    let mut rating = get_test_field_describe("Rating", "xsd:string", "picklist");
+    rating["restrictedPicklist"] = serde_json::json!(true);
+    rating["picklistValues"] = serde_json::json!([
+        {"active": true, "defaultValue": false, "label": "Hot", "validFor": null, "value": "Hot"},
+        {"active": false, "defaultValue": false, "label": "Cold", "validFor": null, "value": "Cold"}
+    ]);
+    let mut regions = get_test_field_describe("Regions__c", "xsd:string", "multipicklist");
+    regions["restrictedPicklist"] = serde_json::json!(true);
+    regions["picklistValues"] = serde_json::json!([
+        {"active": true, "defaultValue": false, "label": "EMEA", "validFor": null, "value": "EMEA"},
+        {"active": true, "defaultValue": false, "label": "APAC", "validFor": null, "value": "APAC"}
+    ]);
+
+    get_test_sobject_type(
+        "Account",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            name,
+            created_date,
+            external_id,
+            amount,
+            rating,
+            regions,
+        ],
+        vec![],
+    )
+}
+
+#[test]
+fn test_sobject_validate() -> Result<()> {
+    let sobject_type = get_validation_test_type()?;
+
+    let valid = SObject::new(&sobject_type)
+        .with_str("Name", "Test")
+        .with_str("ExtId__c", "A-1")
+        .with_double("Amount__c", 999.999)
+        .with_str("Rating", "Hot")
+        .with_str("Regions__c", "EMEA;APAC");
+    assert!(valid.validate(&sobject_type).is_empty());
+
+    let invalid = SObject::new(&sobject_type)
+        .with_str("Name", "Much too long")
+        .with_str("Industry", "Tech")
+        .with_datetime("CreatedDate", DateTime::new(2021, 1, 1, 0, 0, 0, 0)?)
+        .with_int("Amount__c", 1000)
+        .with_str("Rating", "Cold")
+        .with_str("Regions__c", "EMEA;LATAM");
+    assert_eq!(
+        invalid
+            .validate(&sobject_type)
+            .into_iter()
+            .map(|v| (v.field, v.kind))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "amount__c".to_owned(),
+                ViolationKind::PrecisionExceeded {
+                    precision: 5,
+                    scale: 2
+                }
+            ),
+            ("createddate".to_owned(), ViolationKind::NotCreateable),
+            ("industry".to_owned(), ViolationKind::UnknownField),
+            ("name".to_owned(), ViolationKind::TooLong { length: 10 }),
+            (
+                "rating".to_owned(),
+                ViolationKind::InvalidPicklistValue("Cold".to_owned())
+            ),
+            (
+                "regions__c".to_owned(),
+                ViolationKind::InvalidPicklistValue("LATAM".to_owned())
+            ),
+        ]
+    );
+
+    // Records with an Id are checked as updates.
+    let update = SObject::new(&sobject_type)
+        .with_reference("Id", SalesforceId::new("001000000000000AAA")?)
+        .with_str("ExtId__c", "A-1")
+        .with_null("Name")
+        .with_boolean("Amount__c", true);
+    assert_eq!(
+        update
+            .validate(&sobject_type)
+            .into_iter()
+            .map(|v| v.kind)
+            .collect::<Vec<_>>(),
+        vec![
+            ViolationKind::InvalidType,
+            ViolationKind::NotUpdateable,
+            ViolationKind::NotNillable
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_execute_validated() -> Result<()> {
+    let sobject_type = get_validation_test_type()?;
+    let (conn, count) = serve_responses(vec![(
+        "201 Created",
+        r#"{"id": "001000000000000AAA", "success": true, "errors": []}"#,
+    )])
+    .await?;
+
+    let records = vec![
+        SObject::new(&sobject_type).with_str("Name", "Valid"),
+        SObject::new(&sobject_type).with_str("Name", "Much too long"),
+    ];
+    let request = crate::rest::collections::SObjectCollectionCreateRequest::new(&records, true)?;
+    let err = conn
+        .execute_validated(&request, &records)
+        .await
+        .unwrap_err();
+    match err.downcast_ref::<SalesforceError>() {
+        Some(SalesforceError::ValidationFailed(records)) => {
+            assert_eq!(records.len(), 1);
+            assert_eq!(records[0].index, 1);
+            assert_eq!(records[0].violations[0].field, "name");
+        }
+        _ => panic!("Expected a validation failure, got {:?}", err),
+    }
+    assert_eq!(count.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+    let record = SObject::new(&sobject_type).with_str("Name", "Valid");
+    let request = crate::rest::rows::SObjectCreateRequest::new(&record)?;
+    let result = conn
+        .execute_validated(&request, std::slice::from_ref(&record))
+        .await?;
+    assert!(result.success);
+
+    Ok(())
+}
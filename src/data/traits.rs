@@ -8,7 +8,7 @@
 //! and the costs they're willing to pay.
 
 use anyhow::Result;
-use serde_json::{json, Value};
+use serde_json::{json, Map, Value};
 
 use crate::{data::FieldValue, errors::SalesforceError};
 
@@ -108,16 +108,99 @@ where
     T: for<'de> serde::Deserialize<'de> + SObjectBase,
 {
     fn from_value(value: &serde_json::Value, _sobjecttype: &SObjectType) -> Result<Self> {
-        Ok(serde_json::from_value::<Self>(value.clone())?) // TODO: make this not clone.
+        deserialize_sobject(value)
     }
 }
 
+/// Deserialize a typed sObject from an API representation, applying its
+/// field renames and skipped fields.
+pub fn deserialize_sobject<T>(value: &Value) -> Result<T>
+where
+    T: for<'de> serde::Deserialize<'de> + SObjectBase,
+{
+    let renames = T::get_field_renames();
+    let skipped_fields = T::get_skipped_fields();
+
+    match value {
+        Value::Object(map) if !renames.is_empty() || !skipped_fields.is_empty() => {
+            let mut map = map.clone();
+            remove_fields(&mut map, skipped_fields);
+            for (field, api_name) in renames {
+                let key = map
+                    .keys()
+                    .find(|k| k.eq_ignore_ascii_case(api_name))
+                    .cloned();
+                if let Some(key) = key {
+                    let field_value = map.remove(&key).unwrap();
+                    map.insert(field.to_string(), field_value);
+                }
+            }
+
+            Ok(serde_json::from_value::<T>(Value::Object(map))?)
+        }
+        _ => Ok(serde_json::from_value::<T>(value.clone())?), // TODO: make this not clone.
+    }
+}
+
+/// Represents an enum with a variant for each type of sObject that a result
+/// may hold, such as the records of a polymorphic lookup like `WhatId`.
+/// Each record is deserialized as the variant for its `attributes.type`.
+///
+/// Implement this trait with `#[derive(PolymorphicSObject)]`, which also
+/// implements `serde::Deserialize` through `deserialize_polymorphic()`.
+pub trait PolymorphicSObject: Sized {
+    /// Deserialize `value` as the variant for `sobject_type`, or return
+    /// `None` if there is no such variant.
+    fn variant_from_value(sobject_type: &str, value: &Value) -> Option<Result<Self>>;
+
+    fn from_typed_value(value: &Value) -> Result<Self> {
+        let sobject_type = value
+            .get("attributes")
+            .and_then(|a| a.get("type"))
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| {
+                SalesforceError::SchemaError("Record has no attributes.type".to_string())
+            })?;
+
+        Self::variant_from_value(sobject_type, value).unwrap_or_else(|| {
+            Err(SalesforceError::SchemaError(format!(
+                "No variant for sObject type {}",
+                sobject_type
+            ))
+            .into())
+        })
+    }
+}
+
+pub fn deserialize_polymorphic<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: PolymorphicSObject,
+{
+    let value = <Value as serde::Deserialize>::deserialize(deserializer)?;
+
+    T::from_typed_value(&value).map_err(serde::de::Error::custom)
+}
+
 impl<T> SObjectSerialization for T
 where
     T: serde::Serialize + SObjectWithId + TypedSObject + SObjectBase,
 {
     fn to_value(&self) -> Result<Value> {
-        Ok(serde_json::to_value(self)?)
+        let mut value = serde_json::to_value(self)?;
+
+        if let Value::Object(ref mut map) = value {
+            remove_fields(map, Self::get_system_fields());
+            remove_fields(map, Self::get_skipped_fields());
+
+            for (field, api_name) in Self::get_field_renames() {
+                if let Some(field_value) = map.remove(*field) {
+                    map.insert(api_name.to_string(), field_value);
+                }
+            }
+        }
+
+        Ok(value)
     }
 
     fn to_value_with_options(&self, include_type: bool, include_id: bool) -> Result<Value> {
@@ -158,4 +241,40 @@ where
     }
 }
 
-pub trait SObjectBase: Sized + Send + Sync + Unpin + 'static {}
+pub trait SObjectBase: Sized + Send + Sync + Unpin + 'static {
+    /// Fields that are deserialized from the API but never serialized back to it,
+    /// such as `CreatedDate`. Populated by `#[baris(system)]`, `#[baris(read_only)]`,
+    /// and `#[baris(relationship)]` in the derive macro.
+    ///
+    /// Names are the fields' serialized names, after any `#[serde(rename)]` or
+    /// `#[serde(rename_all)]`, and are compared exactly.
+    fn get_system_fields() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Fields that hold local state, and are neither serialized to nor populated
+    /// from the API. They must deserialize when absent, as an `Option` does.
+    /// Populated by `#[baris(skip_serializing)]` in the derive macro, and
+    /// compared like `get_system_fields()`.
+    fn get_skipped_fields() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Pairs of serialized field names and the API names they stand for, such as
+    /// `("custom_field", "Custom_Field__c")`. Populated by `#[baris(rename)]` and
+    /// `#[baris(relationship)]` in the derive macro.
+    fn get_field_renames() -> &'static [(&'static str, &'static str)] {
+        &[]
+    }
+}
+
+fn remove_fields(map: &mut Map<String, Value>, fields: &[&str]) {
+    let keys: Vec<String> = map
+        .keys()
+        .filter(|k| fields.contains(&k.as_str()))
+        .cloned()
+        .collect();
+    for key in keys {
+        map.remove(&key);
+    }
+}
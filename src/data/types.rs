@@ -1,21 +1,27 @@
 use std::{
     convert::{Infallible, TryFrom, TryInto},
     fmt::{self, Display},
+    io,
     ops::Deref,
+    path::Path,
     pin::Pin,
     str::FromStr,
+    time::SystemTime,
 };
 
 use anyhow::Result;
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use chrono::{TimeZone, Utc};
-use futures::Stream;
+use futures::{Stream, StreamExt};
 use serde::{Serialize, Serializer};
-use serde_derive::{Deserialize, Serialize};
+use serde_derive::Deserialize;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio_util::io::StreamReader;
 
+use super::SingleTypedSObject;
 use crate::{api::Connection, errors::SalesforceError, rest::rows::BlobRetrieveRequest};
 
-#[derive(Serialize, Deserialize, Copy, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash)]
 #[serde(try_from = "String")]
 #[serde(into = "String")]
 pub struct SalesforceId {
@@ -111,6 +117,22 @@ impl DateTime {
                 .ok_or(SalesforceError::DateTimeError)?,
         ))
     }
+
+    pub fn now() -> DateTime {
+        DateTime(chrono::Utc::now())
+    }
+}
+
+impl From<chrono::DateTime<chrono::Utc>> for DateTime {
+    fn from(value: chrono::DateTime<chrono::Utc>) -> DateTime {
+        DateTime(value)
+    }
+}
+
+impl From<SystemTime> for DateTime {
+    fn from(value: SystemTime) -> DateTime {
+        DateTime(value.into())
+    }
 }
 
 impl Deref for DateTime {
@@ -124,13 +146,15 @@ impl TryFrom<String> for DateTime {
     type Error = anyhow::Error;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        // Salesforce's version of RFC3339 doesn't include a colon as required by the standard,
-        // giving +0000 instead of the expected +00:00
-
-        Ok(DateTime(
-            chrono::DateTime::parse_from_str(&value, "%Y-%m-%dT%H:%M:%S%.3f%z")?
-                .with_timezone(&Utc),
-        ))
+        // Salesforce's version of RFC3339 usually doesn't include a colon as required by the standard,
+        // giving +0000 instead of the expected +00:00, but some endpoints return other offsets
+        // in either form. All values are normalized to UTC.
+        let parsed = match chrono::DateTime::parse_from_rfc3339(&value) {
+            Ok(parsed) => parsed,
+            Err(_) => chrono::DateTime::parse_from_str(&value, "%Y-%m-%dT%H:%M:%S%.f%z")?,
+        };
+
+        Ok(DateTime(parsed.with_timezone(&Utc)))
     }
 }
 
@@ -171,6 +195,12 @@ impl Time {
     }
 }
 
+impl From<chrono::NaiveTime> for Time {
+    fn from(value: chrono::NaiveTime) -> Time {
+        Time(value)
+    }
+}
+
 impl Deref for Time {
     type Target = chrono::NaiveTime;
     fn deref(&self) -> &Self::Target {
@@ -225,6 +255,12 @@ impl Date {
     }
 }
 
+impl From<chrono::NaiveDate> for Date {
+    fn from(value: chrono::NaiveDate) -> Date {
+        Date(value)
+    }
+}
+
 impl Deref for Date {
     type Target = chrono::NaiveDate;
     fn deref(&self) -> &Self::Target {
@@ -259,16 +295,56 @@ impl FromStr for Date {
 #[serde(into = "String")]
 pub struct Blob(String);
 
-// TODO: can we elide the reqwest reference in our public API via a stream adapter?
 impl Blob {
     pub async fn stream(
         &self,
         conn: &Connection,
-    ) -> Result<Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>>>>> {
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>> {
         Ok(conn
             .execute_raw_request(&BlobRetrieveRequest::new(self.0.clone()))
             .await?)
     }
+
+    /// Read the content through an `AsyncRead`, which reports failures
+    /// as `std::io::Error`.
+    pub async fn reader(&self, conn: &Connection) -> Result<Pin<Box<dyn AsyncRead + Send>>> {
+        let stream = self
+            .stream(conn)
+            .await?
+            .map(|b| b.map_err(io::Error::other));
+
+        Ok(Box::pin(StreamReader::new(stream)))
+    }
+
+    /// Retrieve the whole content into memory.
+    pub async fn bytes(&self, conn: &Connection) -> Result<Bytes> {
+        let mut stream = self.stream(conn).await?;
+        let mut content = BytesMut::new();
+
+        while let Some(chunk) = stream.next().await {
+            content.extend_from_slice(&chunk?);
+        }
+
+        Ok(content.freeze())
+    }
+
+    /// Write the content to `writer`, returning the number of bytes written.
+    pub async fn write_to<W>(&self, conn: &Connection, writer: &mut W) -> Result<u64>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        let written = tokio::io::copy(&mut self.reader(conn).await?, writer).await?;
+        writer.flush().await?;
+
+        Ok(written)
+    }
+
+    /// Download the content to the file at `path`, replacing it if it exists.
+    pub async fn download_to_file(&self, conn: &Connection, path: impl AsRef<Path>) -> Result<u64> {
+        let mut file = tokio::fs::File::create(path).await?;
+
+        self.write_to(conn, &mut file).await
+    }
 }
 
 impl Display for Blob {
@@ -305,7 +381,7 @@ pub struct Address {
     pub street: Option<String>,
 }
 
-#[derive(Debug, Deserialize, PartialEq, Copy, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
 pub enum SoapType {
     #[serde(rename = "urn:address")]
     Address,
@@ -325,6 +401,8 @@ pub enum SoapType {
     Id,
     #[serde(rename = "xsd:int")]
     Integer,
+    #[serde(rename = "xsd:long")]
+    Long,
     #[serde(rename = "urn:location")]
     Geolocation,
     #[serde(rename = "xsd:string")]
@@ -332,3 +410,47 @@ pub enum SoapType {
     #[serde(rename = "xsd:time")]
     Time,
 }
+
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+struct RecordAttributes {
+    #[serde(rename = "type")]
+    sobject_type: String,
+}
+
+/// A related record whose type varies, such as the `What` of a `Task`
+/// selected with `TYPEOF`. It holds the fields selected for its type.
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub struct PolymorphicRecord {
+    attributes: RecordAttributes,
+    #[serde(flatten)]
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl PolymorphicRecord {
+    /// The API name of this record's sObject.
+    pub fn get_type(&self) -> &str {
+        &self.attributes.sobject_type
+    }
+
+    pub fn is<T: SingleTypedSObject>(&self) -> bool {
+        self.get_type().eq_ignore_ascii_case(T::get_type_api_name())
+    }
+
+    pub fn get(&self, field: &str) -> Option<&serde_json::Value> {
+        self.fields.get(field)
+    }
+
+    /// Deserialize this record as a `T`, or return `None` if it is of another type.
+    pub fn downcast<T>(&self) -> Result<Option<T>>
+    where
+        T: SingleTypedSObject + for<'de> serde::Deserialize<'de>,
+    {
+        if !self.is::<T>() {
+            return Ok(None);
+        }
+
+        Ok(Some(serde_json::from_value(serde_json::Value::Object(
+            self.fields.clone(),
+        ))?))
+    }
+}
@@ -0,0 +1,279 @@
+//! Client-side validation of records against their sObject describe.
+//!
+//! Salesforce rejects writes to unknown or read-only fields, text that is
+//! too long, numbers that exceed a field's precision, and values outside a
+//! restricted picklist. Checking these before a request is sent saves an
+//! API round trip, and in large loads, a failed batch.
+
+use std::fmt;
+
+use anyhow::Result;
+
+use super::{
+    fields::is_compatible,
+    sobjects::{FieldValue, SObject, SObjectType},
+    traits::SObjectWithId,
+    types::SoapType,
+};
+use crate::api::{Connection, SalesforceRequest};
+use crate::errors::SalesforceError;
+use crate::rest::describe::{FieldDescribe, SObjectDescribe};
+
+/// The way in which a field's value breaks the constraints of its describe.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ViolationKind {
+    /// The sObject has no field of this name.
+    UnknownField,
+    /// The record is new, and the field cannot be set on create.
+    NotCreateable,
+    /// The record has an Id, and the field cannot be set on update.
+    NotUpdateable,
+    /// The field is required, and the value is null.
+    NotNillable,
+    /// The value's type does not match the field's type.
+    InvalidType,
+    /// The text is longer than the field's length.
+    TooLong { length: u32 },
+    /// The number has more digits before the decimal point than the field allows.
+    PrecisionExceeded { precision: u16, scale: u16 },
+    /// The value is not an active value of a restricted picklist.
+    InvalidPicklistValue(String),
+}
+
+/// A field of a record whose value Salesforce would reject.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldViolation {
+    /// The field's name, as given in the record.
+    pub field: String,
+    pub kind: ViolationKind,
+}
+
+impl fmt::Display for FieldViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ViolationKind::UnknownField => write!(f, "{}: no such field", self.field),
+            ViolationKind::NotCreateable => write!(f, "{}: field is not createable", self.field),
+            ViolationKind::NotUpdateable => write!(f, "{}: field is not updateable", self.field),
+            ViolationKind::NotNillable => write!(f, "{}: field is required", self.field),
+            ViolationKind::InvalidType => write!(f, "{}: value has the wrong type", self.field),
+            ViolationKind::TooLong { length } => {
+                write!(
+                    f,
+                    "{}: value is longer than {} characters",
+                    self.field, length
+                )
+            }
+            ViolationKind::PrecisionExceeded { precision, scale } => write!(
+                f,
+                "{}: value does not fit a precision of {} and scale of {}",
+                self.field, precision, scale
+            ),
+            ViolationKind::InvalidPicklistValue(value) => {
+                write!(f, "{}: {} is not a valid picklist value", self.field, value)
+            }
+        }
+    }
+}
+
+/// The violations found in one record of a request, by its index in the
+/// records given to `Connection::execute_validated()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordViolations {
+    pub index: usize,
+    pub violations: Vec<FieldViolation>,
+}
+
+impl fmt::Display for RecordViolations {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "record {}: {}",
+            self.index,
+            self.violations
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    }
+}
+
+impl SObject {
+    /// Check this record's fields against the describe of `sobject_type`,
+    /// returning every violation found. A record with an Id is checked
+    /// as an update, and one without as a create.
+    ///
+    /// Lookups given as related records or external Id references are found
+    /// through their relationship names. Child records are not checked.
+    pub fn validate(&self, sobject_type: &SObjectType) -> Vec<FieldViolation> {
+        let describe = sobject_type.get_describe();
+        let is_update = matches!(
+            self.get_id(),
+            FieldValue::Id(_) | FieldValue::CompositeReference(_)
+        );
+
+        let mut names: Vec<&String> = self.fields.keys().collect();
+        names.sort();
+
+        let mut violations = Vec::new();
+        for name in names {
+            let value = &self.fields[name];
+            if name.eq_ignore_ascii_case("id") || value.is_child_records() {
+                continue;
+            }
+
+            let field = match find_field(describe, name) {
+                Some(field) => field,
+                None => {
+                    violations.push(FieldViolation {
+                        field: name.clone(),
+                        kind: ViolationKind::UnknownField,
+                    });
+                    continue;
+                }
+            };
+
+            violations.extend(
+                validate_value(field, value, is_update)
+                    .into_iter()
+                    .map(|kind| FieldViolation {
+                        field: name.clone(),
+                        kind,
+                    }),
+            );
+        }
+
+        violations
+    }
+}
+
+fn find_field<'a>(describe: &'a SObjectDescribe, name: &str) -> Option<&'a FieldDescribe> {
+    describe.get_field(name).or_else(|| {
+        describe.get_fields().iter().find(|f| {
+            f.relationship_name
+                .as_ref()
+                .is_some_and(|r| r.eq_ignore_ascii_case(name))
+        })
+    })
+}
+
+fn validate_value(
+    field: &FieldDescribe,
+    value: &FieldValue,
+    is_update: bool,
+) -> Vec<ViolationKind> {
+    let mut violations = Vec::new();
+
+    if is_update && !field.updateable {
+        violations.push(ViolationKind::NotUpdateable);
+    } else if !is_update && !field.createable {
+        violations.push(ViolationKind::NotCreateable);
+    }
+
+    if value.is_null() {
+        if !field.nillable && field.soap_type != SoapType::Boolean {
+            violations.push(ViolationKind::NotNillable);
+        }
+        return violations;
+    }
+
+    if !is_compatible(value, field) {
+        violations.push(ViolationKind::InvalidType);
+        return violations;
+    }
+
+    match value {
+        FieldValue::String(s) => {
+            if field.length > 0 && s.chars().count() > field.length as usize {
+                violations.push(ViolationKind::TooLong {
+                    length: field.length,
+                });
+            }
+            if field.restricted_picklist {
+                violations.extend(
+                    get_invalid_picklist_values(field, s)
+                        .map(|v| ViolationKind::InvalidPicklistValue(v.to_owned())),
+                );
+            }
+        }
+        FieldValue::Integer(i) if exceeds_precision(i.unsigned_abs().to_string().len(), field) => {
+            violations.push(get_precision_violation(field));
+        }
+        FieldValue::Double(d) => {
+            let whole = d.abs().trunc();
+            let digits = if whole < 1.0 {
+                0
+            } else {
+                format!("{:.0}", whole).len()
+            };
+            if exceeds_precision(digits, field) {
+                violations.push(get_precision_violation(field));
+            }
+        }
+        _ => {}
+    }
+
+    violations
+}
+
+// Multi-select picklist values are separated by semicolons.
+fn get_invalid_picklist_values<'a>(
+    field: &'a FieldDescribe,
+    value: &'a str,
+) -> impl Iterator<Item = &'a str> + 'a {
+    let values: Vec<&str> = if field.field_type == "multipicklist" {
+        value.split(';').collect()
+    } else {
+        vec![value]
+    };
+
+    values.into_iter().filter(move |v| {
+        !field
+            .picklist_values
+            .iter()
+            .any(|p| p.active && p.value == *v)
+    })
+}
+
+// The precision of a number field counts the digits on both sides of the
+// decimal point; digits after it beyond the scale are rounded, not rejected.
+fn exceeds_precision(whole_digits: usize, field: &FieldDescribe) -> bool {
+    field.soap_type == SoapType::Double
+        && field.precision > 0
+        && whole_digits > field.precision.saturating_sub(field.scale) as usize
+}
+
+fn get_precision_violation(field: &FieldDescribe) -> ViolationKind {
+    ViolationKind::PrecisionExceeded {
+        precision: field.precision,
+        scale: field.scale,
+    }
+}
+
+impl Connection {
+    /// Validate `records` against their describes, then execute `request`,
+    /// which should write those records. If any record is invalid, the
+    /// request is not sent, and `SalesforceError::ValidationFailed` lists
+    /// the violations of each invalid record.
+    pub async fn execute_validated<K, T>(&self, request: &K, records: &[SObject]) -> Result<T>
+    where
+        K: SalesforceRequest<ReturnValue = T>,
+    {
+        let violations: Vec<RecordViolations> = records
+            .iter()
+            .enumerate()
+            .map(|(index, record)| RecordViolations {
+                index,
+                violations: record.validate(&record.sobject_type),
+            })
+            .filter(|r| !r.violations.is_empty())
+            .collect();
+
+        if !violations.is_empty() {
+            return Err(SalesforceError::ValidationFailed(violations).into());
+        }
+
+        self.execute(request).await
+    }
+}
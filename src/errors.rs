@@ -1,7 +1,15 @@
 use std::error::Error;
 use std::fmt;
+use std::time::Duration;
+
+use crate::api::limits::ApiUsage;
+#[cfg(feature = "bulk")]
+use crate::bulk::v2::BulkIngestSummary;
+use crate::data::RecordViolations;
+use crate::rest::ApiError;
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum SalesforceError {
     InvalidIdError(String),
     RecordExistsError,
@@ -15,6 +23,52 @@ pub enum SalesforceError {
     NotAuthenticated,
     DateTimeError,
     UnsupportedId,
+    DryRunNotSupported,
+    ServiceUnavailable {
+        retry_after: Option<Duration>,
+    },
+    ResultPageError {
+        locator: Option<String>,
+        error: anyhow::Error,
+    },
+    SoqlParseError {
+        position: usize,
+        message: String,
+    },
+    /// Salesforce rejected a request with the errors in its response body.
+    ApiErrors(Vec<ApiError>),
+    /// The org's remaining API requests are within the Connection's `ApiThrottle` headroom.
+    ApiUsageExceeded(ApiUsage),
+    /// An operation was stopped through its `CancellationToken`.
+    Cancelled,
+    /// A long-running job did not complete within its `PollingOptions` timeout.
+    Timeout(Duration),
+    /// A query or Bulk result locator expired before all results were retrieved.
+    LocatorExpired {
+        resume_hint: ResumeHint,
+    },
+    /// Records failed client-side validation against their describes,
+    /// and the request was not sent.
+    ValidationFailed(Vec<RecordViolations>),
+    /// `BulkDmlJob::ingest_split()` failed after uploading and closing the jobs in `summary`.
+    #[cfg(feature = "bulk")]
+    IngestSplitError {
+        summary: BulkIngestSummary,
+        error: anyhow::Error,
+    },
+}
+
+/// Where a result stream stopped when its locator expired.
+///
+/// Locators cannot be refreshed, so recovering means re-issuing the query and
+/// skipping the records already retrieved, ideally through a filter on an
+/// ordered field (such as `Id > :last_id` with `ORDER BY Id`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResumeHint {
+    /// The locator that expired.
+    pub locator: Option<String>,
+    /// The number of records the stream yields before the error.
+    pub records_retrieved: usize,
 }
 
 impl fmt::Display for SalesforceError {
@@ -52,6 +106,67 @@ impl fmt::Display for SalesforceError {
                     "An unsupported Id type (such as a null or composite reference) was provided"
                 )
             }
+            SalesforceError::DryRunNotSupported => {
+                write!(f, "This request cannot be simulated in dry-run mode")
+            }
+            SalesforceError::ServiceUnavailable { retry_after } => match retry_after {
+                Some(retry_after) => write!(
+                    f,
+                    "Salesforce is unavailable; retry after {} seconds",
+                    retry_after.as_secs()
+                ),
+                None => write!(f, "Salesforce is unavailable"),
+            },
+            SalesforceError::ResultPageError { locator, error } => match locator {
+                Some(locator) => write!(
+                    f,
+                    "Unable to retrieve results page at locator {}: {}",
+                    locator, error
+                ),
+                None => write!(f, "Unable to retrieve results page: {}", error),
+            },
+            SalesforceError::SoqlParseError { position, message } => {
+                write!(f, "Invalid SOQL at position {}: {}", position, message)
+            }
+            SalesforceError::ApiErrors(errors) => write!(
+                f,
+                "Salesforce returned errors: {}",
+                errors
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<String>>()
+                    .join("; ")
+            ),
+            SalesforceError::ApiUsageExceeded(usage) => {
+                write!(f, "API usage {} leaves too little headroom", usage)
+            }
+            SalesforceError::Cancelled => write!(f, "The operation was cancelled"),
+            #[cfg(feature = "bulk")]
+            SalesforceError::IngestSplitError { summary, error } => write!(
+                f,
+                "Bulk ingest failed after uploading {} records to {} jobs: {}",
+                summary.get_record_count(),
+                summary.jobs.len(),
+                error
+            ),
+            SalesforceError::Timeout(timeout) => {
+                write!(f, "The job did not complete within {:?}", timeout)
+            }
+            SalesforceError::LocatorExpired { resume_hint } => write!(
+                f,
+                "The result locator {} expired after {} records were retrieved; re-issue the query to resume",
+                resume_hint.locator.as_deref().unwrap_or("(initial)"),
+                resume_hint.records_retrieved
+            ),
+            SalesforceError::ValidationFailed(records) => write!(
+                f,
+                "Records failed validation: {}",
+                records
+                    .iter()
+                    .map(|r| r.to_string())
+                    .collect::<Vec<String>>()
+                    .join("; ")
+            ),
         }
     }
 }
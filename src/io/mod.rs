@@ -0,0 +1,240 @@
+//! Sinks that write query extracts as CSV or newline-delimited JSON.
+//!
+//! An `ExtractSink` wraps any `AsyncWrite`, such as a file opened by
+//! `ExtractSink::create()`, and may compress its output with gzip or zstd
+//! when the `compression` feature is enabled. Output is buffered, and
+//! flushed through the compressor as set by its `FlushPolicy`, so that a
+//! reader can follow a long extract and an interrupted extract keeps the
+//! records written before its last flush.
+
+use std::path::Path;
+use std::pin::Pin;
+
+use anyhow::Result;
+use futures::Stream;
+use tokio::fs::File;
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+use tokio_stream::StreamExt;
+
+#[cfg(feature = "compression")]
+use async_compression::tokio::write::{GzipEncoder, ZstdEncoder};
+
+#[cfg(feature = "bulk")]
+use crate::bulk::v2::SObjectCsvEncoder;
+use crate::data::{SObject, SObjectSerialization};
+
+#[cfg(test)]
+mod test;
+
+/// The size of the buffer between a sink's encoder and its writer.
+pub const DEFAULT_BUFFER_SIZE: usize = 256 * 1024;
+
+/// The uncompressed output written between flushes by default.
+pub const DEFAULT_FLUSH_BYTES: usize = 8 * 1024 * 1024;
+
+/// The encoding of records written to an `ExtractSink`.
+pub enum ExtractFormat {
+    /// CSV, with its columns and dialect set by the encoder.
+    #[cfg(feature = "bulk")]
+    Csv(SObjectCsvEncoder),
+    /// One JSON object per line.
+    Ndjson,
+}
+
+/// The compression applied to the output of an `ExtractSink`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtractCompression {
+    None,
+    #[cfg(feature = "compression")]
+    Gzip,
+    #[cfg(feature = "compression")]
+    Zstd,
+}
+
+/// When an `ExtractSink` flushes its buffered output to its writer.
+///
+/// A flush ends a compressed block, which costs a little compression, so
+/// flushes should be separated by at least a few megabytes of output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlushPolicy {
+    max_records: Option<usize>,
+    max_bytes: Option<usize>,
+}
+
+impl FlushPolicy {
+    /// Flush after every `DEFAULT_FLUSH_BYTES` of uncompressed output.
+    pub fn new() -> FlushPolicy {
+        FlushPolicy {
+            max_records: None,
+            max_bytes: Some(DEFAULT_FLUSH_BYTES),
+        }
+    }
+
+    /// Flush only when the buffer is full and when the sink is finished.
+    pub fn never() -> FlushPolicy {
+        FlushPolicy {
+            max_records: None,
+            max_bytes: None,
+        }
+    }
+
+    /// Flush after every `max_records` records.
+    #[must_use]
+    pub fn with_max_records(mut self, max_records: usize) -> Self {
+        self.max_records = Some(max_records);
+        self
+    }
+
+    /// Flush after every `max_bytes` of uncompressed output.
+    #[must_use]
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn get_max_records(&self) -> Option<usize> {
+        self.max_records
+    }
+
+    pub fn get_max_bytes(&self) -> Option<usize> {
+        self.max_bytes
+    }
+
+    fn is_due(&self, records: usize, bytes: usize) -> bool {
+        self.max_records.is_some_and(|max| records >= max)
+            || self.max_bytes.is_some_and(|max| bytes >= max)
+    }
+}
+
+impl Default for FlushPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Writes sObjects to a CSV or NDJSON extract, optionally compressed.
+///
+/// `finish()` must be called once all records are written, to flush the
+/// buffer and complete the compressed stream.
+pub struct ExtractSink {
+    writer: Pin<Box<dyn AsyncWrite + Send + Sync>>,
+    format: ExtractFormat,
+    flush_policy: FlushPolicy,
+    record_count: usize,
+    unflushed_records: usize,
+    unflushed_bytes: usize,
+}
+
+impl ExtractSink {
+    pub fn new<W>(writer: W, format: ExtractFormat, compression: ExtractCompression) -> Self
+    where
+        W: AsyncWrite + Send + Sync + 'static,
+    {
+        let writer = BufWriter::with_capacity(DEFAULT_BUFFER_SIZE, writer);
+        let writer: Pin<Box<dyn AsyncWrite + Send + Sync>> = match compression {
+            ExtractCompression::None => Box::pin(writer),
+            #[cfg(feature = "compression")]
+            ExtractCompression::Gzip => Box::pin(GzipEncoder::new(writer)),
+            #[cfg(feature = "compression")]
+            ExtractCompression::Zstd => Box::pin(ZstdEncoder::new(writer)),
+        };
+
+        ExtractSink {
+            writer,
+            format,
+            flush_policy: FlushPolicy::new(),
+            record_count: 0,
+            unflushed_records: 0,
+            unflushed_bytes: 0,
+        }
+    }
+
+    /// Write the extract to a new file at `path`, replacing any existing file.
+    pub async fn create(
+        path: impl AsRef<Path>,
+        format: ExtractFormat,
+        compression: ExtractCompression,
+    ) -> Result<Self> {
+        Ok(Self::new(File::create(path).await?, format, compression))
+    }
+
+    #[must_use]
+    pub fn with_flush_policy(mut self, flush_policy: FlushPolicy) -> Self {
+        self.flush_policy = flush_policy;
+        self
+    }
+
+    pub fn get_flush_policy(&self) -> FlushPolicy {
+        self.flush_policy
+    }
+
+    /// The number of records written so far.
+    pub fn get_record_count(&self) -> usize {
+        self.record_count
+    }
+
+    pub async fn write(&mut self, sobject: &SObject) -> Result<()> {
+        let content = match &mut self.format {
+            #[cfg(feature = "bulk")]
+            ExtractFormat::Csv(encoder) => encoder.encode(sobject).await?.to_vec(),
+            ExtractFormat::Ndjson => {
+                let mut line = serde_json::to_vec(&sobject.to_value()?)?;
+                line.push(b'\n');
+                line
+            }
+        };
+
+        self.writer.write_all(&content).await?;
+        self.record_count += 1;
+        self.unflushed_records += 1;
+        self.unflushed_bytes += content.len();
+
+        if self
+            .flush_policy
+            .is_due(self.unflushed_records, self.unflushed_bytes)
+        {
+            self.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Write every record of `records`, such as a query's `ResultStream`,
+    /// returning the number written. The first error ends the extract.
+    pub async fn write_all(
+        &mut self,
+        mut records: impl Stream<Item = Result<SObject>> + Unpin,
+    ) -> Result<usize> {
+        let mut count = 0;
+
+        while let Some(sobject) = records.next().await {
+            self.write(&sobject?).await?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Write out buffered output, ending the current compressed block.
+    pub async fn flush(&mut self) -> Result<()> {
+        self.writer.flush().await?;
+        self.unflushed_records = 0;
+        self.unflushed_bytes = 0;
+
+        Ok(())
+    }
+
+    /// Complete the extract, returning the number of records written.
+    /// A CSV extract of no records with explicit columns still has its header row.
+    pub async fn finish(mut self) -> Result<usize> {
+        #[cfg(feature = "bulk")]
+        if let ExtractFormat::Csv(encoder) = &mut self.format {
+            let header = encoder.finish().await?;
+            self.writer.write_all(&header).await?;
+        }
+
+        self.writer.shutdown().await?;
+
+        Ok(self.record_count)
+    }
+}
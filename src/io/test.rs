@@ -0,0 +1,152 @@
+use std::path::PathBuf;
+use std::{env, fs};
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use super::{ExtractCompression, ExtractFormat, ExtractSink, FlushPolicy};
+#[cfg(feature = "bulk")]
+use crate::bulk::v2::{BulkCsvFormat, SObjectCsvEncoder};
+use crate::data::SObject;
+use crate::test_integration_base::get_test_sobject_type;
+
+fn get_extract_path(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("baris-{}-{}", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    path
+}
+
+fn get_contacts() -> Result<Vec<SObject>> {
+    let contact_type = get_test_sobject_type("Contact", vec![], vec![])?;
+
+    Ok(vec![
+        SObject::new(&contact_type).with_str("LastName", "One"),
+        SObject::new(&contact_type)
+            .with_str("LastName", "Two, Jr.")
+            .with_str("Title", "CEO"),
+    ])
+}
+
+fn parse_ndjson(content: &[u8]) -> Result<Vec<Value>> {
+    Ok(std::str::from_utf8(content)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<Value>, _>>()?)
+}
+
+#[tokio::test]
+async fn test_ndjson_extract() -> Result<()> {
+    let path = get_extract_path("extract.ndjson");
+    let mut sink =
+        ExtractSink::create(&path, ExtractFormat::Ndjson, ExtractCompression::None).await?;
+
+    let count = sink
+        .write_all(tokio_stream::iter(get_contacts()?.into_iter().map(Ok)))
+        .await?;
+    assert_eq!(count, 2);
+    assert_eq!(sink.finish().await?, 2);
+
+    assert_eq!(
+        parse_ndjson(&fs::read(&path)?)?,
+        vec![
+            json!({"lastname": "One"}),
+            json!({"lastname": "Two, Jr.", "title": "CEO"})
+        ]
+    );
+
+    fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[cfg(feature = "bulk")]
+#[tokio::test]
+async fn test_csv_extract() -> Result<()> {
+    let path = get_extract_path("extract.csv");
+    let encoder =
+        SObjectCsvEncoder::new(BulkCsvFormat::default()).with_columns(&["LastName", "Title"]);
+    let mut sink =
+        ExtractSink::create(&path, ExtractFormat::Csv(encoder), ExtractCompression::None).await?;
+
+    for contact in get_contacts()? {
+        sink.write(&contact).await?;
+    }
+    sink.finish().await?;
+    assert_eq!(
+        fs::read(&path)?,
+        b"LastName,Title\nOne,\n\"Two, Jr.\",CEO\n"
+    );
+
+    // An empty extract is still a valid CSV file.
+    let encoder =
+        SObjectCsvEncoder::new(BulkCsvFormat::default()).with_columns(&["LastName", "Title"]);
+    let sink =
+        ExtractSink::create(&path, ExtractFormat::Csv(encoder), ExtractCompression::None).await?;
+    assert_eq!(sink.finish().await?, 0);
+    assert_eq!(fs::read(&path)?, b"LastName,Title\n");
+
+    fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_extract_flush_policy() -> Result<()> {
+    let path = get_extract_path("extract-flush.ndjson");
+    let contacts = get_contacts()?;
+    let mut sink = ExtractSink::create(&path, ExtractFormat::Ndjson, ExtractCompression::None)
+        .await?
+        .with_flush_policy(FlushPolicy::never().with_max_records(2));
+    assert_eq!(sink.get_flush_policy().get_max_records(), Some(2));
+
+    // Output stays buffered until the policy is met.
+    sink.write(&contacts[0]).await?;
+    assert_eq!(fs::read(&path)?.len(), 0);
+    sink.write(&contacts[1]).await?;
+    assert_eq!(parse_ndjson(&fs::read(&path)?)?.len(), 2);
+
+    sink.write(&contacts[0]).await?;
+    assert_eq!(parse_ndjson(&fs::read(&path)?)?.len(), 2);
+    assert_eq!(sink.finish().await?, 3);
+    assert_eq!(parse_ndjson(&fs::read(&path)?)?.len(), 3);
+
+    fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[cfg(feature = "compression")]
+#[tokio::test]
+async fn test_compressed_extract() -> Result<()> {
+    use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
+    use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
+
+    async fn read_all(mut reader: impl AsyncRead + Unpin) -> Result<Vec<u8>> {
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content).await?;
+
+        Ok(content)
+    }
+
+    let expected = vec![
+        json!({"lastname": "One"}),
+        json!({"lastname": "Two, Jr.", "title": "CEO"}),
+    ];
+
+    for compression in [ExtractCompression::Gzip, ExtractCompression::Zstd] {
+        let path = get_extract_path("extract.ndjson.compressed");
+        let mut sink = ExtractSink::create(&path, ExtractFormat::Ndjson, compression).await?;
+        sink.write_all(tokio_stream::iter(get_contacts()?.into_iter().map(Ok)))
+            .await?;
+        sink.finish().await?;
+
+        let file = BufReader::new(tokio::fs::File::open(&path).await?);
+        let content = match compression {
+            ExtractCompression::Gzip => read_all(GzipDecoder::new(file)).await?,
+            _ => read_all(ZstdDecoder::new(file)).await?,
+        };
+        assert_eq!(parse_ndjson(&content)?, expected);
+
+        fs::remove_file(&path)?;
+    }
+
+    Ok(())
+}
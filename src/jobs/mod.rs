@@ -0,0 +1,237 @@
+//! Monitoring of asynchronous and scheduled Apex.
+//!
+//! Automation that enqueues Queueable or Batch Apex, or schedules Apex,
+//! can follow the resulting `AsyncApexJob` and `CronTrigger` records here,
+//! and abort them through Anonymous Apex.
+
+use std::pin::Pin;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_stream::try_stream;
+use futures::Stream;
+use serde_derive::Deserialize;
+use tokio::time::sleep;
+
+use crate::{
+    api::Connection,
+    data::{DateTime, SObjectBase, SalesforceId, SingleTypedSObject},
+    errors::SalesforceError,
+    rest::query::{query_all_as, QueryRequest},
+};
+
+#[cfg(test)]
+mod test;
+
+const ASYNC_APEX_JOB_FIELDS: &str = "Id, JobType, Status, ExtendedStatus, ApexClassId, \
+    MethodName, JobItemsProcessed, TotalJobItems, NumberOfErrors, CreatedDate, CompletedDate, \
+    CronTriggerId";
+
+const CRON_TRIGGER_FIELDS: &str = "Id, State, CronExpression, CronJobDetail.Name, \
+    CronJobDetail.JobType, StartTime, EndTime, NextFireTime, PreviousFireTime, TimesTriggered";
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AsyncApexJobStatus {
+    Holding,
+    Queued,
+    Preparing,
+    Processing,
+    Aborted,
+    Completed,
+    Failed,
+}
+
+impl AsyncApexJobStatus {
+    pub fn is_completed_state(&self) -> bool {
+        matches!(
+            self,
+            AsyncApexJobStatus::Aborted
+                | AsyncApexJobStatus::Completed
+                | AsyncApexJobStatus::Failed
+        )
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct AsyncApexJob {
+    pub id: SalesforceId,
+    /// Such as `Queueable`, `BatchApex`, `Future`, or `ScheduledApex`.
+    pub job_type: String,
+    pub status: AsyncApexJobStatus,
+    pub extended_status: Option<String>,
+    pub apex_class_id: Option<SalesforceId>,
+    pub method_name: Option<String>,
+    pub job_items_processed: u64,
+    pub total_job_items: Option<u64>,
+    pub number_of_errors: Option<u64>,
+    pub created_date: DateTime,
+    pub completed_date: Option<DateTime>,
+    pub cron_trigger_id: Option<SalesforceId>,
+}
+
+impl SObjectBase for AsyncApexJob {}
+
+impl SingleTypedSObject for AsyncApexJob {
+    fn get_type_api_name() -> &'static str {
+        "AsyncApexJob"
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CronTriggerState {
+    Waiting,
+    Acquired,
+    Executing,
+    Complete,
+    Error,
+    Deleted,
+    Paused,
+    Blocked,
+    PausedBlocked,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct CronJobDetail {
+    pub name: String,
+    /// `7` for Scheduled Apex.
+    pub job_type: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct CronTrigger {
+    pub id: SalesforceId,
+    pub state: CronTriggerState,
+    pub cron_expression: String,
+    pub cron_job_detail: Option<CronJobDetail>,
+    pub start_time: Option<DateTime>,
+    pub end_time: Option<DateTime>,
+    pub next_fire_time: Option<DateTime>,
+    pub previous_fire_time: Option<DateTime>,
+    pub times_triggered: Option<u64>,
+}
+
+impl SObjectBase for CronTrigger {}
+
+impl SingleTypedSObject for CronTrigger {
+    fn get_type_api_name() -> &'static str {
+        "CronTrigger"
+    }
+}
+
+fn with_filter(query: String, filter: Option<&str>) -> String {
+    match filter {
+        Some(filter) => format!("{} WHERE {}", query, filter),
+        None => query,
+    }
+}
+
+impl Connection {
+    /// Get the `AsyncApexJob` records matching `filter`, a SOQL `WHERE` clause
+    /// without the `WHERE` keyword, most recent first.
+    pub async fn get_async_apex_jobs(&self, filter: Option<&str>) -> Result<Vec<AsyncApexJob>> {
+        query_all_as(
+            self,
+            &QueryRequest::new(
+                &format!(
+                    "{} ORDER BY CreatedDate DESC",
+                    with_filter(
+                        format!("SELECT {} FROM AsyncApexJob", ASYNC_APEX_JOB_FIELDS),
+                        filter
+                    )
+                ),
+                false,
+            ),
+        )
+        .await
+    }
+
+    pub async fn get_async_apex_job(&self, id: SalesforceId) -> Result<AsyncApexJob> {
+        self.get_async_apex_jobs(Some(&format!("Id = '{}'", id)))
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| SalesforceError::RecordDoesNotExistError.into())
+    }
+
+    /// Poll the `AsyncApexJob` `id` every `interval`, yielding the job whenever
+    /// its status or progress changes. The stream ends once the job completes,
+    /// fails, or is aborted.
+    pub fn watch_async_apex_job(
+        &self,
+        id: SalesforceId,
+        interval: Duration,
+    ) -> Pin<Box<dyn Stream<Item = Result<AsyncApexJob>> + Send>> {
+        let conn = self.clone();
+
+        Box::pin(try_stream! {
+            let mut last: Option<AsyncApexJob> = None;
+
+            loop {
+                let job = conn.get_async_apex_job(id).await?;
+                let done = job.status.is_completed_state();
+
+                if last.as_ref() != Some(&job) {
+                    last = Some(job.clone());
+                    yield job;
+                }
+                if done {
+                    break;
+                }
+
+                sleep(interval).await;
+            }
+        })
+    }
+
+    /// Poll the `AsyncApexJob` `id` every `interval` until it completes,
+    /// fails, or is aborted, and return its final state.
+    pub async fn wait_for_async_apex_job(
+        &self,
+        id: SalesforceId,
+        interval: Duration,
+    ) -> Result<AsyncApexJob> {
+        loop {
+            let job = self.get_async_apex_job(id).await?;
+            if job.status.is_completed_state() {
+                return Ok(job);
+            }
+
+            sleep(interval).await;
+        }
+    }
+
+    /// Get the `CronTrigger` records for scheduled jobs matching `filter`,
+    /// a SOQL `WHERE` clause without the `WHERE` keyword.
+    pub async fn get_cron_triggers(&self, filter: Option<&str>) -> Result<Vec<CronTrigger>> {
+        query_all_as(
+            self,
+            &QueryRequest::new(
+                &with_filter(
+                    format!("SELECT {} FROM CronTrigger", CRON_TRIGGER_FIELDS),
+                    filter,
+                ),
+                false,
+            ),
+        )
+        .await
+    }
+
+    pub async fn get_cron_trigger(&self, id: SalesforceId) -> Result<CronTrigger> {
+        self.get_cron_triggers(Some(&format!("Id = '{}'", id)))
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| SalesforceError::RecordDoesNotExistError.into())
+    }
+
+    /// Abort the queued or running `AsyncApexJob`, or unschedule the `CronTrigger`,
+    /// `id` with `System.abortJob()`.
+    pub async fn abort_job(&self, id: SalesforceId) -> Result<()> {
+        self.execute_anonymous(format!("System.abortJob('{}');", id))
+            .await
+    }
+}
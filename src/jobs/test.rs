@@ -0,0 +1,96 @@
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use anyhow::Result;
+use futures::StreamExt;
+
+use crate::data::SalesforceId;
+use crate::test_integration_base::serve_responses;
+
+use super::{AsyncApexJobStatus, CronTriggerState};
+
+const QUEUED: &str = r#"{"totalSize": 1, "done": true, "records": [{"attributes": {"type": "AsyncApexJob"}, "Id": "707000000000001AAA", "JobType": "BatchApex", "Status": "Queued", "ExtendedStatus": null, "ApexClassId": "01p000000000001AAA", "MethodName": null, "JobItemsProcessed": 0, "TotalJobItems": 0, "NumberOfErrors": 0, "CreatedDate": "2022-01-01T00:00:00.000+0000", "CompletedDate": null, "CronTriggerId": null}]}"#;
+const PROCESSING: &str = r#"{"totalSize": 1, "done": true, "records": [{"attributes": {"type": "AsyncApexJob"}, "Id": "707000000000001AAA", "JobType": "BatchApex", "Status": "Processing", "ExtendedStatus": null, "ApexClassId": "01p000000000001AAA", "MethodName": null, "JobItemsProcessed": 1, "TotalJobItems": 2, "NumberOfErrors": 0, "CreatedDate": "2022-01-01T00:00:00.000+0000", "CompletedDate": null, "CronTriggerId": null}]}"#;
+const COMPLETED: &str = r#"{"totalSize": 1, "done": true, "records": [{"attributes": {"type": "AsyncApexJob"}, "Id": "707000000000001AAA", "JobType": "BatchApex", "Status": "Completed", "ExtendedStatus": null, "ApexClassId": "01p000000000001AAA", "MethodName": null, "JobItemsProcessed": 2, "TotalJobItems": 2, "NumberOfErrors": 0, "CreatedDate": "2022-01-01T00:00:00.000+0000", "CompletedDate": "2022-01-01T00:01:00.000+0000", "CronTriggerId": null}]}"#;
+
+#[tokio::test]
+async fn test_watch_async_apex_job() -> Result<()> {
+    let (conn, count) = serve_responses(vec![
+        ("200 OK", QUEUED),
+        ("200 OK", QUEUED),
+        ("200 OK", PROCESSING),
+        ("200 OK", COMPLETED),
+    ])
+    .await?;
+
+    let statuses: Vec<(AsyncApexJobStatus, u64)> = conn
+        .watch_async_apex_job(
+            SalesforceId::new("707000000000001AAA")?,
+            Duration::from_millis(1),
+        )
+        .map(|job| job.map(|job| (job.status, job.job_items_processed)))
+        .collect::<Vec<Result<_>>>()
+        .await
+        .into_iter()
+        .collect::<Result<_>>()?;
+
+    // Unchanged polls are not yielded, and the stream ends when the job completes.
+    assert_eq!(
+        statuses,
+        vec![
+            (AsyncApexJobStatus::Queued, 0),
+            (AsyncApexJobStatus::Processing, 1),
+            (AsyncApexJobStatus::Completed, 2)
+        ]
+    );
+    assert_eq!(count.load(Ordering::SeqCst), 4);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_wait_for_async_apex_job() -> Result<()> {
+    let (conn, _) = serve_responses(vec![("200 OK", PROCESSING), ("200 OK", COMPLETED)]).await?;
+
+    let job = conn
+        .wait_for_async_apex_job(
+            SalesforceId::new("707000000000001AAA")?,
+            Duration::from_millis(1),
+        )
+        .await?;
+
+    assert_eq!(job.status, AsyncApexJobStatus::Completed);
+    assert!(job.completed_date.is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cron_triggers_and_abort() -> Result<()> {
+    let (conn, count) = serve_responses(vec![
+        (
+            "200 OK",
+            r#"{"totalSize": 1, "done": true, "records": [{"attributes": {"type": "CronTrigger"}, "Id": "08e000000000001AAA", "State": "WAITING", "CronExpression": "0 0 2 * * ?", "CronJobDetail": {"attributes": {"type": "CronJobDetail"}, "Name": "Nightly Sync", "JobType": "7"}, "StartTime": "2022-01-01T00:00:00.000+0000", "EndTime": null, "NextFireTime": "2022-01-02T02:00:00.000+0000", "PreviousFireTime": null, "TimesTriggered": 0}]}"#,
+        ),
+        (
+            "200 OK",
+            r#"{"line": -1, "column": -1, "compiled": true, "success": true, "compileProblem": null, "exceptionStackTrace": null, "exceptionMessage": null}"#,
+        ),
+        ("200 OK", r#"{"totalSize": 0, "done": true, "records": []}"#),
+    ])
+    .await?;
+
+    let triggers = conn.get_cron_triggers(Some("State = 'WAITING'")).await?;
+    assert_eq!(triggers.len(), 1);
+    assert_eq!(triggers[0].state, CronTriggerState::Waiting);
+    assert_eq!(
+        triggers[0].cron_job_detail.as_ref().unwrap().name,
+        "Nightly Sync"
+    );
+
+    conn.abort_job(triggers[0].id).await?;
+    assert!(conn.get_cron_trigger(triggers[0].id).await.is_err());
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+
+    Ok(())
+}
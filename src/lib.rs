@@ -1,17 +1,27 @@
-#![feature(async_stream)]
-
 pub mod api;
 pub mod auth;
+#[cfg(feature = "bulk")]
 pub mod bulk;
+pub mod codegen;
 pub mod data;
 pub mod errors;
+pub mod io;
+pub mod jobs;
+pub mod ops;
+#[cfg(feature = "outbox")]
+pub mod outbox;
 pub mod prelude;
 pub mod rest;
+pub mod schema;
+pub mod soql;
+pub mod streaming;
 mod streams;
+#[cfg(feature = "unstable")]
+pub mod testkit;
 pub mod tooling;
+pub mod users;
 
 #[cfg(test)]
 mod test_integration_base;
 
 extern crate chrono;
-extern crate csv;
@@ -0,0 +1,369 @@
+//! DataLoader-style loads of CSV files, with run manifests.
+//!
+//! `DataLoader::load()` reads records from a CSV file whose header row names
+//! their fields, and loads them through sObject Collections. Each run writes
+//! a `RunManifest`: a success CSV of every loaded row, preceded by its Id,
+//! and an error CSV of every failed row, followed by its error code and
+//! message. Both are flushed after each batch, so an interrupted run's
+//! manifest accounts for every batch sent.
+//!
+//! A load restarted from an earlier success file with `with_restart_from()`
+//! skips the rows listed there, copying them with their Ids into its own
+//! success file. Rows are matched on their values, not their position, so
+//! the input may be edited between runs, such as to correct failed rows.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use csv_async::{AsyncWriter, StringRecord};
+use serde_json::{json, Map, Value};
+use tokio::fs::File;
+use tokio_stream::StreamExt;
+
+use crate::{
+    api::Connection,
+    bulk::v2::BulkCsvFormat,
+    errors::SalesforceError,
+    rest::{
+        collections::{
+            SObjectCollectionCreateRequest, SObjectCollectionUpdateRequest,
+            SObjectCollectionUpsertRequest, MAX_COLLECTION_RECORDS,
+        },
+        DmlResult,
+    },
+};
+
+/// The column holding each loaded record's Id in a success file.
+pub const SUCCESS_ID_COLUMN: &str = "Id";
+/// The columns added to each failed row in an error file.
+pub const ERROR_CODE_COLUMN: &str = "Error Code";
+pub const ERROR_MESSAGE_COLUMN: &str = "Error Message";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadOperation {
+    Insert,
+    /// Update records identified by an `Id` column.
+    Update,
+    Upsert {
+        external_id_field: String,
+    },
+}
+
+/// The success and error files written by a `DataLoader` run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunManifest {
+    success_path: PathBuf,
+    error_path: PathBuf,
+}
+
+impl RunManifest {
+    pub fn new(success_path: impl Into<PathBuf>, error_path: impl Into<PathBuf>) -> RunManifest {
+        RunManifest {
+            success_path: success_path.into(),
+            error_path: error_path.into(),
+        }
+    }
+
+    pub fn get_success_path(&self) -> &Path {
+        &self.success_path
+    }
+
+    pub fn get_error_path(&self) -> &Path {
+        &self.error_path
+    }
+}
+
+/// The outcome of a `DataLoader` run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoadSummary {
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Rows skipped because they succeeded in the run restarted from.
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataLoader {
+    sobject_type: String,
+    operation: LoadOperation,
+    batch_size: usize,
+    csv_format: BulkCsvFormat,
+    restart_from: Option<PathBuf>,
+}
+
+impl DataLoader {
+    /// Load records of `sobject_type` in batches of 200, from comma-separated CSV.
+    pub fn new(sobject_type: &str, operation: LoadOperation) -> DataLoader {
+        DataLoader {
+            sobject_type: sobject_type.to_owned(),
+            operation,
+            batch_size: MAX_COLLECTION_RECORDS,
+            csv_format: BulkCsvFormat::default(),
+            restart_from: None,
+        }
+    }
+
+    /// Send up to `batch_size` records per request, at most 200.
+    #[must_use]
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.clamp(1, MAX_COLLECTION_RECORDS);
+        self
+    }
+
+    /// Read the input, and write the manifest, in `csv_format`.
+    #[must_use]
+    pub fn with_csv_format(mut self, csv_format: BulkCsvFormat) -> Self {
+        self.csv_format = csv_format;
+        self
+    }
+
+    /// Skip the rows in the success file of an earlier run. It may not be
+    /// the success file of this run's manifest, which is replaced.
+    #[must_use]
+    pub fn with_restart_from(mut self, success_path: impl Into<PathBuf>) -> Self {
+        self.restart_from = Some(success_path.into());
+        self
+    }
+
+    pub fn get_batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    pub fn get_restart_from(&self) -> Option<&Path> {
+        self.restart_from.as_deref()
+    }
+
+    /// Load the rows of the CSV file `input`, writing the outcome of each to `manifest`.
+    ///
+    /// Rows that Salesforce rejects, and rows in a request that fails, are
+    /// written to the error file rather than ending the load. Empty values
+    /// are omitted, and so leave fields unchanged on update.
+    pub async fn load(
+        &self,
+        conn: &Connection,
+        input: impl AsRef<Path>,
+        manifest: &RunManifest,
+    ) -> Result<LoadSummary> {
+        if self
+            .restart_from
+            .as_deref()
+            .is_some_and(|p| p == manifest.get_success_path())
+        {
+            return Err(SalesforceError::GeneralError(
+                "A load cannot restart from the success file it writes".to_owned(),
+            )
+            .into());
+        }
+
+        let mut prior = match &self.restart_from {
+            Some(path) => self.read_prior_successes(path).await?,
+            None => HashMap::new(),
+        };
+
+        let mut reader = self
+            .csv_format
+            .get_reader_builder()
+            .has_headers(true)
+            .create_reader(File::open(input).await?);
+        let columns = reader.headers().await?.clone();
+
+        let mut success_writer = self.create_writer(manifest.get_success_path()).await?;
+        let mut error_writer = self.create_writer(manifest.get_error_path()).await?;
+        success_writer
+            .write_record(std::iter::once(SUCCESS_ID_COLUMN).chain(columns.iter()))
+            .await?;
+        error_writer
+            .write_record(
+                columns
+                    .iter()
+                    .chain([ERROR_CODE_COLUMN, ERROR_MESSAGE_COLUMN]),
+            )
+            .await?;
+
+        let mut summary = LoadSummary::default();
+        let mut batch: Vec<StringRecord> = Vec::new();
+        let mut records = reader.records();
+
+        while let Some(row) = records.next().await {
+            let row = row?;
+            let key: Vec<String> = row.iter().map(|v| v.to_owned()).collect();
+
+            if let Some(id) = prior.get_mut(&key).and_then(|ids| ids.pop_front()) {
+                success_writer
+                    .write_record(std::iter::once(id.as_str()).chain(row.iter()))
+                    .await?;
+                summary.skipped += 1;
+                continue;
+            }
+
+            batch.push(row);
+            if batch.len() == self.batch_size {
+                self.load_batch(
+                    conn,
+                    &columns,
+                    std::mem::take(&mut batch),
+                    &mut success_writer,
+                    &mut error_writer,
+                    &mut summary,
+                )
+                .await?;
+            }
+        }
+
+        if !batch.is_empty() {
+            self.load_batch(
+                conn,
+                &columns,
+                batch,
+                &mut success_writer,
+                &mut error_writer,
+                &mut summary,
+            )
+            .await?;
+        }
+
+        success_writer.flush().await?;
+        error_writer.flush().await?;
+
+        Ok(summary)
+    }
+
+    async fn create_writer(&self, path: &Path) -> Result<AsyncWriter<File>> {
+        Ok(self
+            .csv_format
+            .get_writer_builder(false)
+            .create_writer(File::create(path).await?))
+    }
+
+    /// The Ids of the rows in an earlier success file, keyed by their values.
+    async fn read_prior_successes(
+        &self,
+        path: &Path,
+    ) -> Result<HashMap<Vec<String>, VecDeque<String>>> {
+        let mut reader = self
+            .csv_format
+            .get_reader_builder()
+            .has_headers(true)
+            .create_reader(File::open(path).await?);
+        let mut records = reader.records();
+        let mut prior: HashMap<Vec<String>, VecDeque<String>> = HashMap::new();
+
+        while let Some(row) = records.next().await {
+            let row = row?;
+            let mut values = row.iter().map(|v| v.to_owned());
+
+            if let Some(id) = values.next() {
+                prior.entry(values.collect()).or_default().push_back(id);
+            }
+        }
+
+        Ok(prior)
+    }
+
+    fn get_record_value(&self, columns: &StringRecord, row: &StringRecord) -> Value {
+        let mut map = Map::new();
+        map.insert("attributes".to_owned(), json!({"type": self.sobject_type}));
+
+        for (column, value) in columns.iter().zip(row.iter()) {
+            if !value.is_empty() {
+                map.insert(column.to_owned(), json!(value));
+            }
+        }
+
+        Value::Object(map)
+    }
+
+    async fn execute_batch(
+        &self,
+        conn: &Connection,
+        records: Vec<Value>,
+    ) -> Result<Vec<DmlResult>> {
+        match &self.operation {
+            LoadOperation::Insert => {
+                conn.execute(&SObjectCollectionCreateRequest::new_raw(records, false))
+                    .await
+            }
+            LoadOperation::Update => {
+                conn.execute(&SObjectCollectionUpdateRequest::new_raw(records, false))
+                    .await
+            }
+            LoadOperation::Upsert { external_id_field } => {
+                conn.execute(&SObjectCollectionUpsertRequest::new_raw(
+                    records,
+                    external_id_field.clone(),
+                    self.sobject_type.clone(),
+                    false,
+                ))
+                .await
+            }
+        }
+    }
+
+    async fn load_batch(
+        &self,
+        conn: &Connection,
+        columns: &StringRecord,
+        batch: Vec<StringRecord>,
+        success_writer: &mut AsyncWriter<File>,
+        error_writer: &mut AsyncWriter<File>,
+        summary: &mut LoadSummary,
+    ) -> Result<()> {
+        let records = batch
+            .iter()
+            .map(|row| self.get_record_value(columns, row))
+            .collect();
+        let results = match self.execute_batch(conn, records).await {
+            Ok(results) if results.len() == batch.len() => Ok(results),
+            Ok(_) => Err("Salesforce returned the wrong number of results".to_owned()),
+            Err(e) => Err(e.to_string()),
+        };
+
+        match results {
+            Ok(results) => {
+                for (row, result) in batch.iter().zip(results) {
+                    match result.id {
+                        Some(id) if result.success => {
+                            let id = id.to_string();
+                            success_writer
+                                .write_record(std::iter::once(id.as_str()).chain(row.iter()))
+                                .await?;
+                            summary.succeeded += 1;
+                        }
+                        _ => {
+                            let code = result
+                                .errors
+                                .iter()
+                                .filter_map(|e| e.get_error_code().cloned())
+                                .collect::<Vec<String>>()
+                                .join("; ");
+                            let message = result
+                                .errors
+                                .iter()
+                                .map(|e| e.error.message.clone())
+                                .collect::<Vec<String>>()
+                                .join("; ");
+                            error_writer
+                                .write_record(row.iter().chain([code.as_str(), message.as_str()]))
+                                .await?;
+                            summary.failed += 1;
+                        }
+                    }
+                }
+            }
+            Err(message) => {
+                for row in batch.iter() {
+                    error_writer
+                        .write_record(row.iter().chain(["", message.as_str()]))
+                        .await?;
+                    summary.failed += 1;
+                }
+            }
+        }
+
+        success_writer.flush().await?;
+        error_writer.flush().await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+//! Operations that move data between orgs, or into an org from another system.
+//!
+//! Records copied from one org to another receive new Ids. An `IdMap`
+//! tracks the correspondence, so that references to copied records, such as
+//! the parents of files, can be rewritten in the destination org.
+//!
+//! Records synced from another system are matched on an external Id field
+//! by `sync_upsert()`, and records in CSV files are loaded by a `DataLoader`.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use futures::StreamExt;
+use serde_derive::Deserialize;
+use serde_json::{json, Map, Value};
+use tokio::{spawn, sync::mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::{
+    api::Connection,
+    data::SalesforceId,
+    errors::SalesforceError,
+    rest::files::ContentVersionUploadRequest,
+    rest::query::{query_all_as, QueryRequest},
+    rest::rows::{SObjectBlobFieldRequest, SObjectCreateRequest},
+};
+
+#[cfg(feature = "bulk")]
+pub mod load;
+pub mod upsert;
+
+#[cfg(feature = "bulk")]
+pub use load::{DataLoader, LoadOperation, LoadSummary, RunManifest};
+pub use upsert::sync_upsert;
+
+#[cfg(test)]
+mod test;
+
+/// The number of chunks of file content buffered between download and upload.
+const COPY_BUFFER_CHUNKS: usize = 16;
+
+/// A mapping from record Ids in a source org to record Ids in a destination org.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IdMap(HashMap<SalesforceId, SalesforceId>);
+
+impl IdMap {
+    pub fn new() -> IdMap {
+        IdMap::default()
+    }
+
+    pub fn insert(&mut self, source: SalesforceId, destination: SalesforceId) {
+        self.0.insert(source, destination);
+    }
+
+    pub fn get(&self, source: SalesforceId) -> Option<SalesforceId> {
+        self.0.get(&source).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&SalesforceId, &SalesforceId)> {
+        self.0.iter()
+    }
+}
+
+impl FromIterator<(SalesforceId, SalesforceId)> for IdMap {
+    fn from_iter<I: IntoIterator<Item = (SalesforceId, SalesforceId)>>(iter: I) -> Self {
+        IdMap(iter.into_iter().collect())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SourceContentVersion {
+    id: SalesforceId,
+    title: String,
+    path_on_client: Option<String>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SourceContentDocumentLink {
+    linked_entity_id: SalesforceId,
+    share_type: Option<String>,
+    visibility: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CopiedContentVersion {
+    content_document_id: SalesforceId,
+}
+
+/// Copy the latest version of each of the files `content_document_ids` from
+/// `conn_src` to `conn_dst`, streaming its content without holding it in memory.
+///
+/// Each file is shared with the destination records to which `id_map` maps the
+/// records it is shared with in the source org. Links to unmapped records,
+/// such as the owning user's library, are not copied.
+///
+/// Returns an `IdMap` from source to destination `ContentDocument` Ids.
+pub async fn copy_files(
+    conn_src: &Connection,
+    conn_dst: &Connection,
+    content_document_ids: &[SalesforceId],
+    id_map: &IdMap,
+) -> Result<IdMap> {
+    let content_version_type = conn_src.get_type("ContentVersion").await?;
+    let mut documents = IdMap::new();
+
+    for document_id in content_document_ids {
+        let version: SourceContentVersion = query_all_as(
+            conn_src,
+            &QueryRequest::new(
+                &format!(
+                    "SELECT Id, Title, PathOnClient, Description FROM ContentVersion \
+                    WHERE ContentDocumentId = '{}' AND IsLatest = true",
+                    document_id
+                ),
+                false,
+            ),
+        )
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(SalesforceError::RecordDoesNotExistError)?;
+        let links: Vec<SourceContentDocumentLink> = query_all_as(
+            conn_src,
+            &QueryRequest::new(
+                &format!(
+                    "SELECT LinkedEntityId, ShareType, Visibility FROM ContentDocumentLink \
+                    WHERE ContentDocumentId = '{}'",
+                    document_id
+                ),
+                false,
+            ),
+        )
+        .await?;
+
+        let mut fields = Map::new();
+        fields.insert("Title".to_owned(), json!(version.title));
+        if let Some(path_on_client) = version.path_on_client {
+            fields.insert("PathOnClient".to_owned(), json!(path_on_client));
+        }
+        if let Some(description) = version.description {
+            fields.insert("Description".to_owned(), json!(description));
+        }
+
+        // Relay the download to the upload through a bounded channel,
+        // so that only a few chunks are in memory at once.
+        let mut content = conn_src
+            .execute_raw_request(&SObjectBlobFieldRequest::new(
+                &content_version_type,
+                version.id,
+                "VersionData",
+            )?)
+            .await?
+            .stream;
+        let (sender, receiver) = mpsc::channel(COPY_BUFFER_CHUNKS);
+        let relay = spawn(async move {
+            while let Some(chunk) = content.next().await {
+                if sender.send(chunk.map_err(Into::into)).await.is_err() {
+                    break; // The upload failed.
+                }
+            }
+        });
+
+        let result = conn_dst
+            .execute_raw_request(&ContentVersionUploadRequest::new(
+                &Value::Object(fields),
+                ReceiverStream::new(receiver),
+            )?)
+            .await;
+        relay.await?;
+        let new_version_id: SalesforceId = Into::<Result<SalesforceId>>::into(result?)?;
+
+        let new_document_id = query_all_as::<CopiedContentVersion>(
+            conn_dst,
+            &QueryRequest::new(
+                &format!(
+                    "SELECT ContentDocumentId FROM ContentVersion WHERE Id = '{}'",
+                    new_version_id
+                ),
+                false,
+            ),
+        )
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(SalesforceError::RecordDoesNotExistError)?
+        .content_document_id;
+
+        for link in links {
+            if let Some(linked_entity_id) = id_map.get(link.linked_entity_id) {
+                let mut fields = Map::new();
+                fields.insert("ContentDocumentId".to_owned(), json!(new_document_id));
+                fields.insert("LinkedEntityId".to_owned(), json!(linked_entity_id));
+                if let Some(share_type) = link.share_type {
+                    fields.insert("ShareType".to_owned(), json!(share_type));
+                }
+                if let Some(visibility) = link.visibility {
+                    fields.insert("Visibility".to_owned(), json!(visibility));
+                }
+
+                let result = conn_dst
+                    .execute(&SObjectCreateRequest::new_raw(
+                        Value::Object(fields),
+                        "ContentDocumentLink".to_owned(),
+                    ))
+                    .await?;
+                Into::<Result<SalesforceId>>::into(result)?;
+            }
+        }
+
+        documents.insert(*document_id, new_document_id);
+    }
+
+    Ok(documents)
+}
@@ -0,0 +1,353 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Result;
+use reqwest::Method;
+use serde_json::{json, Value};
+
+use super::upsert::{DuplicatePolicy, SyncFailureReason, SyncUpsertOptions};
+use super::{copy_files, sync_upsert, IdMap};
+use crate::api::transport::TransportResponse;
+use crate::prelude::*;
+use crate::test_integration_base::{
+    get_test_field_describe, get_test_sobject_describe, get_test_sobject_type, json_response,
+    raw_response, serve_routes, RecordedRequest, RouteTransport,
+};
+
+const CONTENT: &str = "Quarterly figures";
+
+fn query_response(records: Vec<Value>) -> Result<TransportResponse> {
+    json_response(
+        200,
+        &json!({"totalSize": records.len(), "done": true, "records": records}),
+    )
+}
+
+// Serves either side of a file copy.
+fn get_org_transport() -> RouteTransport {
+    RouteTransport::new()
+        .with_route(Method::GET, "/describe", |_| {
+            json_response(
+                200,
+                &get_test_sobject_describe(
+                    "ContentVersion",
+                    vec![
+                        get_test_field_describe("Id", "tns:ID", "id"),
+                        get_test_field_describe("VersionData", "xsd:base64Binary", "base64"),
+                    ],
+                    vec![],
+                    vec![],
+                )?,
+            )
+        })
+        .with_route(Method::GET, "/query", |r| {
+            let soql = r.get_query_parameter("q").unwrap_or_default();
+            if soql.contains("IsLatest") {
+                query_response(vec![json!({
+                    "attributes": {"type": "ContentVersion"},
+                    "Id": "068000000000001AAA",
+                    "Title": "Figures",
+                    "PathOnClient": "figures.txt",
+                    "Description": null
+                })])
+            } else if soql.contains("FROM ContentDocumentLink") {
+                query_response(vec![
+                    json!({
+                        "attributes": {"type": "ContentDocumentLink"},
+                        "LinkedEntityId": "001000000000001AAA",
+                        "ShareType": "V",
+                        "Visibility": "AllUsers"
+                    }),
+                    json!({
+                        "attributes": {"type": "ContentDocumentLink"},
+                        "LinkedEntityId": "005000000000001AAA",
+                        "ShareType": "I",
+                        "Visibility": "AllUsers"
+                    }),
+                ])
+            } else {
+                query_response(vec![json!({
+                    "attributes": {"type": "ContentVersion"},
+                    "ContentDocumentId": "069000000000002AAA"
+                })])
+            }
+        })
+        .with_route(Method::GET, "/VersionData", |_| raw_response(200, CONTENT))
+        .with_json(
+            Method::POST,
+            "/sobjects/ContentVersion",
+            201,
+            json!({"id": "068000000000002AAA", "success": true, "errors": []}),
+        )
+        .with_json(
+            Method::POST,
+            "/sobjects/ContentDocumentLink",
+            201,
+            json!({"id": "080000000000001AAA", "success": true, "errors": []}),
+        )
+}
+
+#[tokio::test]
+async fn test_copy_files() -> Result<()> {
+    let (conn_src, _) = serve_routes(get_org_transport())?;
+    let (conn_dst, destination) = serve_routes(get_org_transport())?;
+
+    let id_map: IdMap = vec![(
+        SalesforceId::new("001000000000001AAA")?,
+        SalesforceId::new("001000000000002AAA")?,
+    )]
+    .into_iter()
+    .collect();
+    let documents = copy_files(
+        &conn_src,
+        &conn_dst,
+        &[SalesforceId::new("069000000000001AAA")?],
+        &id_map,
+    )
+    .await?;
+
+    assert_eq!(documents.len(), 1);
+    assert_eq!(
+        documents.get(SalesforceId::new("069000000000001AAA")?),
+        Some(SalesforceId::new("069000000000002AAA")?)
+    );
+
+    let requests = destination.get_requests();
+    let upload = requests[0].text();
+    assert_eq!(requests[0].method, Method::POST);
+    assert!(upload.contains(r#""Title":"Figures""#));
+    assert!(upload.contains(r#"filename="figures.txt""#));
+    assert!(upload.contains(CONTENT));
+    assert!(!upload.contains("Description"));
+
+    // Only the link to the mapped Account is recreated.
+    let links: Vec<&RecordedRequest> = requests
+        .iter()
+        .filter(|r| {
+            r.path()
+                .trim_end_matches('/')
+                .ends_with("/sobjects/ContentDocumentLink")
+        })
+        .collect();
+    assert_eq!(links.len(), 1);
+    let link = links[0].json()?;
+    assert_eq!(link["LinkedEntityId"], json!("001000000000002AAA"));
+    assert_eq!(link["ContentDocumentId"], json!("069000000000002AAA"));
+    assert_eq!(link["ShareType"], json!("V"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sync_upsert() -> Result<()> {
+    // Serves sObject Collections upsert responses in turn.
+    let responses = Mutex::new(VecDeque::from(vec![
+        json!([
+            {"id": "001000000000001AAA", "success": true, "created": true, "errors": []},
+            {"success": false, "errors": [{"statusCode": "UNABLE_TO_LOCK_ROW", "message": "locked", "fields": []}]},
+            {"success": false, "errors": [{"statusCode": "REQUIRED_FIELD_MISSING", "message": "Name", "fields": ["Name"]}]}
+        ]),
+        json!([
+            {"id": "001000000000002AAA", "success": true, "created": false, "errors": []}
+        ]),
+    ]));
+    let (conn, transport) = serve_routes(RouteTransport::new().with_route(
+        Method::PATCH,
+        "/composite/sobjects/Account/Ext__c",
+        move |_| json_response(200, &responses.lock().unwrap().pop_front().unwrap()),
+    ))?;
+    let sobject_type = get_test_sobject_type(
+        "Account",
+        vec![
+            get_test_field_describe("Name", "xsd:string", "string"),
+            get_test_field_describe("Ext__c", "xsd:string", "string"),
+        ],
+        vec![],
+    )?;
+    let account = |ext: Option<&str>, name: &str| {
+        let account = SObject::new(&sobject_type).with_str("Name", name);
+        match ext {
+            Some(ext) => account.with_str("Ext__c", ext),
+            None => account,
+        }
+    };
+    let records = vec![
+        account(Some("A"), "First"),
+        account(Some("B"), "Second"),
+        account(Some("A"), "Third"),
+        account(None, "Fourth"),
+        account(Some("C"), "Fifth"),
+    ];
+
+    let summary = sync_upsert(
+        &conn,
+        tokio_stream::iter(records.clone()),
+        "Ext__c",
+        &SyncUpsertOptions::new().with_retries(1, Duration::ZERO),
+    )
+    .await?;
+
+    assert_eq!(summary.superseded, 1);
+    assert_eq!(summary.created.len(), 1);
+    assert_eq!(summary.created[0].external_id, "A");
+    assert_eq!(summary.updated.len(), 1);
+    assert_eq!(summary.updated[0].external_id, "B");
+    assert_eq!(summary.failed.len(), 2);
+    assert!(matches!(
+        summary.failed[0].reason,
+        SyncFailureReason::MissingExternalId
+    ));
+    assert_eq!(summary.failed[1].external_id.as_deref(), Some("C"));
+    assert!(matches!(
+        summary.failed[1].reason,
+        SyncFailureReason::Rejected(_)
+    ));
+
+    // The last record for "A" won, and only "B" was retried.
+    let bodies = transport
+        .get_requests()
+        .iter()
+        .map(|r| r.json())
+        .collect::<Result<Vec<Value>>>()?;
+    assert_eq!(bodies.len(), 2);
+    assert_eq!(bodies[0]["records"].as_array().unwrap().len(), 3);
+    assert_eq!(bodies[0]["records"][0]["name"], "Third");
+    assert_eq!(bodies[1]["records"][0]["ext__c"], "B");
+
+    let summary = sync_upsert(
+        &conn,
+        tokio_stream::iter(vec![records[0].clone(), records[2].clone()]),
+        "Ext__c",
+        &SyncUpsertOptions::new().with_duplicates(DuplicatePolicy::Error),
+    )
+    .await?;
+    assert_eq!(summary.failed.len(), 2);
+    assert!(summary
+        .failed
+        .iter()
+        .all(|f| matches!(f.reason, SyncFailureReason::DuplicateExternalId)));
+
+    Ok(())
+}
+
+// Serves sObject Collections create responses, rejecting records named "Bad".
+#[cfg(feature = "bulk")]
+fn get_load_transport() -> RouteTransport {
+    let created = Mutex::new(0);
+    RouteTransport::new().with_route(Method::POST, "/composite/sobjects", move |r| {
+        let results = r.json()?["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|record| {
+                let mut created = created.lock().unwrap();
+                *created += 1;
+                if record["Name"] == "Bad" {
+                    json!({"success": false, "errors": [{"statusCode": "FIELD_CUSTOM_VALIDATION_EXCEPTION", "message": "Bad name", "fields": []}]})
+                } else {
+                    json!({"id": format!("001{:012}AAA", *created), "success": true, "errors": []})
+                }
+            })
+            .collect();
+
+        json_response(200, &Value::Array(results))
+    })
+}
+
+// The records sent to a `get_load_transport()`, in order.
+#[cfg(feature = "bulk")]
+fn get_loaded_records(transport: &RouteTransport) -> Vec<Value> {
+    transport
+        .get_requests()
+        .iter()
+        .flat_map(|r| r.json().unwrap()["records"].as_array().unwrap().clone())
+        .collect()
+}
+
+#[cfg(feature = "bulk")]
+#[tokio::test]
+async fn test_data_loader_manifest_and_restart() -> Result<()> {
+    use super::load::{DataLoader, LoadOperation, LoadSummary, RunManifest};
+
+    let (conn, transport) = serve_routes(get_load_transport())?;
+    let path = |name: &str| {
+        let path =
+            std::env::temp_dir().join(format!("baris-load-{}-{}.csv", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    };
+    let input = path("input");
+    let first = RunManifest::new(path("success-1"), path("error-1"));
+    let second = RunManifest::new(path("success-2"), path("error-2"));
+
+    std::fs::write(
+        &input,
+        "Name,Industry\nOne,Tech\nBad,\n\"Three, Inc.\",Retail\n",
+    )?;
+    let loader = DataLoader::new("Account", LoadOperation::Insert).with_batch_size(2);
+    assert_eq!(
+        loader.load(&conn, &input, &first).await?,
+        LoadSummary {
+            succeeded: 2,
+            failed: 1,
+            skipped: 0
+        }
+    );
+    assert_eq!(
+        std::fs::read_to_string(first.get_success_path())?,
+        "Id,Name,Industry\n001000000000001AAA,One,Tech\n001000000000003AAA,\"Three, Inc.\",Retail\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(first.get_error_path())?,
+        "Name,Industry,Error Code,Error Message\nBad,,FIELD_CUSTOM_VALIDATION_EXCEPTION,Bad name\n"
+    );
+    {
+        let records = get_loaded_records(&transport);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0]["attributes"]["type"], "Account");
+        // Empty values are omitted.
+        assert_eq!(
+            records[1],
+            json!({"attributes": {"type": "Account"}, "Name": "Bad"})
+        );
+    }
+
+    // A restart with the failed row corrected loads only that row. Skipped
+    // rows are written at once, ahead of the batch in progress.
+    std::fs::write(
+        &input,
+        "Name,Industry\nOne,Tech\nGood,\n\"Three, Inc.\",Retail\n",
+    )?;
+    let loader = loader.with_restart_from(first.get_success_path());
+    assert_eq!(
+        loader.load(&conn, &input, &second).await?,
+        LoadSummary {
+            succeeded: 1,
+            failed: 0,
+            skipped: 2
+        }
+    );
+    assert_eq!(get_loaded_records(&transport).len(), 4);
+    assert_eq!(
+        std::fs::read_to_string(second.get_success_path())?,
+        "Id,Name,Industry\n001000000000001AAA,One,Tech\n001000000000003AAA,\"Three, Inc.\",Retail\n001000000000004AAA,Good,\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(second.get_error_path())?,
+        "Name,Industry,Error Code,Error Message\n"
+    );
+
+    // A run cannot overwrite the success file it restarts from.
+    assert!(loader.load(&conn, &input, &first).await.is_err());
+    assert_eq!(get_loaded_records(&transport).len(), 4);
+
+    for path in [&input, first.get_success_path(), first.get_error_path()]
+        .into_iter()
+        .chain([second.get_success_path(), second.get_error_path()])
+    {
+        std::fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,377 @@
+//! Upserts keyed on external Ids, for integrations that sync records from
+//! another system.
+//!
+//! `sync_upsert()` collapses records that share an external Id before any are
+//! sent, so that a batch never upserts the same record twice, and retries
+//! records that fail transiently, such as on `UNABLE_TO_LOCK_ROW`. Every input
+//! record is accounted for in the returned `SyncUpsertSummary`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::Result;
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+use tokio::time::sleep;
+
+use crate::{
+    api::{retry::is_transient_error, Connection},
+    data::{SObjectSerialization, SalesforceId, TypedSObject},
+    errors::SalesforceError,
+    rest::{
+        collections::{SObjectCollectionUpsertRequest, MAX_COLLECTION_RECORDS},
+        DmlError, DmlResult,
+    },
+};
+
+/// How `sync_upsert()` treats records that share an external Id.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DuplicatePolicy {
+    /// Upsert only the last record received for each external Id.
+    LastWriteWins,
+    /// Fail every record whose external Id is shared with another record.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncUpsertOptions {
+    duplicates: DuplicatePolicy,
+    batch_size: usize,
+    max_retries: u32,
+    retry_backoff: Duration,
+}
+
+impl Default for SyncUpsertOptions {
+    fn default() -> Self {
+        SyncUpsertOptions::new()
+    }
+}
+
+impl SyncUpsertOptions {
+    /// Last write wins, with batches of 200 records and up to three retries.
+    pub fn new() -> SyncUpsertOptions {
+        SyncUpsertOptions {
+            duplicates: DuplicatePolicy::LastWriteWins,
+            batch_size: MAX_COLLECTION_RECORDS,
+            max_retries: 3,
+            retry_backoff: Duration::from_secs(1),
+        }
+    }
+
+    #[must_use]
+    pub fn with_duplicates(mut self, duplicates: DuplicatePolicy) -> Self {
+        self.duplicates = duplicates;
+        self
+    }
+
+    /// Upsert up to `batch_size` records per request, at most 200.
+    #[must_use]
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.clamp(1, MAX_COLLECTION_RECORDS);
+        self
+    }
+
+    /// Retry records that fail transiently up to `max_retries` times, waiting
+    /// `backoff` before the first retry and doubling the wait for each after.
+    #[must_use]
+    pub fn with_retries(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_backoff = backoff;
+        self
+    }
+}
+
+/// A record that was created or updated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncedRecord {
+    pub sobject_type: String,
+    pub external_id: String,
+    pub id: SalesforceId,
+}
+
+#[derive(Debug, Clone)]
+pub enum SyncFailureReason {
+    MissingExternalId,
+    DuplicateExternalId,
+    /// Salesforce rejected the record.
+    Rejected(Vec<DmlError>),
+    /// The request carrying the record failed.
+    RequestFailed(String),
+}
+
+impl fmt::Display for SyncFailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SyncFailureReason::MissingExternalId => write!(f, "No external Id value"),
+            SyncFailureReason::DuplicateExternalId => {
+                write!(f, "The external Id is shared with another record")
+            }
+            SyncFailureReason::Rejected(errors) => write!(
+                f,
+                "{}",
+                errors
+                    .iter()
+                    .map(|e| e.error.to_string())
+                    .collect::<Vec<String>>()
+                    .join("; ")
+            ),
+            SyncFailureReason::RequestFailed(error) => write!(f, "Request failed: {}", error),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncFailure {
+    pub sobject_type: String,
+    pub external_id: Option<String>,
+    pub reason: SyncFailureReason,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SyncUpsertSummary {
+    pub created: Vec<SyncedRecord>,
+    pub updated: Vec<SyncedRecord>,
+    pub failed: Vec<SyncFailure>,
+    /// The number of records superseded by a later record with the same external Id.
+    pub superseded: usize,
+}
+
+impl SyncUpsertSummary {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// The records of one sObject type, keyed by external Id in the order first seen.
+#[derive(Default)]
+struct TypeRecords {
+    order: Vec<String>,
+    records: HashMap<String, (Value, usize)>,
+}
+
+/// Upsert `records` on `external_id_field`, deduplicating them per `options`.
+///
+/// All records are received before any are sent, so that duplicates can be
+/// found. External Ids are compared exactly, which matches Salesforce only for
+/// case-sensitive external Id fields; records whose external Ids differ only in
+/// case are upserted in separate batches.
+pub async fn sync_upsert<T>(
+    conn: &Connection,
+    records: impl Stream<Item = T> + Unpin,
+    external_id_field: &str,
+    options: &SyncUpsertOptions,
+) -> Result<SyncUpsertSummary>
+where
+    T: SObjectSerialization + TypedSObject,
+{
+    let mut summary = SyncUpsertSummary::default();
+    let mut types: Vec<(String, TypeRecords)> = Vec::new();
+    let mut records = records;
+
+    while let Some(record) = records.next().await {
+        let sobject_type = record.get_api_name().to_owned();
+        let value = record.to_value_with_options(true, false)?;
+
+        let external_id = match get_external_id(&value, external_id_field) {
+            Some(external_id) => external_id,
+            None => {
+                summary.failed.push(SyncFailure {
+                    sobject_type,
+                    external_id: None,
+                    reason: SyncFailureReason::MissingExternalId,
+                });
+                continue;
+            }
+        };
+
+        let index = match types
+            .iter()
+            .position(|(t, _)| t.eq_ignore_ascii_case(&sobject_type))
+        {
+            Some(index) => index,
+            None => {
+                types.push((sobject_type, TypeRecords::default()));
+                types.len() - 1
+            }
+        };
+        let type_records = &mut types[index].1;
+
+        match type_records.records.get_mut(&external_id) {
+            Some((existing, count)) => {
+                *existing = value;
+                *count += 1;
+            }
+            None => {
+                type_records.order.push(external_id.clone());
+                type_records.records.insert(external_id, (value, 1));
+            }
+        }
+    }
+
+    for (sobject_type, mut type_records) in types {
+        let mut batch = Vec::new();
+
+        for external_id in type_records.order {
+            let (value, count) = type_records.records.remove(&external_id).unwrap();
+
+            if count > 1 {
+                match options.duplicates {
+                    DuplicatePolicy::LastWriteWins => summary.superseded += count - 1,
+                    DuplicatePolicy::Error => {
+                        summary.failed.extend((0..count).map(|_| SyncFailure {
+                            sobject_type: sobject_type.clone(),
+                            external_id: Some(external_id.clone()),
+                            reason: SyncFailureReason::DuplicateExternalId,
+                        }));
+                        continue;
+                    }
+                }
+            }
+
+            batch.push((external_id, value));
+            if batch.len() == options.batch_size {
+                upsert_batch(
+                    conn,
+                    &sobject_type,
+                    external_id_field,
+                    std::mem::take(&mut batch),
+                    options,
+                    &mut summary,
+                )
+                .await;
+            }
+        }
+
+        if !batch.is_empty() {
+            upsert_batch(
+                conn,
+                &sobject_type,
+                external_id_field,
+                batch,
+                options,
+                &mut summary,
+            )
+            .await;
+        }
+    }
+
+    Ok(summary)
+}
+
+fn get_external_id(value: &Value, external_id_field: &str) -> Option<String> {
+    let (_, external_id) = value
+        .as_object()?
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(external_id_field))?;
+
+    match external_id {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Whether a record failed for a reason that may not recur.
+fn is_transient_failure(result: &DmlResult) -> bool {
+    !result.errors.is_empty()
+        && is_transient_error(
+            &SalesforceError::ApiErrors(result.errors.iter().map(|e| e.error.clone()).collect())
+                .into(),
+        )
+}
+
+/// Upsert `batch`, retrying records that fail transiently, and record
+/// the outcome of each in `summary`.
+async fn upsert_batch(
+    conn: &Connection,
+    sobject_type: &str,
+    external_id_field: &str,
+    batch: Vec<(String, Value)>,
+    options: &SyncUpsertOptions,
+    summary: &mut SyncUpsertSummary,
+) {
+    let mut pending = batch;
+    let mut attempt = 0;
+
+    while !pending.is_empty() {
+        let can_retry = attempt < options.max_retries;
+        let request = SObjectCollectionUpsertRequest::new_raw(
+            pending.iter().map(|(_, v)| v.clone()).collect(),
+            external_id_field.to_owned(),
+            sobject_type.to_owned(),
+            false,
+        );
+        let failed = |external_id: String, reason| SyncFailure {
+            sobject_type: sobject_type.to_owned(),
+            external_id: Some(external_id),
+            reason,
+        };
+
+        let retry = match conn.execute(&request).await {
+            Ok(results) if results.len() != pending.len() => {
+                summary
+                    .failed
+                    .extend(pending.into_iter().map(|(external_id, _)| {
+                        failed(
+                            external_id,
+                            SyncFailureReason::RequestFailed(
+                                "Salesforce returned the wrong number of results".to_owned(),
+                            ),
+                        )
+                    }));
+                Vec::new()
+            }
+            Ok(results) => {
+                let mut retry = Vec::new();
+
+                for ((external_id, value), result) in pending.into_iter().zip(results) {
+                    if result.success {
+                        match result.id {
+                            Some(id) => {
+                                let record = SyncedRecord {
+                                    sobject_type: sobject_type.to_owned(),
+                                    external_id,
+                                    id,
+                                };
+                                if result.created == Some(true) {
+                                    summary.created.push(record);
+                                } else {
+                                    summary.updated.push(record);
+                                }
+                            }
+                            None => summary.failed.push(failed(
+                                external_id,
+                                SyncFailureReason::RequestFailed("No Id was returned".to_owned()),
+                            )),
+                        }
+                    } else if can_retry && is_transient_failure(&result) {
+                        retry.push((external_id, value));
+                    } else {
+                        summary.failed.push(failed(
+                            external_id,
+                            SyncFailureReason::Rejected(result.errors),
+                        ));
+                    }
+                }
+
+                retry
+            }
+            Err(e) if can_retry && is_transient_error(&e) => pending,
+            Err(e) => {
+                summary
+                    .failed
+                    .extend(pending.into_iter().map(|(external_id, _)| {
+                        failed(external_id, SyncFailureReason::RequestFailed(e.to_string()))
+                    }));
+                Vec::new()
+            }
+        };
+
+        if !retry.is_empty() {
+            sleep(options.retry_backoff * 2u32.saturating_pow(attempt)).await;
+            attempt += 1;
+        }
+        pending = retry;
+    }
+}
@@ -0,0 +1,419 @@
+//! Durable queueing of DML for delivery when Salesforce is reachable.
+//!
+//! Applications that must keep working while offline, or while the org's API
+//! limits are exhausted, can record mutations in an `Outbox` instead of sending
+//! them immediately. Draining the outbox sends them in the order they were
+//! recorded, stopping at the first transient failure so that they can be retried
+//! later. Mutations that Salesforce rejects are passed to a conflict handler, and
+//! later mutations of the same record wait until that one is resolved.
+//!
+//! A create that fails after it may have reached Salesforce, such as with a
+//! server error or a dropped connection, is never sent again automatically,
+//! since that could create a duplicate record. Use an upsert on an external Id
+//! for records that must be delivered exactly once.
+//!
+//! `SledOutbox` persists the outbox in an embedded `sled` database; other storage
+//! backends can be supplied by implementing the trait.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use log::{info, warn};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::task::spawn_blocking;
+use tokio::time::sleep;
+
+use crate::{
+    api::{
+        retry::{is_transient_error, is_unsent_error},
+        Connection,
+    },
+    data::{DateTime, SalesforceId},
+    rest::rows::{
+        SObjectCreateRequest, SObjectDeleteRequest, SObjectUpdateRequest, SObjectUpsertRequest,
+    },
+};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum OutboxOperation {
+    Create,
+    Update {
+        id: SalesforceId,
+    },
+    #[serde(rename_all = "camelCase")]
+    Upsert {
+        external_id_field: String,
+        external_id: String,
+    },
+    Delete {
+        id: SalesforceId,
+    },
+}
+
+/// A mutation awaiting delivery.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxEntry {
+    /// The entry's position in the outbox. Entries are sent in ascending order.
+    pub sequence: u64,
+    pub sobject: String,
+    pub operation: OutboxOperation,
+    /// The record's fields, without `attributes`. Ignored for deletes.
+    pub body: Value,
+    pub enqueued: DateTime,
+    /// The number of failed attempts to send this entry.
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    /// Whether this is a create that failed after it may have reached
+    /// Salesforce. Such entries are passed to the conflict handler on each
+    /// drain instead of being sent again.
+    #[serde(default)]
+    pub unconfirmed: bool,
+}
+
+impl OutboxEntry {
+    pub fn new(
+        sequence: u64,
+        sobject: &str,
+        operation: OutboxOperation,
+        body: Value,
+    ) -> OutboxEntry {
+        OutboxEntry {
+            sequence,
+            sobject: sobject.to_owned(),
+            operation,
+            body,
+            enqueued: DateTime::now(),
+            attempts: 0,
+            last_error: None,
+            unconfirmed: false,
+        }
+    }
+
+    // Identifies the record this entry mutates, if it is an existing record.
+    fn get_record_key(&self) -> Option<String> {
+        match &self.operation {
+            OutboxOperation::Create => None,
+            OutboxOperation::Update { id } | OutboxOperation::Delete { id } => Some(id.to_string()),
+            OutboxOperation::Upsert {
+                external_id_field,
+                external_id,
+            } => Some(format!(
+                "{}.{}:{}",
+                self.sobject, external_id_field, external_id
+            )),
+        }
+    }
+
+    async fn send(&self, conn: &Connection) -> Result<()> {
+        match &self.operation {
+            OutboxOperation::Create => {
+                let result: Result<SalesforceId> = conn
+                    .execute(&SObjectCreateRequest::new_raw(
+                        self.body.clone(),
+                        self.sobject.clone(),
+                    ))
+                    .await?
+                    .into();
+                result?;
+            }
+            OutboxOperation::Update { id } => {
+                conn.execute(&SObjectUpdateRequest::new_raw(
+                    self.body.clone(),
+                    self.sobject.clone(),
+                    id.to_string(),
+                ))
+                .await?
+            }
+            OutboxOperation::Upsert {
+                external_id_field,
+                external_id,
+            } => {
+                let result: Result<Option<SalesforceId>> = conn
+                    .execute(&SObjectUpsertRequest::new_raw(
+                        self.body.clone(),
+                        self.sobject.clone(),
+                        external_id_field.clone(),
+                        external_id.clone(),
+                    ))
+                    .await?
+                    .into();
+                result?;
+            }
+            OutboxOperation::Delete { id } => {
+                conn.execute(&SObjectDeleteRequest::new_raw(
+                    self.sobject.clone(),
+                    id.to_string(),
+                ))
+                .await?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// What to do with an entry that Salesforce rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConflictResolution {
+    /// Remove the entry from the outbox.
+    Discard,
+    /// Keep the entry, to be sent again on the next drain. An unconfirmed
+    /// create is kept, but not sent.
+    Hold,
+    /// Replace the entry's body, such as with a merge of the local and
+    /// remote record, to be sent again on the next drain.
+    Replace(Value),
+}
+
+/// Decides how to resolve an entry that Salesforce rejected with an error.
+pub type ConflictHandler = dyn Fn(&OutboxEntry, &anyhow::Error) -> ConflictResolution + Send + Sync;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DrainReport {
+    pub sent: usize,
+    pub discarded: usize,
+    /// Entries rejected by Salesforce and kept in the outbox.
+    pub held: usize,
+    /// Entries not sent because an earlier entry for the same record was held.
+    pub deferred: usize,
+    /// Whether draining stopped early on a transient failure.
+    pub interrupted: bool,
+}
+
+/// Storage for `OutboxEntry`s. Implementors provide persistence; enqueueing
+/// and delivery to Salesforce are provided on top of that storage.
+#[async_trait]
+pub trait Outbox: Send + Sync {
+    /// Add an entry, replacing any entry with the same sequence number.
+    /// The entry must be durably stored before this returns.
+    async fn push(&mut self, entry: OutboxEntry) -> Result<()>;
+    /// All entries, in ascending sequence order.
+    async fn list(&self) -> Result<Vec<OutboxEntry>>;
+    async fn remove(&mut self, sequence: u64) -> Result<Option<OutboxEntry>>;
+
+    /// The sequence number for the next entry.
+    async fn get_next_sequence(&self) -> Result<u64> {
+        Ok(self
+            .list()
+            .await?
+            .iter()
+            .map(|e| e.sequence + 1)
+            .max()
+            .unwrap_or(1))
+    }
+
+    /// Record a mutation for later delivery, returning its sequence number.
+    async fn enqueue(
+        &mut self,
+        sobject: &str,
+        operation: OutboxOperation,
+        body: Value,
+    ) -> Result<u64> {
+        let sequence = self.get_next_sequence().await?;
+
+        self.push(OutboxEntry::new(sequence, sobject, operation, body))
+            .await?;
+
+        Ok(sequence)
+    }
+
+    async fn len(&self) -> Result<usize> {
+        Ok(self.list().await?.len())
+    }
+
+    async fn is_empty(&self) -> Result<bool> {
+        Ok(self.len().await? == 0)
+    }
+
+    /// Send each entry once, in order. Entries that succeed are removed.
+    /// Draining stops at the first failure caused by connectivity, an
+    /// unavailable org, or exhausted limits, leaving that entry and those after
+    /// it for a later drain. Entries that Salesforce rejects, and creates that
+    /// may or may not have taken effect, are resolved by `on_conflict`, or held
+    /// if it is `None`. Later entries for the same record as a held entry are
+    /// deferred to a later drain.
+    async fn drain(
+        &mut self,
+        conn: &Connection,
+        on_conflict: Option<&ConflictHandler>,
+    ) -> Result<DrainReport> {
+        let mut report = DrainReport::default();
+        let mut held_records = HashSet::new();
+
+        for mut entry in self.list().await? {
+            let record_key = entry.get_record_key();
+            if let Some(record_key) = &record_key {
+                if held_records.contains(record_key) {
+                    report.deferred += 1;
+                    continue;
+                }
+            }
+
+            let error = if entry.unconfirmed {
+                anyhow!(entry
+                    .last_error
+                    .clone()
+                    .unwrap_or_else(|| "Delivery is unconfirmed".to_owned()))
+            } else {
+                let error = match entry.send(conn).await {
+                    Ok(()) => {
+                        self.remove(entry.sequence).await?;
+                        report.sent += 1;
+                        continue;
+                    }
+                    Err(error) => error,
+                };
+
+                entry.attempts += 1;
+                entry.last_error = Some(error.to_string());
+
+                if is_transient_error(&error) {
+                    if entry.operation != OutboxOperation::Create || is_unsent_error(&error) {
+                        info!("Outbox delivery interrupted: {}", error);
+                        self.push(entry).await?;
+                        report.interrupted = true;
+                        break;
+                    }
+
+                    warn!(
+                        "Outbox entry {} may have been created: {}",
+                        entry.sequence, error
+                    );
+                    entry.unconfirmed = true;
+                }
+
+                error
+            };
+
+            match on_conflict.map_or(ConflictResolution::Hold, |h| h(&entry, &error)) {
+                ConflictResolution::Discard => {
+                    warn!("Discarding outbox entry {}: {}", entry.sequence, error);
+                    self.remove(entry.sequence).await?;
+                    report.discarded += 1;
+                    continue;
+                }
+                ConflictResolution::Hold => {}
+                ConflictResolution::Replace(body) => {
+                    entry.body = body;
+                    entry.unconfirmed = false;
+                }
+            }
+
+            self.push(entry).await?;
+            report.held += 1;
+            if let Some(record_key) = record_key {
+                held_records.insert(record_key);
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Drain the outbox, waiting `interval` after each interrupted drain,
+    /// until every entry has been sent, discarded, held, or deferred.
+    async fn drain_until_settled(
+        &mut self,
+        conn: &Connection,
+        interval: Duration,
+        on_conflict: Option<&ConflictHandler>,
+    ) -> Result<DrainReport> {
+        let mut total = DrainReport::default();
+
+        loop {
+            let report = self.drain(conn, on_conflict).await?;
+
+            total.sent += report.sent;
+            total.discarded += report.discarded;
+            total.held = report.held;
+            total.deferred = report.deferred;
+            if !report.interrupted {
+                return Ok(total);
+            }
+
+            sleep(interval).await;
+        }
+    }
+}
+
+const SLED_TREE_NAME: &str = "baris-outbox";
+
+/// An `Outbox` persisted in a `sled` database. Every mutation is flushed
+/// to disk before it returns.
+pub struct SledOutbox {
+    tree: sled::Tree,
+}
+
+impl SledOutbox {
+    /// Open the outbox in the database at `path`, creating an empty outbox
+    /// if the database does not yet exist.
+    pub async fn open(path: impl AsRef<Path>) -> Result<SledOutbox> {
+        let path = path.as_ref().to_path_buf();
+        let db = spawn_blocking(move || sled::open(path)).await??;
+
+        SledOutbox::new(&db)
+    }
+
+    /// Use the outbox stored in `db`, which the application may also use
+    /// for its own data.
+    pub fn new(db: &sled::Db) -> Result<SledOutbox> {
+        Ok(SledOutbox {
+            tree: db.open_tree(SLED_TREE_NAME)?,
+        })
+    }
+
+    // Big-endian keys make the tree's order the sequence order.
+    fn get_key(sequence: u64) -> [u8; 8] {
+        sequence.to_be_bytes()
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.tree.flush_async().await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Outbox for SledOutbox {
+    async fn push(&mut self, entry: OutboxEntry) -> Result<()> {
+        self.tree.insert(
+            SledOutbox::get_key(entry.sequence),
+            serde_json::to_vec(&entry)?,
+        )?;
+        self.flush().await
+    }
+
+    async fn list(&self) -> Result<Vec<OutboxEntry>> {
+        self.tree
+            .iter()
+            .values()
+            .map(|value| Ok(serde_json::from_slice(&value?)?))
+            .collect()
+    }
+
+    async fn remove(&mut self, sequence: u64) -> Result<Option<OutboxEntry>> {
+        match self.tree.remove(SledOutbox::get_key(sequence))? {
+            Some(value) => {
+                self.flush().await?;
+                Ok(Some(serde_json::from_slice(&value)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn get_next_sequence(&self) -> Result<u64> {
+        Ok(match self.tree.last()? {
+            Some((_, value)) => serde_json::from_slice::<OutboxEntry>(&value)?.sequence + 1,
+            None => 1,
+        })
+    }
+}
@@ -0,0 +1,208 @@
+use std::{env, fs, path::PathBuf};
+
+use anyhow::Result;
+use serde_json::json;
+
+use super::{
+    ConflictHandler, ConflictResolution, DrainReport, Outbox, OutboxEntry, OutboxOperation,
+    SledOutbox,
+};
+use crate::data::SalesforceId;
+use crate::test_integration_base::{get_offline_connection, serve_responses};
+
+fn get_outbox_path(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("baris-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+
+    path
+}
+
+#[tokio::test]
+async fn test_sled_outbox_round_trip() -> Result<()> {
+    let path = get_outbox_path("outbox-round-trip");
+    let id = SalesforceId::new("001000000000001AAA")?;
+
+    let mut outbox = SledOutbox::open(&path).await?;
+    assert!(outbox.is_empty().await?);
+    assert_eq!(
+        outbox
+            .enqueue("Account", OutboxOperation::Create, json!({"Name": "Acme"}))
+            .await?,
+        1
+    );
+    assert_eq!(
+        outbox
+            .enqueue(
+                "Account",
+                OutboxOperation::Upsert {
+                    external_id_field: "Ext__c".to_owned(),
+                    external_id: "A-1".to_owned()
+                },
+                json!({"Name": "Acme"})
+            )
+            .await?,
+        2
+    );
+    assert_eq!(
+        outbox
+            .enqueue("Account", OutboxOperation::Delete { id }, json!(null))
+            .await?,
+        3
+    );
+    outbox.remove(2).await?;
+    drop(outbox);
+
+    let outbox = SledOutbox::open(&path).await?;
+    let entries = outbox.list().await?;
+    assert_eq!(
+        entries.iter().map(|e| e.sequence).collect::<Vec<u64>>(),
+        vec![1, 3]
+    );
+    assert_eq!(entries[1].operation, OutboxOperation::Delete { id });
+    assert_eq!(entries[0].body, json!({"Name": "Acme"}));
+    assert_eq!(outbox.get_next_sequence().await?, 4);
+
+    drop(outbox);
+    fs::remove_dir_all(&path)?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_outbox_drain() -> Result<()> {
+    let path = get_outbox_path("outbox-drain");
+    let id = SalesforceId::new("001000000000001AAA")?;
+    let other_id = SalesforceId::new("001000000000002AAA")?;
+    let (conn, count) = serve_responses(vec![
+        (
+            "201 Created",
+            r#"{"id": "001000000000001AAA", "success": true, "errors": []}"#,
+        ),
+        (
+            "400 Bad Request",
+            r#"[{"message": "entity is deleted", "errorCode": "ENTITY_IS_DELETED", "fields": []}]"#,
+        ),
+        (
+            "400 Bad Request",
+            r#"[{"message": "bad value", "errorCode": "INVALID_FIELD", "fields": []}]"#,
+        ),
+        ("503 Service Unavailable", ""),
+    ])
+    .await?;
+
+    let mut outbox = SledOutbox::open(&path).await?;
+    outbox
+        .enqueue("Account", OutboxOperation::Create, json!({"Name": "A"}))
+        .await?;
+    outbox
+        .enqueue(
+            "Account",
+            OutboxOperation::Update { id },
+            json!({"Name": "B"}),
+        )
+        .await?;
+    outbox
+        .enqueue(
+            "Account",
+            OutboxOperation::Update { id },
+            json!({"Name": "C"}),
+        )
+        .await?;
+    outbox
+        .enqueue("Account", OutboxOperation::Delete { id }, json!(null))
+        .await?;
+    outbox
+        .enqueue(
+            "Account",
+            OutboxOperation::Update { id: other_id },
+            json!({"Name": "D"}),
+        )
+        .await?;
+    outbox
+        .enqueue("Account", OutboxOperation::Create, json!({"Name": "E"}))
+        .await?;
+
+    let on_conflict: &ConflictHandler = &|entry: &OutboxEntry, _error: &anyhow::Error| {
+        if entry.sequence == 2 {
+            ConflictResolution::Discard
+        } else {
+            ConflictResolution::Replace(json!({"Name": "Merged"}))
+        }
+    };
+    let report = outbox.drain(&conn, Some(on_conflict)).await?;
+
+    assert_eq!(
+        report,
+        DrainReport {
+            sent: 1,
+            discarded: 1,
+            held: 1,
+            deferred: 1,
+            interrupted: true
+        }
+    );
+    // The delete waits behind the held update of the same record, and delivery
+    // stops at the unavailable org, so the last entry is not attempted.
+    assert_eq!(count.load(std::sync::atomic::Ordering::SeqCst), 4);
+
+    let entries = outbox.list().await?;
+    assert_eq!(
+        entries.iter().map(|e| e.sequence).collect::<Vec<u64>>(),
+        vec![3, 4, 5, 6]
+    );
+    assert_eq!(entries[0].body, json!({"Name": "Merged"}));
+    assert_eq!(entries[0].attempts, 1);
+    assert_eq!(entries[1].attempts, 0);
+    assert_eq!(entries[2].attempts, 1);
+    assert!(entries[2].last_error.is_some());
+    assert_eq!(entries[3].attempts, 0);
+
+    drop(outbox);
+    fs::remove_dir_all(&path)?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_outbox_unconfirmed_create() -> Result<()> {
+    let path = get_outbox_path("outbox-unconfirmed");
+    let (conn, count) = serve_responses(vec![("500 Internal Server Error", "")]).await?;
+
+    let mut outbox = SledOutbox::open(&path).await?;
+    outbox
+        .enqueue("Account", OutboxOperation::Create, json!({"Name": "A"}))
+        .await?;
+    outbox
+        .enqueue("Account", OutboxOperation::Create, json!({"Name": "B"}))
+        .await?;
+
+    // The server error leaves the first create's outcome unknown, so it is
+    // held rather than sent again. The second create cannot connect once the
+    // server has stopped, so it is kept to be retried on a later drain.
+    let report = outbox.drain(&conn, None).await?;
+    assert_eq!(report.held, 1);
+    assert!(report.interrupted);
+    assert_eq!(count.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+    let entries = outbox.list().await?;
+    assert!(entries[0].unconfirmed);
+    assert!(!entries[1].unconfirmed);
+    assert_eq!(entries[1].attempts, 1);
+
+    // Unconfirmed creates are passed to the conflict handler without being sent.
+    let conn = get_offline_connection()?;
+    let on_conflict: &ConflictHandler = &|entry: &OutboxEntry, _error: &anyhow::Error| {
+        assert!(entry.unconfirmed);
+        ConflictResolution::Discard
+    };
+    let report = outbox.drain(&conn, Some(on_conflict)).await?;
+    assert_eq!(report.discarded, 1);
+    assert!(report.interrupted);
+    assert_eq!(outbox.len().await?, 1);
+    assert_eq!(outbox.list().await?[0].attempts, 2);
+
+    drop(outbox);
+    fs::remove_dir_all(&path)?;
+
+    Ok(())
+}
@@ -0,0 +1,17 @@
+//! The connection, sObject representations, and field value types that
+//! every use of the crate needs.
+
+pub use crate::api::Connection;
+
+// Data
+pub use crate::data::fields::{FieldHandle, FieldType};
+pub use crate::data::sobjects::{FieldValue, SObject, SObjectType};
+pub use crate::data::traits::{
+    DynamicallyTypedSObject, PolymorphicSObject, SObjectBase, SObjectDeserialization,
+    SObjectRepresentation, SObjectSerialization, SObjectWithId, SingleTypedSObject, TypedSObject,
+};
+pub use crate::data::types::{Address, Date, DateTime, Geolocation, SalesforceId, Time};
+pub use crate::data::validation::{FieldViolation, RecordViolations, ViolationKind};
+
+// Errors
+pub use crate::errors::SalesforceError;
@@ -0,0 +1,14 @@
+//! Traits for loading and extracting records through the Bulk API 2.0.
+
+// Typed Bulk traits
+pub use crate::bulk::v2::traits::{
+    BulkDeletable, BulkInsertable, BulkQueryable, BulkUpdateable, BulkUpsertable,
+};
+// Untyped Bulk traits
+pub use crate::bulk::v2::traits::{
+    SingleTypeBulkDeletable, SingleTypeBulkInsertable, SingleTypeBulkQueryable,
+    SingleTypeBulkUpdateable, SingleTypeBulkUpsertable,
+};
+
+#[cfg(feature = "unstable")]
+pub use crate::bulk::export::SlicedExport;
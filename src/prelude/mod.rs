@@ -1,38 +1,20 @@
-pub use crate::api::Connection;
-// Typed Bulk traits
-pub use crate::bulk::v2::traits::{
-    BulkDeletable, BulkInsertable, BulkQueryable, BulkUpdateable, BulkUpsertable,
-};
-// Untyped Bulk traits
-pub use crate::bulk::v2::traits::{
-    SingleTypeBulkDeletable, SingleTypeBulkInsertable, SingleTypeBulkQueryable,
-    SingleTypeBulkUpdateable, SingleTypeBulkUpsertable,
-};
+//! Commonly used types and traits.
+//!
+//! `prelude::base` holds the types every use of the crate needs, and
+//! `prelude::rest` and `prelude::bulk` the traits for each API. Glob-importing
+//! `prelude::*` brings in all three. No tier is named after a crate, so the
+//! glob import does not shadow paths such as `core::mem`. Experimental APIs,
+//! which may change in minor releases, exist only with the `unstable` feature,
+//! and are exported here when it is enabled.
 
-// Data
-pub use crate::data::sobjects::{FieldValue, SObject, SObjectType};
-pub use crate::data::traits::{
-    DynamicallyTypedSObject, SObjectBase, SObjectDeserialization, SObjectRepresentation,
-    SObjectSerialization, SObjectWithId, SingleTypedSObject, TypedSObject,
-};
-pub use crate::data::types::{Address, Date, DateTime, Geolocation, SalesforceId, Time};
+pub mod base;
+#[cfg(feature = "bulk")]
+pub mod bulk;
+pub mod rest;
+#[cfg(test)]
+mod test;
 
-// REST
-pub use crate::rest::collections::traits::{
-    SObjectCollectionCreateable, SObjectCollectionDeleteable, SObjectCollectionUpdateable,
-    SObjectCollectionUpsertable,
-};
-pub use crate::rest::collections::SObjectStream;
-pub use crate::rest::composite::CompositeRequest;
-pub use crate::rest::query::traits::{Queryable, QueryableSingleType};
-pub use crate::rest::query::AggregateResult;
-pub use crate::rest::rows::traits::{
-    SObjectDynamicallyTypedRetrieval, SObjectRowCreateable, SObjectRowDeletable,
-    SObjectRowUpdateable, SObjectRowUpsertable, SObjectSingleTypedRetrieval,
-};
-
-// Tooling
-pub use crate::tooling;
-
-// Errors
-pub use crate::errors::SalesforceError;
+pub use self::base::*;
+#[cfg(feature = "bulk")]
+pub use self::bulk::*;
+pub use self::rest::*;
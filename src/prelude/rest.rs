@@ -0,0 +1,19 @@
+//! Traits for working with records through the REST API.
+
+pub use crate::rest::collections::traits::{
+    SObjectCollectionCreateable, SObjectCollectionDeleteable, SObjectCollectionUpdateable,
+    SObjectCollectionUpsertable,
+};
+pub use crate::rest::collections::{DeleteMode, SObjectIdStream};
+pub use crate::rest::collections::{SObjectStream, SplittableCollectionRequest};
+pub use crate::rest::composite::traits::SObjectRowFetchable;
+pub use crate::rest::composite::{CompositeRequest, Transaction};
+pub use crate::rest::query::traits::{Queryable, QueryableSingleType};
+pub use crate::rest::query::AggregateResult;
+pub use crate::rest::rows::traits::{
+    SObjectDynamicallyTypedRetrieval, SObjectRowCreateable, SObjectRowDeletable,
+    SObjectRowUpdateable, SObjectRowUpsertable, SObjectSingleTypedRetrieval,
+};
+
+// Tooling
+pub use crate::tooling;
@@ -0,0 +1,9 @@
+// A glob import of the prelude must not shadow the `core` or `std` crates.
+use crate::prelude::*;
+
+#[test]
+fn test_prelude_glob_does_not_shadow_crates() {
+    assert!(core::mem::size_of::<SObject>() > 0);
+    assert!(std::mem::size_of::<FieldValue>() > 0);
+    assert_eq!(core::cmp::max(1, 2), 2);
+}
@@ -0,0 +1,99 @@
+//! Invocation of custom Apex REST services.
+//!
+//! Apex REST endpoints live beneath `/services/apexrest/` rather than the
+//! versioned REST API, but are authenticated, retried, and throttled by the
+//! `Connection` like any other request.
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use anyhow::Result;
+use reqwest::Method;
+use serde_json::Value;
+
+use crate::api::{Connection, SalesforceRequest};
+
+#[cfg(test)]
+mod test;
+
+/// A call to the Apex REST service at `path`, the `urlMapping` of an Apex class
+/// annotated with `@RestResource`, whose JSON response is deserialized as `T`.
+///
+/// Use `Value` for `T` to receive the response as is, or `()` for services that
+/// return nothing.
+pub struct ApexRestRequest<T> {
+    method: Method,
+    path: String,
+    body: Option<Value>,
+    query_parameters: Option<Value>,
+    headers: Option<HashMap<String, String>>,
+    phantom: PhantomData<T>,
+}
+
+impl<T> ApexRestRequest<T> {
+    pub fn new(method: Method, path: &str) -> ApexRestRequest<T> {
+        ApexRestRequest {
+            method,
+            path: path.trim_start_matches('/').to_owned(),
+            body: None,
+            query_parameters: None,
+            headers: None,
+            phantom: PhantomData,
+        }
+    }
+
+    #[must_use]
+    pub fn with_body(mut self, body: Value) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    #[must_use]
+    pub fn with_query_parameters(mut self, query_parameters: Value) -> Self {
+        self.query_parameters = Some(query_parameters);
+        self
+    }
+
+    #[must_use]
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers
+            .get_or_insert_with(HashMap::new)
+            .insert(name.to_owned(), value.to_owned());
+        self
+    }
+}
+
+impl<T> SalesforceRequest for ApexRestRequest<T>
+where
+    T: for<'de> serde::Deserialize<'de>,
+{
+    type ReturnValue = T;
+
+    fn get_body(&self) -> Option<Value> {
+        self.body.clone()
+    }
+
+    fn get_url(&self) -> String {
+        // An absolute path replaces the versioned REST API path.
+        format!("/services/apexrest/{}", self.path)
+    }
+
+    fn get_method(&self) -> Method {
+        self.method.clone()
+    }
+
+    fn get_query_parameters(&self) -> Option<Value> {
+        self.query_parameters.clone()
+    }
+
+    fn get_headers(&self) -> Option<HashMap<String, String>> {
+        self.headers.clone()
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        // Services that return nothing respond with an empty body.
+        Ok(serde_json::from_value(
+            body.cloned().unwrap_or(Value::Null),
+        )?)
+    }
+}
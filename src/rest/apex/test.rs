@@ -0,0 +1,58 @@
+use anyhow::Result;
+use reqwest::Method;
+use serde_derive::Deserialize;
+use serde_json::json;
+
+use super::ApexRestRequest;
+use crate::api::transport::TransportResponse;
+use crate::test_integration_base::{
+    json_response, serve_responses, serve_routes, RecordedRequest, RouteTransport,
+};
+
+// Echoes the method, path, and query of each request.
+fn echo(request: &RecordedRequest) -> Result<TransportResponse> {
+    json_response(
+        200,
+        &json!({
+            "method": request.method.as_str(),
+            "path": request.path(),
+            "query": request.url.query()
+        }),
+    )
+}
+
+#[derive(Deserialize)]
+struct Echo {
+    method: String,
+    path: String,
+    query: Option<String>,
+}
+
+#[tokio::test]
+async fn test_apex_rest_request() -> Result<()> {
+    let (conn, _) = serve_routes(RouteTransport::new().with_any_route("", echo))?;
+
+    let echo: Echo = conn
+        .execute(
+            &ApexRestRequest::new(Method::POST, "/Invoices/v1")
+                .with_body(json!({"amount": 10}))
+                .with_query_parameters(json!({"draft": "true"})),
+        )
+        .await?;
+
+    assert_eq!(echo.method, "POST");
+    assert_eq!(echo.path, "/services/apexrest/Invoices/v1");
+    assert_eq!(echo.query.as_deref(), Some("draft=true"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_apex_rest_request_empty_response() -> Result<()> {
+    let (conn, _) = serve_responses(vec![("204 No Content", "")]).await?;
+
+    conn.execute(&ApexRestRequest::<()>::new(Method::DELETE, "Invoices/v1"))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use std::{
+    collections::{HashMap, HashSet},
+    pin::Pin,
+};
+
+use anyhow::Result;
+use async_stream::try_stream;
+use futures::{Stream, StreamExt};
+
+use crate::{
+    api::Connection,
+    data::traits::{SObjectSerialization, TypedSObject},
+    errors::SalesforceError,
+    rest::DmlResult,
+    schema::ObjectGraph,
+};
+
+use super::{SObjectCollectionUpsertRequest, MAX_COLLECTION_RECORDS};
+
+/// The result of upserting one record of a heterogeneous stream.
+#[derive(Debug, Clone)]
+pub struct GroupedDmlResult {
+    /// The sObject type of the record.
+    pub sobject: String,
+    /// The position of the record in the input stream.
+    pub index: usize,
+    pub result: DmlResult,
+}
+
+/// Group `records` by sObject type, in dependency order so that parent types
+/// are upserted before types that look up to them.
+pub(crate) fn group_records<T>(
+    records: Vec<T>,
+    graph: &ObjectGraph,
+) -> Vec<(String, Vec<(usize, T)>)>
+where
+    T: TypedSObject,
+{
+    let mut names: Vec<String> = Vec::new();
+    let mut groups: HashMap<String, Vec<(usize, T)>> = HashMap::new();
+
+    for (index, record) in records.into_iter().enumerate() {
+        let key = record.get_api_name().to_lowercase();
+
+        if !groups.contains_key(&key) {
+            names.push(record.get_api_name().to_owned());
+        }
+        groups.entry(key).or_default().push((index, record));
+    }
+
+    let order: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+    graph
+        .sort_parents_first(&order)
+        .into_iter()
+        .map(|name| {
+            let records = groups.remove(&name.to_lowercase()).unwrap_or_default();
+            (name.to_owned(), records)
+        })
+        .collect()
+}
+
+impl Connection {
+    /// Upsert a stream of records of several sObject types. Records are
+    /// grouped by type, and each group is upserted with sObject Collections
+    /// requests on the external Id field given for its type in `external_ids`.
+    /// Groups are dispatched parents first, so children can refer to parents
+    /// by external Id.
+    ///
+    /// The whole stream is read before any requests are made. Results are
+    /// yielded in dispatch order; use `GroupedDmlResult::index` to match
+    /// them to input records. `all_or_none` applies to each request.
+    pub async fn upsert_by_type<T>(
+        &self,
+        records: impl Stream<Item = T>,
+        external_ids: &HashMap<String, String>,
+        all_or_none: bool,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<GroupedDmlResult>> + Send>>>
+    where
+        T: SObjectSerialization + TypedSObject + Send + Sync + 'static,
+    {
+        let records: Vec<T> = records.collect().await;
+        let external_ids: HashMap<String, String> = external_ids
+            .iter()
+            .map(|(k, v)| (k.to_lowercase(), v.clone()))
+            .collect();
+
+        let mut graph = ObjectGraph::default();
+        for name in records
+            .iter()
+            .map(|r| r.get_api_name().to_lowercase())
+            .collect::<HashSet<String>>()
+        {
+            if !external_ids.contains_key(&name) {
+                return Err(SalesforceError::GeneralError(format!(
+                    "No external Id field given for sObject {}",
+                    name
+                ))
+                .into());
+            }
+            graph.add_type(self.get_type(&name).await?);
+        }
+
+        let groups = group_records(records, &graph);
+        let conn = self.clone();
+
+        Ok(Box::pin(try_stream! {
+            for (sobject, group) in groups {
+                let external_id = &external_ids[&sobject.to_lowercase()];
+
+                for chunk in group.chunks(MAX_COLLECTION_RECORDS) {
+                    let (indices, chunk_records): (Vec<usize>, Vec<&T>) =
+                        chunk.iter().map(|(i, r)| (*i, r)).unzip();
+                    let request = SObjectCollectionUpsertRequest::new_raw(
+                        chunk_records
+                            .iter()
+                            .map(|r| r.to_value_with_options(true, false))
+                            .collect::<Result<Vec<_>>>()?,
+                        external_id.clone(),
+                        sobject.clone(),
+                        all_or_none,
+                    );
+                    let results = conn.execute(&request).await?;
+
+                    for (index, result) in indices.into_iter().zip(results) {
+                        yield GroupedDmlResult {
+                            sobject: sobject.clone(),
+                            index,
+                            result,
+                        };
+                    }
+                }
+            }
+        }))
+    }
+}
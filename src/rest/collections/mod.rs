@@ -1,14 +1,17 @@
-use std::{marker::PhantomData, pin::Pin};
+use std::{
+    collections::HashMap, hash::Hash, marker::PhantomData, pin::Pin, sync::Arc, time::Instant,
+};
 
 use crate::{
+    api::concurrency::{is_throttling_error, ConcurrencyController},
     api::Connection,
-    api::{CompositeFriendlyRequest, SalesforceRequest},
+    api::{AssignmentRule, CompositeFriendlyRequest, SalesforceRequest},
     data::traits::{
         SObjectDeserialization, SObjectRepresentation, SObjectSerialization, SObjectWithId,
         TypedSObject,
     },
     data::SObjectType,
-    data::SalesforceId,
+    data::{FieldValue, SalesforceId},
     errors::SalesforceError,
 };
 
@@ -18,14 +21,237 @@ use reqwest::Method;
 use serde_json::{json, Value};
 
 use async_stream::stream;
+use async_stream::try_stream;
 use async_trait::async_trait;
 use futures::{Stream, StreamExt};
 use tokio::{spawn, sync::mpsc, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
 
 use super::DmlResult;
 
+pub mod grouped;
+mod recycle_bin;
 pub mod traits;
 
+pub use recycle_bin::DeleteMode;
+
+/// The maximum number of records in a single sObject Collections request.
+pub const MAX_COLLECTION_RECORDS: usize = 200;
+/// The maximum number of Ids in a single sObject Collections retrieve request
+/// made with POST. Requests made with GET are limited to about 800 by URL length.
+pub const MAX_RETRIEVE_IDS: usize = 2000;
+/// The maximum size of a REST API request body.
+pub const MAX_REQUEST_BODY_SIZE: usize = 50 * 1024 * 1024;
+/// The maximum number of type chunks in a single sObject Collections create or
+/// update request. A chunk is a run of consecutive records of the same sObject
+/// type, so records of one type should be kept together.
+pub const MAX_COLLECTION_TYPE_CHUNKS: usize = 10;
+
+fn get_record_type(record: &Value) -> Option<String> {
+    record["attributes"]["type"]
+        .as_str()
+        .map(|t| t.to_lowercase())
+}
+
+/// The number of type chunks in `records`, which Salesforce limits to
+/// `MAX_COLLECTION_TYPE_CHUNKS` per request.
+pub fn count_type_chunks(records: &[Value]) -> usize {
+    records.iter().map(get_record_type).dedup().count()
+}
+
+/// Stably reorder `items` so that items with the same key are contiguous, in
+/// order of each key's first appearance. Returns each item with its original position.
+fn order_by_key<T, K>(items: Vec<T>, key: impl Fn(&T) -> K) -> Vec<(usize, T)>
+where
+    K: Eq + Hash + Clone,
+{
+    let mut keys: Vec<K> = Vec::new();
+    let mut groups: HashMap<K, Vec<(usize, T)>> = HashMap::new();
+
+    for (index, item) in items.into_iter().enumerate() {
+        let k = key(&item);
+        if !groups.contains_key(&k) {
+            keys.push(k.clone());
+        }
+        groups.entry(k).or_default().push((index, item));
+    }
+
+    keys.iter()
+        .flat_map(|k| groups.remove(k).unwrap_or_default())
+        .collect()
+}
+
+/// Reorder `records` so that records of each sObject type are contiguous,
+/// minimizing the number of type chunks. Returns each record with its original
+/// position, so that results can be matched to the input.
+pub fn group_records_by_type(records: Vec<Value>) -> Vec<(usize, Value)> {
+    order_by_key(records, get_record_type)
+}
+
+/// Return an error if `records` cannot be sent in a single sObject Collections
+/// create or update request because of the type chunk limit.
+fn validate_type_chunks(records: &[Value]) -> Result<()> {
+    let chunks = count_type_chunks(records);
+    if chunks <= MAX_COLLECTION_TYPE_CHUNKS {
+        return Ok(());
+    }
+
+    let types = records.iter().map(get_record_type).unique().count();
+    Err(SalesforceError::GeneralError(if types > MAX_COLLECTION_TYPE_CHUNKS {
+        format!(
+            "A collection request may contain at most {} sObject types, but these records have {}",
+            MAX_COLLECTION_TYPE_CHUNKS, types
+        )
+    } else {
+        format!(
+            "These records form {} type chunks, above the limit of {}; group them by sObject type with group_records_by_type()",
+            chunks, MAX_COLLECTION_TYPE_CHUNKS
+        )
+    })
+    .into())
+}
+
+/// Partition `records` into groups of at most `max_records` whose serialized size,
+/// together with `base_size` bytes of request overhead, does not exceed `max_size`.
+/// A group is also ended before it would exceed `MAX_COLLECTION_TYPE_CHUNKS`.
+pub fn split_records_by_size(
+    records: Vec<Value>,
+    base_size: usize,
+    max_records: usize,
+    max_size: usize,
+) -> Result<Vec<Vec<Value>>> {
+    let mut batches = Vec::new();
+    let mut batch = Vec::new();
+    let mut batch_size = base_size;
+    let mut batch_chunks = 0;
+
+    for record in records {
+        // Each record after the first also adds a separating comma.
+        let record_size = record.to_string().len() + 1;
+
+        if base_size + record_size > max_size {
+            return Err(SalesforceError::GeneralError(format!(
+                "A single record of {} bytes exceeds the maximum request size of {} bytes",
+                record_size - 1,
+                max_size
+            ))
+            .into());
+        }
+
+        let new_chunk = batch
+            .last()
+            .is_none_or(|last| get_record_type(last) != get_record_type(&record));
+
+        if !batch.is_empty()
+            && (batch.len() == max_records
+                || batch_size + record_size > max_size
+                || (new_chunk && batch_chunks == MAX_COLLECTION_TYPE_CHUNKS))
+        {
+            batches.push(batch);
+            batch = Vec::new();
+            batch_size = base_size;
+            batch_chunks = 0;
+        }
+
+        if batch.is_empty() || new_chunk {
+            batch_chunks += 1;
+        }
+        batch_size += record_size;
+        batch.push(record);
+    }
+
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+
+    Ok(batches)
+}
+
+/// An sObject Collections DML request, which returns one result per record.
+pub trait CollectionDmlRequest:
+    SalesforceRequest<ReturnValue = Vec<DmlResult>> + CompositeFriendlyRequest
+{
+    /// The number of records, or Ids, in this request.
+    fn get_record_count(&self) -> usize;
+}
+
+/// An sObject Collections create, update, or upsert request, whose records
+/// may be divided among several requests.
+pub trait SplittableCollectionRequest: SalesforceRequest + Sized {
+    /// Remove and return this request's records, leaving it empty.
+    fn take_records(&mut self) -> Vec<Value>;
+
+    /// A request like this one, but with `records` in place of its own.
+    fn with_records(&self, records: Vec<Value>) -> Self;
+
+    /// Split this request into requests within the record and `max_size` limits.
+    /// Note that `all_or_none` applies to each resulting request separately.
+    fn split(mut self, max_size: usize) -> Result<Vec<Self>> {
+        let records = self.take_records();
+        // The empty request is the overhead that each split request carries.
+        let base_size = self.estimate_payload_size();
+
+        Ok(
+            split_records_by_size(records, base_size, MAX_COLLECTION_RECORDS, max_size)?
+                .into_iter()
+                .map(|records| self.with_records(records))
+                .collect(),
+        )
+    }
+}
+
+/// The number of results a stream has yielded, and how many of them were errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StreamProgress {
+    pub processed: usize,
+    pub failed: usize,
+}
+
+/// Wrap a stream of results, such as that of `SObjectStream::create_all()`,
+/// to pass its progress to `progress` after each result, and to end it with
+/// `SalesforceError::Cancelled` once `cancel` is cancelled.
+///
+/// Cancelling drops `stream`. For the streams of `SObjectStream`, this
+/// stops further requests from being sent, but requests already in flight complete.
+pub fn with_progress<T>(
+    stream: Pin<Box<dyn Stream<Item = Result<T>> + Send>>,
+    progress: impl Fn(StreamProgress) + Send + 'static,
+    cancel: CancellationToken,
+) -> Pin<Box<dyn Stream<Item = Result<T>> + Send>>
+where
+    T: Send + 'static,
+{
+    Box::pin(stream! {
+        let mut stream = stream;
+        let mut current = StreamProgress::default();
+
+        loop {
+            // `None` if cancelled. Cancellation takes precedence over ready items.
+            let item = tokio::select! {
+                biased;
+                _ = cancel.cancelled() => None,
+                item = stream.next() => Some(item),
+            };
+
+            match item {
+                Some(Some(item)) => {
+                    current.processed += 1;
+                    if item.is_err() {
+                        current.failed += 1;
+                    }
+                    progress(current);
+                    yield item;
+                }
+                Some(None) => break,
+                None => {
+                    yield Err(SalesforceError::Cancelled.into());
+                    break;
+                }
+            }
+        }
+    })
+}
+
 #[cfg(test)]
 mod test;
 
@@ -62,6 +288,72 @@ pub trait SObjectStream<T> {
         all_or_none: bool,
         parallel: Option<usize>,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<()>> + Send>>>;
+
+    /// Delete the records as `delete_all()` does, then, for `DeleteMode::Hard`,
+    /// empty each batch's deleted records from the Recycle Bin.
+    fn delete_all_with_mode(
+        self,
+        conn: &Connection,
+        batch_size: usize,
+        all_or_none: bool,
+        parallel: Option<usize>,
+        mode: DeleteMode,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<()>> + Send>>>;
+}
+
+/// Retrieve the records named by a stream of Ids.
+pub trait SObjectIdStream {
+    /// Retrieve `fields` of the records whose Ids are in this stream, with
+    /// sObject Collections requests of up to 2,000 Ids, and up to `parallel`
+    /// requests at once. Results are yielded in the order of the Ids, with
+    /// `None` for Ids that do not exist or cannot be read. If a request fails,
+    /// its error ends the stream.
+    fn retrieve_all<T>(
+        self,
+        conn: &Connection,
+        sobject_type: &SObjectType,
+        fields: Vec<String>,
+        parallel: Option<usize>,
+    ) -> Pin<Box<dyn Stream<Item = Result<Option<T>>> + Send>>
+    where
+        T: SObjectDeserialization + Send + 'static;
+}
+
+impl<K> SObjectIdStream for K
+where
+    K: Stream<Item = SalesforceId> + Send + 'static,
+{
+    fn retrieve_all<T>(
+        self,
+        conn: &Connection,
+        sobject_type: &SObjectType,
+        fields: Vec<String>,
+        parallel: Option<usize>,
+    ) -> Pin<Box<dyn Stream<Item = Result<Option<T>>> + Send>>
+    where
+        T: SObjectDeserialization + Send + 'static,
+    {
+        let conn = conn.clone();
+        let sobject_type = sobject_type.clone();
+
+        // Requests are spawned, so that each runs while earlier results are consumed.
+        let mut requests = Box::pin(self.chunks(MAX_RETRIEVE_IDS))
+            .map(move |ids| {
+                let request =
+                    SObjectCollectionRetrieveRequest::<T>::new(&sobject_type, ids, fields.clone());
+                let conn = conn.clone();
+                spawn(async move { conn.execute(&request).await })
+            })
+            .buffered(parallel.unwrap_or(1).max(1));
+
+        Box::pin(try_stream! {
+            while let Some(records) = requests.next().await {
+                for record in records?? {
+                    yield record;
+                }
+            }
+        })
+    }
 }
 
 #[async_trait]
@@ -93,12 +385,19 @@ where
         conn: Connection,
         all_or_none: bool,
     ) -> Result<Vec<Result<Self::ResultType>>> {
-        Ok(conn
+        // Keep each type together, so that mixed batches stay within the type chunk limit.
+        let (indices, sobjects): (Vec<usize>, Vec<T>) =
+            order_by_key(sobjects, |s| s.get_api_name().to_lowercase())
+                .into_iter()
+                .unzip();
+        let results = conn
             .execute(&SObjectCollectionCreateRequest::new(
                 &sobjects,
                 all_or_none,
             )?)
-            .await?
+            .await?;
+
+        Ok(restore_order(indices, results)
             .into_iter()
             .map(|r| r.into())
             .collect())
@@ -120,12 +419,19 @@ where
         conn: Connection,
         all_or_none: bool,
     ) -> Result<Vec<Result<Self::ResultType>>> {
-        Ok(conn
+        // Keep each type together, so that mixed batches stay within the type chunk limit.
+        let (indices, sobjects): (Vec<usize>, Vec<T>) =
+            order_by_key(sobjects, |s| s.get_api_name().to_lowercase())
+                .into_iter()
+                .unzip();
+        let results = conn
             .execute(&SObjectCollectionUpdateRequest::new(
                 &sobjects,
                 all_or_none,
             )?)
-            .await?
+            .await?;
+
+        Ok(restore_order(indices, results)
             .into_iter()
             .map(|r| r.into())
             .collect())
@@ -163,7 +469,9 @@ where
 }
 
 #[derive(Clone)]
-struct DeleteOperation {}
+struct DeleteOperation {
+    mode: DeleteMode,
+}
 
 #[async_trait]
 impl<T> BulkDmlOperation<T> for DeleteOperation
@@ -177,7 +485,7 @@ where
         conn: Connection,
         all_or_none: bool,
     ) -> Result<Vec<Result<Self::ResultType>>> {
-        Ok(conn
+        let results: Vec<Result<()>> = conn
             .execute(&SObjectCollectionDeleteRequest::new(
                 &sobjects,
                 all_or_none,
@@ -185,7 +493,53 @@ where
             .await?
             .into_iter()
             .map(|r| r.into())
-            .collect())
+            .collect();
+
+        if self.mode == DeleteMode::Hard {
+            let deleted: Vec<SalesforceId> = sobjects
+                .iter()
+                .zip(results.iter())
+                .filter(|(_, r)| r.is_ok())
+                .filter_map(|(s, _)| match s.get_id() {
+                    FieldValue::Id(id) => Some(id),
+                    _ => None,
+                })
+                .collect();
+
+            if !deleted.is_empty() {
+                if let Err(e) = conn.empty_recycle_bin(&deleted).await {
+                    // The records are deleted, but remain in the Recycle Bin.
+                    let error = e.to_string();
+                    return Ok(results
+                        .into_iter()
+                        .map(|r| {
+                            r.and_then(|_| Err(SalesforceError::GeneralError(error.clone()).into()))
+                        })
+                        .collect());
+                }
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+/// Return `results`, given in the order of records reordered from `indices`,
+/// in the original order of those records.
+fn restore_order<R>(indices: Vec<usize>, results: Vec<R>) -> Vec<R> {
+    let mut results: Vec<(usize, R)> = indices.into_iter().zip(results).collect();
+    results.sort_by_key(|(i, _)| *i);
+
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+/// Whether a collection request's outcome shows that Salesforce is shedding load.
+fn is_throttled<R>(result: &Result<Vec<Result<R>>>) -> bool {
+    match result {
+        Ok(results) => results
+            .iter()
+            .any(|r| r.as_ref().err().is_some_and(is_throttling_error)),
+        Err(e) => is_throttling_error(e),
     }
 }
 
@@ -195,6 +549,7 @@ fn parallelize_dml<T, K, O: BulkDmlOperation<K>, R>(
     batch_size: usize,
     all_or_none: bool,
     parallel: usize,
+    controller: Option<Arc<ConcurrencyController>>,
     operation: O,
 ) -> mpsc::Receiver<JoinHandle<Result<Vec<Result<R>>>>>
 where
@@ -210,12 +565,32 @@ where
 
     spawn(async move {
         while let Some(chunk) = chunks.next().await {
+            // Stop dispatching requests once the results stream is dropped,
+            // such as when it is cancelled.
+            if tx.is_closed() {
+                break;
+            }
             let c = conn.clone();
             let o = operation.clone();
-            tx.send(spawn(async move {
-                return o.perform_dml(chunk, c, all_or_none).await;
-            }))
-            .await;
+            // Under adaptive concurrency, wait for the controller to admit this request.
+            let permit = match &controller {
+                Some(controller) => Some(controller.acquire().await),
+                None => None,
+            };
+            let controller = controller.clone();
+            let sent = tx.send(spawn(async move {
+                let start = Instant::now();
+                let result = o.perform_dml(chunk, c, all_or_none).await;
+                if let Some(controller) = controller {
+                    controller.record(start.elapsed(), is_throttled(&result));
+                }
+                drop(permit);
+
+                result
+            }));
+            if sent.await.is_err() {
+                break;
+            }
         }
     });
 
@@ -236,7 +611,16 @@ where
     R: Send + 'static,
     T: SObjectRepresentation,
 {
-    let parallelism_degree = if let Some(count) = parallel { count } else { 1 };
+    // A fixed `parallel` count takes precedence over the Connection's adaptive concurrency.
+    let controller = match parallel {
+        Some(_) => None,
+        None => conn.get_concurrency_controller(),
+    };
+    let parallelism_degree = match (parallel, &controller) {
+        (Some(count), _) => count,
+        (None, Some(controller)) => controller.get_policy().get_max(),
+        (None, None) => 1,
+    };
 
     let mut rx = parallelize_dml(
         stream,
@@ -244,6 +628,7 @@ where
         batch_size,
         all_or_none,
         parallelism_degree,
+        controller,
         operation,
     );
     let s = stream! {
@@ -329,7 +714,27 @@ where
             batch_size,
             all_or_none,
             parallel,
-            DeleteOperation {},
+            DeleteOperation {
+                mode: DeleteMode::RecycleBin,
+            },
+        )
+    }
+
+    fn delete_all_with_mode(
+        self,
+        conn: &Connection,
+        batch_size: usize,
+        all_or_none: bool,
+        parallel: Option<usize>,
+        mode: DeleteMode,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<()>> + Send>>> {
+        run_dml(
+            self,
+            conn,
+            batch_size,
+            all_or_none,
+            parallel,
+            DeleteOperation { mode },
         )
     }
 }
@@ -337,6 +742,7 @@ where
 pub struct SObjectCollectionCreateRequest {
     records: Vec<Value>,
     all_or_none: bool,
+    assignment_rule: Option<AssignmentRule>,
 }
 
 impl SObjectCollectionCreateRequest {
@@ -344,8 +750,16 @@ impl SObjectCollectionCreateRequest {
         Self {
             records,
             all_or_none,
+            assignment_rule: None,
         }
     }
+
+    #[must_use]
+    pub fn with_assignment_rule(mut self, assignment_rule: AssignmentRule) -> Self {
+        self.assignment_rule = Some(assignment_rule);
+        self
+    }
+
     pub fn new<T>(objects: &[T], all_or_none: bool) -> Result<Self>
     where
         T: SObjectSerialization + SObjectWithId,
@@ -356,15 +770,28 @@ impl SObjectCollectionCreateRequest {
         if objects.len() > 200 {
             return Err(SalesforceError::SObjectCollectionError.into());
         }
-        // NTH: validate that there are up to 10 chunks.
 
-        Ok(Self::new_raw(
-            objects
-                .iter()
-                .map(|s| s.to_value_with_options(true, false))
-                .collect::<Result<Vec<Value>>>()?,
-            all_or_none,
-        ))
+        let records = objects
+            .iter()
+            .map(|s| s.to_value_with_options(true, false))
+            .collect::<Result<Vec<Value>>>()?;
+        validate_type_chunks(&records)?;
+
+        Ok(Self::new_raw(records, all_or_none))
+    }
+}
+
+impl SplittableCollectionRequest for SObjectCollectionCreateRequest {
+    fn take_records(&mut self) -> Vec<Value> {
+        std::mem::take(&mut self.records)
+    }
+
+    fn with_records(&self, records: Vec<Value>) -> Self {
+        Self {
+            records,
+            all_or_none: self.all_or_none,
+            assignment_rule: self.assignment_rule,
+        }
     }
 }
 
@@ -393,10 +820,28 @@ impl SalesforceRequest for SObjectCollectionCreateRequest {
             Err(SalesforceError::ResponseBodyExpected.into())
         }
     }
+
+    fn get_dry_run_result(&self, conn: &Connection) -> Result<Self::ReturnValue> {
+        Ok(self
+            .records
+            .iter()
+            .map(|_| DmlResult::dry_run(Some(conn.get_dry_run_id())))
+            .collect())
+    }
+
+    fn get_headers(&self) -> Option<HashMap<String, String>> {
+        self.assignment_rule.map(|a| a.get_header())
+    }
 }
 
 impl CompositeFriendlyRequest for SObjectCollectionCreateRequest {}
 
+impl CollectionDmlRequest for SObjectCollectionCreateRequest {
+    fn get_record_count(&self) -> usize {
+        self.records.len()
+    }
+}
+
 pub struct SObjectCollectionRetrieveRequest<T>
 where
     T: SObjectDeserialization,
@@ -444,6 +889,10 @@ where
         Method::POST
     }
 
+    fn is_mutating(&self) -> bool {
+        false
+    }
+
     fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
         if let Some(body) = body {
             if let Value::Array(list) = body {
@@ -474,6 +923,7 @@ impl<T> CompositeFriendlyRequest for SObjectCollectionRetrieveRequest<T> where
 pub struct SObjectCollectionUpdateRequest {
     records: Vec<Value>,
     all_or_none: bool,
+    assignment_rule: Option<AssignmentRule>,
 }
 
 impl SObjectCollectionUpdateRequest {
@@ -481,9 +931,16 @@ impl SObjectCollectionUpdateRequest {
         Self {
             records,
             all_or_none,
+            assignment_rule: None,
         }
     }
 
+    #[must_use]
+    pub fn with_assignment_rule(mut self, assignment_rule: AssignmentRule) -> Self {
+        self.assignment_rule = Some(assignment_rule);
+        self
+    }
+
     pub fn new<T>(objects: &[T], all_or_none: bool) -> Result<Self>
     where
         T: SObjectSerialization + SObjectWithId,
@@ -494,15 +951,28 @@ impl SObjectCollectionUpdateRequest {
         if objects.len() > 200 {
             return Err(SalesforceError::SObjectCollectionError.into());
         }
-        // NTH: validate that there are up to 10 chunks.
 
-        Ok(Self::new_raw(
-            objects
-                .iter()
-                .map(|s| s.to_value_with_options(true, true))
-                .collect::<Result<Vec<Value>>>()?,
-            all_or_none,
-        ))
+        let records = objects
+            .iter()
+            .map(|s| s.to_value_with_options(true, true))
+            .collect::<Result<Vec<Value>>>()?;
+        validate_type_chunks(&records)?;
+
+        Ok(Self::new_raw(records, all_or_none))
+    }
+}
+
+impl SplittableCollectionRequest for SObjectCollectionUpdateRequest {
+    fn take_records(&mut self) -> Vec<Value> {
+        std::mem::take(&mut self.records)
+    }
+
+    fn with_records(&self, records: Vec<Value>) -> Self {
+        Self {
+            records,
+            all_or_none: self.all_or_none,
+            assignment_rule: self.assignment_rule,
+        }
     }
 }
 
@@ -531,15 +1001,34 @@ impl SalesforceRequest for SObjectCollectionUpdateRequest {
             Err(SalesforceError::ResponseBodyExpected.into())
         }
     }
+
+    fn get_dry_run_result(&self, _conn: &Connection) -> Result<Self::ReturnValue> {
+        Ok(self
+            .records
+            .iter()
+            .map(|_| DmlResult::dry_run(None))
+            .collect())
+    }
+
+    fn get_headers(&self) -> Option<HashMap<String, String>> {
+        self.assignment_rule.map(|a| a.get_header())
+    }
 }
 
 impl CompositeFriendlyRequest for SObjectCollectionUpdateRequest {}
 
+impl CollectionDmlRequest for SObjectCollectionUpdateRequest {
+    fn get_record_count(&self) -> usize {
+        self.records.len()
+    }
+}
+
 pub struct SObjectCollectionUpsertRequest {
     objects: Vec<Value>,
     external_id: String,
     sobject_type: String,
     all_or_none: bool,
+    assignment_rule: Option<AssignmentRule>,
 }
 
 impl SObjectCollectionUpsertRequest {
@@ -554,8 +1043,16 @@ impl SObjectCollectionUpsertRequest {
             external_id,
             sobject_type,
             all_or_none,
+            assignment_rule: None,
         }
     }
+
+    #[must_use]
+    pub fn with_assignment_rule(mut self, assignment_rule: AssignmentRule) -> Self {
+        self.assignment_rule = Some(assignment_rule);
+        self
+    }
+
     pub fn new<T>(objects: &[T], external_id: &str, all_or_none: bool) -> Result<Self>
     where
         T: SObjectSerialization + TypedSObject,
@@ -582,6 +1079,22 @@ impl SObjectCollectionUpsertRequest {
     }
 }
 
+impl SplittableCollectionRequest for SObjectCollectionUpsertRequest {
+    fn take_records(&mut self) -> Vec<Value> {
+        std::mem::take(&mut self.objects)
+    }
+
+    fn with_records(&self, objects: Vec<Value>) -> Self {
+        Self {
+            objects,
+            external_id: self.external_id.clone(),
+            sobject_type: self.sobject_type.clone(),
+            all_or_none: self.all_or_none,
+            assignment_rule: self.assignment_rule,
+        }
+    }
+}
+
 impl SalesforceRequest for SObjectCollectionUpsertRequest {
     type ReturnValue = Vec<DmlResult>;
 
@@ -610,10 +1123,28 @@ impl SalesforceRequest for SObjectCollectionUpsertRequest {
             Err(SalesforceError::ResponseBodyExpected.into())
         }
     }
+
+    fn get_dry_run_result(&self, conn: &Connection) -> Result<Self::ReturnValue> {
+        Ok(self
+            .objects
+            .iter()
+            .map(|_| DmlResult::dry_run(Some(conn.get_dry_run_id())))
+            .collect())
+    }
+
+    fn get_headers(&self) -> Option<HashMap<String, String>> {
+        self.assignment_rule.map(|a| a.get_header())
+    }
 }
 
 impl CompositeFriendlyRequest for SObjectCollectionUpsertRequest {}
 
+impl CollectionDmlRequest for SObjectCollectionUpsertRequest {
+    fn get_record_count(&self) -> usize {
+        self.objects.len()
+    }
+}
+
 pub struct SObjectCollectionDeleteRequest {
     ids: Vec<String>,
     all_or_none: bool,
@@ -668,6 +1199,16 @@ impl SalesforceRequest for SObjectCollectionDeleteRequest {
             Err(SalesforceError::ResponseBodyExpected.into())
         }
     }
+
+    fn get_dry_run_result(&self, _conn: &Connection) -> Result<Self::ReturnValue> {
+        Ok(self.ids.iter().map(|_| DmlResult::dry_run(None)).collect())
+    }
 }
 
 impl CompositeFriendlyRequest for SObjectCollectionDeleteRequest {}
+
+impl CollectionDmlRequest for SObjectCollectionDeleteRequest {
+    fn get_record_count(&self) -> usize {
+        self.ids.len()
+    }
+}
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use itertools::Itertools;
+use log::info;
+
+use crate::{api::Connection, data::SalesforceId, tooling::ExecuteAnonymousApexRequest};
+
+/// The number of Ids emptied from the Recycle Bin per request,
+/// which keeps each request's URL to a few kilobytes.
+const EMPTY_RECYCLE_BIN_CHUNK_SIZE: usize = 200;
+
+/// What becomes of deleted records.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum DeleteMode {
+    /// Move records to the Recycle Bin, from which they can be undeleted
+    /// and where they continue to use storage until purged.
+    RecycleBin,
+    /// Delete records, then empty them from the Recycle Bin. For large
+    /// volumes, the Bulk API's hard delete avoids the Recycle Bin entirely,
+    /// but requires the Bulk API Hard Delete permission.
+    Hard,
+}
+
+/// The Apex to permanently delete `ids` from the Recycle Bin, failing if any cannot be.
+pub(crate) fn get_empty_recycle_bin_apex(ids: &[SalesforceId]) -> String {
+    format!(
+        "for (Database.EmptyRecycleBinResult r : Database.emptyRecycleBin(new List<Id>{{{}}})) {{ \
+         System.assert(r.isSuccess(), r.getId() + ': ' + r.getErrors()); }}",
+        ids.iter().map(|id| format!("'{}'", id)).join(", ")
+    )
+}
+
+impl Connection {
+    /// Permanently delete the records `ids` from the Recycle Bin, freeing their storage.
+    /// The sObject Collections API cannot do so, so this runs Anonymous Apex
+    /// in requests of 200 Ids. Records not in the Recycle Bin cause an error.
+    pub async fn empty_recycle_bin(&self, ids: &[SalesforceId]) -> Result<()> {
+        if self.is_dry_run() {
+            info!(
+                "Dry run: skipping emptying {} records from the Recycle Bin",
+                ids.len()
+            );
+            return Ok(());
+        }
+
+        for chunk in ids.chunks(EMPTY_RECYCLE_BIN_CHUNK_SIZE) {
+            let result: Result<()> = self
+                .execute(&ExecuteAnonymousApexRequest::new(
+                    get_empty_recycle_bin_apex(chunk),
+                ))
+                .await?
+                .into();
+            result?;
+        }
+
+        Ok(())
+    }
+}
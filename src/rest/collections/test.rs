@@ -1,9 +1,31 @@
 use anyhow::Result;
+use reqwest::Method;
+use serde_json::{json, Value};
 use tokio_stream::{iter, StreamExt};
+use tokio_util::sync::CancellationToken;
 
-use crate::test_integration_base::{get_test_connection, Account};
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 
-use super::SObjectStream;
+use crate::api::concurrency::AdaptiveConcurrency;
+use crate::api::SalesforceRequest;
+use crate::data::traits::TypedSObject;
+use crate::prelude::*;
+use crate::schema::ObjectGraph;
+use crate::test_integration_base::{
+    get_test_connection, get_test_field_describe, get_test_sobject_type, json_response,
+    serve_responses, serve_routes, Account, RouteTransport,
+};
+
+use super::grouped::group_records;
+use super::recycle_bin::get_empty_recycle_bin_apex;
+use super::{
+    count_type_chunks, group_records_by_type, split_records_by_size, with_progress,
+    SObjectCollectionCreateRequest, SObjectStream, StreamProgress, MAX_COLLECTION_TYPE_CHUNKS,
+    MAX_REQUEST_BODY_SIZE,
+};
+use super::{DeleteMode, SObjectIdStream, MAX_RETRIEVE_IDS};
 
 #[tokio::test]
 #[ignore]
@@ -76,3 +98,387 @@ async fn test_collection_stream_create_delete() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_estimate_payload_size() -> Result<()> {
+    let request = SObjectCollectionCreateRequest::new_raw(vec![json!({"Name": "Test"})], false);
+
+    assert_eq!(
+        request.estimate_payload_size(),
+        request.get_body().unwrap().to_string().len()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_split_records_by_size() -> Result<()> {
+    // Each record serializes to 12 bytes, plus a separator.
+    let records: Vec<_> = (0..5).map(|i| json!({"Name": i.to_string()})).collect();
+    assert_eq!(records[0].to_string().len(), 12);
+
+    let batches = split_records_by_size(records.clone(), 10, 200, 10 + 13 * 2)?;
+    assert_eq!(
+        batches.iter().map(|b| b.len()).collect::<Vec<_>>(),
+        vec![2, 2, 1]
+    );
+
+    let batches = split_records_by_size(records.clone(), 10, 3, 1000)?;
+    assert_eq!(
+        batches.iter().map(|b| b.len()).collect::<Vec<_>>(),
+        vec![3, 2]
+    );
+
+    assert!(split_records_by_size(records, 10, 200, 20).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_split_collection_request() -> Result<()> {
+    let records: Vec<_> = (0..450).map(|i| json!({"Name": i.to_string()})).collect();
+    let requests = SObjectCollectionCreateRequest::new_raw(records, true).split(1000)?;
+
+    assert!(requests.iter().all(|r| r.estimate_payload_size() <= 1000));
+    assert_eq!(
+        requests
+            .iter()
+            .map(|r| r.get_body().unwrap()["records"].as_array().unwrap().len())
+            .sum::<usize>(),
+        450
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_group_records_parents_first() -> Result<()> {
+    let account_type = get_test_sobject_type(
+        "Account",
+        vec![get_test_field_describe("Id", "tns:ID", "id")],
+        vec![],
+    )?;
+    let mut lookup = get_test_field_describe("AccountId", "tns:ID", "reference");
+    lookup["referenceTo"] = json!(["Account"]);
+    let contact_type = get_test_sobject_type(
+        "Contact",
+        vec![get_test_field_describe("Id", "tns:ID", "id"), lookup],
+        vec![],
+    )?;
+    let graph = ObjectGraph::new(&[account_type.clone(), contact_type.clone()]);
+
+    let records = vec![
+        SObject::new(&contact_type).with_str("LastName", "A"),
+        SObject::new(&account_type).with_str("Name", "B"),
+        SObject::new(&contact_type).with_str("LastName", "C"),
+    ];
+
+    let groups = group_records(records, &graph);
+
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].0, "Account");
+    assert_eq!(
+        groups[0].1.iter().map(|(i, _)| *i).collect::<Vec<usize>>(),
+        vec![1]
+    );
+    assert_eq!(groups[1].0, "Contact");
+    assert_eq!(
+        groups[1].1.iter().map(|(i, _)| *i).collect::<Vec<usize>>(),
+        vec![0, 2]
+    );
+    assert!(groups[1]
+        .1
+        .iter()
+        .all(|(_, r)| r.get_api_name() == "Contact"));
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_upsert_by_type() -> Result<()> {
+    let conn = get_test_connection()?;
+    let account_type = conn.get_type("Account").await?;
+    let contact_type = conn.get_type("Contact").await?;
+
+    let records = vec![
+        SObject::new(&contact_type).with_str("LastName", "Grouped Upsert"),
+        SObject::new(&account_type).with_str("Name", "Grouped Upsert"),
+    ];
+    let mut external_ids = HashMap::new();
+    external_ids.insert("Account".to_owned(), "Id".to_owned());
+    external_ids.insert("Contact".to_owned(), "Id".to_owned());
+
+    let results: Vec<_> = conn
+        .upsert_by_type(iter(records), &external_ids, false)
+        .await?
+        .collect::<Result<Vec<_>>>()
+        .await?;
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].sobject, "Account");
+    assert_eq!(results[0].index, 1);
+    assert!(results.iter().all(|r| r.result.success));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_collection_stream_adaptive_concurrency() -> Result<()> {
+    let (conn, count) = serve_responses(vec![
+        (
+            "200 OK",
+            r#"[{"id": "001000000000001AAA", "success": true, "errors": []}]"#,
+        ),
+        (
+            "200 OK",
+            r#"[{"id": null, "success": false, "errors": [{"statusCode": "UNABLE_TO_LOCK_ROW", "message": "unable to obtain exclusive access to this record", "fields": []}]}]"#,
+        ),
+        (
+            "200 OK",
+            r#"[{"id": "001000000000002AAA", "success": true, "errors": []}]"#,
+        ),
+    ])
+    .await?;
+    conn.set_adaptive_concurrency(Some(AdaptiveConcurrency::new(1, 4)));
+
+    let results: Vec<Result<SalesforceId>> = iter(0..3)
+        .map(|i| Account {
+            id: None,
+            name: format!("Account {}", i),
+        })
+        .create_all(&conn, 1, true, None)?
+        .collect()
+        .await;
+
+    assert_eq!(3, count.load(std::sync::atomic::Ordering::SeqCst));
+    assert_eq!(2, results.iter().filter(|r| r.is_ok()).count());
+    let controller = conn.get_concurrency_controller().unwrap();
+    assert_eq!(0, controller.get_in_flight());
+    assert!(controller.get_limit() <= 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_collection_type_chunks() -> Result<()> {
+    let record = |sobject: &str| json!({"attributes": {"type": sobject}, "Name": "Test"});
+    let types: Vec<String> = (0..11).map(|i| format!("Type{}__c", i)).collect();
+
+    // Alternating types form a chunk per record until grouped.
+    let records: Vec<_> = (0..12).map(|i| record(&types[i % 6])).collect();
+    assert_eq!(count_type_chunks(&records), 12);
+    assert!(
+        SObjectCollectionCreateRequest::new_raw(records.clone(), false)
+            .split(MAX_REQUEST_BODY_SIZE)?
+            .iter()
+            .all(
+                |r| count_type_chunks(r.get_body().unwrap()["records"].as_array().unwrap())
+                    <= MAX_COLLECTION_TYPE_CHUNKS
+            )
+    );
+
+    let grouped = group_records_by_type(records);
+    assert_eq!(
+        grouped.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+        vec![0, 6, 1, 7, 2, 8, 3, 9, 4, 10, 5, 11]
+    );
+    let grouped: Vec<Value> = grouped.into_iter().map(|(_, r)| r).collect();
+    assert_eq!(count_type_chunks(&grouped), 6);
+
+    // Eleven distinct types cannot be sent in one request, however ordered.
+    let records: Vec<_> = types.iter().map(|t| record(t)).collect();
+    let batches = split_records_by_size(records, 10, 200, 1000)?;
+    assert_eq!(
+        batches.iter().map(|b| b.len()).collect::<Vec<_>>(),
+        vec![10, 1]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_collection_request_type_chunk_error() -> Result<()> {
+    let account_type = get_test_sobject_type(
+        "Account",
+        vec![get_test_field_describe("Name", "xsd:string", "string")],
+        vec![],
+    )?;
+    let contact_type = get_test_sobject_type(
+        "Contact",
+        vec![get_test_field_describe("Name", "xsd:string", "string")],
+        vec![],
+    )?;
+    let records: Vec<SObject> = (0..11)
+        .map(|i| {
+            SObject::new(if i % 2 == 0 {
+                &account_type
+            } else {
+                &contact_type
+            })
+            .with_str("Name", "Test")
+        })
+        .collect();
+
+    let error = SObjectCollectionCreateRequest::new(&records, false)
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(error.contains("group_records_by_type"));
+    assert!(SObjectCollectionCreateRequest::new(&records[..10], false).is_ok());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stream_with_progress() -> Result<()> {
+    let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let cancel = CancellationToken::new();
+    let results: Vec<Result<usize>> = vec![
+        Ok(1),
+        Err(SalesforceError::UnknownError.into()),
+        Ok(3),
+        Ok(4),
+    ];
+
+    let progress_reports = Arc::clone(&reports);
+    let mut stream = with_progress(
+        Box::pin(tokio_stream::iter(results)),
+        move |p| progress_reports.lock().unwrap().push(p),
+        cancel.clone(),
+    );
+
+    assert_eq!(stream.next().await.unwrap()?, 1);
+    assert!(stream.next().await.unwrap().is_err());
+    cancel.cancel();
+    assert!(matches!(
+        stream
+            .next()
+            .await
+            .unwrap()
+            .unwrap_err()
+            .downcast_ref::<SalesforceError>(),
+        Some(SalesforceError::Cancelled)
+    ));
+    assert!(stream.next().await.is_none());
+
+    assert_eq!(
+        *reports.lock().unwrap(),
+        vec![
+            StreamProgress {
+                processed: 1,
+                failed: 0
+            },
+            StreamProgress {
+                processed: 2,
+                failed: 1
+            },
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_empty_recycle_bin_apex() -> Result<()> {
+    assert_eq!(
+        get_empty_recycle_bin_apex(&[
+            SalesforceId::new("001000000000001AAA")?,
+            SalesforceId::new("001000000000002AAA")?
+        ]),
+        "for (Database.EmptyRecycleBinResult r : Database.emptyRecycleBin(new List<Id>{'001000000000001AAA', '001000000000002AAA'})) { \
+         System.assert(r.isSuccess(), r.getId() + ': ' + r.getErrors()); }"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_delete_all_hard() -> Result<()> {
+    let (conn, count) = serve_responses(vec![
+        (
+            "200 OK",
+            r#"[{"id": "001000000000001AAA", "success": true, "errors": []},
+                {"id": null, "success": false, "errors": [{"statusCode": "ENTITY_IS_DELETED", "message": "entity is deleted", "fields": []}]}]"#,
+        ),
+        (
+            "200 OK",
+            r#"{"line": -1, "column": -1, "compiled": true, "success": true, "compileProblem": null, "exceptionStackTrace": null, "exceptionMessage": null}"#,
+        ),
+    ])
+    .await?;
+
+    let results: Vec<Result<()>> = iter(vec![
+        Account {
+            id: Some(SalesforceId::new("001000000000001AAA")?),
+            name: "".to_owned(),
+        },
+        Account {
+            id: Some(SalesforceId::new("001000000000002AAA")?),
+            name: "".to_owned(),
+        },
+    ])
+    .delete_all_with_mode(&conn, 200, false, None, DeleteMode::Hard)?
+    .collect()
+    .await;
+
+    assert!(results[0].is_ok());
+    assert!(results[1].is_err());
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
+
+/// Answers sObject Collections retrieve requests, omitting the second Account.
+fn get_retrieve_transport() -> RouteTransport {
+    RouteTransport::new().with_route(Method::POST, "/composite/sobjects/Account", |r| {
+        let body = r.json()?;
+        let ids = body["ids"].as_array().unwrap();
+        assert!(ids.len() <= MAX_RETRIEVE_IDS);
+
+        let records: Vec<Value> = ids
+            .iter()
+            .map(|id| match id.as_str().unwrap() {
+                "001000000000001AAA" => Value::Null,
+                id => json!({"attributes": {"type": "Account"}, "Id": id, "Name": id}),
+            })
+            .collect();
+
+        json_response(200, &Value::Array(records))
+    })
+}
+
+#[tokio::test]
+async fn test_retrieve_all() -> Result<()> {
+    let (conn, transport) = serve_routes(get_retrieve_transport())?;
+    let account_type = get_test_sobject_type(
+        "Account",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            get_test_field_describe("Name", "xsd:string", "string"),
+        ],
+        vec![],
+    )?;
+    let ids: Vec<SalesforceId> = (0..=MAX_RETRIEVE_IDS)
+        .map(|i| SalesforceId::new(&format!("001{:012}", i)))
+        .collect::<Result<_, _>>()?;
+
+    let results: Vec<Option<SObject>> = iter(ids.clone())
+        .retrieve_all(
+            &conn,
+            &account_type,
+            vec!["Id".to_owned(), "Name".to_owned()],
+            Some(2),
+        )
+        .collect::<Result<_>>()
+        .await?;
+
+    assert_eq!(transport.get_requests().len(), 2);
+    assert_eq!(results.len(), ids.len());
+    assert!(results[1].is_none());
+    for (id, record) in ids.iter().zip(results.iter()).filter(|(_, r)| r.is_some()) {
+        assert_eq!(record.as_ref().unwrap().get_opt_id(), Some(*id));
+    }
+
+    Ok(())
+}
@@ -1,13 +1,15 @@
 use std::collections::HashMap;
+use std::marker::PhantomData;
 
 use anyhow::Result;
 use reqwest::Method;
 use serde_derive::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 
 use crate::{
     api::Connection,
-    api::{CompositeFriendlyRequest, SalesforceRequest},
+    api::{AssignmentRule, CompositeFriendlyRequest, SalesforceRequest, AUTO_ASSIGN_HEADER},
+    data::{SObjectDeserialization, SObjectType},
     errors::SalesforceError,
 };
 
@@ -16,6 +18,16 @@ use super::ApiError;
 #[cfg(test)]
 mod test;
 
+pub mod traits;
+pub mod transaction;
+pub mod tree;
+pub mod writer;
+
+pub use transaction::Transaction;
+
+/// The maximum number of subrequests in a single Composite request.
+pub const MAX_COMPOSITE_SUBREQUESTS: usize = 25;
+
 pub struct CompositeRequest {
     keys: Vec<String>,
     requests: HashMap<String, CompositeSubrequest>,
@@ -59,12 +71,101 @@ impl CompositeRequest {
                 body: req.get_body(),
                 method: req.get_method().to_string(),
                 reference_id: Some(key.to_string()),
-                http_headers: None,
+                http_headers: req.get_headers(),
+                mutating: req.is_mutating(),
             },
         );
 
         Ok(())
     }
+
+    /// Split this request into Composite requests of at most
+    /// `MAX_COMPOSITE_SUBREQUESTS` subrequests, whose estimated body size
+    /// does not exceed `max_size`, keeping the subrequests in order.
+    ///
+    /// `all_or_none` applies to each resulting request separately. A subrequest
+    /// cannot refer to the result of one sent in an earlier request, so an error
+    /// is returned if the split would separate them.
+    pub fn split(mut self, max_size: usize) -> Result<Vec<CompositeRequest>> {
+        let keys = std::mem::take(&mut self.keys);
+        let base_size = self.estimate_payload_size();
+        let mut batches: Vec<CompositeRequest> = Vec::new();
+        let mut batch_size = base_size;
+
+        for key in keys {
+            let request = self.requests.remove(&key).unwrap();
+            // Each subrequest after the first also adds a separating comma.
+            let request_size = serde_json::to_string(&request)?.len() + 1;
+            if base_size + request_size > max_size {
+                return Err(SalesforceError::GeneralError(format!(
+                    "The subrequest {} of {} bytes exceeds the maximum request size of {} bytes",
+                    key,
+                    request_size - 1,
+                    max_size
+                ))
+                .into());
+            }
+
+            let full = batches.last().is_none_or(|b| {
+                b.keys.len() == MAX_COMPOSITE_SUBREQUESTS || batch_size + request_size > max_size
+            });
+            if full {
+                batches.push(CompositeRequest::new(
+                    self.base_url.clone(),
+                    self.all_or_none,
+                    self.collate_subrequests,
+                ));
+                batch_size = base_size;
+            }
+
+            // References are written `@{referenceId.field}`.
+            let serialized = serde_json::to_string(&(&request.url, &request.body))?;
+            if let Some(earlier) = batches[..batches.len() - 1]
+                .iter()
+                .flat_map(|b| b.keys.iter())
+                .find(|k| serialized.contains(&format!("@{{{}.", k)))
+            {
+                return Err(SalesforceError::GeneralError(format!(
+                    "The subrequest {} refers to {}, which would be sent in an earlier Composite request",
+                    key, earlier
+                ))
+                .into());
+            }
+
+            let batch = batches.last_mut().unwrap();
+            batch.keys.push(key.clone());
+            batch.requests.insert(key, request);
+            batch_size += request_size;
+        }
+
+        Ok(batches)
+    }
+
+    // The request body, with `assignment_rule` applied to each DML
+    // subrequest that does not specify its own.
+    fn get_body_with_assignment_rule(
+        &self,
+        assignment_rule: Option<AssignmentRule>,
+    ) -> Option<Value> {
+        let mut body = CompositeRequestBody {
+            all_or_none: self.all_or_none,
+            collate_subrequests: self.collate_subrequests,
+            composite_request: Vec::with_capacity(self.keys.len()),
+        };
+
+        for k in self.keys.iter() {
+            let mut req = self.requests.get(k).unwrap().clone(); // TODO: don't clone.
+            if let (true, Some(assignment_rule)) = (req.mutating, assignment_rule) {
+                req.http_headers
+                    .get_or_insert_with(HashMap::new)
+                    .entry(AUTO_ASSIGN_HEADER.to_owned())
+                    .or_insert_with(|| assignment_rule.get_header_value());
+            }
+            body.composite_request.push(req);
+        }
+
+        serde_json::to_value(body).ok()
+    }
 }
 
 impl SalesforceRequest for CompositeRequest {
@@ -79,29 +180,94 @@ impl SalesforceRequest for CompositeRequest {
     }
 
     fn get_body(&self) -> Option<Value> {
-        let mut body = CompositeRequestBody {
-            all_or_none: self.all_or_none,
-            collate_subrequests: self.collate_subrequests,
-            composite_request: Vec::with_capacity(self.keys.len()),
-        };
+        self.get_body_with_assignment_rule(None)
+    }
 
-        for k in self.keys.iter() {
-            let req = self.requests.get(k).unwrap();
-            body.composite_request.push(req.clone()); // TODO: don't clone.
+    fn get_body_for_connection(&self, conn: &Connection) -> Option<Value> {
+        self.get_body_with_assignment_rule(conn.get_assignment_rule())
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(serde_json::from_value(body.clone())?) // TODO: don't clone
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
         }
+    }
 
-        serde_json::to_value(body).ok()
+    fn is_mutating(&self) -> bool {
+        // Composite requests that only read data may be executed in dry-run mode.
+        // Subrequest results cannot be synthesized, so mutating Composite requests
+        // return an error in dry-run mode.
+        self.requests.values().any(|r| r.mutating)
+    }
+}
+
+/// Retrieve an sObject within a Composite request, by Id or by a reference
+/// to the result of an earlier subrequest, such as `@{create.id}`.
+pub struct CompositeRetrieveRequest<T>
+where
+    T: SObjectDeserialization,
+{
+    id: String,
+    sobject_type: SObjectType,
+    fields: Option<Vec<String>>,
+    phantom: PhantomData<T>,
+}
+
+impl<T> CompositeRetrieveRequest<T>
+where
+    T: SObjectDeserialization,
+{
+    pub fn new(
+        id: &str,
+        sobject_type: &SObjectType,
+        fields: Option<Vec<String>>,
+    ) -> CompositeRetrieveRequest<T> {
+        CompositeRetrieveRequest {
+            id: id.to_owned(),
+            sobject_type: sobject_type.clone(),
+            fields,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> SalesforceRequest for CompositeRetrieveRequest<T>
+where
+    T: SObjectDeserialization,
+{
+    type ReturnValue = T;
+
+    fn get_url(&self) -> String {
+        format!("sobjects/{}/{}", self.sobject_type.get_api_name(), self.id)
+    }
+
+    fn get_query_parameters(&self) -> Option<Value> {
+        self.fields.as_ref().map(|fields| {
+            let mut hm = Map::new();
+
+            hm.insert("fields".to_string(), Value::String(fields.join(",")));
+
+            Value::Object(hm)
+        })
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
     }
 
     fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
         if let Some(body) = body {
-            Ok(serde_json::from_value(body.clone())?) // TODO: don't clone
+            T::from_value(body, &self.sobject_type)
         } else {
             Err(SalesforceError::ResponseBodyExpected.into())
         }
     }
 }
 
+impl<T> CompositeFriendlyRequest for CompositeRetrieveRequest<T> where T: SObjectDeserialization {}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct CompositeRequestBody {
@@ -118,22 +284,24 @@ struct CompositeSubrequest {
     body: Option<Value>,
     reference_id: Option<String>,
     http_headers: Option<HashMap<String, String>>,
+    #[serde(skip)]
+    mutating: bool,
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct CompositeResponse {
     pub composite_response: Vec<CompositeSubrequestResponse>,
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(untagged)]
 pub enum CompositeSubrequestResponseBody {
     Error(Vec<ApiError>),
     Success(Option<Value>),
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct CompositeSubrequestResponse {
     body: CompositeSubrequestResponseBody,
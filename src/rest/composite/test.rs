@@ -1,10 +1,23 @@
+use std::sync::atomic::Ordering;
+
 use anyhow::Result;
+use serde_derive::{Deserialize, Serialize};
+
+use serde_json::json;
 
-use super::CompositeRequest;
+use super::tree::SObjectTreeRequest;
+use super::writer::CompositeCollectionWriter;
+use super::{CompositeRequest, CompositeRetrieveRequest, MAX_COMPOSITE_SUBREQUESTS};
+use crate::api::{AssignmentRule, Mode, SalesforceRequest};
+use crate::errors::SalesforceError;
 use crate::prelude::*;
-use crate::rest::collections::SObjectCollectionCreateRequest;
+use crate::rest::collections::{SObjectCollectionCreateRequest, MAX_REQUEST_BODY_SIZE};
 use crate::rest::rows::{SObjectCreateRequest, SObjectDeleteRequest, SObjectUpdateRequest};
-use crate::test_integration_base::get_test_connection;
+use crate::test_integration_base::{
+    get_offline_connection, get_test_connection, get_test_field_describe,
+    get_test_sobject_describe, get_test_sobject_type, get_test_sobject_type_with_children,
+    serve_responses, Account,
+};
 
 #[tokio::test]
 #[ignore]
@@ -106,3 +119,686 @@ async fn test_composite_request_collections() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_composite_request_assignment_rules() -> Result<()> {
+    let conn = get_offline_connection()?;
+    let lead_type = get_test_sobject_type("Lead", vec![], vec![])?;
+    let rule_id = SalesforceId::new("01Q000000000001")?;
+    let lead = SObject::new(&lead_type).with_str("LastName", "Smith");
+    let mut existing_lead = lead.clone();
+    existing_lead.set_id(FieldValue::Id(SalesforceId::new("00Q000000000001AAA")?))?;
+
+    let mut request = CompositeRequest::new(conn.get_base_url_path(), Some(true), None);
+    request.add("create", &SObjectCreateRequest::new(&lead)?)?;
+    request.add(
+        "update",
+        &SObjectUpdateRequest::new(&existing_lead)?
+            .with_assignment_rule(AssignmentRule::Specific(rule_id)),
+    )?;
+    request.add(
+        "fetch",
+        &CompositeRetrieveRequest::<SObject>::new("@{create.id}", &lead_type, None),
+    )?;
+
+    let get_headers = |body: &serde_json::Value| -> Vec<serde_json::Value> {
+        body["compositeRequest"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["httpHeaders"].clone())
+            .collect()
+    };
+
+    // Without a default, only the update carries a header.
+    assert_eq!(
+        get_headers(&request.get_body_for_connection(&conn).unwrap()),
+        vec![
+            json!(null),
+            json!({"Sforce-Auto-Assign": rule_id.to_string()}),
+            json!(null)
+        ]
+    );
+
+    // The Connection's default applies to every other DML subrequest.
+    conn.set_assignment_rule(Some(AssignmentRule::Disabled));
+    assert_eq!(
+        get_headers(&request.get_body_for_connection(&conn).unwrap()),
+        vec![
+            json!({"Sforce-Auto-Assign": "FALSE"}),
+            json!({"Sforce-Auto-Assign": rule_id.to_string()}),
+            json!(null)
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_composite_request_split() -> Result<()> {
+    let conn = get_offline_connection()?;
+    let lead_type = get_test_sobject_type("Lead", vec![], vec![])?;
+    let lead = SObject::new(&lead_type).with_str("LastName", "Smith");
+
+    let mut request = CompositeRequest::new(conn.get_base_url_path(), Some(true), None);
+    for i in 0..30 {
+        request.add(&format!("lead{}", i), &SObjectCreateRequest::new(&lead)?)?;
+    }
+    let size = request.estimate_payload_size();
+    let requests = request.split(MAX_REQUEST_BODY_SIZE)?;
+    assert_eq!(
+        requests
+            .iter()
+            .map(|r| r.keys.len())
+            .collect::<Vec<usize>>(),
+        vec![MAX_COMPOSITE_SUBREQUESTS, 5]
+    );
+    assert_eq!(requests[1].keys[0], "lead25");
+    assert_eq!(requests[1].get_body().unwrap()["allOrNone"], json!(true));
+
+    let mut request = CompositeRequest::new(conn.get_base_url_path(), Some(true), None);
+    for i in 0..10 {
+        request.add(&format!("lead{}", i), &SObjectCreateRequest::new(&lead)?)?;
+    }
+    let requests = request.split(size / 4)?;
+    assert!(requests.len() > 1);
+    assert!(requests
+        .iter()
+        .all(|r| r.estimate_payload_size() <= size / 4));
+    assert_eq!(requests.iter().map(|r| r.keys.len()).sum::<usize>(), 10);
+
+    // A reference cannot reach a subrequest sent in an earlier request.
+    let mut request = CompositeRequest::new(conn.get_base_url_path(), Some(true), None);
+    request.add("create", &SObjectCreateRequest::new(&lead)?)?;
+    let size = request.estimate_payload_size();
+    request.add(
+        "fetch",
+        &CompositeRetrieveRequest::<SObject>::new("@{create.id}", &lead_type, None),
+    )?;
+    assert!(request.split(size).is_err());
+
+    Ok(())
+}
+
+fn get_test_accounts(count: usize) -> Vec<Account> {
+    (0..count)
+        .map(|i| Account {
+            id: None,
+            name: format!("Composite Writer {}", i),
+        })
+        .collect()
+}
+
+#[test]
+fn test_composite_collection_writer_packing() {
+    let requests: Vec<SObjectCollectionCreateRequest> = (0..12)
+        .map(|i| {
+            SObjectCollectionCreateRequest::new_raw(
+                vec![json!({ "Name": format!("Account {:02}", i) })],
+                false,
+            )
+        })
+        .collect();
+
+    let batches = CompositeCollectionWriter::new(false).pack(&requests);
+    assert_eq!(
+        batches.iter().map(|b| b.len()).collect::<Vec<usize>>(),
+        vec![5, 5, 2]
+    );
+
+    let batches = CompositeCollectionWriter::new(false)
+        .with_max_subrequests(3)
+        .pack(&requests);
+    assert_eq!(batches.len(), 4);
+
+    let size = requests[0].estimate_payload_size();
+    let batches = CompositeCollectionWriter::new(false)
+        .with_max_size(size * 2)
+        .pack(&requests);
+    assert_eq!(batches.len(), 6);
+    assert!(batches.iter().all(|b| b.len() == 2));
+}
+
+#[tokio::test]
+async fn test_composite_collection_writer_dry_run() -> Result<()> {
+    let conn = get_offline_connection()?;
+    conn.set_mode(Mode::DryRun);
+
+    let results = CompositeCollectionWriter::new(false)
+        .create(&conn, &get_test_accounts(1050))
+        .await?;
+
+    assert_eq!(results.len(), 1050);
+    assert!(results.iter().all(|r| r.success && r.id.is_some()));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_composite_collection_writer_subrequest_errors() -> Result<()> {
+    let (conn, count) = serve_responses(vec![
+        (
+            "200 OK",
+            json!({"compositeResponse": [
+                {
+                    "body": [{"id": "001000000000001AAA", "success": true, "errors": []}],
+                    "httpHeaders": {}, "httpStatusCode": 200, "referenceId": "collection0"
+                },
+                {
+                    "body": [{"message": "Rejected", "errorCode": "INVALID_FIELD"}],
+                    "httpHeaders": {}, "httpStatusCode": 400, "referenceId": "collection1"
+                }
+            ]})
+            .to_string(),
+        ),
+        // The failed subrequest is resent alone, and rejected again.
+        (
+            "400 Bad Request",
+            json!([{"message": "Rejected", "errorCode": "INVALID_FIELD"}]).to_string(),
+        ),
+    ])
+    .await?;
+    let requests: Vec<SObjectCollectionCreateRequest> = (0..2)
+        .map(|i| {
+            SObjectCollectionCreateRequest::new_raw(
+                vec![json!({"attributes": {"type": "Account"}, "Name": format!("Account {}", i)})],
+                false,
+            )
+        })
+        .collect();
+
+    let results = CompositeCollectionWriter::new(false)
+        .execute(&conn, &requests)
+        .await?;
+
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+    assert_eq!(results.len(), 2);
+    assert!(results[0].success);
+    assert!(!results[1].success);
+    assert_eq!(
+        results[1].errors[0].get_error_code().map(|c| c.as_str()),
+        Some("INVALID_FIELD")
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_composite_collection_writer() -> Result<()> {
+    let conn = get_test_connection()?;
+    let writer = CompositeCollectionWriter::new(false);
+
+    let results = writer.create(&conn, &get_test_accounts(450)).await?;
+    assert_eq!(results.len(), 450);
+    assert!(results.iter().all(|r| r.success));
+
+    let mut accounts = get_test_accounts(450);
+    for (account, result) in accounts.iter_mut().zip(results.iter()) {
+        account.id = result.id;
+    }
+
+    let results = writer.delete(&conn, &accounts).await?;
+    assert_eq!(results.len(), 450);
+    assert!(results.iter().all(|r| r.success));
+
+    Ok(())
+}
+
+fn get_tree_types() -> Result<(SObjectType, SObjectType)> {
+    let account_type = get_test_sobject_type_with_children(
+        "Account",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            get_test_field_describe("Name", "xsd:string", "string"),
+        ],
+        vec![],
+        vec![get_contacts_relationship()],
+    )?;
+    let contact_type = get_test_sobject_type(
+        "Contact",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            get_test_field_describe("LastName", "xsd:string", "string"),
+        ],
+        vec![],
+    )?;
+
+    Ok((account_type, contact_type))
+}
+
+fn get_contacts_relationship() -> serde_json::Value {
+    json!({
+        "cascadeDelete": true,
+        "childSObject": "Contact",
+        "deprecatedAndHidden": false,
+        "field": "AccountId",
+        "junctionIdListNames": [],
+        "junctionReferenceTo": [],
+        "relationshipName": "Contacts",
+        "restrictedDelete": false
+    })
+}
+
+const TREE_RESULT: &str = r#"{"hasErrors": false, "results": [
+    {"referenceId": "ref1", "id": "001000000000001AAA"},
+    {"referenceId": "ref2", "id": "003000000000001AAA"},
+    {"referenceId": "ref3", "id": "003000000000002AAA"}
+]}"#;
+
+#[tokio::test]
+async fn test_sobject_tree_request() -> Result<()> {
+    let (conn, _) = serve_responses(vec![("201 Created", TREE_RESULT)]).await?;
+    let (account_type, contact_type) = get_tree_types()?;
+
+    let mut accounts = vec![SObject::new(&account_type)
+        .with_str("Name", "Parent")
+        .with_relationship(
+            "contacts",
+            SObject::new(&contact_type).with_str("LastName", "Child"),
+        )];
+    let request = SObjectTreeRequest::new(&accounts)?;
+
+    assert_eq!(request.get_url(), "composite/tree/Account");
+    assert_eq!(
+        request.get_body().unwrap(),
+        json!({"records": [{
+            "attributes": {"type": "Account", "referenceId": "ref1"},
+            "name": "Parent",
+            "Contacts": {"records": [{
+                "attributes": {"type": "Contact", "referenceId": "ref2"},
+                "lastname": "Child"
+            }]}
+        }]})
+    );
+
+    let result = conn.execute(&request).await?;
+    assert!(!result.has_errors);
+    assert_eq!(
+        result.get_id("ref2"),
+        Some(SalesforceId::new("003000000000001AAA")?)
+    );
+
+    result.set_ids(&mut accounts)?;
+    assert_eq!(
+        accounts[0].get_opt_id(),
+        Some(SalesforceId::new("001000000000001AAA")?)
+    );
+    match accounts[0].get("Contacts") {
+        Some(FieldValue::Relationship(contact)) => assert_eq!(
+            contact.get_opt_id(),
+            Some(SalesforceId::new("003000000000001AAA")?)
+        ),
+        _ => panic!("Expected a child record"),
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TreeContact {
+    id: Option<SalesforceId>,
+    last_name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TreeAccount {
+    id: Option<SalesforceId>,
+    name: String,
+    contacts: Vec<TreeContact>,
+}
+
+impl SingleTypedSObject for TreeAccount {
+    fn get_type_api_name() -> &'static str {
+        "Account"
+    }
+}
+
+#[tokio::test]
+async fn test_sobject_tree_request_typed() -> Result<()> {
+    let describe = get_test_sobject_describe(
+        "Account",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            get_test_field_describe("Name", "xsd:string", "string"),
+        ],
+        vec![],
+        vec![get_contacts_relationship()],
+    )?
+    .to_string();
+    let (conn, count) = serve_responses(vec![
+        ("200 OK", describe.as_str()),
+        ("201 Created", TREE_RESULT),
+    ])
+    .await?;
+
+    let mut accounts = vec![TreeAccount {
+        id: None,
+        name: "Parent".to_owned(),
+        contacts: vec![
+            TreeContact {
+                id: None,
+                last_name: "First".to_owned(),
+            },
+            TreeContact {
+                id: None,
+                last_name: "Second".to_owned(),
+            },
+        ],
+    }];
+    let request = SObjectTreeRequest::new_typed(&conn, &accounts).await?;
+
+    assert_eq!(
+        request.get_body().unwrap(),
+        json!({"records": [{
+            "attributes": {"type": "Account", "referenceId": "ref1"},
+            "Name": "Parent",
+            "Contacts": {"records": [
+                {"attributes": {"type": "Contact", "referenceId": "ref2"}, "LastName": "First"},
+                {"attributes": {"type": "Contact", "referenceId": "ref3"}, "LastName": "Second"}
+            ]}
+        }]})
+    );
+
+    let result = conn.execute(&request).await?;
+    result.set_typed_ids(&mut accounts)?;
+
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+    assert_eq!(
+        accounts[0].id,
+        Some(SalesforceId::new("001000000000001AAA")?)
+    );
+    assert_eq!(
+        accounts[0].contacts[1].id,
+        Some(SalesforceId::new("003000000000002AAA")?)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sobject_tree_request_errors() -> Result<()> {
+    let (conn, _) = serve_responses(vec![(
+        "400 Bad Request",
+        r#"{"hasErrors": true, "results": [{"referenceId": "ref2", "errors": [
+            {"statusCode": "REQUIRED_FIELD_MISSING", "message": "Required fields are missing: [LastName]", "fields": ["LastName"]}
+        ]}]}"#,
+    )])
+    .await?;
+    let (account_type, contact_type) = get_tree_types()?;
+
+    let request = SObjectTreeRequest::new(&[SObject::new(&account_type)
+        .with_str("Name", "Parent")
+        .with_relationship("Contacts", SObject::new(&contact_type))])?;
+    let result = conn.execute(&request).await?;
+
+    assert!(result.has_errors);
+    assert_eq!(result.results[0].reference_id, "ref2");
+    assert_eq!(result.results[0].id, None);
+    assert_eq!(
+        result.get_errors()[0].get_error_code(),
+        Some(&"REQUIRED_FIELD_MISSING".to_owned())
+    );
+
+    // Errors for the request as a whole are still returned as errors.
+    let (conn, _) = serve_responses(vec![(
+        "400 Bad Request",
+        r#"[{"errorCode": "JSON_PARSER_ERROR", "message": "Unexpected character"}]"#,
+    )])
+    .await?;
+    match conn.execute(&request).await.unwrap_err().downcast_ref() {
+        Some(SalesforceError::ApiErrors(errors)) => assert_eq!(
+            errors[0].get_error_code(),
+            Some(&"JSON_PARSER_ERROR".to_owned())
+        ),
+        _ => panic!("Expected API errors"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sobject_tree_request_limits() -> Result<()> {
+    let (account_type, contact_type) = get_tree_types()?;
+
+    assert!(SObjectTreeRequest::new(&Vec::<SObject>::new()).is_err());
+    assert!(
+        SObjectTreeRequest::new(&[SObject::new(&account_type), SObject::new(&contact_type)])
+            .is_err()
+    );
+    assert!(SObjectTreeRequest::new(&[
+        SObject::new(&account_type).with_relationship("Cases", SObject::new(&contact_type))
+    ])
+    .is_err());
+    assert!(SObjectTreeRequest::new(&vec![SObject::new(&account_type); 201]).is_err());
+
+    Ok(())
+}
+
+fn get_account_describe() -> Result<String> {
+    Ok(get_test_sobject_describe(
+        "Account",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            get_test_field_describe("Name", "xsd:string", "string"),
+        ],
+        vec![],
+        vec![],
+    )?
+    .to_string())
+}
+
+#[tokio::test]
+async fn test_create_and_fetch() -> Result<()> {
+    let (conn, count) = serve_responses(vec![
+        ("200 OK", get_account_describe()?.as_str()),
+        (
+            "200 OK",
+            r#"{"compositeResponse": [
+                {"body": {"id": "001000000000001AAA", "success": true, "errors": []},
+                 "httpHeaders": {}, "httpStatusCode": 201, "referenceId": "dml"},
+                {"body": {"attributes": {"type": "Account"}, "Id": "001000000000001AAA", "Name": "Test (Renamed)"},
+                 "httpHeaders": {}, "httpStatusCode": 200, "referenceId": "fetch"}
+            ]}"#,
+        ),
+    ])
+    .await?;
+
+    let account = Account {
+        id: None,
+        name: "Test".to_owned(),
+    }
+    .create_and_fetch(&conn, None)
+    .await?;
+
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+    assert_eq!(account.id, Some(SalesforceId::new("001000000000001AAA")?));
+    assert_eq!(account.name, "Test (Renamed)");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_update_and_fetch() -> Result<()> {
+    let (conn, _) = serve_responses(vec![
+        ("200 OK", get_account_describe()?.as_str()),
+        (
+            "200 OK",
+            r#"{"compositeResponse": [
+                {"body": null, "httpHeaders": {}, "httpStatusCode": 204, "referenceId": "dml"},
+                {"body": {"attributes": {"type": "Account"}, "Id": "001000000000001AAA", "Name": "Updated"},
+                 "httpHeaders": {}, "httpStatusCode": 200, "referenceId": "fetch"}
+            ]}"#,
+        ),
+    ])
+    .await?;
+
+    let account = Account {
+        id: Some(SalesforceId::new("001000000000001AAA")?),
+        name: "Updated".to_owned(),
+    };
+
+    assert_eq!(
+        account.update_and_fetch(&conn, None).await?.name,
+        "Updated".to_owned()
+    );
+
+    let new_account = Account {
+        id: None,
+        name: "Test".to_owned(),
+    };
+    assert!(new_account.update_and_fetch(&conn, None).await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_and_fetch_error() -> Result<()> {
+    let (conn, _) = serve_responses(vec![
+        ("200 OK", get_account_describe()?.as_str()),
+        (
+            "200 OK",
+            r#"{"compositeResponse": [
+                {"body": [{"message": "Required fields are missing: [Name]", "errorCode": "REQUIRED_FIELD_MISSING"}],
+                 "httpHeaders": {}, "httpStatusCode": 400, "referenceId": "dml"},
+                {"body": [{"message": "The transaction was rolled back since another operation in the same transaction failed.", "errorCode": "PROCESSING_HALTED"}],
+                 "httpHeaders": {}, "httpStatusCode": 400, "referenceId": "fetch"}
+            ]}"#,
+        ),
+    ])
+    .await?;
+
+    let result = Account {
+        id: None,
+        name: "".to_owned(),
+    }
+    .create_and_fetch(&conn, None)
+    .await;
+
+    match result {
+        Err(error) => assert!(error.to_string().starts_with("REQUIRED_FIELD_MISSING")),
+        Ok(_) => panic!("Expected the create error"),
+    }
+
+    Ok(())
+}
+
+fn get_transaction_types() -> Result<(SObjectType, SObjectType)> {
+    let (account_type, _) = get_tree_types()?;
+    let contact_type = get_test_sobject_type(
+        "Contact",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            get_test_field_describe("AccountId", "tns:ID", "reference"),
+            get_test_field_describe("LastName", "xsd:string", "string"),
+        ],
+        vec![],
+    )?;
+
+    Ok((account_type, contact_type))
+}
+
+#[tokio::test]
+async fn test_transaction() -> Result<()> {
+    let (account_type, contact_type) = get_transaction_types()?;
+    let (conn, _) = serve_responses(vec![(
+        "200 OK",
+        r#"{"compositeResponse": [
+            {"body": {"id": "001000000000001AAA", "success": true, "errors": []},
+             "httpHeaders": {}, "httpStatusCode": 201, "referenceId": "op0"},
+            {"body": {"id": "003000000000001AAA", "success": true, "errors": []},
+             "httpHeaders": {}, "httpStatusCode": 201, "referenceId": "op1"},
+            {"body": null, "httpHeaders": {}, "httpStatusCode": 204, "referenceId": "op2"},
+            {"body": null, "httpHeaders": {}, "httpStatusCode": 204, "referenceId": "op3"}
+        ]}"#,
+    )])
+    .await?;
+
+    let mut account = SObject::new(&account_type).with_str("Name", "Acme");
+    let mut contact = SObject::new(&contact_type).with_str("LastName", "Smith");
+    let mut renamed = SObject::new(&account_type).with_str("Name", "Acme Corporation");
+    let mut old_contact = SObject::new(&contact_type);
+    old_contact.set_id(FieldValue::Id(SalesforceId::new("003000000000002AAA")?))?;
+
+    let mut transaction = Transaction::new();
+    let account_ref = transaction.create(&mut account)?;
+    assert_eq!(account_ref, "@{op0.id}");
+
+    contact.put(
+        "AccountId",
+        FieldValue::CompositeReference(account_ref.clone()),
+    );
+    renamed.set_id(FieldValue::CompositeReference(account_ref))?;
+    transaction.create(&mut contact)?;
+    transaction.update(&mut renamed)?;
+    transaction.delete(&mut old_contact)?;
+
+    let body = transaction
+        .get_request(conn.get_base_url_path())?
+        .get_body()
+        .unwrap();
+    assert_eq!(body["allOrNone"], true);
+    assert_eq!(
+        body["compositeRequest"][1]["body"]["accountid"],
+        "@{op0.id}"
+    );
+    assert!(body["compositeRequest"][2]["url"]
+        .as_str()
+        .unwrap()
+        .ends_with("sobjects/Account/@{op0.id}"));
+    assert_eq!(body["compositeRequest"][3]["method"], "DELETE");
+
+    transaction.execute(&conn).await?;
+
+    let account_id = SalesforceId::new("001000000000001AAA")?;
+    assert_eq!(account.get_id(), FieldValue::Id(account_id));
+    assert_eq!(
+        contact.get_id(),
+        FieldValue::Id(SalesforceId::new("003000000000001AAA")?)
+    );
+    assert_eq!(contact.get("AccountId"), Some(&FieldValue::Id(account_id)));
+    assert_eq!(renamed.get_id(), FieldValue::Id(account_id));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transaction_rollback() -> Result<()> {
+    let (account_type, _) = get_transaction_types()?;
+    let (conn, _) = serve_responses(vec![(
+        "200 OK",
+        r#"{"compositeResponse": [
+            {"body": [{"message": "The transaction was rolled back since another operation in the same transaction failed.", "errorCode": "PROCESSING_HALTED"}],
+             "httpHeaders": {}, "httpStatusCode": 400, "referenceId": "op0"},
+            {"body": [{"message": "Required fields are missing: [Name]", "errorCode": "REQUIRED_FIELD_MISSING"}],
+             "httpHeaders": {}, "httpStatusCode": 400, "referenceId": "op1"}
+        ]}"#,
+    )])
+    .await?;
+
+    let mut first = SObject::new(&account_type).with_str("Name", "Acme");
+    let mut second = SObject::new(&account_type);
+
+    let mut transaction = Transaction::new();
+    transaction.create(&mut first)?;
+    transaction.create(&mut second)?;
+
+    match transaction.execute(&conn).await {
+        Err(error) => match error.downcast_ref::<SalesforceError>() {
+            Some(SalesforceError::ApiErrors(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert_eq!(
+                    errors[0].get_error_code().map(|c| c.as_str()),
+                    Some("REQUIRED_FIELD_MISSING")
+                );
+            }
+            _ => panic!("Expected ApiErrors"),
+        },
+        Ok(_) => panic!("Expected the transaction to fail"),
+    }
+    assert_eq!(first.get_id(), FieldValue::Null);
+
+    Ok(())
+}
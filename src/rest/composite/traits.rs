@@ -0,0 +1,82 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use super::{CompositeRequest, CompositeRetrieveRequest};
+use crate::api::Connection;
+use crate::data::{SObjectDeserialization, SObjectSerialization, SObjectWithId, TypedSObject};
+use crate::rest::rows::{SObjectCreateRequest, SObjectUpdateRequest};
+
+const DML_KEY: &str = "dml";
+const FETCH_KEY: &str = "fetch";
+
+/// Write a single sObject and read back its saved state, including
+/// system fields, formula fields, and values set by automation,
+/// in a single all-or-none Composite request.
+#[async_trait]
+pub trait SObjectRowFetchable: Sized {
+    /// Create this sObject and return the saved record.
+    /// `fields` limits the fields retrieved; by default, all are returned.
+    async fn create_and_fetch(
+        &self,
+        conn: &Connection,
+        fields: Option<Vec<String>>,
+    ) -> Result<Self>;
+
+    /// Update this sObject and return the saved record.
+    /// `fields` limits the fields retrieved; by default, all are returned.
+    async fn update_and_fetch(
+        &self,
+        conn: &Connection,
+        fields: Option<Vec<String>>,
+    ) -> Result<Self>;
+}
+
+#[async_trait]
+impl<T> SObjectRowFetchable for T
+where
+    T: SObjectSerialization + SObjectDeserialization + SObjectWithId + TypedSObject,
+{
+    async fn create_and_fetch(
+        &self,
+        conn: &Connection,
+        fields: Option<Vec<String>>,
+    ) -> Result<Self> {
+        let sobject_type = conn.get_type(self.get_api_name()).await?;
+        let create_request = SObjectCreateRequest::new(self)?;
+        let fetch_request = CompositeRetrieveRequest::<T>::new(
+            &format!("@{{{}.id}}", DML_KEY),
+            &sobject_type,
+            fields,
+        );
+
+        let mut request = CompositeRequest::new(conn.get_base_url_path(), Some(true), None);
+        request.add(DML_KEY, &create_request)?;
+        request.add(FETCH_KEY, &fetch_request)?;
+
+        let result = conn.execute(&request).await?;
+        let created: Result<()> = result.get_result(conn, DML_KEY, &create_request)?.into();
+        created?;
+
+        result.get_result(conn, FETCH_KEY, &fetch_request)
+    }
+
+    async fn update_and_fetch(
+        &self,
+        conn: &Connection,
+        fields: Option<Vec<String>>,
+    ) -> Result<Self> {
+        let sobject_type = conn.get_type(self.get_api_name()).await?;
+        let update_request = SObjectUpdateRequest::new(self)?;
+        let fetch_request =
+            CompositeRetrieveRequest::<T>::new(&self.get_id().as_string(), &sobject_type, fields);
+
+        let mut request = CompositeRequest::new(conn.get_base_url_path(), Some(true), None);
+        request.add(DML_KEY, &update_request)?;
+        request.add(FETCH_KEY, &fetch_request)?;
+
+        let result = conn.execute(&request).await?;
+        result.get_result(conn, DML_KEY, &update_request)?;
+
+        result.get_result(conn, FETCH_KEY, &fetch_request)
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::{
+    api::Connection,
+    data::{FieldValue, SObject, SObjectWithId, SalesforceId},
+    errors::SalesforceError,
+    rest::rows::{SObjectCreateRequest, SObjectDeleteRequest, SObjectUpdateRequest},
+    rest::DmlResult,
+};
+
+use super::{CompositeRequest, CompositeResponse, CompositeSubrequestResponseBody};
+
+/// The maximum number of subrequests in a single Composite request.
+pub const MAX_TRANSACTION_OPERATIONS: usize = 25;
+
+enum TransactionOperation {
+    Create,
+    Update,
+    Delete,
+}
+
+struct TransactionStep<'a> {
+    operation: TransactionOperation,
+    record: &'a mut SObject,
+}
+
+/// Creates, updates, and deletes records of any sObject type together,
+/// in a single all-or-none Composite request.
+///
+/// `create()` returns a reference to the new record's Id, such as `@{op0.id}`,
+/// which later operations may use as a `FieldValue::CompositeReference`: as
+/// the value of a lookup field, or as the Id of a record to update or delete.
+/// Once the transaction commits, the new Ids are written back to the created
+/// records and replace references to them in every record in the transaction.
+/// If any operation fails, none take effect.
+#[derive(Default)]
+pub struct Transaction<'a> {
+    steps: Vec<TransactionStep<'a>>,
+}
+
+impl<'a> Transaction<'a> {
+    pub fn new() -> Transaction<'a> {
+        Transaction { steps: Vec::new() }
+    }
+
+    /// Add a record to create, returning a reference to its Id.
+    pub fn create(&mut self, record: &'a mut SObject) -> Result<String> {
+        let key = self.add(TransactionOperation::Create, record)?;
+
+        Ok(get_reference(&key))
+    }
+
+    pub fn update(&mut self, record: &'a mut SObject) -> Result<()> {
+        self.add(TransactionOperation::Update, record)?;
+        Ok(())
+    }
+
+    pub fn delete(&mut self, record: &'a mut SObject) -> Result<()> {
+        self.add(TransactionOperation::Delete, record)?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    fn add(&mut self, operation: TransactionOperation, record: &'a mut SObject) -> Result<String> {
+        if self.steps.len() == MAX_TRANSACTION_OPERATIONS {
+            return Err(SalesforceError::GeneralError(format!(
+                "A transaction may contain at most {} operations",
+                MAX_TRANSACTION_OPERATIONS
+            ))
+            .into());
+        }
+
+        self.steps.push(TransactionStep { operation, record });
+        Ok(get_key(self.steps.len() - 1))
+    }
+
+    pub(crate) fn get_request(&self, base_url: String) -> Result<CompositeRequest> {
+        let mut request = CompositeRequest::new(base_url, Some(true), None);
+
+        for (i, step) in self.steps.iter().enumerate() {
+            let key = get_key(i);
+            match step.operation {
+                TransactionOperation::Create => {
+                    request.add(&key, &SObjectCreateRequest::new(&*step.record)?)?
+                }
+                TransactionOperation::Update => {
+                    request.add(&key, &SObjectUpdateRequest::new(&*step.record)?)?
+                }
+                TransactionOperation::Delete => {
+                    request.add(&key, &SObjectDeleteRequest::new(&*step.record)?)?
+                }
+            }
+        }
+
+        Ok(request)
+    }
+
+    /// Execute the transaction. On failure, the errors of the operations that
+    /// caused the rollback are returned as `SalesforceError::ApiErrors`.
+    pub async fn execute(self, conn: &Connection) -> Result<()> {
+        if self.steps.is_empty() {
+            return Ok(());
+        }
+
+        let response = conn
+            .execute(&self.get_request(conn.get_base_url_path())?)
+            .await?;
+
+        self.apply_response(&response)
+    }
+
+    fn apply_response(mut self, response: &CompositeResponse) -> Result<()> {
+        let mut errors = Vec::new();
+        let mut ids = HashMap::new();
+
+        for (i, step) in self.steps.iter().enumerate() {
+            let key = get_key(i);
+            let result = response.get_result_value(&key).ok_or_else(|| {
+                SalesforceError::GeneralError(format!("No result returned for {}", key))
+            })?;
+
+            match &result.body {
+                // Operations that did not fail themselves are rolled back
+                // with the error PROCESSING_HALTED.
+                CompositeSubrequestResponseBody::Error(errs) => errors.extend(
+                    errs.iter()
+                        .filter(|e| {
+                            e.get_error_code()
+                                .is_none_or(|code| code != "PROCESSING_HALTED")
+                        })
+                        .cloned(),
+                ),
+                CompositeSubrequestResponseBody::Success(Some(body)) => {
+                    if let TransactionOperation::Create = step.operation {
+                        let result: DmlResult = serde_json::from_value(body.clone())?;
+                        if let Some(id) = result.id {
+                            ids.insert(get_reference(&key), id);
+                        }
+                    }
+                }
+                CompositeSubrequestResponseBody::Success(None) => {}
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(SalesforceError::ApiErrors(errors).into());
+        }
+
+        for (i, step) in self.steps.iter_mut().enumerate() {
+            if let TransactionOperation::Create = step.operation {
+                if let Some(id) = ids.get(&get_reference(&get_key(i))) {
+                    step.record.set_id(FieldValue::Id(*id))?;
+                }
+            }
+            resolve_references(step.record, &ids);
+        }
+
+        Ok(())
+    }
+}
+
+// Replace references to created records, including in the Id field, with their Ids.
+fn resolve_references(record: &mut SObject, ids: &HashMap<String, SalesforceId>) {
+    for value in record.fields.values_mut() {
+        if let FieldValue::CompositeReference(reference) = value {
+            if let Some(id) = ids.get(reference) {
+                *value = FieldValue::Id(*id);
+            }
+        }
+    }
+}
+
+fn get_key(index: usize) -> String {
+    format!("op{}", index)
+}
+
+fn get_reference(key: &str) -> String {
+    format!("@{{{}.id}}", key)
+}
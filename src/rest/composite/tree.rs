@@ -0,0 +1,467 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use futures::future::{BoxFuture, FutureExt};
+use reqwest::{Method, StatusCode};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    api::Connection,
+    api::SalesforceRequest,
+    data::{FieldValue, SObject, SObjectType, SObjectWithId, SalesforceId, TypedSObject},
+    errors::SalesforceError,
+    rest::describe::ChildRelationshipDescribe,
+    rest::{ApiError, DmlError},
+};
+
+/// The maximum number of records, across all levels, in a single sObject Tree request.
+pub const MAX_TREE_RECORDS: usize = 200;
+/// The maximum number of levels in a record hierarchy in a single sObject Tree request.
+pub const MAX_TREE_DEPTH: usize = 5;
+
+// Reference Ids are assigned to records in depth-first order, parents before
+// children and children in field order, so that the results can be mapped
+// back onto the records by walking them again in the same order.
+#[derive(Default)]
+struct ReferenceIds {
+    count: usize,
+}
+
+impl ReferenceIds {
+    fn next(&mut self) -> Result<String> {
+        self.count += 1;
+        if self.count > MAX_TREE_RECORDS {
+            return Err(SalesforceError::GeneralError(format!(
+                "An sObject Tree may contain at most {} records",
+                MAX_TREE_RECORDS
+            ))
+            .into());
+        }
+
+        Ok(format!("ref{}", self.count))
+    }
+}
+
+fn is_id_field(key: &str) -> bool {
+    key.eq_ignore_ascii_case("id")
+}
+
+fn check_depth(depth: usize) -> Result<()> {
+    if depth > MAX_TREE_DEPTH {
+        Err(SalesforceError::GeneralError(format!(
+            "An sObject Tree may be at most {} levels deep",
+            MAX_TREE_DEPTH
+        ))
+        .into())
+    } else {
+        Ok(())
+    }
+}
+
+fn get_child_relationship<'a>(
+    sobject_type: &'a SObjectType,
+    relationship_name: &str,
+) -> Result<&'a ChildRelationshipDescribe> {
+    sobject_type
+        .get_describe()
+        .child_relationships
+        .iter()
+        .find(|r| r.relationship_name.eq_ignore_ascii_case(relationship_name))
+        .ok_or_else(|| {
+            SalesforceError::SchemaError(format!(
+                "{} is not a child relationship of {}",
+                relationship_name,
+                sobject_type.get_api_name()
+            ))
+            .into()
+        })
+}
+
+fn get_attributes(api_name: &str, references: &mut ReferenceIds) -> Result<Value> {
+    Ok(json!({"type": api_name, "referenceId": references.next()?}))
+}
+
+fn sobject_to_tree(
+    sobject: &SObject,
+    references: &mut ReferenceIds,
+    depth: usize,
+) -> Result<Value> {
+    check_depth(depth)?;
+
+    let mut map = Map::new();
+    map.insert(
+        "attributes".to_string(),
+        get_attributes(sobject.get_api_name(), references)?,
+    );
+
+    let mut keys: Vec<&String> = sobject.fields.keys().collect();
+    keys.sort();
+
+    for key in keys.into_iter().filter(|k| !is_id_field(k)) {
+        match &sobject.fields[key] {
+            FieldValue::Relationship(child) => {
+                let relationship = get_child_relationship(&sobject.sobject_type, key)?;
+                map.insert(
+                    relationship.relationship_name.clone(),
+                    json!({ "records": [sobject_to_tree(child, references, depth + 1)?] }),
+                );
+            }
+            value => {
+                map.insert(value.get_field_name(key).to_string(), value.into());
+            }
+        }
+    }
+
+    Ok(Value::Object(map))
+}
+
+fn set_sobject_ids(
+    sobject: &mut SObject,
+    ids: &HashMap<String, SalesforceId>,
+    references: &mut ReferenceIds,
+) -> Result<()> {
+    if let Some(id) = ids.get(&references.next()?) {
+        sobject.set_id(FieldValue::Id(*id))?;
+    }
+
+    let mut keys: Vec<String> = sobject
+        .fields
+        .iter()
+        .filter(|(_, v)| v.is_relationship())
+        .map(|(k, _)| k.clone())
+        .collect();
+    keys.sort();
+
+    for key in keys {
+        if let Some(FieldValue::Relationship(child)) = sobject.fields.get_mut(&key) {
+            set_sobject_ids(child, ids, references)?;
+        }
+    }
+
+    Ok(())
+}
+
+// In serialized typed structs, child records are arrays of objects.
+fn get_children(value: &Value) -> Option<&Vec<Value>> {
+    match value {
+        Value::Array(children) if children.iter().all(Value::is_object) => Some(children),
+        _ => None,
+    }
+}
+
+fn typed_to_tree<'a>(
+    conn: &'a Connection,
+    api_name: String,
+    value: Value,
+    references: &'a mut ReferenceIds,
+    depth: usize,
+) -> BoxFuture<'a, Result<Value>> {
+    async move {
+        check_depth(depth)?;
+
+        let fields = match value {
+            Value::Object(fields) => fields,
+            _ => {
+                return Err(SalesforceError::GeneralError("Invalid record JSON".to_string()).into())
+            }
+        };
+        let mut map = Map::new();
+        map.insert(
+            "attributes".to_string(),
+            get_attributes(&api_name, references)?,
+        );
+
+        let mut sobject_type = None;
+        for (key, value) in fields {
+            if key == "attributes" || is_id_field(&key) {
+                continue;
+            }
+
+            if let Some(children) = get_children(&value) {
+                if children.is_empty() {
+                    continue;
+                }
+                if sobject_type.is_none() {
+                    sobject_type = Some(conn.get_type(&api_name).await?);
+                }
+                let relationship = get_child_relationship(sobject_type.as_ref().unwrap(), &key)?;
+
+                let mut records = Vec::new();
+                for child in children {
+                    records.push(
+                        typed_to_tree(
+                            conn,
+                            relationship.child_sobject.clone(),
+                            child.clone(),
+                            references,
+                            depth + 1,
+                        )
+                        .await?,
+                    );
+                }
+                map.insert(
+                    relationship.relationship_name.clone(),
+                    json!({ "records": records }),
+                );
+            } else {
+                map.insert(key, value);
+            }
+        }
+
+        Ok(Value::Object(map))
+    }
+    .boxed()
+}
+
+fn set_value_ids(
+    value: &mut Value,
+    ids: &HashMap<String, SalesforceId>,
+    references: &mut ReferenceIds,
+) -> Result<()> {
+    let map = match value {
+        Value::Object(map) => map,
+        _ => return Err(SalesforceError::GeneralError("Invalid record JSON".to_string()).into()),
+    };
+
+    if let Some(id) = ids.get(&references.next()?) {
+        let key = map
+            .keys()
+            .find(|k| is_id_field(k))
+            .cloned()
+            .unwrap_or_else(|| "Id".to_string());
+        map.insert(key, Value::String(id.to_string()));
+    }
+
+    for (key, value) in map.iter_mut() {
+        if key == "attributes" || get_children(value).is_none() {
+            continue;
+        }
+        if let Value::Array(children) = value {
+            for child in children {
+                set_value_ids(child, ids, references)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn count_tree_records(records: &[Value]) -> usize {
+    records
+        .iter()
+        .map(|record| {
+            1 + record
+                .as_object()
+                .map(|map| {
+                    map.values()
+                        .filter_map(|v| v.get("records").and_then(Value::as_array))
+                        .map(|children| count_tree_records(children))
+                        .sum()
+                })
+                .unwrap_or(0)
+        })
+        .sum()
+}
+
+// sObject Tree Requests
+
+/// Insert one or more record hierarchies of the same root sObject type,
+/// such as Accounts with their Contacts and Opportunities, in a single call.
+///
+/// Children may be nested up to `MAX_TREE_DEPTH` levels deep, and a request may
+/// contain up to `MAX_TREE_RECORDS` records in total. If any record fails,
+/// no records are saved.
+pub struct SObjectTreeRequest {
+    api_name: String,
+    records: Vec<Value>,
+}
+
+impl SObjectTreeRequest {
+    /// Create a request from records already in the sObject Tree format,
+    /// each with `attributes` containing `type` and `referenceId`.
+    pub fn new_raw(api_name: String, records: Vec<Value>) -> SObjectTreeRequest {
+        SObjectTreeRequest { api_name, records }
+    }
+
+    /// Create a request from `SObject` hierarchies. A child record is
+    /// a `FieldValue::Relationship` stored under the name of the parent's
+    /// child relationship, such as `Contacts`.
+    pub fn new(records: &[SObject]) -> Result<SObjectTreeRequest> {
+        let api_name = Self::get_root_type(records)?;
+        let mut references = ReferenceIds::default();
+
+        Ok(Self::new_raw(
+            api_name,
+            records
+                .iter()
+                .map(|r| sobject_to_tree(r, &mut references, 1))
+                .collect::<Result<Vec<Value>>>()?,
+        ))
+    }
+
+    /// Create a request from typed record hierarchies. A child collection is
+    /// a field containing a list of records, named for the parent's child
+    /// relationship, such as `Contacts`. The describes of parent types
+    /// are used to determine the sObject type of each child.
+    pub async fn new_typed<T>(conn: &Connection, records: &[T]) -> Result<SObjectTreeRequest>
+    where
+        T: serde::Serialize + TypedSObject,
+    {
+        let api_name = Self::get_root_type(records)?;
+        let mut references = ReferenceIds::default();
+
+        let mut values = Vec::new();
+        for record in records {
+            values.push(
+                typed_to_tree(
+                    conn,
+                    api_name.clone(),
+                    serde_json::to_value(record)?,
+                    &mut references,
+                    1,
+                )
+                .await?,
+            );
+        }
+
+        Ok(Self::new_raw(api_name, values))
+    }
+
+    fn get_root_type<T: TypedSObject>(records: &[T]) -> Result<String> {
+        let api_name = records
+            .first()
+            .ok_or_else(|| {
+                SalesforceError::GeneralError("An sObject Tree requires a record".to_string())
+            })?
+            .get_api_name();
+
+        if records.iter().any(|r| r.get_api_name() != api_name) {
+            return Err(SalesforceError::GeneralError(
+                "The root records of an sObject Tree must be of the same sObject type".to_string(),
+            )
+            .into());
+        }
+
+        Ok(api_name.to_owned())
+    }
+}
+
+impl SalesforceRequest for SObjectTreeRequest {
+    type ReturnValue = SObjectTreeResult;
+
+    fn get_body(&self) -> Option<Value> {
+        Some(json!({ "records": self.records }))
+    }
+
+    fn get_url(&self) -> String {
+        format!("composite/tree/{}", self.api_name)
+    }
+
+    fn get_method(&self) -> Method {
+        Method::POST
+    }
+
+    // A tree with failed records is returned with 400 Bad Request,
+    // and its errors are reported per reference Id.
+    fn accepts_error_status(&self, status: StatusCode) -> bool {
+        status == StatusCode::BAD_REQUEST
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            match serde_json::from_value::<Self::ReturnValue>(body.clone()) {
+                Ok(result) => Ok(result),
+                Err(e) => match serde_json::from_value::<Vec<ApiError>>(body.clone()) {
+                    Ok(errors) if !errors.is_empty() => {
+                        Err(SalesforceError::ApiErrors(errors).into())
+                    }
+                    _ => Err(e.into()),
+                },
+            }
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+
+    fn get_dry_run_result(&self, conn: &Connection) -> Result<Self::ReturnValue> {
+        Ok(SObjectTreeResult {
+            has_errors: false,
+            results: (1..=count_tree_records(&self.records))
+                .map(|i| SObjectTreeReferenceResult {
+                    reference_id: format!("ref{}", i),
+                    id: Some(conn.get_dry_run_id()),
+                    errors: Vec::new(),
+                })
+                .collect(),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SObjectTreeReferenceResult {
+    pub reference_id: String,
+    pub id: Option<SalesforceId>,
+    #[serde(default)]
+    pub errors: Vec<DmlError>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SObjectTreeResult {
+    pub has_errors: bool,
+    pub results: Vec<SObjectTreeReferenceResult>,
+}
+
+impl SObjectTreeResult {
+    pub fn get_id(&self, reference_id: &str) -> Option<SalesforceId> {
+        self.results
+            .iter()
+            .find(|r| r.reference_id == reference_id)
+            .and_then(|r| r.id)
+    }
+
+    fn get_ids(&self) -> HashMap<String, SalesforceId> {
+        self.results
+            .iter()
+            .filter_map(|r| r.id.map(|id| (r.reference_id.clone(), id)))
+            .collect()
+    }
+
+    /// Set the Ids of the created records on the `SObject` hierarchies
+    /// from which the request was created.
+    pub fn set_ids(&self, records: &mut [SObject]) -> Result<()> {
+        let ids = self.get_ids();
+        let mut references = ReferenceIds::default();
+
+        for record in records {
+            set_sobject_ids(record, &ids, &mut references)?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the Ids of the created records on the typed hierarchies
+    /// from which the request was created.
+    pub fn set_typed_ids<T>(&self, records: &mut [T]) -> Result<()>
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        let ids = self.get_ids();
+        let mut references = ReferenceIds::default();
+
+        for record in records {
+            let mut value = serde_json::to_value(&*record)?;
+            set_value_ids(&mut value, &ids, &mut references)?;
+            *record = serde_json::from_value(value)?;
+        }
+
+        Ok(())
+    }
+
+    /// All errors returned for records in the tree.
+    pub fn get_errors(&self) -> Vec<&DmlError> {
+        self.results.iter().flat_map(|r| r.errors.iter()).collect()
+    }
+}
@@ -0,0 +1,255 @@
+use anyhow::{Error, Result};
+
+use crate::{
+    api::{is_client_error, Connection, SalesforceRequest},
+    data::traits::{SObjectSerialization, SObjectWithId, TypedSObject},
+    errors::SalesforceError,
+    rest::collections::{
+        CollectionDmlRequest, SObjectCollectionCreateRequest, SObjectCollectionDeleteRequest,
+        SObjectCollectionUpdateRequest, SObjectCollectionUpsertRequest, MAX_COLLECTION_RECORDS,
+        MAX_REQUEST_BODY_SIZE,
+    },
+    rest::{ApiError, DmlError, DmlResult},
+};
+
+use super::{CompositeRequest, CompositeSubrequestResponseBody};
+
+/// The maximum number of sObject Collections subrequests in one Composite request.
+pub const MAX_COMPOSITE_COLLECTION_SUBREQUESTS: usize = 5;
+
+/// Writes records by packing up to five sObject Collections requests of 200
+/// records each into a single Composite request, for up to 1,000 records
+/// per API call.
+///
+/// `all_or_none` applies to each Collections request of up to 200 records,
+/// not to the whole write. Results are returned one per record, in order.
+///
+/// If Salesforce rejects a Composite request outright, or fails an individual
+/// Collections subrequest, the affected Collections requests are sent on
+/// their own instead. If Salesforce rejects one of those requests as well,
+/// each of its records fails with the errors returned, and the write goes on.
+/// Dry-run Connections always send Collections requests individually, since
+/// Composite results cannot be simulated.
+pub struct CompositeCollectionWriter {
+    all_or_none: bool,
+    max_subrequests: usize,
+    max_size: usize,
+}
+
+impl CompositeCollectionWriter {
+    pub fn new(all_or_none: bool) -> CompositeCollectionWriter {
+        CompositeCollectionWriter {
+            all_or_none,
+            max_subrequests: MAX_COMPOSITE_COLLECTION_SUBREQUESTS,
+            max_size: MAX_REQUEST_BODY_SIZE,
+        }
+    }
+
+    /// Limit the number of Collections requests per Composite request,
+    /// between 1 and `MAX_COMPOSITE_COLLECTION_SUBREQUESTS`.
+    #[must_use]
+    pub fn with_max_subrequests(mut self, max_subrequests: usize) -> Self {
+        self.max_subrequests = max_subrequests.clamp(1, MAX_COMPOSITE_COLLECTION_SUBREQUESTS);
+        self
+    }
+
+    /// Limit the estimated size of each Composite request body.
+    #[must_use]
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    pub async fn create<T>(&self, conn: &Connection, records: &[T]) -> Result<Vec<DmlResult>>
+    where
+        T: SObjectSerialization + SObjectWithId,
+    {
+        let requests = records
+            .chunks(MAX_COLLECTION_RECORDS)
+            .map(|chunk| SObjectCollectionCreateRequest::new(chunk, self.all_or_none))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.execute(conn, &requests).await
+    }
+
+    pub async fn update<T>(&self, conn: &Connection, records: &[T]) -> Result<Vec<DmlResult>>
+    where
+        T: SObjectSerialization + SObjectWithId,
+    {
+        let requests = records
+            .chunks(MAX_COLLECTION_RECORDS)
+            .map(|chunk| SObjectCollectionUpdateRequest::new(chunk, self.all_or_none))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.execute(conn, &requests).await
+    }
+
+    pub async fn upsert<T>(
+        &self,
+        conn: &Connection,
+        records: &[T],
+        external_id: &str,
+    ) -> Result<Vec<DmlResult>>
+    where
+        T: SObjectSerialization + TypedSObject,
+    {
+        let requests = records
+            .chunks(MAX_COLLECTION_RECORDS)
+            .map(|chunk| SObjectCollectionUpsertRequest::new(chunk, external_id, self.all_or_none))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.execute(conn, &requests).await
+    }
+
+    pub async fn delete<T>(&self, conn: &Connection, records: &[T]) -> Result<Vec<DmlResult>>
+    where
+        T: SObjectWithId,
+    {
+        let requests = records
+            .chunks(MAX_COLLECTION_RECORDS)
+            .map(|chunk| SObjectCollectionDeleteRequest::new(chunk, self.all_or_none))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.execute(conn, &requests).await
+    }
+
+    /// Execute prepared Collections requests, returning their results in order.
+    pub async fn execute<R>(&self, conn: &Connection, requests: &[R]) -> Result<Vec<DmlResult>>
+    where
+        R: CollectionDmlRequest,
+    {
+        let mut results = Vec::new();
+
+        for batch in self.pack(requests) {
+            results.extend(self.execute_batch(conn, batch).await?);
+        }
+
+        Ok(results)
+    }
+
+    /// Group `requests` into batches within the subrequest and size limits.
+    pub(super) fn pack<'a, R>(&self, requests: &'a [R]) -> Vec<&'a [R]>
+    where
+        R: SalesforceRequest,
+    {
+        let mut batches = Vec::new();
+        let mut start = 0;
+        let mut size = 0;
+
+        for (i, request) in requests.iter().enumerate() {
+            let request_size = request.estimate_payload_size();
+
+            if i > start
+                && (i - start == self.max_subrequests || size + request_size > self.max_size)
+            {
+                batches.push(&requests[start..i]);
+                start = i;
+                size = 0;
+            }
+
+            size += request_size;
+        }
+
+        if start < requests.len() {
+            batches.push(&requests[start..]);
+        }
+
+        batches
+    }
+
+    async fn execute_batch<R>(&self, conn: &Connection, requests: &[R]) -> Result<Vec<DmlResult>>
+    where
+        R: CollectionDmlRequest,
+    {
+        if requests.len() == 1 || conn.is_dry_run() {
+            return execute_individually(conn, requests).await;
+        }
+
+        let mut composite = CompositeRequest::new(conn.get_base_url_path(), Some(false), None);
+        for (i, request) in requests.iter().enumerate() {
+            composite.add(&get_key(i), request)?;
+        }
+
+        let response = match conn.execute(&composite).await {
+            Ok(response) => response,
+            // A 4xx response means Salesforce processed none of the Composite
+            // request, so its subrequests can safely be resent on their own.
+            Err(e) if is_client_error(&e) => {
+                return execute_individually(conn, requests).await;
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut results = Vec::new();
+        for (i, request) in requests.iter().enumerate() {
+            let key = get_key(i);
+            let failed = response
+                .get_result_value(&key)
+                .is_none_or(|r| matches!(r.body, CompositeSubrequestResponseBody::Error(_)));
+
+            if failed {
+                results.extend(execute_alone(conn, request).await?);
+            } else {
+                results.extend(response.get_result(conn, &key, request)?);
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+async fn execute_individually<R>(conn: &Connection, requests: &[R]) -> Result<Vec<DmlResult>>
+where
+    R: CollectionDmlRequest,
+{
+    let mut results = Vec::new();
+
+    for request in requests {
+        results.extend(execute_alone(conn, request).await?);
+    }
+
+    Ok(results)
+}
+
+// Execute `request` on its own. If Salesforce rejects it, each of its
+// records fails with the errors returned.
+async fn execute_alone<R>(conn: &Connection, request: &R) -> Result<Vec<DmlResult>>
+where
+    R: CollectionDmlRequest,
+{
+    match conn.execute(request).await {
+        Err(e) if is_client_error(&e) => Ok(get_failed_results(&e, request.get_record_count())),
+        result => result,
+    }
+}
+
+fn get_failed_results(error: &Error, count: usize) -> Vec<DmlResult> {
+    let errors: Vec<DmlError> = match error.downcast_ref::<SalesforceError>() {
+        Some(SalesforceError::ApiErrors(errors)) => errors.clone(),
+        _ => vec![ApiError {
+            message: error.to_string(),
+            error_code: None,
+            status_code: None,
+            duplicate_result: None,
+        }],
+    }
+    .into_iter()
+    .map(|error| DmlError {
+        fields: Vec::new(),
+        error,
+    })
+    .collect();
+
+    (0..count)
+        .map(|_| DmlResult {
+            id: None,
+            created: None,
+            success: false,
+            errors: errors.clone(),
+        })
+        .collect()
+}
+
+fn get_key(index: usize) -> String {
+    format!("collection{}", index)
+}
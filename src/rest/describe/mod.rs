@@ -2,34 +2,173 @@ use std::collections::HashMap;
 
 use anyhow::Result;
 use reqwest::Method;
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::{
-    api::Connection, api::SalesforceRequest, data::SalesforceId, data::SoapType,
+    api::Connection, api::SalesforceRequest, data::DateTime, data::SalesforceId, data::SoapType,
     errors::SalesforceError,
 };
 
 #[cfg(test)]
 mod test;
 
+fn get_if_modified_since_header(timestamp: Option<&DateTime>) -> Option<HashMap<String, String>> {
+    timestamp.map(|timestamp| {
+        let mut headers = HashMap::new();
+        headers.insert(
+            "If-Modified-Since".to_owned(),
+            timestamp.format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+        );
+        headers
+    })
+}
+
+/// List all sObjects available in the org.
+pub struct GlobalDescribeRequest {
+    if_modified_since: Option<DateTime>,
+}
+
+impl GlobalDescribeRequest {
+    pub fn new() -> GlobalDescribeRequest {
+        GlobalDescribeRequest {
+            if_modified_since: None,
+        }
+    }
+
+    /// Return a result only if the list of sObjects has changed since
+    /// `timestamp`. Otherwise, the request returns `None`.
+    #[must_use]
+    pub fn with_if_modified_since(mut self, timestamp: DateTime) -> Self {
+        self.if_modified_since = Some(timestamp);
+        self
+    }
+}
+
+impl Default for GlobalDescribeRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SalesforceRequest for GlobalDescribeRequest {
+    type ReturnValue = Option<GlobalDescribe>;
+
+    fn get_url(&self) -> String {
+        "sobjects/".to_owned()
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    fn get_headers(&self) -> Option<HashMap<String, String>> {
+        get_if_modified_since_header(self.if_modified_since.as_ref())
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        // A request with If-Modified-Since returns 304 Not Modified, with no body,
+        // if nothing has changed.
+        if let Some(body) = body {
+            Ok(Some(serde_json::from_value::<GlobalDescribe>(
+                body.clone(),
+            )?))
+        } else if self.if_modified_since.is_some() {
+            Ok(None)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GlobalDescribe {
+    pub encoding: String,
+    pub max_batch_size: u32,
+    pub sobjects: Vec<GlobalSObjectDescribe>,
+}
+
+impl GlobalDescribe {
+    pub fn get_sobject(&self, api_name: &str) -> Option<&GlobalSObjectDescribe> {
+        self.sobjects
+            .iter()
+            .find(|s| s.name.eq_ignore_ascii_case(api_name))
+    }
+
+    pub fn get_sobject_names(&self) -> Vec<&str> {
+        self.sobjects.iter().map(|s| s.name.as_str()).collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GlobalSObjectDescribe {
+    pub activateable: bool,
+    pub createable: bool,
+    pub custom: bool,
+    pub custom_setting: bool,
+    pub deletable: bool,
+    pub deprecated_and_hidden: bool,
+    pub feed_enabled: bool,
+    pub has_subtypes: bool,
+    pub is_subtype: bool,
+    pub key_prefix: Option<String>,
+    pub label: String,
+    pub label_plural: String,
+    pub layoutable: bool,
+    pub mergeable: bool,
+    pub mru_enabled: bool,
+    pub name: String,
+    pub queryable: bool,
+    pub replicateable: bool,
+    pub retrieveable: bool,
+    pub searchable: bool,
+    pub triggerable: bool,
+    pub undeletable: bool,
+    pub updateable: bool,
+    pub urls: HashMap<String, String>,
+}
+
+impl Connection {
+    /// List all sObjects available in the org.
+    pub async fn describe_global(&self) -> Result<GlobalDescribe> {
+        self.execute(&GlobalDescribeRequest::new())
+            .await?
+            .ok_or_else(|| SalesforceError::ResponseBodyExpected.into())
+    }
+}
+
 pub struct SObjectDescribeRequest {
     sobject: String,
+    tooling: bool,
 }
 
 impl SObjectDescribeRequest {
     pub fn new(sobject: &str) -> SObjectDescribeRequest {
         SObjectDescribeRequest {
             sobject: sobject.to_owned(),
+            tooling: false,
         }
     }
+
+    /// Describe a Tooling API sObject, such as `TraceFlag`.
+    #[must_use]
+    pub fn with_tooling_api(mut self) -> Self {
+        self.tooling = true;
+        self
+    }
 }
 
 impl SalesforceRequest for SObjectDescribeRequest {
     type ReturnValue = SObjectDescribe;
 
     fn get_url(&self) -> String {
-        format!("sobjects/{}/describe", self.sobject)
+        if self.tooling {
+            format!("tooling/sobjects/{}/describe", self.sobject)
+        } else {
+            format!("sobjects/{}/describe", self.sobject)
+        }
     }
 
     fn get_method(&self) -> Method {
@@ -45,7 +184,49 @@ impl SalesforceRequest for SObjectDescribeRequest {
     }
 }
 
-#[derive(Debug, Deserialize)]
+/// Revalidate a cached describe, returning `None` if the describe
+/// has not changed since `if_modified_since`.
+pub(crate) struct SObjectDescribeIfModifiedRequest {
+    sobject: String,
+    if_modified_since: DateTime,
+}
+
+impl SObjectDescribeIfModifiedRequest {
+    pub(crate) fn new(sobject: &str, if_modified_since: DateTime) -> Self {
+        SObjectDescribeIfModifiedRequest {
+            sobject: sobject.to_owned(),
+            if_modified_since,
+        }
+    }
+}
+
+impl SalesforceRequest for SObjectDescribeIfModifiedRequest {
+    type ReturnValue = Option<SObjectDescribe>;
+
+    fn get_url(&self) -> String {
+        format!("sobjects/{}/describe", self.sobject)
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    fn get_headers(&self) -> Option<HashMap<String, String>> {
+        get_if_modified_since_header(Some(&self.if_modified_since))
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        // 304 Not Modified has no body.
+        match body {
+            Some(body) => Ok(Some(serde_json::from_value::<SObjectDescribe>(
+                body.clone(),
+            )?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FieldDescribe {
     pub aggregatable: bool,
@@ -108,7 +289,7 @@ pub struct FieldDescribe {
     pub write_requires_master_read: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ChildRelationshipDescribe {
     pub cascade_delete: bool,
@@ -122,7 +303,7 @@ pub struct ChildRelationshipDescribe {
     pub restricted_delete: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RecordTypeDescribe {
     pub active: bool,
@@ -135,13 +316,13 @@ pub struct RecordTypeDescribe {
     pub urls: HashMap<String, String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ScopeDescribe {
     pub label: String,
     pub name: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SObjectDescribe {
     //action_overrides: Vec<ActionOverrideDescribe>,
@@ -150,6 +331,8 @@ pub struct SObjectDescribe {
     pub createable: bool,
     pub custom: bool,
     pub custom_setting: bool,
+    #[serde(default)]
+    pub child_relationships: Vec<ChildRelationshipDescribe>,
     pub deep_cloneable: bool,
     //defaultImplementation: null // FIXME
     pub deletable: bool,
@@ -188,6 +371,10 @@ pub struct SObjectDescribe {
 }
 
 impl SObjectDescribe {
+    pub fn get_fields(&self) -> &[FieldDescribe] {
+        &self.fields
+    }
+
     pub fn get_field(&self, api_name: &str) -> Option<&FieldDescribe> {
         // TODO: cache a case-insensitive HashMap for fields.
         let target = api_name.to_lowercase();
@@ -202,7 +389,7 @@ impl SObjectDescribe {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PicklistValueDescribe {
     pub active: bool,
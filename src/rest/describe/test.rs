@@ -1 +1,75 @@
+use anyhow::Result;
 
+use super::GlobalDescribeRequest;
+use crate::api::SalesforceRequest;
+use crate::data::DateTime;
+use crate::test_integration_base::{get_test_connection, serve_responses};
+
+const GLOBAL_DESCRIBE: &str = r#"{
+    "encoding": "UTF-8",
+    "maxBatchSize": 200,
+    "sobjects": [{
+        "activateable": false, "createable": true, "custom": false, "customSetting": false,
+        "deletable": true, "deprecatedAndHidden": false, "feedEnabled": true,
+        "hasSubtypes": false, "isSubtype": false, "keyPrefix": "001", "label": "Account",
+        "labelPlural": "Accounts", "layoutable": true, "mergeable": true, "mruEnabled": true,
+        "name": "Account", "queryable": true, "replicateable": true, "retrieveable": true,
+        "searchable": true, "triggerable": true, "undeletable": true, "updateable": true,
+        "urls": {"sobject": "/services/data/v52.0/sobjects/Account"}
+    }, {
+        "activateable": false, "createable": false, "custom": false, "customSetting": false,
+        "deletable": false, "deprecatedAndHidden": false, "feedEnabled": false,
+        "hasSubtypes": false, "isSubtype": false, "keyPrefix": null, "label": "Account History",
+        "labelPlural": "Account History", "layoutable": false, "mergeable": false,
+        "mruEnabled": false, "name": "AccountHistory", "queryable": true, "replicateable": true,
+        "retrieveable": true, "searchable": false, "triggerable": false, "undeletable": false,
+        "updateable": false, "urls": {}
+    }]
+}"#;
+
+#[tokio::test]
+async fn test_describe_global() -> Result<()> {
+    let (conn, _) = serve_responses(vec![("200 OK", GLOBAL_DESCRIBE)]).await?;
+
+    let describe = conn.describe_global().await?;
+
+    assert_eq!(describe.max_batch_size, 200);
+    assert_eq!(
+        describe.get_sobject_names(),
+        vec!["Account", "AccountHistory"]
+    );
+    assert_eq!(
+        describe.get_sobject("account").unwrap().key_prefix,
+        Some("001".to_owned())
+    );
+    assert!(describe.get_sobject("Contact").is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_describe_global_not_modified() -> Result<()> {
+    let (conn, _) = serve_responses(vec![("304 Not Modified", "")]).await?;
+    let request =
+        GlobalDescribeRequest::new().with_if_modified_since(DateTime::new(2021, 3, 4, 5, 6, 7, 0)?);
+
+    assert_eq!(
+        request.get_headers().unwrap()["If-Modified-Since"],
+        "Thu, 04 Mar 2021 05:06:07 GMT"
+    );
+    assert!(conn.execute(&request).await?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_describe_global_live() -> Result<()> {
+    let conn = get_test_connection()?;
+
+    let describe = conn.describe_global().await?;
+
+    assert!(describe.get_sobject("Account").is_some());
+
+    Ok(())
+}
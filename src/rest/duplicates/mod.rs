@@ -0,0 +1,188 @@
+use anyhow::Result;
+use reqwest::Method;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    api::Connection, api::SalesforceRequest, data::SObjectSerialization, data::SalesforceId,
+    errors::SalesforceError,
+};
+
+use super::ApiError;
+
+#[cfg(test)]
+mod test;
+
+/// The maximum number of records that may be checked in a single request.
+pub const MAX_DUPLICATE_RECORDS: usize = 50;
+
+// Find Duplicates Requests
+
+/// Run the org's active duplicate rules against prospective records,
+/// without saving them, to find existing records that they would duplicate.
+pub struct FindDuplicatesRequest {
+    records: Vec<Value>,
+}
+
+impl FindDuplicatesRequest {
+    pub fn new_raw(records: Vec<Value>) -> FindDuplicatesRequest {
+        FindDuplicatesRequest { records }
+    }
+
+    pub fn new<T>(objects: &[T]) -> Result<Self>
+    where
+        T: SObjectSerialization,
+    {
+        if objects.len() > MAX_DUPLICATE_RECORDS {
+            return Err(SalesforceError::GeneralError(format!(
+                "Cannot check more than {} records for duplicates in one request",
+                MAX_DUPLICATE_RECORDS
+            ))
+            .into());
+        }
+
+        Ok(Self::new_raw(
+            objects
+                .iter()
+                .map(|s| s.to_value_with_options(true, true))
+                .collect::<Result<Vec<Value>>>()?,
+        ))
+    }
+}
+
+impl SalesforceRequest for FindDuplicatesRequest {
+    type ReturnValue = Vec<FindDuplicatesResult>;
+
+    fn get_body(&self) -> Option<Value> {
+        Some(json!({ "records": self.records }))
+    }
+
+    fn get_url(&self) -> String {
+        "duplicates/".to_owned()
+    }
+
+    fn get_method(&self) -> Method {
+        Method::POST
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(serde_json::from_value::<Self::ReturnValue>(body.clone())?)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+
+    // Finding duplicates never saves the prospective records.
+    fn is_mutating(&self) -> bool {
+        false
+    }
+}
+
+// Duplicate Rule Results, shared by find-duplicates requests
+// and by DML errors with the code DUPLICATES_DETECTED.
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FindDuplicatesResult {
+    pub duplicate_results: Vec<DuplicateResult>,
+    #[serde(default)]
+    pub errors: Vec<ApiError>,
+    pub success: bool,
+}
+
+impl FindDuplicatesResult {
+    /// Whether any duplicate rule matched the prospective record.
+    pub fn has_duplicates(&self) -> bool {
+        self.duplicate_results.iter().any(|r| r.has_matches())
+    }
+
+    /// Whether every matching duplicate rule would allow the record to be saved.
+    pub fn is_save_allowed(&self) -> bool {
+        self.duplicate_results
+            .iter()
+            .all(|r| r.allow_save || !r.has_matches())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateResult {
+    pub allow_save: bool,
+    pub duplicate_rule: String,
+    pub duplicate_rule_entity_type: String,
+    pub error_message: Option<String>,
+    pub match_results: Vec<MatchResult>,
+}
+
+impl DuplicateResult {
+    pub fn has_matches(&self) -> bool {
+        self.match_results
+            .iter()
+            .any(|m| !m.match_records.is_empty())
+    }
+
+    /// The Ids of all existing records matched by this duplicate rule.
+    pub fn get_matching_ids(&self) -> Vec<SalesforceId> {
+        self.match_results
+            .iter()
+            .flat_map(|m| m.match_records.iter())
+            .filter_map(|r| r.get_id())
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchResult {
+    pub entity_type: String,
+    #[serde(default)]
+    pub errors: Vec<ApiError>,
+    pub match_engine: String,
+    pub match_records: Vec<MatchRecord>,
+    pub rule: String,
+    pub size: usize,
+    pub success: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchRecord {
+    #[serde(default)]
+    pub additional_information: Vec<AdditionalInformation>,
+    #[serde(default)]
+    pub field_diffs: Vec<FieldDiff>,
+    pub match_confidence: f64,
+    pub record: Value,
+}
+
+impl MatchRecord {
+    pub fn get_id(&self) -> Option<SalesforceId> {
+        self.record
+            .get("Id")
+            .and_then(|id| id.as_str())
+            .and_then(|id| SalesforceId::new(id).ok())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AdditionalInformation {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FieldDifference {
+    Same,
+    Different,
+    Null,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldDiff {
+    pub name: String,
+    pub difference: FieldDifference,
+}
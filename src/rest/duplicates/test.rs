@@ -0,0 +1,105 @@
+use anyhow::Result;
+
+use crate::api::{Mode, SalesforceRequest};
+use crate::prelude::*;
+use crate::rest::DmlResult;
+use crate::test_integration_base::{serve_responses, Account};
+
+use super::{FieldDifference, FindDuplicatesRequest, MAX_DUPLICATE_RECORDS};
+
+const DUPLICATE_RESULT: &str = r#"{
+    "allowSave": false,
+    "duplicateRule": "Standard_Account_Duplicate_Rule",
+    "duplicateRuleEntityType": "Account",
+    "errorMessage": "You're creating a duplicate record.",
+    "matchResults": [{
+        "entityType": "Account",
+        "errors": [],
+        "matchEngine": "FuzzyMatchEngine",
+        "matchRecords": [{
+            "additionalInformation": [],
+            "fieldDiffs": [{"name": "Name", "difference": "SAME"}, {"name": "BillingCity", "difference": "NULL"}],
+            "matchConfidence": 100.0,
+            "record": {"attributes": {"type": "Account"}, "Id": "001000000000001AAA", "Name": "Acme"}
+        }],
+        "rule": "Standard_Account_Match_Rule_v1_0",
+        "size": 1,
+        "success": true
+    }]
+}"#;
+
+#[tokio::test]
+async fn test_find_duplicates() -> Result<()> {
+    let body = format!(
+        r#"[{{"duplicateResults": [{}], "errors": [], "success": true}}, {{"duplicateResults": [], "errors": [], "success": true}}]"#,
+        DUPLICATE_RESULT
+    );
+    let (conn, count) = serve_responses(vec![("200 OK", body)]).await?;
+    // Finding duplicates saves nothing, so it is permitted in dry-run mode.
+    conn.set_mode(Mode::DryRun);
+
+    let request = FindDuplicatesRequest::new(&[
+        Account {
+            id: None,
+            name: "Acme".to_owned(),
+        },
+        Account {
+            id: None,
+            name: "Unique".to_owned(),
+        },
+    ])?;
+    assert_eq!(
+        request.get_body().unwrap()["records"][0],
+        serde_json::json!({"attributes": {"type": "Account"}, "Name": "Acme"})
+    );
+
+    let results = conn.execute(&request).await?;
+
+    assert_eq!(count.load(std::sync::atomic::Ordering::SeqCst), 1);
+    assert_eq!(results.len(), 2);
+    assert!(results[0].has_duplicates());
+    assert!(!results[0].is_save_allowed());
+    assert!(!results[1].has_duplicates());
+    assert!(results[1].is_save_allowed());
+
+    let duplicate = &results[0].duplicate_results[0];
+    assert_eq!(
+        duplicate.get_matching_ids(),
+        vec![SalesforceId::new("001000000000001AAA")?]
+    );
+    let record = &duplicate.match_results[0].match_records[0];
+    assert_eq!(record.field_diffs[0].difference, FieldDifference::Same);
+    assert_eq!(record.field_diffs[1].difference, FieldDifference::Null);
+
+    Ok(())
+}
+
+#[test]
+fn test_find_duplicates_limit() {
+    let accounts: Vec<Account> = (0..=MAX_DUPLICATE_RECORDS)
+        .map(|i| Account {
+            id: None,
+            name: format!("Account {}", i),
+        })
+        .collect();
+
+    assert!(FindDuplicatesRequest::new(&accounts).is_err());
+}
+
+#[test]
+fn test_duplicates_detected_dml_error() -> Result<()> {
+    let result: DmlResult = serde_json::from_str(&format!(
+        r#"{{"id": null, "success": false, "errors": [{{"statusCode": "DUPLICATES_DETECTED", "message": "Use one of these records?", "fields": [], "duplicateResult": {}}}]}}"#,
+        DUPLICATE_RESULT
+    ))?;
+
+    let duplicate = result.errors[0].get_duplicate_result().unwrap();
+    assert_eq!(duplicate.duplicate_rule, "Standard_Account_Duplicate_Rule");
+    assert!(!duplicate.allow_save);
+    assert_eq!(
+        duplicate.get_matching_ids(),
+        vec![SalesforceId::new("001000000000001AAA")?]
+    );
+
+    Ok(())
+}
@@ -0,0 +1,314 @@
+use anyhow::Result;
+use reqwest::Method;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    api::Connection, api::SalesforceRequest, data::SalesforceId, errors::SalesforceError,
+    tooling::ExecuteAnonymousApexRequest,
+};
+
+use super::ApiError;
+
+#[cfg(test)]
+mod test;
+
+/// An email to send from the org, either through the `emailSimple`
+/// standard action or, for features that action does not support
+/// (such as CC and BCC recipients), as a `Messaging.SingleEmailMessage`
+/// in Anonymous Apex.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Email {
+    to: Vec<String>,
+    cc: Vec<String>,
+    bcc: Vec<String>,
+    subject: Option<String>,
+    body: Option<String>,
+    template_id: Option<SalesforceId>,
+    recipient_id: Option<SalesforceId>,
+    related_record_id: Option<SalesforceId>,
+}
+
+impl Email {
+    pub fn new() -> Email {
+        Email::default()
+    }
+
+    #[must_use]
+    pub fn with_to(mut self, address: &str) -> Self {
+        self.to.push(address.to_owned());
+        self
+    }
+
+    #[must_use]
+    pub fn with_cc(mut self, address: &str) -> Self {
+        self.cc.push(address.to_owned());
+        self
+    }
+
+    #[must_use]
+    pub fn with_bcc(mut self, address: &str) -> Self {
+        self.bcc.push(address.to_owned());
+        self
+    }
+
+    #[must_use]
+    pub fn with_subject(mut self, subject: &str) -> Self {
+        self.subject = Some(subject.to_owned());
+        self
+    }
+
+    /// Set the plain-text body of the email.
+    #[must_use]
+    pub fn with_body(mut self, body: &str) -> Self {
+        self.body = Some(body.to_owned());
+        self
+    }
+
+    /// Compose the email from the email template `template_id`. Templates
+    /// require a recipient Contact, Lead, or User; see `with_recipient()`.
+    #[must_use]
+    pub fn with_template(mut self, template_id: SalesforceId) -> Self {
+        self.template_id = Some(template_id);
+        self
+    }
+
+    /// Send the email to the Contact, Lead, or User `recipient_id`,
+    /// whose fields are available to the email template.
+    #[must_use]
+    pub fn with_recipient(mut self, recipient_id: SalesforceId) -> Self {
+        self.recipient_id = Some(recipient_id);
+        self
+    }
+
+    /// Relate the email to the record `related_record_id`, whose fields
+    /// are available to the email template.
+    #[must_use]
+    pub fn with_related_record(mut self, related_record_id: SalesforceId) -> Self {
+        self.related_record_id = Some(related_record_id);
+        self
+    }
+
+    /// Whether this email uses features that the `emailSimple` action does not
+    /// support, and so must be sent via Anonymous Apex.
+    pub fn requires_apex(&self) -> bool {
+        !self.cc.is_empty() || !self.bcc.is_empty()
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.to.is_empty() && self.recipient_id.is_none() {
+            return Err(
+                SalesforceError::GeneralError("An email requires a recipient".to_string()).into(),
+            );
+        }
+        if self.template_id.is_some() && self.recipient_id.is_none() {
+            return Err(SalesforceError::GeneralError(
+                "An email template requires a recipient Id".to_string(),
+            )
+            .into());
+        }
+        if self.template_id.is_none() && self.body.is_none() {
+            return Err(SalesforceError::GeneralError(
+                "An email requires a body or a template".to_string(),
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    fn to_action_inputs(&self) -> Value {
+        let mut inputs = Map::new();
+
+        if !self.to.is_empty() {
+            inputs.insert("emailAddressesArray".to_string(), json!(self.to));
+        }
+        if let Some(subject) = &self.subject {
+            inputs.insert("emailSubject".to_string(), json!(subject));
+        }
+        if let Some(body) = &self.body {
+            inputs.insert("emailBody".to_string(), json!(body));
+        }
+        if let Some(template_id) = self.template_id {
+            inputs.insert("composeFromTemplate".to_string(), json!(true));
+            inputs.insert(
+                "emailTemplateId".to_string(),
+                json!(template_id.to_string()),
+            );
+        }
+        if let Some(recipient_id) = self.recipient_id {
+            inputs.insert("recipientId".to_string(), json!(recipient_id.to_string()));
+        }
+        if let Some(related_record_id) = self.related_record_id {
+            inputs.insert(
+                "relatedRecordId".to_string(),
+                json!(related_record_id.to_string()),
+            );
+        }
+
+        Value::Object(inputs)
+    }
+
+    /// The Anonymous Apex that sends this email as a `Messaging.SingleEmailMessage`.
+    pub fn to_apex(&self) -> String {
+        let mut apex = vec![
+            "Messaging.SingleEmailMessage m = new Messaging.SingleEmailMessage();".to_string(),
+        ];
+
+        for (setter, addresses) in [
+            ("setToAddresses", &self.to),
+            ("setCcAddresses", &self.cc),
+            ("setBccAddresses", &self.bcc),
+        ] {
+            if !addresses.is_empty() {
+                apex.push(format!(
+                    "m.{}(new List<String>{{{}}});",
+                    setter,
+                    addresses
+                        .iter()
+                        .map(|a| apex_string(a))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ));
+            }
+        }
+        for (setter, value) in [
+            ("setSubject", self.subject.clone()),
+            ("setPlainTextBody", self.body.clone()),
+            ("setTemplateId", self.template_id.map(|id| id.to_string())),
+            (
+                "setTargetObjectId",
+                self.recipient_id.map(|id| id.to_string()),
+            ),
+            ("setWhatId", self.related_record_id.map(|id| id.to_string())),
+        ] {
+            if let Some(value) = value {
+                apex.push(format!("m.{}({});", setter, apex_string(&value)));
+            }
+        }
+        if self.recipient_id.is_some() {
+            // Sending to a User via setTargetObjectId requires this to be false.
+            apex.push("m.setSaveAsActivity(false);".to_string());
+        }
+        apex.push("Messaging.sendEmail(new List<Messaging.SingleEmailMessage>{m});".to_string());
+
+        apex.join("\n")
+    }
+}
+
+fn apex_string(value: &str) -> String {
+    format!(
+        "'{}'",
+        value
+            .replace('\\', "\\\\")
+            .replace('\'', "\\'")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+    )
+}
+
+// Send Email Requests
+
+/// Send emails through the `emailSimple` standard invocable action.
+pub struct SendEmailRequest {
+    inputs: Vec<Value>,
+}
+
+impl SendEmailRequest {
+    pub fn new(emails: &[Email]) -> Result<SendEmailRequest> {
+        for email in emails {
+            email.validate()?;
+            if email.requires_apex() {
+                return Err(SalesforceError::GeneralError(
+                    "The emailSimple action does not support CC or BCC recipients".to_string(),
+                )
+                .into());
+            }
+        }
+
+        Ok(SendEmailRequest {
+            inputs: emails.iter().map(|e| e.to_action_inputs()).collect(),
+        })
+    }
+}
+
+impl SalesforceRequest for SendEmailRequest {
+    type ReturnValue = Vec<InvocableActionResult>;
+
+    fn get_body(&self) -> Option<Value> {
+        Some(json!({ "inputs": self.inputs }))
+    }
+
+    fn get_url(&self) -> String {
+        "actions/standard/emailSimple".to_owned()
+    }
+
+    fn get_method(&self) -> Method {
+        Method::POST
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(serde_json::from_value::<Self::ReturnValue>(body.clone())?)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+
+    fn get_dry_run_result(&self, _conn: &Connection) -> Result<Self::ReturnValue> {
+        Ok(self
+            .inputs
+            .iter()
+            .map(|_| InvocableActionResult {
+                action_name: "emailSimple".to_string(),
+                errors: None,
+                is_success: true,
+                output_values: None,
+            })
+            .collect())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InvocableActionResult {
+    pub action_name: String,
+    pub errors: Option<Vec<ApiError>>,
+    pub is_success: bool,
+    pub output_values: Option<Value>,
+}
+
+impl From<InvocableActionResult> for Result<()> {
+    fn from(val: InvocableActionResult) -> Self {
+        if val.is_success {
+            Ok(())
+        } else {
+            match val.errors {
+                Some(errors) if !errors.is_empty() => {
+                    Err(SalesforceError::ApiErrors(errors).into())
+                }
+                _ => Err(SalesforceError::UnknownError.into()),
+            }
+        }
+    }
+}
+
+impl Connection {
+    /// Send `email` through the `emailSimple` action, or through
+    /// Anonymous Apex if it uses features that the action does not support.
+    pub async fn send_email(&self, email: &Email) -> Result<()> {
+        if email.requires_apex() {
+            email.validate()?;
+            self.execute(&ExecuteAnonymousApexRequest::new(email.to_apex()))
+                .await?
+                .into()
+        } else {
+            self.execute(&SendEmailRequest::new(std::slice::from_ref(email))?)
+                .await?
+                .into_iter()
+                .next()
+                .ok_or(SalesforceError::ResponseBodyExpected)?
+                .into()
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::sync::atomic::Ordering;
+
+use anyhow::Result;
+use serde_json::json;
+
+use crate::api::{Mode, SalesforceRequest};
+use crate::prelude::*;
+use crate::test_integration_base::serve_responses;
+
+use super::{Email, SendEmailRequest};
+
+#[test]
+fn test_send_email_request() -> Result<()> {
+    let request = SendEmailRequest::new(&[
+        Email::new()
+            .with_to("a@example.com")
+            .with_to("b@example.com")
+            .with_subject("Load complete")
+            .with_body("1,000 records loaded."),
+        Email::new()
+            .with_template(SalesforceId::new("00X000000000001EAA")?)
+            .with_recipient(SalesforceId::new("003000000000001AAA")?)
+            .with_related_record(SalesforceId::new("001000000000001AAA")?),
+    ])?;
+
+    assert_eq!(request.get_url(), "actions/standard/emailSimple");
+    assert_eq!(
+        request.get_body().unwrap(),
+        json!({"inputs": [
+            {
+                "emailAddressesArray": ["a@example.com", "b@example.com"],
+                "emailSubject": "Load complete",
+                "emailBody": "1,000 records loaded."
+            },
+            {
+                "composeFromTemplate": true,
+                "emailTemplateId": "00X000000000001EAA",
+                "recipientId": "003000000000001AAA",
+                "relatedRecordId": "001000000000001AAA"
+            }
+        ]})
+    );
+
+    assert!(SendEmailRequest::new(&[Email::new().with_body("No recipient")]).is_err());
+    assert!(SendEmailRequest::new(&[Email::new()
+        .with_to("a@example.com")
+        .with_template(SalesforceId::new("00X000000000001EAA")?)])
+    .is_err());
+    assert!(SendEmailRequest::new(&[Email::new()
+        .with_to("a@example.com")
+        .with_cc("b@example.com")
+        .with_body("CC")])
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_email_to_apex() {
+    let email = Email::new()
+        .with_to("a@example.com")
+        .with_cc("b@example.com")
+        .with_subject("It's done")
+        .with_body("Line one\nLine two");
+
+    assert!(email.requires_apex());
+    assert_eq!(
+        email.to_apex(),
+        "Messaging.SingleEmailMessage m = new Messaging.SingleEmailMessage();\n\
+         m.setToAddresses(new List<String>{'a@example.com'});\n\
+         m.setCcAddresses(new List<String>{'b@example.com'});\n\
+         m.setSubject('It\\'s done');\n\
+         m.setPlainTextBody('Line one\\nLine two');\n\
+         Messaging.sendEmail(new List<Messaging.SingleEmailMessage>{m});"
+    );
+}
+
+#[tokio::test]
+async fn test_send_email() -> Result<()> {
+    let (conn, count) = serve_responses(vec![
+        (
+            "200 OK",
+            r#"[{"actionName": "emailSimple", "errors": null, "isSuccess": true, "outputValues": null}]"#,
+        ),
+        (
+            "200 OK",
+            r#"[{"actionName": "emailSimple", "errors": [{"statusCode": "INVALID_EMAIL_ADDRESS", "message": "Email address is invalid: nobody", "fields": []}], "isSuccess": false, "outputValues": null}]"#,
+        ),
+        (
+            "200 OK",
+            r#"{"line": -1, "column": -1, "compiled": true, "success": true, "compileProblem": null, "exceptionStackTrace": null, "exceptionMessage": null}"#,
+        ),
+    ])
+    .await?;
+
+    conn.send_email(&Email::new().with_to("a@example.com").with_body("Done"))
+        .await?;
+
+    let error = conn
+        .send_email(&Email::new().with_to("nobody").with_body("Done"))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<SalesforceError>(),
+        Some(SalesforceError::ApiErrors(_))
+    ));
+
+    conn.send_email(
+        &Email::new()
+            .with_to("a@example.com")
+            .with_bcc("b@example.com")
+            .with_body("Done"),
+    )
+    .await?;
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+
+    // Emails are not sent in dry-run mode.
+    conn.set_mode(Mode::DryRun);
+    conn.send_email(&Email::new().with_to("a@example.com").with_body("Done"))
+        .await?;
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+
+    Ok(())
+}
@@ -0,0 +1,361 @@
+//! Downloads and uploads of large blob content, such as `ContentVersion` bodies.
+//!
+//! A `BlobDownload` fetches content in byte ranges, several at a time, and
+//! writes them in order to a file or any `AsyncWrite`. Content can be verified
+//! against an MD5 checksum, such as `ContentVersion.Checksum`.
+//!
+//! A `ContentVersionUploadRequest` creates a `ContentVersion` from a stream of
+//! bytes, so that its content need not be held in memory.
+
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::RwLock;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{stream, Stream, StreamExt};
+use md5::{Digest, Md5};
+use reqwest::{header, Method, Response};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_derive::Deserialize;
+use serde_json::Value;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    api::{transport::TransportBody, Connection, SalesforceRawRequest},
+    data::{SObjectType, SalesforceId},
+    errors::SalesforceError,
+    rest::query::{query_all_values, QueryRequest},
+    rest::rows::SObjectBlobFieldRequest,
+    rest::DmlResult,
+};
+
+#[cfg(test)]
+mod test;
+
+pub const DEFAULT_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// A download of a blob field's content in ranges fetched in parallel.
+pub struct BlobDownload {
+    url: String,
+    size: Option<u64>,
+    checksum: Option<String>,
+    chunk_size: u64,
+    concurrency: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContentVersionInfo {
+    checksum: Option<String>,
+    content_size: Option<u64>,
+}
+
+impl BlobDownload {
+    pub fn new(sobject_type: &SObjectType, id: SalesforceId, field: &str) -> Result<BlobDownload> {
+        Ok(BlobDownload {
+            url: SObjectBlobFieldRequest::new(sobject_type, id, field)?.get_url(),
+            size: None,
+            checksum: None,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            concurrency: DEFAULT_CONCURRENCY,
+        })
+    }
+
+    /// Download the `VersionData` of the ContentVersion `id`,
+    /// verified against its size and `Checksum`.
+    pub async fn content_version(conn: &Connection, id: SalesforceId) -> Result<BlobDownload> {
+        let content_version_type = conn.get_type("ContentVersion").await?;
+        let info: ContentVersionInfo = serde_json::from_value(
+            query_all_values(
+                conn,
+                &QueryRequest::new(
+                    &format!(
+                        "SELECT Checksum, ContentSize FROM ContentVersion WHERE Id = '{}'",
+                        id
+                    ),
+                    false,
+                ),
+            )
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(SalesforceError::RecordDoesNotExistError)?,
+        )?;
+
+        let mut download = BlobDownload::new(&content_version_type, id, "VersionData")?;
+        download.size = info.content_size;
+        download.checksum = info.checksum;
+
+        Ok(download)
+    }
+
+    /// Download in ranges of `chunk_size` bytes.
+    #[must_use]
+    pub fn with_chunk_size(mut self, chunk_size: u64) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Fetch up to `concurrency` ranges at once.
+    #[must_use]
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// The size of the content in bytes, if known. Otherwise, it is
+    /// learned from the response to the first range.
+    #[must_use]
+    pub fn with_size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Verify the content against `checksum`, a hex-encoded MD5 digest.
+    #[must_use]
+    pub fn with_checksum(mut self, checksum: &str) -> Self {
+        self.checksum = Some(checksum.to_owned());
+        self
+    }
+
+    /// Download the content to `writer`, returning the number of bytes written.
+    pub async fn write_to<W>(&self, conn: &Connection, writer: &mut W) -> Result<u64>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut hasher = Md5::new();
+        let mut written = 0;
+
+        let size = match self.size {
+            Some(size) => size,
+            None => {
+                let first = conn
+                    .execute_raw_request(&BlobRangeRequest::new(&self.url, 0, self.chunk_size))
+                    .await?;
+
+                hasher.update(&first.data);
+                writer.write_all(&first.data).await?;
+                written = first.data.len() as u64;
+
+                // Without a Content-Range, the whole content was returned.
+                first.total.unwrap_or(written)
+            }
+        };
+
+        let chunk_size = self.chunk_size;
+        let mut chunks = stream::iter((written..size).step_by(chunk_size as usize))
+            .map(|start| {
+                let request =
+                    BlobRangeRequest::new(&self.url, start, (start + chunk_size).min(size));
+                async move { conn.execute_raw_request(&request).await }
+            })
+            .buffered(self.concurrency);
+
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk?;
+
+            hasher.update(&chunk.data);
+            writer.write_all(&chunk.data).await?;
+            written += chunk.data.len() as u64;
+        }
+        writer.flush().await?;
+
+        if written != size {
+            return Err(SalesforceError::GeneralError(format!(
+                "Expected {} bytes, but received {}",
+                size, written
+            ))
+            .into());
+        }
+
+        if let Some(checksum) = &self.checksum {
+            let actual = format!("{:x}", hasher.finalize());
+
+            if !actual.eq_ignore_ascii_case(checksum) {
+                return Err(SalesforceError::GeneralError(format!(
+                    "Checksum mismatch: expected {}, but received {}",
+                    checksum, actual
+                ))
+                .into());
+            }
+        }
+
+        Ok(written)
+    }
+
+    /// Download the content to the file at `path`, replacing it if it exists.
+    pub async fn write_to_file(&self, conn: &Connection, path: impl AsRef<Path>) -> Result<u64> {
+        let mut file = tokio::fs::File::create(path).await?;
+
+        self.write_to(conn, &mut file).await
+    }
+}
+
+struct BlobChunk {
+    total: Option<u64>,
+    data: Bytes,
+}
+
+/// Retrieve the bytes `start..end` of blob content.
+struct BlobRangeRequest {
+    url: String,
+    start: u64,
+    end: u64,
+}
+
+impl BlobRangeRequest {
+    fn new(url: &str, start: u64, end: u64) -> BlobRangeRequest {
+        BlobRangeRequest {
+            url: url.to_owned(),
+            start,
+            end,
+        }
+    }
+}
+
+#[async_trait]
+impl SalesforceRawRequest for BlobRangeRequest {
+    type ReturnValue = BlobChunk;
+
+    fn get_url(&self) -> String {
+        self.url.clone()
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    fn get_headers(&self) -> Option<std::collections::HashMap<String, String>> {
+        // HTTP ranges are inclusive.
+        Some(
+            [(
+                header::RANGE.to_string(),
+                format!("bytes={}-{}", self.start, self.end - 1),
+            )]
+            .into_iter()
+            .collect(),
+        )
+    }
+
+    async fn get_result(
+        &self,
+        _conn: &Connection,
+        response: Response,
+    ) -> Result<Self::ReturnValue> {
+        // Content-Range: bytes 0-1023/4096
+        let total = response
+            .headers()
+            .get(header::CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit('/').next())
+            .and_then(|v| v.parse().ok());
+
+        Ok(BlobChunk {
+            total,
+            data: response.bytes().await?,
+        })
+    }
+}
+
+type BodyStream = Pin<Box<dyn Stream<Item = Result<Bytes>> + Send + Sync>>;
+
+/// Create a `ContentVersion` whose `VersionData` is streamed from `content`
+/// in a multipart request.
+pub struct ContentVersionUploadRequest {
+    boundary: String,
+    body: RwLock<Option<BodyStream>>,
+}
+
+impl ContentVersionUploadRequest {
+    /// `fields` holds the new ContentVersion's other fields, such as `Title`
+    /// and `PathOnClient`.
+    pub fn new(
+        fields: &Value,
+        content: impl Stream<Item = Result<Bytes>> + 'static + Send + Sync,
+    ) -> Result<Self> {
+        let mut nonce = [0u8; 16];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| SalesforceError::GeneralError("Unable to generate boundary".into()))?;
+        let boundary = format!(
+            "baris-{}",
+            nonce
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        );
+        let filename = fields
+            .get("PathOnClient")
+            .and_then(|v| v.as_str())
+            .unwrap_or("file")
+            .replace('"', "");
+
+        let head = Bytes::from(format!(
+            "--{b}\r\n\
+            Content-Disposition: form-data; name=\"entity_content\"\r\n\
+            Content-Type: application/json\r\n\r\n\
+            {fields}\r\n\
+            --{b}\r\n\
+            Content-Disposition: form-data; name=\"VersionData\"; filename=\"{filename}\"\r\n\
+            Content-Type: application/octet-stream\r\n\r\n",
+            b = boundary,
+            fields = serde_json::to_string(fields)?,
+            filename = filename,
+        ));
+        let tail = Bytes::from(format!("\r\n--{}--\r\n", boundary));
+
+        Ok(ContentVersionUploadRequest {
+            boundary,
+            body: RwLock::new(Some(Box::pin(
+                stream::once(async { Ok(head) })
+                    .chain(content)
+                    .chain(stream::once(async { Ok(tail) })),
+            ))),
+        })
+    }
+}
+
+#[async_trait]
+impl SalesforceRawRequest for ContentVersionUploadRequest {
+    type ReturnValue = DmlResult;
+
+    fn get_url(&self) -> String {
+        "sobjects/ContentVersion".to_owned()
+    }
+
+    fn get_method(&self) -> Method {
+        Method::POST
+    }
+
+    fn get_body(&self) -> Option<TransportBody> {
+        // Like Bulk API ingest, the body can be sent only once.
+        self.body
+            .write()
+            .unwrap()
+            .take()
+            .map(TransportBody::wrap_stream)
+    }
+
+    fn is_replayable(&self) -> bool {
+        false
+    }
+
+    fn get_mime_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    async fn get_result(
+        &self,
+        _conn: &Connection,
+        response: Response,
+    ) -> Result<Self::ReturnValue> {
+        Ok(response.json().await?)
+    }
+
+    fn get_dry_run_result(&self, conn: &Connection) -> Result<Self::ReturnValue> {
+        Ok(DmlResult::dry_run(Some(conn.get_dry_run_id())))
+    }
+}
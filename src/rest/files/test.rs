@@ -0,0 +1,136 @@
+use anyhow::Result;
+use md5::{Digest, Md5};
+use reqwest::Method;
+
+use super::BlobDownload;
+use crate::prelude::*;
+use crate::test_integration_base::{
+    get_test_field_describe, get_test_sobject_describe, get_test_sobject_type, json_response,
+    raw_response, serve_routes, RouteTransport,
+};
+
+fn get_content() -> Vec<u8> {
+    (0..10_000).map(|i| (i % 251) as u8).collect()
+}
+
+// Serves a ContentVersion describe, its Checksum and ContentSize,
+// and ranges of its content.
+fn get_file_transport(content: Vec<u8>, checksum: String) -> RouteTransport {
+    let size = content.len();
+    RouteTransport::new()
+        .with_route(Method::GET, "/describe", |_| {
+            json_response(
+                200,
+                &get_test_sobject_describe(
+                    "ContentVersion",
+                    vec![
+                        get_test_field_describe("Id", "tns:ID", "id"),
+                        get_test_field_describe("VersionData", "xsd:base64Binary", "base64"),
+                    ],
+                    vec![],
+                    vec![],
+                )?,
+            )
+        })
+        .with_json(
+            Method::GET,
+            "/query",
+            200,
+            serde_json::json!({
+                "totalSize": 1,
+                "done": true,
+                "records": [{
+                    "attributes": {"type": "ContentVersion"},
+                    "Checksum": checksum,
+                    "ContentSize": size
+                }]
+            }),
+        )
+        .with_route(Method::GET, "", move |r| {
+            let range = r.get_header("Range").unwrap();
+            let (start, end) = range.trim_start_matches("bytes=").split_once('-').unwrap();
+            let start: usize = start.parse()?;
+            let end: usize = end.parse::<usize>()?.min(size - 1);
+
+            let mut response = raw_response(206, content[start..=end].to_vec())?;
+            response.headers_mut().insert(
+                "Content-Range",
+                format!("bytes {}-{}/{}", start, end, size).parse()?,
+            );
+            Ok(response)
+        })
+}
+
+fn get_file_connection(checksum: Option<&str>) -> Result<Connection> {
+    let content = get_content();
+    let checksum = match checksum {
+        Some(checksum) => checksum.to_owned(),
+        None => format!("{:x}", Md5::digest(&content)),
+    };
+    let (conn, _) = serve_routes(get_file_transport(content, checksum))?;
+
+    Ok(conn)
+}
+
+#[tokio::test]
+async fn test_content_version_download() -> Result<()> {
+    let conn = get_file_connection(None)?;
+    let id = SalesforceId::new("068000000000001AAA")?;
+
+    let mut content = Vec::new();
+    let written = BlobDownload::content_version(&conn, id)
+        .await?
+        .with_chunk_size(1000)
+        .with_concurrency(4)
+        .write_to(&conn, &mut content)
+        .await?;
+
+    assert_eq!(written, 10_000);
+    assert_eq!(content, get_content());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_blob_download_unknown_size() -> Result<()> {
+    let conn = get_file_connection(None)?;
+    let sobject_type = get_test_sobject_type(
+        "ContentVersion",
+        vec![get_test_field_describe(
+            "VersionData",
+            "xsd:base64Binary",
+            "base64",
+        )],
+        vec![],
+    )?;
+    let download = BlobDownload::new(
+        &sobject_type,
+        SalesforceId::new("068000000000001AAA")?,
+        "VersionData",
+    )?
+    .with_chunk_size(3000);
+
+    let mut content = Vec::new();
+    assert_eq!(download.write_to(&conn, &mut content).await?, 10_000);
+    assert_eq!(content, get_content());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_blob_download_checksum_mismatch() -> Result<()> {
+    let conn = get_file_connection(Some("00000000000000000000000000000000"))?;
+
+    let result = BlobDownload::content_version(&conn, SalesforceId::new("068000000000001AAA")?)
+        .await?
+        .with_chunk_size(4096)
+        .write_to(&conn, &mut Vec::new())
+        .await;
+
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("Checksum mismatch"));
+
+    Ok(())
+}
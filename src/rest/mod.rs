@@ -1,18 +1,26 @@
 use crate::{data::SalesforceId, errors::SalesforceError};
 
-use serde_derive::Deserialize;
+use self::duplicates::DuplicateResult;
+
+use serde_derive::{Deserialize, Serialize};
 use std::error::Error;
 use std::fmt;
 
 use anyhow::Result;
 
+pub mod apex;
 pub mod collections;
 pub mod composite;
 pub mod describe;
+pub mod duplicates;
+pub mod email;
+pub mod files;
+pub mod org;
 pub mod query;
 pub mod rows;
+pub mod ui_api;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiError {
     pub message: String,
@@ -22,6 +30,9 @@ pub struct ApiError {
     // The sObject Collections endpoints use statusCode:
     // https://developer.salesforce.com/docs/atlas.en-us.api_rest.meta/api_rest/resources_composite_sobjects_collections_create.htm
     pub status_code: Option<String>,
+    // Present when a duplicate rule blocked the operation (DUPLICATES_DETECTED).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate_result: Option<DuplicateResult>,
 }
 
 impl ApiError {
@@ -32,6 +43,10 @@ impl ApiError {
             self.status_code.as_ref()
         }
     }
+
+    pub fn get_duplicate_result(&self) -> Option<&DuplicateResult> {
+        self.duplicate_result.as_ref()
+    }
 }
 
 impl fmt::Display for ApiError {
@@ -49,7 +64,7 @@ impl fmt::Display for ApiError {
 impl Error for ApiError {}
 
 // Result structures for DML operations, shared across various APIs.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct DmlError {
     pub fields: Vec<String>,
@@ -61,9 +76,13 @@ impl DmlError {
     pub fn get_error_code(&self) -> Option<&String> {
         self.error.get_error_code()
     }
+
+    pub fn get_duplicate_result(&self) -> Option<&DuplicateResult> {
+        self.error.get_duplicate_result()
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DmlResult {
     pub id: Option<SalesforceId>,
     pub created: Option<bool>,
@@ -71,6 +90,18 @@ pub struct DmlResult {
     pub errors: Vec<DmlError>,
 }
 
+impl DmlResult {
+    /// A successful result for an operation skipped in dry-run mode.
+    pub(crate) fn dry_run(id: Option<SalesforceId>) -> DmlResult {
+        DmlResult {
+            created: Some(id.is_some()),
+            id,
+            success: true,
+            errors: Vec::new(),
+        }
+    }
+}
+
 impl From<DmlResult> for Result<SalesforceId> {
     fn from(val: DmlResult) -> Self {
         if !val.success {
@@ -0,0 +1,184 @@
+//! The org's limits, the API versions it supports, and its recently viewed records.
+//!
+//! Unlike `ApiUsage`, which reflects the `Sforce-Limit-Info` header of the most
+//! recent response, `Connection::get_limits()` reports every org limit, such
+//! as `DataStorageMB` and `DailyBulkV2QueryJobs`, so that heavy jobs can be
+//! scheduled for when the org has room for them.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use reqwest::Method;
+use serde_derive::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    api::{Connection, SalesforceRequest},
+    data::PolymorphicRecord,
+    errors::SalesforceError,
+};
+
+#[cfg(test)]
+mod test;
+
+/// The maximum number of recently viewed records Salesforce returns.
+pub const MAX_RECENT_ITEMS: usize = 200;
+
+/// The allocation and remaining amount of one org limit.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct OrgLimit {
+    pub max: i64,
+    pub remaining: i64,
+}
+
+impl OrgLimit {
+    pub fn get_used(&self) -> i64 {
+        self.max - self.remaining
+    }
+}
+
+/// Get the org's limits, keyed by name, such as `DailyApiRequests`.
+pub struct LimitsRequest {}
+
+impl LimitsRequest {
+    pub fn new() -> LimitsRequest {
+        LimitsRequest {}
+    }
+}
+
+impl Default for LimitsRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SalesforceRequest for LimitsRequest {
+    type ReturnValue = HashMap<String, OrgLimit>;
+
+    fn get_url(&self) -> String {
+        "limits/".to_string()
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(serde_json::from_value(body.clone())?)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+}
+
+/// An API version supported by the org.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ApiVersion {
+    /// The release name, such as `Winter '22`.
+    pub label: String,
+    pub url: String,
+    /// The version number, such as `53.0`.
+    pub version: String,
+}
+
+/// Get the API versions supported by the org, oldest first.
+pub struct ApiVersionsRequest {}
+
+impl ApiVersionsRequest {
+    pub fn new() -> ApiVersionsRequest {
+        ApiVersionsRequest {}
+    }
+}
+
+impl Default for ApiVersionsRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SalesforceRequest for ApiVersionsRequest {
+    type ReturnValue = Vec<ApiVersion>;
+
+    // The versions resource is outside any API version's base URL.
+    fn get_url(&self) -> String {
+        "/services/data/".to_string()
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(serde_json::from_value(body.clone())?)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+}
+
+/// Get the records most recently viewed or referenced by the current user.
+/// Each holds its Id and name.
+pub struct RecentItemsRequest {
+    limit: Option<usize>,
+}
+
+impl RecentItemsRequest {
+    pub fn new(limit: Option<usize>) -> RecentItemsRequest {
+        RecentItemsRequest { limit }
+    }
+}
+
+impl SalesforceRequest for RecentItemsRequest {
+    type ReturnValue = Vec<PolymorphicRecord>;
+
+    fn get_url(&self) -> String {
+        "recent/".to_string()
+    }
+
+    fn get_query_parameters(&self) -> Option<Value> {
+        self.limit.map(|limit| {
+            let mut hm = Map::new();
+
+            hm.insert(
+                "limit".to_string(),
+                Value::String(limit.min(MAX_RECENT_ITEMS).to_string()),
+            );
+
+            Value::Object(hm)
+        })
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(serde_json::from_value(body.clone())?)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+}
+
+impl Connection {
+    pub async fn get_limits(&self) -> Result<HashMap<String, OrgLimit>> {
+        self.execute(&LimitsRequest::new()).await
+    }
+
+    /// Get one org limit, such as `DailyApiRequests`, or `None` if the org has no such limit.
+    pub async fn get_limit(&self, name: &str) -> Result<Option<OrgLimit>> {
+        Ok(self.get_limits().await?.remove(name))
+    }
+
+    pub async fn get_api_versions(&self) -> Result<Vec<ApiVersion>> {
+        self.execute(&ApiVersionsRequest::new()).await
+    }
+
+    pub async fn get_recent_items(&self, limit: Option<usize>) -> Result<Vec<PolymorphicRecord>> {
+        self.execute(&RecentItemsRequest::new(limit)).await
+    }
+}
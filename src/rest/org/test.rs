@@ -0,0 +1,61 @@
+use anyhow::Result;
+
+use crate::api::SalesforceRequest;
+use crate::test_integration_base::serve_responses;
+
+use super::RecentItemsRequest;
+
+#[test]
+fn test_recent_items_limit() {
+    assert_eq!(RecentItemsRequest::new(None).get_query_parameters(), None);
+    assert_eq!(
+        RecentItemsRequest::new(Some(500)).get_query_parameters(),
+        Some(serde_json::json!({"limit": "200"}))
+    );
+}
+
+#[tokio::test]
+async fn test_limits_versions_and_recent_items() -> Result<()> {
+    let (conn, _) = serve_responses(vec![
+        (
+            "200 OK",
+            r#"{
+                "DailyApiRequests": {"Max": 15000, "Remaining": 14998, "Ant Migration Tool": {"Max": 0, "Remaining": 0}},
+                "DataStorageMB": {"Max": 5, "Remaining": 4}
+            }"#,
+        ),
+        (
+            "200 OK",
+            r#"[
+                {"label": "Summer '21", "url": "/services/data/v52.0", "version": "52.0"},
+                {"label": "Winter '22", "url": "/services/data/v53.0", "version": "53.0"}
+            ]"#,
+        ),
+        (
+            "200 OK",
+            r#"[
+                {"attributes": {"type": "Account", "url": "/services/data/v52.0/sobjects/Account/001000000000001AAA"},
+                 "Id": "001000000000001AAA", "Name": "Acme"}
+            ]"#,
+        ),
+    ])
+    .await?;
+
+    let limits = conn.get_limits().await?;
+    let api_requests = limits["DailyApiRequests"];
+    assert_eq!(api_requests.max, 15000);
+    assert_eq!(api_requests.remaining, 14998);
+    assert_eq!(api_requests.get_used(), 2);
+    assert_eq!(limits["DataStorageMB"].remaining, 4);
+
+    let versions = conn.get_api_versions().await?;
+    assert_eq!(versions.len(), 2);
+    assert_eq!(versions[1].version, "53.0");
+
+    let recent = conn.get_recent_items(Some(10)).await?;
+    assert_eq!(recent.len(), 1);
+    assert_eq!(recent[0].get_type(), "Account");
+    assert_eq!(recent[0].get("Name"), Some(&serde_json::json!("Acme")));
+
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use anyhow::Result;
+use itertools::Itertools;
+
+use crate::soql::TypeOf;
+use crate::{api::Connection, data::SObjectType, errors::SalesforceError};
+
+/// The maximum `LIMIT` Salesforce permits on queries using `FIELDS(ALL)` or `FIELDS(CUSTOM)`.
+pub const FIELDS_WILDCARD_MAX_ROWS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldsWildcard {
+    All,
+    Standard,
+    Custom,
+}
+
+impl FieldsWildcard {
+    fn to_soql(self) -> &'static str {
+        match self {
+            FieldsWildcard::All => "FIELDS(ALL)",
+            FieldsWildcard::Standard => "FIELDS(STANDARD)",
+            FieldsWildcard::Custom => "FIELDS(CUSTOM)",
+        }
+    }
+
+    fn is_bounded(self) -> bool {
+        // FIELDS(STANDARD) may be used without a LIMIT.
+        !matches!(self, FieldsWildcard::Standard)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QueryBuilder {
+    sobject: String,
+    fields: Vec<String>,
+    wildcard: Option<FieldsWildcard>,
+    filter: Option<String>,
+    order_by: Option<String>,
+    limit: Option<usize>,
+}
+
+impl QueryBuilder {
+    pub fn new(sobject: &str) -> QueryBuilder {
+        QueryBuilder {
+            sobject: sobject.to_owned(),
+            fields: Vec::new(),
+            wildcard: None,
+            filter: None,
+            order_by: None,
+            limit: None,
+        }
+    }
+
+    #[must_use]
+    pub fn field(mut self, field: &str) -> QueryBuilder {
+        self.fields.push(field.to_owned());
+        self
+    }
+
+    #[must_use]
+    pub fn fields(mut self, fields: &[&str]) -> QueryBuilder {
+        self.fields.extend(fields.iter().map(|f| f.to_string()));
+        self
+    }
+
+    /// Select fields of the polymorphic relationship `type_of.relationship`
+    /// that depend on the type of each related record.
+    #[must_use]
+    pub fn type_of(mut self, type_of: TypeOf) -> QueryBuilder {
+        self.fields.push(type_of.to_string());
+        self
+    }
+
+    #[must_use]
+    pub fn fields_wildcard(mut self, wildcard: FieldsWildcard) -> QueryBuilder {
+        self.wildcard = Some(wildcard);
+        self
+    }
+
+    /// Set the `WHERE` clause, without the `WHERE` keyword.
+    #[must_use]
+    pub fn filter(mut self, filter: &str) -> QueryBuilder {
+        self.filter = Some(filter.to_owned());
+        self
+    }
+
+    /// Set the `ORDER BY` clause, without the `ORDER BY` keywords.
+    #[must_use]
+    pub fn order_by(mut self, order_by: &str) -> QueryBuilder {
+        self.order_by = Some(order_by.to_owned());
+        self
+    }
+
+    #[must_use]
+    pub fn limit(mut self, limit: usize) -> QueryBuilder {
+        self.limit = Some(limit);
+        self
+    }
+
+    fn requires_expansion(&self) -> bool {
+        if let Some(wildcard) = self.wildcard {
+            wildcard.is_bounded()
+                && self
+                    .limit
+                    .is_none_or(|limit| limit > FIELDS_WILDCARD_MAX_ROWS)
+        } else {
+            false
+        }
+    }
+
+    fn build_with_fields(&self, fields: &[String]) -> Result<String> {
+        if fields.is_empty() {
+            return Err(SalesforceError::GeneralError(
+                "A query must select at least one field".to_owned(),
+            )
+            .into());
+        }
+
+        let mut query = format!("SELECT {} FROM {}", fields.join(", "), self.sobject);
+
+        if let Some(filter) = &self.filter {
+            query.push_str(&format!(" WHERE {}", filter));
+        }
+        if let Some(order_by) = &self.order_by {
+            query.push_str(&format!(" ORDER BY {}", order_by));
+        }
+        if let Some(limit) = self.limit {
+            query.push_str(&format!(" LIMIT {}", limit));
+        }
+
+        Ok(query)
+    }
+
+    /// Render this query as SOQL.
+    ///
+    /// Returns an error if `FIELDS(ALL)` or `FIELDS(CUSTOM)` is selected without a
+    /// `LIMIT` of at most 200 rows. Use `build_for_type()` to expand the wildcard
+    /// into an explicit field list in that case.
+    pub fn build(&self) -> Result<String> {
+        if self.requires_expansion() {
+            return Err(SalesforceError::GeneralError(format!(
+                "FIELDS(ALL) and FIELDS(CUSTOM) require a LIMIT of at most {} rows",
+                FIELDS_WILDCARD_MAX_ROWS
+            ))
+            .into());
+        }
+
+        let mut fields = self.fields.clone();
+        if let Some(wildcard) = self.wildcard {
+            fields.push(wildcard.to_soql().to_owned());
+        }
+
+        self.build_with_fields(&fields)
+    }
+
+    /// Render this query as SOQL. If the query selects a `FIELDS()` wildcard
+    /// that cannot be used with its `LIMIT`, the wildcard is replaced with
+    /// the equivalent fields from the describe of `sobject_type`.
+    pub fn build_for_type(&self, sobject_type: &SObjectType) -> Result<String> {
+        if !self.requires_expansion() {
+            return self.build();
+        }
+
+        let custom_only = matches!(self.wildcard, Some(FieldsWildcard::Custom));
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .cloned()
+            .chain(
+                sobject_type
+                    .get_describe()
+                    .get_fields()
+                    .iter()
+                    .filter(|f| !custom_only || f.custom)
+                    .map(|f| f.name.clone()),
+            )
+            .unique_by(|f| f.to_lowercase())
+            .collect();
+
+        self.build_with_fields(&fields)
+    }
+
+    /// Render this query as SOQL, retrieving the describe for the queried sObject
+    /// if needed to expand a `FIELDS()` wildcard.
+    pub async fn build_for_connection(&self, conn: &Connection) -> Result<String> {
+        if self.requires_expansion() {
+            self.build_for_type(&conn.get_type(&self.sobject).await?)
+        } else {
+            self.build()
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use anyhow::Result;
+use reqwest::Method;
+use serde_derive::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    api::{Connection, SalesforceRequest},
+    errors::SalesforceError,
+};
+
+/// A note on why the query optimizer did not consider an index or filter.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryPlanNote {
+    pub description: String,
+    pub fields: Vec<String>,
+    pub table_enum_or_id: String,
+}
+
+/// One way the query optimizer could run a query.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryPlan {
+    /// The estimated number of records the leading operation returns.
+    pub cardinality: u64,
+    /// The indexed fields used by the leading operation.
+    pub fields: Vec<String>,
+    /// `Index`, `Other`, `Sharing`, or `TableScan`.
+    pub leading_operation_type: String,
+    #[serde(default)]
+    pub notes: Vec<QueryPlanNote>,
+    /// The cost of this plan relative to the selectivity threshold.
+    /// Plans costing more than 1.0 are not selective.
+    pub relative_cost: f64,
+    /// The approximate number of records of the queried sObject.
+    pub sobject_cardinality: u64,
+    pub sobject_type: String,
+}
+
+/// The plans the query optimizer considered for a query, cheapest first.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryExplainResult {
+    pub plans: Vec<QueryPlan>,
+    pub source_query: Option<String>,
+}
+
+impl QueryExplainResult {
+    /// The plan the optimizer would use.
+    pub fn get_best_plan(&self) -> Option<&QueryPlan> {
+        self.plans.first()
+    }
+}
+
+/// Get the query plan for a query without running it.
+pub struct QueryExplainRequest {
+    query: String,
+}
+
+impl QueryExplainRequest {
+    pub fn new(query: &str) -> QueryExplainRequest {
+        QueryExplainRequest {
+            query: query.to_owned(),
+        }
+    }
+}
+
+impl SalesforceRequest for QueryExplainRequest {
+    type ReturnValue = QueryExplainResult;
+
+    fn get_query_parameters(&self) -> Option<Value> {
+        let mut hm = Map::new();
+
+        hm.insert("explain".to_string(), Value::String(self.query.clone()));
+
+        Some(Value::Object(hm))
+    }
+
+    fn get_url(&self) -> String {
+        "query".to_string()
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(serde_json::from_value::<QueryExplainResult>(body.clone())?)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+}
+
+impl Connection {
+    /// Get the query plan for `query` without running it, such as to check
+    /// that a large extract is selective before starting it.
+    pub async fn explain(&self, query: &str) -> Result<QueryExplainResult> {
+        self.execute(&QueryExplainRequest::new(query)).await
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use itertools::Itertools;
+use serde_json::Value;
+
+use crate::{
+    api::Connection,
+    data::{SObjectDeserialization, SObjectType, SalesforceId, SingleTypedSObject},
+    errors::SalesforceError,
+};
+
+use super::{query_all_values, QueryRequest};
+
+/// The number of parent Ids included in each `IN` clause while walking a hierarchy.
+const HIERARCHY_CHUNK_SIZE: usize = 200;
+
+/// A record and its descendants in a self-referencing hierarchy,
+/// such as Accounts related by `ParentId`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HierarchyNode<T> {
+    pub id: SalesforceId,
+    pub record: T,
+    pub children: Vec<HierarchyNode<T>>,
+}
+
+impl<T> HierarchyNode<T> {
+    /// The number of records in this node and all of its descendants.
+    pub fn size(&self) -> usize {
+        1 + self.children.iter().map(|c| c.size()).sum::<usize>()
+    }
+}
+
+impl<T> HierarchyNode<T>
+where
+    T: SObjectDeserialization,
+{
+    /// Retrieve the full hierarchies beneath `roots`, querying breadth-first
+    /// for records whose `parent_field` refers to the previous level.
+    pub async fn query(
+        conn: &Connection,
+        sobject_type: &SObjectType,
+        fields: &[&str],
+        parent_field: &str,
+        roots: &[SalesforceId],
+    ) -> Result<Vec<HierarchyNode<T>>> {
+        let api_name = sobject_type.get_api_name();
+        let mut select: Vec<&str> = fields.to_vec();
+        for required in ["Id", parent_field] {
+            if !select.iter().any(|f| f.eq_ignore_ascii_case(required)) {
+                select.push(required);
+            }
+        }
+        let select = select.join(", ");
+
+        let mut rows = Vec::new();
+        let mut seen: HashSet<SalesforceId> = HashSet::new();
+        let mut filter_field = "Id";
+        let mut level: Vec<SalesforceId> = roots.iter().copied().unique().collect();
+
+        while !level.is_empty() {
+            let mut next_level = Vec::new();
+
+            for chunk in level.chunks(HIERARCHY_CHUNK_SIZE) {
+                let query = format!(
+                    "SELECT {} FROM {} WHERE {} IN ({})",
+                    select,
+                    api_name,
+                    filter_field,
+                    chunk.iter().map(|id| format!("'{}'", id)).join(", ")
+                );
+
+                for value in query_all_values(conn, &QueryRequest::new(&query, false)).await? {
+                    let id = get_id_field(&value, "Id")?.ok_or_else(|| {
+                        SalesforceError::GeneralError("Record has no Id".to_owned())
+                    })?;
+                    // Guard against cycles in malformed data.
+                    if seen.insert(id) {
+                        let parent = get_id_field(&value, parent_field)?;
+                        rows.push((id, parent, T::from_value(&value, sobject_type)?));
+                        next_level.push(id);
+                    }
+                }
+            }
+
+            filter_field = parent_field;
+            level = next_level;
+        }
+
+        Ok(build_tree(roots, rows))
+    }
+}
+
+impl<T> HierarchyNode<T>
+where
+    T: SingleTypedSObject + SObjectDeserialization,
+{
+    pub async fn query_t(
+        conn: &Connection,
+        fields: &[&str],
+        parent_field: &str,
+        roots: &[SalesforceId],
+    ) -> Result<Vec<HierarchyNode<T>>> {
+        HierarchyNode::query(
+            conn,
+            &conn.get_type(T::get_type_api_name()).await?,
+            fields,
+            parent_field,
+            roots,
+        )
+        .await
+    }
+}
+
+fn get_id_field(value: &Value, field: &str) -> Result<Option<SalesforceId>> {
+    if let Value::Object(map) = value {
+        match map.iter().find(|(k, _)| k.eq_ignore_ascii_case(field)) {
+            Some((_, Value::String(id))) => Ok(Some(SalesforceId::new(id)?)),
+            _ => Ok(None),
+        }
+    } else {
+        Err(SalesforceError::UnknownError.into())
+    }
+}
+
+/// Assemble `(id, parent id, record)` rows into trees rooted at `roots`.
+/// Rows not reachable from a root are discarded.
+pub(super) fn build_tree<T>(
+    roots: &[SalesforceId],
+    rows: Vec<(SalesforceId, Option<SalesforceId>, T)>,
+) -> Vec<HierarchyNode<T>> {
+    let root_set: HashSet<SalesforceId> = roots.iter().copied().collect();
+    let mut records: HashMap<SalesforceId, T> = HashMap::new();
+    let mut children: HashMap<SalesforceId, Vec<SalesforceId>> = HashMap::new();
+
+    for (id, parent, record) in rows {
+        if let Some(parent) = parent {
+            // A root's own parent lies outside the requested hierarchy.
+            if !root_set.contains(&id) {
+                children.entry(parent).or_default().push(id);
+            }
+        }
+        records.insert(id, record);
+    }
+
+    fn assemble<T>(
+        id: SalesforceId,
+        records: &mut HashMap<SalesforceId, T>,
+        children: &HashMap<SalesforceId, Vec<SalesforceId>>,
+    ) -> Option<HierarchyNode<T>> {
+        let record = records.remove(&id)?;
+
+        Some(HierarchyNode {
+            id,
+            record,
+            children: children
+                .get(&id)
+                .map(|ids| {
+                    ids.iter()
+                        .filter_map(|c| assemble(*c, records, children))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+
+    roots
+        .iter()
+        .unique()
+        .filter_map(|r| assemble(*r, &mut records, &children))
+        .collect()
+}
@@ -1,22 +1,35 @@
-use std::{collections::VecDeque, marker::PhantomData};
+use std::{
+    collections::{HashSet, VecDeque},
+    marker::PhantomData,
+    sync::Arc,
+};
 
 use anyhow::Result;
+use log::warn;
 use reqwest::Method;
+use serde::{de::DeserializeOwned, Deserialize as _};
 use serde_derive::Deserialize;
 use serde_json::{Map, Value};
 use tokio::{spawn, task::JoinHandle};
 
+use crate::api::erased::JsonRequest;
 use crate::{
     api::Connection,
     api::SalesforceRequest,
     data::traits::{SObjectBase, SObjectDeserialization},
     data::SObjectType,
     errors::SalesforceError,
+    soql::{self, Expression, SelectItem},
     streams::{ResultStream, ResultStreamManager, ResultStreamState},
 };
 
+pub mod builder;
+pub mod explain;
+pub mod hierarchy;
 pub mod traits;
 
+pub use explain::{QueryExplainRequest, QueryExplainResult, QueryPlan, QueryPlanNote};
+
 #[cfg(test)]
 mod test;
 
@@ -33,9 +46,157 @@ impl SObjectDeserialization for AggregateResult {
     }
 }
 
+/// Records with more fields than this are decoded with a warning suggesting
+/// a `FieldProjection`.
+pub const WIDE_RECORD_FIELDS: usize = 200;
+
+/// The fields to decode from each query result record. Other fields are
+/// dropped before decoding, so that no conversion work is spent on them.
+///
+/// Fields are matched case-insensitively. A relationship path such as
+/// `Account.Name` retains the whole `Account` relationship.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldProjection {
+    fields: HashSet<String>,
+}
+
+impl FieldProjection {
+    pub fn new(fields: &[&str]) -> FieldProjection {
+        FieldProjection {
+            fields: fields
+                .iter()
+                .map(|f| f.split('.').next().unwrap_or(f).to_lowercase())
+                .collect(),
+        }
+    }
+
+    pub fn contains(&self, field: &str) -> bool {
+        self.fields.contains(&field.to_lowercase())
+    }
+
+    /// A copy of `record` holding only the projected fields and its `attributes`.
+    pub fn project(&self, record: &Value) -> Value {
+        match record {
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .filter(|(k, _)| *k == "attributes" || self.contains(k))
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
+
+/// A record returned by a `queryAll` query, with its `IsDeleted` value.
+/// Use with `QueryRequest::with_is_deleted()` or
+/// `QueryableSingleType::query_all_with_deleted_t()`, which select `IsDeleted`.
+pub struct QueryAllRecord<T> {
+    record: T,
+    is_deleted: bool,
+}
+
+impl<T> QueryAllRecord<T> {
+    pub fn get_record(&self) -> &T {
+        &self.record
+    }
+
+    pub fn into_record(self) -> T {
+        self.record
+    }
+
+    /// Whether the record is in the Recycle Bin. `false` if `IsDeleted` was not selected.
+    pub fn is_deleted(&self) -> bool {
+        self.is_deleted
+    }
+}
+
+impl<T> SObjectBase for QueryAllRecord<T> where T: SObjectBase {}
+
+impl<T> SObjectDeserialization for QueryAllRecord<T>
+where
+    T: SObjectDeserialization,
+{
+    fn from_value(value: &Value, sobjecttype: &SObjectType) -> Result<Self> {
+        Ok(QueryAllRecord {
+            record: T::from_value(value, sobjecttype)?,
+            is_deleted: value
+                .get("IsDeleted")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+        })
+    }
+}
+
+/// Add `IsDeleted` to the top-level field list of `query` if it is not already selected.
+pub(crate) fn select_is_deleted(query: &str) -> String {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut in_fields = false;
+    let mut field_start = 0;
+    let mut fields = Vec::new();
+    let bytes = query.as_bytes();
+
+    for (i, c) in query.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '\'' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match c {
+            '\'' => in_string = true,
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 && in_fields => {
+                fields.push(&query[field_start..i]);
+                field_start = i + 1;
+            }
+            _ if depth == 0 && (i == 0 || bytes[i - 1].is_ascii_whitespace()) => {
+                let rest = &query[i..];
+                // `get()`, since the keyword's length may not fall on a character boundary.
+                let is_keyword = |keyword: &str| {
+                    rest.get(..keyword.len())
+                        .is_some_and(|word| word.eq_ignore_ascii_case(keyword))
+                        && rest
+                            .as_bytes()
+                            .get(keyword.len())
+                            .is_some_and(|c| c.is_ascii_whitespace())
+                };
+
+                if !in_fields && is_keyword("SELECT") {
+                    in_fields = true;
+                    field_start = i + "SELECT".len();
+                } else if in_fields && is_keyword("FROM") {
+                    fields.push(&query[field_start..i]);
+
+                    if fields
+                        .iter()
+                        .any(|f| f.trim().eq_ignore_ascii_case("IsDeleted"))
+                    {
+                        return query.to_owned();
+                    }
+
+                    return format!("{}, IsDeleted {}", query[..i].trim_end(), &query[i..]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    query.to_owned()
+}
+
 pub struct QueryRequest {
     query: String,
     all: bool,
+    tooling: bool,
 }
 
 impl QueryRequest {
@@ -43,7 +204,26 @@ impl QueryRequest {
         QueryRequest {
             query: query.to_owned(),
             all,
+            tooling: false,
+        }
+    }
+
+    /// Select `IsDeleted` in a `queryAll` query so that recycled records can be
+    /// distinguished, unless the query already selects it. Has no effect
+    /// unless `all` is set. Not suitable for aggregate queries.
+    #[must_use]
+    pub fn with_is_deleted(mut self) -> QueryRequest {
+        if self.all {
+            self.query = select_is_deleted(&self.query);
         }
+        self
+    }
+
+    /// Run this query against the Tooling API rather than the REST API.
+    #[must_use]
+    pub fn with_tooling_api(mut self) -> QueryRequest {
+        self.tooling = true;
+        self
     }
 }
 
@@ -59,10 +239,12 @@ impl SalesforceRequest for QueryRequest {
     }
 
     fn get_url(&self) -> String {
-        if self.all {
-            "queryAll".to_string()
+        let endpoint = if self.all { "queryAll" } else { "query" };
+
+        if self.tooling {
+            format!("tooling/{}", endpoint)
         } else {
-            "query".to_string()
+            endpoint.to_string()
         }
     }
 
@@ -79,6 +261,89 @@ impl SalesforceRequest for QueryRequest {
     }
 }
 
+/// Count the records matching a `SELECT COUNT()` query, reading only the
+/// total size from the response.
+pub struct QueryCountRequest {
+    query: String,
+    all: bool,
+}
+
+impl QueryCountRequest {
+    pub fn new(query: &str, all: bool) -> QueryCountRequest {
+        QueryCountRequest {
+            query: query.to_owned(),
+            all,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueryCountResult {
+    total_size: usize,
+}
+
+impl SalesforceRequest for QueryCountRequest {
+    type ReturnValue = usize;
+
+    fn get_query_parameters(&self) -> Option<Value> {
+        let mut hm = Map::new();
+
+        hm.insert("q".to_string(), Value::String(self.query.clone()));
+
+        Some(Value::Object(hm))
+    }
+
+    fn get_url(&self) -> String {
+        if self.all { "queryAll" } else { "query" }.to_string()
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(QueryCountResult::deserialize(body)?.total_size)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+}
+
+/// Rewrite `query` to select `COUNT()`, dropping its ordering, which
+/// `COUNT()` does not permit. Grouped queries cannot be rewritten.
+pub(crate) fn to_count_query(query: &str) -> Result<String> {
+    let mut query = soql::parse(query)?;
+
+    if !query.group_by.is_empty() {
+        return Err(SalesforceError::GeneralError(
+            "Grouped queries cannot be counted with COUNT()".to_owned(),
+        )
+        .into());
+    }
+
+    query.select = vec![SelectItem::Expression {
+        expression: Expression::Function {
+            name: "COUNT".to_owned(),
+            args: Vec::new(),
+        },
+        alias: None,
+    }];
+    query.order_by.clear();
+
+    Ok(query.to_string())
+}
+
+impl Connection {
+    /// Count the records `query` would return, without retrieving them.
+    /// The query's SELECT list is replaced with `COUNT()`.
+    pub async fn count(&self, query: &str) -> Result<usize> {
+        self.execute(&QueryCountRequest::new(&to_count_query(query)?, false))
+            .await
+    }
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct QueryResult {
@@ -89,6 +354,38 @@ pub struct QueryResult {
 }
 
 impl QueryResult {
+    /// Extend `sobject_type` with the describes of the types of the parent and
+    /// child records nested in these results, through `conn`'s describe cache.
+    pub(crate) async fn resolve_related_types(
+        &self,
+        conn: &Connection,
+        sobject_type: &SObjectType,
+    ) -> Result<SObjectType> {
+        let mut names = HashSet::new();
+        for record in self.records.iter() {
+            collect_nested_types(record, &mut names);
+        }
+
+        if names
+            .iter()
+            .all(|name| sobject_type.get_related_type(name).is_some())
+        {
+            return Ok(sobject_type.clone());
+        }
+
+        // Nested records are decoded with their own types, so each needs the others.
+        let mut related_types = Vec::with_capacity(names.len());
+        for name in names.iter() {
+            related_types.push(conn.get_type(name).await?);
+        }
+        let related_types: Vec<SObjectType> = related_types
+            .iter()
+            .map(|t| t.with_related_types(&related_types))
+            .collect();
+
+        Ok(sobject_type.with_related_types(&related_types))
+    }
+
     pub fn to_result_stream<T>(
         self,
         conn: &Connection,
@@ -97,19 +394,48 @@ impl QueryResult {
     where
         T: SObjectDeserialization + Sync + Send + Unpin + 'static,
     {
+        self.to_projected_result_stream(conn, sobject_type, None)
+    }
+
+    /// Stream the results, decoding only the fields in `projection`, if given.
+    pub fn to_projected_result_stream<T>(
+        self,
+        conn: &Connection,
+        sobject_type: &SObjectType,
+        projection: Option<FieldProjection>,
+    ) -> Result<ResultStream<T>>
+    where
+        T: SObjectDeserialization + Sync + Send + Unpin + 'static,
+    {
+        let projection = projection.map(Arc::new);
+
+        if projection.is_none() {
+            if let Some(Value::Object(first)) = self.records.first() {
+                if first.len() > WIDE_RECORD_FIELDS {
+                    warn!(
+                        "Decoding {} records with {} fields each; consider a FieldProjection",
+                        sobject_type,
+                        first.len()
+                    );
+                }
+            }
+        }
+
         Ok(ResultStream::new(
-            Some(self.to_result_stream_state(sobject_type)?),
+            Some(self.to_result_stream_state(sobject_type, projection.as_deref())?),
             Box::new(QueryStreamLocatorManager {
                 conn: conn.clone(),
                 sobject_type: sobject_type.clone(),
+                projection,
                 phantom: PhantomData,
             }),
         ))
     }
 
-    pub(crate) fn to_result_stream_state<T>(
+    fn to_result_stream_state<T>(
         self,
         sobject_type: &SObjectType,
+        projection: Option<&FieldProjection>,
     ) -> Result<ResultStreamState<T>>
     where
         T: SObjectDeserialization + Sync + Send + Unpin + 'static,
@@ -117,7 +443,10 @@ impl QueryResult {
         Ok(ResultStreamState::new(
             self.records
                 .iter()
-                .map(|r| T::from_value(r, sobject_type))
+                .map(|r| match projection {
+                    Some(projection) => T::from_value(&projection.project(r), sobject_type),
+                    None => T::from_value(r, sobject_type),
+                })
                 .collect::<Result<VecDeque<T>>>()?,
             self.next_records_url,
             Some(self.total_size),
@@ -126,9 +455,105 @@ impl QueryResult {
     }
 }
 
+// Collect the types of the parent and subquery records nested in `record`.
+fn collect_nested_types(record: &Value, names: &mut HashSet<String>) {
+    let fields = match record.as_object() {
+        Some(fields) => fields,
+        None => return,
+    };
+
+    for (name, value) in fields.iter() {
+        if name == "attributes" {
+            continue;
+        }
+
+        if let Some(records) = value.get("records").and_then(|r| r.as_array()) {
+            for child in records.iter() {
+                if let Some(type_name) = child.pointer("/attributes/type").and_then(|t| t.as_str())
+                {
+                    names.insert(type_name.to_owned());
+                }
+                collect_nested_types(child, names);
+            }
+        } else if let Some(type_name) = value.pointer("/attributes/type").and_then(|t| t.as_str()) {
+            names.insert(type_name.to_owned());
+            collect_nested_types(value, names);
+        }
+    }
+}
+
+/// Resume retrieving query results at `locator`, a `nextRecordsUrl` such as the
+/// locator carried by a `SalesforceError::ResultPageError`.
+pub fn resume_query_stream<T>(
+    conn: &Connection,
+    sobject_type: &SObjectType,
+    locator: String,
+) -> ResultStream<T>
+where
+    T: SObjectDeserialization + Sync + Send + Unpin + 'static,
+{
+    ResultStream::new(
+        Some(ResultStreamState::new(
+            VecDeque::new(),
+            Some(locator),
+            None,
+            false,
+        )),
+        Box::new(QueryStreamLocatorManager {
+            conn: conn.clone(),
+            sobject_type: sobject_type.clone(),
+            projection: None,
+            phantom: PhantomData,
+        }),
+    )
+}
+
+/// Quote and escape a value for use as a string literal in SOQL.
+pub(crate) fn quote_soql_string(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Execute `request` and retrieve every page of its results as raw records.
+pub(crate) async fn query_all_values(
+    conn: &Connection,
+    request: &QueryRequest,
+) -> Result<Vec<Value>> {
+    let mut result = conn.execute(request).await?;
+    let mut records = Vec::new();
+
+    loop {
+        records.append(&mut result.records);
+
+        match result.next_records_url.take() {
+            Some(locator) if !result.done => {
+                result = serde_json::from_value(
+                    conn.execute(&JsonRequest::new(Method::GET, &locator))
+                        .await?,
+                )?;
+            }
+            _ => break,
+        }
+    }
+
+    Ok(records)
+}
+
+/// Execute `request` and deserialize every record of every page as a `T`.
+pub(crate) async fn query_all_as<T: DeserializeOwned>(
+    conn: &Connection,
+    request: &QueryRequest,
+) -> Result<Vec<T>> {
+    Ok(query_all_values(conn, request)
+        .await?
+        .into_iter()
+        .map(serde_json::from_value)
+        .collect::<Result<Vec<T>, _>>()?)
+}
+
 struct QueryStreamLocatorManager<T: SObjectDeserialization + Unpin> {
     conn: Connection,
     sobject_type: SObjectType,
+    projection: Option<Arc<FieldProjection>>,
     phantom: PhantomData<T>,
 }
 
@@ -144,18 +569,16 @@ where
     ) -> JoinHandle<Result<ResultStreamState<T>>> {
         let conn = self.conn.clone();
         let sobject_type = self.sobject_type.clone();
+        let projection = self.projection.clone();
         spawn(async move {
             let locator = state.unwrap().locator.unwrap();
-            let result: QueryResult = conn
-                .get_client()
-                .await?
-                .get(conn.get_instance_url().await?.join(&locator)?)
-                .send()
-                .await?
-                .json()
-                .await?;
-
-            result.to_result_stream_state(&sobject_type)
+            let result: QueryResult = serde_json::from_value(
+                conn.execute(&JsonRequest::new(Method::GET, &locator))
+                    .await?,
+            )?;
+            let sobject_type = result.resolve_related_types(&conn, &sobject_type).await?;
+
+            result.to_result_stream_state(&sobject_type, projection.as_deref())
         })
     }
 }
@@ -1 +1,477 @@
+use anyhow::Result;
+use reqwest::Method;
+use serde_json::json;
+use tokio_stream::StreamExt;
 
+use super::builder::{FieldsWildcard, QueryBuilder};
+use super::hierarchy::build_tree;
+use super::{
+    quote_soql_string, select_is_deleted, to_count_query, FieldProjection, QueryAllRecord,
+    QueryCountRequest, QueryRequest,
+};
+use crate::api::SalesforceRequest;
+use crate::data::traits::SObjectDeserialization;
+use crate::data::SalesforceId;
+use crate::prelude::*;
+use crate::test_integration_base::{
+    get_test_field_describe, get_test_sobject_describe, get_test_sobject_type, json_response,
+    serve_responses, serve_routes, Account, RouteTransport,
+};
+
+#[test]
+fn test_query_builder_explicit_fields() -> Result<()> {
+    assert_eq!(
+        QueryBuilder::new("Account")
+            .fields(&["Id", "Name"])
+            .filter("Name != null")
+            .order_by("Name")
+            .limit(10)
+            .build()?,
+        "SELECT Id, Name FROM Account WHERE Name != null ORDER BY Name LIMIT 10"
+    );
+    assert!(QueryBuilder::new("Account").build().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_query_builder_type_of() -> Result<()> {
+    assert_eq!(
+        QueryBuilder::new("Task")
+            .field("Subject")
+            .type_of(
+                crate::soql::TypeOf::new("What")
+                    .when("Account", &["Name"])
+                    .otherwise(&["Id"])
+            )
+            .build()?,
+        "SELECT Subject, TYPEOF What WHEN Account THEN Name ELSE Id END FROM Task"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_query_builder_fields_wildcard_bounds() -> Result<()> {
+    assert_eq!(
+        QueryBuilder::new("Account")
+            .fields_wildcard(FieldsWildcard::All)
+            .limit(200)
+            .build()?,
+        "SELECT FIELDS(ALL) FROM Account LIMIT 200"
+    );
+    assert_eq!(
+        QueryBuilder::new("Account")
+            .fields_wildcard(FieldsWildcard::Standard)
+            .build()?,
+        "SELECT FIELDS(STANDARD) FROM Account"
+    );
+    assert!(QueryBuilder::new("Account")
+        .fields_wildcard(FieldsWildcard::All)
+        .build()
+        .is_err());
+    assert!(QueryBuilder::new("Account")
+        .fields_wildcard(FieldsWildcard::Custom)
+        .limit(201)
+        .build()
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_query_builder_fields_wildcard_expansion() -> Result<()> {
+    let mut custom_field = get_test_field_describe("Ext__c", "xsd:string", "string");
+    custom_field["custom"] = json!(true);
+    let sobject_type = get_test_sobject_type(
+        "Account",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            get_test_field_describe("Name", "xsd:string", "string"),
+            custom_field,
+        ],
+        vec![],
+    )?;
+
+    assert_eq!(
+        QueryBuilder::new("Account")
+            .fields_wildcard(FieldsWildcard::All)
+            .limit(10)
+            .build_for_type(&sobject_type)?,
+        "SELECT FIELDS(ALL) FROM Account LIMIT 10"
+    );
+    assert_eq!(
+        QueryBuilder::new("Account")
+            .fields_wildcard(FieldsWildcard::All)
+            .build_for_type(&sobject_type)?,
+        "SELECT Id, Name, Ext__c FROM Account"
+    );
+    assert_eq!(
+        QueryBuilder::new("Account")
+            .field("id")
+            .fields_wildcard(FieldsWildcard::Custom)
+            .limit(500)
+            .build_for_type(&sobject_type)?,
+        "SELECT id, Ext__c FROM Account LIMIT 500"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_hierarchy_build_tree() -> Result<()> {
+    let root = SalesforceId::new("0013600000AAAAA")?;
+    let root_parent = SalesforceId::new("0013600000ZZZZZ")?;
+    let child_a = SalesforceId::new("0013600000BBBBB")?;
+    let child_b = SalesforceId::new("0013600000CCCCC")?;
+    let grandchild = SalesforceId::new("0013600000DDDDD")?;
+    let orphan = SalesforceId::new("0013600000EEEEE")?;
+
+    let tree = build_tree(
+        &[root],
+        vec![
+            (root, Some(root_parent), "root"),
+            (child_a, Some(root), "a"),
+            (child_b, Some(root), "b"),
+            (grandchild, Some(child_a), "grandchild"),
+            (orphan, Some(root_parent), "orphan"),
+        ],
+    );
+
+    assert_eq!(tree.len(), 1);
+    assert_eq!(tree[0].record, "root");
+    assert_eq!(tree[0].size(), 4);
+    assert_eq!(
+        tree[0]
+            .children
+            .iter()
+            .map(|c| c.record)
+            .collect::<Vec<&str>>(),
+        vec!["a", "b"]
+    );
+    assert_eq!(tree[0].children[0].children[0].id, grandchild);
+    assert!(tree[0].children[1].children.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_quote_soql_string() {
+    assert_eq!(quote_soql_string("test@example.com"), "'test@example.com'");
+    assert_eq!(quote_soql_string("o'brien"), "'o\\'brien'");
+    assert_eq!(quote_soql_string("back\\slash"), "'back\\\\slash'");
+}
+
+#[test]
+fn test_select_is_deleted() {
+    assert_eq!(
+        select_is_deleted("SELECT Id, Name FROM Account"),
+        "SELECT Id, Name, IsDeleted FROM Account"
+    );
+    assert_eq!(
+        select_is_deleted("SELECT Id, isdeleted FROM Account"),
+        "SELECT Id, isdeleted FROM Account"
+    );
+    assert_eq!(
+        select_is_deleted(
+            "select Id, (SELECT Id, IsDeleted FROM Contacts) from Account WHERE Name = 'x FROM y'"
+        ),
+        "select Id, (SELECT Id, IsDeleted FROM Contacts), IsDeleted from Account WHERE Name = 'x FROM y'"
+    );
+    assert_eq!(select_is_deleted("not a query"), "not a query");
+    // Multi-byte characters may straddle the length of a keyword.
+    assert_eq!(
+        select_is_deleted("SELECT Ünï__c FROM Account"),
+        "SELECT Ünï__c, IsDeleted FROM Account"
+    );
+    assert_eq!(select_is_deleted("Sélect"), "Sélect");
+}
+
+#[test]
+fn test_query_request_with_is_deleted() {
+    let request = QueryRequest::new("SELECT Id FROM Account", true).with_is_deleted();
+    assert_eq!(
+        request.get_query_parameters().unwrap()["q"],
+        "SELECT Id, IsDeleted FROM Account"
+    );
+
+    let request = QueryRequest::new("SELECT Id FROM Account", false).with_is_deleted();
+    assert_eq!(
+        request.get_query_parameters().unwrap()["q"],
+        "SELECT Id FROM Account"
+    );
+}
+
+#[test]
+fn test_query_all_record_is_deleted() -> Result<()> {
+    let sobject_type = get_test_sobject_type(
+        "Account",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            get_test_field_describe("Name", "xsd:string", "string"),
+            get_test_field_describe("IsDeleted", "xsd:boolean", "boolean"),
+        ],
+        vec![],
+    )?;
+    let value = json!({"Id": "001000000000000AAA", "Name": "Test", "IsDeleted": true});
+
+    let record = QueryAllRecord::<Account>::from_value(&value, &sobject_type)?;
+    assert!(record.is_deleted());
+    assert_eq!(record.get_record().name, "Test");
+
+    let record = QueryAllRecord::<Account>::from_value(&json!({"Name": "Test"}), &sobject_type)?;
+    assert!(!record.is_deleted());
+
+    let sobject = SObject::from_value(&value, &sobject_type)?;
+    assert_eq!(sobject.is_deleted(), Some(true));
+    let sobject = SObject::from_value(&json!({"Name": "Test"}), &sobject_type)?;
+    assert_eq!(sobject.is_deleted(), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_field_projection() {
+    let projection = FieldProjection::new(&["Name", "Owner.Email"]);
+    let record = json!({
+        "attributes": {"type": "Account"},
+        "Id": "001000000000000AAA",
+        "name": "Test",
+        "Description": "Long text",
+        "Owner": {"attributes": {"type": "User"}, "Email": "a@example.com"}
+    });
+
+    assert!(projection.contains("NAME"));
+    assert!(!projection.contains("Owner.Email"));
+    assert_eq!(
+        projection.project(&record),
+        json!({
+            "attributes": {"type": "Account"},
+            "name": "Test",
+            "Owner": {"attributes": {"type": "User"}, "Email": "a@example.com"}
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_query_projected() -> Result<()> {
+    let sobject_type = get_test_sobject_type(
+        "Account",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            get_test_field_describe("Name", "xsd:string", "string"),
+            get_test_field_describe("Description", "xsd:string", "textarea"),
+        ],
+        vec![],
+    )?;
+    let (conn, _) = serve_responses(vec![
+        (
+            "200 OK",
+            r#"{"totalSize": 2, "done": false, "nextRecordsUrl": "/services/data/v52.0/query/01g-2000", "records": [
+                {"attributes": {"type": "Account"}, "Id": "001000000000000AAA", "Name": "One", "Description": "First"}
+            ]}"#,
+        ),
+        (
+            "200 OK",
+            r#"{"totalSize": 2, "done": true, "records": [
+                {"attributes": {"type": "Account"}, "Id": "001000000000001AAA", "Name": "Two", "Description": "Second"}
+            ]}"#,
+        ),
+    ])
+    .await?;
+
+    let records = SObject::query_projected(
+        &conn,
+        &sobject_type,
+        "SELECT Id, Name, Description FROM Account",
+        false,
+        FieldProjection::new(&["Id", "Name"]),
+    )
+    .await?
+    .collect::<Result<Vec<SObject>>>()
+    .await?;
+
+    assert_eq!(records.len(), 2);
+    for record in &records {
+        assert!(record.get("Name").is_some());
+        assert!(record.get("Description").is_none());
+    }
+    assert_eq!(
+        records[1].get("Name"),
+        Some(&FieldValue::String("Two".to_owned()))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_to_count_query() -> Result<()> {
+    assert_eq!(
+        to_count_query("SELECT Id, Name FROM Account WHERE Name != null ORDER BY Name LIMIT 10")?,
+        "SELECT COUNT() FROM Account WHERE Name != null LIMIT 10"
+    );
+    assert_eq!(
+        to_count_query("SELECT COUNT() FROM Contact")?,
+        "SELECT COUNT() FROM Contact"
+    );
+    assert!(to_count_query("SELECT Industry, COUNT(Id) FROM Account GROUP BY Industry").is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_count_and_explain() -> Result<()> {
+    let (conn, _) = serve_responses(vec![
+        ("200 OK", r#"{"totalSize": 1234, "done": true, "records": []}"#),
+        (
+            "200 OK",
+            r#"{"plans": [
+                {"cardinality": 1, "fields": ["Name"], "leadingOperationType": "Index", "notes": [],
+                 "relativeCost": 0.1, "sobjectCardinality": 5000, "sobjectType": "Account"},
+                {"cardinality": 5000, "fields": [], "leadingOperationType": "TableScan",
+                 "notes": [{"description": "Not considering filter for optimization because unindexed",
+                            "fields": ["IsDeleted"], "tableEnumOrId": "Account"}],
+                 "relativeCost": 2.8, "sobjectCardinality": 5000, "sobjectType": "Account"}
+            ], "sourceQuery": "SELECT Id FROM Account WHERE Name = 'Acme'"}"#,
+        ),
+    ])
+    .await?;
+
+    assert_eq!(
+        QueryCountRequest::new("SELECT COUNT() FROM Account", true).get_url(),
+        "queryAll"
+    );
+    assert_eq!(
+        conn.count("SELECT Id FROM Account ORDER BY Name").await?,
+        1234
+    );
+
+    let explain = conn
+        .explain("SELECT Id FROM Account WHERE Name = 'Acme'")
+        .await?;
+    let plan = explain.get_best_plan().unwrap();
+    assert_eq!(plan.leading_operation_type, "Index");
+    assert_eq!(plan.fields, vec!["Name".to_owned()]);
+    assert_eq!(
+        explain.plans[1].notes[0].fields,
+        vec!["IsDeleted".to_owned()]
+    );
+    assert!(explain.plans[1].relative_cost > 1.0);
+
+    Ok(())
+}
+
+// Serves describes of Contact, Account, and User, and two pages of Contacts
+// with their parent Accounts, the second also with each Account's Owner.
+fn get_related_query_transport() -> RouteTransport {
+    RouteTransport::new()
+        .with_route(Method::GET, "/describe", |r| {
+            let name = r
+                .path()
+                .trim_end_matches("/describe")
+                .rsplit('/')
+                .next()
+                .unwrap();
+            let (reference, target) = match name {
+                "Contact" => ("AccountId", "Account"),
+                "Account" => ("OwnerId", "User"),
+                _ => ("ManagerId", "User"),
+            };
+            let mut reference_field = get_test_field_describe(reference, "tns:ID", "reference");
+            reference_field["referenceTo"] = json!([target]);
+            reference_field["relationshipName"] = json!(reference.trim_end_matches("Id"));
+
+            json_response(
+                200,
+                &get_test_sobject_describe(
+                    name,
+                    vec![
+                        get_test_field_describe("Id", "tns:ID", "id"),
+                        get_test_field_describe("Name", "xsd:string", "string"),
+                        reference_field,
+                    ],
+                    vec![],
+                    vec![],
+                )?,
+            )
+        })
+        .with_json(
+            Method::GET,
+            "/query",
+            200,
+            json!({
+                "totalSize": 2,
+                "done": false,
+                "nextRecordsUrl": "/services/data/v52.0/query/01g-2000",
+                "records": [{
+                    "attributes": {"type": "Contact"},
+                    "Name": "One",
+                    "Account": {"attributes": {"type": "Account"}, "Name": "Parent One"}
+                }]
+            }),
+        )
+        .with_json(
+            Method::GET,
+            "/query/01g-2000",
+            200,
+            json!({
+                "totalSize": 2,
+                "done": true,
+                "records": [{
+                    "attributes": {"type": "Contact"},
+                    "Name": "Two",
+                    "Account": {
+                        "attributes": {"type": "Account"},
+                        "Name": "Parent Two",
+                        "Owner": {"attributes": {"type": "User"}, "Name": "Owner Two"}
+                    }
+                }]
+            }),
+        )
+}
+
+#[tokio::test]
+async fn test_query_related_types() -> Result<()> {
+    let (conn, transport) = serve_routes(get_related_query_transport())?;
+
+    let contact_type = conn.get_type("Contact").await?;
+    let records = SObject::query(
+        &conn,
+        &contact_type,
+        "SELECT Name, Account.Name, Account.Owner.Name FROM Contact",
+        false,
+    )
+    .await?
+    .collect::<Result<Vec<SObject>>>()
+    .await?;
+
+    assert_eq!(records.len(), 2);
+    let parent = |record: &SObject| match record.get("Account") {
+        Some(FieldValue::Relationship(account)) => account.clone(),
+        other => panic!("Expected a parent Account, not {:?}", other),
+    };
+    assert_eq!(
+        parent(&records[0]).get("Name"),
+        Some(&FieldValue::String("Parent One".to_owned()))
+    );
+    match parent(&records[1]).get("Owner") {
+        Some(FieldValue::Relationship(owner)) => assert_eq!(
+            owner.get("Name"),
+            Some(&FieldValue::String("Owner Two".to_owned()))
+        ),
+        other => panic!("Expected an Owner, not {:?}", other),
+    }
+
+    // Each type is described once, through the Connection's describe cache.
+    assert_eq!(
+        transport
+            .get_requests()
+            .iter()
+            .filter_map(|r| r.path().strip_suffix("/describe"))
+            .filter_map(|p| p.rsplit('/').next())
+            .collect::<Vec<&str>>(),
+        vec!["Contact", "Account", "User"]
+    );
+
+    Ok(())
+}
@@ -9,11 +9,14 @@ use crate::{
     streams::ResultStream,
 };
 
-use super::{AggregateResult, QueryRequest};
+use super::{AggregateResult, FieldProjection, QueryAllRecord, QueryRequest};
 
 #[async_trait]
 pub trait Queryable: DynamicallyTypedSObject + SObjectDeserialization {
     // TODO: is a default implementation here the right approach, or a blanket impl?
+    /// Run `query`, decoding its records as `sobject_type`. Parent and child
+    /// records of other types, such as `Account.Name` selected from Contact,
+    /// are decoded with describes retrieved through `conn`'s describe cache.
     async fn query(
         conn: &Connection,
         sobject_type: &SObjectType,
@@ -21,11 +24,27 @@ pub trait Queryable: DynamicallyTypedSObject + SObjectDeserialization {
         all: bool,
     ) -> Result<ResultStream<Self>> {
         let request = QueryRequest::new(query, all);
+        let result = conn.execute(&request).await?;
+        let sobject_type = result.resolve_related_types(conn, sobject_type).await?;
 
-        Ok(conn
-            .execute(&request)
-            .await?
-            .to_result_stream(conn, sobject_type)?)
+        Ok(result.to_result_stream(conn, &sobject_type)?)
+    }
+
+    /// Run `query`, decoding only the fields in `projection`. For wide
+    /// sObjects, this avoids converting columns that are not needed.
+    /// Related records are decoded as by `query()`.
+    async fn query_projected(
+        conn: &Connection,
+        sobject_type: &SObjectType,
+        query: &str,
+        all: bool,
+        projection: FieldProjection,
+    ) -> Result<ResultStream<Self>> {
+        let request = QueryRequest::new(query, all);
+        let result = conn.execute(&request).await?;
+        let sobject_type = result.resolve_related_types(conn, sobject_type).await?;
+
+        Ok(result.to_projected_result_stream(conn, &sobject_type, Some(projection))?)
     }
 
     async fn aggregate_query(
@@ -65,13 +84,30 @@ impl<T> Queryable for T where T: DynamicallyTypedSObject + SObjectDeserializatio
 
 #[async_trait]
 pub trait QueryableSingleType: SingleTypedSObject + SObjectDeserialization {
+    /// Run `query`. Related records are decoded as by `Queryable::query()`.
     async fn query_t(conn: &Connection, query: &str, all: bool) -> Result<ResultStream<Self>> {
         let request = QueryRequest::new(query, all);
+        let result = conn.execute(&request).await?;
+        let sobject_type = result
+            .resolve_related_types(conn, &conn.get_type(Self::get_type_api_name()).await?)
+            .await?;
 
-        Ok(conn
-            .execute(&request)
-            .await?
-            .to_result_stream(conn, &conn.get_type(Self::get_type_api_name()).await?)?)
+        Ok(result.to_result_stream(conn, &sobject_type)?)
+    }
+
+    /// Run `query` with `queryAll`, selecting `IsDeleted` so that each
+    /// record reports whether it is in the Recycle Bin.
+    async fn query_all_with_deleted_t(
+        conn: &Connection,
+        query: &str,
+    ) -> Result<ResultStream<QueryAllRecord<Self>>> {
+        let request = QueryRequest::new(query, true).with_is_deleted();
+        let result = conn.execute(&request).await?;
+        let sobject_type = result
+            .resolve_related_types(conn, &conn.get_type(Self::get_type_api_name()).await?)
+            .await?;
+
+        Ok(result.to_result_stream(conn, &sobject_type)?)
     }
 
     async fn aggregate_query_t(
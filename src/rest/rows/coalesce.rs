@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Error, Result};
+use futures::future::{BoxFuture, FutureExt, Shared};
+use reqwest::Method;
+use serde_json::Value;
+
+use crate::api::erased::JsonRequest;
+use crate::api::{Connection, SalesforceRequest};
+use crate::data::SObjectDeserialization;
+
+use super::SObjectRetrieveRequest;
+
+type SharedRetrieve = Shared<BoxFuture<'static, Result<Value, Arc<Error>>>>;
+
+// (sObject, Id, sorted fields), case-insensitively.
+type RetrieveKey = (String, String, Option<Vec<String>>);
+
+/// Shares one retrieve among identical concurrent retrieves, and reuses its
+/// result for `ttl` after it completes. Failed retrieves are not reused.
+pub(crate) struct RetrieveCoalescer {
+    ttl: Duration,
+    retrieves: Mutex<HashMap<RetrieveKey, (SharedRetrieve, Instant)>>,
+}
+
+impl RetrieveCoalescer {
+    pub(crate) fn new(ttl: Duration) -> RetrieveCoalescer {
+        RetrieveCoalescer {
+            ttl,
+            retrieves: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn get_ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    fn get_key<T>(request: &SObjectRetrieveRequest<T>) -> RetrieveKey
+    where
+        T: SObjectDeserialization,
+    {
+        (
+            request.sobject_type.get_api_name().to_lowercase(),
+            request.id.to_string(),
+            request.fields.as_ref().map(|fields| {
+                let mut fields: Vec<String> = fields.iter().map(|f| f.to_lowercase()).collect();
+                fields.sort();
+                fields.dedup();
+                fields
+            }),
+        )
+    }
+
+    fn get_or_start<T>(
+        &self,
+        conn: &Connection,
+        request: &SObjectRetrieveRequest<T>,
+    ) -> SharedRetrieve
+    where
+        T: SObjectDeserialization,
+    {
+        let key = Self::get_key(request);
+        let mut retrieves = self.retrieves.lock().unwrap();
+        let now = Instant::now();
+
+        // Drop completed retrieves that are too old to reuse, and any that failed.
+        retrieves.retain(|_, (retrieve, started)| match retrieve.peek() {
+            None => true,
+            Some(Ok(_)) => now.duration_since(*started) < self.ttl,
+            Some(Err(_)) => false,
+        });
+
+        if let Some((retrieve, _)) = retrieves.get(&key) {
+            return retrieve.clone();
+        }
+
+        let mut json_request = JsonRequest::new(Method::GET, &request.get_url());
+        if let Some(params) = request.get_query_parameters() {
+            json_request = json_request.with_query_parameters(params);
+        }
+        let conn = conn.clone();
+        let retrieve = async move { conn.execute(&json_request).await.map_err(Arc::new) }
+            .boxed()
+            .shared();
+
+        retrieves.insert(key, (retrieve.clone(), now));
+        retrieve
+    }
+
+    pub(crate) async fn retrieve<T>(
+        &self,
+        conn: &Connection,
+        request: &SObjectRetrieveRequest<T>,
+    ) -> Result<T>
+    where
+        T: SObjectDeserialization,
+    {
+        match self.get_or_start(conn, request).await {
+            Ok(value) => T::from_value(&value, &request.sobject_type),
+            Err(error) => {
+                {
+                    let mut retrieves = self.retrieves.lock().unwrap();
+                    let key = Self::get_key(request);
+                    if let Some((retrieve, _)) = retrieves.get(&key) {
+                        if matches!(retrieve.peek(), Some(Err(e)) if Arc::ptr_eq(e, &error)) {
+                            retrieves.remove(&key);
+                        }
+                    }
+                }
+
+                // The last caller to see a failure receives the original error;
+                // others share a copy of its message.
+                Err(Arc::try_unwrap(error).unwrap_or_else(|e| anyhow!("{:#}", e)))
+            }
+        }
+    }
+}
+
+impl Connection {
+    /// Share a single request among identical retrieves (same sObject, Id,
+    /// and fields) made concurrently through this Connection, and reuse its
+    /// result for `ttl` afterwards. Retrieved records may therefore be up to
+    /// `ttl` out of date. If `None`, every retrieve makes its own request.
+    pub fn set_retrieve_coalescing(&self, ttl: Option<Duration>) {
+        *self.retrieve_coalescer.write().unwrap() =
+            ttl.map(|ttl| Arc::new(RetrieveCoalescer::new(ttl)));
+    }
+
+    pub fn get_retrieve_coalescing(&self) -> Option<Duration> {
+        self.retrieve_coalescer
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|c| c.get_ttl())
+    }
+
+    pub(crate) async fn execute_retrieve<T>(&self, request: &SObjectRetrieveRequest<T>) -> Result<T>
+    where
+        T: SObjectDeserialization,
+    {
+        let coalescer = self.retrieve_coalescer.read().unwrap().clone();
+
+        match coalescer {
+            Some(coalescer) => coalescer.retrieve(self, request).await,
+            None => self.execute(request).await,
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::pin::Pin;
 
@@ -5,11 +6,13 @@ use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures::Stream;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use reqwest::Method;
 use reqwest::Response;
 use serde_json::Map;
 use serde_json::Value;
 
+use crate::api::AssignmentRule;
 use crate::api::CompositeFriendlyRequest;
 use crate::api::SalesforceRawRequest;
 use crate::api::SalesforceRequest;
@@ -18,27 +21,57 @@ use crate::data::SObjectDeserialization;
 use crate::data::SObjectRepresentation;
 use crate::data::SObjectSerialization;
 use crate::data::SObjectWithId;
+use crate::data::SoapType;
 use crate::data::TypedSObject;
 use crate::{api::Connection, data::SObjectType, data::SalesforceId, errors::SalesforceError};
 
 use super::DmlError;
 use super::DmlResult;
 
+pub(crate) mod coalesce;
 pub mod traits;
 
 #[cfg(test)]
 mod test;
 
+fn get_sobjects_url(tooling: bool) -> &'static str {
+    if tooling {
+        "tooling/sobjects"
+    } else {
+        "sobjects"
+    }
+}
+
 // SObject Create Requests
 
 pub struct SObjectCreateRequest {
     body: Value,
     api_name: String,
+    assignment_rule: Option<AssignmentRule>,
+    tooling: bool,
 }
 
 impl SObjectCreateRequest {
     pub fn new_raw(body: Value, api_name: String) -> SObjectCreateRequest {
-        SObjectCreateRequest { body, api_name }
+        SObjectCreateRequest {
+            body,
+            api_name,
+            assignment_rule: None,
+            tooling: false,
+        }
+    }
+
+    #[must_use]
+    pub fn with_assignment_rule(mut self, assignment_rule: AssignmentRule) -> Self {
+        self.assignment_rule = Some(assignment_rule);
+        self
+    }
+
+    /// Create a Tooling API sObject, such as a `TraceFlag`, rather than a record.
+    #[must_use]
+    pub fn with_tooling_api(mut self) -> Self {
+        self.tooling = true;
+        self
     }
 
     pub fn new<T>(sobject: &T) -> Result<Self>
@@ -59,10 +92,10 @@ impl SObjectCreateRequest {
             }
         }
 
-        Ok(Self {
-            body: sobject.to_value_with_options(false, false)?,
-            api_name: sobject.get_api_name().to_owned(),
-        })
+        Ok(Self::new_raw(
+            sobject.to_value_with_options(false, false)?,
+            sobject.get_api_name().to_owned(),
+        ))
     }
 }
 
@@ -74,7 +107,7 @@ impl SalesforceRequest for SObjectCreateRequest {
     }
 
     fn get_url(&self) -> String {
-        format!("sobjects/{}/", self.api_name)
+        format!("{}/{}/", get_sobjects_url(self.tooling), self.api_name)
     }
 
     fn get_method(&self) -> Method {
@@ -88,6 +121,14 @@ impl SalesforceRequest for SObjectCreateRequest {
             Err(SalesforceError::ResponseBodyExpected.into())
         }
     }
+
+    fn get_dry_run_result(&self, conn: &Connection) -> Result<Self::ReturnValue> {
+        Ok(DmlResult::dry_run(Some(conn.get_dry_run_id())))
+    }
+
+    fn get_headers(&self) -> Option<HashMap<String, String>> {
+        self.assignment_rule.map(|a| a.get_header())
+    }
 }
 
 impl CompositeFriendlyRequest for SObjectCreateRequest {}
@@ -98,11 +139,32 @@ pub struct SObjectUpdateRequest {
     body: Value,
     api_name: String,
     id: String,
+    assignment_rule: Option<AssignmentRule>,
+    tooling: bool,
 }
 
 impl SObjectUpdateRequest {
     pub fn new_raw(body: Value, api_name: String, id: String) -> SObjectUpdateRequest {
-        SObjectUpdateRequest { body, api_name, id }
+        SObjectUpdateRequest {
+            body,
+            api_name,
+            id,
+            assignment_rule: None,
+            tooling: false,
+        }
+    }
+
+    #[must_use]
+    pub fn with_assignment_rule(mut self, assignment_rule: AssignmentRule) -> Self {
+        self.assignment_rule = Some(assignment_rule);
+        self
+    }
+
+    /// Update a Tooling API sObject rather than a record.
+    #[must_use]
+    pub fn with_tooling_api(mut self) -> Self {
+        self.tooling = true;
+        self
     }
 
     pub fn new<T>(sobject: &T) -> Result<Self>
@@ -137,7 +199,12 @@ impl SalesforceRequest for SObjectUpdateRequest {
     }
 
     fn get_url(&self) -> String {
-        format!("sobjects/{}/{}", self.api_name, self.id)
+        format!(
+            "{}/{}/{}",
+            get_sobjects_url(self.tooling),
+            self.api_name,
+            self.id
+        )
     }
 
     fn get_method(&self) -> Method {
@@ -152,6 +219,14 @@ impl SalesforceRequest for SObjectUpdateRequest {
             Ok(())
         }
     }
+
+    fn get_dry_run_result(&self, _conn: &Connection) -> Result<Self::ReturnValue> {
+        Ok(())
+    }
+
+    fn get_headers(&self) -> Option<HashMap<String, String>> {
+        self.assignment_rule.map(|a| a.get_header())
+    }
 }
 
 impl CompositeFriendlyRequest for SObjectUpdateRequest {}
@@ -162,6 +237,7 @@ pub struct SObjectUpsertRequest {
     api_name: String,
     external_id: String,
     external_id_value: String,
+    assignment_rule: Option<AssignmentRule>,
 }
 
 impl SObjectUpsertRequest {
@@ -176,33 +252,137 @@ impl SObjectUpsertRequest {
             api_name,
             external_id,
             external_id_value,
+            assignment_rule: None,
         }
     }
 
+    #[must_use]
+    pub fn with_assignment_rule(mut self, assignment_rule: AssignmentRule) -> Self {
+        self.assignment_rule = Some(assignment_rule);
+        self
+    }
+
     pub fn new<T>(sobject: &T, external_id: &str) -> Result<SObjectUpsertRequest>
     where
         T: SObjectSerialization + TypedSObject,
     {
-        let s = sobject.to_value()?;
-        if let Value::Object(ref map) = s {
-            let field_value = map.get(external_id);
-            if let Some(field_value) = field_value {
-                let ext_id_value = field_value.to_string();
-                Ok(Self::new_raw(
-                    s,
-                    sobject.get_api_name().to_owned(),
-                    external_id.to_owned(),
-                    ext_id_value, // TODO: does this yield the correct value for all ExtId-capable types?
-                ))
-            } else {
+        let body = sobject.to_value()?;
+        let value = get_external_id_field(&body, external_id)?;
+        let external_id_value = get_external_id_value(value_to_field_value(value))?;
+
+        Ok(Self::new_raw(
+            body,
+            sobject.get_api_name().to_owned(),
+            external_id.to_owned(),
+            external_id_value,
+        ))
+    }
+
+    /// Build an upsert request whose external Id value is interpreted through
+    /// the describe of `sobject_type`. Fails if `external_id` is not an
+    /// external Id field (or `Id`) on `sobject_type`.
+    pub fn new_with_describe<T>(
+        sobject: &T,
+        sobject_type: &SObjectType,
+        external_id: &str,
+    ) -> Result<SObjectUpsertRequest>
+    where
+        T: SObjectSerialization + TypedSObject,
+    {
+        let describe = sobject_type.get_describe();
+        let field_describe = describe.get_field(external_id).ok_or_else(|| {
+            SalesforceError::SchemaError(format!(
+                "Field {} does not exist on {}",
+                external_id,
+                sobject_type.get_api_name()
+            ))
+        })?;
+
+        if !field_describe.external_id && field_describe.name != "Id" {
+            return Err(SalesforceError::SchemaError(format!(
+                "Field {} on {} is not an external Id field",
+                external_id,
+                sobject_type.get_api_name()
+            ))
+            .into());
+        }
+
+        let body = sobject.to_value()?;
+        let value = get_external_id_field(&body, external_id)?;
+        let external_id_value =
+            get_external_id_value(FieldValue::from_json(value, field_describe.soap_type)?)?;
+
+        Ok(Self::new_raw(
+            body,
+            sobject_type.get_api_name().to_owned(),
+            field_describe.name.clone(),
+            external_id_value,
+        ))
+    }
+}
+
+/// Characters escaped in an external Id value placed in a URL path.
+const EXTERNAL_ID_ESCAPES: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+fn get_external_id_field<'a>(body: &'a Value, external_id: &str) -> Result<&'a Value> {
+    let map = body.as_object().ok_or(SalesforceError::UnknownError)?;
+
+    map.iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(external_id))
+        .map(|(_, v)| v)
+        .ok_or_else(|| {
+            SalesforceError::GeneralError("Cannot upsert without a field value.".to_string()).into()
+        })
+}
+
+/// Interpret a serialized value without a describe, as for typed sObjects.
+fn value_to_field_value(value: &Value) -> FieldValue {
+    match value {
+        Value::Null => FieldValue::Null,
+        Value::String(s) => FieldValue::String(s.clone()),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => FieldValue::Integer(i),
+            None => n
+                .as_f64()
+                .map_or_else(|| FieldValue::Any(value.clone()), FieldValue::Double),
+        },
+        _ => FieldValue::Any(value.clone()),
+    }
+}
+
+/// Render an external Id value in its canonical string form.
+fn get_external_id_value(value: FieldValue) -> Result<String> {
+    match value {
+        FieldValue::String(_)
+        | FieldValue::Integer(_)
+        | FieldValue::Double(_)
+        | FieldValue::Id(_)
+        | FieldValue::Date(_)
+        | FieldValue::DateTime(_)
+        | FieldValue::Time(_) => {
+            let external_id_value = value.as_string();
+            if external_id_value.is_empty() {
                 Err(SalesforceError::GeneralError(
                     "Cannot upsert without a field value.".to_string(),
                 )
                 .into())
+            } else {
+                Ok(external_id_value)
             }
-        } else {
-            Err(SalesforceError::UnknownError.into())
         }
+        FieldValue::Null => Err(SalesforceError::GeneralError(
+            "Cannot upsert without a field value.".to_string(),
+        )
+        .into()),
+        _ => Err(SalesforceError::GeneralError(format!(
+            "{:?} cannot be used as an external Id value",
+            value
+        ))
+        .into()),
     }
 }
 
@@ -216,7 +396,9 @@ impl SalesforceRequest for SObjectUpsertRequest {
     fn get_url(&self) -> String {
         format!(
             "sobjects/{}/{}/{}",
-            self.api_name, self.external_id, self.external_id_value
+            self.api_name,
+            self.external_id,
+            utf8_percent_encode(&self.external_id_value, EXTERNAL_ID_ESCAPES)
         )
     }
 
@@ -231,6 +413,14 @@ impl SalesforceRequest for SObjectUpsertRequest {
             Err(SalesforceError::ResponseBodyExpected.into())
         }
     }
+
+    fn get_dry_run_result(&self, conn: &Connection) -> Result<Self::ReturnValue> {
+        Ok(DmlResult::dry_run(Some(conn.get_dry_run_id())))
+    }
+
+    fn get_headers(&self) -> Option<HashMap<String, String>> {
+        self.assignment_rule.map(|a| a.get_header())
+    }
 }
 
 impl CompositeFriendlyRequest for SObjectUpsertRequest {}
@@ -240,11 +430,23 @@ impl CompositeFriendlyRequest for SObjectUpsertRequest {}
 pub struct SObjectDeleteRequest {
     api_name: String,
     id: String,
+    tooling: bool,
 }
 
 impl SObjectDeleteRequest {
     pub fn new_raw(api_name: String, id: String) -> SObjectDeleteRequest {
-        SObjectDeleteRequest { api_name, id }
+        SObjectDeleteRequest {
+            api_name,
+            id,
+            tooling: false,
+        }
+    }
+
+    /// Delete a Tooling API sObject rather than a record.
+    #[must_use]
+    pub fn with_tooling_api(mut self) -> Self {
+        self.tooling = true;
+        self
     }
 
     pub fn new<T>(sobject: &T) -> Result<SObjectDeleteRequest>
@@ -274,7 +476,12 @@ impl SalesforceRequest for SObjectDeleteRequest {
     type ReturnValue = ();
 
     fn get_url(&self) -> String {
-        format!("sobjects/{}/{}", self.api_name, self.id)
+        format!(
+            "{}/{}/{}",
+            get_sobjects_url(self.tooling),
+            self.api_name,
+            self.id
+        )
     }
 
     fn get_method(&self) -> Method {
@@ -289,6 +496,10 @@ impl SalesforceRequest for SObjectDeleteRequest {
             Ok(())
         }
     }
+
+    fn get_dry_run_result(&self, _conn: &Connection) -> Result<Self::ReturnValue> {
+        Ok(())
+    }
 }
 
 impl CompositeFriendlyRequest for SObjectDeleteRequest {}
@@ -302,6 +513,7 @@ where
     id: SalesforceId,
     sobject_type: SObjectType,
     fields: Option<Vec<String>>,
+    tooling: bool,
     phantom: PhantomData<T>,
 }
 
@@ -318,9 +530,18 @@ where
             id,
             sobject_type: sobject_type.clone(),
             fields,
+            tooling: false,
             phantom: PhantomData,
         }
     }
+
+    /// Retrieve a Tooling API sObject. `sobject_type` should be
+    /// the type's Tooling API describe.
+    #[must_use]
+    pub fn with_tooling_api(mut self) -> Self {
+        self.tooling = true;
+        self
+    }
 }
 
 impl<T> SalesforceRequest for SObjectRetrieveRequest<T>
@@ -330,7 +551,12 @@ where
     type ReturnValue = T;
 
     fn get_url(&self) -> String {
-        format!("sobjects/{}/{}/", self.sobject_type.get_api_name(), self.id)
+        format!(
+            "{}/{}/{}/",
+            get_sobjects_url(self.tooling),
+            self.sobject_type.get_api_name(),
+            self.id
+        )
     }
 
     fn get_query_parameters(&self) -> Option<Value> {
@@ -372,7 +598,7 @@ impl BlobRetrieveRequest {
 
 #[async_trait]
 impl SalesforceRawRequest for BlobRetrieveRequest {
-    type ReturnValue = Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>>>>;
+    type ReturnValue = Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>;
 
     fn get_url(&self) -> String {
         self.path.clone()
@@ -390,3 +616,79 @@ impl SalesforceRawRequest for BlobRetrieveRequest {
         Ok(Box::pin(response.bytes_stream()))
     }
 }
+
+/// The content of a blob field, along with the metadata Salesforce reports for it.
+pub struct BlobContent {
+    pub content_type: Option<String>,
+    pub content_length: Option<u64>,
+    pub stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
+}
+
+/// Retrieve the content of a blob field, such as `ContentVersion.VersionData`,
+/// by sObject Id and field name.
+pub struct SObjectBlobFieldRequest {
+    api_name: String,
+    id: SalesforceId,
+    field: String,
+}
+
+impl SObjectBlobFieldRequest {
+    pub fn new(
+        sobject_type: &SObjectType,
+        id: SalesforceId,
+        field: &str,
+    ) -> Result<SObjectBlobFieldRequest> {
+        let describe = sobject_type.get_describe();
+        let field_describe = describe.get_field(field).ok_or_else(|| {
+            SalesforceError::SchemaError(format!(
+                "Field {} does not exist on {}",
+                field,
+                sobject_type.get_api_name()
+            ))
+        })?;
+
+        if field_describe.soap_type != SoapType::Blob {
+            return Err(SalesforceError::SchemaError(format!(
+                "Field {} on {} is not a blob field",
+                field,
+                sobject_type.get_api_name()
+            ))
+            .into());
+        }
+
+        Ok(SObjectBlobFieldRequest {
+            api_name: sobject_type.get_api_name().to_owned(),
+            id,
+            field: field_describe.name.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl SalesforceRawRequest for SObjectBlobFieldRequest {
+    type ReturnValue = BlobContent;
+
+    fn get_url(&self) -> String {
+        format!("sobjects/{}/{}/{}", self.api_name, self.id, self.field)
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    async fn get_result(
+        &self,
+        _conn: &Connection,
+        response: Response,
+    ) -> Result<Self::ReturnValue> {
+        Ok(BlobContent {
+            content_type: response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_owned()),
+            content_length: response.content_length(),
+            stream: Box::pin(response.bytes_stream()),
+        })
+    }
+}
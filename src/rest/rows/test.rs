@@ -1,7 +1,16 @@
 use anyhow::Result;
+use bytes::{BufMut, BytesMut};
+use futures::future::join_all;
+use futures::StreamExt;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
 
+use super::{SObjectBlobFieldRequest, SObjectUpsertRequest};
+use crate::api::{SalesforceRawRequest, SalesforceRequest};
 use crate::prelude::*;
-use crate::test_integration_base::{get_test_connection, Account};
+use crate::test_integration_base::{
+    get_test_connection, get_test_field_describe, get_test_sobject_type, serve_responses, Account,
+};
 
 #[tokio::test]
 #[ignore]
@@ -93,3 +102,169 @@ async fn test_concrete_sobject_rows() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_retrieve_coalescing() -> Result<()> {
+    let (conn, count) = serve_responses(vec![(
+        "200 OK",
+        r#"{"attributes": {"type": "Account"}, "Id": "001000000000001AAA", "Name": "Test"}"#,
+    )])
+    .await?;
+    let account_type = get_test_sobject_type(
+        "Account",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            get_test_field_describe("Name", "xsd:string", "string"),
+        ],
+        vec![],
+    )?;
+    let id = SalesforceId::new("001000000000001AAA")?;
+
+    conn.set_retrieve_coalescing(Some(Duration::from_secs(60)));
+    assert_eq!(
+        conn.get_retrieve_coalescing(),
+        Some(Duration::from_secs(60))
+    );
+
+    let results = join_all((0..3).map(|i| {
+        let fields = if i % 2 == 0 {
+            vec!["Id".to_owned(), "Name".to_owned()]
+        } else {
+            vec!["name".to_owned(), "id".to_owned()]
+        };
+        SObject::retrieve(&conn, &account_type, id, Some(fields))
+    }))
+    .await;
+
+    for result in results {
+        assert_eq!(
+            result?.get("Name").unwrap(),
+            &FieldValue::String("Test".to_owned())
+        );
+    }
+
+    // Reused within the TTL.
+    SObject::retrieve(
+        &conn,
+        &account_type,
+        id,
+        Some(vec!["Id".to_owned(), "Name".to_owned()]),
+    )
+    .await?;
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    // Without coalescing, the request is sent (and fails).
+    conn.set_retrieve_coalescing(None);
+    assert!(SObject::retrieve(&conn, &account_type, id, None)
+        .await
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_blob_field_request_url() -> Result<()> {
+    let sobject_type = get_test_sobject_type(
+        "ContentVersion",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            get_test_field_describe("VersionData", "xsd:base64Binary", "base64"),
+        ],
+        vec![],
+    )?;
+    let id = SalesforceId::new("0683600000AAAAA")?;
+
+    assert_eq!(
+        SObjectBlobFieldRequest::new(&sobject_type, id, "versiondata")?.get_url(),
+        format!("sobjects/ContentVersion/{}/VersionData", id)
+    );
+    assert!(SObjectBlobFieldRequest::new(&sobject_type, id, "Id").is_err());
+    assert!(SObjectBlobFieldRequest::new(&sobject_type, id, "Missing").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_upsert_request_external_id_value() -> Result<()> {
+    let mut ext_id = get_test_field_describe("Ext__c", "xsd:string", "string");
+    ext_id["externalId"] = serde_json::json!(true);
+    let mut number_ext_id = get_test_field_describe("Number__c", "xsd:double", "double");
+    number_ext_id["externalId"] = serde_json::json!(true);
+    let sobject_type = get_test_sobject_type(
+        "Account",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            get_test_field_describe("Name", "xsd:string", "string"),
+            ext_id,
+            number_ext_id,
+        ],
+        vec![],
+    )?;
+
+    let record = SObject::new(&sobject_type)
+        .with_str("Name", "Acme")
+        .with_str("Ext__c", "A/B 1")
+        .with_double("Number__c", 42.0);
+
+    assert_eq!(
+        SObjectUpsertRequest::new(&record, "Ext__c")?.get_url(),
+        "sobjects/Account/Ext__c/A%2FB%201"
+    );
+    assert_eq!(
+        SObjectUpsertRequest::new_with_describe(&record, &sobject_type, "ext__c")?.get_url(),
+        "sobjects/Account/Ext__c/A%2FB%201"
+    );
+    assert_eq!(
+        SObjectUpsertRequest::new_with_describe(&record, &sobject_type, "Number__c")?.get_url(),
+        "sobjects/Account/Number__c/42"
+    );
+    assert!(SObjectUpsertRequest::new_with_describe(&record, &sobject_type, "Name").is_err());
+    assert!(SObjectUpsertRequest::new_with_describe(&record, &sobject_type, "Missing").is_err());
+    assert!(
+        SObjectUpsertRequest::new(&SObject::new(&sobject_type).with_null("Ext__c"), "Ext__c")
+            .is_err()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_blob_field_request() -> Result<()> {
+    let conn = get_test_connection()?;
+
+    conn.execute_anonymous(
+        "insert new ContentVersion(Title = 'Bar', PathOnClient = 'Test.txt', VersionData = Blob.valueOf('Foo'));".to_owned(),
+    )
+    .await?;
+
+    let content_version_type = conn.get_type("ContentVersion").await?;
+    let mut sobjects = SObject::query_vec(
+        &conn,
+        &content_version_type,
+        "SELECT Id FROM ContentVersion LIMIT 1",
+        false,
+    )
+    .await?;
+
+    let blob = conn
+        .execute_raw_request(&SObjectBlobFieldRequest::new(
+            &content_version_type,
+            sobjects[0].get_opt_id().unwrap(),
+            "VersionData",
+        )?)
+        .await?;
+    let mut stream = blob.stream;
+    let mut result = BytesMut::with_capacity(32);
+
+    while let Some(chunk) = stream.next().await {
+        result.put(&chunk?[..]);
+    }
+
+    assert_eq!(b"Foo", &result[..]);
+    assert!(blob.content_type.is_some());
+
+    sobjects.delete(&conn, false).await?;
+
+    Ok(())
+}
@@ -2,6 +2,7 @@ use crate::data::{
     DynamicallyTypedSObject, SObjectDeserialization, SObjectSerialization, SObjectWithId,
     SingleTypedSObject, TypedSObject,
 };
+use crate::rest::collections::DeleteMode;
 use crate::{api::Connection, data::FieldValue, data::SObjectType, data::SalesforceId};
 use anyhow::Result;
 use async_trait::async_trait;
@@ -33,6 +34,8 @@ pub trait SObjectRowUpsertable {
 pub trait SObjectRowDeletable {
     fn delete_request(&self) -> Result<SObjectDeleteRequest>;
     async fn delete(&mut self, conn: &Connection) -> Result<()>;
+    /// Delete this record and, for `DeleteMode::Hard`, empty it from the Recycle Bin.
+    async fn delete_with_mode(&mut self, conn: &Connection, mode: DeleteMode) -> Result<()>;
 }
 
 #[async_trait]
@@ -141,6 +144,16 @@ where
 
         result
     }
+
+    async fn delete_with_mode(&mut self, conn: &Connection, mode: DeleteMode) -> Result<()> {
+        let id = self.get_id();
+        self.delete(conn).await?;
+
+        match (mode, id) {
+            (DeleteMode::Hard, FieldValue::Id(id)) => conn.empty_recycle_bin(&[id]).await,
+            _ => Ok(()),
+        }
+    }
 }
 
 #[async_trait]
@@ -162,7 +175,7 @@ where
         id: SalesforceId,
         fields: Option<Vec<String>>,
     ) -> Result<Self> {
-        conn.execute(&Self::retrieve_request(sobject_type, id, fields))
+        conn.execute_retrieve(&Self::retrieve_request(sobject_type, id, fields))
             .await
     }
 }
@@ -185,7 +198,7 @@ where
         id: SalesforceId,
         fields: Option<Vec<String>>,
     ) -> Result<Self> {
-        conn.execute(&SObjectRetrieveRequest::new(
+        conn.execute_retrieve(&SObjectRetrieveRequest::new(
             id,
             &conn.get_type(T::get_type_api_name()).await?,
             fields,
@@ -0,0 +1,237 @@
+//! Object and picklist metadata from the User Interface API.
+//!
+//! The UI API reports dependent picklists in a usable form: each value lists
+//! the indexes of the controlling values for which it is valid, rather than
+//! the base64 bitmap of the describe's `validFor`. Picklist values are also
+//! filtered to those available for a record type.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use reqwest::Method;
+use serde_derive::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    api::{Connection, SalesforceRequest},
+    data::SalesforceId,
+    errors::SalesforceError,
+};
+
+#[cfg(test)]
+mod test;
+
+/// The Id of the master record type, which every sObject has.
+pub const MASTER_RECORD_TYPE_ID: &str = "012000000000000AAA";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordTypeInfo {
+    pub available: bool,
+    pub default_record_type_mapping: bool,
+    pub master: bool,
+    pub name: String,
+    pub record_type_id: SalesforceId,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldInfo {
+    pub api_name: String,
+    pub label: String,
+    /// The UI API data type, such as `Picklist` or `Reference`.
+    pub data_type: String,
+    pub required: bool,
+    pub createable: bool,
+    pub updateable: bool,
+    pub controller_name: Option<String>,
+    /// The fields that control this one, nearest first.
+    #[serde(default)]
+    pub controlling_fields: Vec<String>,
+}
+
+/// An sObject's metadata as seen by the User Interface API.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectInfo {
+    pub api_name: String,
+    pub label: String,
+    pub label_plural: String,
+    pub key_prefix: Option<String>,
+    pub createable: bool,
+    pub updateable: bool,
+    pub deletable: bool,
+    pub queryable: bool,
+    pub custom: bool,
+    pub default_record_type_id: Option<SalesforceId>,
+    /// Record types keyed by Id.
+    #[serde(default)]
+    pub record_type_infos: HashMap<String, RecordTypeInfo>,
+    /// Fields keyed by API name.
+    #[serde(default)]
+    pub fields: HashMap<String, FieldInfo>,
+    /// The tree of dependent fields under each controlling field, keyed by API name.
+    #[serde(default)]
+    pub dependent_fields: HashMap<String, Value>,
+}
+
+impl ObjectInfo {
+    /// Get a field's metadata by API name, ignoring case.
+    pub fn get_field(&self, api_name: &str) -> Option<&FieldInfo> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(api_name))
+            .map(|(_, v)| v)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PicklistValue {
+    pub label: String,
+    pub value: String,
+    /// The indexes, into the field's `controller_values`, of the controlling
+    /// values for which this value is valid.
+    #[serde(default)]
+    pub valid_for: Vec<usize>,
+}
+
+/// The values of one picklist field available for a record type.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PicklistFieldValues {
+    /// The index of each value of the controlling field, for dependent picklists.
+    #[serde(default)]
+    pub controller_values: HashMap<String, usize>,
+    pub default_value: Option<PicklistValue>,
+    pub values: Vec<PicklistValue>,
+}
+
+impl PicklistFieldValues {
+    pub fn is_dependent(&self) -> bool {
+        !self.controller_values.is_empty()
+    }
+
+    /// The values valid when the controlling field holds `controller_value`.
+    /// For a picklist that is not dependent, all values are returned.
+    pub fn get_values_for(&self, controller_value: &str) -> Vec<&PicklistValue> {
+        if !self.is_dependent() {
+            return self.values.iter().collect();
+        }
+
+        match self.controller_values.get(controller_value) {
+            Some(index) => self
+                .values
+                .iter()
+                .filter(|v| v.valid_for.contains(index))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// The values of every picklist field of an sObject available for a record type.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordTypePicklistValues {
+    /// Picklist fields keyed by API name.
+    pub picklist_field_values: HashMap<String, PicklistFieldValues>,
+}
+
+impl RecordTypePicklistValues {
+    /// Get a picklist field's values by API name, ignoring case.
+    pub fn get_field(&self, api_name: &str) -> Option<&PicklistFieldValues> {
+        self.picklist_field_values
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(api_name))
+            .map(|(_, v)| v)
+    }
+}
+
+pub struct ObjectInfoRequest {
+    sobject: String,
+}
+
+impl ObjectInfoRequest {
+    pub fn new(sobject: &str) -> ObjectInfoRequest {
+        ObjectInfoRequest {
+            sobject: sobject.to_owned(),
+        }
+    }
+}
+
+impl SalesforceRequest for ObjectInfoRequest {
+    type ReturnValue = ObjectInfo;
+
+    fn get_url(&self) -> String {
+        format!("ui-api/object-info/{}", self.sobject)
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(serde_json::from_value(body.clone())?)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+}
+
+pub struct PicklistValuesRequest {
+    sobject: String,
+    record_type_id: String,
+}
+
+impl PicklistValuesRequest {
+    /// Get the picklist values of `sobject` for a record type, or for
+    /// the master record type if `record_type_id` is `None`.
+    pub fn new(sobject: &str, record_type_id: Option<SalesforceId>) -> PicklistValuesRequest {
+        PicklistValuesRequest {
+            sobject: sobject.to_owned(),
+            record_type_id: record_type_id
+                .map(|id| id.to_string())
+                .unwrap_or_else(|| MASTER_RECORD_TYPE_ID.to_owned()),
+        }
+    }
+}
+
+impl SalesforceRequest for PicklistValuesRequest {
+    type ReturnValue = RecordTypePicklistValues;
+
+    fn get_url(&self) -> String {
+        format!(
+            "ui-api/object-info/{}/picklist-values/{}",
+            self.sobject, self.record_type_id
+        )
+    }
+
+    fn get_method(&self) -> Method {
+        Method::GET
+    }
+
+    fn get_result(&self, _conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        if let Some(body) = body {
+            Ok(serde_json::from_value(body.clone())?)
+        } else {
+            Err(SalesforceError::ResponseBodyExpected.into())
+        }
+    }
+}
+
+impl Connection {
+    pub async fn get_object_info(&self, sobject: &str) -> Result<ObjectInfo> {
+        self.execute(&ObjectInfoRequest::new(sobject)).await
+    }
+
+    pub async fn get_picklist_values(
+        &self,
+        sobject: &str,
+        record_type_id: Option<SalesforceId>,
+    ) -> Result<RecordTypePicklistValues> {
+        self.execute(&PicklistValuesRequest::new(sobject, record_type_id))
+            .await
+    }
+}
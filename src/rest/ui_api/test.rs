@@ -0,0 +1,90 @@
+use anyhow::Result;
+
+use super::PicklistValuesRequest;
+use crate::api::SalesforceRequest;
+use crate::data::SalesforceId;
+use crate::test_integration_base::serve_responses;
+
+#[test]
+fn test_picklist_values_request_url() -> Result<()> {
+    assert_eq!(
+        PicklistValuesRequest::new("Account", None).get_url(),
+        "ui-api/object-info/Account/picklist-values/012000000000000AAA"
+    );
+    assert_eq!(
+        PicklistValuesRequest::new("Account", Some(SalesforceId::new("012360000000001AAA")?))
+            .get_url(),
+        "ui-api/object-info/Account/picklist-values/012360000000001AAA"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_object_info_and_picklist_values() -> Result<()> {
+    let (conn, _) = serve_responses(vec![
+        (
+            "200 OK",
+            r#"{
+                "apiName": "Account", "label": "Account", "labelPlural": "Accounts",
+                "keyPrefix": "001", "createable": true, "updateable": true, "deletable": true,
+                "queryable": true, "custom": false,
+                "defaultRecordTypeId": "012000000000000AAA",
+                "recordTypeInfos": {
+                    "012000000000000AAA": {"available": true, "defaultRecordTypeMapping": true,
+                        "master": true, "name": "Master", "recordTypeId": "012000000000000AAA"}
+                },
+                "fields": {
+                    "Region__c": {"apiName": "Region__c", "label": "Region", "dataType": "Picklist",
+                        "required": false, "createable": true, "updateable": true,
+                        "controllerName": null, "controllingFields": []},
+                    "Country__c": {"apiName": "Country__c", "label": "Country", "dataType": "Picklist",
+                        "required": false, "createable": true, "updateable": true,
+                        "controllerName": "Region__c", "controllingFields": ["Region__c"]}
+                },
+                "dependentFields": {"Region__c": {"Country__c": {}}}
+            }"#,
+        ),
+        (
+            "200 OK",
+            r#"{
+                "picklistFieldValues": {
+                    "Region__c": {"controllerValues": {}, "defaultValue": null, "values": [
+                        {"attributes": null, "label": "Europe", "validFor": [], "value": "Europe"},
+                        {"attributes": null, "label": "Americas", "validFor": [], "value": "Americas"}
+                    ]},
+                    "Country__c": {"controllerValues": {"Europe": 0, "Americas": 1}, "defaultValue": null, "values": [
+                        {"attributes": null, "label": "France", "validFor": [0], "value": "FR"},
+                        {"attributes": null, "label": "Canada", "validFor": [1], "value": "CA"},
+                        {"attributes": null, "label": "Other", "validFor": [0, 1], "value": "XX"}
+                    ]}
+                }
+            }"#,
+        ),
+    ])
+    .await?;
+
+    let info = conn.get_object_info("Account").await?;
+    assert_eq!(info.key_prefix.as_deref(), Some("001"));
+    assert!(info.record_type_infos["012000000000000AAA"].master);
+    let country = info.get_field("country__c").unwrap();
+    assert_eq!(country.controller_name.as_deref(), Some("Region__c"));
+    assert!(info.dependent_fields.contains_key("Region__c"));
+
+    let picklists = conn.get_picklist_values("Account", None).await?;
+    let region = picklists.get_field("Region__c").unwrap();
+    assert!(!region.is_dependent());
+    assert_eq!(region.get_values_for("anything").len(), 2);
+
+    let country = picklists.get_field("Country__c").unwrap();
+    assert!(country.is_dependent());
+    let european: Vec<&str> = country
+        .get_values_for("Europe")
+        .iter()
+        .map(|v| v.value.as_str())
+        .collect();
+    assert_eq!(european, vec!["FR", "XX"]);
+    assert!(country.get_values_for("Asia").is_empty());
+
+    Ok(())
+}
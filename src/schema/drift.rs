@@ -0,0 +1,274 @@
+//! Detection of schema drift between runs.
+//!
+//! A `SchemaSnapshot` records, for each sObject a pipeline depends on, the type
+//! and requiredness of each field, along with a hash of those details. A later
+//! run passes the snapshot to `drift_check()`, which describes the objects
+//! afresh and reports changes that can break the pipeline: removed objects
+//! and fields, changed field types, and fields that have become required.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use md5::{Digest, Md5};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    api::Connection,
+    data::{DateTime, SObjectType},
+    errors::SalesforceError,
+    rest::describe::FieldDescribe,
+};
+
+/// The details of a field that matter to code that reads and writes it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldSnapshot {
+    pub name: String,
+    pub field_type: String,
+    /// Whether a value must be supplied when creating a record.
+    pub required: bool,
+}
+
+impl From<&FieldDescribe> for FieldSnapshot {
+    fn from(field: &FieldDescribe) -> Self {
+        FieldSnapshot {
+            name: field.name.clone(),
+            field_type: field.field_type.clone(),
+            required: field.createable && !field.nillable && !field.defaulted_on_create,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectSnapshot {
+    pub name: String,
+    /// A hash of `fields`, which changes whenever any of them changes.
+    pub version: String,
+    /// The fields of the object, keyed by lowercased name.
+    pub fields: BTreeMap<String, FieldSnapshot>,
+}
+
+impl From<&SObjectType> for ObjectSnapshot {
+    fn from(sobject_type: &SObjectType) -> Self {
+        let describe = sobject_type.get_describe();
+        let fields: BTreeMap<String, FieldSnapshot> = describe
+            .get_fields()
+            .iter()
+            .map(|f| (f.name.to_lowercase(), f.into()))
+            .collect();
+        // `fields` is ordered, so the hash does not depend on describe order.
+        let version = format!(
+            "{:x}",
+            Md5::digest(serde_json::to_vec(&fields).unwrap_or_default())
+        );
+
+        ObjectSnapshot {
+            name: describe.name.clone(),
+            version,
+            fields,
+        }
+    }
+}
+
+/// The schema of a set of sObjects at a point in time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaSnapshot {
+    pub taken: DateTime,
+    /// The objects in the snapshot, keyed by lowercased name.
+    pub objects: BTreeMap<String, ObjectSnapshot>,
+}
+
+impl SchemaSnapshot {
+    pub fn new(sobject_types: &[SObjectType]) -> SchemaSnapshot {
+        SchemaSnapshot {
+            taken: DateTime::now(),
+            objects: sobject_types
+                .iter()
+                .map(|t| (t.get_api_name().to_lowercase(), t.into()))
+                .collect(),
+        }
+    }
+
+    /// Describe each of `sobjects` afresh and snapshot their schema.
+    pub async fn capture(conn: &Connection, sobjects: &[&str]) -> Result<SchemaSnapshot> {
+        let mut sobject_types = Vec::with_capacity(sobjects.len());
+
+        for sobject in sobjects {
+            sobject_types.push(conn.refresh_type(sobject).await?);
+        }
+
+        Ok(SchemaSnapshot::new(&sobject_types))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<SchemaSnapshot> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// The changes from this snapshot to `current` that can break code written
+    /// against this snapshot. Objects absent from `current` are reported as removed.
+    pub fn compare(&self, current: &SchemaSnapshot) -> DriftReport {
+        let mut changes = Vec::new();
+
+        for (key, before) in &self.objects {
+            let after = match current.objects.get(key) {
+                Some(after) if after.version == before.version => continue,
+                Some(after) => after,
+                None => {
+                    changes.push(SchemaChange::ObjectRemoved {
+                        sobject: before.name.clone(),
+                    });
+                    continue;
+                }
+            };
+
+            for (field_key, field) in &before.fields {
+                match after.fields.get(field_key) {
+                    None => changes.push(SchemaChange::FieldRemoved {
+                        sobject: before.name.clone(),
+                        field: field.name.clone(),
+                    }),
+                    Some(new_field) if new_field.field_type != field.field_type => {
+                        changes.push(SchemaChange::FieldTypeChanged {
+                            sobject: before.name.clone(),
+                            field: field.name.clone(),
+                            from: field.field_type.clone(),
+                            to: new_field.field_type.clone(),
+                        })
+                    }
+                    Some(new_field) if new_field.required && !field.required => {
+                        changes.push(SchemaChange::FieldNowRequired {
+                            sobject: before.name.clone(),
+                            field: field.name.clone(),
+                        })
+                    }
+                    _ => {}
+                }
+            }
+
+            // A new field can break record creation only if it is required.
+            for (field_key, field) in &after.fields {
+                if field.required && !before.fields.contains_key(field_key) {
+                    changes.push(SchemaChange::FieldNowRequired {
+                        sobject: before.name.clone(),
+                        field: field.name.clone(),
+                    });
+                }
+            }
+        }
+
+        DriftReport { changes }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaChange {
+    ObjectRemoved {
+        sobject: String,
+    },
+    FieldRemoved {
+        sobject: String,
+        field: String,
+    },
+    FieldTypeChanged {
+        sobject: String,
+        field: String,
+        from: String,
+        to: String,
+    },
+    /// An existing field became required, or a new required field was added.
+    FieldNowRequired {
+        sobject: String,
+        field: String,
+    },
+}
+
+impl fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaChange::ObjectRemoved { sobject } => {
+                write!(f, "sObject {} no longer exists or is inaccessible", sobject)
+            }
+            SchemaChange::FieldRemoved { sobject, field } => write!(
+                f,
+                "Field {}.{} no longer exists or is inaccessible",
+                sobject, field
+            ),
+            SchemaChange::FieldTypeChanged {
+                sobject,
+                field,
+                from,
+                to,
+            } => write!(
+                f,
+                "Field {}.{} changed type from {} to {}",
+                sobject, field, from, to
+            ),
+            SchemaChange::FieldNowRequired { sobject, field } => {
+                write!(f, "Field {}.{} is now required", sobject, field)
+            }
+        }
+    }
+}
+
+/// The breaking changes found by `drift_check()`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DriftReport {
+    pub changes: Vec<SchemaChange>,
+}
+
+impl DriftReport {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Return a `SalesforceError::SchemaError` describing every change, if
+    /// there are any, so that a pipeline can stop before it reads or writes data.
+    pub fn into_result(self) -> Result<()> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(SalesforceError::SchemaError(
+                self.changes
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<String>>()
+                    .join("; "),
+            )
+            .into())
+        }
+    }
+}
+
+/// Describe the objects in `snapshot` afresh and report the breaking changes
+/// made since it was taken. An object that can no longer be described is
+/// reported as removed.
+pub async fn drift_check(conn: &Connection, snapshot: &SchemaSnapshot) -> Result<DriftReport> {
+    let mut sobject_types = Vec::with_capacity(snapshot.objects.len());
+
+    for object in snapshot.objects.values() {
+        match conn.refresh_type(&object.name).await {
+            Ok(sobject_type) => sobject_types.push(sobject_type),
+            Err(e) if is_not_found(&e) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(snapshot.compare(&SchemaSnapshot::new(&sobject_types)))
+}
+
+fn is_not_found(error: &anyhow::Error) -> bool {
+    match error.downcast_ref::<SalesforceError>() {
+        Some(SalesforceError::ApiErrors(errors)) => errors
+            .iter()
+            .any(|e| e.error_code.as_deref() == Some("NOT_FOUND")),
+        _ => false,
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+
+use crate::{api::Connection, data::SObjectType, rest::describe::FieldDescribe};
+
+pub mod drift;
+
+pub use drift::drift_check;
+
+#[cfg(test)]
+mod test;
+
+/// A lookup or master-detail field from `sobject` to `target`.
+///
+/// Polymorphic fields produce one relationship per target type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relationship {
+    pub sobject: String,
+    pub field: String,
+    pub relationship_name: Option<String>,
+    pub target: String,
+    pub polymorphic: bool,
+    pub cascade_delete: bool,
+}
+
+/// A graph of sObjects built from their describes, with an edge for each
+/// relationship field. Object names are matched case-insensitively.
+///
+/// Only described objects contribute relationships: `children_of()` returns
+/// only those children that have been added to the graph.
+#[derive(Debug, Default, Clone)]
+pub struct ObjectGraph {
+    objects: HashMap<String, SObjectType>,
+    relationships: Vec<Relationship>,
+}
+
+impl ObjectGraph {
+    pub fn new(sobject_types: &[SObjectType]) -> ObjectGraph {
+        let mut graph = ObjectGraph::default();
+
+        for sobject_type in sobject_types {
+            graph.add_type(sobject_type.clone());
+        }
+
+        graph
+    }
+
+    /// Add an sObject to the graph. Adding an object that is already present
+    /// replaces its relationships.
+    pub fn add_type(&mut self, sobject_type: SObjectType) {
+        let key = sobject_type.get_api_name().to_lowercase();
+
+        self.relationships
+            .retain(|r| r.sobject.to_lowercase() != key);
+
+        for field in sobject_type.get_describe().get_fields() {
+            self.relationships
+                .extend(get_relationships(sobject_type.get_api_name(), field));
+        }
+
+        self.objects.insert(key, sobject_type);
+    }
+
+    pub fn get_object(&self, name: &str) -> Option<&SObjectType> {
+        self.objects.get(&name.to_lowercase())
+    }
+
+    pub fn get_objects(&self) -> impl Iterator<Item = &SObjectType> {
+        self.objects.values()
+    }
+
+    pub fn get_field(&self, sobject: &str, field: &str) -> Option<&FieldDescribe> {
+        self.get_object(sobject)?.get_describe().get_field(field)
+    }
+
+    pub fn get_relationships(&self) -> &[Relationship] {
+        &self.relationships
+    }
+
+    /// The relationship fields on `sobject`.
+    pub fn lookups_from(&self, sobject: &str) -> Vec<&Relationship> {
+        self.relationships
+            .iter()
+            .filter(|r| r.sobject.eq_ignore_ascii_case(sobject))
+            .collect()
+    }
+
+    /// The relationship fields, on any described object, that point to `sobject`.
+    pub fn lookups_to(&self, sobject: &str) -> Vec<&Relationship> {
+        self.relationships
+            .iter()
+            .filter(|r| r.target.eq_ignore_ascii_case(sobject))
+            .collect()
+    }
+
+    /// The names of the objects `sobject` looks up to, in field order.
+    pub fn parents_of(&self, sobject: &str) -> Vec<&str> {
+        unique_names(
+            self.lookups_from(sobject)
+                .into_iter()
+                .map(|r| r.target.as_str()),
+        )
+    }
+
+    /// The names of the described objects that look up to `sobject`.
+    pub fn children_of(&self, sobject: &str) -> Vec<&str> {
+        unique_names(
+            self.lookups_to(sobject)
+                .into_iter()
+                .map(|r| r.sobject.as_str()),
+        )
+    }
+
+    /// Order `sobjects` so that each object follows the objects it looks up to.
+    /// Self-lookups are ignored. Lookup cycles are broken at the object with
+    /// the fewest unsorted parents; otherwise, input order is preserved.
+    pub fn sort_parents_first<'a>(&self, sobjects: &[&'a str]) -> Vec<&'a str> {
+        let mut remaining: Vec<&'a str> = sobjects.to_vec();
+        let mut sorted = Vec::with_capacity(remaining.len());
+
+        while !remaining.is_empty() {
+            let unsorted_parents: Vec<usize> = remaining
+                .iter()
+                .map(|candidate| {
+                    self.parents_of(candidate)
+                        .iter()
+                        .filter(|parent| {
+                            !parent.eq_ignore_ascii_case(candidate)
+                                && remaining.iter().any(|r| r.eq_ignore_ascii_case(parent))
+                        })
+                        .count()
+                })
+                .collect();
+            // `min_by_key` returns the first of equal minimums.
+            let (next, _) = unsorted_parents
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, count)| **count)
+                .unwrap();
+
+            sorted.push(remaining.remove(next));
+        }
+
+        sorted
+    }
+}
+
+fn get_relationships(sobject: &str, field: &FieldDescribe) -> Vec<Relationship> {
+    field
+        .reference_to
+        .iter()
+        .map(|target| Relationship {
+            sobject: sobject.to_owned(),
+            field: field.name.clone(),
+            relationship_name: field.relationship_name.clone(),
+            target: target.clone(),
+            polymorphic: field.polymorphic_foreign_key || field.reference_to.len() > 1,
+            cascade_delete: field.cascade_delete,
+        })
+        .collect()
+}
+
+fn unique_names<'a>(names: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let mut seen = HashSet::new();
+
+    names
+        .filter(|name| seen.insert(name.to_lowercase()))
+        .collect()
+}
+
+impl Connection {
+    /// Describe each of `sobjects` and build an `ObjectGraph` from them.
+    pub async fn get_object_graph(&self, sobjects: &[&str]) -> Result<ObjectGraph> {
+        let mut graph = ObjectGraph::default();
+
+        for sobject in sobjects {
+            graph.add_type(self.get_type(sobject).await?);
+        }
+
+        Ok(graph)
+    }
+}
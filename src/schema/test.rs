@@ -0,0 +1,226 @@
+use anyhow::Result;
+use serde_json::json;
+
+use crate::data::SObjectType;
+use crate::test_integration_base::{
+    get_test_connection, get_test_field_describe, get_test_sobject_type,
+};
+
+use super::drift::{SchemaChange, SchemaSnapshot};
+use super::ObjectGraph;
+
+fn get_lookup_field(name: &str, relationship: &str, targets: &[&str]) -> serde_json::Value {
+    let mut field = get_test_field_describe(name, "tns:ID", "reference");
+    field["referenceTo"] = json!(targets);
+    field["relationshipName"] = json!(relationship);
+    field
+}
+
+fn get_test_types() -> Result<Vec<SObjectType>> {
+    Ok(vec![
+        get_test_sobject_type(
+            "Account",
+            vec![
+                get_test_field_describe("Id", "tns:ID", "id"),
+                get_lookup_field("ParentId", "Parent", &["Account"]),
+            ],
+            vec![],
+        )?,
+        get_test_sobject_type(
+            "Contact",
+            vec![
+                get_test_field_describe("Id", "tns:ID", "id"),
+                get_lookup_field("AccountId", "Account", &["Account"]),
+            ],
+            vec![],
+        )?,
+        get_test_sobject_type(
+            "Task",
+            vec![
+                get_test_field_describe("Id", "tns:ID", "id"),
+                get_lookup_field("WhatId", "What", &["Account", "Opportunity"]),
+                get_lookup_field("WhoId", "Who", &["Contact", "Lead"]),
+            ],
+            vec![],
+        )?,
+    ])
+}
+
+#[test]
+fn test_object_graph_adjacency() -> Result<()> {
+    let graph = ObjectGraph::new(&get_test_types()?);
+
+    assert_eq!(graph.get_objects().count(), 3);
+    assert!(graph.get_object("contact").is_some());
+    assert!(graph.get_field("Task", "whoid").is_some());
+
+    assert_eq!(
+        graph.children_of("Account"),
+        vec!["Account", "Contact", "Task"]
+    );
+    assert_eq!(graph.children_of("Contact"), vec!["Task"]);
+    assert!(graph.children_of("Task").is_empty());
+    assert_eq!(
+        graph.parents_of("task"),
+        vec!["Account", "Opportunity", "Contact", "Lead"]
+    );
+
+    let lookups = graph.lookups_to("Account");
+    assert_eq!(lookups.len(), 3);
+    let task_lookup = lookups.iter().find(|r| r.sobject == "Task").unwrap();
+    assert_eq!(task_lookup.field, "WhatId");
+    assert_eq!(task_lookup.relationship_name.as_deref(), Some("What"));
+    assert!(task_lookup.polymorphic);
+    assert!(
+        !lookups
+            .iter()
+            .find(|r| r.sobject == "Contact")
+            .unwrap()
+            .polymorphic
+    );
+
+    assert_eq!(graph.lookups_from("Task").len(), 4);
+
+    Ok(())
+}
+
+#[test]
+fn test_object_graph_replaces_type() -> Result<()> {
+    let mut graph = ObjectGraph::new(&get_test_types()?);
+
+    graph.add_type(get_test_sobject_type(
+        "Contact",
+        vec![get_test_field_describe("Id", "tns:ID", "id")],
+        vec![],
+    )?);
+
+    assert_eq!(graph.get_objects().count(), 3);
+    assert_eq!(graph.children_of("Account"), vec!["Account", "Task"]);
+    assert!(graph.lookups_from("Contact").is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_object_graph_sort_parents_first() -> Result<()> {
+    let graph = ObjectGraph::new(&get_test_types()?);
+
+    assert_eq!(
+        graph.sort_parents_first(&["Task", "Contact", "Account"]),
+        vec!["Account", "Contact", "Task"]
+    );
+    assert_eq!(
+        graph.sort_parents_first(&["Contact", "Lead", "Task"]),
+        vec!["Contact", "Lead", "Task"]
+    );
+
+    let mut cyclic = get_test_types()?;
+    cyclic.push(get_test_sobject_type(
+        "Account",
+        vec![get_lookup_field(
+            "PrimaryContactId",
+            "PrimaryContact",
+            &["Contact"],
+        )],
+        vec![],
+    )?);
+    let graph = ObjectGraph::new(&cyclic);
+
+    assert_eq!(
+        graph.sort_parents_first(&["Task", "Contact", "Account"]),
+        vec!["Contact", "Account", "Task"]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_get_object_graph() -> Result<()> {
+    let conn = get_test_connection()?;
+    let graph = conn.get_object_graph(&["Account", "Contact"]).await?;
+
+    assert!(graph.children_of("Account").contains(&"Contact"));
+    assert!(graph
+        .lookups_to("Account")
+        .iter()
+        .any(|r| r.sobject == "Contact" && r.field == "AccountId"));
+
+    Ok(())
+}
+
+#[test]
+fn test_schema_drift() -> Result<()> {
+    let field = |name: &str, field_type: &str, required: bool| {
+        let mut field = get_test_field_describe(name, "xsd:string", field_type);
+        field["nillable"] = json!(!required);
+        field
+    };
+    let before = SchemaSnapshot::new(&[
+        get_test_sobject_type(
+            "Account",
+            vec![
+                field("Name", "string", false),
+                field("Rating", "picklist", false),
+                field("Region__c", "string", false),
+                field("Score__c", "double", false),
+            ],
+            vec![],
+        )?,
+        get_test_sobject_type("Legacy__c", vec![field("Name", "string", false)], vec![])?,
+    ]);
+    let unchanged = before.compare(&before);
+    assert!(unchanged.is_empty());
+    assert!(unchanged.into_result().is_ok());
+
+    let after = SchemaSnapshot::new(&[get_test_sobject_type(
+        "Account",
+        vec![
+            field("Name", "string", true),
+            field("Rating", "picklist", false),
+            field("Score__c", "string", false),
+            field("Tier__c", "string", true),
+            field("Notes__c", "textarea", false),
+        ],
+        vec![],
+    )?]);
+    assert_ne!(
+        before.objects["account"].version,
+        after.objects["account"].version
+    );
+
+    let report = before.compare(&after);
+    assert_eq!(
+        report.changes,
+        vec![
+            SchemaChange::FieldNowRequired {
+                sobject: "Account".to_owned(),
+                field: "Name".to_owned()
+            },
+            SchemaChange::FieldRemoved {
+                sobject: "Account".to_owned(),
+                field: "Region__c".to_owned()
+            },
+            SchemaChange::FieldTypeChanged {
+                sobject: "Account".to_owned(),
+                field: "Score__c".to_owned(),
+                from: "double".to_owned(),
+                to: "string".to_owned()
+            },
+            SchemaChange::FieldNowRequired {
+                sobject: "Account".to_owned(),
+                field: "Tier__c".to_owned()
+            },
+            SchemaChange::ObjectRemoved {
+                sobject: "Legacy__c".to_owned()
+            },
+        ]
+    );
+    assert!(report
+        .into_result()
+        .unwrap_err()
+        .to_string()
+        .contains("Field Account.Score__c changed type from double to string"));
+
+    Ok(())
+}
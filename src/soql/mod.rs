@@ -0,0 +1,1115 @@
+//! Parsing of SOQL queries into a syntax tree.
+//!
+//! The parser understands SELECT lists, including functions, aliases,
+//! relationship subqueries, and `TYPEOF` expressions; WHERE and HAVING
+//! conditions, including semi-join subqueries; and the GROUP BY, ORDER BY,
+//! LIMIT, and OFFSET clauses. Other clauses, such as `WITH SECURITY_ENFORCED`
+//! or `FOR UPDATE`, are preserved as written but not interpreted.
+//!
+//! A parsed `Query` can be validated against sObject describes, rewritten,
+//! and rendered back to SOQL with `to_string()`. Rendering normalizes
+//! whitespace and keyword case.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Result;
+
+use crate::{data::FieldHandle, errors::SalesforceError, schema::ObjectGraph};
+
+#[cfg(test)]
+mod test;
+
+/// A dotted field reference, such as `Name` or `Account.Owner.Name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldPath(pub Vec<String>);
+
+impl FieldPath {
+    pub fn get_path(&self) -> &[String] {
+        &self.0
+    }
+
+    /// Whether this path traverses a relationship.
+    pub fn is_relationship(&self) -> bool {
+        self.0.len() > 1
+    }
+}
+
+impl From<&FieldHandle> for FieldPath {
+    fn from(field: &FieldHandle) -> FieldPath {
+        FieldPath(vec![field.get_name().to_owned()])
+    }
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.join("."))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Field(FieldPath),
+    /// A function call, such as `COUNT(Id)` or `toLabel(Status)`.
+    Function {
+        name: String,
+        args: Vec<Expression>,
+    },
+    /// A literal function argument, such as the unit in `DISTANCE()`.
+    Literal(String),
+}
+
+impl Expression {
+    fn collect_field_paths<'a>(&'a self, paths: &mut Vec<&'a FieldPath>) {
+        match self {
+            Expression::Field(path) => paths.push(path),
+            Expression::Function { args, .. } => {
+                for arg in args {
+                    arg.collect_field_paths(paths);
+                }
+            }
+            Expression::Literal(_) => {}
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expression::Field(path) => write!(f, "{}", path),
+            Expression::Function { name, args } => write!(f, "{}({})", name, join(args, ", ")),
+            Expression::Literal(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// The fields a `TYPEOF` expression selects when the related record is `sobject`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeOfBranch {
+    pub sobject: String,
+    pub fields: Vec<FieldPath>,
+}
+
+/// A `TYPEOF` expression, which selects different fields of a polymorphic
+/// relationship, such as `Task.What`, depending on the type of the related record:
+///
+/// `TYPEOF What WHEN Account THEN Name, Phone WHEN Opportunity THEN Amount ELSE Name END`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeOf {
+    pub relationship: String,
+    pub branches: Vec<TypeOfBranch>,
+    /// The fields selected for records of any other type.
+    pub otherwise: Vec<FieldPath>,
+}
+
+fn to_field_paths(fields: &[&str]) -> Vec<FieldPath> {
+    fields
+        .iter()
+        .map(|f| FieldPath(f.split('.').map(|s| s.to_owned()).collect()))
+        .collect()
+}
+
+impl TypeOf {
+    pub fn new(relationship: &str) -> TypeOf {
+        TypeOf {
+            relationship: relationship.to_owned(),
+            branches: Vec::new(),
+            otherwise: Vec::new(),
+        }
+    }
+
+    /// Select `fields` when the related record is an `sobject`.
+    #[must_use]
+    pub fn when(mut self, sobject: &str, fields: &[&str]) -> Self {
+        self.branches.push(TypeOfBranch {
+            sobject: sobject.to_owned(),
+            fields: to_field_paths(fields),
+        });
+        self
+    }
+
+    /// Select `fields` when the related record is of a type with no `WHEN` branch.
+    #[must_use]
+    pub fn otherwise(mut self, fields: &[&str]) -> Self {
+        self.otherwise = to_field_paths(fields);
+        self
+    }
+
+    /// The sObjects named in `WHEN` branches.
+    pub fn get_types(&self) -> Vec<&str> {
+        self.branches.iter().map(|b| b.sobject.as_str()).collect()
+    }
+}
+
+impl fmt::Display for TypeOf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TYPEOF {}", self.relationship)?;
+        for branch in &self.branches {
+            write!(
+                f,
+                " WHEN {} THEN {}",
+                branch.sobject,
+                join(&branch.fields, ", ")
+            )?;
+        }
+        if !self.otherwise.is_empty() {
+            write!(f, " ELSE {}", join(&self.otherwise, ", "))?;
+        }
+        write!(f, " END")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectItem {
+    Expression {
+        expression: Expression,
+        alias: Option<String>,
+    },
+    /// A parent-to-child relationship subquery.
+    Subquery(Box<Query>),
+    TypeOf(TypeOf),
+}
+
+impl fmt::Display for SelectItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SelectItem::Expression {
+                expression,
+                alias: Some(alias),
+            } => write!(f, "{} {}", expression, alias),
+            SelectItem::Expression { expression, .. } => write!(f, "{}", expression),
+            SelectItem::Subquery(query) => write!(f, "({})", query),
+            SelectItem::TypeOf(type_of) => write!(f, "{}", type_of),
+        }
+    }
+}
+
+/// The right-hand side of a comparison.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    /// A literal as written, such as `'Acme'`, `10`, `NULL`, `TODAY`, or a bind variable.
+    Literal(String),
+    List(Vec<Operand>),
+    /// A semi-join or anti-join subquery.
+    Subquery(Box<Query>),
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Literal(value) => write!(f, "{}", value),
+            Operand::List(values) => write!(f, "({})", join(values, ", ")),
+            Operand::Subquery(query) => write!(f, "({})", query),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Comparison {
+        left: Expression,
+        /// The operator in upper case, such as `=`, `LIKE`, or `NOT IN`.
+        operator: String,
+        right: Operand,
+    },
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    fn collect_field_paths<'a>(&'a self, paths: &mut Vec<&'a FieldPath>) {
+        match self {
+            Condition::Comparison { left, .. } => left.collect_field_paths(paths),
+            Condition::And(conditions) | Condition::Or(conditions) => {
+                for condition in conditions {
+                    condition.collect_field_paths(paths);
+                }
+            }
+            Condition::Not(condition) => condition.collect_field_paths(paths),
+        }
+    }
+
+    fn collect_subqueries<'a>(&'a self, queries: &mut Vec<&'a Query>) {
+        match self {
+            Condition::Comparison {
+                right: Operand::Subquery(query),
+                ..
+            } => queries.push(query),
+            Condition::Comparison { .. } => {}
+            Condition::And(conditions) | Condition::Or(conditions) => {
+                for condition in conditions {
+                    condition.collect_subqueries(queries);
+                }
+            }
+            Condition::Not(condition) => condition.collect_subqueries(queries),
+        }
+    }
+
+    // AND binds more tightly than OR, so only OR needs parentheses within AND.
+    fn fmt_operand(&self, f: &mut fmt::Formatter, parent_is_and: bool) -> fmt::Result {
+        match self {
+            Condition::Or(_) if parent_is_and => write!(f, "({})", self),
+            _ => write!(f, "{}", self),
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Condition::Comparison {
+                left,
+                operator,
+                right,
+            } => write!(f, "{} {} {}", left, operator, right),
+            Condition::And(conditions) | Condition::Or(conditions) => {
+                let is_and = matches!(self, Condition::And(_));
+
+                for (i, condition) in conditions.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " {} ", if is_and { "AND" } else { "OR" })?;
+                    }
+                    condition.fmt_operand(f, is_and)?;
+                }
+                Ok(())
+            }
+            Condition::Not(condition) => match condition.as_ref() {
+                Condition::Comparison { .. } => write!(f, "NOT {}", condition),
+                _ => write!(f, "NOT ({})", condition),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderBy {
+    pub expression: Expression,
+    pub descending: bool,
+    /// `Some(true)` for `NULLS LAST`, `Some(false)` for `NULLS FIRST`.
+    pub nulls_last: Option<bool>,
+}
+
+impl fmt::Display for OrderBy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.expression)?;
+        if self.descending {
+            write!(f, " DESC")?;
+        }
+        match self.nulls_last {
+            Some(true) => write!(f, " NULLS LAST"),
+            Some(false) => write!(f, " NULLS FIRST"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub select: Vec<SelectItem>,
+    /// The sObject queried, or the child relationship name in a subquery.
+    pub from: String,
+    pub alias: Option<String>,
+    pub using_scope: Option<String>,
+    pub filter: Option<Condition>,
+    /// The body of the `WITH` clause, as written.
+    pub with: Option<String>,
+    pub group_by: Vec<Expression>,
+    pub having: Option<Condition>,
+    pub order_by: Vec<OrderBy>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+    /// Any `FOR` or `UPDATE` clause, as written.
+    pub for_clause: Option<String>,
+}
+
+/// Parse a SOQL query.
+pub fn parse(query: &str) -> Result<Query> {
+    let mut parser = Parser::new(query)?;
+    let parsed = parser.parse_query()?;
+    parser.expect_end()?;
+
+    Ok(parsed)
+}
+
+/// Parse a SOQL condition, such as the body of a `WHERE` clause.
+pub fn parse_condition(condition: &str) -> Result<Condition> {
+    let mut parser = Parser::new(condition)?;
+    let parsed = parser.parse_condition()?;
+    parser.expect_end()?;
+
+    Ok(parsed)
+}
+
+impl FromStr for Query {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse(s)
+    }
+}
+
+impl Query {
+    /// The sObject this query targets.
+    pub fn get_object(&self) -> &str {
+        &self.from
+    }
+
+    /// The fields referenced by this query's SELECT list, filters, grouping,
+    /// and ordering, excluding those in subqueries.
+    pub fn get_field_paths(&self) -> Vec<&FieldPath> {
+        let mut paths = Vec::new();
+
+        for item in &self.select {
+            if let SelectItem::Expression { expression, .. } = item {
+                expression.collect_field_paths(&mut paths);
+            }
+        }
+        if let Some(filter) = &self.filter {
+            filter.collect_field_paths(&mut paths);
+        }
+        for expression in &self.group_by {
+            expression.collect_field_paths(&mut paths);
+        }
+        if let Some(having) = &self.having {
+            having.collect_field_paths(&mut paths);
+        }
+        for order_by in &self.order_by {
+            order_by.expression.collect_field_paths(&mut paths);
+        }
+
+        paths
+    }
+
+    /// Add `filter`, a SOQL condition, to this query's WHERE clause with `AND`.
+    pub fn add_filter(&mut self, filter: &str) -> Result<()> {
+        let filter = parse_condition(filter)?;
+
+        self.filter = Some(match self.filter.take() {
+            Some(Condition::And(mut conditions)) => {
+                conditions.push(filter);
+                Condition::And(conditions)
+            }
+            Some(existing) => Condition::And(vec![existing, filter]),
+            None => filter,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_limit(&mut self, limit: Option<u64>) {
+        self.limit = limit;
+    }
+
+    /// Check that each field this query references exists, following
+    /// relationship paths through the objects described in `graph`. The
+    /// queried object must be described. Paths through polymorphic
+    /// relationships, or to objects not in `graph`, are checked only as far
+    /// as their first unresolvable step.
+    pub fn validate(&self, graph: &ObjectGraph) -> Result<()> {
+        self.validate_against(&self.from, graph, true)
+    }
+
+    fn validate_against(&self, sobject: &str, graph: &ObjectGraph, required: bool) -> Result<()> {
+        let sobject_type = match graph.get_object(sobject) {
+            Some(sobject_type) => sobject_type,
+            None if required => {
+                return Err(SalesforceError::SchemaError(format!(
+                    "The sObject {} is not described",
+                    sobject
+                ))
+                .into())
+            }
+            None => return Ok(()),
+        };
+        let sobject = sobject_type.get_api_name();
+        let aliases: Vec<&str> = self
+            .select
+            .iter()
+            .filter_map(|item| match item {
+                SelectItem::Expression {
+                    alias: Some(alias), ..
+                } => Some(alias.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        for path in self.get_field_paths() {
+            let mut segments = path.get_path();
+
+            if segments.len() == 1 && aliases.iter().any(|a| a.eq_ignore_ascii_case(&segments[0])) {
+                continue;
+            }
+            if segments.len() > 1
+                && self
+                    .alias
+                    .as_ref()
+                    .is_some_and(|a| a.eq_ignore_ascii_case(&segments[0]))
+            {
+                segments = &segments[1..];
+            }
+
+            validate_path(sobject, segments, graph)?;
+        }
+
+        for item in &self.select {
+            if let SelectItem::TypeOf(type_of) = item {
+                if !sobject_type.get_describe().get_fields().iter().any(|f| {
+                    f.relationship_name
+                        .as_ref()
+                        .is_some_and(|r| r.eq_ignore_ascii_case(&type_of.relationship))
+                }) {
+                    return Err(SalesforceError::SchemaError(format!(
+                        "No relationship {} on {}",
+                        type_of.relationship, sobject
+                    ))
+                    .into());
+                }
+                for branch in &type_of.branches {
+                    for path in &branch.fields {
+                        validate_path(&branch.sobject, path.get_path(), graph)?;
+                    }
+                }
+            }
+            if let SelectItem::Subquery(subquery) = item {
+                let child = sobject_type
+                    .get_describe()
+                    .child_relationships
+                    .iter()
+                    .find(|c| c.relationship_name.eq_ignore_ascii_case(&subquery.from))
+                    .ok_or_else(|| {
+                        SalesforceError::SchemaError(format!(
+                            "No child relationship {} on {}",
+                            subquery.from, sobject
+                        ))
+                    })?;
+
+                subquery.validate_against(&child.child_sobject, graph, false)?;
+            }
+        }
+
+        let mut subqueries = Vec::new();
+        for condition in self.filter.iter().chain(self.having.iter()) {
+            condition.collect_subqueries(&mut subqueries);
+        }
+        for subquery in subqueries {
+            subquery.validate_against(&subquery.from, graph, false)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_path(sobject: &str, segments: &[String], graph: &ObjectGraph) -> Result<()> {
+    let mut current = sobject.to_owned();
+
+    for (i, segment) in segments.iter().enumerate() {
+        let describe = match graph.get_object(&current) {
+            Some(sobject_type) => sobject_type.get_describe(),
+            None => return Ok(()),
+        };
+
+        if i == segments.len() - 1 {
+            if describe.get_field(segment).is_none() {
+                return Err(SalesforceError::SchemaError(format!(
+                    "No field {} on {}",
+                    segment, current
+                ))
+                .into());
+            }
+        } else {
+            let field = describe
+                .get_fields()
+                .iter()
+                .find(|f| {
+                    f.relationship_name
+                        .as_ref()
+                        .is_some_and(|r| r.eq_ignore_ascii_case(segment))
+                })
+                .ok_or_else(|| {
+                    SalesforceError::SchemaError(format!(
+                        "No relationship {} on {}",
+                        segment, current
+                    ))
+                })?;
+
+            if field.reference_to.len() != 1 {
+                return Ok(());
+            }
+            current = field.reference_to[0].clone();
+        }
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SELECT {} FROM {}", join(&self.select, ", "), self.from)?;
+
+        if let Some(alias) = &self.alias {
+            write!(f, " {}", alias)?;
+        }
+        if let Some(scope) = &self.using_scope {
+            write!(f, " USING SCOPE {}", scope)?;
+        }
+        if let Some(filter) = &self.filter {
+            write!(f, " WHERE {}", filter)?;
+        }
+        if let Some(with) = &self.with {
+            write!(f, " WITH {}", with)?;
+        }
+        if !self.group_by.is_empty() {
+            write!(f, " GROUP BY {}", join(&self.group_by, ", "))?;
+        }
+        if let Some(having) = &self.having {
+            write!(f, " HAVING {}", having)?;
+        }
+        if !self.order_by.is_empty() {
+            write!(f, " ORDER BY {}", join(&self.order_by, ", "))?;
+        }
+        if let Some(limit) = self.limit {
+            write!(f, " LIMIT {}", limit)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " OFFSET {}", offset)?;
+        }
+        if let Some(for_clause) = &self.for_clause {
+            write!(f, " {}", for_clause)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn join<T: fmt::Display>(items: &[T], separator: &str) -> String {
+    items
+        .iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+// Parsing
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TokenKind {
+    Word,
+    String,
+    Symbol,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+}
+
+const SYMBOLS: &[&str] = &["!=", "<>", "<=", ">=", "(", ")", ",", "=", "<", ">"];
+
+// Words that end an alias-free position, such as after FROM or a SELECT item.
+const CLAUSE_KEYWORDS: &[&str] = &[
+    "FROM", "WHERE", "WITH", "GROUP", "HAVING", "ORDER", "LIMIT", "OFFSET", "FOR", "UPDATE",
+    "USING",
+];
+
+fn is_symbol_char(c: char) -> bool {
+    "(),=<>!'".contains(c)
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '\'' {
+            chars.next();
+            let mut escaped = false;
+            let end = loop {
+                match chars.next() {
+                    Some((_, '\\')) if !escaped => escaped = true,
+                    Some((i, '\'')) if !escaped => break i + 1,
+                    Some(_) => escaped = false,
+                    None => return Err(parse_error(start, "Unterminated string literal")),
+                }
+            };
+            tokens.push(Token {
+                kind: TokenKind::String,
+                start,
+                end,
+            });
+        } else if is_symbol_char(c) {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|s| source[start..].starts_with(*s))
+                .ok_or_else(|| parse_error(start, &format!("Unexpected character {}", c)))?;
+
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            tokens.push(Token {
+                kind: TokenKind::Symbol,
+                start,
+                end: start + symbol.len(),
+            });
+        } else {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if c.is_whitespace() || is_symbol_char(c) {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token {
+                kind: TokenKind::Word,
+                start,
+                end,
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_error(position: usize, message: &str) -> anyhow::Error {
+    SalesforceError::SoqlParseError {
+        position,
+        message: message.to_owned(),
+    }
+    .into()
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Result<Parser<'a>> {
+        Ok(Parser {
+            source,
+            tokens: tokenize(source)?,
+            position: 0,
+        })
+    }
+
+    fn text(&self, token: &Token) -> &'a str {
+        &self.source[token.start..token.end]
+    }
+
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        self.peek().is_some_and(|t| {
+            t.kind == TokenKind::Word && self.text(&t).eq_ignore_ascii_case(keyword)
+        })
+    }
+
+    fn peek_symbol(&self, symbol: &str) -> bool {
+        self.peek()
+            .is_some_and(|t| t.kind == TokenKind::Symbol && self.text(&t) == symbol)
+    }
+
+    fn peek_clause_keyword(&self) -> bool {
+        CLAUSE_KEYWORDS.iter().any(|k| self.peek_keyword(k))
+    }
+
+    fn error_here(&self, message: &str) -> anyhow::Error {
+        parse_error(self.peek().map_or(self.source.len(), |t| t.start), message)
+    }
+
+    fn next_word(&mut self, expected: &str) -> Result<&'a str> {
+        match self.peek() {
+            Some(token) if token.kind == TokenKind::Word => {
+                self.position += 1;
+                Ok(self.text(&token))
+            }
+            _ => Err(self.error_here(&format!("Expected {}", expected))),
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.peek_keyword(keyword) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error_here(&format!("Expected {}", keyword)))
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<()> {
+        if self.peek_symbol(symbol) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error_here(&format!("Expected {}", symbol)))
+        }
+    }
+
+    fn accept_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword(keyword);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn accept_symbol(&mut self, symbol: &str) -> bool {
+        let found = self.peek_symbol(symbol);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect_end(&self) -> Result<()> {
+        match self.peek() {
+            Some(_) => Err(self.error_here("Unexpected input")),
+            None => Ok(()),
+        }
+    }
+
+    fn parse_query(&mut self) -> Result<Query> {
+        self.expect_keyword("SELECT")?;
+
+        let mut select = vec![self.parse_select_item()?];
+        while self.accept_symbol(",") {
+            select.push(self.parse_select_item()?);
+        }
+
+        self.expect_keyword("FROM")?;
+        let from = self.next_word("an sObject name")?.to_owned();
+        let alias = if self.peek().is_some_and(|t| t.kind == TokenKind::Word)
+            && !self.peek_clause_keyword()
+        {
+            Some(self.next_word("an alias")?.to_owned())
+        } else {
+            None
+        };
+
+        let mut query = Query {
+            select,
+            from,
+            alias,
+            using_scope: None,
+            filter: None,
+            with: None,
+            group_by: Vec::new(),
+            having: None,
+            order_by: Vec::new(),
+            limit: None,
+            offset: None,
+            for_clause: None,
+        };
+
+        if self.accept_keyword("USING") {
+            self.expect_keyword("SCOPE")?;
+            query.using_scope = Some(self.next_word("a scope")?.to_owned());
+        }
+        if self.accept_keyword("WHERE") {
+            query.filter = Some(self.parse_condition()?);
+        }
+        if self.accept_keyword("WITH") {
+            query.with =
+                Some(self.take_raw(&["GROUP", "ORDER", "LIMIT", "OFFSET", "FOR", "UPDATE"])?);
+        }
+        if self.accept_keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            query.group_by.push(self.parse_expression()?);
+            while self.accept_symbol(",") {
+                query.group_by.push(self.parse_expression()?);
+            }
+        }
+        if self.accept_keyword("HAVING") {
+            query.having = Some(self.parse_condition()?);
+        }
+        if self.accept_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            query.order_by.push(self.parse_order_by()?);
+            while self.accept_symbol(",") {
+                query.order_by.push(self.parse_order_by()?);
+            }
+        }
+        if self.accept_keyword("LIMIT") {
+            query.limit = Some(self.parse_integer()?);
+        }
+        if self.accept_keyword("OFFSET") {
+            query.offset = Some(self.parse_integer()?);
+        }
+        if self.peek_keyword("FOR") || self.peek_keyword("UPDATE") {
+            query.for_clause = Some(self.take_raw(&[])?);
+        }
+
+        Ok(query)
+    }
+
+    /// Consume tokens as written, up to one of `terminators`, a closing
+    /// parenthesis at this depth, or the end of input.
+    fn take_raw(&mut self, terminators: &[&str]) -> Result<String> {
+        let start = self.position;
+        let mut depth = 0;
+
+        while self.peek().is_some() {
+            if depth == 0
+                && (self.peek_symbol(")") || terminators.iter().any(|k| self.peek_keyword(k)))
+            {
+                break;
+            }
+            if self.peek_symbol("(") {
+                depth += 1;
+            } else if self.peek_symbol(")") {
+                depth -= 1;
+            }
+            self.position += 1;
+        }
+
+        if self.position == start {
+            return Err(self.error_here("Expected a clause body"));
+        }
+
+        Ok(self.source[self.tokens[start].start..self.tokens[self.position - 1].end].to_owned())
+    }
+
+    fn parse_integer(&mut self) -> Result<u64> {
+        let start = self.peek();
+        self.next_word("a number")?
+            .parse()
+            .map_err(|_| parse_error(start.map_or(0, |t| t.start), "Expected a number"))
+    }
+
+    fn parse_select_item(&mut self) -> Result<SelectItem> {
+        if self.accept_symbol("(") {
+            let query = self.parse_query()?;
+            self.expect_symbol(")")?;
+            return Ok(SelectItem::Subquery(Box::new(query)));
+        }
+
+        if self.accept_keyword("TYPEOF") {
+            return Ok(SelectItem::TypeOf(self.parse_type_of()?));
+        }
+
+        if self.peek_clause_keyword() {
+            return Err(self.error_here("Expected a field"));
+        }
+
+        let expression = self.parse_expression()?;
+        let alias = if self.peek().is_some_and(|t| t.kind == TokenKind::Word)
+            && !self.peek_clause_keyword()
+        {
+            Some(self.next_word("an alias")?.to_owned())
+        } else {
+            None
+        };
+
+        Ok(SelectItem::Expression { expression, alias })
+    }
+
+    fn parse_type_of(&mut self) -> Result<TypeOf> {
+        let mut type_of = TypeOf::new(self.next_word("a relationship")?);
+
+        self.expect_keyword("WHEN")?;
+        loop {
+            let sobject = self.next_word("an sObject")?.to_owned();
+            self.expect_keyword("THEN")?;
+            type_of.branches.push(TypeOfBranch {
+                sobject,
+                fields: self.parse_field_list()?,
+            });
+
+            if !self.accept_keyword("WHEN") {
+                break;
+            }
+        }
+        if self.accept_keyword("ELSE") {
+            type_of.otherwise = self.parse_field_list()?;
+        }
+        self.expect_keyword("END")?;
+
+        Ok(type_of)
+    }
+
+    fn parse_field_list(&mut self) -> Result<Vec<FieldPath>> {
+        let mut fields = Vec::new();
+
+        loop {
+            match self.parse_expression()? {
+                Expression::Field(path) => fields.push(path),
+                _ => return Err(self.error_here("Expected a field")),
+            }
+            if !self.accept_symbol(",") {
+                return Ok(fields);
+            }
+        }
+    }
+
+    fn parse_expression(&mut self) -> Result<Expression> {
+        let token = self
+            .peek()
+            .ok_or_else(|| self.error_here("Expected an expression"))?;
+        let text = self.text(&token);
+
+        match token.kind {
+            TokenKind::String => {
+                self.position += 1;
+                return Ok(Expression::Literal(text.to_owned()));
+            }
+            TokenKind::Symbol => return Err(self.error_here("Expected an expression")),
+            TokenKind::Word => self.position += 1,
+        }
+
+        if self.accept_symbol("(") {
+            let mut args = Vec::new();
+
+            if !self.accept_symbol(")") {
+                args.push(self.parse_expression()?);
+                while self.accept_symbol(",") {
+                    args.push(self.parse_expression()?);
+                }
+                self.expect_symbol(")")?;
+            }
+
+            Ok(Expression::Function {
+                name: text.to_owned(),
+                args,
+            })
+        } else if text.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+            Ok(Expression::Literal(text.to_owned()))
+        } else {
+            Ok(Expression::Field(FieldPath(
+                text.split('.').map(|s| s.to_owned()).collect(),
+            )))
+        }
+    }
+
+    fn parse_order_by(&mut self) -> Result<OrderBy> {
+        let expression = self.parse_expression()?;
+        let descending = if self.accept_keyword("DESC") {
+            true
+        } else {
+            self.accept_keyword("ASC");
+            false
+        };
+        let nulls_last = if self.accept_keyword("NULLS") {
+            if self.accept_keyword("LAST") {
+                Some(true)
+            } else {
+                self.expect_keyword("FIRST")?;
+                Some(false)
+            }
+        } else {
+            None
+        };
+
+        Ok(OrderBy {
+            expression,
+            descending,
+            nulls_last,
+        })
+    }
+
+    fn parse_condition(&mut self) -> Result<Condition> {
+        let mut conditions = vec![self.parse_and()?];
+
+        while self.accept_keyword("OR") {
+            conditions.push(self.parse_and()?);
+        }
+
+        Ok(if conditions.len() == 1 {
+            conditions.remove(0)
+        } else {
+            Condition::Or(conditions)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Condition> {
+        let mut conditions = vec![self.parse_not()?];
+
+        while self.accept_keyword("AND") {
+            conditions.push(self.parse_not()?);
+        }
+
+        Ok(if conditions.len() == 1 {
+            conditions.remove(0)
+        } else {
+            Condition::And(conditions)
+        })
+    }
+
+    fn parse_not(&mut self) -> Result<Condition> {
+        if self.accept_keyword("NOT") {
+            Ok(Condition::Not(Box::new(self.parse_not()?)))
+        } else if self.accept_symbol("(") {
+            let condition = self.parse_condition()?;
+            self.expect_symbol(")")?;
+            Ok(condition)
+        } else {
+            self.parse_comparison()
+        }
+    }
+
+    fn parse_comparison(&mut self) -> Result<Condition> {
+        let left = self.parse_expression()?;
+        let token = self
+            .peek()
+            .ok_or_else(|| self.error_here("Expected an operator"))?;
+        let text = self.text(&token).to_uppercase();
+
+        let operator = match (token.kind, text.as_str()) {
+            (TokenKind::Symbol, "(" | ")" | ",") => {
+                return Err(self.error_here("Expected an operator"))
+            }
+            (TokenKind::Symbol, _) | (TokenKind::Word, "LIKE" | "IN" | "INCLUDES" | "EXCLUDES") => {
+                self.position += 1;
+                text
+            }
+            (TokenKind::Word, "NOT") => {
+                self.position += 1;
+                self.expect_keyword("IN")?;
+                "NOT IN".to_owned()
+            }
+            _ => return Err(self.error_here("Expected an operator")),
+        };
+
+        Ok(Condition::Comparison {
+            left,
+            operator,
+            right: self.parse_operand()?,
+        })
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand> {
+        if self.accept_symbol("(") {
+            let operand = if self.peek_keyword("SELECT") {
+                Operand::Subquery(Box::new(self.parse_query()?))
+            } else {
+                let mut values = vec![self.parse_literal()?];
+                while self.accept_symbol(",") {
+                    values.push(self.parse_literal()?);
+                }
+                Operand::List(values)
+            };
+            self.expect_symbol(")")?;
+
+            Ok(operand)
+        } else {
+            self.parse_literal()
+        }
+    }
+
+    fn parse_literal(&mut self) -> Result<Operand> {
+        match self.peek() {
+            Some(token) if token.kind != TokenKind::Symbol => {
+                self.position += 1;
+                Ok(Operand::Literal(self.text(&token).to_owned()))
+            }
+            _ => Err(self.error_here("Expected a value")),
+        }
+    }
+}
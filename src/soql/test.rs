@@ -0,0 +1,295 @@
+use anyhow::Result;
+use serde_json::json;
+
+use crate::errors::SalesforceError;
+use crate::schema::ObjectGraph;
+use crate::test_integration_base::{
+    get_test_field_describe, get_test_sobject_type, get_test_sobject_type_with_children,
+};
+
+use super::{
+    parse, parse_condition, Condition, Expression, FieldPath, Operand, SelectItem, TypeOf,
+};
+
+fn get_lookup_field(name: &str, relationship: &str, targets: &[&str]) -> serde_json::Value {
+    let mut field = get_test_field_describe(name, "tns:ID", "reference");
+    field["referenceTo"] = json!(targets);
+    field["relationshipName"] = json!(relationship);
+    field
+}
+
+fn get_test_graph() -> Result<ObjectGraph> {
+    Ok(ObjectGraph::new(&[
+        get_test_sobject_type_with_children(
+            "Account",
+            vec![
+                get_test_field_describe("Id", "tns:ID", "id"),
+                get_test_field_describe("Name", "xsd:string", "string"),
+                get_test_field_describe("Industry", "xsd:string", "picklist"),
+            ],
+            vec![],
+            vec![json!({
+                "cascadeDelete": true,
+                "childSObject": "Contact",
+                "deprecatedAndHidden": false,
+                "field": "AccountId",
+                "junctionIdListNames": null,
+                "junctionReferenceTo": null,
+                "relationshipName": "Contacts",
+                "restrictedDelete": false
+            })],
+        )?,
+        get_test_sobject_type(
+            "Contact",
+            vec![
+                get_test_field_describe("Id", "tns:ID", "id"),
+                get_test_field_describe("LastName", "xsd:string", "string"),
+                get_lookup_field("AccountId", "Account", &["Account"]),
+                get_lookup_field("OwnerId", "Owner", &["User", "Group"]),
+            ],
+            vec![],
+        )?,
+    ]))
+}
+
+fn assert_schema_error(result: Result<()>, expected: &str) {
+    match result {
+        Err(e) => match e.downcast_ref::<SalesforceError>() {
+            Some(SalesforceError::SchemaError(message)) => assert_eq!(message, expected),
+            _ => panic!("Unexpected error {}", e),
+        },
+        Ok(()) => panic!("Expected a schema error"),
+    }
+}
+
+#[test]
+fn test_parse_select() -> Result<()> {
+    let query = parse(
+        "select Id, Account.Owner.Name, COUNT(Id) cnt, (SELECT LastName FROM Contacts) \
+         from Contact WHERE Name = 'Test' order by Name desc nulls last limit 10 offset 5",
+    )?;
+
+    assert_eq!(query.get_object(), "Contact");
+    assert_eq!(query.select.len(), 4);
+    assert_eq!(
+        query.select[1],
+        SelectItem::Expression {
+            expression: Expression::Field(FieldPath(vec![
+                "Account".to_owned(),
+                "Owner".to_owned(),
+                "Name".to_owned()
+            ])),
+            alias: None
+        }
+    );
+    assert_eq!(
+        query.select[2],
+        SelectItem::Expression {
+            expression: Expression::Function {
+                name: "COUNT".to_owned(),
+                args: vec![Expression::Field(FieldPath(vec!["Id".to_owned()]))]
+            },
+            alias: Some("cnt".to_owned())
+        }
+    );
+    match &query.select[3] {
+        SelectItem::Subquery(subquery) => assert_eq!(subquery.get_object(), "Contacts"),
+        _ => panic!("Expected a subquery"),
+    }
+    assert_eq!(query.limit, Some(10));
+    assert_eq!(query.offset, Some(5));
+    assert!(query.order_by[0].descending);
+    assert_eq!(query.order_by[0].nulls_last, Some(true));
+
+    assert_eq!(
+        query.to_string(),
+        "SELECT Id, Account.Owner.Name, COUNT(Id) cnt, (SELECT LastName FROM Contacts) \
+         FROM Contact WHERE Name = 'Test' ORDER BY Name DESC NULLS LAST LIMIT 10 OFFSET 5"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_where() -> Result<()> {
+    let query = parse(
+        "SELECT Id FROM Account WHERE (Name LIKE 'A\\'s%' OR Industry IN ('Tech', 'Retail')) \
+         AND NOT Id IN (SELECT AccountId FROM Contact) AND CreatedDate > LAST_N_DAYS:30 \
+         AND CALENDAR_YEAR(CreatedDate) = 2021 AND Name != :name",
+    )?;
+
+    match query.filter.as_ref().unwrap() {
+        Condition::And(conditions) => {
+            assert_eq!(conditions.len(), 5);
+            assert!(matches!(conditions[0], Condition::Or(_)));
+            match &conditions[1] {
+                Condition::Not(condition) => match condition.as_ref() {
+                    Condition::Comparison {
+                        operator, right, ..
+                    } => {
+                        assert_eq!(operator, "IN");
+                        assert!(matches!(right, Operand::Subquery(_)));
+                    }
+                    _ => panic!("Expected a comparison"),
+                },
+                _ => panic!("Expected NOT"),
+            }
+        }
+        _ => panic!("Expected AND"),
+    }
+
+    assert_eq!(
+        query.to_string(),
+        "SELECT Id FROM Account WHERE (Name LIKE 'A\\'s%' OR Industry IN ('Tech', 'Retail')) \
+         AND NOT Id IN (SELECT AccountId FROM Contact) AND CreatedDate > LAST_N_DAYS:30 \
+         AND CALENDAR_YEAR(CreatedDate) = 2021 AND Name != :name"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_preserves_other_clauses() -> Result<()> {
+    let query = parse(
+        "SELECT Id, TYPEOF What WHEN Account THEN Name ELSE Id END FROM Task \
+         USING SCOPE mine WITH SECURITY_ENFORCED GROUP BY ROLLUP(Status) \
+         HAVING COUNT(Id) > 1 FOR VIEW",
+    )?;
+
+    assert_eq!(
+        query.select[1],
+        SelectItem::TypeOf(
+            TypeOf::new("What")
+                .when("Account", &["Name"])
+                .otherwise(&["Id"])
+        )
+    );
+    assert_eq!(query.using_scope.as_deref(), Some("mine"));
+    assert_eq!(query.with.as_deref(), Some("SECURITY_ENFORCED"));
+    assert_eq!(query.for_clause.as_deref(), Some("FOR VIEW"));
+    assert_eq!(parse(&query.to_string())?, query);
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_type_of() -> Result<()> {
+    let query = parse(
+        "SELECT Id, TYPEOF What WHEN Account THEN Name, Owner.Name \
+         WHEN Opportunity THEN Amount ELSE Name END FROM Task",
+    )?;
+    let type_of = TypeOf::new("What")
+        .when("Account", &["Name", "Owner.Name"])
+        .when("Opportunity", &["Amount"])
+        .otherwise(&["Name"]);
+
+    assert_eq!(query.select[1], SelectItem::TypeOf(type_of.clone()));
+    assert_eq!(type_of.get_types(), vec!["Account", "Opportunity"]);
+    assert_eq!(
+        type_of.to_string(),
+        "TYPEOF What WHEN Account THEN Name, Owner.Name WHEN Opportunity THEN Amount \
+         ELSE Name END"
+    );
+    assert_eq!(parse(&query.to_string())?, query);
+
+    assert!(parse("SELECT TYPEOF What END FROM Task").is_err());
+    assert!(parse("SELECT TYPEOF What WHEN Account THEN COUNT(Id) END FROM Task").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_errors() {
+    for (query, position) in [
+        ("SELECT FROM Account", 7),
+        ("SELECT Id FROM Account WHERE Name = 'Test", 36),
+        ("SELECT Id FROM Account LIMIT ten", 29),
+        ("SELECT Id FROM Account WHERE Name Test", 34),
+        ("SELECT Id FROM Account extra tokens", 29),
+    ] {
+        match parse(query).map_err(|e| e.downcast::<SalesforceError>()) {
+            Err(Ok(SalesforceError::SoqlParseError { position: p, .. })) => {
+                assert_eq!(p, position, "{}", query)
+            }
+            _ => panic!("Expected a parse error for {}", query),
+        }
+    }
+}
+
+#[test]
+fn test_rewrite_query() -> Result<()> {
+    let mut query = parse("SELECT Id FROM Account WHERE Name = 'A' OR Name = 'B'")?;
+
+    query.add_filter("Industry = 'Tech'")?;
+    query.add_filter("IsDeleted = false")?;
+    query.set_limit(Some(100));
+
+    assert_eq!(
+        query.to_string(),
+        "SELECT Id FROM Account WHERE (Name = 'A' OR Name = 'B') AND Industry = 'Tech' \
+         AND IsDeleted = false LIMIT 100"
+    );
+
+    let mut query = parse("SELECT Id FROM Account")?;
+    query.add_filter("Name = 'A'")?;
+    assert_eq!(query.to_string(), "SELECT Id FROM Account WHERE Name = 'A'");
+
+    assert!(parse_condition("Name =").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_validate_query() -> Result<()> {
+    let graph = get_test_graph()?;
+
+    parse(
+        "SELECT Id, LastName, Account.Name, Owner.Anything \
+         FROM Contact c WHERE c.Account.Industry = 'Tech' \
+         AND AccountId IN (SELECT Id FROM Account)",
+    )?
+    .validate(&graph)?;
+    parse("SELECT Name, (SELECT LastName, Account.Name FROM Contacts) FROM Account")?
+        .validate(&graph)?;
+    parse("SELECT Industry, COUNT(Id) total FROM Account GROUP BY Industry ORDER BY total")?
+        .validate(&graph)?;
+
+    assert_schema_error(
+        parse("SELECT Id, FirstName FROM Contact")?.validate(&graph),
+        "No field FirstName on Contact",
+    );
+    assert_schema_error(
+        parse("SELECT Account.Rating FROM Contact")?.validate(&graph),
+        "No field Rating on Account",
+    );
+    assert_schema_error(
+        parse("SELECT Parent.Name FROM Contact")?.validate(&graph),
+        "No relationship Parent on Contact",
+    );
+    assert_schema_error(
+        parse("SELECT Id, (SELECT Id FROM Opportunities) FROM Account")?.validate(&graph),
+        "No child relationship Opportunities on Account",
+    );
+    assert_schema_error(
+        parse(
+            "SELECT Id FROM Account WHERE Id IN (SELECT AccountId FROM Contact WHERE Name = 'A')",
+        )?
+        .validate(&graph),
+        "No field Name on Contact",
+    );
+    parse("SELECT TYPEOF Owner WHEN User THEN Name END FROM Contact")?.validate(&graph)?;
+    assert_schema_error(
+        parse("SELECT TYPEOF Account WHEN Account THEN Rating END FROM Contact")?.validate(&graph),
+        "No field Rating on Account",
+    );
+    assert_schema_error(
+        parse("SELECT TYPEOF What WHEN Account THEN Name END FROM Contact")?.validate(&graph),
+        "No relationship What on Contact",
+    );
+    assert_schema_error(
+        parse("SELECT Id FROM Lead")?.validate(&graph),
+        "The sObject Lead is not described",
+    );
+
+    Ok(())
+}
@@ -0,0 +1,407 @@
+//! Subscription to the Streaming API, including Change Data Capture events,
+//! Platform Events, and PushTopics, over CometD long polling.
+//!
+//! A `StreamingClient` tracks the last replay Id received on each channel.
+//! When the connection to Salesforce drops, or Salesforce asks the client to
+//! handshake again, it resubscribes from those replay Ids, so no events are
+//! missed within Salesforce's retention window.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_stream::stream;
+use futures::Stream;
+use log::info;
+use reqwest::{header, Method, StatusCode, Url};
+use serde_derive::Deserialize;
+use serde_json::{json, Value};
+use tokio::time::sleep;
+
+use crate::api::error_for_status;
+use crate::{api::Connection, errors::SalesforceError};
+
+#[cfg(test)]
+mod test;
+
+/// The default number of consecutive failed reconnection attempts before a
+/// `StreamingClient` gives up.
+pub const DEFAULT_MAX_RECONNECTS: usize = 5;
+
+/// Where a subscription starts in a channel's retained events.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplayId {
+    /// Receive only events published after subscribing.
+    Tip,
+    /// Receive all retained events.
+    All,
+    /// Receive events published after the event with this replay Id.
+    After(i64),
+}
+
+impl ReplayId {
+    pub fn get_value(&self) -> i64 {
+        match self {
+            ReplayId::Tip => -1,
+            ReplayId::All => -2,
+            ReplayId::After(id) => *id,
+        }
+    }
+}
+
+/// The header of a Change Data Capture event.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeEventHeader {
+    pub entity_name: String,
+    pub change_type: String,
+    #[serde(default)]
+    pub change_origin: String,
+    pub transaction_key: String,
+    pub sequence_number: i64,
+    pub commit_timestamp: i64,
+    pub commit_user: String,
+    pub commit_number: i64,
+    pub record_ids: Vec<String>,
+    #[serde(default)]
+    pub changed_fields: Vec<String>,
+    #[serde(default)]
+    pub nulled_fields: Vec<String>,
+}
+
+/// An event received on a Streaming API channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeEvent {
+    pub channel: String,
+    pub replay_id: i64,
+    /// The PushTopic event type, such as `created`. Absent for Change Data
+    /// Capture and Platform Events.
+    pub event_type: Option<String>,
+    /// The event payload for Change Data Capture and Platform Events, or the
+    /// record for PushTopic events.
+    pub payload: Value,
+}
+
+impl ChangeEvent {
+    /// The Change Data Capture header, if this is a Change Data Capture event.
+    pub fn get_header(&self) -> Option<ChangeEventHeader> {
+        serde_json::from_value(self.payload.get("ChangeEventHeader")?.clone()).ok()
+    }
+
+    fn from_message(message: &Value) -> Option<ChangeEvent> {
+        let channel = message.get("channel")?.as_str()?;
+        let data = message.get("data")?;
+        let event = data.get("event")?;
+
+        Some(ChangeEvent {
+            channel: channel.to_owned(),
+            replay_id: event.get("replayId")?.as_i64()?,
+            event_type: event
+                .get("type")
+                .and_then(|t| t.as_str())
+                .map(|t| t.to_owned()),
+            payload: data
+                .get("payload")
+                .or_else(|| data.get("sobject"))
+                .cloned()
+                .unwrap_or(Value::Null),
+        })
+    }
+}
+
+/// Subscribes to Streaming API channels, such as `/data/ChangeEvents` or
+/// `/topic/MyPushTopic`, and streams their events.
+pub struct StreamingClient {
+    conn: Connection,
+    subscriptions: HashMap<String, i64>,
+    max_reconnects: usize,
+    reconnect_backoff: Duration,
+}
+
+impl StreamingClient {
+    pub fn new(conn: &Connection) -> StreamingClient {
+        StreamingClient {
+            conn: conn.clone(),
+            subscriptions: HashMap::new(),
+            max_reconnects: DEFAULT_MAX_RECONNECTS,
+            reconnect_backoff: Duration::from_secs(1),
+        }
+    }
+
+    #[must_use]
+    pub fn with_subscription(mut self, channel: &str, replay_id: ReplayId) -> Self {
+        self.subscriptions
+            .insert(channel.to_owned(), replay_id.get_value());
+        self
+    }
+
+    /// Give up after `max_reconnects` consecutive failed attempts to reach Salesforce.
+    #[must_use]
+    pub fn with_max_reconnects(mut self, max_reconnects: usize) -> Self {
+        self.max_reconnects = max_reconnects;
+        self
+    }
+
+    /// Wait `reconnect_backoff` before the first reconnection attempt,
+    /// doubling the wait after each further failure.
+    #[must_use]
+    pub fn with_reconnect_backoff(mut self, reconnect_backoff: Duration) -> Self {
+        self.reconnect_backoff = reconnect_backoff;
+        self
+    }
+
+    /// Stream events from all subscribed channels. The stream ends with an
+    /// error if a subscription is rejected or reconnection fails.
+    pub fn stream(self) -> Pin<Box<dyn Stream<Item = Result<ChangeEvent>> + Send>> {
+        let StreamingClient {
+            conn,
+            subscriptions,
+            max_reconnects,
+            reconnect_backoff,
+        } = self;
+
+        Box::pin(stream! {
+            let mut session = BayeuxSession::new(subscriptions);
+            let mut failures: u32 = 0;
+
+            loop {
+                match session.poll(&conn).await {
+                    Ok(events) => {
+                        failures = 0;
+                        for event in events {
+                            yield Ok(event);
+                        }
+                    }
+                    Err(e) => {
+                        if session.subscription_failed || failures as usize >= max_reconnects {
+                            yield Err(e);
+                            break;
+                        }
+
+                        info!("Streaming API connection failed, reconnecting: {}", e);
+                        session.reset();
+                        sleep(reconnect_backoff.saturating_mul(2u32.saturating_pow(failures))).await;
+                        failures += 1;
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Advice {
+    Retry,
+    Handshake,
+    None,
+}
+
+pub(crate) struct BayeuxSession {
+    pub(crate) client_id: Option<String>,
+    cookies: Option<String>,
+    pub(crate) replay_ids: HashMap<String, i64>,
+    pub(crate) subscription_failed: bool,
+}
+
+impl BayeuxSession {
+    pub(crate) fn new(replay_ids: HashMap<String, i64>) -> BayeuxSession {
+        BayeuxSession {
+            client_id: None,
+            cookies: None,
+            replay_ids,
+            subscription_failed: false,
+        }
+    }
+
+    /// Discard the CometD session, so the next poll handshakes again.
+    pub(crate) fn reset(&mut self) {
+        self.client_id = None;
+        self.cookies = None;
+    }
+
+    async fn poll(&mut self, conn: &Connection) -> Result<Vec<ChangeEvent>> {
+        if self.client_id.is_none() {
+            self.handshake(conn).await?;
+            self.subscribe(conn).await?;
+        }
+
+        let messages = self
+            .send(
+                conn,
+                json!([{
+                    "channel": "/meta/connect",
+                    "clientId": self.client_id,
+                    "connectionType": "long-polling",
+                }]),
+            )
+            .await?;
+
+        let (events, advice) = self.process_messages(&messages)?;
+        if advice == Advice::Handshake {
+            self.reset();
+        }
+
+        Ok(events)
+    }
+
+    async fn handshake(&mut self, conn: &Connection) -> Result<()> {
+        let messages = self
+            .send(
+                conn,
+                json!([{
+                    "channel": "/meta/handshake",
+                    "version": "1.0",
+                    "supportedConnectionTypes": ["long-polling"],
+                }]),
+            )
+            .await?;
+        let response = get_meta_response(&messages, "/meta/handshake")?;
+
+        self.client_id = Some(
+            response
+                .get("clientId")
+                .and_then(|c| c.as_str())
+                .ok_or(SalesforceError::ResponseBodyExpected)?
+                .to_owned(),
+        );
+
+        Ok(())
+    }
+
+    async fn subscribe(&mut self, conn: &Connection) -> Result<()> {
+        let messages = self.send(conn, self.get_subscribe_messages()).await?;
+
+        for message in messages
+            .iter()
+            .filter(|m| m["channel"] == "/meta/subscribe")
+        {
+            if message["successful"] != true {
+                self.subscription_failed = true;
+                return Err(SalesforceError::GeneralError(format!(
+                    "Unable to subscribe to {}: {}",
+                    message["subscription"], message["error"]
+                ))
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn get_subscribe_messages(&self) -> Value {
+        Value::Array(
+            self.replay_ids
+                .iter()
+                .map(|(channel, replay_id)| {
+                    json!({
+                        "channel": "/meta/subscribe",
+                        "clientId": self.client_id,
+                        "subscription": channel,
+                        "ext": {"replay": {channel: replay_id}},
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    /// Extract events from a `/meta/connect` response, recording their replay
+    /// Ids, and determine how Salesforce advises us to reconnect.
+    pub(crate) fn process_messages(
+        &mut self,
+        messages: &[Value],
+    ) -> Result<(Vec<ChangeEvent>, Advice)> {
+        let mut events = Vec::new();
+        let mut advice = Advice::None;
+
+        for message in messages {
+            if message["channel"] == "/meta/connect" {
+                advice = match message["advice"]["reconnect"].as_str() {
+                    Some("handshake") => Advice::Handshake,
+                    Some("retry") => Advice::Retry,
+                    Some("none") => {
+                        return Err(SalesforceError::GeneralError(format!(
+                            "Streaming API connection closed: {}",
+                            message["error"]
+                        ))
+                        .into())
+                    }
+                    _ if message["successful"] != true => Advice::Handshake,
+                    _ => Advice::Retry,
+                };
+            } else if let Some(event) = ChangeEvent::from_message(message) {
+                self.replay_ids
+                    .insert(event.channel.clone(), event.replay_id);
+                events.push(event);
+            }
+        }
+
+        Ok((events, advice))
+    }
+
+    async fn send(&mut self, conn: &Connection, messages: Value) -> Result<Vec<Value>> {
+        let url = get_streaming_url(conn).await?;
+        let mut builder = conn
+            .new_request(Method::POST, &url)
+            .await?
+            .header(header::CONTENT_TYPE, "application/json");
+
+        if let Some(cookies) = &self.cookies {
+            builder = builder.header(header::COOKIE, cookies);
+        }
+
+        let response = conn
+            .send_request(builder.body(serde_json::to_vec(&messages)?.into())?)
+            .await?;
+
+        // An expired token invalidates the CometD session too.
+        if response.status() == StatusCode::UNAUTHORIZED {
+            conn.refresh_access_token().await?;
+        }
+
+        let response = error_for_status(response).await?;
+        let cookies: Vec<&str> = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .filter_map(|c| c.to_str().ok())
+            .filter_map(|c| c.split(';').next())
+            .collect();
+        if !cookies.is_empty() {
+            self.cookies = Some(cookies.join("; "));
+        }
+
+        Ok(response.json().await?)
+    }
+}
+
+fn get_meta_response<'a>(messages: &'a [Value], channel: &str) -> Result<&'a Value> {
+    let response = messages
+        .iter()
+        .find(|m| m["channel"] == channel)
+        .ok_or(SalesforceError::ResponseBodyExpected)?;
+
+    if response["successful"] != true {
+        return Err(SalesforceError::GeneralError(format!(
+            "Streaming API {} failed: {}",
+            channel, response["error"]
+        ))
+        .into());
+    }
+
+    Ok(response)
+}
+
+async fn get_streaming_url(conn: &Connection) -> Result<Url> {
+    Ok(conn.get_instance_url().await?.join(&format!(
+        "/cometd/{}",
+        conn.api_version.trim_start_matches('v')
+    ))?)
+}
+
+impl Connection {
+    /// Create a `StreamingClient` for this Connection.
+    pub fn streaming_client(&self) -> StreamingClient {
+        StreamingClient::new(self)
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::Result;
+use futures::StreamExt;
+use serde_json::json;
+
+use crate::test_integration_base::get_test_connection;
+
+use super::{Advice, BayeuxSession, ChangeEvent, ReplayId};
+
+fn get_test_session() -> BayeuxSession {
+    let mut replay_ids = HashMap::new();
+    replay_ids.insert(
+        "/data/AccountChangeEvent".to_owned(),
+        ReplayId::Tip.get_value(),
+    );
+
+    BayeuxSession::new(replay_ids)
+}
+
+#[test]
+fn test_replay_id_values() {
+    assert_eq!(ReplayId::Tip.get_value(), -1);
+    assert_eq!(ReplayId::All.get_value(), -2);
+    assert_eq!(ReplayId::After(42).get_value(), 42);
+}
+
+#[test]
+fn test_subscribe_messages() {
+    let mut session = get_test_session();
+    session.client_id = Some("abc".to_owned());
+
+    assert_eq!(
+        session.get_subscribe_messages(),
+        json!([{
+            "channel": "/meta/subscribe",
+            "clientId": "abc",
+            "subscription": "/data/AccountChangeEvent",
+            "ext": {"replay": {"/data/AccountChangeEvent": -1}},
+        }])
+    );
+}
+
+#[test]
+fn test_process_messages_records_replay_ids() -> Result<()> {
+    let mut session = get_test_session();
+    let messages = vec![
+        json!({
+            "channel": "/data/AccountChangeEvent",
+            "data": {
+                "schema": "abc",
+                "payload": {
+                    "ChangeEventHeader": {
+                        "entityName": "Account",
+                        "changeType": "UPDATE",
+                        "changeOrigin": "",
+                        "transactionKey": "001",
+                        "sequenceNumber": 1,
+                        "commitTimestamp": 1600000000000i64,
+                        "commitUser": "005000000000001",
+                        "commitNumber": 10,
+                        "recordIds": ["001000000000001"],
+                        "changedFields": ["Name"]
+                    },
+                    "Name": "Test"
+                },
+                "event": {"replayId": 15}
+            }
+        }),
+        json!({
+            "channel": "/topic/Accounts",
+            "data": {
+                "event": {"replayId": 3, "type": "created", "createdDate": "2021-01-01T00:00:00.000Z"},
+                "sobject": {"Id": "001000000000001", "Name": "Test"}
+            }
+        }),
+        json!({"channel": "/meta/connect", "successful": true, "advice": {"reconnect": "retry"}}),
+    ];
+
+    let (events, advice) = session.process_messages(&messages)?;
+
+    assert_eq!(advice, Advice::Retry);
+    assert_eq!(events.len(), 2);
+    assert_eq!(session.replay_ids["/data/AccountChangeEvent"], 15);
+    assert_eq!(session.replay_ids["/topic/Accounts"], 3);
+
+    let header = events[0].get_header().unwrap();
+    assert_eq!(header.entity_name, "Account");
+    assert_eq!(header.change_type, "UPDATE");
+    assert_eq!(header.changed_fields, vec!["Name"]);
+    assert!(header.nulled_fields.is_empty());
+
+    assert_eq!(
+        events[1],
+        ChangeEvent {
+            channel: "/topic/Accounts".to_owned(),
+            replay_id: 3,
+            event_type: Some("created".to_owned()),
+            payload: json!({"Id": "001000000000001", "Name": "Test"}),
+        }
+    );
+    assert!(events[1].get_header().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_process_messages_advice() -> Result<()> {
+    let mut session = get_test_session();
+
+    let (events, advice) = session.process_messages(&[json!({
+        "channel": "/meta/connect",
+        "successful": false,
+        "error": "403::Unknown client",
+        "advice": {"reconnect": "handshake"}
+    })])?;
+    assert!(events.is_empty());
+    assert_eq!(advice, Advice::Handshake);
+
+    let (_, advice) = session.process_messages(&[json!({
+        "channel": "/meta/connect",
+        "successful": false,
+        "error": "403::Unknown client"
+    })])?;
+    assert_eq!(advice, Advice::Handshake);
+
+    assert!(session
+        .process_messages(&[json!({
+            "channel": "/meta/connect",
+            "successful": false,
+            "advice": {"reconnect": "none"}
+        })])
+        .is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_streaming_client() -> Result<()> {
+    let conn = get_test_connection()?;
+    let mut stream = conn
+        .streaming_client()
+        .with_subscription("/data/ChangeEvents", ReplayId::All)
+        .stream();
+
+    // Salesforce holds each long poll open for up to two minutes.
+    let event = tokio::time::timeout(Duration::from_secs(150), stream.next()).await?;
+
+    if let Some(event) = event {
+        assert!(event?.replay_id > 0);
+    }
+
+    Ok(())
+}
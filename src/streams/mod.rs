@@ -1,21 +1,35 @@
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::VecDeque,
     future::Future,
     mem,
     pin::Pin,
     task::{Context, Poll},
+    time::{Duration, Instant},
 };
 
 use anyhow::{Error, Result};
+#[cfg(feature = "bulk")]
 use serde_json::{Map, Value};
+#[cfg(feature = "bulk")]
+use std::collections::HashMap;
 use tokio::task::JoinHandle;
+use tokio::time::{sleep, Sleep};
 use tokio_stream::Stream;
 
-use crate::{data::FieldValue, data::SObjectDeserialization, data::SObjectType};
+#[cfg(feature = "bulk")]
+use crate::data::{FieldValue, SObjectType};
+use crate::{
+    api::metrics::MetricsRecorder,
+    api::metrics::StreamMetrics,
+    api::retry::is_locator_expired,
+    data::SObjectDeserialization,
+    errors::{ResumeHint, SalesforceError},
+};
 
 #[cfg(test)]
 mod test;
 
+#[cfg(feature = "bulk")]
 pub fn value_from_csv(rec: &HashMap<String, String>, sobjecttype: &SObjectType) -> Result<Value> {
     let mut ret = Map::new();
 
@@ -41,10 +55,12 @@ pub(crate) trait ResultStreamManager: Send + Sync {
 }
 
 pub(crate) struct ResultStreamState<T: SObjectDeserialization> {
-    pub buffer: VecDeque<T>, // TODO: we should decouple the buffer from the locator state to enable prefetching
+    pub buffer: VecDeque<T>,
     pub locator: Option<String>,
     pub total_size: Option<usize>,
     pub done: bool,
+    /// The size of the page's data, if known.
+    pub bytes: usize,
 }
 
 impl<T> ResultStreamState<T>
@@ -62,16 +78,32 @@ where
             locator,
             total_size,
             done,
+            bytes: 0,
         }
     }
 }
 
 pub struct ResultStream<T: SObjectDeserialization + Unpin> {
     manager: Box<dyn ResultStreamManager<Output = T>>,
+    // The locator state of the last page retrieved. Its records are moved
+    // to `pages` as soon as it arrives, so that the next page can be fetched.
     state: Option<ResultStreamState<T>>,
+    buffer: VecDeque<T>,
+    pages: VecDeque<VecDeque<T>>,
+    prefetch: usize,
     yielded: usize,
-    error: Option<Error>, // TODO
+    // A terminal error, yielded once the records retrieved before it are drained.
+    error: Option<Error>,
     retrieve_task: Option<JoinHandle<Result<ResultStreamState<T>>>>,
+    // The locator and total size of the state from which the current page
+    // is being retrieved, so that a failed retrieval can be retried.
+    pending: Option<(Option<String>, Option<usize>)>,
+    max_retries: usize,
+    retry_backoff: Duration,
+    attempts: usize,
+    retry_delay: Option<Pin<Box<Sleep>>>,
+    metrics: MetricsRecorder,
+    retrieval_started: Option<Instant>,
 }
 
 impl<T> ResultStream<T>
@@ -82,26 +114,145 @@ where
         initial_values: Option<ResultStreamState<T>>,
         manager: Box<dyn ResultStreamManager<Output = T>>,
     ) -> Self {
+        let mut state = initial_values;
+        let buffer = state
+            .as_mut()
+            .map(|s| mem::take(&mut s.buffer))
+            .unwrap_or_default();
+        let metrics = MetricsRecorder::new();
+        if let Some(state) = &state {
+            // The first page was retrieved before the stream was created.
+            metrics.record_page(state.bytes as u64, None);
+        }
+
         ResultStream {
             manager,
-            state: initial_values,
+            state,
+            buffer,
+            pages: VecDeque::new(),
+            prefetch: 0,
             retrieve_task: None,
             yielded: 0,
             error: None,
+            pending: None,
+            max_retries: 0,
+            retry_backoff: Duration::ZERO,
+            attempts: 0,
+            retry_delay: None,
+            metrics,
+            retrieval_started: None,
         }
     }
 
-    fn try_to_yield(&mut self) -> Option<T> {
-        if let Some(state) = &mut self.state {
-            if let Some(item) = state.buffer.pop_front() {
-                self.yielded += 1;
-                Some(item)
-            } else {
-                None
-            }
-        } else {
+    /// Retry a failed page retrieval up to `max_retries` times, waiting
+    /// `backoff` before the first retry and doubling the wait for each after.
+    /// Once retries are exhausted, the stream yields a
+    /// `SalesforceError::ResultPageError` carrying the locator of the failed
+    /// page, and then ends. An expired locator is never retried; the stream
+    /// instead yields `SalesforceError::LocatorExpired` with a `ResumeHint`.
+    #[must_use]
+    pub fn with_retries(mut self, max_retries: usize, backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Retrieve up to `pages` pages ahead while the current page is consumed.
+    /// By default, the next page is retrieved only once the current page is exhausted.
+    #[must_use]
+    pub fn with_prefetch(mut self, pages: usize) -> Self {
+        self.prefetch = pages;
+        self
+    }
+
+    /// Record the stream's metrics in `metrics`, such as one configured to log them.
+    #[must_use]
+    pub fn with_metrics(mut self, metrics: MetricsRecorder) -> Self {
+        let current = self.metrics.get_metrics();
+        metrics.record_records(current.records);
+        if current.pages > 0 {
+            metrics.record_page(current.bytes, None);
+        }
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn get_metrics(&self) -> StreamMetrics {
+        self.metrics.get_metrics()
+    }
+
+    /// Whether another page should be retrieved before the consumer needs it.
+    fn should_prefetch(&self) -> bool {
+        self.retrieve_task.is_none()
+            && self.retry_delay.is_none()
+            && self.pages.len() < self.prefetch
+            && self.state.as_ref().is_some_and(|s| !s.done)
+    }
+
+    fn start_retrieval(&mut self) {
+        let state = mem::take(&mut self.state);
+        self.pending = state.as_ref().map(|s| (s.locator.clone(), s.total_size));
+        self.retrieval_started = Some(Instant::now());
+        self.retrieve_task = Some(self.manager.get_next_future(state));
+    }
+
+    /// Handle a failed retrieval, returning the terminal error if no retries remain.
+    fn retrieval_failed(&mut self, error: Error) -> Option<Error> {
+        let pending = mem::take(&mut self.pending);
+
+        if is_locator_expired(&error) {
+            let (locator, total_size) = pending.unwrap_or((None, None));
+            // Records already retrieved are still yielded ahead of the error.
+            let records_retrieved = self.yielded
+                + self.buffer.len()
+                + self.pages.iter().map(|p| p.len()).sum::<usize>();
+
+            self.state = Some(ResultStreamState::new(
+                VecDeque::new(),
+                None,
+                total_size,
+                true,
+            ));
+            Some(
+                SalesforceError::LocatorExpired {
+                    resume_hint: ResumeHint {
+                        locator,
+                        records_retrieved,
+                    },
+                }
+                .into(),
+            )
+        } else if self.attempts < self.max_retries {
+            self.retry_delay = Some(Box::pin(sleep(
+                self.retry_backoff * 2u32.saturating_pow(self.attempts as u32),
+            )));
+            self.attempts += 1;
+            self.state = pending.map(|(locator, total_size)| {
+                ResultStreamState::new(VecDeque::new(), locator, total_size, false)
+            });
             None
+        } else {
+            let (locator, total_size) = pending.unwrap_or((None, None));
+
+            // End the stream after yielding the error.
+            self.state = Some(ResultStreamState::new(
+                VecDeque::new(),
+                None,
+                total_size,
+                true,
+            ));
+            Some(SalesforceError::ResultPageError { locator, error }.into())
+        }
+    }
+
+    fn try_to_yield(&mut self) -> Option<T> {
+        while self.buffer.is_empty() {
+            self.buffer = self.pages.pop_front()?;
         }
+
+        self.yielded += 1;
+        self.metrics.record_records(1);
+        self.buffer.pop_front()
     }
 }
 
@@ -116,17 +267,41 @@ where
             // First, check if we have sObjects ready to yield.
             let sobject = self.try_to_yield();
             if let Some(sobject) = sobject {
+                if self.should_prefetch() {
+                    self.start_retrieval();
+                }
                 return Poll::Ready(Some(Ok(sobject)));
+            } else if let Some(error) = self.error.take() {
+                return Poll::Ready(Some(Err(error)));
+            } else if let Some(delay) = &mut self.retry_delay {
+                // We are waiting to retry a failed retrieval.
+                if delay.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                self.retry_delay = None;
+                self.start_retrieval();
             } else if let Some(task) = &mut self.retrieve_task {
                 // We have a task waiting already.
                 // TODO: can we replace this task with a channel?
                 let fut = unsafe { Pin::new_unchecked(task) };
                 let poll = fut.poll(cx);
                 if let Poll::Ready(result) = poll {
-                    self.state = Some(result??);
-
                     self.retrieve_task = None;
-                    // Fall through, next loop iteration will yield
+
+                    match result.map_err(Error::from).and_then(|r| r) {
+                        Ok(mut state) => {
+                            let latency = self.retrieval_started.take().map(|s| s.elapsed());
+                            self.metrics.record_page(state.bytes as u64, latency);
+                            self.pages.push_back(mem::take(&mut state.buffer));
+                            self.state = Some(state);
+                            self.pending = None;
+                            self.attempts = 0;
+                            // Fall through, next loop iteration will yield
+                        }
+                        Err(e) => {
+                            self.error = self.retrieval_failed(e);
+                        }
+                    }
                 } else {
                     return Poll::Pending;
                 }
@@ -136,13 +311,11 @@ where
                     return Poll::Ready(None);
                 } else {
                     // Create a new task to get the next state.
-                    let state = mem::take(&mut self.state);
-                    self.retrieve_task = Some(self.manager.get_next_future(state));
+                    self.start_retrieval();
                 }
             } else {
                 // Create a new task to get the next state.
-                let state = mem::take(&mut self.state);
-                self.retrieve_task = Some(self.manager.get_next_future(state));
+                self.start_retrieval();
             }
         }
     }
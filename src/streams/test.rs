@@ -1 +1,254 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
+use anyhow::Result;
+use tokio::task::{spawn, JoinHandle};
+use tokio_stream::StreamExt;
+
+use super::{ResultStream, ResultStreamManager, ResultStreamState};
+use crate::api::metrics::MetricsRecorder;
+use crate::api::retry::is_locator_expired;
+use crate::errors::{ResumeHint, SalesforceError};
+use crate::rest::ApiError;
+use crate::test_integration_base::Account;
+
+// Serves two pages of one record each, failing the first `failures`
+// attempts to retrieve the second page, with an expired locator error if
+// `expired` is set.
+struct FlakyManager {
+    failures: usize,
+    expired: bool,
+    attempts: Arc<AtomicUsize>,
+}
+
+impl ResultStreamManager for FlakyManager {
+    type Output = Account;
+
+    fn get_next_future(
+        &mut self,
+        state: Option<ResultStreamState<Account>>,
+    ) -> JoinHandle<Result<ResultStreamState<Account>>> {
+        let locator = state.and_then(|s| s.locator);
+        let failures = self.failures;
+        let expired = self.expired;
+        let attempts = Arc::clone(&self.attempts);
+
+        spawn(async move {
+            let account = |name: &str| Account {
+                id: None,
+                name: name.to_owned(),
+            };
+
+            match locator {
+                None => Ok(ResultStreamState::new(
+                    VecDeque::from(vec![account("First")]),
+                    Some("page2".to_owned()),
+                    Some(2),
+                    false,
+                )),
+                Some(_) => {
+                    if attempts.fetch_add(1, Ordering::SeqCst) < failures {
+                        if expired {
+                            Err(SalesforceError::ApiErrors(vec![ApiError {
+                                message: "invalid query locator".to_owned(),
+                                error_code: Some("INVALID_QUERY_LOCATOR".to_owned()),
+                                status_code: None,
+                                duplicate_result: None,
+                            }])
+                            .into())
+                        } else {
+                            Err(SalesforceError::UnknownError.into())
+                        }
+                    } else {
+                        Ok(ResultStreamState::new(
+                            VecDeque::from(vec![account("Second")]),
+                            None,
+                            Some(2),
+                            true,
+                        ))
+                    }
+                }
+            }
+        })
+    }
+}
+
+fn get_stream(failures: usize) -> (ResultStream<Account>, Arc<AtomicUsize>) {
+    get_flaky_stream(failures, false)
+}
+
+fn get_flaky_stream(failures: usize, expired: bool) -> (ResultStream<Account>, Arc<AtomicUsize>) {
+    let attempts = Arc::new(AtomicUsize::new(0));
+
+    (
+        ResultStream::new(
+            None,
+            Box::new(FlakyManager {
+                failures,
+                expired,
+                attempts: Arc::clone(&attempts),
+            }),
+        ),
+        attempts,
+    )
+}
+
+#[tokio::test]
+async fn test_result_stream_retries_failed_page() -> Result<()> {
+    let (stream, attempts) = get_stream(2);
+    let mut stream = stream.with_retries(2, Duration::from_millis(1));
+
+    assert_eq!(stream.next().await.unwrap()?.name, "First");
+    assert_eq!(stream.next().await.unwrap()?.name, "Second");
+    assert!(stream.next().await.is_none());
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_result_stream_exposes_locator_after_retries() -> Result<()> {
+    let (stream, attempts) = get_stream(5);
+    let mut stream = stream.with_retries(1, Duration::from_millis(1));
+
+    assert_eq!(stream.next().await.unwrap()?.name, "First");
+
+    let err = match stream.next().await.unwrap() {
+        Err(err) => err,
+        Ok(_) => panic!("Expected an error"),
+    };
+    match err.downcast_ref::<SalesforceError>() {
+        Some(SalesforceError::ResultPageError { locator, .. }) => {
+            assert_eq!(locator.as_deref(), Some("page2"))
+        }
+        _ => panic!("Expected a ResultPageError"),
+    }
+
+    assert!(stream.next().await.is_none());
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_result_stream_does_not_retry_expired_locator() -> Result<()> {
+    let (stream, attempts) = get_flaky_stream(5, true);
+    let mut stream = stream.with_retries(3, Duration::from_millis(1));
+
+    assert_eq!(stream.next().await.unwrap()?.name, "First");
+
+    let err = match stream.next().await.unwrap() {
+        Err(err) => err,
+        Ok(_) => panic!("Expected an error"),
+    };
+    assert!(is_locator_expired(&err));
+    match err.downcast_ref::<SalesforceError>() {
+        Some(SalesforceError::LocatorExpired { resume_hint }) => assert_eq!(
+            resume_hint,
+            &ResumeHint {
+                locator: Some("page2".to_owned()),
+                records_retrieved: 1,
+            }
+        ),
+        _ => panic!("Expected a LocatorExpired error"),
+    }
+
+    assert!(stream.next().await.is_none());
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
+
+// Serves `pages` pages of two records each, counting retrievals.
+struct CountingManager {
+    pages: usize,
+    retrievals: Arc<AtomicUsize>,
+}
+
+impl ResultStreamManager for CountingManager {
+    type Output = Account;
+
+    fn get_next_future(
+        &mut self,
+        state: Option<ResultStreamState<Account>>,
+    ) -> JoinHandle<Result<ResultStreamState<Account>>> {
+        let page: usize = state
+            .and_then(|s| s.locator)
+            .map_or(0, |l| l.parse().unwrap());
+        let pages = self.pages;
+        self.retrievals.fetch_add(1, Ordering::SeqCst);
+
+        spawn(async move {
+            let account = |i: usize| Account {
+                id: None,
+                name: format!("Account {}", i),
+            };
+            let done = page + 1 == pages;
+
+            Ok(ResultStreamState::new(
+                VecDeque::from(vec![account(page * 2), account(page * 2 + 1)]),
+                (!done).then(|| (page + 1).to_string()),
+                Some(pages * 2),
+                done,
+            ))
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_result_stream_prefetch() -> Result<()> {
+    let retrievals = Arc::new(AtomicUsize::new(0));
+    let get_stream = || {
+        ResultStream::new(
+            None,
+            Box::new(CountingManager {
+                pages: 3,
+                retrievals: Arc::clone(&retrievals),
+            }),
+        )
+    };
+
+    let mut stream = get_stream();
+    stream.next().await.unwrap()?;
+    assert_eq!(retrievals.swap(0, Ordering::SeqCst), 1);
+
+    // The next page is requested as soon as the first record is yielded.
+    let mut stream = get_stream().with_prefetch(1);
+    assert_eq!(stream.next().await.unwrap()?.name, "Account 0");
+    assert_eq!(retrievals.load(Ordering::SeqCst), 2);
+
+    let names: Vec<String> = stream.map(|a| a.unwrap().name).collect().await;
+    assert_eq!(
+        names,
+        (1..6).map(|i| format!("Account {}", i)).collect::<Vec<_>>()
+    );
+    assert_eq!(retrievals.load(Ordering::SeqCst), 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_result_stream_metrics() -> Result<()> {
+    let recorder = MetricsRecorder::new().with_log("Accounts", Duration::ZERO);
+    let mut stream = ResultStream::new(
+        None,
+        Box::new(CountingManager {
+            pages: 2,
+            retrievals: Arc::new(AtomicUsize::new(0)),
+        }),
+    )
+    .with_metrics(recorder.clone());
+
+    stream.next().await.unwrap()?;
+    let metrics = stream.get_metrics();
+    assert_eq!((metrics.records, metrics.pages), (1, 1));
+
+    while stream.next().await.is_some() {}
+    let metrics = recorder.get_metrics();
+    assert_eq!((metrics.records, metrics.pages, metrics.bytes), (4, 2, 0));
+    assert!(metrics.average_page_latency.is_some());
+
+    Ok(())
+}
@@ -1,8 +1,17 @@
 use anyhow::Result;
-use reqwest::Url;
+use async_trait::async_trait;
+use bytes::Bytes;
+use reqwest::{Method, Url};
 use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
 
+use crate::api::transport::{HttpTransport, TransportBody, TransportRequest, TransportResponse};
 use crate::prelude::*;
 use crate::{api::Connection, auth::AccessTokenAuth};
 
@@ -19,6 +28,257 @@ pub fn get_test_connection() -> Result<Connection> {
     )
 }
 
+pub fn get_offline_connection() -> Result<Connection> {
+    // Nothing listens on this port, so any request that is actually sent fails.
+    Connection::new(
+        Box::new(AccessTokenAuth::new(
+            "token".to_owned(),
+            Url::parse("http://127.0.0.1:9")?,
+        )),
+        "v52.0",
+    )
+}
+
+/// Serve one canned `(status, body)` response per connection, in order, and
+/// return a Connection to the server with a count of the requests received.
+/// Requests beyond the canned responses fail.
+pub async fn serve_responses<B: Into<String>>(
+    responses: Vec<(&'static str, B)>,
+) -> Result<(Connection, Arc<AtomicUsize>)> {
+    serve_responses_with_headers(
+        responses
+            .into_iter()
+            .map(|(status, body)| (status, vec![], body))
+            .collect(),
+    )
+    .await
+}
+
+/// A canned `(status, headers, body)` response.
+pub type CannedResponse<B> = (&'static str, Vec<(&'static str, &'static str)>, B);
+
+/// Like `serve_responses()`, but each response also carries the given headers.
+pub async fn serve_responses_with_headers<B: Into<String>>(
+    responses: Vec<CannedResponse<B>>,
+) -> Result<(Connection, Arc<AtomicUsize>)> {
+    let responses: Vec<CannedResponse<String>> = responses
+        .into_iter()
+        .map(|(status, headers, body)| (status, headers, body.into()))
+        .collect();
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = Url::parse(&format!("http://{}", listener.local_addr()?))?;
+    let count = Arc::new(AtomicUsize::new(0));
+    let server_count = count.clone();
+
+    tokio::spawn(async move {
+        for (status, headers, body) in responses {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let _ = socket.read(&mut buf).await.unwrap();
+            server_count.fetch_add(1, Ordering::SeqCst);
+            socket
+                .write_all(
+                    format!(
+                        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+                        status,
+                        body.len(),
+                        headers
+                            .iter()
+                            .map(|(name, value)| format!("{}: {}\r\n", name, value))
+                            .collect::<String>(),
+                        body
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+        }
+    });
+
+    Ok((
+        Connection::new(
+            Box::new(AccessTokenAuth::new("token".to_owned(), url)),
+            "v52.0",
+        )?,
+        count,
+    ))
+}
+
+/// A request received by a `RouteTransport`, with its body read into memory.
+pub struct RecordedRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: http::HeaderMap,
+    pub body: Bytes,
+    /// Whether the body was streamed, rather than sent whole.
+    pub streamed: bool,
+}
+
+impl RecordedRequest {
+    pub fn path(&self) -> &str {
+        self.url.path()
+    }
+
+    pub fn get_query_parameter(&self, name: &str) -> Option<String> {
+        self.url
+            .query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    }
+
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json(&self) -> Result<Value> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+type RouteHandler = Box<dyn Fn(&RecordedRequest) -> Result<TransportResponse> + Send + Sync>;
+
+struct Route {
+    method: Option<Method>,
+    path: String,
+    delay: Duration,
+    handler: RouteHandler,
+}
+
+/// An `HttpTransport` that answers each request from the first route whose
+/// method matches and whose path is a suffix of the request's path, ignoring
+/// trailing slashes, and records every request it receives. A request that
+/// matches no route panics.
+#[derive(Default)]
+pub struct RouteTransport {
+    routes: Vec<Route>,
+    requests: Mutex<Vec<RecordedRequest>>,
+}
+
+impl RouteTransport {
+    pub fn new() -> RouteTransport {
+        RouteTransport::default()
+    }
+
+    /// Answer requests with `method` to paths ending in `path` by calling `handler`.
+    #[must_use]
+    pub fn with_route<F>(mut self, method: Method, path: &str, handler: F) -> Self
+    where
+        F: Fn(&RecordedRequest) -> Result<TransportResponse> + Send + Sync + 'static,
+    {
+        self.add_route(Some(method), path, Box::new(handler));
+        self
+    }
+
+    /// Answer requests with any method to paths ending in `path` by calling `handler`.
+    /// An empty `path` matches every request.
+    #[must_use]
+    pub fn with_any_route<F>(mut self, path: &str, handler: F) -> Self
+    where
+        F: Fn(&RecordedRequest) -> Result<TransportResponse> + Send + Sync + 'static,
+    {
+        self.add_route(None, path, Box::new(handler));
+        self
+    }
+
+    /// Answer requests with `method` to paths ending in `path` with a fixed JSON body.
+    #[must_use]
+    pub fn with_json(self, method: Method, path: &str, status: u16, body: Value) -> Self {
+        self.with_route(method, path, move |_| json_response(status, &body))
+    }
+
+    /// Wait for `delay` before answering from the route added last.
+    #[must_use]
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        if let Some(route) = self.routes.last_mut() {
+            route.delay = delay;
+        }
+        self
+    }
+
+    fn add_route(&mut self, method: Option<Method>, path: &str, handler: RouteHandler) {
+        self.routes.push(Route {
+            method,
+            path: path.trim_end_matches('/').to_owned(),
+            delay: Duration::ZERO,
+            handler,
+        });
+    }
+
+    /// The requests received so far, in order.
+    pub fn get_requests(&self) -> MutexGuard<'_, Vec<RecordedRequest>> {
+        self.requests.lock().unwrap()
+    }
+
+    /// The number of requests received so far to paths ending in `path`.
+    pub fn count_requests(&self, path: &str) -> usize {
+        let path = path.trim_end_matches('/');
+        self.get_requests()
+            .iter()
+            .filter(|r| r.path().trim_end_matches('/').ends_with(path))
+            .count()
+    }
+}
+
+#[async_trait]
+impl HttpTransport for RouteTransport {
+    async fn send(&self, request: TransportRequest) -> Result<TransportResponse> {
+        let (parts, body) = request.into_parts();
+        let request = RecordedRequest {
+            method: parts.method,
+            url: Url::parse(&parts.uri.to_string())?,
+            headers: parts.headers,
+            streamed: body.as_bytes().is_none(),
+            body: body.into_bytes().await?,
+        };
+        let path = request.path().trim_end_matches('/');
+        let route = self
+            .routes
+            .iter()
+            .find(|r| {
+                (r.method.is_none() || r.method.as_ref() == Some(&request.method))
+                    && path.ends_with(&r.path)
+            })
+            .unwrap_or_else(|| panic!("Unexpected {} request to {}", request.method, path));
+
+        let response = (route.handler)(&request);
+        self.requests.lock().unwrap().push(request);
+        if !route.delay.is_zero() {
+            tokio::time::sleep(route.delay).await;
+        }
+
+        response
+    }
+}
+
+/// Serve requests from `transport`, and return a Connection that sends its
+/// requests there along with the transport, to inspect the requests received.
+pub fn serve_routes(transport: RouteTransport) -> Result<(Connection, Arc<RouteTransport>)> {
+    let conn = get_offline_connection()?;
+    let transport = Arc::new(transport);
+    conn.set_transport(transport.clone());
+
+    Ok((conn, transport))
+}
+
+/// A response with a JSON body.
+pub fn json_response<B: ToString + ?Sized>(status: u16, body: &B) -> Result<TransportResponse> {
+    Ok(http::Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(TransportBody::from(body.to_string()))?)
+}
+
+/// A response with an unlabelled body, such as CSV or binary content.
+pub fn raw_response(status: u16, body: impl Into<Bytes>) -> Result<TransportResponse> {
+    Ok(http::Response::builder()
+        .status(status)
+        .body(TransportBody::Bytes(body.into()))?)
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Account {
@@ -65,3 +325,164 @@ impl SingleTypedSObject for Account {
         "Account"
     }
 }
+
+pub fn get_test_record_type_describe(developer_name: &str, id: &str) -> Value {
+    json!({
+        "active": true,
+        "available": true,
+        "defaultRecordTypeMapping": false,
+        "developerName": developer_name,
+        "master": false,
+        "name": developer_name,
+        "recordTypeId": id,
+        "urls": {}
+    })
+}
+
+pub fn get_test_field_describe(name: &str, soap_type: &str, field_type: &str) -> Value {
+    // Built from a string, as this many keys exceed the `json!` recursion limit.
+    let mut field: Value = serde_json::from_str(
+        r#"{
+            "aggregatable": true,
+            "aiPredictionField": false,
+            "autoNumber": false,
+            "byteLength": 0,
+            "calculated": false,
+            "calculatedFormula": null,
+            "cascadeDelete": false,
+            "caseSensitive": false,
+            "compoundFieldName": null,
+            "controllerName": null,
+            "createable": true,
+            "custom": false,
+            "defaultValue": null,
+            "defaultValueFormula": null,
+            "defaultedOnCreate": false,
+            "dependentPicklist": false,
+            "deprecatedAndHidden": false,
+            "digits": 0,
+            "displayLocationInDecimal": false,
+            "encrypted": false,
+            "externalId": false,
+            "filterable": true,
+            "formulaTreatNullNumberAsZero": false,
+            "groupable": true,
+            "highScaleNumber": false,
+            "htmlFormatted": false,
+            "idLookup": false,
+            "inlineHelpText": null,
+            "length": 255,
+            "nameField": false,
+            "namePointing": false,
+            "nillable": true,
+            "permissionable": true,
+            "picklistValues": [],
+            "polymorphicForeignKey": false,
+            "precision": 0,
+            "queryByDistance": false,
+            "referenceTargetField": null,
+            "referenceTo": [],
+            "relationshipName": null,
+            "relationshipOrder": null,
+            "restrictedDelete": false,
+            "restrictedPicklist": false,
+            "scale": 0,
+            "searchPrefilterable": false,
+            "sortable": true,
+            "unique": false,
+            "updateable": true,
+            "writeRequiresMasterRead": false
+        }"#,
+    )
+    .unwrap();
+
+    let map = field.as_object_mut().unwrap();
+    map.insert("name".to_owned(), json!(name));
+    map.insert("label".to_owned(), json!(name));
+    map.insert("soapType".to_owned(), json!(soap_type));
+    map.insert("type".to_owned(), json!(field_type));
+
+    field
+}
+
+/// Build an `SObjectType` from a synthetic describe, for tests that do not
+/// have access to an org.
+pub fn get_test_sobject_type(
+    name: &str,
+    fields: Vec<Value>,
+    record_types: Vec<Value>,
+) -> Result<SObjectType> {
+    get_test_sobject_type_with_children(name, fields, record_types, vec![])
+}
+
+pub fn get_test_sobject_type_with_children(
+    name: &str,
+    fields: Vec<Value>,
+    record_types: Vec<Value>,
+    child_relationships: Vec<Value>,
+) -> Result<SObjectType> {
+    Ok(SObjectType::new(
+        name.to_owned(),
+        serde_json::from_value(get_test_sobject_describe(
+            name,
+            fields,
+            record_types,
+            child_relationships,
+        )?)?,
+    ))
+}
+
+/// The JSON describe of a test sObject, as returned by Salesforce.
+pub fn get_test_sobject_describe(
+    name: &str,
+    fields: Vec<Value>,
+    record_types: Vec<Value>,
+    child_relationships: Vec<Value>,
+) -> Result<Value> {
+    let mut describe: Value = serde_json::from_str(
+        r#"{
+            "activateable": false,
+            "compactLayoutable": true,
+            "createable": true,
+            "custom": false,
+            "customSetting": false,
+            "deepCloneable": false,
+            "deletable": true,
+            "feedEnabled": false,
+            "hasSubtypes": false,
+            "isInterface": false,
+            "isSubtype": false,
+            "keyPrefix": "001",
+            "layoutable": true,
+            "listviewable": null,
+            "lookupLayoutable": null,
+            "mergeable": true,
+            "mruEnabled": true,
+            "namedLayoutInfos": [],
+            "networkScopeFieldName": null,
+            "queryable": true,
+            "replicateable": true,
+            "retrieveable": true,
+            "searchLayoutable": true,
+            "searchable": true,
+            "supportedScopes": [],
+            "triggerable": true,
+            "undeletable": true,
+            "updateable": true,
+            "urls": {}
+        }"#,
+    )?;
+
+    let map = describe.as_object_mut().unwrap();
+    map.insert("name".to_owned(), json!(name));
+    map.insert("label".to_owned(), json!(name));
+    map.insert("labelPlural".to_owned(), json!(name));
+    map.insert("fields".to_owned(), Value::Array(fields));
+    map.insert("recordTypeInfos".to_owned(), Value::Array(record_types));
+    map.insert(
+        "childRelationships".to_owned(),
+        Value::Array(child_relationships),
+    );
+
+    Ok(describe)
+}
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use serde_json::json;
+
+use super::{generate_records, GeneratorConfig};
+use crate::data::{FieldValue, SalesforceId};
+use crate::test_integration_base::{get_test_field_describe, get_test_sobject_type};
+
+fn get_required(mut field: serde_json::Value) -> serde_json::Value {
+    field["nillable"] = json!(false);
+    field["defaultedOnCreate"] = json!(false);
+    field
+}
+
+#[test]
+fn test_generate_records() -> Result<()> {
+    let mut name = get_required(get_test_field_describe("Name", "xsd:string", "string"));
+    name["length"] = json!(12);
+    name["nameField"] = json!(true);
+    let mut rating = get_required(get_test_field_describe("Rating", "xsd:string", "picklist"));
+    rating["restrictedPicklist"] = json!(true);
+    rating["picklistValues"] = json!([
+        {"active": true, "defaultValue": false, "label": "Hot", "validFor": null, "value": "Hot"},
+        {"active": false, "defaultValue": false, "label": "Old", "validFor": null, "value": "Old"}
+    ]);
+    let mut amount = get_required(get_test_field_describe("Amount", "xsd:double", "currency"));
+    amount["precision"] = json!(5);
+    amount["scale"] = json!(2);
+    let mut parent_id = get_required(get_test_field_describe("ParentId", "tns:ID", "reference"));
+    parent_id["referenceTo"] = json!(["Account"]);
+    let mut formula = get_test_field_describe("Score__c", "xsd:double", "double");
+    formula["calculated"] = json!(true);
+    let sobject_type = get_test_sobject_type(
+        "Account",
+        vec![
+            name,
+            rating,
+            amount,
+            parent_id,
+            formula,
+            get_test_field_describe("Website", "xsd:string", "url"),
+        ],
+        vec![],
+    )?;
+    let parent = SalesforceId::new("001000000000001AAA")?;
+    let config = GeneratorConfig::new()
+        .with_seed(42)
+        .with_references("ParentId", &[parent]);
+
+    let records = generate_records(&sobject_type, 50, &config)?;
+
+    assert_eq!(records.len(), 50);
+    for record in &records {
+        assert!(record.required_missing().is_empty());
+        match record.get("Name") {
+            Some(FieldValue::String(name)) => assert!(name.chars().count() <= 12),
+            _ => panic!("Expected a Name"),
+        }
+        assert_eq!(
+            record.get("Rating"),
+            Some(&FieldValue::String("Hot".to_owned()))
+        );
+        match record.get("Amount") {
+            Some(FieldValue::Double(amount)) => assert!(*amount < 1000.0),
+            _ => panic!("Expected an Amount"),
+        }
+        assert_eq!(record.get("ParentId"), Some(&FieldValue::Id(parent)));
+        assert!(record.get("Score__c").is_none());
+    }
+
+    // Generation is reproducible from a seed.
+    assert_eq!(generate_records(&sobject_type, 50, &config)?, records);
+
+    // Optional fields are filled at the configured rate.
+    assert!(
+        generate_records(&sobject_type, 20, &config.clone().with_fill_rate(0.0))?
+            .iter()
+            .all(|r| r.get("Website").is_none())
+    );
+    assert!(
+        generate_records(&sobject_type, 20, &config.clone().with_fill_rate(1.0))?
+            .iter()
+            .all(|r| r.get("Website").is_some())
+    );
+
+    // Required lookups need candidate Ids.
+    assert!(generate_records(&sobject_type, 1, &GeneratorConfig::new()).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,162 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use anyhow::Result;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    api::Connection,
+    rest::query::{query_all_values, QueryRequest},
+};
+
+/// A metadata component that participates in a dependency.
+///
+/// Ids are kept as strings because the Tooling API reports some components,
+/// such as standard objects, by name rather than by Salesforce Id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MetadataComponent {
+    pub id: String,
+    pub name: String,
+    pub namespace: Option<String>,
+    pub component_type: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct MetadataComponentDependency {
+    pub metadata_component_id: String,
+    pub metadata_component_name: String,
+    pub metadata_component_namespace: Option<String>,
+    pub metadata_component_type: String,
+    pub ref_metadata_component_id: String,
+    pub ref_metadata_component_name: String,
+    pub ref_metadata_component_namespace: Option<String>,
+    pub ref_metadata_component_type: String,
+}
+
+impl MetadataComponentDependency {
+    pub fn get_component(&self) -> MetadataComponent {
+        MetadataComponent {
+            id: self.metadata_component_id.clone(),
+            name: self.metadata_component_name.clone(),
+            namespace: self.metadata_component_namespace.clone(),
+            component_type: self.metadata_component_type.clone(),
+        }
+    }
+
+    pub fn get_referenced_component(&self) -> MetadataComponent {
+        MetadataComponent {
+            id: self.ref_metadata_component_id.clone(),
+            name: self.ref_metadata_component_name.clone(),
+            namespace: self.ref_metadata_component_namespace.clone(),
+            component_type: self.ref_metadata_component_type.clone(),
+        }
+    }
+}
+
+/// A directed graph of metadata components, with an edge from each component
+/// to each component it references.
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    components: HashMap<String, MetadataComponent>,
+    dependencies: HashMap<String, Vec<String>>,
+    dependents: HashMap<String, Vec<String>>,
+}
+
+impl DependencyGraph {
+    pub fn new(rows: &[MetadataComponentDependency]) -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+
+        for row in rows {
+            let component = row.get_component();
+            let referenced = row.get_referenced_component();
+
+            graph
+                .dependencies
+                .entry(component.id.clone())
+                .or_default()
+                .push(referenced.id.clone());
+            graph
+                .dependents
+                .entry(referenced.id.clone())
+                .or_default()
+                .push(component.id.clone());
+            graph.components.insert(component.id.clone(), component);
+            graph.components.insert(referenced.id.clone(), referenced);
+        }
+
+        graph
+    }
+
+    pub fn get_component(&self, id: &str) -> Option<&MetadataComponent> {
+        self.components.get(id)
+    }
+
+    pub fn get_components(&self) -> impl Iterator<Item = &MetadataComponent> {
+        self.components.values()
+    }
+
+    /// The components directly referenced by the component `id`.
+    pub fn get_dependencies(&self, id: &str) -> Vec<&MetadataComponent> {
+        self.resolve(self.dependencies.get(id))
+    }
+
+    /// The components that directly reference the component `id`.
+    pub fn get_dependents(&self, id: &str) -> Vec<&MetadataComponent> {
+        self.resolve(self.dependents.get(id))
+    }
+
+    /// Every component reachable from the component `id`, in breadth-first order.
+    pub fn get_transitive_dependencies(&self, id: &str) -> Vec<&MetadataComponent> {
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut queue: VecDeque<&str> = VecDeque::new();
+        let mut result = Vec::new();
+
+        seen.insert(id);
+        queue.push_back(id);
+
+        while let Some(current) = queue.pop_front() {
+            for next in self.dependencies.get(current).into_iter().flatten() {
+                if seen.insert(next) {
+                    queue.push_back(next);
+                    if let Some(component) = self.components.get(next) {
+                        result.push(component);
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    fn resolve(&self, ids: Option<&Vec<String>>) -> Vec<&MetadataComponent> {
+        ids.into_iter()
+            .flatten()
+            .filter_map(|id| self.components.get(id))
+            .collect()
+    }
+}
+
+impl Connection {
+    /// Build a dependency graph from `MetadataComponentDependency` records matching
+    /// `filter`, a SOQL `WHERE` clause without the `WHERE` keyword.
+    ///
+    /// The Tooling API returns at most 2,000 dependency records per query, so
+    /// large orgs should filter by component type or name.
+    pub async fn get_dependency_graph(&self, filter: Option<&str>) -> Result<DependencyGraph> {
+        let mut query = "SELECT MetadataComponentId, MetadataComponentName, MetadataComponentNamespace, \
+            MetadataComponentType, RefMetadataComponentId, RefMetadataComponentName, \
+            RefMetadataComponentNamespace, RefMetadataComponentType FROM MetadataComponentDependency"
+            .to_owned();
+        if let Some(filter) = filter {
+            query.push_str(&format!(" WHERE {}", filter));
+        }
+
+        let rows = query_all_values(self, &QueryRequest::new(&query, false).with_tooling_api())
+            .await?
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<Vec<MetadataComponentDependency>, _>>()?;
+
+        Ok(DependencyGraph::new(&rows))
+    }
+}
@@ -2,10 +2,23 @@ use std::{error::Error, fmt::Display};
 
 use anyhow::Result;
 use reqwest::Method;
-use serde_derive::Deserialize;
-use serde_json::json;
-
-use crate::{api::Connection, api::SalesforceRequest, errors::SalesforceError};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    api::Connection,
+    api::SalesforceRequest,
+    data::{SObject, SObjectType, SalesforceId},
+    errors::SalesforceError,
+    rest::describe::SObjectDescribeRequest,
+    rest::query::{query_all_as, QueryRequest, QueryResult},
+    rest::rows::{
+        SObjectCreateRequest, SObjectDeleteRequest, SObjectRetrieveRequest, SObjectUpdateRequest,
+    },
+};
+
+pub mod dependencies;
+pub mod symbols;
 
 #[cfg(test)]
 mod test;
@@ -20,7 +33,7 @@ impl ExecuteAnonymousApexRequest {
     }
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ExecuteAnonymousApexResponse {
     pub line: i64,
@@ -101,6 +114,57 @@ impl SalesforceRequest for ExecuteAnonymousApexRequest {
     fn get_query_parameters(&self) -> Option<serde_json::Value> {
         Some(json!({"anonymousBody": self.anonymous_body}))
     }
+
+    fn is_mutating(&self) -> bool {
+        // Anonymous Apex is executed via GET, but may perform arbitrary DML.
+        true
+    }
+
+    fn get_dry_run_result(&self, _conn: &Connection) -> anyhow::Result<Self::ReturnValue> {
+        Ok(ExecuteAnonymousApexResponse {
+            line: -1,
+            column: -1,
+            compiled: true,
+            success: true,
+            compile_problem: None,
+            exception_stack_trace: None,
+            exception_message: None,
+        })
+    }
+}
+
+/// A SOQL query against the Tooling API, such as for `ApexClass`
+/// or `TraceFlag` records.
+pub struct ToolingQueryRequest {
+    request: QueryRequest,
+}
+
+impl ToolingQueryRequest {
+    pub fn new(query: &str) -> ToolingQueryRequest {
+        ToolingQueryRequest {
+            request: QueryRequest::new(query, false).with_tooling_api(),
+        }
+    }
+}
+
+impl SalesforceRequest for ToolingQueryRequest {
+    type ReturnValue = QueryResult;
+
+    fn get_query_parameters(&self) -> Option<Value> {
+        self.request.get_query_parameters()
+    }
+
+    fn get_url(&self) -> String {
+        self.request.get_url()
+    }
+
+    fn get_method(&self) -> Method {
+        self.request.get_method()
+    }
+
+    fn get_result(&self, conn: &Connection, body: Option<&Value>) -> Result<Self::ReturnValue> {
+        self.request.get_result(conn, body)
+    }
 }
 
 impl Connection {
@@ -109,4 +173,65 @@ impl Connection {
             .await?
             .into()
     }
+
+    /// Run `query` against the Tooling API and return all of its results.
+    pub async fn query_tooling<T>(&self, query: &str) -> Result<Vec<T>>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        query_all_as(self, &ToolingQueryRequest::new(query).request).await
+    }
+
+    /// Describe the Tooling API sObject `type_name`. Unlike `get_type()`,
+    /// this describes the type afresh on each call.
+    pub async fn get_tooling_type(&self, type_name: &str) -> Result<SObjectType> {
+        let describe = self
+            .execute(&SObjectDescribeRequest::new(type_name).with_tooling_api())
+            .await?;
+
+        Ok(SObjectType::new(describe.name.clone(), describe))
+    }
+
+    /// Create a Tooling API sObject of type `type_name` from `fields`.
+    pub async fn create_tooling(&self, type_name: &str, fields: Value) -> Result<SalesforceId> {
+        self.execute(
+            &SObjectCreateRequest::new_raw(fields, type_name.to_owned()).with_tooling_api(),
+        )
+        .await?
+        .into()
+    }
+
+    pub async fn update_tooling(
+        &self,
+        type_name: &str,
+        id: SalesforceId,
+        fields: Value,
+    ) -> Result<()> {
+        self.execute(
+            &SObjectUpdateRequest::new_raw(fields, type_name.to_owned(), id.to_string())
+                .with_tooling_api(),
+        )
+        .await
+    }
+
+    pub async fn delete_tooling(&self, type_name: &str, id: SalesforceId) -> Result<()> {
+        self.execute(
+            &SObjectDeleteRequest::new_raw(type_name.to_owned(), id.to_string()).with_tooling_api(),
+        )
+        .await
+    }
+
+    /// Retrieve the Tooling API sObject `id`, with all of its fields
+    /// or only `fields`.
+    pub async fn retrieve_tooling(
+        &self,
+        type_name: &str,
+        id: SalesforceId,
+        fields: Option<Vec<String>>,
+    ) -> Result<SObject> {
+        let sobject_type = self.get_tooling_type(type_name).await?;
+
+        self.execute(&SObjectRetrieveRequest::new(id, &sobject_type, fields).with_tooling_api())
+            .await
+    }
 }
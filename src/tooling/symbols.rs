@@ -0,0 +1,141 @@
+use anyhow::Result;
+use itertools::Itertools;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    api::Connection,
+    data::SalesforceId,
+    rest::query::{query_all_values, quote_soql_string, QueryRequest},
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Position {
+    pub line: i64,
+    pub column: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Symbol {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub symbol_type: Option<String>,
+    pub location: Option<Position>,
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+    #[serde(default)]
+    pub references: Vec<Position>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub parameter_type: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MethodSymbol {
+    pub name: String,
+    pub return_type: Option<String>,
+    pub location: Option<Position>,
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+    #[serde(default)]
+    pub parameters: Vec<Parameter>,
+    #[serde(default)]
+    pub references: Vec<Position>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalMethod {
+    pub name: String,
+    #[serde(default)]
+    pub arg_types: Vec<String>,
+    #[serde(default)]
+    pub is_static: bool,
+    pub return_type: Option<String>,
+    #[serde(default)]
+    pub references: Vec<Position>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ExternalSymbol {
+    pub name: String,
+    #[serde(default)]
+    pub references: Vec<Position>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ExternalReference {
+    pub name: String,
+    pub namespace: Option<String>,
+    #[serde(default)]
+    pub references: Vec<Position>,
+    #[serde(default)]
+    pub methods: Vec<ExternalMethod>,
+    #[serde(default)]
+    pub variables: Vec<ExternalSymbol>,
+}
+
+/// The Tooling API's analysis of a compiled Apex class.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolTable {
+    pub name: String,
+    pub namespace: Option<String>,
+    pub key: Option<String>,
+    pub parent_class: Option<String>,
+    pub table_declaration: Option<Symbol>,
+    #[serde(default)]
+    pub interfaces: Vec<String>,
+    #[serde(default)]
+    pub constructors: Vec<MethodSymbol>,
+    #[serde(default)]
+    pub methods: Vec<MethodSymbol>,
+    #[serde(default)]
+    pub properties: Vec<Symbol>,
+    #[serde(default)]
+    pub variables: Vec<Symbol>,
+    #[serde(default)]
+    pub inner_classes: Vec<SymbolTable>,
+    #[serde(default)]
+    pub external_references: Vec<ExternalReference>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApexClassSymbols {
+    pub id: SalesforceId,
+    pub name: String,
+    pub namespace_prefix: Option<String>,
+    /// Absent if the class has not been compiled since it was last changed.
+    pub symbol_table: Option<SymbolTable>,
+}
+
+impl Connection {
+    /// Retrieve the SymbolTables of the named Apex classes.
+    pub async fn get_apex_symbol_tables(
+        &self,
+        class_names: &[&str],
+    ) -> Result<Vec<ApexClassSymbols>> {
+        let mut classes = Vec::new();
+
+        for chunk in class_names.chunks(100) {
+            let query = format!(
+                "SELECT Id, Name, NamespacePrefix, SymbolTable FROM ApexClass WHERE Name IN ({})",
+                chunk.iter().map(|n| quote_soql_string(n)).join(", ")
+            );
+
+            for value in
+                query_all_values(self, &QueryRequest::new(&query, false).with_tooling_api()).await?
+            {
+                classes.push(serde_json::from_value(value)?);
+            }
+        }
+
+        Ok(classes)
+    }
+}
@@ -1,7 +1,14 @@
 use crate::test_integration_base::get_test_connection;
 use anyhow::Result;
+use serde_json::json;
 
-use super::{ExecuteAnonymousApexRequest, ExecuteAnonymousApexResponse};
+use super::dependencies::{DependencyGraph, MetadataComponent, MetadataComponentDependency};
+use super::symbols::SymbolTable;
+use super::{ExecuteAnonymousApexRequest, ExecuteAnonymousApexResponse, ToolingQueryRequest};
+use crate::api::SalesforceRequest;
+use crate::prelude::*;
+use crate::rest::describe::SObjectDescribeRequest;
+use crate::rest::rows::{SObjectCreateRequest, SObjectDeleteRequest, SObjectUpdateRequest};
 
 #[tokio::test]
 #[ignore]
@@ -80,3 +87,182 @@ async fn test_anon_apex_exception() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_symbol_table_deserialize() -> Result<()> {
+    let table: SymbolTable = serde_json::from_value(json!({
+        "name": "Greeter",
+        "namespace": null,
+        "key": "Greeter",
+        "parentClass": "",
+        "interfaces": [],
+        "tableDeclaration": {
+            "name": "Greeter",
+            "type": "Greeter",
+            "location": {"line": 1, "column": 27},
+            "modifiers": ["public", "with sharing"],
+            "references": []
+        },
+        "constructors": [],
+        "methods": [{
+            "name": "greet",
+            "returnType": "String",
+            "location": {"line": 2, "column": 26},
+            "modifiers": ["public", "static"],
+            "parameters": [{"name": "name", "type": "String"}],
+            "references": []
+        }],
+        "properties": [],
+        "variables": [],
+        "innerClasses": [],
+        "externalReferences": [{
+            "name": "Account",
+            "namespace": "",
+            "references": [{"line": 3, "column": 9}],
+            "methods": [],
+            "variables": []
+        }]
+    }))?;
+
+    assert_eq!(table.methods[0].name, "greet");
+    assert_eq!(table.methods[0].parameters[0].parameter_type, "String");
+    assert_eq!(
+        table.table_declaration.unwrap().modifiers,
+        vec!["public", "with sharing"]
+    );
+    assert_eq!(table.external_references[0].references[0].line, 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_dependency_graph() -> Result<()> {
+    let edge = |from: &str, to: &str| -> Result<MetadataComponentDependency> {
+        Ok(serde_json::from_value(json!({
+            "MetadataComponentId": from,
+            "MetadataComponentName": from,
+            "MetadataComponentNamespace": null,
+            "MetadataComponentType": "ApexClass",
+            "RefMetadataComponentId": to,
+            "RefMetadataComponentName": to,
+            "RefMetadataComponentNamespace": null,
+            "RefMetadataComponentType": "ApexClass"
+        }))?)
+    };
+    let graph = DependencyGraph::new(&[
+        edge("A", "B")?,
+        edge("B", "C")?,
+        edge("C", "A")?,
+        edge("D", "B")?,
+    ]);
+
+    let names = |components: Vec<&MetadataComponent>| {
+        let mut names: Vec<String> = components.iter().map(|c| c.name.clone()).collect();
+        names.sort();
+        names
+    };
+
+    assert_eq!(graph.get_components().count(), 4);
+    assert_eq!(names(graph.get_dependencies("A")), vec!["B"]);
+    assert_eq!(names(graph.get_dependents("B")), vec!["A", "D"]);
+    assert_eq!(
+        names(graph.get_transitive_dependencies("D")),
+        vec!["A", "B", "C"]
+    );
+    assert!(graph.get_dependencies("missing").is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_apex_symbol_tables() -> Result<()> {
+    let conn = get_test_connection()?;
+
+    // Retrieving an absent class is not an error.
+    assert!(conn
+        .get_apex_symbol_tables(&["Baris_Nonexistent_Class"])
+        .await?
+        .is_empty());
+
+    conn.get_dependency_graph(Some("MetadataComponentType = 'ApexClass'"))
+        .await?;
+
+    Ok(())
+}
+
+#[test]
+fn test_tooling_request_urls() -> Result<()> {
+    let id = SalesforceId::new("7dl000000000001AAA")?;
+
+    assert_eq!(
+        ToolingQueryRequest::new("SELECT Id FROM ApexClass").get_url(),
+        "tooling/query"
+    );
+    assert_eq!(
+        SObjectDescribeRequest::new("TraceFlag")
+            .with_tooling_api()
+            .get_url(),
+        "tooling/sobjects/TraceFlag/describe"
+    );
+    assert_eq!(
+        SObjectCreateRequest::new_raw(json!({}), "DebugLevel".to_owned())
+            .with_tooling_api()
+            .get_url(),
+        "tooling/sobjects/DebugLevel/"
+    );
+    assert_eq!(
+        SObjectUpdateRequest::new_raw(json!({}), "DebugLevel".to_owned(), id.to_string())
+            .with_tooling_api()
+            .get_url(),
+        format!("tooling/sobjects/DebugLevel/{}", id)
+    );
+    assert_eq!(
+        SObjectDeleteRequest::new_raw("DebugLevel".to_owned(), id.to_string())
+            .with_tooling_api()
+            .get_url(),
+        format!("tooling/sobjects/DebugLevel/{}", id)
+    );
+    assert_eq!(
+        SObjectDeleteRequest::new_raw("Account".to_owned(), id.to_string()).get_url(),
+        format!("sobjects/Account/{}", id)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_tooling_crud() -> Result<()> {
+    let conn = get_test_connection()?;
+    let id = conn
+        .create_tooling(
+            "DebugLevel",
+            json!({
+                "DeveloperName": "Baris_Test_Level",
+                "MasterLabel": "Baris Test Level",
+                "ApexCode": "DEBUG"
+            }),
+        )
+        .await?;
+
+    conn.update_tooling("DebugLevel", id, json!({"ApexCode": "FINEST"}))
+        .await?;
+
+    let level = conn
+        .retrieve_tooling("DebugLevel", id, Some(vec!["ApexCode".to_owned()]))
+        .await?;
+    assert_eq!(
+        level.get("ApexCode"),
+        Some(&FieldValue::String("FINEST".to_owned()))
+    );
+
+    let rows: Vec<serde_json::Value> = conn
+        .query_tooling(&format!("SELECT Id FROM DebugLevel WHERE Id = '{}'", id))
+        .await?;
+    assert_eq!(rows.len(), 1);
+
+    conn.delete_tooling("DebugLevel", id).await?;
+
+    Ok(())
+}
@@ -0,0 +1,196 @@
+//! Resolution of users by username, email, or Id.
+//!
+//! Mapping users between orgs (for example, when loading `OwnerId` values
+//! from a source system that identifies users by username) requires many lookups.
+//! Lookups are batched into as few queries as possible and cached on the `Connection`.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use itertools::Itertools;
+use serde_derive::Deserialize;
+
+use crate::{
+    api::Connection,
+    data::{SObjectBase, SalesforceId, SingleTypedSObject},
+    rest::query::{quote_soql_string, traits::QueryableSingleType},
+};
+
+#[cfg(test)]
+mod test;
+
+const USER_QUERY_BATCH_SIZE: usize = 100;
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct User {
+    pub id: SalesforceId,
+    pub username: String,
+    pub email: String,
+    pub is_active: bool,
+}
+
+impl SObjectBase for User {}
+
+impl SingleTypedSObject for User {
+    fn get_type_api_name() -> &'static str {
+        "User"
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct UserCache {
+    users: HashMap<SalesforceId, User>,
+    ids_by_username: HashMap<String, SalesforceId>,
+    // Values already queried, so that lookups that match no user are not repeated.
+    queried_usernames: HashSet<String>,
+    queried_emails: HashSet<String>,
+    queried_ids: HashSet<SalesforceId>,
+}
+
+impl UserCache {
+    fn insert(&mut self, user: User) {
+        // Usernames and emails are case-insensitive.
+        self.ids_by_username
+            .insert(user.username.to_lowercase(), user.id);
+        self.users.insert(user.id, user);
+    }
+
+    fn get_by_email(&self, email: &str) -> Vec<&User> {
+        let email = email.to_lowercase();
+
+        self.users
+            .values()
+            .filter(|u| u.email.to_lowercase() == email)
+            .collect()
+    }
+}
+
+impl Connection {
+    async fn query_users(&self, field: &str, values: &[String]) -> Result<()> {
+        for chunk in values.chunks(USER_QUERY_BATCH_SIZE) {
+            let users = User::query_vec_t(
+                self,
+                &format!(
+                    "SELECT Id, Username, Email, IsActive FROM User WHERE {} IN ({})",
+                    field,
+                    chunk.iter().map(|v| quote_soql_string(v)).join(", ")
+                ),
+                false,
+            )
+            .await?;
+
+            let mut cache = self.user_cache.write().await;
+            for user in users {
+                cache.insert(user);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the Id of the user with the given username, if there is one.
+    pub async fn get_user_id(&self, username: &str) -> Result<Option<SalesforceId>> {
+        Ok(self.get_user_ids(&[username]).await?.remove(username))
+    }
+
+    /// Get the Ids of the users with the given usernames.
+    /// Usernames that do not match a user are omitted from the result.
+    pub async fn get_user_ids(&self, usernames: &[&str]) -> Result<HashMap<String, SalesforceId>> {
+        let missing: Vec<String> = {
+            let cache = self.user_cache.read().await;
+
+            usernames
+                .iter()
+                .map(|u| u.to_lowercase())
+                .filter(|u| {
+                    !cache.ids_by_username.contains_key(u) && !cache.queried_usernames.contains(u)
+                })
+                .unique()
+                .collect()
+        };
+
+        if !missing.is_empty() {
+            self.query_users("Username", &missing).await?;
+            self.user_cache
+                .write()
+                .await
+                .queried_usernames
+                .extend(missing);
+        }
+
+        let cache = self.user_cache.read().await;
+
+        Ok(usernames
+            .iter()
+            .filter_map(|u| {
+                cache
+                    .ids_by_username
+                    .get(&u.to_lowercase())
+                    .map(|id| (u.to_string(), *id))
+            })
+            .collect())
+    }
+
+    /// Get the users whose email address is one of `emails`.
+    /// Email addresses are not unique, so each may map to more than one user.
+    pub async fn get_users_by_email(&self, emails: &[&str]) -> Result<HashMap<String, Vec<User>>> {
+        let missing: Vec<String> = {
+            let cache = self.user_cache.read().await;
+
+            emails
+                .iter()
+                .map(|e| e.to_lowercase())
+                .filter(|e| !cache.queried_emails.contains(e))
+                .unique()
+                .collect()
+        };
+
+        if !missing.is_empty() {
+            self.query_users("Email", &missing).await?;
+            self.user_cache.write().await.queried_emails.extend(missing);
+        }
+
+        let cache = self.user_cache.read().await;
+
+        Ok(emails
+            .iter()
+            .filter_map(|e| {
+                let users: Vec<User> = cache.get_by_email(e).into_iter().cloned().collect();
+
+                if users.is_empty() {
+                    None
+                } else {
+                    Some((e.to_string(), users))
+                }
+            })
+            .collect())
+    }
+
+    /// Get the users with the given Ids.
+    /// Ids that do not match a user are omitted from the result.
+    pub async fn get_users(&self, ids: &[SalesforceId]) -> Result<HashMap<SalesforceId, User>> {
+        let missing: Vec<SalesforceId> = {
+            let cache = self.user_cache.read().await;
+
+            ids.iter()
+                .filter(|id| !cache.users.contains_key(id) && !cache.queried_ids.contains(id))
+                .unique()
+                .copied()
+                .collect()
+        };
+
+        if !missing.is_empty() {
+            let values: Vec<String> = missing.iter().map(|id| id.to_string()).collect();
+            self.query_users("Id", &values).await?;
+            self.user_cache.write().await.queried_ids.extend(missing);
+        }
+
+        let cache = self.user_cache.read().await;
+
+        Ok(ids
+            .iter()
+            .filter_map(|id| cache.users.get(id).map(|u| (*id, u.clone())))
+            .collect())
+    }
+}
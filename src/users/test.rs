@@ -0,0 +1,92 @@
+use std::sync::atomic::Ordering;
+
+use anyhow::Result;
+
+use super::User;
+use crate::prelude::*;
+use crate::test_integration_base::{
+    get_test_connection, get_test_field_describe, get_test_sobject_describe, serve_responses,
+};
+
+#[tokio::test]
+#[ignore]
+async fn test_user_lookups() -> Result<()> {
+    let conn = get_test_connection()?;
+
+    let user = User::query_vec_t(
+        &conn,
+        "SELECT Id, Username, Email, IsActive FROM User WHERE IsActive = true LIMIT 1",
+        false,
+    )
+    .await?
+    .remove(0);
+
+    assert_eq!(conn.get_user_id(&user.username).await?, Some(user.id));
+    assert_eq!(
+        conn.get_user_id(&user.username.to_uppercase()).await?,
+        Some(user.id)
+    );
+    assert_eq!(conn.get_user_id("nobody@baris.invalid").await?, None);
+    assert_eq!(conn.get_users(&[user.id]).await?[&user.id], user);
+    assert!(conn.get_users_by_email(&[&user.email]).await?[&user.email].contains(&user));
+
+    Ok(())
+}
+
+const NO_USERS: &str = r#"{"totalSize": 0, "done": true, "records": []}"#;
+
+#[tokio::test]
+async fn test_user_lookups_are_batched_and_cached() -> Result<()> {
+    let describe = get_test_sobject_describe(
+        "User",
+        vec![
+            get_test_field_describe("Id", "tns:ID", "id"),
+            get_test_field_describe("Username", "xsd:string", "string"),
+            get_test_field_describe("Email", "xsd:string", "email"),
+            get_test_field_describe("IsActive", "xsd:boolean", "boolean"),
+        ],
+        vec![],
+        vec![],
+    )?
+    .to_string();
+    let (conn, count) = serve_responses(vec![
+        (
+            "200 OK",
+            r#"{"totalSize": 1, "done": true, "records": [
+                {"attributes": {"type": "User"}, "Id": "005000000000000AAA", "Username": "user0@baris.invalid", "Email": "user0@baris.invalid", "IsActive": true}
+            ]}"#,
+        ),
+        ("200 OK", describe.as_str()),
+        ("200 OK", NO_USERS),
+        ("200 OK", NO_USERS),
+    ])
+    .await?;
+    let usernames: Vec<String> = (0..150)
+        .map(|i| format!("user{}@baris.invalid", i))
+        .collect();
+    let usernames: Vec<&str> = usernames.iter().map(|u| u.as_str()).collect();
+    let user_id = SalesforceId::new("005000000000000AAA")?;
+    let missing_id = SalesforceId::new("005000000000001AAA")?;
+
+    // 150 usernames take two queries, and the first also describes User.
+    let ids = conn.get_user_ids(&usernames).await?;
+    assert_eq!(ids.len(), 1);
+    assert_eq!(ids["user0@baris.invalid"], user_id);
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+    assert!(conn.get_users(&[missing_id]).await?.is_empty());
+    assert_eq!(count.load(Ordering::SeqCst), 4);
+
+    // Neither matches nor misses are queried again.
+    assert_eq!(conn.get_user_ids(&usernames).await?, ids);
+    assert_eq!(
+        conn.get_user_id("USER0@baris.invalid").await?,
+        Some(user_id)
+    );
+    assert!(conn
+        .get_users(&[user_id, missing_id])
+        .await?
+        .contains_key(&user_id));
+    assert_eq!(count.load(Ordering::SeqCst), 4);
+
+    Ok(())
+}